@@ -41,8 +41,12 @@ use quickwit_proto::types::IndexId;
 use quickwit_rest_client::models::IngestSource;
 use quickwit_rest_client::rest_client::{CommitType, IngestEvent};
 use quickwit_search::SearchResponseRest;
-use quickwit_serve::{ListSplitsQueryParams, SearchRequestQueryString, SortBy};
+use quickwit_serve::{
+    ListSplitsQueryParams, ReindexQueryParams, SearchRequestQueryString, SortBy,
+    TailRequestQueryString,
+};
 use quickwit_storage::{load_file, StorageResolver};
+use serde_json::json;
 use tabled::settings::object::{FirstRow, Rows, Segment};
 use tabled::settings::panel::Footer;
 use tabled::settings::{Alignment, Disable, Format, Modify, Panel, Rotate, Style};
@@ -195,6 +199,52 @@ pub fn build_index_command() -> Command {
                         .required(false),
                 ])
             )
+        .subcommand(
+            Command::new("tail")
+                .display_order(9)
+                .about("Streams the documents matching a query as they become searchable.")
+                .args(&[
+                    arg!(--index <INDEX> "ID of the target index")
+                        .display_order(1)
+                        .required(true),
+                    arg!(--query <QUERY> "Query expressed in natural query language ((barack AND obama) OR \"president of united states\"). Learn more on https://quickwit.io/docs/reference/search-language.")
+                        .display_order(2)
+                        .required(true),
+                    arg!(--"search-fields" <FIELD_NAME> "List of fields that Quickwit will search into if the user query does not explicitly target a field in the query. It overrides the default search fields defined in the index config. Space-separated list, e.g. \"field1 field2\". ")
+                        .num_args(1..)
+                        .required(false),
+                    arg!(--"start-timestamp" <TIMESTAMP> "Only streams the documents with a timestamp greater or equal to that timestamp, including the documents already searchable. By default, streams the documents that become searchable after the start of the command.")
+                        .required(false),
+                    arg!(--"max-hits" <MAX_HITS> "Maximum number of documents fetched per search.")
+                        .default_value("100")
+                        .required(false),
+                ])
+            )
+        .subcommand(
+            Command::new("reindex")
+                .display_order(10)
+                .about("Copies the documents matching a query from an index into another index.")
+                .long_about("Copies the documents of the index `--source-index` matching the query specified with `--query` into the index `--dest-index`, optionally transforming them with a VRL script. Documents are copied in batches. When the command fails, it prints a cursor that can be passed to `--resume-after` to resume the copy where it stopped.")
+                .args(&[
+                    arg!(--"source-index" <SOURCE_INDEX> "ID of the index to copy the documents from.")
+                        .display_order(1)
+                        .required(true),
+                    arg!(--"dest-index" <DEST_INDEX> "ID of the index to copy the documents into.")
+                        .display_order(2)
+                        .required(true),
+                    arg!(--query <QUERY> "Query expressed in natural query language selecting the documents to copy. Defaults to all the documents of the source index.")
+                        .required(false),
+                    arg!(--"transform-script" <TRANSFORM_SCRIPT> "Location of a VRL script applied to the documents before they are ingested into the destination index.")
+                        .required(false),
+                    arg!(--"batch-size" <BATCH_SIZE> "Number of documents copied per batch.")
+                        .default_value("1000")
+                        .required(false),
+                    arg!(--"requests-per-second" <REQUESTS_PER_SECOND> "Maximum number of documents copied per second. Defaults to no limit.")
+                        .required(false),
+                    arg!(--"resume-after" <CURSOR> "Resumes an interrupted copy after the document designated by the cursor printed by a previous run.")
+                        .required(false),
+                ])
+            )
         .arg_required_else_help(true)
 }
 
@@ -251,6 +301,28 @@ pub struct SearchIndexArgs {
     pub sort_by_score: bool,
 }
 
+#[derive(Debug, Eq, PartialEq)]
+pub struct TailIndexArgs {
+    pub client_args: ClientArgs,
+    pub index_id: IndexId,
+    pub query: String,
+    pub search_fields: Option<Vec<String>>,
+    pub start_timestamp: Option<i64>,
+    pub max_hits: u64,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct ReindexArgs {
+    pub client_args: ClientArgs,
+    pub source_index_id: IndexId,
+    pub dest_index_id: IndexId,
+    pub query: Option<String>,
+    pub transform_script_uri: Option<Uri>,
+    pub batch_size: u64,
+    pub requests_per_second: Option<u32>,
+    pub resume_after: Option<String>,
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub struct DeleteIndexArgs {
     pub client_args: ClientArgs,
@@ -273,13 +345,15 @@ pub enum IndexCliCommand {
     Describe(DescribeIndexArgs),
     Ingest(IngestDocsArgs),
     List(ListIndexesArgs),
+    Reindex(ReindexArgs),
     Search(SearchIndexArgs),
+    Tail(TailIndexArgs),
 }
 
 impl IndexCliCommand {
     pub fn default_log_level(&self) -> Level {
         match self {
-            Self::Search(_) => Level::ERROR,
+            Self::Search(_) | Self::Tail(_) => Level::ERROR,
             _ => Level::INFO,
         }
     }
@@ -295,7 +369,9 @@ impl IndexCliCommand {
             "describe" => Self::parse_describe_args(submatches),
             "ingest" => Self::parse_ingest_args(submatches),
             "list" => Self::parse_list_args(submatches),
+            "reindex" => Self::parse_reindex_args(submatches),
             "search" => Self::parse_search_args(submatches),
+            "tail" => Self::parse_tail_args(submatches),
             "update" => Self::parse_update_args(submatches),
             _ => bail!("unknown index subcommand `{subcommand}`"),
         }
@@ -452,6 +528,72 @@ impl IndexCliCommand {
         }))
     }
 
+    fn parse_tail_args(mut matches: ArgMatches) -> anyhow::Result<Self> {
+        let index_id = matches
+            .remove_one::<String>("index")
+            .expect("`index` should be a required arg.");
+        let query = matches
+            .remove_one::<String>("query")
+            .context("`query` should be a required arg")?;
+        let search_fields = matches
+            .remove_many::<String>("search-fields")
+            .map(|values| values.collect());
+        let start_timestamp = matches
+            .remove_one::<String>("start-timestamp")
+            .map(|ts| ts.parse())
+            .transpose()?;
+        let max_hits = matches
+            .remove_one::<String>("max-hits")
+            .expect("`max-hits` should have a default value.")
+            .parse()?;
+        let client_args = ClientArgs::parse(&mut matches)?;
+        Ok(Self::Tail(TailIndexArgs {
+            client_args,
+            index_id,
+            query,
+            search_fields,
+            start_timestamp,
+            max_hits,
+        }))
+    }
+
+    fn parse_reindex_args(mut matches: ArgMatches) -> anyhow::Result<Self> {
+        let source_index_id = matches
+            .remove_one::<String>("source-index")
+            .expect("`source-index` should be a required arg.");
+        let dest_index_id = matches
+            .remove_one::<String>("dest-index")
+            .expect("`dest-index` should be a required arg.");
+        let query = matches.remove_one::<String>("query");
+        let transform_script_uri = matches
+            .remove_one::<String>("transform-script")
+            .map(|uri| Uri::from_str(&uri))
+            .transpose()?;
+        let batch_size = matches
+            .remove_one::<String>("batch-size")
+            .expect("`batch-size` should have a default value.")
+            .parse()?;
+        if batch_size == 0 {
+            bail!("`--batch-size` must be greater than 0");
+        }
+        let requests_per_second = matches
+            .remove_one::<String>("requests-per-second")
+            .map(|requests_per_second| requests_per_second.parse())
+            .transpose()?;
+        let resume_after = matches.remove_one::<String>("resume-after");
+        let client_args = ClientArgs::parse(&mut matches)?;
+        Ok(Self::Reindex(ReindexArgs {
+            client_args,
+            source_index_id,
+            dest_index_id,
+            query,
+            transform_script_uri,
+            batch_size,
+            requests_per_second,
+            resume_after,
+        }))
+    }
+
     fn parse_delete_args(mut matches: ArgMatches) -> anyhow::Result<Self> {
         let client_args = ClientArgs::parse(&mut matches)?;
         let index_id = matches
@@ -475,7 +617,9 @@ impl IndexCliCommand {
             Self::Describe(args) => describe_index_cli(args).await,
             Self::Ingest(args) => ingest_docs_cli(args).await,
             Self::List(args) => list_index_cli(args).await,
+            Self::Reindex(args) => reindex_cli(args).await,
             Self::Search(args) => search_index_cli(args).await,
+            Self::Tail(args) => tail_index_cli(args).await,
             Self::Update(args) => update_index_cli(args).await,
         }
     }
@@ -1101,6 +1245,131 @@ pub async fn search_index_cli(args: SearchIndexArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
+pub async fn tail_index_cli(args: TailIndexArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "tail-index");
+    let tail_request = TailRequestQueryString {
+        query: args.query,
+        search_fields: args.search_fields,
+        start_timestamp: args.start_timestamp,
+        max_hits: args.max_hits,
+    };
+    let qw_client = args.client_args.client();
+    let mut tail_stream = qw_client.tail(&args.index_id, tail_request).await?;
+
+    while let Some(document) = tail_stream.next_document().await? {
+        println!("{}", document.json);
+    }
+    Ok(())
+}
+
+pub async fn reindex_cli(args: ReindexArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "reindex");
+    let script_opt = if let Some(transform_script_uri) = &args.transform_script_uri {
+        let storage_resolver = StorageResolver::unconfigured();
+        let file_content = load_file(&storage_resolver, transform_script_uri).await?;
+        let script = std::str::from_utf8(&file_content)
+            .with_context(|| format!("Invalid utf8: `{transform_script_uri}`"))?
+            .to_string();
+        Some(script)
+    } else {
+        None
+    };
+    println!(
+        "❯ Copying documents from index `{}` into index `{}`...",
+        args.source_index_id, args.dest_index_id
+    );
+    let qw_client = args.client_args.client();
+    let mut resume_after_opt = args.resume_after;
+    let mut num_copied_docs = 0;
+
+    // Each request copies a single batch, which lets us report progress and the cursor to resume
+    // from after each batch.
+    loop {
+        let reindex_request = build_reindex_request(
+            &args.source_index_id,
+            &args.dest_index_id,
+            args.query.as_deref(),
+            script_opt.as_deref(),
+            args.batch_size,
+            resume_after_opt.as_deref(),
+        );
+        let reindex_query_params = ReindexQueryParams {
+            requests_per_second: args
+                .requests_per_second
+                .map(|requests_per_second| requests_per_second as f32),
+        };
+        let reindex_response = match qw_client
+            .reindex(reindex_request, reindex_query_params)
+            .await
+        {
+            Ok(reindex_response) => reindex_response,
+            Err(error) => {
+                print_reindex_resume_hint(resume_after_opt.as_deref());
+                return Err(error.into());
+            }
+        };
+        num_copied_docs += reindex_response.created;
+
+        if let Some(failure) = reindex_response.failures.first() {
+            // The cursor of the response designates the last document copied before the failure.
+            let resume_after_opt = reindex_response.search_after.or(resume_after_opt);
+            print_reindex_resume_hint(resume_after_opt.as_deref());
+            bail!(
+                "failed to copy documents into index `{}`: {}",
+                failure.index,
+                failure.cause.reason.as_deref().unwrap_or("unknown error")
+            );
+        }
+        let Some(search_after) = reindex_response.search_after else {
+            break;
+        };
+        println!("{num_copied_docs} documents copied (cursor: `{search_after}`)");
+        resume_after_opt = Some(search_after);
+    }
+    println!(
+        "{} Reindex successfully completed: {num_copied_docs} documents copied.",
+        "✔".color(GREEN_COLOR)
+    );
+    Ok(())
+}
+
+fn build_reindex_request(
+    source_index_id: &str,
+    dest_index_id: &str,
+    query_opt: Option<&str>,
+    script_opt: Option<&str>,
+    batch_size: u64,
+    resume_after_opt: Option<&str>,
+) -> serde_json::Value {
+    let mut source = json!({
+        "index": source_index_id,
+        "size": batch_size,
+    });
+    if let Some(query) = query_opt {
+        source["query"] = json!({"query_string": {"query": query}});
+    }
+    if let Some(resume_after) = resume_after_opt {
+        source["search_after"] = json!(resume_after);
+    }
+    let mut reindex_request = json!({
+        "source": source,
+        "dest": {"index": dest_index_id},
+        "max_docs": batch_size,
+    });
+    if let Some(script) = script_opt {
+        reindex_request["script"] = json!({"source": script, "lang": "vrl"});
+    }
+    reindex_request
+}
+
+fn print_reindex_resume_hint(resume_after_opt: Option<&str>) {
+    if let Some(resume_after) = resume_after_opt {
+        println!("To resume the copy, run the command again with `--resume-after {resume_after}`.");
+    } else {
+        println!("No documents were copied, run the command again to restart the copy.");
+    }
+}
+
 pub async fn delete_index_cli(args: DeleteIndexArgs) -> anyhow::Result<()> {
     debug!(args=?args, "delete-index");
     if !args.dry_run && !args.assume_yes {
@@ -1270,4 +1539,40 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_build_reindex_request() {
+        let reindex_request =
+            build_reindex_request("source-index", "dest-index", None, None, 100, None);
+        assert_eq!(
+            reindex_request,
+            json!({
+                "source": {"index": "source-index", "size": 100},
+                "dest": {"index": "dest-index"},
+                "max_docs": 100,
+            })
+        );
+        let reindex_request = build_reindex_request(
+            "source-index",
+            "dest-index",
+            Some("severity_text:ERROR"),
+            Some(".body = upcase!(.body)"),
+            100,
+            Some("split:00000000:00000063"),
+        );
+        assert_eq!(
+            reindex_request,
+            json!({
+                "source": {
+                    "index": "source-index",
+                    "size": 100,
+                    "query": {"query_string": {"query": "severity_text:ERROR"}},
+                    "search_after": "split:00000000:00000063",
+                },
+                "dest": {"index": "dest-index"},
+                "max_docs": 100,
+                "script": {"source": ".body = upcase!(.body)", "lang": "vrl"},
+            })
+        );
+    }
 }
@@ -136,7 +136,7 @@ mod tests {
     use quickwit_cli::cli::{build_cli, CliCommand};
     use quickwit_cli::index::{
         ClearIndexArgs, CreateIndexArgs, DeleteIndexArgs, DescribeIndexArgs, IndexCliCommand,
-        IngestDocsArgs, SearchIndexArgs,
+        IngestDocsArgs, ReindexArgs, SearchIndexArgs, TailIndexArgs,
     };
     use quickwit_cli::split::{DescribeSplitArgs, SplitCliCommand};
     use quickwit_cli::tool::{
@@ -523,6 +523,75 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_parse_tail_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from([
+            "index",
+            "tail",
+            "--index",
+            "hdfs-logs",
+            "--query",
+            "severity_text:ERROR",
+            "--start-timestamp",
+            "1000",
+            "--search-fields",
+            "body",
+        ])?;
+        let command = CliCommand::parse_cli_args(matches)?;
+        assert!(matches!(
+            command,
+            CliCommand::Index(IndexCliCommand::Tail(TailIndexArgs {
+                client_args: _,
+                index_id,
+                query,
+                search_fields: Some(search_field_names),
+                start_timestamp: Some(1000),
+                max_hits: 100,
+            })) if &index_id == "hdfs-logs"
+                  && query == "severity_text:ERROR"
+                  && search_field_names == vec!["body".to_string()]
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_reindex_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from([
+            "index",
+            "reindex",
+            "--source-index",
+            "hdfs-logs",
+            "--dest-index",
+            "hdfs-logs-v2",
+            "--query",
+            "severity_text:ERROR",
+            "--requests-per-second",
+            "500",
+            "--resume-after",
+            "split:00000000:00000063",
+        ])?;
+        let command = CliCommand::parse_cli_args(matches)?;
+        assert!(matches!(
+            command,
+            CliCommand::Index(IndexCliCommand::Reindex(ReindexArgs {
+                client_args: _,
+                source_index_id,
+                dest_index_id,
+                query: Some(query),
+                transform_script_uri: None,
+                batch_size: 1000,
+                requests_per_second: Some(500),
+                resume_after: Some(resume_after),
+            })) if &source_index_id == "hdfs-logs"
+                  && &dest_index_id == "hdfs-logs-v2"
+                  && query == "severity_text:ERROR"
+                  && resume_after == "split:00000000:00000063"
+        ));
+        Ok(())
+    }
+
     #[test]
     fn test_parse_local_search_args() {
         let app = build_cli().no_binary_name(true);
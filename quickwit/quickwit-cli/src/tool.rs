@@ -555,6 +555,9 @@ pub async fn local_search_cli(args: LocalSearchArgs) -> anyhow::Result<()> {
         sort_by,
         count_all: CountHits::CountAll,
         allow_failed_splits: false,
+        real_time: false,
+        runtime_fields: Vec::new(),
+        profile: false,
     };
     let search_request =
         search_request_from_api_request(vec![args.index_id], search_request_query_string)?;
@@ -940,6 +943,7 @@ async fn create_empty_cluster(config: &NodeConfig) -> anyhow::Result<Cluster> {
         gossip_advertise_addr: config.gossip_advertise_addr,
         grpc_advertise_addr: config.grpc_advertise_addr,
         indexing_cpu_capacity: CpuCapacity::zero(),
+        availability_zone_opt: None,
         indexing_tasks: Vec::new(),
     };
     let cluster = Cluster::join(
@@ -950,6 +954,7 @@ async fn create_empty_cluster(config: &NodeConfig) -> anyhow::Result<Cluster> {
         config.gossip_interval,
         FailureDetectorConfig::default(),
         &ChannelTransport::default(),
+        None,
     )
     .await?;
 
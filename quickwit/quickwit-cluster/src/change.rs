@@ -26,6 +26,7 @@ use chitchat::{ChitchatId, NodeState};
 use futures::Stream;
 use pin_project::pin_project;
 use quickwit_common::sorted_iter::{KeyDiff, SortedByKeyIterator};
+use quickwit_common::tls::ReloadingClientConfig;
 use quickwit_common::tower::{make_channel, warmup_channel};
 use quickwit_proto::types::NodeId;
 use tokio::sync::mpsc;
@@ -79,6 +80,7 @@ pub(crate) async fn compute_cluster_change_events(
     previous_nodes: &mut BTreeMap<NodeId, ClusterNode>,
     previous_node_states: &BTreeMap<ChitchatId, NodeState>,
     new_node_states: &BTreeMap<ChitchatId, NodeState>,
+    client_tls_config_opt: Option<&ReloadingClientConfig>,
 ) -> Vec<ClusterChange> {
     let mut cluster_events = Vec::new();
 
@@ -95,6 +97,7 @@ pub(crate) async fn compute_cluster_change_events(
                     chitchat_id,
                     node_state,
                     previous_nodes,
+                    client_tls_config_opt,
                 )
                 .await;
 
@@ -139,6 +142,7 @@ async fn compute_cluster_change_events_on_added(
     new_chitchat_id: &ChitchatId,
     new_node_state: &NodeState,
     previous_nodes: &mut BTreeMap<NodeId, ClusterNode>,
+    client_tls_config_opt: Option<&ReloadingClientConfig>,
 ) -> Vec<ClusterChange> {
     let is_self_node = self_chitchat_id == new_chitchat_id;
     let new_node_id: NodeId = new_chitchat_id.node_id.clone().into();
@@ -166,8 +170,14 @@ async fn compute_cluster_change_events_on_added(
             events.push(ClusterChange::Remove(previous_node));
         }
     }
-    let Some(new_node) =
-        try_new_node(cluster_id, new_chitchat_id, new_node_state, is_self_node).await
+    let Some(new_node) = try_new_node(
+        cluster_id,
+        new_chitchat_id,
+        new_node_state,
+        is_self_node,
+        client_tls_config_opt,
+    )
+    .await
     else {
         return events;
     };
@@ -300,10 +310,11 @@ async fn try_new_node(
     chitchat_id: &ChitchatId,
     node_state: &NodeState,
     is_self_node: bool,
+    client_tls_config_opt: Option<&ReloadingClientConfig>,
 ) -> Option<ClusterNode> {
     match node_state.grpc_advertise_addr() {
         Ok(socket_addr) => {
-            let channel = make_channel(socket_addr).await;
+            let channel = make_channel(socket_addr, client_tls_config_opt).await;
             try_new_node_with_channel(cluster_id, chitchat_id, node_state, channel, is_self_node)
         }
         Err(error) => {
@@ -443,6 +454,7 @@ pub(crate) mod tests {
                 &new_chitchat_id,
                 &new_node_state,
                 &mut previous_nodes,
+                None,
             )
             .await;
             assert!(events.is_empty());
@@ -465,6 +477,7 @@ pub(crate) mod tests {
                 &new_chitchat_id,
                 &new_node_state,
                 &mut previous_nodes,
+                None,
             )
             .await;
             assert!(events.is_empty());
@@ -493,6 +506,7 @@ pub(crate) mod tests {
                 &new_chitchat_id,
                 &new_node_state,
                 &mut previous_nodes,
+                None,
             )
             .await;
 
@@ -515,6 +529,7 @@ pub(crate) mod tests {
                 &rejoined_chitchat_id,
                 &new_node_state,
                 &mut previous_nodes,
+                None,
             )
             .await;
             assert_eq!(events.len(), 2);
@@ -543,6 +558,7 @@ pub(crate) mod tests {
                 &new_chitchat_id,
                 &new_node_state,
                 &mut previous_nodes,
+                None,
             )
             .await;
             assert!(events.is_empty());
@@ -567,6 +583,7 @@ pub(crate) mod tests {
                 &new_chitchat_id,
                 &new_node_state,
                 &mut previous_nodes,
+                None,
             )
             .await;
             assert_eq!(events.len(), 1);
@@ -897,6 +914,7 @@ pub(crate) mod tests {
                 &mut previous_nodes,
                 &previous_node_states,
                 &new_node_states,
+                None,
             )
             .await;
             assert!(events.is_empty());
@@ -926,6 +944,7 @@ pub(crate) mod tests {
                 &mut previous_nodes,
                 &previous_node_states,
                 &new_node_states,
+                None,
             )
             .await;
             assert!(events.is_empty());
@@ -943,6 +962,7 @@ pub(crate) mod tests {
                 &mut previous_nodes,
                 &previous_node_states,
                 &new_node_states,
+                None,
             )
             .await;
             assert_eq!(events.len(), 1);
@@ -957,6 +977,7 @@ pub(crate) mod tests {
                 &mut previous_nodes,
                 &new_node_states,
                 &new_node_states,
+                None,
             )
             .await;
             assert_eq!(events.len(), 0);
@@ -989,6 +1010,7 @@ pub(crate) mod tests {
                 &mut previous_nodes,
                 &previous_node_states,
                 &new_node_states,
+                None,
             )
             .await;
             assert_eq!(events.len(), 1);
@@ -1008,6 +1030,7 @@ pub(crate) mod tests {
                 &mut previous_nodes,
                 &previous_node_states,
                 &new_node_states,
+                None,
             )
             .await;
             assert_eq!(events.len(), 1);
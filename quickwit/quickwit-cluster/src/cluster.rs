@@ -31,6 +31,7 @@ use chitchat::{
     FailureDetectorConfig, KeyChangeEvent, ListenerHandle, NodeState,
 };
 use itertools::Itertools;
+use quickwit_common::tls::ReloadingClientConfig;
 use quickwit_proto::indexing::{IndexingPipelineId, IndexingTask, PipelineMetrics};
 use quickwit_proto::types::{NodeId, NodeIdRef, PipelineUid, ShardId};
 use serde::{Deserialize, Serialize};
@@ -43,7 +44,7 @@ use tracing::{info, warn};
 use crate::change::{compute_cluster_change_events, ClusterChange, ClusterChangeStreamFactory};
 use crate::grpc_gossip::spawn_catchup_callback_task;
 use crate::member::{
-    build_cluster_member, ClusterMember, NodeStateExt, ENABLED_SERVICES_KEY,
+    build_cluster_member, ClusterMember, NodeStateExt, AVAILABILITY_ZONE_KEY, ENABLED_SERVICES_KEY,
     GRPC_ADVERTISE_ADDR_KEY, PIPELINE_METRICS_PREFIX, READINESS_KEY, READINESS_VALUE_NOT_READY,
     READINESS_VALUE_READY,
 };
@@ -115,6 +116,7 @@ impl Cluster {
         gossip_interval: Duration,
         failure_detector_config: FailureDetectorConfig,
         transport: &dyn Transport,
+        client_tls_config_opt: Option<ReloadingClientConfig>,
     ) -> anyhow::Result<Self> {
         info!(
             cluster_id=%cluster_id,
@@ -148,25 +150,25 @@ impl Cluster {
             catchup_callback: Some(Box::new(catchup_callback)),
             extra_liveness_predicate: Some(Box::new(extra_liveness_predicate)),
         };
-        let chitchat_handle = spawn_chitchat(
-            chitchat_config,
-            vec![
-                (
-                    ENABLED_SERVICES_KEY.to_string(),
-                    self_node.enabled_services.iter().join(","),
-                ),
-                (
-                    GRPC_ADVERTISE_ADDR_KEY.to_string(),
-                    self_node.grpc_advertise_addr.to_string(),
-                ),
-                (
-                    READINESS_KEY.to_string(),
-                    READINESS_VALUE_NOT_READY.to_string(),
-                ),
-            ],
-            transport,
-        )
-        .await?;
+        let mut initial_key_values = vec![
+            (
+                ENABLED_SERVICES_KEY.to_string(),
+                self_node.enabled_services.iter().join(","),
+            ),
+            (
+                GRPC_ADVERTISE_ADDR_KEY.to_string(),
+                self_node.grpc_advertise_addr.to_string(),
+            ),
+            (
+                READINESS_KEY.to_string(),
+                READINESS_VALUE_NOT_READY.to_string(),
+            ),
+        ];
+        if let Some(availability_zone) = &self_node.availability_zone_opt {
+            initial_key_values.push((AVAILABILITY_ZONE_KEY.to_string(), availability_zone.clone()));
+        }
+        let chitchat_handle =
+            spawn_chitchat(chitchat_config, initial_key_values, transport).await?;
 
         let chitchat = chitchat_handle.chitchat();
         let chitchat_guard = chitchat.lock().await;
@@ -185,6 +187,7 @@ impl Cluster {
             weak_chitchat,
             live_nodes_rx,
             catchup_callback_rx.clone(),
+            client_tls_config_opt.clone(),
         )
         .await;
 
@@ -195,6 +198,7 @@ impl Cluster {
             live_nodes: BTreeMap::new(),
             change_stream_subscribers: Vec::new(),
             ready_members_rx,
+            client_tls_config_opt,
         };
         let cluster = Cluster {
             cluster_id,
@@ -559,6 +563,7 @@ async fn spawn_change_stream_task(cluster: Cluster) {
     let cluster_id = cluster_guard.cluster_id.clone();
     let self_chitchat_id = cluster_guard.self_chitchat_id.clone();
     let chitchat = cluster_guard.chitchat_handle.chitchat();
+    let client_tls_config_opt = cluster_guard.client_tls_config_opt.clone();
     let weak_cluster = Arc::downgrade(&cluster.inner);
     drop(cluster_guard);
     drop(cluster);
@@ -580,6 +585,7 @@ async fn spawn_change_stream_task(cluster: Cluster) {
                 previous_live_nodes,
                 &previous_live_node_states,
                 &new_live_node_states,
+                client_tls_config_opt.as_ref(),
             )
             .await;
             if !events.is_empty() {
@@ -604,6 +610,8 @@ struct InnerCluster {
     live_nodes: BTreeMap<NodeId, ClusterNode>,
     change_stream_subscribers: Vec<mpsc::UnboundedSender<ClusterChange>>,
     ready_members_rx: watch::Receiver<Vec<ClusterMember>>,
+    /// TLS configuration of the gRPC clients connecting to the other nodes, if TLS is enabled.
+    client_tls_config_opt: Option<ReloadingClientConfig>,
 }
 
 // Not used within the code, used for documentation.
@@ -686,6 +694,7 @@ pub async fn create_cluster_for_test_with_id(
         grpc_advertise_addr: grpc_addr_from_listen_addr_for_test(gossip_advertise_addr),
         indexing_tasks: Vec::new(),
         indexing_cpu_capacity: PIPELINE_FULL_CAPACITY,
+        availability_zone_opt: None,
     };
     let failure_detector_config = create_failure_detector_config_for_test();
     let cluster = Cluster::join(
@@ -696,6 +705,7 @@ pub async fn create_cluster_for_test_with_id(
         Duration::from_millis(25),
         failure_detector_config,
         transport,
+        None,
     )
     .await?;
     cluster.set_self_node_readiness(self_node_readiness).await;
@@ -26,6 +26,7 @@ use std::time::{Duration, Instant};
 use chitchat::{Chitchat, ChitchatId, NodeState, VersionedValue};
 use futures::Future;
 use quickwit_common::pretty::PrettyDisplay;
+use quickwit_common::tls::ReloadingClientConfig;
 use quickwit_proto::cluster::{ClusterService, ClusterServiceClient, FetchClusterStateRequest};
 use rand::seq::IteratorRandom;
 use tokio::sync::{watch, Mutex};
@@ -46,7 +47,11 @@ pub(crate) async fn spawn_catchup_callback_task(
     weak_chitchat: Weak<Mutex<Chitchat>>,
     live_nodes_rx: watch::Receiver<BTreeMap<ChitchatId, NodeState>>,
     mut catchup_callback_rx: watch::Receiver<()>,
+    client_tls_config_opt: Option<ReloadingClientConfig>,
 ) {
+    let grpc_client_factory = move |socket_addr: SocketAddr| {
+        cluster_grpc_client(socket_addr, client_tls_config_opt.clone())
+    };
     let catchup_callback_future = async move {
         let mut interval = tokio::time::interval(Duration::from_secs(60));
         interval.tick().await;
@@ -60,7 +65,7 @@ pub(crate) async fn spawn_catchup_callback_task(
                 &self_chitchat_id,
                 chitchat,
                 live_nodes_rx.clone(),
-                cluster_grpc_client,
+                &grpc_client_factory,
             )
             .await;
 
@@ -22,6 +22,7 @@ use std::net::SocketAddr;
 use bytesize::ByteSize;
 use itertools::Itertools;
 use once_cell::sync::Lazy;
+use quickwit_common::tls::ReloadingClientConfig;
 use quickwit_common::tower::{make_channel, GrpcMetricsLayer};
 use quickwit_proto::cluster::cluster_service_grpc_server::ClusterServiceGrpcServer;
 use quickwit_proto::cluster::{
@@ -40,8 +41,11 @@ static CLUSTER_GRPC_CLIENT_METRICS_LAYER: Lazy<GrpcMetricsLayer> =
 static CLUSTER_GRPC_SERVER_METRICS_LAYER: Lazy<GrpcMetricsLayer> =
     Lazy::new(|| GrpcMetricsLayer::new("cluster", "server"));
 
-pub(crate) async fn cluster_grpc_client(socket_addr: SocketAddr) -> ClusterServiceClient {
-    let channel = make_channel(socket_addr).await;
+pub(crate) async fn cluster_grpc_client(
+    socket_addr: SocketAddr,
+    client_tls_config_opt: Option<ReloadingClientConfig>,
+) -> ClusterServiceClient {
+    let channel = make_channel(socket_addr, client_tls_config_opt.as_ref()).await;
 
     ClusterServiceClient::tower()
         .stack_layer(CLUSTER_GRPC_CLIENT_METRICS_LAYER.clone())
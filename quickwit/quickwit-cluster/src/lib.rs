@@ -30,6 +30,7 @@ mod node;
 use std::net::SocketAddr;
 use std::time::Duration;
 
+use anyhow::Context;
 use async_trait::async_trait;
 pub use chitchat::transport::ChannelTransport;
 use chitchat::transport::{Socket, Transport, UdpSocket};
@@ -37,6 +38,7 @@ use chitchat::{ChitchatMessage, Serializable};
 pub use chitchat::{FailureDetectorConfig, KeyChangeEvent, ListenerHandle};
 pub use grpc_service::cluster_grpc_server;
 use quickwit_common::metrics::IntCounter;
+use quickwit_common::tls::ReloadingClientConfig;
 use quickwit_config::service::QuickwitService;
 use quickwit_config::NodeConfig;
 use quickwit_proto::indexing::CpuCapacity;
@@ -146,11 +148,19 @@ pub async fn start_cluster_service(node_config: &NodeConfig) -> anyhow::Result<C
         grpc_advertise_addr: node_config.grpc_advertise_addr,
         indexing_tasks,
         indexing_cpu_capacity,
+        availability_zone_opt: node_config.availability_zone_opt.clone(),
     };
     let failure_detector_config = FailureDetectorConfig {
         dead_node_grace_period: Duration::from_secs(2 * 60 * 60), // 2 hours
         ..Default::default()
     };
+    let client_tls_config_opt = node_config
+        .grpc_config
+        .tls
+        .as_ref()
+        .map(|tls_config| ReloadingClientConfig::try_new(tls_config.tls_paths()))
+        .transpose()
+        .context("failed to load gRPC client TLS configuration")?;
     let cluster = Cluster::join(
         cluster_id,
         self_node,
@@ -159,6 +169,7 @@ pub async fn start_cluster_service(node_config: &NodeConfig) -> anyhow::Result<C
         node_config.gossip_interval,
         failure_detector_config,
         &CountingUdpTransport,
+        client_tls_config_opt,
     )
     .await?;
     if node_config
@@ -43,6 +43,8 @@ pub(crate) const READINESS_VALUE_NOT_READY: &str = "NOT_READY";
 
 pub const INDEXING_CPU_CAPACITY_KEY: &str = "indexing_cpu_capacity";
 
+pub(crate) const AVAILABILITY_ZONE_KEY: &str = "availability_zone";
+
 pub(crate) trait NodeStateExt {
     fn grpc_advertise_addr(&self) -> anyhow::Result<SocketAddr>;
 
@@ -105,6 +107,8 @@ pub struct ClusterMember {
     pub indexing_tasks: Vec<IndexingTask>,
     /// Indexing cpu capacity of the node expressed in milli cpu.
     pub indexing_cpu_capacity: CpuCapacity,
+    /// Availability zone of the node, if configured.
+    pub availability_zone_opt: Option<String>,
     pub is_ready: bool,
 }
 
@@ -157,6 +161,10 @@ pub(crate) fn build_cluster_member(
     let grpc_advertise_addr = node_state.grpc_advertise_addr()?;
     let indexing_tasks = parse_indexing_tasks(node_state);
     let indexing_cpu_capacity = parse_indexing_cpu_capacity(node_state);
+    let availability_zone_opt = node_state
+        .get(AVAILABILITY_ZONE_KEY)
+        .filter(|availability_zone| !availability_zone.is_empty())
+        .map(|availability_zone| availability_zone.to_string());
     let member = ClusterMember {
         node_id: chitchat_id.node_id.into(),
         generation_id: chitchat_id.generation_id.into(),
@@ -166,6 +174,7 @@ pub(crate) fn build_cluster_member(
         grpc_advertise_addr,
         indexing_tasks,
         indexing_cpu_capacity,
+        availability_zone_opt,
     };
     Ok(member)
 }
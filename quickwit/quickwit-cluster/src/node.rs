@@ -51,6 +51,7 @@ impl ClusterNode {
             grpc_advertise_addr: member.grpc_advertise_addr,
             indexing_tasks: member.indexing_tasks,
             indexing_capacity: member.indexing_cpu_capacity,
+            availability_zone_opt: member.availability_zone_opt,
             is_ready: member.is_ready,
             is_self_node,
         };
@@ -76,7 +77,7 @@ impl ClusterNode {
         let gossip_advertise_addr = ([127, 0, 0, 1], port).into();
         let grpc_advertise_addr = ([127, 0, 0, 1], port + 1).into();
         let chitchat_id = ChitchatId::new(node_id.to_string(), 0, gossip_advertise_addr);
-        let channel = make_channel(grpc_advertise_addr).await;
+        let channel = make_channel(grpc_advertise_addr, None).await;
         let mut node_state = NodeState::for_test();
         node_state.set(ENABLED_SERVICES_KEY, enabled_services.join(","));
         node_state.set(GRPC_ADVERTISE_ADDR_KEY, grpc_advertise_addr.to_string());
@@ -130,6 +131,10 @@ impl ClusterNode {
         self.inner.indexing_capacity
     }
 
+    pub fn availability_zone(&self) -> Option<&str> {
+        self.inner.availability_zone_opt.as_deref()
+    }
+
     pub fn is_ready(&self) -> bool {
         self.inner.is_ready
     }
@@ -156,6 +161,7 @@ impl PartialEq for ClusterNode {
             && self.inner.enabled_services == other.inner.enabled_services
             && self.inner.grpc_advertise_addr == other.inner.grpc_advertise_addr
             && self.inner.indexing_tasks == other.inner.indexing_tasks
+            && self.inner.availability_zone_opt == other.inner.availability_zone_opt
             && self.inner.is_ready == other.inner.is_ready
             && self.inner.is_self_node == other.inner.is_self_node
     }
@@ -168,6 +174,7 @@ struct InnerNode {
     grpc_advertise_addr: SocketAddr,
     indexing_tasks: Vec<IndexingTask>,
     indexing_capacity: CpuCapacity,
+    availability_zone_opt: Option<String>,
     is_ready: bool,
     is_self_node: bool,
 }
@@ -41,9 +41,11 @@ pub mod shared_consts;
 pub mod sorted_iter;
 pub mod stream_utils;
 pub mod temp_dir;
+pub mod tenant_quotas;
 #[cfg(any(test, feature = "testsuite"))]
 pub mod test_utils;
 pub mod thread_pool;
+pub mod tls;
 pub mod tower;
 pub mod type_map;
 pub mod uri;
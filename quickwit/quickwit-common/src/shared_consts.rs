@@ -26,6 +26,10 @@ use tracing::warn;
 /// Field name reserved for storing the dynamically indexed fields.
 pub const FIELD_PRESENCE_FIELD_NAME: &str = "_field_presence";
 
+/// Prefix of the fields reserved for storing the elements of `nested` fields, one value per
+/// element, so that nested queries can evaluate their conditions element by element.
+pub const NESTED_FIELD_PREFIX: &str = "_nested.";
+
 pub const MINIMUM_DELETION_GRACE_PERIOD: Duration = Duration::from_secs(5 * 60); // 5mn
 const MAXIMUM_DELETION_GRACE_PERIOD: Duration = Duration::from_secs(2 * 24 * 3600); // 2 days
 
@@ -71,6 +75,9 @@ pub const INGESTER_PRIMARY_SHARDS_PREFIX: &str = "ingester.primary_shards:";
 /// File name for the encoded list of fields in the split
 pub const SPLIT_FIELDS_FILE_NAME: &str = "split_fields";
 
+/// File name for the encoded bloom filters in the split
+pub const SPLIT_BLOOM_FILTERS_FILE_NAME: &str = "split_bloom_filters";
+
 pub const DEFAULT_SHARD_THROUGHPUT_LIMIT: ByteSize = ByteSize::mib(5);
 
 // (Just a reexport).
@@ -0,0 +1,466 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Quotas enforced per index or per tenant, a tenant being the set of indexes whose IDs share a
+//! common prefix.
+//!
+//! When several quotas cover an index, all of them are enforced. Quotas are enforced locally by
+//! each node: the ingest throughput and concurrent searches limits apply to each ingest router and
+//! root searcher independently.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::rate_limiter::{RateLimiter, RateLimiterSettings};
+
+/// The quota that caused a request to be rejected.
+#[derive(Debug, Copy, Clone, thiserror::Error, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaExceeded {
+    #[error("ingest throughput quota exceeded")]
+    IngestThroughput,
+    #[error("storage quota exceeded")]
+    Storage,
+    #[error("concurrent searches quota exceeded")]
+    ConcurrentSearches,
+    #[error("scanned bytes per query quota exceeded")]
+    ScannedBytes,
+}
+
+/// Limits of a single quota.
+#[derive(Debug, Clone)]
+pub struct QuotaSettings {
+    /// Index ID or index ID prefix followed by a trailing `*`.
+    pub index_id_pattern: String,
+    pub ingest_rate_limiter_settings_opt: Option<RateLimiterSettings>,
+    pub max_storage_bytes_opt: Option<u64>,
+    pub max_concurrent_searches_opt: Option<usize>,
+    pub max_scanned_bytes_per_query_opt: Option<u64>,
+}
+
+struct Quota {
+    index_id_pattern: String,
+    ingest_rate_limiter_opt: Option<Mutex<RateLimiter>>,
+    max_storage_bytes_opt: Option<u64>,
+    // Size of the published splits of the indexes covered by the quota, refreshed periodically.
+    storage_bytes: AtomicU64,
+    search_semaphore_opt: Option<Arc<Semaphore>>,
+    max_scanned_bytes_per_query_opt: Option<u64>,
+}
+
+impl Quota {
+    fn new(settings: QuotaSettings) -> Self {
+        Self {
+            index_id_pattern: settings.index_id_pattern,
+            ingest_rate_limiter_opt: settings
+                .ingest_rate_limiter_settings_opt
+                .map(|settings| Mutex::new(RateLimiter::from_settings(settings))),
+            max_storage_bytes_opt: settings.max_storage_bytes_opt,
+            storage_bytes: AtomicU64::new(0),
+            search_semaphore_opt: settings
+                .max_concurrent_searches_opt
+                .map(|max_concurrent_searches| Arc::new(Semaphore::new(max_concurrent_searches))),
+            max_scanned_bytes_per_query_opt: settings.max_scanned_bytes_per_query_opt,
+        }
+    }
+
+    fn covers(&self, index_id: &str) -> bool {
+        if let Some(prefix) = self.index_id_pattern.strip_suffix('*') {
+            index_id.starts_with(prefix)
+        } else {
+            self.index_id_pattern == index_id
+        }
+    }
+}
+
+/// Holds the quotas of a node and the state required to enforce them. Cloning is cheap and clones
+/// share the same state.
+#[derive(Clone, Default)]
+pub struct TenantQuotas {
+    quotas: Arc<Vec<Quota>>,
+}
+
+impl fmt::Debug for TenantQuotas {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(
+                self.quotas
+                    .iter()
+                    .map(|quota| quota.index_id_pattern.as_str()),
+            )
+            .finish()
+    }
+}
+
+impl TenantQuotas {
+    pub fn new(quota_settings: impl IntoIterator<Item = QuotaSettings>) -> Self {
+        let quotas: Vec<Quota> = quota_settings.into_iter().map(Quota::new).collect();
+        Self {
+            quotas: Arc::new(quotas),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.quotas.is_empty()
+    }
+
+    /// Returns the positions of the quotas covering the index.
+    fn find_quotas<'a>(&'a self, index_id: &'a str) -> impl Iterator<Item = usize> + 'a {
+        self.quotas
+            .iter()
+            .enumerate()
+            .filter(move |(_, quota)| quota.covers(index_id))
+            .map(|(quota_ord, _)| quota_ord)
+    }
+
+    /// Returns whether `num_bytes` can be ingested into the index right now, consuming the
+    /// corresponding ingest throughput credits of every quota covering the index if they can.
+    pub fn acquire_ingest(&self, index_id: &str, num_bytes: u64) -> Result<(), QuotaExceeded> {
+        let quotas: Vec<&Quota> = self
+            .find_quotas(index_id)
+            .map(|quota_ord| &self.quotas[quota_ord])
+            .collect();
+
+        for quota in &quotas {
+            if let Some(max_storage_bytes) = quota.max_storage_bytes_opt {
+                if quota.storage_bytes.load(Ordering::Relaxed) >= max_storage_bytes {
+                    return Err(QuotaExceeded::Storage);
+                }
+            }
+        }
+        let ingest_rate_limiters = quotas
+            .iter()
+            .filter_map(|quota| quota.ingest_rate_limiter_opt.as_ref());
+
+        for (num_acquired, ingest_rate_limiter) in ingest_rate_limiters.clone().enumerate() {
+            if !ingest_rate_limiter
+                .lock()
+                .expect("lock should not be poisoned")
+                .acquire(num_bytes)
+            {
+                // The request is rejected: let's give back the credits consumed from the other
+                // quotas.
+                for ingest_rate_limiter in ingest_rate_limiters.take(num_acquired) {
+                    ingest_rate_limiter
+                        .lock()
+                        .expect("lock should not be poisoned")
+                        .release(num_bytes);
+                }
+                return Err(QuotaExceeded::IngestThroughput);
+            }
+        }
+        Ok(())
+    }
+
+    /// Acquires one search permit for each distinct quota covering the targeted indexes. The
+    /// permits are released when the returned guard is dropped.
+    pub fn acquire_search_permits<'a>(
+        &self,
+        index_ids: impl IntoIterator<Item = &'a str>,
+    ) -> Result<SearchQuotaPermits, QuotaExceeded> {
+        let quota_ords: BTreeSet<usize> = index_ids
+            .into_iter()
+            .flat_map(|index_id| self.find_quotas(index_id))
+            .collect();
+        let mut permits = Vec::with_capacity(quota_ords.len());
+
+        for quota_ord in quota_ords {
+            let Some(search_semaphore) = &self.quotas[quota_ord].search_semaphore_opt else {
+                continue;
+            };
+            let Ok(permit) = search_semaphore.clone().try_acquire_owned() else {
+                return Err(QuotaExceeded::ConcurrentSearches);
+            };
+            permits.push(permit);
+        }
+        Ok(SearchQuotaPermits { _permits: permits })
+    }
+
+    /// Checks that the number of bytes scanned by a search request, summed over the indexes
+    /// covered by each quota, does not exceed the quota.
+    pub fn check_scanned_bytes<'a>(
+        &self,
+        scanned_bytes_per_index: impl IntoIterator<Item = (&'a str, u64)>,
+    ) -> Result<(), QuotaExceeded> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let mut scanned_bytes_per_quota: BTreeMap<usize, u64> = BTreeMap::new();
+
+        for (index_id, num_bytes) in scanned_bytes_per_index {
+            for quota_ord in self.find_quotas(index_id) {
+                *scanned_bytes_per_quota.entry(quota_ord).or_default() += num_bytes;
+            }
+        }
+        for (quota_ord, scanned_bytes) in scanned_bytes_per_quota {
+            if let Some(max_scanned_bytes) = self.quotas[quota_ord].max_scanned_bytes_per_query_opt
+            {
+                if scanned_bytes > max_scanned_bytes {
+                    return Err(QuotaExceeded::ScannedBytes);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the index ID patterns of the quotas limiting storage, i.e. the indexes for which
+    /// storage usage needs to be tracked.
+    pub fn storage_quota_index_id_patterns(&self) -> Vec<String> {
+        self.quotas
+            .iter()
+            .filter(|quota| quota.max_storage_bytes_opt.is_some())
+            .map(|quota| quota.index_id_pattern.clone())
+            .collect()
+    }
+
+    /// Replaces the storage usage of every quota with the sum of the sizes of the indexes it
+    /// covers.
+    pub fn update_storage_usage<'a>(
+        &self,
+        storage_bytes_per_index: impl IntoIterator<Item = (&'a str, u64)>,
+    ) {
+        let mut storage_bytes_per_quota: Vec<u64> = vec![0; self.quotas.len()];
+
+        for (index_id, num_bytes) in storage_bytes_per_index {
+            for quota_ord in self.find_quotas(index_id) {
+                storage_bytes_per_quota[quota_ord] += num_bytes;
+            }
+        }
+        for (quota, storage_bytes) in self.quotas.iter().zip(storage_bytes_per_quota) {
+            quota.storage_bytes.store(storage_bytes, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the storage usage of the quotas limiting storage, keyed by index ID pattern.
+    pub fn storage_usage(&self) -> Vec<(String, u64)> {
+        self.quotas
+            .iter()
+            .filter(|quota| quota.max_storage_bytes_opt.is_some())
+            .map(|quota| {
+                let storage_bytes = quota.storage_bytes.load(Ordering::Relaxed);
+                (quota.index_id_pattern.clone(), storage_bytes)
+            })
+            .collect()
+    }
+
+    /// Sets the storage usage of the quotas with the given index ID pattern, as computed by
+    /// another node.
+    pub fn set_storage_usage(&self, index_id_pattern: &str, storage_bytes: u64) {
+        for quota in self.quotas.iter() {
+            if quota.index_id_pattern == index_id_pattern {
+                quota.storage_bytes.store(storage_bytes, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Search permits acquired from [`TenantQuotas::acquire_search_permits`].
+#[derive(Debug)]
+pub struct SearchQuotaPermits {
+    _permits: Vec<OwnedSemaphorePermit>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bytesize::ByteSize;
+
+    use super::*;
+    use crate::tower::ConstantRate;
+
+    fn quota_settings(index_id_pattern: &str) -> QuotaSettings {
+        QuotaSettings {
+            index_id_pattern: index_id_pattern.to_string(),
+            ingest_rate_limiter_settings_opt: None,
+            max_storage_bytes_opt: None,
+            max_concurrent_searches_opt: None,
+            max_scanned_bytes_per_query_opt: None,
+        }
+    }
+
+    #[test]
+    fn test_tenant_quotas_find_quotas() {
+        let tenant_quotas = TenantQuotas::new([
+            quota_settings("acme-*"),
+            quota_settings("acme-logs-*"),
+            quota_settings("acme-logs-prod"),
+        ]);
+        let find_quotas =
+            |index_id: &str| -> Vec<usize> { tenant_quotas.find_quotas(index_id).collect() };
+        assert!(find_quotas("foo").is_empty());
+        assert_eq!(find_quotas("acme-traces"), [0]);
+        assert_eq!(find_quotas("acme-logs-dev"), [0, 1]);
+        assert_eq!(find_quotas("acme-logs-prod"), [0, 1, 2]);
+        assert_eq!(find_quotas("acme-logs-prod-eu"), [0, 1]);
+    }
+
+    #[test]
+    fn test_tenant_quotas_acquire_ingest() {
+        let tenant_quotas = TenantQuotas::new([
+            QuotaSettings {
+                ingest_rate_limiter_settings_opt: Some(RateLimiterSettings {
+                    burst_limit: ByteSize::mb(2).as_u64(),
+                    rate_limit: ConstantRate::bytes_per_sec(ByteSize::mb(1)),
+                    refill_period: Duration::from_secs(60),
+                }),
+                ..quota_settings("acme-*")
+            },
+            QuotaSettings {
+                max_storage_bytes_opt: Some(ByteSize::gb(1).as_u64()),
+                ..quota_settings("globex-*")
+            },
+        ]);
+        tenant_quotas
+            .acquire_ingest("foo", ByteSize::gb(1).as_u64())
+            .unwrap();
+
+        tenant_quotas
+            .acquire_ingest("acme-logs", ByteSize::mb(1).as_u64())
+            .unwrap();
+        tenant_quotas
+            .acquire_ingest("acme-traces", ByteSize::mb(1).as_u64())
+            .unwrap();
+        assert_eq!(
+            tenant_quotas
+                .acquire_ingest("acme-logs", ByteSize::kb(1).as_u64())
+                .unwrap_err(),
+            QuotaExceeded::IngestThroughput
+        );
+
+        tenant_quotas
+            .acquire_ingest("globex-logs", ByteSize::mb(1).as_u64())
+            .unwrap();
+        tenant_quotas.update_storage_usage([
+            ("globex-logs", ByteSize::mb(600).as_u64()),
+            ("globex-traces", ByteSize::mb(400).as_u64()),
+        ]);
+        assert_eq!(
+            tenant_quotas
+                .acquire_ingest("globex-logs", ByteSize::kb(1).as_u64())
+                .unwrap_err(),
+            QuotaExceeded::Storage
+        );
+        tenant_quotas.update_storage_usage([("globex-logs", ByteSize::mb(600).as_u64())]);
+        tenant_quotas
+            .acquire_ingest("globex-logs", ByteSize::kb(1).as_u64())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_tenant_quotas_acquire_ingest_enforces_all_matching_quotas() {
+        let ingest_rate_limiter_settings = |burst_limit: ByteSize| RateLimiterSettings {
+            burst_limit: burst_limit.as_u64(),
+            rate_limit: ConstantRate::bytes_per_sec(ByteSize::kb(1)),
+            refill_period: Duration::from_secs(60),
+        };
+        let tenant_quotas = TenantQuotas::new([
+            QuotaSettings {
+                ingest_rate_limiter_settings_opt: Some(ingest_rate_limiter_settings(ByteSize::mb(
+                    3,
+                ))),
+                max_storage_bytes_opt: Some(ByteSize::gb(1).as_u64()),
+                ..quota_settings("acme-*")
+            },
+            QuotaSettings {
+                ingest_rate_limiter_settings_opt: Some(ingest_rate_limiter_settings(ByteSize::mb(
+                    2,
+                ))),
+                ..quota_settings("acme-logs")
+            },
+        ]);
+        tenant_quotas
+            .acquire_ingest("acme-logs", ByteSize::mb(2).as_u64())
+            .unwrap();
+        // The exhausted `acme-logs` quota rejects the request and the credits taken from the
+        // `acme-*` quota are given back.
+        assert_eq!(
+            tenant_quotas
+                .acquire_ingest("acme-logs", ByteSize::kb(512).as_u64())
+                .unwrap_err(),
+            QuotaExceeded::IngestThroughput
+        );
+        tenant_quotas
+            .acquire_ingest("acme-traces", ByteSize::mb(1).as_u64())
+            .unwrap();
+
+        // The storage of `acme-logs` counts towards the `acme-*` quota.
+        tenant_quotas.update_storage_usage([("acme-logs", ByteSize::gb(1).as_u64())]);
+        assert_eq!(
+            tenant_quotas.storage_usage(),
+            [("acme-*".to_string(), ByteSize::gb(1).as_u64())]
+        );
+        assert_eq!(
+            tenant_quotas.acquire_ingest("acme-traces", 1).unwrap_err(),
+            QuotaExceeded::Storage
+        );
+        tenant_quotas.set_storage_usage("acme-*", 0);
+        tenant_quotas.acquire_ingest("acme-traces", 1).unwrap();
+    }
+
+    #[test]
+    fn test_tenant_quotas_acquire_search_permits() {
+        let tenant_quotas = TenantQuotas::new([QuotaSettings {
+            max_concurrent_searches_opt: Some(1),
+            ..quota_settings("acme-*")
+        }]);
+        let permits = tenant_quotas
+            .acquire_search_permits(["acme-logs", "acme-traces", "foo"])
+            .unwrap();
+        assert_eq!(
+            tenant_quotas
+                .acquire_search_permits(["acme-logs"])
+                .unwrap_err(),
+            QuotaExceeded::ConcurrentSearches
+        );
+        tenant_quotas.acquire_search_permits(["foo"]).unwrap();
+
+        drop(permits);
+        tenant_quotas.acquire_search_permits(["acme-logs"]).unwrap();
+    }
+
+    #[test]
+    fn test_tenant_quotas_check_scanned_bytes() {
+        let tenant_quotas = TenantQuotas::new([QuotaSettings {
+            max_scanned_bytes_per_query_opt: Some(ByteSize::gb(1).as_u64()),
+            ..quota_settings("acme-*")
+        }]);
+        tenant_quotas
+            .check_scanned_bytes([
+                ("acme-logs", ByteSize::mb(500).as_u64()),
+                ("acme-traces", ByteSize::mb(500).as_u64()),
+                ("foo", ByteSize::gb(10).as_u64()),
+            ])
+            .unwrap();
+        assert_eq!(
+            tenant_quotas
+                .check_scanned_bytes([
+                    ("acme-logs", ByteSize::mb(600).as_u64()),
+                    ("acme-traces", ByteSize::mb(600).as_u64()),
+                ])
+                .unwrap_err(),
+            QuotaExceeded::ScannedBytes
+        );
+    }
+}
@@ -0,0 +1,561 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! TLS configurations of the REST and gRPC servers and of the gRPC clients.
+//!
+//! The certificates and keys are loaded from PEM files. When a new connection is established,
+//! the files are checked for changes (at most once per second) and reloaded in the background if
+//! they were modified, so certificates can be rotated without restarting the node. Established
+//! connections keep using the certificates negotiated during their handshake.
+
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::{bail, Context as _};
+use arc_swap::ArcSwap;
+use futures::Stream;
+use http::Uri;
+use rustls::server::{AllowAnyAnonymousOrAuthenticatedClient, AllowAnyAuthenticatedClient};
+use rustls::{Certificate, ClientConfig, PrivateKey, RootCertStore, ServerConfig, ServerName};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::{client, server, TlsAcceptor, TlsConnector};
+use tokio_stream::wrappers::ReceiverStream;
+use tower::{BoxError, Service};
+use tracing::{debug, info, warn};
+
+use crate::tower::BoxFuture;
+
+/// Minimum interval between two checks for changes of the certificate, key, and CA files.
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Maximum duration of a TLS handshake. Connections that do not complete their handshake in time
+/// are dropped.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A TLS connection accepted by a server.
+pub type ServerTlsStream = server::TlsStream<TcpStream>;
+
+/// Paths of the PEM files holding the certificate chain, the private key, and optionally the CA
+/// certificates used to verify the peers.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TlsPaths {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+    pub ca_path_opt: Option<PathBuf>,
+}
+
+impl TlsPaths {
+    fn last_modified(&self) -> Vec<Option<SystemTime>> {
+        [
+            Some(&self.cert_path),
+            Some(&self.key_path),
+            self.ca_path_opt.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|path| {
+            std::fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+        .collect()
+    }
+}
+
+/// Whether and how the server verifies the certificates of its clients.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ClientAuth {
+    /// Client certificates are not requested.
+    Disabled,
+    /// Client certificates are requested and verified when presented, but not required.
+    Optional,
+    /// Connections without a valid client certificate are rejected during the handshake.
+    Required,
+}
+
+type BuildConfigFn<T> = Box<dyn Fn(&TlsPaths) -> anyhow::Result<T> + Send + Sync>;
+
+struct ReloadingConfigInner<T> {
+    tls_paths: TlsPaths,
+    build_fn: BuildConfigFn<T>,
+    config: ArcSwap<T>,
+    /// Modification times of the files the current configuration was built from.
+    last_modified: Mutex<Vec<Option<SystemTime>>>,
+    last_checked_at: Mutex<Instant>,
+    is_reloading: AtomicBool,
+}
+
+impl<T> ReloadingConfigInner<T> {
+    /// Rebuilds the configuration if its files have changed since it was built.
+    fn reload_if_modified(&self) {
+        let modified = self.tls_paths.last_modified();
+        let mut last_modified = self
+            .last_modified
+            .lock()
+            .expect("lock should not be poisoned");
+
+        if modified != *last_modified {
+            match (self.build_fn)(&self.tls_paths) {
+                Ok(config) => {
+                    self.config.store(Arc::new(config));
+                    *last_modified = modified;
+                    info!(cert_path=%self.tls_paths.cert_path.display(), "reloaded TLS certificates");
+                }
+                Err(error) => {
+                    // The files may be in the middle of being rotated. We keep using the previous
+                    // certificates and try again on the next check.
+                    warn!(
+                        cert_path=%self.tls_paths.cert_path.display(),
+                        %error,
+                        "failed to reload TLS certificates"
+                    );
+                }
+            }
+        }
+        self.is_reloading.store(false, Ordering::Release);
+    }
+}
+
+/// A configuration that is rebuilt from its source files whenever they change on disk. The files
+/// are checked lazily, when the configuration is accessed, and reloaded on the blocking thread
+/// pool so as not to delay the handshakes.
+struct ReloadingConfig<T> {
+    inner: Arc<ReloadingConfigInner<T>>,
+}
+
+impl<T> Clone for ReloadingConfig<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static> ReloadingConfig<T> {
+    fn try_new<F>(tls_paths: TlsPaths, build_fn: F) -> anyhow::Result<Self>
+    where F: Fn(&TlsPaths) -> anyhow::Result<T> + Send + Sync + 'static {
+        let last_modified = tls_paths.last_modified();
+        let config = build_fn(&tls_paths)?;
+        let inner = ReloadingConfigInner {
+            tls_paths,
+            build_fn: Box::new(build_fn),
+            config: ArcSwap::from_pointee(config),
+            last_modified: Mutex::new(last_modified),
+            last_checked_at: Mutex::new(Instant::now()),
+            is_reloading: AtomicBool::new(false),
+        };
+        Ok(Self {
+            inner: Arc::new(inner),
+        })
+    }
+
+    /// Returns the current configuration. If the check interval has elapsed, the files are checked
+    /// for changes in the background: the configuration returned may be the previous one.
+    fn get(&self) -> Arc<T> {
+        if self.should_check() && !self.inner.is_reloading.swap(true, Ordering::AcqRel) {
+            let inner = self.inner.clone();
+
+            match tokio::runtime::Handle::try_current() {
+                Ok(runtime_handle) => {
+                    runtime_handle.spawn_blocking(move || inner.reload_if_modified());
+                }
+                Err(_) => inner.reload_if_modified(),
+            }
+        }
+        self.inner.config.load_full()
+    }
+
+    fn should_check(&self) -> bool {
+        let Ok(mut last_checked_at) = self.inner.last_checked_at.try_lock() else {
+            return false;
+        };
+        if last_checked_at.elapsed() < RELOAD_CHECK_INTERVAL {
+            return false;
+        }
+        *last_checked_at = Instant::now();
+        true
+    }
+}
+
+/// A rustls server configuration reloaded when its certificate files change.
+#[derive(Clone)]
+pub struct ReloadingServerConfig {
+    inner: ReloadingConfig<ServerConfig>,
+}
+
+impl ReloadingServerConfig {
+    /// Loads the server configuration. `alpn_protocols` lists the application protocols
+    /// advertised during the handshake, for instance `h2` for gRPC.
+    pub fn try_new(
+        tls_paths: TlsPaths,
+        client_auth: ClientAuth,
+        alpn_protocols: Vec<Vec<u8>>,
+    ) -> anyhow::Result<Self> {
+        let inner = ReloadingConfig::try_new(tls_paths, move |tls_paths| {
+            build_server_config(tls_paths, client_auth, alpn_protocols.clone())
+        })?;
+        Ok(Self { inner })
+    }
+
+    fn acceptor(&self) -> TlsAcceptor {
+        TlsAcceptor::from(self.inner.get())
+    }
+}
+
+/// A rustls client configuration reloaded when its certificate files change.
+#[derive(Clone)]
+pub struct ReloadingClientConfig {
+    inner: ReloadingConfig<ClientConfig>,
+}
+
+impl ReloadingClientConfig {
+    /// Loads the client configuration. The certificate is presented to the servers and the CA
+    /// certificates are used to verify theirs.
+    pub fn try_new(tls_paths: TlsPaths) -> anyhow::Result<Self> {
+        let inner = ReloadingConfig::try_new(tls_paths, build_client_config)?;
+        Ok(Self { inner })
+    }
+
+    /// Returns a connector for tonic channels establishing TLS connections to `socket_addr`.
+    pub fn grpc_connector(&self, socket_addr: SocketAddr) -> GrpcTlsConnector {
+        GrpcTlsConnector {
+            socket_addr,
+            client_config: self.clone(),
+        }
+    }
+
+    fn connector(&self) -> TlsConnector {
+        TlsConnector::from(self.inner.get())
+    }
+}
+
+fn load_certs(path: &Path) -> anyhow::Result<Vec<Certificate>> {
+    let file = File::open(path)
+        .with_context(|| format!("failed to open certificate file `{}`", path.display()))?;
+    let certs: Vec<Certificate> = rustls_pemfile::certs(&mut BufReader::new(file))
+        .with_context(|| format!("failed to parse certificate file `{}`", path.display()))?
+        .into_iter()
+        .map(Certificate)
+        .collect();
+    if certs.is_empty() {
+        bail!("no certificate found in file `{}`", path.display());
+    }
+    Ok(certs)
+}
+
+fn load_private_key(path: &Path) -> anyhow::Result<PrivateKey> {
+    let file = File::open(path)
+        .with_context(|| format!("failed to open private key file `{}`", path.display()))?;
+    let items = rustls_pemfile::read_all(&mut BufReader::new(file))
+        .with_context(|| format!("failed to parse private key file `{}`", path.display()))?;
+
+    for item in items {
+        match item {
+            rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::ECKey(key) => return Ok(PrivateKey(key)),
+            _ => {}
+        }
+    }
+    bail!("no private key found in file `{}`", path.display())
+}
+
+fn load_root_store(path: &Path) -> anyhow::Result<RootCertStore> {
+    let mut root_store = RootCertStore::empty();
+
+    for cert in load_certs(path)? {
+        root_store
+            .add(&cert)
+            .with_context(|| format!("invalid CA certificate in file `{}`", path.display()))?;
+    }
+    Ok(root_store)
+}
+
+fn build_server_config(
+    tls_paths: &TlsPaths,
+    client_auth: ClientAuth,
+    alpn_protocols: Vec<Vec<u8>>,
+) -> anyhow::Result<ServerConfig> {
+    let certs = load_certs(&tls_paths.cert_path)?;
+    let private_key = load_private_key(&tls_paths.key_path)?;
+    let config_builder = ServerConfig::builder().with_safe_defaults();
+
+    let config_builder = match (client_auth, &tls_paths.ca_path_opt) {
+        (ClientAuth::Disabled, _) => config_builder.with_no_client_auth(),
+        (ClientAuth::Optional, Some(ca_path)) => {
+            let root_store = load_root_store(ca_path)?;
+            config_builder.with_client_cert_verifier(
+                AllowAnyAnonymousOrAuthenticatedClient::new(root_store).boxed(),
+            )
+        }
+        (ClientAuth::Required, Some(ca_path)) => {
+            let root_store = load_root_store(ca_path)?;
+            config_builder
+                .with_client_cert_verifier(AllowAnyAuthenticatedClient::new(root_store).boxed())
+        }
+        (_, None) => bail!("a CA certificate is required to verify client certificates"),
+    };
+    let mut server_config = config_builder
+        .with_single_cert(certs, private_key)
+        .context("invalid certificate or private key")?;
+    server_config.alpn_protocols = alpn_protocols;
+    Ok(server_config)
+}
+
+fn build_client_config(tls_paths: &TlsPaths) -> anyhow::Result<ClientConfig> {
+    let Some(ca_path) = &tls_paths.ca_path_opt else {
+        bail!("a CA certificate is required to verify server certificates");
+    };
+    let root_store = load_root_store(ca_path)?;
+    let certs = load_certs(&tls_paths.cert_path)?;
+    let private_key = load_private_key(&tls_paths.key_path)?;
+
+    let mut client_config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(root_store)
+        .with_client_auth_cert(certs, private_key)
+        .context("invalid certificate or private key")?;
+    client_config.alpn_protocols = vec![b"h2".to_vec()];
+    Ok(client_config)
+}
+
+/// Accepts the TCP connections of `tcp_listener` and performs their TLS handshakes concurrently,
+/// so that a slow or malicious client does not hold up the others. Connections failing their
+/// handshake are dropped and never yielded by the returned stream.
+pub fn tls_incoming(
+    tcp_listener: TcpListener,
+    server_config: ReloadingServerConfig,
+) -> impl Stream<Item = io::Result<ServerTlsStream>> + Send + 'static {
+    let (tls_stream_tx, tls_stream_rx) = mpsc::channel(128);
+
+    let accept_loop = async move {
+        loop {
+            let (tcp_stream, peer_addr) = match tcp_listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(error) => {
+                    warn!(%error, "failed to accept TCP connection");
+                    // Accept errors are usually caused by file descriptor exhaustion: let's give
+                    // the other connections a chance to close.
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            };
+            if tls_stream_tx.is_closed() {
+                return;
+            }
+            let _ = tcp_stream.set_nodelay(true);
+            let acceptor = server_config.acceptor();
+            let tls_stream_tx = tls_stream_tx.clone();
+
+            tokio::spawn(async move {
+                match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(tcp_stream)).await {
+                    Ok(Ok(tls_stream)) => {
+                        let _ = tls_stream_tx.send(Ok(tls_stream)).await;
+                    }
+                    Ok(Err(error)) => {
+                        debug!(%peer_addr, %error, "TLS handshake failed");
+                    }
+                    Err(_) => {
+                        debug!(%peer_addr, "TLS handshake timed out");
+                    }
+                }
+            });
+        }
+    };
+    tokio::spawn(accept_loop);
+    ReceiverStream::new(tls_stream_rx)
+}
+
+/// Returns whether the client of the TLS connection presented a certificate. The certificate has
+/// necessarily been verified against the CA during the handshake.
+pub fn has_client_cert(tls_stream: &ServerTlsStream) -> bool {
+    tls_stream.get_ref().1.peer_certificates().is_some()
+}
+
+/// Connector for tonic channels establishing TLS connections. The certificate of the server is
+/// verified against its IP address, so the certificates of the nodes must list their gRPC
+/// advertise IP addresses as subject alternative names.
+#[derive(Clone)]
+pub struct GrpcTlsConnector {
+    socket_addr: SocketAddr,
+    client_config: ReloadingClientConfig,
+}
+
+impl Service<Uri> for GrpcTlsConnector {
+    type Response = client::TlsStream<TcpStream>;
+    type Error = BoxError;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _uri: Uri) -> Self::Future {
+        let socket_addr = self.socket_addr;
+        let connector = self.client_config.connector();
+
+        Box::pin(async move {
+            let tcp_stream = TcpStream::connect(socket_addr).await?;
+            tcp_stream.set_nodelay(true)?;
+            let server_name = ServerName::IpAddress(socket_addr.ip());
+            let tls_stream = connector.connect(server_name, tcp_stream).await?;
+            Ok(tls_stream)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tls_paths_last_modified() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cert_path = temp_dir.path().join("node.crt");
+        let key_path = temp_dir.path().join("node.key");
+        std::fs::write(&cert_path, b"cert").unwrap();
+
+        let tls_paths = TlsPaths {
+            cert_path,
+            key_path: key_path.clone(),
+            ca_path_opt: None,
+        };
+        let last_modified = tls_paths.last_modified();
+        assert_eq!(last_modified.len(), 2);
+        assert!(last_modified[0].is_some());
+        assert!(last_modified[1].is_none());
+
+        std::fs::write(&key_path, b"key").unwrap();
+        assert_ne!(tls_paths.last_modified(), last_modified);
+    }
+
+    #[test]
+    fn test_load_certs_and_key_errors() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let missing_path = temp_dir.path().join("missing.crt");
+        let error = load_certs(&missing_path).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("failed to open certificate file"));
+
+        let empty_path = temp_dir.path().join("empty.pem");
+        std::fs::write(&empty_path, b"").unwrap();
+        let error = load_certs(&empty_path).unwrap_err();
+        assert!(error.to_string().starts_with("no certificate found"));
+
+        let error = load_private_key(&empty_path).unwrap_err();
+        assert!(error.to_string().starts_with("no private key found"));
+    }
+
+    #[test]
+    fn test_reloading_config_reloads_modified_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cert_path = temp_dir.path().join("node.crt");
+        let key_path = temp_dir.path().join("node.key");
+        std::fs::write(&cert_path, b"cert-v1").unwrap();
+        std::fs::write(&key_path, b"key").unwrap();
+
+        let tls_paths = TlsPaths {
+            cert_path: cert_path.clone(),
+            key_path,
+            ca_path_opt: None,
+        };
+        let reloading_config = ReloadingConfig::try_new(tls_paths, |tls_paths| {
+            let cert = std::fs::read_to_string(&tls_paths.cert_path)?;
+            if cert.is_empty() {
+                bail!("empty certificate");
+            }
+            Ok(cert)
+        })
+        .unwrap();
+        assert_eq!(*reloading_config.get(), "cert-v1");
+
+        let force_check = || {
+            *reloading_config.inner.last_checked_at.lock().unwrap() -= RELOAD_CHECK_INTERVAL;
+            // Modification times may have a coarse granularity: let's make sure the change is
+            // detected.
+            reloading_config.inner.last_modified.lock().unwrap().clear();
+        };
+        std::fs::write(&cert_path, b"cert-v2").unwrap();
+        // The files are not checked again before the check interval has elapsed.
+        assert_eq!(*reloading_config.get(), "cert-v1");
+
+        force_check();
+        assert_eq!(*reloading_config.get(), "cert-v2");
+
+        // Invalid files are ignored and the previous configuration is kept.
+        std::fs::write(&cert_path, b"").unwrap();
+        force_check();
+        assert_eq!(*reloading_config.get(), "cert-v2");
+    }
+
+    #[tokio::test]
+    async fn test_reloading_config_reloads_in_the_background() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cert_path = temp_dir.path().join("node.crt");
+        let key_path = temp_dir.path().join("node.key");
+        std::fs::write(&cert_path, b"cert-v1").unwrap();
+        std::fs::write(&key_path, b"key").unwrap();
+
+        let tls_paths = TlsPaths {
+            cert_path: cert_path.clone(),
+            key_path,
+            ca_path_opt: None,
+        };
+        let reloading_config = ReloadingConfig::try_new(tls_paths, |tls_paths| {
+            Ok(std::fs::read_to_string(&tls_paths.cert_path)?)
+        })
+        .unwrap();
+        std::fs::write(&cert_path, b"cert-v2").unwrap();
+        *reloading_config.inner.last_checked_at.lock().unwrap() -= RELOAD_CHECK_INTERVAL;
+        reloading_config.inner.last_modified.lock().unwrap().clear();
+
+        // The first access schedules the reload and returns the current configuration.
+        reloading_config.get();
+
+        for _ in 0..100 {
+            if *reloading_config.get() == "cert-v2" {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("the configuration should have been reloaded");
+    }
+
+    #[test]
+    fn test_reloading_client_config_requires_ca() {
+        let tls_paths = TlsPaths {
+            cert_path: PathBuf::from("node.crt"),
+            key_path: PathBuf::from("node.key"),
+            ca_path_opt: None,
+        };
+        let error = ReloadingClientConfig::try_new(tls_paths).err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "a CA certificate is required to verify server certificates"
+        );
+    }
+}
@@ -39,6 +39,7 @@ use tower::load::{CompleteOnResponse, PendingRequestsDiscover};
 use tower::{BoxError, Service, ServiceExt};
 
 use super::{BoxFuture, Change};
+use crate::tls::ReloadingClientConfig;
 use crate::BoxStream;
 
 // Transforms a boxed stream of `Change<K, Channel>` into a stream of `Result<TowerChange<K,
@@ -197,19 +198,27 @@ where K: Hash + Eq + Clone + Send + Sync + 'static
     }
 }
 
-/// Creates a channel from a socket address.
+/// Creates a channel from a socket address. The connection is established over TLS if a client
+/// TLS configuration is provided.
 ///
 /// The function is marked as `async` because it requires an executor (`connect_lazy`).
-pub async fn make_channel(socket_addr: SocketAddr) -> Channel {
+pub async fn make_channel(
+    socket_addr: SocketAddr,
+    client_tls_config_opt: Option<&ReloadingClientConfig>,
+) -> Channel {
     let uri = Uri::builder()
         .scheme("http")
         .authority(socket_addr.to_string())
         .path_and_query("/")
         .build()
         .expect("provided arguments should be valid");
-    Endpoint::from(uri)
-        .connect_timeout(Duration::from_secs(5))
-        .connect_lazy()
+    let endpoint = Endpoint::from(uri).connect_timeout(Duration::from_secs(5));
+
+    if let Some(client_tls_config) = client_tls_config_opt {
+        let tls_connector = client_tls_config.grpc_connector(socket_addr);
+        return endpoint.connect_with_connector_lazy(tls_connector);
+    }
+    endpoint.connect_lazy()
 }
 
 /// Forces a channel to initiate the underlying HTTP connection. Calling this function only makes
@@ -30,15 +30,19 @@ use chrono::Utc;
 use cron::Schedule;
 use humantime::parse_duration;
 use quickwit_common::uri::Uri;
-use quickwit_doc_mapper::{DocMapper, DocMapperBuilder, DocMapping};
+use quickwit_doc_mapper::{DocMapper, DocMapperBuilder, DocMapping, RuntimeField};
+use quickwit_proto::search::SortOrder;
 use quickwit_proto::types::IndexId;
 use serde::{Deserialize, Serialize};
-pub use serialize::{load_index_config_from_user_config, load_index_config_update};
+pub use serialize::{
+    load_index_config_from_user_config, load_index_config_update, validate_index_config_update,
+};
 use siphasher::sip::SipHasher;
 use tracing::warn;
 
 use crate::index_config::serialize::VersionedIndexConfig;
 use crate::merge_policy_config::MergePolicyConfig;
+use crate::validate_identifier;
 
 #[derive(Clone, Debug, Serialize, Deserialize, utoipa::ToSchema)]
 #[serde(deny_unknown_fields)]
@@ -121,6 +125,19 @@ pub struct IndexingSettings {
     pub merge_policy: MergePolicyConfig,
     #[serde(default)]
     pub resources: IndexingResources,
+    /// Fast fields by which the documents of each split are sorted, in order of precedence.
+    /// Searches sorted by the first of these fields can stop early.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sort_by: Vec<IndexSortField>,
+}
+
+/// A fast field by which the documents of the splits of an index are sorted.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct IndexSortField {
+    pub field: String,
+    pub order: SortOrder,
 }
 
 impl IndexingSettings {
@@ -162,6 +179,7 @@ impl Default for IndexingSettings {
             split_num_docs_target: Self::default_split_num_docs_target(),
             merge_policy: MergePolicyConfig::default(),
             resources: IndexingResources::default(),
+            sort_by: Vec::new(),
         }
     }
 }
@@ -171,6 +189,10 @@ impl Default for IndexingSettings {
 pub struct SearchSettings {
     #[serde(default)]
     pub default_search_fields: Vec<String>,
+    /// Fields computed at query time by evaluating a VRL script over the stored documents.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub runtime_fields: Vec<RuntimeField>,
 }
 
 #[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
@@ -216,14 +238,7 @@ impl RetentionPolicy {
 
     pub fn duration_until_next_evaluation(&self) -> anyhow::Result<Duration> {
         let schedule = self.evaluation_schedule()?;
-        let future_date = schedule
-            .upcoming(Utc)
-            .next()
-            .expect("Failed to obtain next evaluation date.");
-        let duration = (future_date - Utc::now())
-            .to_std()
-            .map_err(|err| anyhow::anyhow!(err.to_string()))?;
-        Ok(duration)
+        duration_until_next_upcoming(&schedule)
     }
 
     pub(super) fn validate(&self) -> anyhow::Result<()> {
@@ -233,6 +248,124 @@ impl RetentionPolicy {
     }
 }
 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RollupMetric {
+    /// Numeric fast field to aggregate.
+    pub field: String,
+    /// Percentiles to compute in addition to the min, max, sum and average values (`50`, `99.9`,
+    /// ...).
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub percentiles: Vec<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RollupConfig {
+    /// ID of the index the pre-aggregated documents are written to.
+    pub target_index_id: IndexId,
+
+    /// Width of the date histogram buckets, expressed in a human-friendly way (`1m`, `1h`, ...).
+    #[serde(default = "RollupConfig::default_interval")]
+    pub interval: String,
+
+    /// Fast fields the documents are grouped by, in addition to the timestamp.
+    #[serde(default)]
+    pub group_by: Vec<String>,
+
+    /// Maximum number of terms kept per group by field within a bucket.
+    #[serde(default = "RollupConfig::default_max_terms")]
+    pub max_terms: u32,
+
+    /// Numeric fields aggregated within each bucket.
+    #[serde(default)]
+    pub metrics: Vec<RollupMetric>,
+
+    /// Delay after which a time bucket is considered closed and gets rolled up, expressed in a
+    /// human-friendly way (`5m`, `1h`, ...). Documents arriving after their bucket was rolled up
+    /// are not taken into account.
+    #[serde(default = "RollupConfig::default_delay")]
+    pub delay: String,
+
+    /// Defines the frequency at which the rollup job runs, expressed in a human-friendly way
+    /// (`hourly`, `daily`, ...) or as a cron expression.
+    #[serde(default = "RollupConfig::default_schedule")]
+    pub schedule: String,
+}
+
+impl RollupConfig {
+    fn default_interval() -> String {
+        "1m".to_string()
+    }
+
+    fn default_max_terms() -> u32 {
+        1_000
+    }
+
+    fn default_delay() -> String {
+        "10m".to_string()
+    }
+
+    fn default_schedule() -> String {
+        "hourly".to_string()
+    }
+
+    pub fn interval(&self) -> anyhow::Result<Duration> {
+        parse_duration(&self.interval)
+            .with_context(|| format!("failed to parse rollup interval `{}`", self.interval))
+    }
+
+    pub fn delay(&self) -> anyhow::Result<Duration> {
+        parse_duration(&self.delay)
+            .with_context(|| format!("failed to parse rollup delay `{}`", self.delay))
+    }
+
+    pub fn schedule(&self) -> anyhow::Result<Schedule> {
+        let schedule = prepend_at_char(&self.schedule);
+
+        Schedule::from_str(&schedule)
+            .with_context(|| format!("failed to parse rollup schedule `{}`", self.schedule))
+    }
+
+    pub fn duration_until_next_execution(&self) -> anyhow::Result<Duration> {
+        let schedule = self.schedule()?;
+        duration_until_next_upcoming(&schedule)
+    }
+
+    pub(super) fn validate(&self) -> anyhow::Result<()> {
+        validate_identifier("rollup target index", &self.target_index_id)?;
+        let interval = self.interval()?;
+        ensure!(
+            interval.as_secs() > 0 && interval.subsec_nanos() == 0,
+            "rollup interval must be a non-zero whole number of seconds"
+        );
+        self.delay()?;
+        self.schedule()?;
+
+        for metric in &self.metrics {
+            for percentile in &metric.percentiles {
+                ensure!(
+                    (0.0..=100.0).contains(percentile),
+                    "rollup percentiles must be within [0, 100], got `{percentile}`"
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+fn duration_until_next_upcoming(schedule: &Schedule) -> anyhow::Result<Duration> {
+    let future_date = schedule
+        .upcoming(Utc)
+        .next()
+        .expect("Failed to obtain next evaluation date.");
+    let duration = (future_date - Utc::now())
+        .to_std()
+        .map_err(|err| anyhow::anyhow!(err.to_string()))?;
+    Ok(duration)
+}
+
 /// Prepends an `@` char at the start of the cron expression if necessary:
 /// `hourly` -> `@hourly`
 fn prepend_at_char(schedule: &str) -> String {
@@ -258,6 +391,7 @@ pub struct IndexConfig {
     pub indexing_settings: IndexingSettings,
     pub search_settings: SearchSettings,
     pub retention_policy_opt: Option<RetentionPolicy>,
+    pub rollup_opt: Option<RollupConfig>,
 }
 
 impl IndexConfig {
@@ -344,6 +478,7 @@ impl IndexConfig {
                 r#"attributes.server"#.to_string(),
                 r"attributes.server\.status".to_string(),
             ],
+            ..Default::default()
         };
         IndexConfig {
             index_id: index_id.to_string(),
@@ -352,6 +487,7 @@ impl IndexConfig {
             indexing_settings,
             search_settings,
             retention_policy_opt: Default::default(),
+            rollup_opt: None,
         }
     }
 }
@@ -419,6 +555,8 @@ impl crate::TestableForRegression for IndexConfig {
             ],
             timestamp_field: Some("timestamp".to_string()),
             tag_fields: BTreeSet::from_iter(["tenant_id".to_string(), "log_level".to_string()]),
+            zone_map_fields: BTreeSet::new(),
+            bloom_filter_fields: BTreeSet::new(),
             partition_key: Some("tenant_id".to_string()),
             max_num_partitions: NonZeroU32::new(100).unwrap(),
             index_field_presence: true,
@@ -449,6 +587,7 @@ impl crate::TestableForRegression for IndexConfig {
         };
         let search_settings = SearchSettings {
             default_search_fields: vec!["message".to_string()],
+            ..Default::default()
         };
         IndexConfig {
             index_id: "my-index".to_string(),
@@ -456,6 +595,7 @@ impl crate::TestableForRegression for IndexConfig {
             doc_mapping,
             indexing_settings,
             retention_policy_opt: retention_policy,
+            rollup_opt: None,
             search_settings,
         }
     }
@@ -477,6 +617,7 @@ pub fn build_doc_mapper(
     let builder = DocMapperBuilder {
         doc_mapping: doc_mapping.clone(),
         default_search_fields: search_settings.default_search_fields.clone(),
+        runtime_fields: search_settings.runtime_fields.clone(),
         legacy_type_tag: None,
     };
     Ok(Arc::new(builder.try_build()?))
@@ -489,14 +630,22 @@ pub(super) fn validate_index_config(
     indexing_settings: &IndexingSettings,
     search_settings: &SearchSettings,
     retention_policy_opt: &Option<RetentionPolicy>,
+    rollup_opt: &Option<RollupConfig>,
 ) -> anyhow::Result<()> {
     // Note: this needs a deep refactoring to separate the doc mapping configuration,
     // and doc mapper implementations.
     // TODO see if we should store the byproducton the IndexConfig.
-    build_doc_mapper(doc_mapping, search_settings)?;
+    let doc_mapper = build_doc_mapper(doc_mapping, search_settings)?;
 
+    #[cfg(feature = "vrl")]
+    for runtime_field in &search_settings.runtime_fields {
+        crate::compile_vrl_script(&runtime_field.script).with_context(|| {
+            format!("invalid script for runtime field `{}`", runtime_field.name)
+        })?;
+    }
     indexing_settings.merge_policy.validate()?;
     indexing_settings.resources.validate()?;
+    validate_index_sort(&indexing_settings.sort_by, doc_mapping, &doc_mapper)?;
 
     if let Some(retention_policy) = retention_policy_opt {
         retention_policy.validate()?;
@@ -506,6 +655,40 @@ pub(super) fn validate_index_config(
             "retention policy requires a timestamp field, but doc mapping does not declare one"
         );
     }
+    if let Some(rollup) = rollup_opt {
+        rollup.validate()?;
+
+        ensure!(
+            doc_mapping.timestamp_field.is_some(),
+            "rollup requires a timestamp field, but doc mapping does not declare one"
+        );
+    }
+    Ok(())
+}
+
+fn validate_index_sort(
+    sort_by: &[IndexSortField],
+    doc_mapping: &DocMapping,
+    doc_mapper: &DocMapper,
+) -> anyhow::Result<()> {
+    if sort_by.is_empty() {
+        return Ok(());
+    }
+    // Sorted merges rebuild the documents of the merged split from their source.
+    ensure!(
+        doc_mapping.store_source,
+        "index sort requires `store_source` to be enabled in the doc mapping"
+    );
+    for (sort_field_idx, sort_field) in sort_by.iter().enumerate() {
+        ensure!(
+            !sort_by[..sort_field_idx]
+                .iter()
+                .any(|previous_sort_field| previous_sort_field.field == sort_field.field),
+            "index sort field `{}` is declared more than once",
+            sort_field.field
+        );
+        doc_mapper.validate_index_sort_field(&sort_field.field)?;
+    }
     Ok(())
 }
 
@@ -516,7 +699,9 @@ mod tests {
     use quickwit_doc_mapper::ModeType;
 
     use super::*;
-    use crate::merge_policy_config::MergePolicyConfig;
+    use crate::merge_policy_config::{
+        MergePolicyConfig, TimeBucket, TimePartitionedMergePolicyConfig,
+    };
     use crate::ConfigFormat;
 
     fn get_index_config_filepath(index_config_filename: &str) -> String {
@@ -593,6 +778,7 @@ mod tests {
             index_config.search_settings,
             SearchSettings {
                 default_search_fields: vec!["severity_text".to_string(), "body".to_string()],
+                ..Default::default()
             }
         );
     }
@@ -635,6 +821,7 @@ mod tests {
                 index_config.search_settings,
                 SearchSettings {
                     default_search_fields: vec!["body".to_string()],
+                    ..Default::default()
                 }
             );
         }
@@ -669,6 +856,7 @@ mod tests {
                 index_config.search_settings,
                 SearchSettings {
                     default_search_fields: vec!["body".to_string()],
+                    ..Default::default()
                 }
             );
         }
@@ -731,6 +919,207 @@ mod tests {
             .contains("failed to parse human-readable duration `x`"));
     }
 
+    #[test]
+    fn test_index_config_with_index_sort() {
+        let config_yaml = r#"
+            version: 0.8
+            index_id: hdfs-logs
+            index_uri: "s3://my-index"
+            doc_mapping:
+              field_mappings:
+                - name: timestamp
+                  type: datetime
+                  fast: true
+                - name: severity
+                  type: u64
+                  fast: true
+            indexing_settings:
+              sort_by:
+                - field: timestamp
+                  order: desc
+                - field: severity
+                  order: asc
+        "#;
+        let index_config = load_index_config_from_user_config(
+            ConfigFormat::Yaml,
+            config_yaml.as_bytes(),
+            &Uri::for_test("s3://my-index"),
+        )
+        .unwrap();
+        assert_eq!(
+            index_config.indexing_settings.sort_by,
+            [
+                IndexSortField {
+                    field: "timestamp".to_string(),
+                    order: SortOrder::Desc,
+                },
+                IndexSortField {
+                    field: "severity".to_string(),
+                    order: SortOrder::Asc,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_index_config_with_invalid_index_sort() {
+        let load_index_config = |field_mappings: &str, sort_by: &str, store_source: bool| {
+            let config_yaml = format!(
+                r#"
+                version: 0.8
+                index_id: hdfs-logs
+                index_uri: "s3://my-index"
+                doc_mapping:
+                  store_source: {store_source}
+                  field_mappings: {field_mappings}
+                indexing_settings:
+                  sort_by: {sort_by}
+                "#
+            );
+            let error = load_index_config_from_user_config(
+                ConfigFormat::Yaml,
+                config_yaml.as_bytes(),
+                &Uri::for_test("s3://my-index"),
+            )
+            .unwrap_err();
+            format!("{error:#}")
+        };
+        let error = load_index_config(
+            r#"[{"name": "severity", "type": "u64", "fast": true}]"#,
+            r#"[{"field": "severity", "order": "asc"}]"#,
+            false,
+        );
+        assert!(error.contains("store_source"), "{error}");
+
+        let error = load_index_config(
+            r#"[{"name": "severity", "type": "u64", "fast": true}]"#,
+            r#"[{"field": "unknown", "order": "asc"}]"#,
+            true,
+        );
+        assert!(error.contains("unknown index sort field"), "{error}");
+
+        let error = load_index_config(
+            r#"[{"name": "severity", "type": "u64", "fast": false}]"#,
+            r#"[{"field": "severity", "order": "asc"}]"#,
+            true,
+        );
+        assert!(error.contains("required to be fast"), "{error}");
+
+        let error = load_index_config(
+            r#"[{"name": "body", "type": "text", "fast": true}]"#,
+            r#"[{"field": "body", "order": "asc"}]"#,
+            true,
+        );
+        assert!(error.contains("only allowed on"), "{error}");
+
+        let error = load_index_config(
+            r#"[{"name": "severity", "type": "u64", "fast": true}]"#,
+            r#"[{"field": "severity", "order": "asc"}, {"field": "severity", "order": "desc"}]"#,
+            true,
+        );
+        assert!(error.contains("more than once"), "{error}");
+    }
+
+    #[test]
+    fn test_index_config_with_time_partitioned_merge_policy() {
+        let config_yaml = r#"
+            version: 0.8
+            index_id: hdfs-logs
+            index_uri: "s3://my-index"
+            doc_mapping: {}
+            indexing_settings:
+              merge_policy:
+                type: time_partitioned
+                time_bucket: hour
+                merge_factor: 5
+                maturation_period: 1h
+        "#;
+        let index_config = load_index_config_from_user_config(
+            ConfigFormat::Yaml,
+            config_yaml.as_bytes(),
+            &Uri::for_test("s3://my-index"),
+        )
+        .unwrap();
+        let expected_merge_policy =
+            MergePolicyConfig::TimePartitioned(TimePartitionedMergePolicyConfig {
+                time_bucket: TimeBucket::Hour,
+                merge_factor: 5,
+                maturation_period: Duration::from_secs(3600),
+                ..Default::default()
+            });
+        assert_eq!(
+            index_config.indexing_settings.merge_policy,
+            expected_merge_policy
+        );
+    }
+
+    #[test]
+    fn test_index_config_with_rollup() {
+        let config_yaml = r#"
+            version: 0.8
+            index_id: hdfs-logs
+            doc_mapping:
+              field_mappings:
+                - name: timestamp
+                  type: datetime
+                  fast: true
+                - name: severity_text
+                  type: text
+                  tokenizer: raw
+                  fast: true
+                - name: latency
+                  type: f64
+                  fast: true
+              timestamp_field: timestamp
+            rollup:
+              target_index_id: hdfs-logs-rollup
+              group_by: [severity_text]
+              metrics:
+                - field: latency
+                  percentiles: [50, 99.9]
+        "#;
+        let index_config = load_index_config_from_user_config(
+            ConfigFormat::Yaml,
+            config_yaml.as_bytes(),
+            &Uri::for_test("s3://my-index"),
+        )
+        .unwrap();
+        let rollup = index_config.rollup_opt.unwrap();
+        assert_eq!(rollup.target_index_id, "hdfs-logs-rollup");
+        assert_eq!(rollup.interval().unwrap(), Duration::from_secs(60));
+        assert_eq!(rollup.delay().unwrap(), Duration::from_secs(600));
+        assert_eq!(rollup.group_by, ["severity_text"]);
+        assert_eq!(rollup.max_terms, 1_000);
+        assert_eq!(
+            rollup.metrics,
+            [RollupMetric {
+                field: "latency".to_string(),
+                percentiles: vec![50.0, 99.9],
+            }]
+        );
+        rollup.duration_until_next_execution().unwrap();
+
+        let invalid_config_yaml = config_yaml.replace("hdfs-logs-rollup", "hdfs-logs");
+        let error = load_index_config_from_user_config(
+            ConfigFormat::Yaml,
+            invalid_config_yaml.as_bytes(),
+            &Uri::for_test("s3://my-index"),
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("rollup target index must be different"));
+
+        let invalid_config_yaml = config_yaml.replace("[50, 99.9]", "[101]");
+        let error = load_index_config_from_user_config(
+            ConfigFormat::Yaml,
+            invalid_config_yaml.as_bytes(),
+            &Uri::for_test("s3://my-index"),
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("rollup percentiles"));
+    }
+
     #[test]
     fn test_retention_policy_serialization() {
         let retention_policy = RetentionPolicy {
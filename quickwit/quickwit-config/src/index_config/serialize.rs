@@ -29,7 +29,7 @@ use tracing::info;
 use super::validate_index_config;
 use crate::{
     validate_identifier, ConfigFormat, DocMapping, IndexConfig, IndexingSettings, RetentionPolicy,
-    SearchSettings,
+    RollupConfig, SearchSettings,
 };
 
 /// Alias for the latest serialization format.
@@ -84,11 +84,20 @@ pub fn load_index_config_update(
         .index_uri
         .parent()
         .expect("index URI should have a parent");
-    let mut new_index_config = load_index_config_from_user_config(
+    let new_index_config = load_index_config_from_user_config(
         config_format,
         index_config_bytes,
         current_index_parent_dir,
     )?;
+    validate_index_config_update(new_index_config, current_index_config)
+}
+
+/// Validates an [`IndexConfig`] update against the current index config, see
+/// [`load_index_config_update`].
+pub fn validate_index_config_update(
+    mut new_index_config: IndexConfig,
+    current_index_config: &IndexConfig,
+) -> anyhow::Result<IndexConfig> {
     ensure!(
         current_index_config.index_id == new_index_config.index_id,
         "`index_id` in config file {} does not match updated `index_id` {}",
@@ -101,6 +110,10 @@ pub fn load_index_config_update(
         current_index_config.index_uri,
         new_index_config.index_uri
     );
+    ensure!(
+        current_index_config.rollup_opt == new_index_config.rollup_opt,
+        "`rollup` cannot be updated"
+    );
 
     // verify the new mapping is coherent
     let doc_mapper_builder = DocMapperBuilder {
@@ -109,6 +122,7 @@ pub fn load_index_config_update(
             .search_settings
             .default_search_fields
             .clone(),
+        runtime_fields: new_index_config.search_settings.runtime_fields.clone(),
         legacy_type_tag: None,
     };
     doc_mapper_builder
@@ -196,13 +210,21 @@ impl IndexConfigForSerialization {
             indexing_settings: self.indexing_settings,
             search_settings: self.search_settings,
             retention_policy_opt: self.retention_policy_opt,
+            rollup_opt: self.rollup_opt,
         };
         validate_index_config(
             &index_config.doc_mapping,
             &index_config.indexing_settings,
             &index_config.search_settings,
             &index_config.retention_policy_opt,
+            &index_config.rollup_opt,
         )?;
+        if let Some(rollup) = &index_config.rollup_opt {
+            ensure!(
+                rollup.target_index_id != index_config.index_id,
+                "rollup target index must be different from the index being rolled up"
+            );
+        }
         Ok(index_config)
     }
 }
@@ -244,6 +266,10 @@ pub struct IndexConfigV0_8 {
     #[serde(rename = "retention")]
     #[serde(default)]
     pub retention_policy_opt: Option<RetentionPolicy>,
+    #[serde(rename = "rollup")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rollup_opt: Option<RollupConfig>,
 }
 
 impl From<IndexConfig> for IndexConfigV0_8 {
@@ -255,6 +281,7 @@ impl From<IndexConfig> for IndexConfigV0_8 {
             indexing_settings: index_config.indexing_settings,
             search_settings: index_config.search_settings,
             retention_policy_opt: index_config.retention_policy_opt,
+            rollup_opt: index_config.rollup_opt,
         }
     }
 }
@@ -79,6 +79,7 @@ impl IndexTemplate {
             indexing_settings: self.indexing_settings.clone(),
             search_settings: self.search_settings.clone(),
             retention_policy_opt: self.retention_policy_opt.clone(),
+            rollup_opt: None,
         };
         Ok(index_config)
     }
@@ -98,6 +99,7 @@ impl IndexTemplate {
             &self.indexing_settings,
             &self.search_settings,
             &self.retention_policy_opt,
+            &None,
         )?;
         Ok(())
     }
@@ -232,6 +234,7 @@ mod tests {
         };
         index_template.search_settings = SearchSettings {
             default_search_fields: vec!["message".to_string()],
+            ..Default::default()
         };
         index_template.retention_policy_opt = Some(RetentionPolicy {
             retention_period: "42 days".to_string(),
@@ -48,20 +48,23 @@ pub use cluster_config::ClusterConfig;
 // See #2048
 use index_config::serialize::{IndexConfigV0_8, VersionedIndexConfig};
 pub use index_config::{
-    build_doc_mapper, load_index_config_from_user_config, load_index_config_update, IndexConfig,
-    IndexingResources, IndexingSettings, RetentionPolicy, SearchSettings,
+    build_doc_mapper, load_index_config_from_user_config, load_index_config_update,
+    validate_index_config_update, IndexConfig, IndexSortField, IndexingResources, IndexingSettings,
+    RetentionPolicy, RollupConfig, RollupMetric, SearchSettings,
 };
-pub use quickwit_doc_mapper::DocMapping;
+pub use quickwit_doc_mapper::{DocMapping, RuntimeField, RuntimeFieldType};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use serde_json::Value as JsonValue;
+#[cfg(feature = "vrl")]
+pub use source_config::compile_vrl_script;
 use source_config::FileSourceParamsForSerde;
 pub use source_config::{
     load_source_config_from_user_config, FileSourceMessageType, FileSourceNotification,
     FileSourceParams, FileSourceSqs, KafkaSourceParams, KinesisSourceParams, PubSubSourceParams,
     PulsarSourceAuth, PulsarSourceParams, RegionOrEndpoint, SourceConfig, SourceInputFormat,
     SourceParams, TransformConfig, VecSourceParams, VoidSourceParams, CLI_SOURCE_ID,
-    INGEST_API_SOURCE_ID, INGEST_V2_SOURCE_ID,
+    INGEST_API_SOURCE_ID, INGEST_V2_SOURCE_ID, REINDEX_TASK_SOURCE_ID, ROLLUP_SOURCE_ID,
 };
 use tracing::warn;
 
@@ -69,13 +72,15 @@ use crate::index_template::IndexTemplateV0_8;
 pub use crate::index_template::{IndexTemplate, IndexTemplateId, VersionedIndexTemplate};
 use crate::merge_policy_config::{
     ConstWriteAmplificationMergePolicyConfig, MergePolicyConfig, StableLogMergePolicyConfig,
+    TimeBucket, TimePartitionedMergePolicyConfig,
 };
 pub use crate::metastore_config::{
     MetastoreBackend, MetastoreConfig, MetastoreConfigs, PostgresMetastoreConfig,
 };
 pub use crate::node_config::{
-    IndexerConfig, IngestApiConfig, JaegerConfig, NodeConfig, SearcherConfig, SplitCacheLimits,
-    StorageTimeoutPolicy, DEFAULT_QW_CONFIG_PATH,
+    IndexerConfig, IngestApiConfig, JaegerConfig, NodeConfig, QuotaConfig, RootSearchCacheConfig,
+    SearcherConfig, SlowQueryLogConfig, SplitCacheLimits, StorageTimeoutPolicy, TlsConfig,
+    DEFAULT_QW_CONFIG_PATH,
 };
 use crate::source_config::serialize::{SourceConfigV0_7, SourceConfigV0_8, VersionedSourceConfig};
 pub use crate::storage_config::{
@@ -101,8 +106,13 @@ pub fn disable_ingest_v1() -> bool {
 #[openapi(components(schemas(
     IndexingResources,
     IndexingSettings,
+    IndexSortField,
     SearchSettings,
+    RuntimeField,
+    RuntimeFieldType,
     RetentionPolicy,
+    RollupConfig,
+    RollupMetric,
     MergePolicyConfig,
     DocMapping,
     VersionedSourceConfig,
@@ -126,6 +136,8 @@ pub fn disable_ingest_v1() -> bool {
     RegionOrEndpoint,
     ConstWriteAmplificationMergePolicyConfig,
     StableLogMergePolicyConfig,
+    TimePartitionedMergePolicyConfig,
+    TimeBucket,
     TransformConfig,
     VecSourceParams,
     VoidSourceParams,
@@ -93,6 +93,66 @@ pub struct StableLogMergePolicyConfig {
     pub maturation_period: Duration,
 }
 
+/// Calendar bucket used by the time partitioned merge policy to group splits.
+#[derive(
+    Clone, Copy, Debug, Default, Serialize, Deserialize, Eq, PartialEq, Hash, utoipa::ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeBucket {
+    Hour,
+    #[default]
+    Day,
+}
+
+impl TimeBucket {
+    /// Returns the duration of the bucket in seconds.
+    pub fn as_secs(&self) -> i64 {
+        match self {
+            TimeBucket::Hour => 3_600,
+            TimeBucket::Day => 86_400,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TimePartitionedMergePolicyConfig {
+    /// Calendar bucket (UTC) splits are grouped by. Splits belonging to different
+    /// buckets are never merged together.
+    #[serde(default)]
+    pub time_bucket: TimeBucket,
+    /// Number of splits to merge together in a single merge operation.
+    #[serde(default = "default_merge_factor")]
+    pub merge_factor: usize,
+    /// Maximum number of splits that can be merged together in a single merge operation.
+    #[serde(default = "default_max_merge_factor")]
+    pub max_merge_factor: usize,
+    /// Maximum number of merges that a given split should undergo.
+    #[serde(default = "default_max_merge_ops")]
+    pub max_merge_ops: usize,
+    /// Duration relative to `split.created_timestamp` after which a split
+    /// becomes mature.
+    /// If `now() >= split.created_timestamp + maturation_period` then
+    /// the split is mature.
+    #[schema(value_type = String)]
+    #[serde(default = "default_maturation_period")]
+    #[serde(deserialize_with = "parse_human_duration")]
+    #[serde(serialize_with = "serialize_duration")]
+    pub maturation_period: Duration,
+}
+
+impl Default for TimePartitionedMergePolicyConfig {
+    fn default() -> Self {
+        TimePartitionedMergePolicyConfig {
+            time_bucket: TimeBucket::default(),
+            merge_factor: default_merge_factor(),
+            max_merge_factor: default_max_merge_factor(),
+            max_merge_ops: default_max_merge_ops(),
+            maturation_period: default_maturation_period(),
+        }
+    }
+}
+
 fn default_merge_factor() -> usize {
     10
 }
@@ -152,6 +212,8 @@ pub enum MergePolicyConfig {
     #[serde(rename = "stable_log")]
     #[serde(alias = "default")]
     StableLog(StableLogMergePolicyConfig),
+    #[serde(rename = "time_partitioned")]
+    TimePartitioned(TimePartitionedMergePolicyConfig),
 }
 
 impl Default for MergePolicyConfig {
@@ -174,6 +236,9 @@ impl MergePolicyConfig {
                 (config.merge_factor, config.max_merge_factor)
             }
             MergePolicyConfig::StableLog(config) => (config.merge_factor, config.max_merge_factor),
+            MergePolicyConfig::TimePartitioned(config) => {
+                (config.merge_factor, config.max_merge_factor)
+            }
         };
         if max_merge_factor < merge_factor {
             anyhow::bail!(
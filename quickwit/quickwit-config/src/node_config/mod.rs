@@ -31,6 +31,7 @@ use bytesize::ByteSize;
 use http::HeaderMap;
 use quickwit_common::net::HostAddr;
 use quickwit_common::shared_consts::DEFAULT_SHARD_THROUGHPUT_LIMIT;
+use quickwit_common::tls::TlsPaths;
 use quickwit_common::uri::Uri;
 use quickwit_proto::indexing::CpuCapacity;
 use quickwit_proto::types::NodeId;
@@ -51,6 +52,50 @@ pub struct RestConfig {
     pub cors_allow_origins: Vec<String>,
     #[serde(with = "http_serde::header_map")]
     pub extra_headers: HeaderMap,
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+}
+
+/// TLS settings of the REST or gRPC server. When set on the gRPC server, the same certificate is
+/// also presented by the node to the other nodes of the cluster (mutual TLS).
+///
+/// The certificate, the key, and the CA files are watched and reloaded when they change on disk.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    /// Path to the PEM-encoded certificate chain of the node.
+    pub cert_path: PathBuf,
+    /// Path to the PEM-encoded private key of the node.
+    pub key_path: PathBuf,
+    /// Path to the PEM-encoded CA certificates used to verify the certificates of the peers.
+    #[serde(default)]
+    pub ca_path: Option<PathBuf>,
+    /// Rejects the connections of the clients that do not present a valid certificate.
+    #[serde(default)]
+    pub require_client_cert: bool,
+    /// Rejects the requests sent to the ingest endpoints by clients that do not present a valid
+    /// certificate. Other endpoints remain accessible without a certificate.
+    #[serde(default)]
+    pub require_client_cert_for_ingest: bool,
+}
+
+impl TlsConfig {
+    pub fn validate(&self, config_key: &str) -> anyhow::Result<()> {
+        ensure!(
+            self.ca_path.is_some()
+                || !(self.require_client_cert || self.require_client_cert_for_ingest),
+            "`{config_key}.ca_path` must be set to require client certificates"
+        );
+        Ok(())
+    }
+
+    pub fn tls_paths(&self) -> TlsPaths {
+        TlsPaths {
+            cert_path: self.cert_path.clone(),
+            key_path: self.key_path.clone(),
+            ca_path_opt: self.ca_path.clone(),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
@@ -58,6 +103,8 @@ pub struct RestConfig {
 pub struct GrpcConfig {
     #[serde(default = "GrpcConfig::default_max_message_size")]
     pub max_message_size: ByteSize,
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
 }
 
 impl GrpcConfig {
@@ -71,6 +118,15 @@ impl GrpcConfig {
             "max gRPC message size (`grpc.max_message_size`) must be at least 1MB, got `{}`",
             self.max_message_size
         );
+        if let Some(tls_config) = &self.tls {
+            tls_config.validate("grpc.tls")?;
+            // The CA is also used by the gRPC clients to verify the certificates of the other
+            // nodes.
+            ensure!(
+                tls_config.ca_path.is_some(),
+                "`grpc.tls.ca_path` must be set to verify the certificates of the other nodes"
+            );
+        }
         Ok(())
     }
 }
@@ -79,6 +135,7 @@ impl Default for GrpcConfig {
     fn default() -> Self {
         Self {
             max_message_size: Self::default_max_message_size(),
+            tls: None,
         }
     }
 }
@@ -206,6 +263,93 @@ impl SplitCacheLimits {
     }
 }
 
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RootSearchCacheConfig {
+    /// Memory allotted to the cached search responses on each searcher.
+    #[serde(default = "RootSearchCacheConfig::default_capacity")]
+    pub capacity: ByteSize,
+    /// How long a search response stays in the cache.
+    #[serde(default = "RootSearchCacheConfig::default_ttl_secs")]
+    pub ttl_secs: NonZeroU64,
+}
+
+impl RootSearchCacheConfig {
+    pub fn ttl(&self) -> Duration {
+        Duration::from_secs(self.ttl_secs.get())
+    }
+
+    fn default_capacity() -> ByteSize {
+        ByteSize::mb(64)
+    }
+
+    fn default_ttl_secs() -> NonZeroU64 {
+        NonZeroU64::new(600).unwrap()
+    }
+}
+
+impl Default for RootSearchCacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: Self::default_capacity(),
+            ttl_secs: Self::default_ttl_secs(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SlowQueryLogConfig {
+    /// Root searches taking longer than this threshold are recorded.
+    #[serde(default = "SlowQueryLogConfig::default_threshold_millis")]
+    pub threshold_millis: NonZeroU64,
+    /// Percentage of the slow searches recorded.
+    #[serde(default = "SlowQueryLogConfig::default_sample_percent")]
+    pub sample_percent: u8,
+    /// ID of the index the records are ingested into. The index is created on startup if it
+    /// does not exist.
+    #[serde(default = "SlowQueryLogConfig::default_index_id")]
+    pub index_id: String,
+}
+
+impl SlowQueryLogConfig {
+    pub fn threshold(&self) -> Duration {
+        Duration::from_millis(self.threshold_millis.get())
+    }
+
+    fn default_threshold_millis() -> NonZeroU64 {
+        NonZeroU64::new(5_000).unwrap()
+    }
+
+    fn default_sample_percent() -> u8 {
+        100
+    }
+
+    fn default_index_id() -> String {
+        "slow-query-log-v0_1".to_string()
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        ensure!(
+            (1..=100).contains(&self.sample_percent),
+            "slow_query_log.sample_percent must be between 1 and 100, got `{}`",
+            self.sample_percent
+        );
+        crate::validate_identifier("slow query log index", &self.index_id)?;
+        Ok(())
+    }
+}
+
+impl Default for SlowQueryLogConfig {
+    fn default() -> Self {
+        Self {
+            threshold_millis: Self::default_threshold_millis(),
+            sample_percent: Self::default_sample_percent(),
+            index_id: Self::default_index_id(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields, default)]
 pub struct SearcherConfig {
@@ -221,6 +365,13 @@ pub struct SearcherConfig {
     // TODO document and fix if necessary.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub split_cache: Option<SplitCacheLimits>,
+    /// Caches the responses of aggregation-only root search requests targeting immutable splits.
+    /// Disabled if None.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root_search_cache: Option<RootSearchCacheConfig>,
+    /// Records the slow root searches into a Quickwit index. Disabled if None.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slow_query_log: Option<SlowQueryLogConfig>,
     #[serde(default = "SearcherConfig::default_request_timeout_secs")]
     request_timeout_secs: NonZeroU64,
     #[serde(default)]
@@ -272,6 +423,8 @@ impl Default for SearcherConfig {
             aggregation_memory_limit: ByteSize::mb(500),
             aggregation_bucket_limit: 65000,
             split_cache: None,
+            root_search_cache: None,
+            slow_query_log: None,
             request_timeout_secs: Self::default_request_timeout_secs(),
             storage_timeout_policy: None,
         }
@@ -309,6 +462,9 @@ impl SearcherConfig {
                 );
             }
         }
+        if let Some(slow_query_log_config) = &self.slow_query_log {
+            slow_query_log_config.validate()?;
+        }
         Ok(())
     }
 }
@@ -386,6 +542,72 @@ impl IngestApiConfig {
     }
 }
 
+/// Quotas enforced for an index or for a tenant, i.e. the set of indexes whose IDs share a common
+/// prefix.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QuotaConfig {
+    /// Index ID (`my-index`) or index ID prefix followed by a trailing wildcard (`acme-*`). When
+    /// several quotas match an index, the most specific one applies.
+    pub index_id: String,
+    /// Maximum ingest throughput, in bytes per second, accepted by each ingest router for the
+    /// indexes covered by this quota.
+    #[serde(default)]
+    pub max_ingest_throughput: Option<ByteSize>,
+    /// Maximum size of the published splits of the indexes covered by this quota. Once reached,
+    /// ingest requests are rejected.
+    #[serde(default)]
+    pub max_storage: Option<ByteSize>,
+    /// Maximum number of concurrent search requests handled by each root searcher for the
+    /// indexes covered by this quota.
+    #[serde(default)]
+    pub max_concurrent_searches: Option<NonZeroUsize>,
+    /// Maximum number of bytes, measured as the uncompressed size of the documents of the
+    /// targeted splits, that a single search request is allowed to scan.
+    #[serde(default)]
+    pub max_scanned_bytes_per_query: Option<ByteSize>,
+}
+
+impl QuotaConfig {
+    fn validate(&self) -> anyhow::Result<()> {
+        crate::validate_index_id_pattern(&self.index_id, false)?;
+
+        if let Some(wildcard_pos) = self.index_id.find('*') {
+            ensure!(
+                wildcard_pos == self.index_id.len() - 1,
+                "quota index ID `{}` is invalid: the wildcard `*` is only allowed as the last \
+                 character",
+                self.index_id
+            );
+        }
+        ensure!(
+            self.max_ingest_throughput.is_some()
+                || self.max_storage.is_some()
+                || self.max_concurrent_searches.is_some()
+                || self.max_scanned_bytes_per_query.is_some(),
+            "quota for `{}` does not define any limit",
+            self.index_id
+        );
+        Ok(())
+    }
+}
+
+pub(crate) fn validate_quota_configs(quota_configs: &[QuotaConfig]) -> anyhow::Result<()> {
+    let mut index_ids: HashSet<&str> = HashSet::with_capacity(quota_configs.len());
+
+    for quota_config in quota_configs {
+        quota_config.validate()?;
+
+        if !index_ids.insert(&quota_config.index_id) {
+            bail!(
+                "quota for `{}` is defined more than once",
+                quota_config.index_id
+            );
+        }
+    }
+    Ok(())
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct JaegerConfig {
@@ -465,6 +687,10 @@ pub struct NodeConfig {
     pub gossip_advertise_addr: SocketAddr,
     pub grpc_advertise_addr: SocketAddr,
     pub gossip_interval: Duration,
+    /// Availability zone (failure domain) of the node, advertised to the other nodes of the
+    /// cluster. It is used to spread ingest replicas across zones and to keep indexing and search
+    /// traffic within a zone when possible.
+    pub availability_zone_opt: Option<String>,
     pub peer_seeds: Vec<String>,
     pub data_dir_path: PathBuf,
     pub metastore_uri: Uri,
@@ -477,6 +703,7 @@ pub struct NodeConfig {
     pub searcher_config: SearcherConfig,
     pub ingest_api_config: IngestApiConfig,
     pub jaeger_config: JaegerConfig,
+    pub quota_configs: Vec<QuotaConfig>,
 }
 
 impl NodeConfig {
@@ -679,12 +906,73 @@ mod tests {
     fn test_grpc_config_validate() {
         let grpc_config = GrpcConfig {
             max_message_size: ByteSize::mb(1),
+            tls: None,
         };
         assert!(grpc_config.validate().is_ok());
 
         let grpc_config = GrpcConfig {
             max_message_size: ByteSize::kb(1),
+            tls: None,
         };
         assert!(grpc_config.validate().is_err());
     }
+
+    #[test]
+    fn test_grpc_config_tls_serde() {
+        let grpc_config: GrpcConfig = serde_yaml::from_str(
+            r#"
+                tls:
+                    cert_path: /etc/quickwit/node.crt
+                    key_path: /etc/quickwit/node.key
+                    ca_path: /etc/quickwit/ca.crt
+                    require_client_cert: true
+            "#,
+        )
+        .unwrap();
+        let tls_config = grpc_config.tls.as_ref().unwrap();
+        assert_eq!(
+            tls_config.cert_path,
+            PathBuf::from("/etc/quickwit/node.crt")
+        );
+        assert_eq!(tls_config.key_path, PathBuf::from("/etc/quickwit/node.key"));
+        assert_eq!(
+            tls_config.ca_path.as_deref(),
+            Some(std::path::Path::new("/etc/quickwit/ca.crt"))
+        );
+        assert!(tls_config.require_client_cert);
+        assert!(!tls_config.require_client_cert_for_ingest);
+        grpc_config.validate().unwrap();
+    }
+
+    #[test]
+    fn test_tls_config_validate() {
+        let mut tls_config = TlsConfig {
+            cert_path: PathBuf::from("node.crt"),
+            key_path: PathBuf::from("node.key"),
+            ca_path: None,
+            require_client_cert: false,
+            require_client_cert_for_ingest: false,
+        };
+        tls_config.validate("rest.tls").unwrap();
+
+        tls_config.require_client_cert_for_ingest = true;
+        let error = tls_config.validate("rest.tls").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "`rest.tls.ca_path` must be set to require client certificates"
+        );
+        tls_config.ca_path = Some(PathBuf::from("ca.crt"));
+        tls_config.validate("rest.tls").unwrap();
+
+        // The gRPC clients need the CA to verify the certificates of the other nodes.
+        let grpc_config = GrpcConfig {
+            max_message_size: ByteSize::mib(20),
+            tls: Some(TlsConfig {
+                ca_path: None,
+                require_client_cert_for_ingest: false,
+                ..tls_config
+            }),
+        };
+        grpc_config.validate().unwrap_err();
+    }
 }
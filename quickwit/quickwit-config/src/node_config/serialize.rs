@@ -31,7 +31,7 @@ use quickwit_proto::types::NodeId;
 use serde::{Deserialize, Serialize};
 use tracing::{info, warn};
 
-use super::{GrpcConfig, RestConfig};
+use super::{validate_quota_configs, GrpcConfig, QuotaConfig, RestConfig, TlsConfig};
 use crate::config_value::ConfigValue;
 use crate::qw_env_vars::*;
 use crate::service::QuickwitService;
@@ -177,6 +177,7 @@ struct NodeConfigBuilder {
     #[serde(default = "default_listen_address")]
     listen_address: ConfigValue<String, QW_LISTEN_ADDRESS>,
     advertise_address: ConfigValue<String, QW_ADVERTISE_ADDRESS>,
+    availability_zone: ConfigValue<String, QW_AVAILABILITY_ZONE>,
     // Deprecated, use `rest.listen_port` instead.
     rest_listen_port: Option<u16>,
     gossip_listen_port: ConfigValue<u16, QW_GOSSIP_LISTEN_PORT>,
@@ -213,6 +214,9 @@ struct NodeConfigBuilder {
     #[serde(rename = "jaeger")]
     #[serde(default)]
     jaeger_config: JaegerConfig,
+    #[serde(rename = "quotas")]
+    #[serde(default)]
+    quota_configs: Vec<QuotaConfig>,
 }
 
 impl NodeConfigBuilder {
@@ -273,6 +277,8 @@ impl NodeConfigBuilder {
         let gossip_advertise_addr = SocketAddr::new(advertise_ip, gossip_listen_port);
         let grpc_advertise_addr = SocketAddr::new(advertise_ip, grpc_listen_port);
 
+        let availability_zone_opt = self.availability_zone.resolve_optional(env_vars)?;
+
         let data_dir_uri = self.data_dir_uri.resolve(env_vars)?;
         let data_dir_path = data_dir_uri
             .filepath()
@@ -298,6 +304,7 @@ impl NodeConfigBuilder {
         self.storage_configs.apply_flavors();
         self.ingest_api_config.validate()?;
         self.searcher_config.validate()?;
+        validate_quota_configs(&self.quota_configs)?;
 
         let gossip_interval = self
             .gossip_interval_ms
@@ -314,6 +321,7 @@ impl NodeConfigBuilder {
             gossip_advertise_addr,
             grpc_advertise_addr,
             gossip_interval,
+            availability_zone_opt,
             peer_seeds: self.peer_seeds.resolve(env_vars)?.0,
             data_dir_path,
             metastore_uri,
@@ -326,6 +334,7 @@ impl NodeConfigBuilder {
             searcher_config: self.searcher_config,
             ingest_api_config: self.ingest_api_config,
             jaeger_config: self.jaeger_config,
+            quota_configs: self.quota_configs,
         };
 
         validate(&node_config)?;
@@ -359,6 +368,7 @@ impl Default for NodeConfigBuilder {
             grpc_listen_port: ConfigValue::none(),
             gossip_interval_ms: ConfigValue::none(),
             advertise_address: ConfigValue::none(),
+            availability_zone: ConfigValue::none(),
             peer_seeds: ConfigValue::with_default(List::default()),
             data_dir_uri: default_data_dir_uri(),
             metastore_uri: ConfigValue::none(),
@@ -371,6 +381,7 @@ impl Default for NodeConfigBuilder {
             searcher_config: SearcherConfig::default(),
             ingest_api_config: IngestApiConfig::default(),
             jaeger_config: JaegerConfig::default(),
+            quota_configs: Vec::new(),
         }
     }
 }
@@ -387,6 +398,8 @@ struct RestConfigBuilder {
     #[serde(with = "http_serde::header_map")]
     #[serde(default)]
     pub extra_headers: HeaderMap,
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
 }
 
 impl RestConfigBuilder {
@@ -401,10 +414,14 @@ impl RestConfigBuilder {
             listen_port_from_config_or_default,
         )
         .resolve(env_vars)?;
+        if let Some(tls_config) = &self.tls {
+            tls_config.validate("rest.tls")?;
+        }
         let rest_config = RestConfig {
             listen_addr: SocketAddr::new(listen_ip, listen_port),
             cors_allow_origins: self.cors_allow_origins,
             extra_headers: self.extra_headers,
+            tls: self.tls,
         };
         Ok(rest_config)
     }
@@ -442,6 +459,7 @@ pub fn node_config_for_tests_from_ports(
         listen_addr: rest_listen_addr,
         cors_allow_origins: Vec::new(),
         extra_headers: HeaderMap::new(),
+        tls: None,
     };
     NodeConfig {
         cluster_id: default_cluster_id().unwrap(),
@@ -452,6 +470,7 @@ pub fn node_config_for_tests_from_ports(
         gossip_listen_addr,
         grpc_listen_addr,
         gossip_interval: Duration::from_millis(25u64),
+        availability_zone_opt: None,
         peer_seeds: Vec::new(),
         data_dir_path,
         metastore_uri,
@@ -464,6 +483,7 @@ pub fn node_config_for_tests_from_ports(
         searcher_config: SearcherConfig::default(),
         ingest_api_config: IngestApiConfig::default(),
         jaeger_config: JaegerConfig::default(),
+        quota_configs: Vec::new(),
     }
 }
 
@@ -611,6 +631,8 @@ mod tests {
                 max_num_concurrent_split_searches: 150,
                 max_num_concurrent_split_streams: 120,
                 split_cache: None,
+                root_search_cache: None,
+                slow_query_log: None,
                 request_timeout_secs: NonZeroU64::new(30).unwrap(),
                 storage_timeout_policy: Some(crate::StorageTimeoutPolicy {
                     min_throughtput_bytes_per_secs: 100_000,
@@ -728,6 +750,7 @@ mod tests {
         );
         env_vars.insert("QW_LISTEN_ADDRESS".to_string(), "172.0.0.12".to_string());
         env_vars.insert("QW_ADVERTISE_ADDRESS".to_string(), "172.0.0.13".to_string());
+        env_vars.insert("QW_AVAILABILITY_ZONE".to_string(), "us-east-1a".to_string());
         env_vars.insert("QW_REST_LISTEN_PORT".to_string(), "1234".to_string());
         env_vars.insert("QW_GOSSIP_LISTEN_PORT".to_string(), "5678".to_string());
         env_vars.insert("QW_GRPC_LISTEN_PORT".to_string(), "9012".to_string());
@@ -779,6 +802,7 @@ mod tests {
             config.grpc_advertise_addr,
             SocketAddr::new(IpAddr::V4(Ipv4Addr::new(172, 0, 0, 13)), 9012)
         );
+        assert_eq!(config.availability_zone_opt.as_deref(), Some("us-east-1a"));
         assert_eq!(
             config.peer_seeds,
             vec![
@@ -1106,6 +1130,157 @@ mod tests {
             .contains("max_trace_duration_secs: invalid value: integer `0`"))
     }
 
+    #[tokio::test]
+    async fn test_node_config_quotas() {
+        {
+            let config_yaml = r#"
+                version: 0.8
+                quotas:
+                  - index_id: acme-*
+                    max_ingest_throughput: 10MB
+                    max_storage: 1TB
+                  - index_id: acme-logs
+                    max_concurrent_searches: 4
+                    max_scanned_bytes_per_query: 50GB
+            "#;
+            let config = load_node_config_with_env(
+                ConfigFormat::Yaml,
+                config_yaml.as_bytes(),
+                &HashMap::default(),
+            )
+            .await
+            .unwrap();
+            assert_eq!(config.quota_configs.len(), 2);
+
+            let tenant_quota_config = &config.quota_configs[0];
+            assert_eq!(tenant_quota_config.index_id, "acme-*");
+            assert_eq!(
+                tenant_quota_config.max_ingest_throughput,
+                Some(ByteSize::mb(10))
+            );
+            assert_eq!(tenant_quota_config.max_storage, Some(ByteSize::tb(1)));
+            assert!(tenant_quota_config.max_concurrent_searches.is_none());
+
+            let index_quota_config = &config.quota_configs[1];
+            assert_eq!(index_quota_config.index_id, "acme-logs");
+            assert_eq!(
+                index_quota_config.max_concurrent_searches,
+                Some(NonZeroUsize::new(4).unwrap())
+            );
+            assert_eq!(
+                index_quota_config.max_scanned_bytes_per_query,
+                Some(ByteSize::gb(50))
+            );
+        }
+        {
+            let config_yaml = r#"
+                version: 0.8
+                quotas:
+                  - index_id: acme-*-logs
+                    max_storage: 1TB
+            "#;
+            let error = load_node_config_with_env(
+                ConfigFormat::Yaml,
+                config_yaml.as_bytes(),
+                &HashMap::default(),
+            )
+            .await
+            .unwrap_err();
+            assert!(error
+                .to_string()
+                .contains("the wildcard `*` is only allowed as the last character"));
+        }
+        {
+            let config_yaml = r#"
+                version: 0.8
+                quotas:
+                  - index_id: acme-*
+            "#;
+            let error = load_node_config_with_env(
+                ConfigFormat::Yaml,
+                config_yaml.as_bytes(),
+                &HashMap::default(),
+            )
+            .await
+            .unwrap_err();
+            assert!(error.to_string().contains("does not define any limit"));
+        }
+        {
+            let config_yaml = r#"
+                version: 0.8
+                quotas:
+                  - index_id: acme-*
+                    max_storage: 1TB
+                  - index_id: acme-*
+                    max_concurrent_searches: 4
+            "#;
+            let error = load_node_config_with_env(
+                ConfigFormat::Yaml,
+                config_yaml.as_bytes(),
+                &HashMap::default(),
+            )
+            .await
+            .unwrap_err();
+            assert!(error.to_string().contains("is defined more than once"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_node_config_root_search_cache() {
+        let config_yaml = r#"
+            version: 0.8
+            searcher:
+              root_search_cache:
+                capacity: 1G
+        "#;
+        let config = load_node_config_with_env(
+            ConfigFormat::Yaml,
+            config_yaml.as_bytes(),
+            &HashMap::default(),
+        )
+        .await
+        .unwrap();
+        let root_search_cache_config = config.searcher_config.root_search_cache.unwrap();
+        assert_eq!(root_search_cache_config.capacity, ByteSize::gb(1));
+        assert_eq!(root_search_cache_config.ttl(), Duration::from_secs(600));
+    }
+
+    #[tokio::test]
+    async fn test_node_config_slow_query_log() {
+        let config_yaml = r#"
+            version: 0.8
+            searcher:
+              slow_query_log:
+                threshold_millis: 2000
+        "#;
+        let config = load_node_config_with_env(
+            ConfigFormat::Yaml,
+            config_yaml.as_bytes(),
+            &HashMap::default(),
+        )
+        .await
+        .unwrap();
+        let slow_query_log_config = config.searcher_config.slow_query_log.unwrap();
+        assert_eq!(slow_query_log_config.threshold(), Duration::from_secs(2));
+        assert_eq!(slow_query_log_config.sample_percent, 100);
+        assert_eq!(slow_query_log_config.index_id, "slow-query-log-v0_1");
+
+        let invalid_config_yaml = r#"
+            version: 0.8
+            searcher:
+              slow_query_log:
+                sample_percent: 0
+        "#;
+        let error = load_node_config_with_env(
+            ConfigFormat::Yaml,
+            invalid_config_yaml.as_bytes(),
+            &HashMap::default(),
+        )
+        .await
+        .unwrap_err();
+        assert!(error.to_string().contains("sample_percent"));
+    }
+
     #[tokio::test]
     async fn test_rest_config_accepts_wildcard() {
         let rest_config_yaml = r#"
@@ -1161,6 +1336,45 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_rest_config_tls() {
+        let rest_config_yaml = r#"
+            version: 0.8
+            rest:
+              tls:
+                cert_path: /etc/quickwit/node.crt
+                key_path: /etc/quickwit/node.key
+                ca_path: /etc/quickwit/ca.crt
+                require_client_cert_for_ingest: true
+        "#;
+        let config = load_node_config_with_env(
+            ConfigFormat::Yaml,
+            rest_config_yaml.as_bytes(),
+            &Default::default(),
+        )
+        .await
+        .expect("Deserialize rest config");
+        let tls_config = config.rest_config.tls.unwrap();
+        assert!(!tls_config.require_client_cert);
+        assert!(tls_config.require_client_cert_for_ingest);
+
+        let rest_config_yaml = r#"
+            version: 0.8
+            rest:
+              tls:
+                cert_path: /etc/quickwit/node.crt
+                key_path: /etc/quickwit/node.key
+                require_client_cert_for_ingest: true
+        "#;
+        load_node_config_with_env(
+            ConfigFormat::Yaml,
+            rest_config_yaml.as_bytes(),
+            &Default::default(),
+        )
+        .await
+        .unwrap_err();
+    }
+
     #[tokio::test]
     async fn test_rest_config_accepts_multi_origin() {
         let rest_config_yaml = r#"
@@ -48,8 +48,19 @@ pub const INGEST_API_SOURCE_ID: &str = "_ingest-api-source";
 /// (this is for ingest v2)
 pub const INGEST_V2_SOURCE_ID: &str = "_ingest-source";
 
-pub const RESERVED_SOURCE_IDS: &[&str] =
-    &[CLI_SOURCE_ID, INGEST_API_SOURCE_ID, INGEST_V2_SOURCE_ID];
+/// Reserved source ID used for recording the progress of the index rollup job.
+pub const ROLLUP_SOURCE_ID: &str = "_rollup-source";
+
+/// Reserved source ID used for recording an ongoing reindex task on an index.
+pub const REINDEX_TASK_SOURCE_ID: &str = "_reindex-task-source";
+
+pub const RESERVED_SOURCE_IDS: &[&str] = &[
+    CLI_SOURCE_ID,
+    INGEST_API_SOURCE_ID,
+    INGEST_V2_SOURCE_ID,
+    ROLLUP_SOURCE_ID,
+    REINDEX_TASK_SOURCE_ID,
+];
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(into = "VersionedSourceConfig")]
@@ -131,6 +142,33 @@ impl SourceConfig {
         }
     }
 
+    /// Creates the rollup source config. The rollup source is disabled and never indexes
+    /// anything: its checkpoint records the progress of the index rollup job.
+    pub fn rollup() -> Self {
+        Self {
+            source_id: ROLLUP_SOURCE_ID.to_string(),
+            num_pipelines: NonZeroUsize::MIN,
+            enabled: false,
+            source_params: SourceParams::void(),
+            transform_config: None,
+            input_format: SourceInputFormat::Json,
+        }
+    }
+
+    /// Creates the reindex task source config. Like the rollup source, it is disabled and never
+    /// indexes anything: it records that the splits of the index must be rebuilt with the
+    /// current doc mapping, and holds the transform to apply to their documents.
+    pub fn reindex_task(transform_config_opt: Option<TransformConfig>) -> Self {
+        Self {
+            source_id: REINDEX_TASK_SOURCE_ID.to_string(),
+            num_pipelines: NonZeroUsize::MIN,
+            enabled: false,
+            source_params: SourceParams::void(),
+            transform_config: transform_config_opt,
+            input_format: SourceInputFormat::Json,
+        }
+    }
+
     /// Creates the default ingest-api source config.
     pub fn ingest_api_default() -> Self {
         Self {
@@ -576,26 +614,7 @@ impl TransformConfig {
         // Append "\n." to the script to return the entire document and not only the modified
         // fields.
         let vrl_script = self.vrl_script.clone() + "\n.";
-        let functions = vrl::stdlib::all();
-
-        let compilation_res = match vrl::compiler::compile(&vrl_script, &functions) {
-            Ok(compilation_res) => compilation_res,
-            Err(diagnostics) => {
-                let mut formatter = vrl::diagnostic::Formatter::new(&vrl_script, diagnostics);
-                formatter.enable_colors(!quickwit_common::no_color());
-                anyhow::bail!("failed to compile VRL script:\n {formatter}")
-            }
-        };
-
-        let vrl::compiler::CompilationResult {
-            program, warnings, ..
-        } = compilation_res;
-
-        if !warnings.is_empty() {
-            let mut formatter = vrl::diagnostic::Formatter::new(&vrl_script, warnings);
-            formatter.enable_colors(!quickwit_common::no_color());
-            tracing::warn!("VRL program compiled with some warnings: {formatter}");
-        }
+        let program = compile_vrl_script(&vrl_script)?;
         Ok((program, timezone))
     }
 
@@ -608,6 +627,33 @@ impl TransformConfig {
     }
 }
 
+/// Compiles a VRL script to a VRL [`Program`](vrl::compiler::Program) using the VRL standard
+/// library.
+#[cfg(feature = "vrl")]
+pub fn compile_vrl_script(vrl_script: &str) -> anyhow::Result<vrl::compiler::Program> {
+    let functions = vrl::stdlib::all();
+
+    let compilation_res = match vrl::compiler::compile(vrl_script, &functions) {
+        Ok(compilation_res) => compilation_res,
+        Err(diagnostics) => {
+            let mut formatter = vrl::diagnostic::Formatter::new(vrl_script, diagnostics);
+            formatter.enable_colors(!quickwit_common::no_color());
+            anyhow::bail!("failed to compile VRL script:\n {formatter}")
+        }
+    };
+
+    let vrl::compiler::CompilationResult {
+        program, warnings, ..
+    } = compilation_res;
+
+    if !warnings.is_empty() {
+        let mut formatter = vrl::diagnostic::Formatter::new(vrl_script, warnings);
+        formatter.enable_colors(!quickwit_common::no_color());
+        tracing::warn!("VRL program compiled with some warnings: {formatter}");
+    }
+    Ok(program)
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
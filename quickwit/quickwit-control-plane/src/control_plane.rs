@@ -169,6 +169,7 @@ impl ControlPlane {
                 let ingest_controller = IngestController::new(
                     metastore.clone(),
                     ingester_pool.clone(),
+                    indexer_pool.clone(),
                     replication_factor,
                     shard_throughput_limit_mib,
                 );
@@ -282,8 +283,11 @@ impl ControlPlane {
                 index_template_match,
                 &self.cluster_config.default_index_root_uri,
             )?;
-            // We disable ingest V1 for index templates.
-            let source_configs = [SourceConfig::ingest_v2(), SourceConfig::cli()];
+            // We disable ingest V1 for index templates. Indexes created from a template are only
+            // reachable through ingest V2, so its source is always enabled.
+            let mut ingest_v2_source_config = SourceConfig::ingest_v2();
+            ingest_v2_source_config.enabled = true;
+            let source_configs = [ingest_v2_source_config, SourceConfig::cli()];
 
             let create_index_request = CreateIndexRequest::try_from_index_and_source_configs(
                 &index_config,
@@ -1608,6 +1612,7 @@ mod tests {
             client,
             indexing_tasks: Vec::new(),
             indexing_capacity: CpuCapacity::from_cpu_millis(4_000),
+            availability_zone_opt: None,
         };
         indexer_pool.insert(indexer_node_info.node_id.clone(), indexer_node_info);
         let ingester_pool = IngesterPool::default();
@@ -1760,6 +1765,7 @@ mod tests {
             client,
             indexing_tasks: Vec::new(),
             indexing_capacity: CpuCapacity::from_cpu_millis(4_000),
+            availability_zone_opt: None,
         };
         indexer_pool.insert(indexer_node_info.node_id.clone(), indexer_node_info);
         let ingester_pool = IngesterPool::default();
@@ -1838,6 +1844,7 @@ mod tests {
             client,
             indexing_tasks: Vec::new(),
             indexing_capacity: CpuCapacity::from_cpu_millis(4_000),
+            availability_zone_opt: None,
         };
         indexer_pool.insert(indexer_node_info.node_id.clone(), indexer_node_info);
         let ingester_pool = IngesterPool::default();
@@ -2482,6 +2489,7 @@ mod tests {
             client: indexer,
             indexing_tasks: Vec::new(),
             indexing_capacity: CpuCapacity::from_cpu_millis(1_000),
+            availability_zone_opt: None,
         };
         indexer_pool.insert(ingester_id.clone(), indexer_info);
 
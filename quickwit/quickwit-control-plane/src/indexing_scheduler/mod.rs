@@ -275,6 +275,14 @@ impl IndexingScheduler {
             })
             .collect();
 
+        let indexer_id_to_zones: FnvHashMap<String, String> = indexers
+            .iter()
+            .filter_map(|indexer| {
+                let availability_zone = indexer.availability_zone_opt.clone()?;
+                Some((indexer.node_id.to_string(), availability_zone))
+            })
+            .collect();
+
         if indexer_id_to_cpu_capacities.is_empty() {
             if !sources.is_empty() {
                 warn!("no indexing capacity available, cannot schedule an indexing plan");
@@ -286,6 +294,7 @@ impl IndexingScheduler {
         let new_physical_plan = build_physical_indexing_plan(
             &sources,
             &indexer_id_to_cpu_capacities,
+            &indexer_id_to_zones,
             self.state.last_applied_physical_plan.as_ref(),
             &shard_locations,
         );
@@ -970,8 +979,13 @@ mod tests {
         indexer_max_loads.insert("indexer1".to_string(), mcpu(3_000));
         indexer_max_loads.insert("indexer2".to_string(), mcpu(3_000));
         let shard_locations = ShardLocations::default();
-        let physical_plan =
-            build_physical_indexing_plan(&sources[..], &indexer_max_loads, None, &shard_locations);
+        let physical_plan = build_physical_indexing_plan(
+            &sources[..],
+            &indexer_max_loads,
+            &FnvHashMap::default(),
+            None,
+            &shard_locations,
+        );
         assert_eq!(physical_plan.indexing_tasks_per_indexer().len(), 2);
         let indexing_tasks_1 = physical_plan.indexer("indexer1").unwrap();
         assert_eq!(indexing_tasks_1.len(), 2);
@@ -1047,7 +1061,7 @@ mod tests {
                 indexer_max_loads.insert(indexer_id, mcpu(4_000));
             }
             let shard_locations = ShardLocations::default();
-            let _physical_indexing_plan = build_physical_indexing_plan(&sources, &indexer_max_loads, None, &shard_locations);
+            let _physical_indexing_plan = build_physical_indexing_plan(&sources, &indexer_max_loads, &FnvHashMap::default(), None, &shard_locations);
         }
     }
 
@@ -646,6 +646,7 @@ fn inflate_node_capacities_if_necessary(problem: &mut SchedulingProblem) {
 pub fn build_physical_indexing_plan(
     sources: &[SourceToSchedule],
     indexer_id_to_cpu_capacities: &FnvHashMap<String, CpuCapacity>,
+    indexer_id_to_zones: &FnvHashMap<String, String>,
     previous_plan_opt: Option<&PhysicalIndexingPlan>,
     shard_locations: &ShardLocations,
 ) -> PhysicalIndexingPlan {
@@ -657,8 +658,12 @@ pub fn build_physical_indexing_plan(
     // Instead of individual shard ids, we just keep count of shards.
     // Similarly, instead of accurate locality, we just keep the number of shards local
     // to an indexer.
-    let (id_to_ord_map, problem) =
-        convert_to_simplified_problem(indexer_id_to_cpu_capacities, sources, shard_locations);
+    let (id_to_ord_map, problem) = convert_to_simplified_problem(
+        indexer_id_to_cpu_capacities,
+        indexer_id_to_zones,
+        sources,
+        shard_locations,
+    );
 
     // Populate the previous solution, if any.
     let mut previous_solution = problem.new_solution();
@@ -699,6 +704,7 @@ fn check_sources(sources: &[SourceToSchedule]) {
 
 fn convert_to_simplified_problem<'a>(
     indexer_id_to_cpu_capacities: &'a FnvHashMap<String, CpuCapacity>,
+    indexer_id_to_zones: &FnvHashMap<String, String>,
     sources: &'a [SourceToSchedule],
     shard_locations: &ShardLocations,
 ) -> (IdToOrdMap<'a>, SchedulingProblem) {
@@ -716,6 +722,12 @@ fn convert_to_simplified_problem<'a>(
 
     let mut problem = SchedulingProblem::with_indexer_cpu_capacities(indexer_cpu_capacities);
 
+    for (indexer_id, zone) in indexer_id_to_zones {
+        if let Some(indexer_ord) = id_to_ord_map.indexer_ord(indexer_id) {
+            problem.set_indexer_zone(indexer_ord, zone.clone());
+        }
+    }
+
     for source in sources {
         if let Some(source_ord) = populate_problem(source, &mut problem) {
             let registered_source_ord = id_to_ord_map.add_source(source);
@@ -818,6 +830,7 @@ mod tests {
         let indexing_plan = build_physical_indexing_plan(
             &[source_0, source_1, source_2],
             &indexer_id_to_cpu_capacities,
+            &FnvHashMap::default(),
             None,
             &shard_locations,
         );
@@ -889,6 +902,7 @@ mod tests {
         let plan = build_physical_indexing_plan(
             &sources,
             &indexer_id_to_cpu_capacities,
+            &FnvHashMap::default(),
             None,
             &shard_locations,
         );
@@ -920,8 +934,13 @@ mod tests {
         {
             indexer_max_loads.insert(indexer1.clone(), mcpu(1_999));
             // This test what happens when there isn't enough capacity on the cluster.
-            let physical_plan =
-                build_physical_indexing_plan(&sources, &indexer_max_loads, None, &shard_locations);
+            let physical_plan = build_physical_indexing_plan(
+                &sources,
+                &indexer_max_loads,
+                &FnvHashMap::default(),
+                None,
+                &shard_locations,
+            );
             assert_eq!(physical_plan.indexing_tasks_per_indexer().len(), 1);
             let expected_tasks = physical_plan.indexer(&indexer1).unwrap();
             assert_eq!(expected_tasks.len(), 2);
@@ -930,8 +949,13 @@ mod tests {
         {
             indexer_max_loads.insert(indexer1.clone(), mcpu(2_000));
             // This test what happens when there isn't enough capacity on the cluster.
-            let physical_plan =
-                build_physical_indexing_plan(&sources, &indexer_max_loads, None, &shard_locations);
+            let physical_plan = build_physical_indexing_plan(
+                &sources,
+                &indexer_max_loads,
+                &FnvHashMap::default(),
+                None,
+                &shard_locations,
+            );
             assert_eq!(physical_plan.indexing_tasks_per_indexer().len(), 1);
             let expected_tasks = physical_plan.indexer(&indexer1).unwrap();
             assert_eq!(expected_tasks.len(), 2);
@@ -999,6 +1023,7 @@ mod tests {
         let new_plan = build_physical_indexing_plan(
             &sources,
             &indexer_id_to_cpu_capacities,
+            &FnvHashMap::default(),
             Some(&indexing_plan),
             &shard_locations,
         );
@@ -1040,6 +1065,7 @@ mod tests {
         let new_plan = build_physical_indexing_plan(
             &sources,
             &indexer_id_to_cpu_capacities,
+            &FnvHashMap::default(),
             Some(&indexing_plan),
             &shard_locations,
         );
@@ -1226,7 +1252,13 @@ mod tests {
         let mut capacities = FnvHashMap::default();
         capacities.insert("indexer-1".to_string(), CpuCapacity::from_cpu_millis(8000));
         let shard_locations = ShardLocations::default();
-        build_physical_indexing_plan(&sources_to_schedule, &capacities, None, &shard_locations);
+        build_physical_indexing_plan(
+            &sources_to_schedule,
+            &capacities,
+            &FnvHashMap::default(),
+            None,
+            &shard_locations,
+        );
     }
 
     #[test]
@@ -19,7 +19,7 @@
 
 use std::cmp::Reverse;
 use std::collections::btree_map::Entry;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 use itertools::Itertools;
 use quickwit_proto::indexing::CpuCapacity;
@@ -69,6 +69,9 @@ pub fn solve(
     // We first assign sources to indexers that have some affinity with them
     // (provided they have the capacity.)
     place_unassigned_shards_with_affinity(&problem, &mut solution);
+    // We then assign shards to indexers located in the same availability zone as the indexers
+    // they have some affinity with, to avoid cross-zone traffic.
+    place_unassigned_shards_with_zone_affinity(&problem, &mut solution);
     // Finally we assign the remaining shards, regardess of whether they have affinity
     // or not.
     place_unassigned_shards_ignoring_affinity(problem, &solution)
@@ -268,6 +271,46 @@ fn place_unassigned_shards_with_affinity(
     }
 }
 
+// Places the remaining shards of the sources on indexers that live in the same availability zone
+// as one of the indexers the source has some affinity with. Indexers are sorted by decreasing
+// available capacity.
+//
+// This is a no-op if the availability zones of the indexers are unknown.
+fn place_unassigned_shards_with_zone_affinity(
+    problem: &SchedulingProblem,
+    solution: &mut SchedulingSolution,
+) {
+    let mut unassigned_shards: Vec<Source> = compute_unassigned_sources(problem, solution);
+    unassigned_shards.sort_by_key(|source| {
+        let load = source.num_shards * source.load_per_shard.get();
+        Reverse(load)
+    });
+    for source in &unassigned_shards {
+        let source_zones: BTreeSet<&str> = source
+            .affinities
+            .iter()
+            .filter(|&(_, &affinity)| affinity != 0u32)
+            .filter_map(|(&indexer_ord, _)| problem.indexer_zone(indexer_ord))
+            .collect();
+        if source_zones.is_empty() {
+            continue;
+        }
+        let indexers_in_zone_with_available_capacity =
+            compute_indexer_available_capacity(problem, solution)
+                .filter(|(indexer_ord, _)| {
+                    problem
+                        .indexer_zone(*indexer_ord)
+                        .is_some_and(|zone| source_zones.contains(zone))
+                })
+                .sorted_by_key(|(indexer_ord, capacity)| Reverse((*capacity, *indexer_ord)));
+        let _ = place_unassigned_shards_single_source(
+            source,
+            indexers_in_zone_with_available_capacity,
+            solution,
+        );
+    }
+}
+
 // ----------------------------------------------------
 // Phase 3
 // Place unassigned sources.
@@ -607,6 +650,30 @@ mod tests {
         assert_eq!(solution.indexer_assignments[1].num_shards(0), 4);
     }
 
+    #[test]
+    fn test_place_unassigned_shards_with_zone_affinity() {
+        let mut problem = SchedulingProblem::with_indexer_cpu_capacities(vec![
+            mcpu(2_000),
+            mcpu(4_000),
+            mcpu(8_000),
+        ]);
+        problem.set_indexer_zone(0, "zone-a".to_string());
+        problem.set_indexer_zone(1, "zone-a".to_string());
+        problem.set_indexer_zone(2, "zone-b".to_string());
+        problem.add_source(4, NonZeroU32::new(1_000).unwrap());
+        for _ in 0..4 {
+            problem.inc_affinity(0, 0);
+        }
+        let mut solution = problem.new_solution();
+        place_unassigned_shards_with_affinity(&problem, &mut solution);
+        assert_eq!(solution.indexer_assignments[0].num_shards(0), 2);
+
+        place_unassigned_shards_with_zone_affinity(&problem, &mut solution);
+        assert_eq!(solution.indexer_assignments[0].num_shards(0), 2);
+        assert_eq!(solution.indexer_assignments[1].num_shards(0), 2);
+        assert_eq!(solution.indexer_assignments[2].num_shards(0), 0);
+    }
+
     #[test]
     fn test_place_unassigned_shards_reach_capacity() {
         let mut problem =
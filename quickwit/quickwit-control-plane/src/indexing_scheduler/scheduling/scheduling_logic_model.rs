@@ -83,6 +83,8 @@ impl Source {
 pub struct SchedulingProblem {
     sources: Vec<Source>,
     indexer_cpu_capacities: Vec<CpuCapacity>,
+    /// Availability zones of the indexers, if known.
+    indexer_zones: Vec<Option<String>>,
 }
 
 impl SchedulingProblem {
@@ -98,9 +100,11 @@ impl SchedulingProblem {
             .iter()
             .all(|cpu_capacity| cpu_capacity.cpu_millis() > 0));
         // TODO assert for affinity.
+        let indexer_zones = vec![None; indexer_cpu_capacities.len()];
         SchedulingProblem {
             sources: Vec::new(),
             indexer_cpu_capacities,
+            indexer_zones,
         }
     }
 
@@ -112,6 +116,14 @@ impl SchedulingProblem {
         self.indexer_cpu_capacities[indexer_ord]
     }
 
+    pub fn set_indexer_zone(&mut self, indexer_ord: IndexerOrd, zone: String) {
+        self.indexer_zones[indexer_ord] = Some(zone);
+    }
+
+    pub fn indexer_zone(&self, indexer_ord: IndexerOrd) -> Option<&str> {
+        self.indexer_zones[indexer_ord].as_deref()
+    }
+
     /// Scales the cpu capacity by the given scaling factor.
     ///
     /// Resulting cpu capacity are ceiled to the next integer millicpus value.
@@ -62,6 +62,7 @@ use super::scaling_arbiter::ScalingArbiter;
 use crate::control_plane::ControlPlane;
 use crate::ingest::wait_handle::WaitHandle;
 use crate::model::{ControlPlaneModel, ScalingMode, ShardEntry, ShardStats};
+use crate::IndexerPool;
 
 const CLOSE_SHARDS_REQUEST_TIMEOUT: Duration = if cfg!(test) {
     Duration::from_millis(50)
@@ -145,7 +146,23 @@ fn pick_one<'a>(
     };
     let nodes = occupied_shard_entry.get_mut();
     let position = pick_position(nodes, except_node_opt, rng)?;
+    Some(increment_shard_count(
+        shard_count_to_node_ids,
+        shard_count,
+        position,
+    ))
+}
 
+/// Moves the node at `position` in the `shard_count` level of `shard_count_to_node_ids` to the
+/// `shard_count + 1` level and returns it. Empty levels are removed.
+fn increment_shard_count<'a>(
+    shard_count_to_node_ids: &mut BTreeMap<usize, Vec<&'a NodeIdRef>>,
+    shard_count: usize,
+    position: usize,
+) -> &'a NodeIdRef {
+    let nodes = shard_count_to_node_ids
+        .get_mut(&shard_count)
+        .expect("shard count level should exist");
     let node_id = nodes.swap_remove(position);
     let new_shard_count = shard_count + 1;
     let should_remove_entry = nodes.is_empty();
@@ -157,25 +174,71 @@ fn pick_one<'a>(
         .entry(new_shard_count)
         .or_default()
         .push(node_id);
-    Some(node_id)
+    node_id
+}
+
+/// Picks a node located in a different availability zone than `leader` from
+/// `shard_count_to_node_ids`. As in [`pick_one`], nodes with the least number of shards are
+/// preferred and ties are broken randomly. Returns `None` if the zone of the leader is unknown or
+/// if no node is located in another known zone.
+fn pick_one_in_other_zone<'a>(
+    shard_count_to_node_ids: &mut BTreeMap<usize, Vec<&'a NodeIdRef>>,
+    leader: &NodeIdRef,
+    node_zones: &HashMap<NodeId, String>,
+    rng: &mut ThreadRng,
+) -> Option<&'a NodeIdRef> {
+    let leader_zone = node_zones.get(leader.as_str())?;
+    let is_in_other_zone = |node_id: &NodeIdRef| {
+        node_zones
+            .get(node_id.as_str())
+            .is_some_and(|zone| zone != leader_zone)
+    };
+    let (shard_count, positions) =
+        shard_count_to_node_ids
+            .iter()
+            .find_map(|(&shard_count, node_ids)| {
+                let positions: Vec<usize> = node_ids
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, node_id)| is_in_other_zone(node_id))
+                    .map(|(position, _)| position)
+                    .collect();
+                (!positions.is_empty()).then_some((shard_count, positions))
+            })?;
+    let position = positions[rng.gen_range(0..positions.len())];
+    Some(increment_shard_count(
+        shard_count_to_node_ids,
+        shard_count,
+        position,
+    ))
 }
 
 /// Pick two ingester nodes from `shard_count_to_node_ids` different one from each other.
-/// Ingesters with the lower number of shards are preferred.
+/// Ingesters with the lower number of shards are preferred. When the availability zones of the
+/// ingesters are known, the follower is placed in a different zone than the leader whenever
+/// possible, so that a shard survives the loss of a zone.
 fn pick_two<'a>(
     shard_count_to_node_ids: &mut BTreeMap<usize, Vec<&'a NodeIdRef>>,
+    node_zones: &HashMap<NodeId, String>,
     rng: &mut ThreadRng,
 ) -> Option<(&'a NodeIdRef, &'a NodeIdRef)> {
     let leader = pick_one(shard_count_to_node_ids, None, rng)?;
-    let follower = pick_one(shard_count_to_node_ids, Some(leader), rng)?;
+    let follower = if let Some(follower) =
+        pick_one_in_other_zone(shard_count_to_node_ids, leader, node_zones, rng)
+    {
+        follower
+    } else {
+        pick_one(shard_count_to_node_ids, Some(leader), rng)?
+    };
     Some((leader, follower))
 }
 
-fn allocate_shards(
-    node_id_shard_counts: &HashMap<NodeId, usize>,
+fn allocate_shards<'a>(
+    node_id_shard_counts: &'a HashMap<NodeId, usize>,
+    node_zones: &HashMap<NodeId, String>,
     num_shards: usize,
     replication_enabled: bool,
-) -> Option<Vec<(&NodeIdRef, Option<&NodeIdRef>)>> {
+) -> Option<Vec<(&'a NodeIdRef, Option<&'a NodeIdRef>)>> {
     let mut shard_count_to_node_ids: BTreeMap<usize, Vec<&NodeIdRef>> = BTreeMap::default();
     for (node_id, &num_shards) in node_id_shard_counts {
         shard_count_to_node_ids
@@ -188,7 +251,7 @@ fn allocate_shards(
         Vec::with_capacity(num_shards);
     for _ in 0..num_shards {
         if replication_enabled {
-            let (leader, follower) = pick_two(&mut shard_count_to_node_ids, &mut rng)?;
+            let (leader, follower) = pick_two(&mut shard_count_to_node_ids, node_zones, &mut rng)?;
             shard_allocations.push((leader, Some(follower)));
         } else {
             let leader = pick_one(&mut shard_count_to_node_ids, None, &mut rng)?;
@@ -205,6 +268,8 @@ pub struct IngestControllerStats {
 
 pub struct IngestController {
     ingester_pool: IngesterPool,
+    // Used to look up the availability zones of the ingesters, which are also indexers.
+    indexer_pool: IndexerPool,
     metastore: MetastoreServiceClient,
     replication_factor: usize,
     // This lock ensures that only one rebalance operation is performed at a time.
@@ -288,12 +353,14 @@ impl IngestController {
     pub fn new(
         metastore: MetastoreServiceClient,
         ingester_pool: IngesterPool,
+        indexer_pool: IndexerPool,
         replication_factor: usize,
         max_shard_ingestion_throughput_mib_per_sec: f32,
     ) -> Self {
         IngestController {
             metastore,
             ingester_pool,
+            indexer_pool,
             replication_factor,
             rebalance_lock: Arc::new(Mutex::new(())),
             stats: IngestControllerStats::default(),
@@ -575,9 +642,20 @@ impl IngestController {
             }
         }
 
+        let node_zones: HashMap<NodeId, String> = self
+            .indexer_pool
+            .values()
+            .into_iter()
+            .filter_map(|indexer_node_info| {
+                let availability_zone = indexer_node_info.availability_zone_opt?;
+                Some((indexer_node_info.node_id, availability_zone))
+            })
+            .collect();
+
         assert!(self.replication_factor == 1 || self.replication_factor == 2);
         let leader_follower_pairs: Vec<(&NodeIdRef, Option<&NodeIdRef>)> = allocate_shards(
             &per_node_num_open_shards,
+            &node_zones,
             num_shards_to_allocate,
             self.replication_factor == 2,
         )?;
@@ -1350,6 +1428,7 @@ mod tests {
         let mut controller = IngestController::new(
             metastore,
             ingester_pool.clone(),
+            IndexerPool::default(),
             replication_factor,
             TEST_SHARD_THROUGHPUT_LIMIT_MIB,
         );
@@ -1535,6 +1614,7 @@ mod tests {
         let mut controller = IngestController::new(
             metastore,
             ingester_pool,
+            IndexerPool::default(),
             replication_factor,
             TEST_SHARD_THROUGHPUT_LIMIT_MIB,
         );
@@ -1577,6 +1657,7 @@ mod tests {
         let mut controller = IngestController::new(
             metastore,
             ingester_pool,
+            IndexerPool::default(),
             replication_factor,
             TEST_SHARD_THROUGHPUT_LIMIT_MIB,
         );
@@ -1627,6 +1708,7 @@ mod tests {
         let controller = IngestController::new(
             metastore,
             ingester_pool.clone(),
+            IndexerPool::default(),
             replication_factor,
             TEST_SHARD_THROUGHPUT_LIMIT_MIB,
         );
@@ -1809,6 +1891,7 @@ mod tests {
         let controller = IngestController::new(
             metastore,
             ingester_pool.clone(),
+            IndexerPool::default(),
             replication_factor,
             TEST_SHARD_THROUGHPUT_LIMIT_MIB,
         );
@@ -2032,6 +2115,7 @@ mod tests {
         let mut controller = IngestController::new(
             metastore,
             ingester_pool.clone(),
+            IndexerPool::default(),
             replication_factor,
             TEST_SHARD_THROUGHPUT_LIMIT_MIB,
         );
@@ -2169,6 +2253,7 @@ mod tests {
         let mut controller = IngestController::new(
             metastore,
             ingester_pool.clone(),
+            IndexerPool::default(),
             replication_factor,
             TEST_SHARD_THROUGHPUT_LIMIT_MIB,
         );
@@ -2366,6 +2451,7 @@ mod tests {
         let mut controller = IngestController::new(
             metastore,
             ingester_pool.clone(),
+            IndexerPool::default(),
             replication_factor,
             TEST_SHARD_THROUGHPUT_LIMIT_MIB,
         );
@@ -2490,6 +2576,7 @@ mod tests {
         let mut controller = IngestController::new(
             metastore,
             ingester_pool.clone(),
+            IndexerPool::default(),
             replication_factor,
             TEST_SHARD_THROUGHPUT_LIMIT_MIB,
         );
@@ -2601,6 +2688,7 @@ mod tests {
         let controller = IngestController::new(
             metastore,
             ingester_pool.clone(),
+            IndexerPool::default(),
             replication_factor,
             TEST_SHARD_THROUGHPUT_LIMIT_MIB,
         );
@@ -2827,6 +2915,7 @@ mod tests {
         let controller = IngestController::new(
             metastore,
             ingester_pool.clone(),
+            IndexerPool::default(),
             replication_factor,
             TEST_SHARD_THROUGHPUT_LIMIT_MIB,
         );
@@ -2910,6 +2999,7 @@ mod tests {
         let controller = IngestController::new(
             metastore,
             ingester_pool,
+            IndexerPool::default(),
             replication_factor,
             TEST_SHARD_THROUGHPUT_LIMIT_MIB,
         );
@@ -2985,6 +3075,7 @@ mod tests {
         let controller = IngestController::new(
             metastore,
             ingester_pool.clone(),
+            IndexerPool::default(),
             replication_factor,
             TEST_SHARD_THROUGHPUT_LIMIT_MIB,
         );
@@ -3141,6 +3232,7 @@ mod tests {
         let mut controller = IngestController::new(
             metastore,
             ingester_pool.clone(),
+            IndexerPool::default(),
             replication_factor,
             TEST_SHARD_THROUGHPUT_LIMIT_MIB,
         );
@@ -3299,8 +3391,12 @@ mod tests {
         num_shards: usize,
         replication_enabled: bool,
     ) {
-        let shard_allocations_opt =
-            super::allocate_shards(shard_counts_map, num_shards, replication_enabled);
+        let shard_allocations_opt = super::allocate_shards(
+            shard_counts_map,
+            &HashMap::new(),
+            num_shards,
+            replication_enabled,
+        );
         if num_shards == 0 {
             assert_eq!(shard_allocations_opt, Some(Vec::new()));
             return;
@@ -3394,6 +3490,44 @@ mod tests {
         test_allocate_shards_aux(&[7, 7, 7]);
     }
 
+    #[test]
+    fn test_allocate_shards_places_followers_in_other_zone() {
+        let mut shard_counts_map: HashMap<NodeId, usize> = HashMap::new();
+        let mut node_zones: HashMap<NodeId, String> = HashMap::new();
+
+        for (node_id, zone) in [
+            ("node-1", "zone-a"),
+            ("node-2", "zone-a"),
+            ("node-3", "zone-b"),
+            ("node-4", "zone-b"),
+        ] {
+            shard_counts_map.insert(NodeId::from(node_id), 0);
+            node_zones.insert(NodeId::from(node_id), zone.to_string());
+        }
+        let shard_allocations =
+            super::allocate_shards(&shard_counts_map, &node_zones, 8, true).unwrap();
+        assert_eq!(shard_allocations.len(), 8);
+
+        for (leader, follower_opt) in shard_allocations {
+            let follower = follower_opt.unwrap();
+            assert_ne!(node_zones[leader.as_str()], node_zones[follower.as_str()]);
+        }
+        // When all the nodes live in the same zone, we fall back to picking any other node.
+        node_zones.clear();
+        for node_id in ["node-1", "node-2"] {
+            node_zones.insert(NodeId::from(node_id), "zone-a".to_string());
+        }
+        shard_counts_map.remove("node-3");
+        shard_counts_map.remove("node-4");
+
+        let shard_allocations =
+            super::allocate_shards(&shard_counts_map, &node_zones, 2, true).unwrap();
+
+        for (leader, follower_opt) in shard_allocations {
+            assert_ne!(leader, follower_opt.unwrap());
+        }
+    }
+
     #[test]
     fn test_pick_one() {
         let mut shard_counts = BTreeMap::default();
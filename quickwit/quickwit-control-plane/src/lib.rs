@@ -36,6 +36,7 @@ pub struct IndexerNodeInfo {
     pub client: IndexingServiceClient,
     pub indexing_tasks: Vec<IndexingTask>,
     pub indexing_capacity: CpuCapacity,
+    pub availability_zone_opt: Option<String>,
 }
 
 pub type IndexerPool = Pool<NodeId, IndexerNodeInfo>;
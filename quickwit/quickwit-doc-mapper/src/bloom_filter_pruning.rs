@@ -0,0 +1,568 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::hash::Hasher;
+
+use anyhow::{bail, ensure, Context};
+use quickwit_query::query_ast::QueryAst;
+use quickwit_query::InterpretUserInput;
+use siphasher::sip128::{Hasher128, SipHasher};
+use tantivy::schema::{FieldType, Schema};
+use tantivy::Term;
+
+/// Target false positive rate of the bloom filters.
+const FALSE_POSITIVE_RATE: f64 = 0.01;
+
+/// Version of the serialization format of [`SplitBloomFilters`].
+const SPLIT_BLOOM_FILTERS_FORMAT_VERSION: u8 = 1;
+
+/// A bloom filter over the terms of a field.
+///
+/// A bloom filter can tell with certainty that a term is absent from a split. When it answers
+/// that a term may be present, it is wrong with a probability close to `FALSE_POSITIVE_RATE`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BloomFilter {
+    num_hashes: u32,
+    bits: Vec<u64>,
+}
+
+impl BloomFilter {
+    /// Creates an empty bloom filter sized to hold `num_items` items while meeting the target
+    /// false positive rate.
+    pub fn with_num_items(num_items: usize) -> Self {
+        let ln_2 = std::f64::consts::LN_2;
+        let num_bits_per_item = -FALSE_POSITIVE_RATE.ln() / (ln_2 * ln_2);
+        let num_bits = (num_items.max(1) as f64 * num_bits_per_item).ceil() as usize;
+        let num_words = num_bits.div_ceil(64);
+        let num_hashes = ((num_words * 64) as f64 / num_items.max(1) as f64 * ln_2).round() as u32;
+
+        BloomFilter {
+            num_hashes: num_hashes.clamp(1, 16),
+            bits: vec![0u64; num_words],
+        }
+    }
+
+    /// Adds a key to the bloom filter.
+    pub fn insert(&mut self, key: &[u8]) {
+        for bit_pos in self.bit_positions(key) {
+            self.bits[bit_pos / 64] |= 1u64 << (bit_pos % 64);
+        }
+    }
+
+    /// Returns false if and only if the key has never been inserted into the bloom filter.
+    pub fn may_contain(&self, key: &[u8]) -> bool {
+        self.bit_positions(key)
+            .all(|bit_pos| self.bits[bit_pos / 64] & (1u64 << (bit_pos % 64)) != 0)
+    }
+
+    /// Returns the number of bytes used by the bloom filter.
+    pub fn num_bytes(&self) -> usize {
+        self.bits.len() * 8
+    }
+
+    // Derives the bit positions of a key from a single 128-bit hash using double hashing.
+    fn bit_positions(&self, key: &[u8]) -> impl Iterator<Item = usize> {
+        let mut hasher = SipHasher::new();
+        hasher.write(key);
+        let hash = hasher.finish128();
+        let num_bits = self.bits.len() as u64 * 64;
+
+        (0..self.num_hashes as u64).map(move |hash_ord| {
+            let combined_hash = hash.h1.wrapping_add(hash_ord.wrapping_mul(hash.h2));
+            (combined_hash % num_bits) as usize
+        })
+    }
+}
+
+/// The bloom filters of a split, keyed by field name.
+///
+/// They are built for each field registered in the [`DocMapping`](crate::DocMapping)
+/// `bloom_filter_fields` attribute and stored in the split bundle.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SplitBloomFilters {
+    bloom_filters: BTreeMap<String, BloomFilter>,
+}
+
+impl SplitBloomFilters {
+    /// Registers the bloom filter of a field.
+    pub fn insert(&mut self, field_name: String, bloom_filter: BloomFilter) {
+        self.bloom_filters.insert(field_name, bloom_filter);
+    }
+
+    /// Returns the bloom filter of a field, if any.
+    pub fn get(&self, field_name: &str) -> Option<&BloomFilter> {
+        self.bloom_filters.get(field_name)
+    }
+
+    /// Returns true if the split has no bloom filters.
+    pub fn is_empty(&self) -> bool {
+        self.bloom_filters.is_empty()
+    }
+
+    /// Serializes the bloom filters.
+    ///
+    /// Format: `[version: u8][num filters: u32]` followed, for each filter, by
+    /// `[field name len: u32][field name][num hashes: u32][num words: u32][words: u64...]`.
+    /// Integers are encoded in little endian.
+    pub fn serialize(&self) -> Vec<u8> {
+        let num_bytes = 5 + self
+            .bloom_filters
+            .iter()
+            .map(|(field_name, bloom_filter)| 12 + field_name.len() + bloom_filter.num_bytes())
+            .sum::<usize>();
+        let mut buffer = Vec::with_capacity(num_bytes);
+        buffer.push(SPLIT_BLOOM_FILTERS_FORMAT_VERSION);
+        buffer.extend((self.bloom_filters.len() as u32).to_le_bytes());
+
+        for (field_name, bloom_filter) in &self.bloom_filters {
+            buffer.extend((field_name.len() as u32).to_le_bytes());
+            buffer.extend(field_name.as_bytes());
+            buffer.extend(bloom_filter.num_hashes.to_le_bytes());
+            buffer.extend((bloom_filter.bits.len() as u32).to_le_bytes());
+
+            for word in &bloom_filter.bits {
+                buffer.extend(word.to_le_bytes());
+            }
+        }
+        buffer
+    }
+
+    /// Deserializes bloom filters serialized with [`SplitBloomFilters::serialize`].
+    pub fn deserialize(mut bytes: &[u8]) -> anyhow::Result<Self> {
+        let version = read_bytes::<1>(&mut bytes)?[0];
+
+        if version != SPLIT_BLOOM_FILTERS_FORMAT_VERSION {
+            bail!("unsupported bloom filters format version `{version}`");
+        }
+        let num_filters = read_u32(&mut bytes)?;
+        let mut bloom_filters = BTreeMap::new();
+
+        for _ in 0..num_filters {
+            let field_name_len = read_u32(&mut bytes)? as usize;
+            ensure!(bytes.len() >= field_name_len, "bloom filters are truncated");
+            let (field_name_bytes, rest) = bytes.split_at(field_name_len);
+            let field_name = std::str::from_utf8(field_name_bytes)
+                .context("bloom filter field name is not valid UTF-8")?
+                .to_string();
+            bytes = rest;
+
+            let num_hashes = read_u32(&mut bytes)?;
+            let num_words = read_u32(&mut bytes)? as usize;
+            ensure!(
+                num_hashes > 0 && num_words > 0,
+                "bloom filter of field `{field_name}` is empty"
+            );
+            ensure!(bytes.len() >= num_words * 8, "bloom filters are truncated");
+            let mut bits = Vec::with_capacity(num_words);
+
+            for _ in 0..num_words {
+                bits.push(u64::from_le_bytes(read_bytes::<8>(&mut bytes)?));
+            }
+            bloom_filters.insert(field_name, BloomFilter { num_hashes, bits });
+        }
+        Ok(SplitBloomFilters { bloom_filters })
+    }
+}
+
+fn read_bytes<const N: usize>(bytes: &mut &[u8]) -> anyhow::Result<[u8; N]> {
+    ensure!(bytes.len() >= N, "bloom filters are truncated");
+    let (head, rest) = bytes.split_at(N);
+    *bytes = rest;
+    Ok(head.try_into().expect("slice should have the right length"))
+}
+
+fn read_u32(bytes: &mut &[u8]) -> anyhow::Result<u32> {
+    read_bytes::<4>(bytes).map(u32::from_le_bytes)
+}
+
+/// A term looked up in the bloom filter of a field.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BloomFilterTerm {
+    /// Name of the field.
+    pub field_name: String,
+    /// Bytes of the term, as stored in the term dictionary of the field.
+    pub term_bytes: Vec<u8>,
+}
+
+/// A conjunction of disjunctions of terms, one of which at least must be present in each
+/// disjunction for a split to match the query.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BloomFilterQuery {
+    clauses: Vec<Vec<BloomFilterTerm>>,
+}
+
+impl BloomFilterQuery {
+    /// Returns true if the query does not constrain the terms of the split.
+    pub fn is_empty(&self) -> bool {
+        self.clauses.is_empty()
+    }
+
+    /// Evaluates the query over the bloom filters of a split.
+    ///
+    /// Returns false if and only if no document of the split can match the query. Terms of
+    /// fields without a bloom filter may always be present.
+    pub fn evaluate(&self, split_bloom_filters: &SplitBloomFilters) -> bool {
+        self.clauses.iter().all(|clause| {
+            clause.iter().any(|term| {
+                split_bloom_filters
+                    .get(&term.field_name)
+                    .map(|bloom_filter| bloom_filter.may_contain(&term.term_bytes))
+                    .unwrap_or(true)
+            })
+        })
+    }
+}
+
+/// Extracts from a user query the terms over the fields registered in
+/// `bloom_filter_field_names` that documents are required to contain.
+///
+/// If the returned query evaluates to false for the bloom filters of a split, we are
+/// guaranteed that no documents in the split match the query.
+pub fn extract_bloom_filter_query(
+    query_ast: &QueryAst,
+    schema: &Schema,
+    bloom_filter_field_names: &BTreeSet<String>,
+) -> BloomFilterQuery {
+    let mut bloom_filter_query = BloomFilterQuery::default();
+
+    if !bloom_filter_field_names.is_empty() {
+        collect_bloom_filter_clauses(
+            query_ast,
+            schema,
+            bloom_filter_field_names,
+            &mut bloom_filter_query.clauses,
+        );
+    }
+    bloom_filter_query
+}
+
+fn collect_bloom_filter_clauses(
+    query_ast: &QueryAst,
+    schema: &Schema,
+    bloom_filter_field_names: &BTreeSet<String>,
+    clauses: &mut Vec<Vec<BloomFilterTerm>>,
+) {
+    match query_ast {
+        QueryAst::Bool(bool_query)
+            if !bool_query.must.is_empty() || !bool_query.filter.is_empty() =>
+        {
+            // Only the `must` and `filter` clauses are required to match.
+            for child_ast in bool_query.must.iter().chain(&bool_query.filter) {
+                collect_bloom_filter_clauses(child_ast, schema, bloom_filter_field_names, clauses);
+            }
+        }
+        QueryAst::Boost { underlying, .. } => {
+            collect_bloom_filter_clauses(underlying, schema, bloom_filter_field_names, clauses);
+        }
+        _ => {
+            if let Some(clause) =
+                extract_bloom_filter_disjunction(query_ast, schema, bloom_filter_field_names)
+            {
+                clauses.push(clause);
+            }
+        }
+    }
+}
+
+/// Returns the terms one of which at least must be present in a document matching the query,
+/// or `None` if the query can match documents containing none of the terms.
+fn extract_bloom_filter_disjunction(
+    query_ast: &QueryAst,
+    schema: &Schema,
+    bloom_filter_field_names: &BTreeSet<String>,
+) -> Option<Vec<BloomFilterTerm>> {
+    match query_ast {
+        QueryAst::Term(term_query) => {
+            let term = build_bloom_filter_term(
+                &term_query.field,
+                &term_query.value,
+                schema,
+                bloom_filter_field_names,
+            )?;
+            Some(vec![term])
+        }
+        QueryAst::FullText(full_text_query) => {
+            // A custom tokenizer may split the text into several terms.
+            if full_text_query.params.tokenizer.is_some() {
+                return None;
+            }
+            let term = build_bloom_filter_term(
+                &full_text_query.field,
+                &full_text_query.text,
+                schema,
+                bloom_filter_field_names,
+            )?;
+            Some(vec![term])
+        }
+        QueryAst::TermSet(term_set_query) => {
+            let mut terms = Vec::new();
+
+            for (field_name, values) in &term_set_query.terms_per_field {
+                for value in values {
+                    terms.push(build_bloom_filter_term(
+                        field_name,
+                        value,
+                        schema,
+                        bloom_filter_field_names,
+                    )?);
+                }
+            }
+            if terms.is_empty() {
+                return None;
+            }
+            Some(terms)
+        }
+        QueryAst::Bool(bool_query)
+            if bool_query.must.is_empty()
+                && bool_query.filter.is_empty()
+                && !bool_query.should.is_empty()
+                && bool_query.minimum_should_match != Some(0) =>
+        {
+            // At least one of the `should` clauses is required to match.
+            let mut terms = Vec::new();
+
+            for child_ast in &bool_query.should {
+                terms.extend(extract_bloom_filter_disjunction(
+                    child_ast,
+                    schema,
+                    bloom_filter_field_names,
+                )?);
+            }
+            Some(terms)
+        }
+        QueryAst::Boost { underlying, .. } => {
+            extract_bloom_filter_disjunction(underlying, schema, bloom_filter_field_names)
+        }
+        _ => None,
+    }
+}
+
+fn build_bloom_filter_term(
+    field_name: &str,
+    value: &str,
+    schema: &Schema,
+    bloom_filter_field_names: &BTreeSet<String>,
+) -> Option<BloomFilterTerm> {
+    if !bloom_filter_field_names.contains(field_name) {
+        return None;
+    }
+    let field = schema.get_field(field_name).ok()?;
+    let term = match schema.get_field_entry(field).field_type() {
+        // Bloom filters are only allowed on text fields with the `raw` tokenizer, so the term is
+        // the value itself.
+        FieldType::Str(_) => Term::from_field_text(field, value),
+        FieldType::U64(_) => Term::from_field_u64(field, u64::interpret_str(value)?),
+        FieldType::I64(_) => Term::from_field_i64(field, i64::interpret_str(value)?),
+        FieldType::Bytes(_) => Term::from_field_bytes(field, &Vec::<u8>::interpret_str(value)?),
+        _ => return None,
+    };
+    let bloom_filter_term = BloomFilterTerm {
+        field_name: field_name.to_string(),
+        term_bytes: term.serialized_value_bytes().to_vec(),
+    };
+    Some(bloom_filter_term)
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_query::query_ast::{BoolQuery, TermQuery, TermSetQuery};
+    use tantivy::schema::{BytesOptions, INDEXED, STRING};
+
+    use super::*;
+
+    fn test_schema() -> Schema {
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_text_field("request_id", STRING);
+        schema_builder.add_u64_field("user_id", INDEXED);
+        schema_builder.add_bytes_field("trace_id", BytesOptions::default().set_indexed());
+        schema_builder.add_text_field("service", STRING);
+        schema_builder.build()
+    }
+
+    fn bloom_filter_field_names() -> BTreeSet<String> {
+        ["request_id", "user_id", "trace_id"]
+            .into_iter()
+            .map(|field_name| field_name.to_string())
+            .collect()
+    }
+
+    fn term_query(field: &str, value: &str) -> QueryAst {
+        TermQuery {
+            field: field.to_string(),
+            value: value.to_string(),
+        }
+        .into()
+    }
+
+    fn bloom_filter_term(field_name: &str, term_bytes: &[u8]) -> BloomFilterTerm {
+        BloomFilterTerm {
+            field_name: field_name.to_string(),
+            term_bytes: term_bytes.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_bloom_filter() {
+        let num_items = 10_000;
+        let mut bloom_filter = BloomFilter::with_num_items(num_items);
+
+        for item in 0..num_items {
+            bloom_filter.insert(format!("item-{item}").as_bytes());
+        }
+        for item in 0..num_items {
+            assert!(bloom_filter.may_contain(format!("item-{item}").as_bytes()));
+        }
+        let num_false_positives = (num_items..2 * num_items)
+            .filter(|item| bloom_filter.may_contain(format!("item-{item}").as_bytes()))
+            .count();
+        assert!(num_false_positives < num_items * 2 / 100);
+    }
+
+    #[test]
+    fn test_split_bloom_filters_serialization() {
+        let mut split_bloom_filters = SplitBloomFilters::default();
+        assert_eq!(
+            SplitBloomFilters::deserialize(&split_bloom_filters.serialize()).unwrap(),
+            split_bloom_filters
+        );
+        let mut bloom_filter = BloomFilter::with_num_items(3);
+        bloom_filter.insert(b"abc");
+        split_bloom_filters.insert("request_id".to_string(), bloom_filter);
+        split_bloom_filters.insert("user_id".to_string(), BloomFilter::with_num_items(1_000));
+
+        let serialized_bloom_filters = split_bloom_filters.serialize();
+        let deserialized_bloom_filters =
+            SplitBloomFilters::deserialize(&serialized_bloom_filters).unwrap();
+        assert_eq!(deserialized_bloom_filters, split_bloom_filters);
+        assert!(deserialized_bloom_filters
+            .get("request_id")
+            .unwrap()
+            .may_contain(b"abc"));
+
+        let truncated_bloom_filters =
+            &serialized_bloom_filters[..serialized_bloom_filters.len() - 1];
+        SplitBloomFilters::deserialize(truncated_bloom_filters).unwrap_err();
+    }
+
+    #[test]
+    fn test_extract_bloom_filter_query() {
+        let schema = test_schema();
+        let bloom_filter_field_names = bloom_filter_field_names();
+
+        let query_ast: QueryAst = BoolQuery {
+            must: vec![term_query("request_id", "req-1")],
+            filter: vec![BoolQuery {
+                should: vec![term_query("trace_id", "00ff"), term_query("user_id", "7")],
+                ..Default::default()
+            }
+            .into()],
+            should: vec![term_query("request_id", "req-2")],
+            must_not: vec![term_query("request_id", "req-3")],
+            ..Default::default()
+        }
+        .into();
+        let bloom_filter_query =
+            extract_bloom_filter_query(&query_ast, &schema, &bloom_filter_field_names);
+        assert_eq!(
+            bloom_filter_query.clauses,
+            [
+                vec![bloom_filter_term("request_id", b"req-1")],
+                vec![
+                    bloom_filter_term("trace_id", &[0, 255]),
+                    bloom_filter_term("user_id", &7u64.to_be_bytes()),
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_bloom_filter_query_uninformative() {
+        let schema = test_schema();
+        let bloom_filter_field_names = bloom_filter_field_names();
+
+        // Not a bloom filter field.
+        let query_ast = term_query("service", "api");
+        assert!(
+            extract_bloom_filter_query(&query_ast, &schema, &bloom_filter_field_names).is_empty()
+        );
+        // Invalid value.
+        let query_ast = term_query("user_id", "abc");
+        assert!(
+            extract_bloom_filter_query(&query_ast, &schema, &bloom_filter_field_names).is_empty()
+        );
+        // One of the alternatives is not a bloom filter field.
+        let query_ast: QueryAst = BoolQuery {
+            should: vec![
+                term_query("request_id", "req-1"),
+                term_query("service", "api"),
+            ],
+            ..Default::default()
+        }
+        .into();
+        assert!(
+            extract_bloom_filter_query(&query_ast, &schema, &bloom_filter_field_names).is_empty()
+        );
+        // Optional clause.
+        let query_ast: QueryAst = BoolQuery {
+            must: vec![term_query("service", "api")],
+            should: vec![term_query("request_id", "req-1")],
+            ..Default::default()
+        }
+        .into();
+        assert!(
+            extract_bloom_filter_query(&query_ast, &schema, &bloom_filter_field_names).is_empty()
+        );
+    }
+
+    #[test]
+    fn test_bloom_filter_query_evaluate() {
+        let schema = test_schema();
+        let bloom_filter_field_names = bloom_filter_field_names();
+
+        let mut bloom_filter = BloomFilter::with_num_items(2);
+        bloom_filter.insert(b"req-1");
+        bloom_filter.insert(b"req-2");
+        let mut split_bloom_filters = SplitBloomFilters::default();
+        split_bloom_filters.insert("request_id".to_string(), bloom_filter);
+
+        let query_ast: QueryAst = TermSetQuery {
+            terms_per_field: [(
+                "request_id".to_string(),
+                ["req-2".to_string(), "req-3".to_string()].into(),
+            )]
+            .into(),
+        }
+        .into();
+        let bloom_filter_query =
+            extract_bloom_filter_query(&query_ast, &schema, &bloom_filter_field_names);
+        assert!(bloom_filter_query.evaluate(&split_bloom_filters));
+
+        let query_ast = term_query("request_id", "req-3");
+        let bloom_filter_query =
+            extract_bloom_filter_query(&query_ast, &schema, &bloom_filter_field_names);
+        assert!(!bloom_filter_query.evaluate(&split_bloom_filters));
+
+        // The split has no bloom filter for the field.
+        let query_ast = term_query("user_id", "7");
+        let bloom_filter_query =
+            extract_bloom_filter_query(&query_ast, &schema, &bloom_filter_field_names);
+        assert!(bloom_filter_query.evaluate(&split_bloom_filters));
+    }
+}
@@ -20,7 +20,7 @@
 use serde::de::IgnoredAny;
 use serde::{Deserialize, Serialize};
 
-use crate::{DocMapper, DocMapping};
+use crate::{DocMapper, DocMapping, RuntimeField};
 
 /// DocMapperBuilder is here
 /// to create a valid DocMapper.
@@ -37,6 +37,10 @@ pub struct DocMapperBuilder {
     /// Default search field names.
     #[serde(default)]
     pub default_search_fields: Vec<String>,
+    /// Runtime fields.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub runtime_fields: Vec<RuntimeField>,
 
     /// Allow the "type" field separately.
     /// This is a residue from when the DocMapper was a trait.
@@ -83,6 +87,7 @@ mod tests {
         assert!(default_doc_mapper_builder.doc_mapping.tag_fields.is_empty());
         assert_eq!(default_doc_mapper_builder.doc_mapping.store_source, false);
         assert!(default_doc_mapper_builder.default_search_fields.is_empty());
+        assert!(default_doc_mapper_builder.runtime_fields.is_empty());
     }
 
     #[test]
@@ -46,7 +46,7 @@ use crate::query_builder::build_query;
 use crate::routing_expression::RoutingExpr;
 use crate::{
     Cardinality, DocMapping, DocParsingError, Mode, ModeType, NamedField, QueryParserError,
-    TokenizerEntry, WarmupInfo, DOCUMENT_SIZE_FIELD_NAME, DYNAMIC_FIELD_NAME,
+    RuntimeField, TokenizerEntry, WarmupInfo, DOCUMENT_SIZE_FIELD_NAME, DYNAMIC_FIELD_NAME,
     FIELD_PRESENCE_FIELD_NAME, SOURCE_FIELD_NAME,
 };
 
@@ -89,6 +89,10 @@ pub struct DocMapper {
     schema: Schema,
     /// List of field names used for tagging.
     tag_field_names: BTreeSet<String>,
+    /// List of field names for which zone maps are recorded.
+    zone_map_field_names: BTreeSet<String>,
+    /// List of field names for which bloom filters are built.
+    bloom_filter_field_names: BTreeSet<String>,
     /// The partition key is a DSL used to route documents
     /// into specific splits.
     partition_key: RoutingExpr,
@@ -100,6 +104,8 @@ pub struct DocMapper {
     tokenizer_entries: Vec<TokenizerEntry>,
     /// Tokenizer manager.
     tokenizer_manager: TokenizerManager,
+    /// Fields computed at query time.
+    runtime_fields: Vec<RuntimeField>,
 }
 
 fn validate_timestamp_field(
@@ -144,6 +150,8 @@ impl From<DocMapper> for DocMapperBuilder {
             field_mappings: default_doc_mapper.field_mappings.into(),
             timestamp_field: default_doc_mapper.timestamp_field_name,
             tag_fields: default_doc_mapper.tag_field_names,
+            zone_map_fields: default_doc_mapper.zone_map_field_names,
+            bloom_filter_fields: default_doc_mapper.bloom_filter_field_names,
             partition_key: partition_key_opt,
             max_num_partitions: default_doc_mapper.max_num_partitions,
             index_field_presence: default_doc_mapper.index_field_presence,
@@ -154,6 +162,7 @@ impl From<DocMapper> for DocMapperBuilder {
         Self {
             doc_mapping,
             default_search_fields: default_doc_mapper.default_search_field_names,
+            runtime_fields: default_doc_mapper.runtime_fields,
             legacy_type_tag: None,
         }
     }
@@ -241,6 +250,21 @@ impl TryFrom<DocMapperBuilder> for DocMapper {
                 .iter()
                 .any(|filter| matches!(filter, crate::TokenFilterType::LowerCaser));
             tokenizer_manager.register(&tokenizer_config_entry.name, tokenizer, does_lowercasing);
+
+            if tokenizer_config_entry.config.has_query_specific_filters() {
+                let query_tokenizer = tokenizer_config_entry
+                    .config
+                    .query_text_analyzer()
+                    .map_err(|error| {
+                        anyhow::anyhow!(
+                            "failed to build query tokenizer `{}`: {:?}",
+                            tokenizer_config_entry.name,
+                            error
+                        )
+                    })?;
+                tokenizer_manager
+                    .register_query_tokenizer(&tokenizer_config_entry.name, query_tokenizer);
+            }
             custom_tokenizer_names.insert(&tokenizer_config_entry.name);
         }
         validate_fields_tokenizers(&schema, &tokenizer_manager)?;
@@ -265,11 +289,40 @@ impl TryFrom<DocMapperBuilder> for DocMapper {
             default_search_field_names.push(default_search_field_name.clone());
         }
 
+        // Validate runtime fields
+        let mut runtime_field_names = HashSet::new();
+        for runtime_field in &builder.runtime_fields {
+            runtime_field.validate()?;
+
+            if !runtime_field_names.insert(&runtime_field.name) {
+                bail!("duplicated runtime field: `{}`", runtime_field.name);
+            }
+            if field_mappings
+                .find_field_mapping_type(&runtime_field.name)
+                .is_some()
+            {
+                bail!(
+                    "runtime field `{}` conflicts with a field of the doc mapping",
+                    runtime_field.name
+                );
+            }
+        }
+
         // Resolve tag fields
         for tag_field_name in &doc_mapping.tag_fields {
             validate_tag(tag_field_name, &schema)?;
         }
 
+        // Resolve zone map fields
+        for zone_map_field_name in &doc_mapping.zone_map_fields {
+            validate_zone_map_field(zone_map_field_name, &schema)?;
+        }
+
+        // Resolve bloom filter fields
+        for bloom_filter_field_name in &doc_mapping.bloom_filter_fields {
+            validate_bloom_filter_field(bloom_filter_field_name, &schema)?;
+        }
+
         let partition_key_expr: &str = doc_mapping.partition_key.as_deref().unwrap_or("");
         let partition_key = RoutingExpr::new(partition_key_expr).with_context(|| {
             format!("failed to interpret the partition key: `{partition_key_expr}`")
@@ -296,11 +349,14 @@ impl TryFrom<DocMapperBuilder> for DocMapper {
             field_mappings,
             concatenate_dynamic_fields,
             tag_field_names,
+            zone_map_field_names: doc_mapping.zone_map_fields,
+            bloom_filter_field_names: doc_mapping.bloom_filter_fields,
             partition_key,
             max_num_partitions: doc_mapping.max_num_partitions,
             mode: doc_mapping.mode,
             tokenizer_entries: doc_mapping.tokenizers,
             tokenizer_manager,
+            runtime_fields: builder.runtime_fields,
         })
     }
 }
@@ -357,6 +413,78 @@ fn validate_tag(tag_field_name: &str, schema: &Schema) -> Result<(), anyhow::Err
     Ok(())
 }
 
+fn validate_zone_map_field(zone_map_field_name: &str, schema: &Schema) -> anyhow::Result<()> {
+    let field = schema
+        .get_field(zone_map_field_name)
+        .with_context(|| format!("unknown zone map field: `{zone_map_field_name}`"))?;
+    let field_type = schema.get_field_entry(field).field_type();
+
+    if !matches!(
+        field_type,
+        FieldType::U64(_) | FieldType::I64(_) | FieldType::F64(_) | FieldType::Date(_)
+    ) {
+        bail!(
+            "zone maps are only allowed on `u64`, `i64`, `f64`, and `datetime` fields. (`{}` is a \
+             `{}` field)",
+            zone_map_field_name,
+            field_type.value_type().name().to_lowercase()
+        );
+    }
+    if !field_type.is_fast() {
+        bail!(
+            "zone map fields are required to be fast. (`{}` is not configured as fast)",
+            zone_map_field_name
+        );
+    }
+    Ok(())
+}
+
+fn validate_bloom_filter_field(
+    bloom_filter_field_name: &str,
+    schema: &Schema,
+) -> anyhow::Result<()> {
+    let field = schema
+        .get_field(bloom_filter_field_name)
+        .with_context(|| format!("unknown bloom filter field: `{bloom_filter_field_name}`"))?;
+    let field_entry = schema.get_field_entry(field);
+    let field_type = field_entry.field_type();
+
+    if !matches!(
+        field_type,
+        FieldType::Str(_) | FieldType::U64(_) | FieldType::I64(_) | FieldType::Bytes(_)
+    ) {
+        bail!(
+            "bloom filters are only allowed on `text`, `u64`, `i64`, and `bytes` fields. (`{}` is \
+             a `{}` field)",
+            bloom_filter_field_name,
+            field_type.value_type().name().to_lowercase()
+        );
+    }
+    if !field_entry.is_indexed() {
+        bail!(
+            "bloom filter fields are required to be indexed. (`{}` is not configured as indexed)",
+            bloom_filter_field_name
+        );
+    }
+    // Exact-match lookups on text fields are only equivalent to term lookups when the text is
+    // not tokenized.
+    if let FieldType::Str(text_options) = field_type {
+        let tokenizer_name = text_options
+            .get_indexing_options()
+            .map(|text_field_indexing| text_field_indexing.tokenizer())
+            .unwrap_or_default();
+        if tokenizer_name != RAW_TOKENIZER_NAME {
+            bail!(
+                "bloom filters on text fields require the `raw` tokenizer. (`{}` uses the `{}` \
+                 tokenizer)",
+                bloom_filter_field_name,
+                tokenizer_name
+            );
+        }
+    }
+    Ok(())
+}
+
 /// Checks that a given text/json field name has a registered tokenizer.
 fn validate_fields_tokenizers(
     schema: &Schema,
@@ -667,6 +795,38 @@ impl DocMapper {
         self.schema.clone()
     }
 
+    /// Checks that the documents of the index can be sorted by the field `sort_field_name`.
+    pub fn validate_index_sort_field(&self, sort_field_name: &str) -> anyhow::Result<()> {
+        let field = self
+            .schema
+            .get_field(sort_field_name)
+            .with_context(|| format!("unknown index sort field: `{sort_field_name}`"))?;
+        let field_type = self.schema.get_field_entry(field).field_type();
+
+        if !matches!(
+            field_type,
+            FieldType::U64(_)
+                | FieldType::I64(_)
+                | FieldType::F64(_)
+                | FieldType::Bool(_)
+                | FieldType::Date(_)
+        ) {
+            bail!(
+                "index sort is only allowed on `u64`, `i64`, `f64`, `bool`, and `datetime` \
+                 fields. (`{}` is a `{}` field)",
+                sort_field_name,
+                field_type.value_type().name().to_lowercase()
+            );
+        }
+        if !field_type.is_fast() {
+            bail!(
+                "index sort fields are required to be fast. (`{}` is not configured as fast)",
+                sort_field_name
+            );
+        }
+        Ok(())
+    }
+
     /// Returns the timestamp field name.
     pub fn timestamp_field_name(&self) -> Option<&str> {
         self.timestamp_field_name.as_deref()
@@ -697,6 +857,63 @@ impl DocMapper {
         self.tag_field_names.clone()
     }
 
+    /// Returns the zone map `NamedField`s on the current schema.
+    /// Returns an error if a zone map field is not found in this schema.
+    pub fn zone_map_named_fields(&self) -> anyhow::Result<Vec<NamedField>> {
+        let index_schema = self.schema();
+        self.zone_map_field_names
+            .iter()
+            .map(|field_name| {
+                index_schema
+                    .get_field(field_name)
+                    .context(format!("field `{field_name}` must exist in the schema"))
+                    .map(|field| NamedField {
+                        name: field_name.clone(),
+                        field,
+                        field_type: index_schema.get_field_entry(field).field_type().clone(),
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()
+    }
+
+    /// Returns the names of the fields for which zone maps are recorded.
+    pub fn zone_map_field_names(&self) -> &BTreeSet<String> {
+        &self.zone_map_field_names
+    }
+
+    /// Returns the bloom filter `NamedField`s on the current schema.
+    /// Returns an error if a bloom filter field is not found in this schema.
+    pub fn bloom_filter_named_fields(&self) -> anyhow::Result<Vec<NamedField>> {
+        let index_schema = self.schema();
+        self.bloom_filter_field_names
+            .iter()
+            .map(|field_name| {
+                index_schema
+                    .get_field(field_name)
+                    .context(format!("field `{field_name}` must exist in the schema"))
+                    .map(|field| NamedField {
+                        name: field_name.clone(),
+                        field,
+                        field_type: index_schema.get_field_entry(field).field_type().clone(),
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()
+    }
+
+    /// Returns the names of the fields for which bloom filters are built.
+    pub fn bloom_filter_field_names(&self) -> &BTreeSet<String> {
+        &self.bloom_filter_field_names
+    }
+
+    /// Returns whether the field is a concatenate field, whose values are derived from the
+    /// values of other fields of the documents.
+    pub fn is_concatenate_field(&self, field_name: &str) -> bool {
+        matches!(
+            self.field_mappings.find_field_mapping_type(field_name),
+            Some(FieldMappingType::Concatenate(_))
+        )
+    }
+
     /// Returns the maximum number of partitions.
     pub fn max_num_partitions(&self) -> NonZeroU32 {
         self.max_num_partitions
@@ -706,6 +923,37 @@ impl DocMapper {
     pub fn tokenizer_manager(&self) -> &TokenizerManager {
         &self.tokenizer_manager
     }
+
+    /// Returns the fields computed at query time.
+    pub fn runtime_fields(&self) -> &[RuntimeField] {
+        &self.runtime_fields
+    }
+
+    /// Returns a copy of this doc mapper with additional runtime fields, typically defined in a
+    /// search request. They take precedence over the runtime fields with the same name.
+    pub fn with_runtime_fields(&self, runtime_fields: Vec<RuntimeField>) -> anyhow::Result<Self> {
+        let mut builder = DocMapperBuilder::from(self.clone());
+        builder.runtime_fields.retain(|existing_runtime_field| {
+            runtime_fields
+                .iter()
+                .all(|runtime_field| runtime_field.name != existing_runtime_field.name)
+        });
+        builder.runtime_fields.extend(runtime_fields);
+        builder.try_build()
+    }
+
+    /// Returns the doc mapper of the ephemeral index in which the documents are re-indexed along
+    /// with the values of their runtime fields. In this doc mapper, the runtime fields are regular
+    /// fast fields.
+    pub fn runtime_doc_mapper(&self) -> anyhow::Result<Self> {
+        let mut builder = DocMapperBuilder::from(self.clone());
+
+        for runtime_field in std::mem::take(&mut builder.runtime_fields) {
+            let field_mapping_entry = runtime_field.field_mapping_entry()?;
+            builder.doc_mapping.field_mappings.push(field_mapping_entry);
+        }
+        builder.try_build()
+    }
 }
 
 #[cfg(test)]
@@ -724,8 +972,8 @@ mod tests {
     use super::DocMapper;
     use crate::doc_mapper::field_mapping_entry::{DEFAULT_TOKENIZER_NAME, RAW_TOKENIZER_NAME};
     use crate::{
-        DocMapperBuilder, DocParsingError, DOCUMENT_SIZE_FIELD_NAME, DYNAMIC_FIELD_NAME,
-        FIELD_PRESENCE_FIELD_NAME, SOURCE_FIELD_NAME,
+        DocMapperBuilder, DocParsingError, FieldMappingType, RuntimeField, RuntimeFieldType,
+        DOCUMENT_SIZE_FIELD_NAME, DYNAMIC_FIELD_NAME, FIELD_PRESENCE_FIELD_NAME, SOURCE_FIELD_NAME,
     };
 
     fn example_json_doc_value() -> JsonValue {
@@ -1470,6 +1718,175 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_build_doc_mapper_with_zone_map_fields() {
+        let doc_mapper = r#"{
+            "default_search_fields": [],
+            "zone_map_fields": ["status_code", "received_at"],
+            "field_mappings": [
+                {
+                    "name": "status_code",
+                    "type": "u64",
+                    "fast": true
+                },
+                {
+                    "name": "received_at",
+                    "type": "datetime",
+                    "fast": true
+                }
+            ]
+        }"#;
+        let doc_mapper = serde_json::from_str::<DocMapper>(doc_mapper).unwrap();
+        let zone_map_fields: Vec<String> = doc_mapper
+            .zone_map_named_fields()
+            .unwrap()
+            .into_iter()
+            .map(|named_field| named_field.name)
+            .collect();
+        assert_eq!(zone_map_fields, ["received_at", "status_code"]);
+    }
+
+    #[test]
+    fn test_fail_to_build_doc_mapper_with_wrong_zone_map_fields() {
+        let doc_mapper_one = r#"{
+            "default_search_fields": [],
+            "zone_map_fields": ["status_code"],
+            "field_mappings": [
+                {
+                    "name": "status_code",
+                    "type": "u64"
+                }
+            ]
+        }"#;
+        assert_eq!(
+            serde_json::from_str::<DocMapperBuilder>(doc_mapper_one)
+                .unwrap()
+                .try_build()
+                .unwrap_err()
+                .to_string(),
+            "zone map fields are required to be fast. (`status_code` is not configured as fast)",
+        );
+
+        let doc_mapper_two = r#"{
+            "default_search_fields": [],
+            "zone_map_fields": ["service"],
+            "field_mappings": [
+                {
+                    "name": "service",
+                    "type": "text",
+                    "tokenizer": "raw",
+                    "fast": true
+                }
+            ]
+        }"#;
+        assert_eq!(
+            serde_json::from_str::<DocMapperBuilder>(doc_mapper_two)
+                .unwrap()
+                .try_build()
+                .unwrap_err()
+                .to_string(),
+            "zone maps are only allowed on `u64`, `i64`, `f64`, and `datetime` fields. (`service` \
+             is a `str` field)",
+        );
+    }
+
+    #[test]
+    fn test_build_doc_mapper_with_bloom_filter_fields() {
+        let doc_mapper = r#"{
+            "default_search_fields": [],
+            "bloom_filter_fields": ["trace_id", "request_id", "user_id"],
+            "field_mappings": [
+                {
+                    "name": "trace_id",
+                    "type": "bytes",
+                    "input_format": "hex"
+                },
+                {
+                    "name": "request_id",
+                    "type": "text",
+                    "tokenizer": "raw"
+                },
+                {
+                    "name": "user_id",
+                    "type": "u64"
+                }
+            ]
+        }"#;
+        let doc_mapper = serde_json::from_str::<DocMapper>(doc_mapper).unwrap();
+        let bloom_filter_fields: Vec<String> = doc_mapper
+            .bloom_filter_named_fields()
+            .unwrap()
+            .into_iter()
+            .map(|named_field| named_field.name)
+            .collect();
+        assert_eq!(bloom_filter_fields, ["request_id", "trace_id", "user_id"]);
+    }
+
+    #[test]
+    fn test_fail_to_build_doc_mapper_with_wrong_bloom_filter_fields() {
+        let doc_mapper_one = r#"{
+            "default_search_fields": [],
+            "bloom_filter_fields": ["request_id"],
+            "field_mappings": [
+                {
+                    "name": "request_id",
+                    "type": "text"
+                }
+            ]
+        }"#;
+        assert_eq!(
+            serde_json::from_str::<DocMapperBuilder>(doc_mapper_one)
+                .unwrap()
+                .try_build()
+                .unwrap_err()
+                .to_string(),
+            "bloom filters on text fields require the `raw` tokenizer. (`request_id` uses the \
+             `default` tokenizer)",
+        );
+
+        let doc_mapper_two = r#"{
+            "default_search_fields": [],
+            "bloom_filter_fields": ["user_id"],
+            "field_mappings": [
+                {
+                    "name": "user_id",
+                    "type": "u64",
+                    "indexed": false,
+                    "fast": true
+                }
+            ]
+        }"#;
+        assert_eq!(
+            serde_json::from_str::<DocMapperBuilder>(doc_mapper_two)
+                .unwrap()
+                .try_build()
+                .unwrap_err()
+                .to_string(),
+            "bloom filter fields are required to be indexed. (`user_id` is not configured as \
+             indexed)",
+        );
+
+        let doc_mapper_three = r#"{
+            "default_search_fields": [],
+            "bloom_filter_fields": ["latency_ms"],
+            "field_mappings": [
+                {
+                    "name": "latency_ms",
+                    "type": "f64"
+                }
+            ]
+        }"#;
+        assert_eq!(
+            serde_json::from_str::<DocMapperBuilder>(doc_mapper_three)
+                .unwrap()
+                .try_build()
+                .unwrap_err()
+                .to_string(),
+            "bloom filters are only allowed on `text`, `u64`, `i64`, and `bytes` fields. \
+             (`latency_ms` is a `f64` field)",
+        );
+    }
+
     // See #1132
     #[test]
     fn test_by_default_store_source_is_false_and_fields_are_stored_individually() {
@@ -2344,6 +2761,51 @@ mod tests {
         assert_eq!(token_stream.next().unwrap().text, "hello");
     }
 
+    #[test]
+    fn test_build_doc_mapper_tokenizer_manager_with_language_filters() {
+        let mapper = serde_json::from_str::<DocMapper>(
+            r#"{
+            "tokenizers": [
+                {
+                    "name": "german",
+                    "type": "simple",
+                    "filters": [
+                        "lower_caser",
+                        {"stop_words": {"language": "german", "words": ["bitte"]}},
+                        {"synonyms": {"synonyms": ["katze, mieze"], "query_time": true}},
+                        {"stemmer": {"language": "german"}}
+                    ]
+                }
+            ],
+            "field_mappings": [
+                {
+                    "name": "my_text",
+                    "type": "text",
+                    "tokenizer": "german"
+                }
+            ]
+        }"#,
+        )
+        .unwrap();
+        let tokenizer_manager = mapper.tokenizer_manager();
+
+        let mut tokenizer = tokenizer_manager.get_tokenizer("german").unwrap();
+        let mut token_stream = tokenizer.token_stream("Bitte die Katzen und die Katze");
+        let mut tokens = Vec::new();
+        while let Some(token) = token_stream.next() {
+            tokens.push(token.text.clone());
+        }
+        assert_eq!(tokens, ["katz", "katz"]);
+
+        let mut query_tokenizer = tokenizer_manager.get_query_tokenizer("german").unwrap();
+        let mut token_stream = query_tokenizer.token_stream("Katze");
+        let mut tokens = Vec::new();
+        while let Some(token) = token_stream.next() {
+            tokens.push(token.text.clone());
+        }
+        assert_eq!(tokens, ["katz", "miez"]);
+    }
+
     #[test]
     fn test_build_doc_mapper_with_custom_invalid_regex_tokenizer() {
         let mapper_builder = serde_json::from_str::<DocMapperBuilder>(
@@ -2505,4 +2967,107 @@ mod tests {
 
         assert_eq!(new_mapper.doc_to_json(named_doc.0).unwrap(), doc);
     }
+
+    #[test]
+    fn test_nested_field_doc_round_trip() {
+        use tantivy::Document;
+
+        let doc_mapper: DocMapper = serde_json::from_str(
+            r#"{
+            "field_mappings": [
+                {
+                    "name": "items",
+                    "type": "nested",
+                    "field_mappings": [
+                        {"name": "sku", "type": "text", "tokenizer": "raw"},
+                        {"name": "qty", "type": "u64"}
+                    ]
+                }
+            ]
+        }"#,
+        )
+        .unwrap();
+        let schema = doc_mapper.schema();
+        let nested_field = schema.get_field("_nested.items").unwrap();
+        assert!(schema.get_field_entry(nested_field).is_fast());
+        assert!(matches!(
+            doc_mapper.field_mappings.find_field_mapping_type("items"),
+            Some(FieldMappingType::Nested(_))
+        ));
+
+        let JsonValue::Object(doc) = json!({
+            "items": [
+                {"sku": "A", "qty": 1},
+                {"sku": "B", "qty": 10}
+            ]
+        }) else {
+            panic!();
+        };
+        let (_, tantivy_doc) = doc_mapper.doc_from_json_obj(doc.clone(), 0).unwrap();
+        assert_eq!(tantivy_doc.get_all(nested_field).count(), 2);
+        let sku_field = schema.get_field("items.sku").unwrap();
+        assert_eq!(tantivy_doc.get_all(sku_field).count(), 2);
+
+        let named_doc = tantivy_doc.to_named_doc(&schema);
+        assert_eq!(doc_mapper.doc_to_json(named_doc.0).unwrap(), doc);
+
+        doc_mapper
+            .doc_from_json_str(r#"{"items": {"sku": "A", "qty": 1}}"#)
+            .unwrap();
+        let error = doc_mapper
+            .doc_from_json_str(r#"{"items": ["A", "B"]}"#)
+            .unwrap_err();
+        assert!(matches!(error, DocParsingError::ValueError(_, _)));
+    }
+
+    #[test]
+    fn test_doc_mapper_runtime_fields() {
+        let doc_mapper: DocMapper = serde_json::from_value(json!({
+            "field_mappings": [{"name": "message", "type": "text"}],
+            "runtime_fields": [
+                {
+                    "name": "status",
+                    "type": "u64",
+                    "script": "parse_int!(parse_regex!(.message, r'status=(?P<status>\\d+)').status)"
+                }
+            ]
+        }))
+        .unwrap();
+        assert_eq!(doc_mapper.runtime_fields().len(), 1);
+        assert!(doc_mapper.schema().get_field("status").is_err());
+
+        let runtime_doc_mapper = doc_mapper.runtime_doc_mapper().unwrap();
+        assert!(runtime_doc_mapper.runtime_fields().is_empty());
+
+        let runtime_schema = runtime_doc_mapper.schema();
+        let status_field = runtime_schema.get_field("status").unwrap();
+        let status_field_entry = runtime_schema.get_field_entry(status_field);
+        assert!(status_field_entry.is_fast());
+        assert_eq!(status_field_entry.field_type().value_type(), Type::U64);
+
+        let doc_mapper = doc_mapper
+            .with_runtime_fields(vec![RuntimeField {
+                name: "status".to_string(),
+                field_type: RuntimeFieldType::Text,
+                script: ".status".to_string(),
+            }])
+            .unwrap();
+        assert_eq!(doc_mapper.runtime_fields().len(), 1);
+        assert_eq!(
+            doc_mapper.runtime_fields()[0].field_type,
+            RuntimeFieldType::Text
+        );
+
+        let error = doc_mapper
+            .with_runtime_fields(vec![RuntimeField {
+                name: "message".to_string(),
+                field_type: RuntimeFieldType::Text,
+                script: ".message".to_string(),
+            }])
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "runtime field `message` conflicts with a field of the doc mapping"
+        );
+    }
 }
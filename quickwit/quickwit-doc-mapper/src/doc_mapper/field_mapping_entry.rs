@@ -23,6 +23,7 @@ use std::convert::TryFrom;
 use anyhow::bail;
 use base64::prelude::{Engine, BASE64_STANDARD};
 use once_cell::sync::Lazy;
+use quickwit_common::shared_consts::NESTED_FIELD_PREFIX;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
@@ -286,7 +287,7 @@ impl QuickwitTextTokenizer {
     pub const fn from_static(name: &'static str) -> Self {
         Self(Cow::Borrowed(name))
     }
-    pub(crate) fn name(&self) -> &str {
+    pub fn name(&self) -> &str {
         &self.0
     }
     pub fn raw() -> Self {
@@ -736,6 +737,13 @@ fn deserialize_mapping_type(
             }
             return Ok(FieldMappingType::Object(object_options));
         }
+        QuickwitFieldType::Nested => {
+            let object_options: QuickwitObjectOptions = serde_json::from_value(json)?;
+            if object_options.field_mappings.is_empty() {
+                anyhow::bail!("nested type must have at least one field mapping");
+            }
+            return Ok(FieldMappingType::Nested(object_options));
+        }
         QuickwitFieldType::Concatenate => {
             let concatenate_options: QuickwitConcatenateOptions = serde_json::from_value(json)?;
             if concatenate_options.concatenate_fields.is_empty()
@@ -837,7 +845,9 @@ fn typed_mapping_to_json_params(
         FieldMappingType::IpAddr(options, _) => serialize_to_map(&options),
         FieldMappingType::DateTime(date_time_options, _) => serialize_to_map(&date_time_options),
         FieldMappingType::Json(json_options, _) => serialize_to_map(&json_options),
-        FieldMappingType::Object(object_options) => serialize_to_map(&object_options),
+        FieldMappingType::Object(object_options) | FieldMappingType::Nested(object_options) => {
+            serialize_to_map(&object_options)
+        }
         FieldMappingType::Concatenate(concatenate_options) => {
             serialize_to_map(&concatenate_options)
         }
@@ -871,7 +881,9 @@ pub const FIELD_MAPPING_NAME_PATTERN: &str = r"^[@$_\-a-zA-Z][@$_/\.\-a-zA-Z0-9]
 ///   hyphens `-`, underscores `_`, at `@` and dollar `$` signs;
 /// - must not start with a dot or a digit;
 /// - must be different from Quickwit's reserved field mapping names `_source`, `_dynamic`,
-///   `_field_presence`;
+///   `_field_presence`, `_doc_length`, `_nested`;
+/// - must not start with `_nested.`, which prefixes the fields storing the elements of nested
+///   fields;
 /// - must not be longer than 255 characters.
 pub fn validate_field_mapping_name(field_mapping_name: &str) -> anyhow::Result<()> {
     static FIELD_MAPPING_NAME_PTN: Lazy<Regex> =
@@ -884,6 +896,11 @@ pub fn validate_field_mapping_name(field_mapping_name: &str) -> anyhow::Result<(
             QW_RESERVED_FIELD_NAMES.join(", "),
         );
     }
+    if field_mapping_name.starts_with(NESTED_FIELD_PREFIX) {
+        bail!(
+            "field name `{field_mapping_name}` is reserved. field names starting with              `{NESTED_FIELD_PREFIX}` are reserved for Quickwit internal usage"
+        );
+    }
     if FIELD_MAPPING_NAME_PTN.is_match(field_mapping_name) {
         return Ok(());
     }
@@ -954,11 +971,20 @@ mod tests {
             .unwrap_err()
             .to_string()
             .contains("are reserved for Quickwit"));
+        assert!(validate_field_mapping_name("_nested")
+            .unwrap_err()
+            .to_string()
+            .contains("are reserved for Quickwit"));
+        assert!(validate_field_mapping_name("_nested.items")
+            .unwrap_err()
+            .to_string()
+            .contains("are reserved for Quickwit"));
         assert!(validate_field_mapping_name("my-field!")
             .unwrap_err()
             .to_string()
             .contains("illegal characters"));
         assert!(validate_field_mapping_name("_my_field").is_ok());
+        assert!(validate_field_mapping_name("_nested_field").is_ok());
         assert!(validate_field_mapping_name("-my-field").is_ok());
         assert!(validate_field_mapping_name("my-field").is_ok());
         assert!(validate_field_mapping_name("my.field").is_ok());
@@ -1270,6 +1296,56 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_deserialize_nested_mapping_entry() {
+        let mapping_entry = serde_json::from_str::<FieldMappingEntry>(
+            r#"
+            {
+            "name": "items",
+            "type": "nested",
+            "field_mappings": [
+                {
+                    "name": "sku",
+                    "type": "text",
+                    "tokenizer": "raw"
+                },
+                {
+                    "name": "qty",
+                    "type": "u64"
+                }
+            ]
+            }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(mapping_entry.name, "items");
+        let FieldMappingType::Nested(options) = &mapping_entry.mapping_type else {
+            panic!("wrong property type");
+        };
+        assert_eq!(options.field_mappings.len(), 2);
+        let mapping_entry_json = serde_json::to_value(&mapping_entry).unwrap();
+        assert_eq!(mapping_entry_json["type"], "nested");
+        assert_eq!(
+            serde_json::from_value::<FieldMappingEntry>(mapping_entry_json).unwrap(),
+            mapping_entry
+        );
+
+        let error = serde_json::from_str::<FieldMappingEntry>(
+            r#"
+            {
+                "name": "items",
+                "type": "nested",
+                "field_mappings": []
+            }
+            "#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "error while parsing field `items`: nested type must have at least one field mapping"
+        );
+    }
+
     #[test]
     fn test_deserialize_mapping_with_unknown_type() {
         let result = serde_json::from_str::<FieldMappingEntry>(
@@ -24,6 +24,7 @@ use std::str::FromStr;
 
 use anyhow::bail;
 use itertools::Itertools;
+use quickwit_common::shared_consts::NESTED_FIELD_PREFIX;
 use serde_json::Value as JsonValue;
 use serde_json_borrow::{Map as BorrowedJsonMap, Value as BorrowedJsonValue};
 use tantivy::schema::{
@@ -734,6 +735,9 @@ impl NumVal for f64 {
 pub(crate) struct MappingNode {
     pub branches: fnv::FnvHashMap<String, MappingTree>,
     branches_order: Vec<String>,
+    /// For nested fields, the bytes field storing the elements of the field, one JSON encoded
+    /// value per element.
+    nested_field: Option<Field>,
 }
 
 fn get_or_insert_path<'a>(
@@ -879,24 +883,113 @@ impl MappingNode {
         Ok(())
     }
 
+    /// Adds the elements of a nested field to the document.
+    ///
+    /// Each element is stored as a whole in the nested bytes field, and its fields are also
+    /// indexed in the regular (flattened) fields, so that they can be searched and aggregated
+    /// like the fields of an object array.
+    fn nested_doc_from_json(
+        &self,
+        json_value: JsonValue,
+        mode: ModeType,
+        document: &mut Document,
+        path: &mut Vec<String>,
+        dynamic_json_obj: &mut serde_json::Map<String, JsonValue>,
+    ) -> Result<(), DocParsingError> {
+        let Some(nested_field) = self.nested_field else {
+            return Ok(());
+        };
+        let json_elements = match json_value {
+            JsonValue::Array(json_elements) => json_elements,
+            JsonValue::Object(_) => vec![json_value],
+            JsonValue::Null => Vec::new(),
+            _ => {
+                return Err(DocParsingError::ValueError(
+                    path.join("."),
+                    format!("expected an array of JSON objects, got {json_value}"),
+                ));
+            }
+        };
+        for json_element in json_elements {
+            let JsonValue::Object(json_obj) = json_element else {
+                return Err(DocParsingError::ValueError(
+                    path.join("."),
+                    format!("expected an array of JSON objects, got {json_element}"),
+                ));
+            };
+            let element_bytes =
+                serde_json::to_vec(&json_obj).expect("serializing a JSON object should never fail");
+            document.add_bytes(nested_field, &element_bytes);
+            self.doc_from_json(json_obj, mode, document, path, dynamic_json_obj)?;
+        }
+        Ok(())
+    }
+
     pub fn populate_json<'a>(
         &'a self,
         named_doc: &mut BTreeMap<String, Vec<TantivyValue>>,
         field_path: &mut Vec<&'a str>,
         doc_json: &mut serde_json::Map<String, JsonValue>,
     ) {
+        if self.nested_field.is_some() && self.populate_nested_json(named_doc, field_path, doc_json)
+        {
+            return;
+        }
         for (field_name, field_mapping) in &self.branches {
             field_path.push(field_name);
             field_mapping.populate_json(named_doc, field_path, doc_json);
             field_path.pop();
         }
     }
+
+    /// Rebuilds the array of elements of a nested field from its stored elements.
+    ///
+    /// Returns false if the elements were not stored, in which case the field is rebuilt from
+    /// its flattened fields, like an object.
+    fn populate_nested_json<'a>(
+        &'a self,
+        named_doc: &mut BTreeMap<String, Vec<TantivyValue>>,
+        field_path: &mut Vec<&'a str>,
+        doc_json: &mut serde_json::Map<String, JsonValue>,
+    ) -> bool {
+        let nested_field_name = format!(
+            "{NESTED_FIELD_PREFIX}{}",
+            field_name_for_field_path(field_path)
+        );
+        let Some(element_values) = named_doc.remove(&nested_field_name) else {
+            return false;
+        };
+        let json_elements: Vec<JsonValue> = element_values
+            .into_iter()
+            .filter_map(|element_value| {
+                let TantivyValue::Bytes(element_bytes) = element_value else {
+                    return None;
+                };
+                serde_json::from_slice(&element_bytes).ok()
+            })
+            .collect();
+        // The flattened values of the elements are redundant with the stored elements, we still
+        // need to remove them from the named document.
+        let mut flattened_doc_json = serde_json::Map::new();
+        for (field_name, field_mapping) in &self.branches {
+            field_path.push(field_name);
+            field_mapping.populate_json(named_doc, field_path, &mut flattened_doc_json);
+            field_path.pop();
+        }
+        insert_json_val(field_path, JsonValue::Array(json_elements), doc_json);
+        true
+    }
 }
 
 impl From<MappingTree> for FieldMappingType {
     fn from(mapping_tree: MappingTree) -> Self {
         match mapping_tree {
             MappingTree::Leaf(leaf) => leaf.into(),
+            MappingTree::Node(node) if node.nested_field.is_some() => {
+                FieldMappingType::Nested(QuickwitObjectOptions {
+                    field_mappings: node.into(),
+                })
+            }
             MappingTree::Node(node) => FieldMappingType::Object(QuickwitObjectOptions {
                 field_mappings: node.into(),
             }),
@@ -946,6 +1039,21 @@ impl MappingTree {
             MappingTree::Node(mapping_node) => {
                 if let Some(json_obj) = json_value.as_object() {
                     mapping_node.validate_from_json(json_obj, strict_mode, field_path)
+                } else if mapping_node.nested_field.is_some() && json_value.is_null() {
+                    Ok(())
+                } else if let (Some(_), BorrowedJsonValue::Array(json_elements)) =
+                    (mapping_node.nested_field, json_value)
+                {
+                    for json_element in json_elements {
+                        let Some(json_obj) = json_element.as_object() else {
+                            return Err(DocParsingError::ValueError(
+                                field_path.join("."),
+                                format!("expected an array of JSON objects, got {json_value}"),
+                            ));
+                        };
+                        mapping_node.validate_from_json(json_obj, strict_mode, field_path)?;
+                    }
+                    Ok(())
                 } else {
                     Err(DocParsingError::ValueError(
                         field_path.join("."),
@@ -968,6 +1076,8 @@ impl MappingTree {
             MappingTree::Leaf(mapping_leaf) => {
                 mapping_leaf.doc_from_json(json_value, document, path)
             }
+            MappingTree::Node(mapping_node) if mapping_node.nested_field.is_some() => mapping_node
+                .nested_doc_from_json(json_value, mode, document, path, dynamic_json_obj),
             MappingTree::Node(mapping_node) => {
                 if let JsonValue::Object(json_obj) = json_value {
                     mapping_node.doc_from_json(json_obj, mode, document, path, dynamic_json_obj)
@@ -1322,6 +1432,25 @@ fn build_mapping_from_field_type<'a>(
                 concatenate_dynamic_fields,
             ))
         }
+        FieldMappingType::Nested(entries) => {
+            let MappingNodeRoot {
+                mut field_mappings,
+                concatenate_dynamic_fields,
+            } = build_mapping_tree_from_entries(
+                &entries.field_mappings,
+                field_path,
+                schema_builder,
+            )?;
+            let nested_field_name = format!("{NESTED_FIELD_PREFIX}{field_name}");
+            let nested_field_options = BytesOptions::default().set_fast().set_stored();
+            let nested_field =
+                schema_builder.add_bytes_field(&nested_field_name, nested_field_options);
+            field_mappings.nested_field = Some(nested_field);
+            Ok((
+                MappingTree::Node(field_mappings),
+                concatenate_dynamic_fields,
+            ))
+        }
         FieldMappingType::Concatenate(_) => {
             bail!("Concatenate shouldn't reach build_mapping_from_field_type: this is a bug")
         }
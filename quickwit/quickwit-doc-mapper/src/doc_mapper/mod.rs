@@ -24,6 +24,7 @@ mod field_mapping_entry;
 mod field_mapping_type;
 mod field_presence;
 mod mapping_tree;
+mod runtime_field;
 mod tantivy_val_to_json;
 mod tokenizer_entry;
 
@@ -45,12 +46,15 @@ pub(crate) use field_mapping_entry::{
 #[cfg(test)]
 pub(crate) use field_mapping_entry::{QuickwitNumericOptions, QuickwitTextOptions};
 pub use field_mapping_type::FieldMappingType;
+pub use runtime_field::{RuntimeField, RuntimeFieldType};
 use serde_json::Value as JsonValue;
 use tantivy::schema::{Field, FieldType};
 use tantivy::Term;
 pub use tokenizer_entry::{analyze_text, TokenizerConfig, TokenizerEntry};
 pub(crate) use tokenizer_entry::{
-    NgramTokenizerOption, RegexTokenizerOption, TokenFilterType, TokenizerType,
+    NgramTokenizerOption, RegexTokenizerOption, StemmerFilterOption, StopWordsFilterOption,
+    SynonymsFilterOption, TokenFilterLanguage, TokenFilterType, TokenizerType,
+    WordDelimiterFilterOption,
 };
 
 /// Function used with serde to initialize boolean value at true if there is no value in json.
@@ -0,0 +1,105 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::field_mapping_entry::validate_field_mapping_name;
+use super::FieldMappingEntry;
+
+/// A `RuntimeField` defines a field that is not extracted at indexing time but computed at query
+/// time, for each document, by evaluating a VRL script over the stored document.
+///
+/// Runtime fields can be used in filters, sorts, and aggregations like any other fast field.
+/// However, they cannot benefit from the inverted index nor from the columnar storage: every
+/// document matching the rest of the query is fetched from the doc store and run through the
+/// script, which is orders of magnitude slower than querying an indexed field.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Hash, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RuntimeField {
+    /// Field name.
+    pub name: String,
+    /// Type of the values returned by the script.
+    #[serde(rename = "type")]
+    pub field_type: RuntimeFieldType,
+    /// VRL script evaluated with the stored document as target (`.`). The value of the last
+    /// expression is the value of the field.
+    pub script: String,
+}
+
+/// Type of the values of a runtime field.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Hash, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RuntimeFieldType {
+    /// Text, indexed as a single token like a keyword.
+    Text,
+    /// Signed integer.
+    I64,
+    /// Unsigned integer.
+    U64,
+    /// Floating point number.
+    F64,
+    /// Boolean.
+    Bool,
+    /// Datetime, parsed from RFC 3339 strings or Unix timestamps.
+    Datetime,
+}
+
+impl RuntimeField {
+    pub(crate) fn validate(&self) -> anyhow::Result<()> {
+        validate_field_mapping_name(&self.name)?;
+
+        if self.name.contains('.') {
+            bail!(
+                "runtime field name `{}` must not contain a dot `.`",
+                self.name
+            );
+        }
+        if self.script.trim().is_empty() {
+            bail!("the script of runtime field `{}` is empty", self.name);
+        }
+        Ok(())
+    }
+
+    /// Returns the field mapping used to index the values of the runtime field in the ephemeral
+    /// index built at query time.
+    pub(crate) fn field_mapping_entry(&self) -> anyhow::Result<FieldMappingEntry> {
+        let field_mapping_json = match self.field_type {
+            RuntimeFieldType::Text => json!({
+                "name": self.name,
+                "type": "text",
+                "tokenizer": "raw",
+                "fast": true,
+            }),
+            RuntimeFieldType::I64 => json!({"name": self.name, "type": "i64", "fast": true}),
+            RuntimeFieldType::U64 => json!({"name": self.name, "type": "u64", "fast": true}),
+            RuntimeFieldType::F64 => json!({"name": self.name, "type": "f64", "fast": true}),
+            RuntimeFieldType::Bool => json!({"name": self.name, "type": "bool", "fast": true}),
+            RuntimeFieldType::Datetime => json!({
+                "name": self.name,
+                "type": "datetime",
+                "input_formats": ["rfc3339", "unix_timestamp"],
+                "fast": true,
+            }),
+        };
+        let field_mapping_entry = serde_json::from_value(field_mapping_json)?;
+        Ok(field_mapping_entry)
+    }
+}
@@ -17,12 +17,16 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::HashMap;
+
 use anyhow::Context;
-use quickwit_query::{CodeTokenizer, DEFAULT_REMOVE_TOKEN_LENGTH};
+use quickwit_query::{
+    CodeTokenizer, SynonymFilter, WordDelimiterFilter, DEFAULT_REMOVE_TOKEN_LENGTH,
+};
 use serde::{Deserialize, Serialize};
 use tantivy::tokenizer::{
-    AsciiFoldingFilter, LowerCaser, NgramTokenizer, RegexTokenizer, RemoveLongFilter,
-    SimpleTokenizer, TextAnalyzer, Token,
+    AsciiFoldingFilter, Language, LowerCaser, NgramTokenizer, RegexTokenizer, RemoveLongFilter,
+    SimpleTokenizer, Stemmer, StopWordFilter, TextAnalyzer, Token,
 };
 
 /// A `TokenizerEntry` defines a custom tokenizer with its name and configuration.
@@ -35,6 +39,55 @@ pub struct TokenizerEntry {
     pub(crate) config: TokenizerConfig,
 }
 
+impl TokenizerEntry {
+    /// Returns the paths of the word list files referenced by the filters of the tokenizer,
+    /// relative to the index URI.
+    pub fn word_list_paths(&self) -> Vec<String> {
+        self.config
+            .filters
+            .iter()
+            .filter_map(|filter| match filter {
+                TokenFilterType::StopWords(options) => options.words_path.clone(),
+                TokenFilterType::Synonyms(options) => options.synonyms_path.clone(),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Inlines the content of the word list files referenced by the filters of the tokenizer,
+    /// given the content of each file keyed by path. The files contain one word or synonym rule
+    /// per line, empty lines and lines starting with `#` are ignored.
+    pub fn inline_word_lists(
+        &mut self,
+        word_list_files: &HashMap<String, String>,
+    ) -> anyhow::Result<()> {
+        for filter in &mut self.config.filters {
+            let (words, words_path_opt) = match filter {
+                TokenFilterType::StopWords(options) => {
+                    (&mut options.words, &mut options.words_path)
+                }
+                TokenFilterType::Synonyms(options) => {
+                    (&mut options.synonyms, &mut options.synonyms_path)
+                }
+                _ => continue,
+            };
+            let Some(words_path) = words_path_opt.take() else {
+                continue;
+            };
+            let word_list_file = word_list_files
+                .get(&words_path)
+                .with_context(|| format!("word list file `{words_path}` not found"))?;
+            let file_words = word_list_file
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_string);
+            words.extend(file_words);
+        }
+        Ok(())
+    }
+}
+
 /// Tokenizer configuration.
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Hash, utoipa::ToSchema)]
 pub struct TokenizerConfig {
@@ -47,6 +100,23 @@ pub struct TokenizerConfig {
 impl TokenizerConfig {
     /// Build a `TextAnalyzer` from a `TokenizerConfig`.
     pub fn text_analyzer(&self) -> anyhow::Result<TextAnalyzer> {
+        self.build_text_analyzer(false)
+    }
+
+    /// Build the `TextAnalyzer` used to tokenize queries from a `TokenizerConfig`. It differs from
+    /// the indexing one only if some filters are applied at query time.
+    pub fn query_text_analyzer(&self) -> anyhow::Result<TextAnalyzer> {
+        self.build_text_analyzer(true)
+    }
+
+    /// Returns true if the tokenizer tokenizes queries and documents differently.
+    pub fn has_query_specific_filters(&self) -> bool {
+        self.filters
+            .iter()
+            .any(|filter| !filter.applies_at(true) || !filter.applies_at(false))
+    }
+
+    fn build_text_analyzer(&self, query_time: bool) -> anyhow::Result<TextAnalyzer> {
         let mut text_analyzer_builder = match &self.tokenizer_type {
             TokenizerType::Simple => TextAnalyzer::builder(SimpleTokenizer::default()).dynamic(),
             #[cfg(any(test, feature = "multilang"))]
@@ -67,15 +137,32 @@ impl TokenizerConfig {
             }
         };
         for filter in &self.filters {
-            match filter.tantivy_token_filter_enum() {
-                TantivyTokenFilterEnum::RemoveLong(token_filter) => {
-                    text_analyzer_builder = text_analyzer_builder.filter_dynamic(token_filter);
-                }
-                TantivyTokenFilterEnum::LowerCaser(token_filter) => {
-                    text_analyzer_builder = text_analyzer_builder.filter_dynamic(token_filter);
-                }
-                TantivyTokenFilterEnum::AsciiFolding(token_filter) => {
-                    text_analyzer_builder = text_analyzer_builder.filter_dynamic(token_filter);
+            if !filter.applies_at(query_time) {
+                continue;
+            }
+            for token_filter in filter.tantivy_token_filter_enums()? {
+                match token_filter {
+                    TantivyTokenFilterEnum::RemoveLong(token_filter) => {
+                        text_analyzer_builder = text_analyzer_builder.filter_dynamic(token_filter);
+                    }
+                    TantivyTokenFilterEnum::LowerCaser(token_filter) => {
+                        text_analyzer_builder = text_analyzer_builder.filter_dynamic(token_filter);
+                    }
+                    TantivyTokenFilterEnum::AsciiFolding(token_filter) => {
+                        text_analyzer_builder = text_analyzer_builder.filter_dynamic(token_filter);
+                    }
+                    TantivyTokenFilterEnum::Stemmer(token_filter) => {
+                        text_analyzer_builder = text_analyzer_builder.filter_dynamic(token_filter);
+                    }
+                    TantivyTokenFilterEnum::StopWords(token_filter) => {
+                        text_analyzer_builder = text_analyzer_builder.filter_dynamic(token_filter);
+                    }
+                    TantivyTokenFilterEnum::Synonyms(token_filter) => {
+                        text_analyzer_builder = text_analyzer_builder.filter_dynamic(token_filter);
+                    }
+                    TantivyTokenFilterEnum::WordDelimiter(token_filter) => {
+                        text_analyzer_builder = text_analyzer_builder.filter_dynamic(token_filter);
+                    }
                 }
             }
         }
@@ -83,9 +170,14 @@ impl TokenizerConfig {
     }
 }
 
-/// Helper function to analyze a text with a given `TokenizerConfig`.
-pub fn analyze_text(text: &str, tokenizer: &TokenizerConfig) -> anyhow::Result<Vec<Token>> {
-    let mut text_analyzer = tokenizer.text_analyzer()?;
+/// Helper function to analyze a text with a given `TokenizerConfig`. If `query_time` is true,
+/// the text is tokenized as a query rather than as a document.
+pub fn analyze_text(
+    text: &str,
+    tokenizer: &TokenizerConfig,
+    query_time: bool,
+) -> anyhow::Result<Vec<Token>> {
+    let mut text_analyzer = tokenizer.build_text_analyzer(query_time)?;
     let mut token_stream = text_analyzer.token_stream(text);
     let mut tokens = Vec::new();
     token_stream.process(&mut |token| {
@@ -100,6 +192,10 @@ pub enum TokenFilterType {
     RemoveLong,
     LowerCaser,
     AsciiFolding,
+    Stemmer(StemmerFilterOption),
+    StopWords(StopWordsFilterOption),
+    Synonyms(SynonymsFilterOption),
+    WordDelimiter(WordDelimiterFilterOption),
 }
 
 /// Tantivy token filter enum to build
@@ -108,20 +204,157 @@ enum TantivyTokenFilterEnum {
     RemoveLong(RemoveLongFilter),
     LowerCaser(LowerCaser),
     AsciiFolding(AsciiFoldingFilter),
+    Stemmer(Stemmer),
+    StopWords(StopWordFilter),
+    Synonyms(SynonymFilter),
+    WordDelimiter(WordDelimiterFilter),
 }
 
 impl TokenFilterType {
-    fn tantivy_token_filter_enum(&self) -> TantivyTokenFilterEnum {
-        match &self {
-            Self::RemoveLong => TantivyTokenFilterEnum::RemoveLong(RemoveLongFilter::limit(
+    /// Returns true if the filter is applied when tokenizing queries (`query_time` is true) or
+    /// documents (`query_time` is false). Synonyms are applied either at indexing or at query
+    /// time, the other filters are always applied.
+    fn applies_at(&self, query_time: bool) -> bool {
+        match self {
+            Self::Synonyms(options) => options.query_time == query_time,
+            _ => true,
+        }
+    }
+
+    fn tantivy_token_filter_enums(&self) -> anyhow::Result<Vec<TantivyTokenFilterEnum>> {
+        let token_filters = match &self {
+            Self::RemoveLong => vec![TantivyTokenFilterEnum::RemoveLong(RemoveLongFilter::limit(
                 DEFAULT_REMOVE_TOKEN_LENGTH,
-            )),
-            Self::LowerCaser => TantivyTokenFilterEnum::LowerCaser(LowerCaser),
-            Self::AsciiFolding => TantivyTokenFilterEnum::AsciiFolding(AsciiFoldingFilter),
+            ))],
+            Self::LowerCaser => vec![TantivyTokenFilterEnum::LowerCaser(LowerCaser)],
+            Self::AsciiFolding => vec![TantivyTokenFilterEnum::AsciiFolding(AsciiFoldingFilter)],
+            Self::Stemmer(options) => vec![TantivyTokenFilterEnum::Stemmer(Stemmer::new(
+                options.language.into(),
+            ))],
+            Self::StopWords(options) => {
+                let mut token_filters = Vec::new();
+
+                if let Some(language) = options.language {
+                    let stop_word_filter =
+                        StopWordFilter::new(language.into()).with_context(|| {
+                            format!("no built-in stop word list for language `{language:?}`")
+                        })?;
+                    token_filters.push(TantivyTokenFilterEnum::StopWords(stop_word_filter));
+                }
+                if !options.words.is_empty() {
+                    let stop_word_filter = StopWordFilter::remove(options.words.iter().cloned());
+                    token_filters.push(TantivyTokenFilterEnum::StopWords(stop_word_filter));
+                }
+                token_filters
+            }
+            Self::Synonyms(options) => {
+                let synonym_filter = SynonymFilter::from_rules(
+                    options.synonyms.iter().map(|synonym| synonym.as_str()),
+                )
+                .context("invalid synonyms filter")?;
+                vec![TantivyTokenFilterEnum::Synonyms(synonym_filter)]
+            }
+            Self::WordDelimiter(options) => vec![TantivyTokenFilterEnum::WordDelimiter(
+                WordDelimiterFilter::new(options.preserve_original),
+            )],
+        };
+        Ok(token_filters)
+    }
+}
+
+/// Languages supported by the stemmer and stop words filters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenFilterLanguage {
+    Arabic,
+    Danish,
+    Dutch,
+    English,
+    Finnish,
+    French,
+    German,
+    Greek,
+    Hungarian,
+    Italian,
+    Norwegian,
+    Portuguese,
+    Romanian,
+    Russian,
+    Spanish,
+    Swedish,
+    Tamil,
+    Turkish,
+}
+
+impl From<TokenFilterLanguage> for Language {
+    fn from(language: TokenFilterLanguage) -> Self {
+        match language {
+            TokenFilterLanguage::Arabic => Language::Arabic,
+            TokenFilterLanguage::Danish => Language::Danish,
+            TokenFilterLanguage::Dutch => Language::Dutch,
+            TokenFilterLanguage::English => Language::English,
+            TokenFilterLanguage::Finnish => Language::Finnish,
+            TokenFilterLanguage::French => Language::French,
+            TokenFilterLanguage::German => Language::German,
+            TokenFilterLanguage::Greek => Language::Greek,
+            TokenFilterLanguage::Hungarian => Language::Hungarian,
+            TokenFilterLanguage::Italian => Language::Italian,
+            TokenFilterLanguage::Norwegian => Language::Norwegian,
+            TokenFilterLanguage::Portuguese => Language::Portuguese,
+            TokenFilterLanguage::Romanian => Language::Romanian,
+            TokenFilterLanguage::Russian => Language::Russian,
+            TokenFilterLanguage::Spanish => Language::Spanish,
+            TokenFilterLanguage::Swedish => Language::Swedish,
+            TokenFilterLanguage::Tamil => Language::Tamil,
+            TokenFilterLanguage::Turkish => Language::Turkish,
         }
     }
 }
 
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Hash, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct StemmerFilterOption {
+    pub language: TokenFilterLanguage,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Hash, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct StopWordsFilterOption {
+    /// Removes the words of the built-in stop word list of the language.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<TokenFilterLanguage>,
+    /// Additional stop words.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub words: Vec<String>,
+    /// Path of a file, relative to the index URI, listing additional stop words. The file is read
+    /// when the index is created.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub words_path: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Hash, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SynonymsFilterOption {
+    /// Synonym rules in the Solr format.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub synonyms: Vec<String>,
+    /// Path of a file, relative to the index URI, listing additional synonym rules. The file is
+    /// read when the index is created.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub synonyms_path: Option<String>,
+    /// Applies the synonyms when tokenizing queries instead of documents.
+    #[serde(default)]
+    pub query_time: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Hash, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct WordDelimiterFilterOption {
+    /// Emits the original token in addition to its parts.
+    #[serde(default)]
+    pub preserve_original: bool,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, utoipa::ToSchema)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum TokenizerType {
@@ -150,8 +383,10 @@ pub struct RegexTokenizerOption {
 
 #[cfg(test)]
 mod tests {
-    use super::{NgramTokenizerOption, TokenizerType};
-    use crate::doc_mapper::RegexTokenizerOption;
+    use std::collections::HashMap;
+
+    use super::{analyze_text, NgramTokenizerOption, TokenizerType};
+    use crate::doc_mapper::{RegexTokenizerOption, TokenFilterType};
     use crate::TokenizerEntry;
 
     #[test]
@@ -243,4 +478,50 @@ mod tests {
             _ => panic!("Unexpected tokenizer type"),
         }
     }
+
+    #[test]
+    fn test_tokenizer_entry_word_lists() {
+        let mut tokenizer_entry = serde_json::from_str::<TokenizerEntry>(
+            r#"
+            {
+                "name": "my_tokenizer",
+                "type": "simple",
+                "filters": [
+                    {"word_delimiter": {}},
+                    "lower_caser",
+                    {"stop_words": {"words": ["la"], "words_path": "stop_words.txt"}},
+                    {"synonyms": {"synonyms_path": "synonyms.txt"}}
+                ]
+            }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            tokenizer_entry.word_list_paths(),
+            ["stop_words.txt", "synonyms.txt"]
+        );
+        let word_list_files = HashMap::from_iter([
+            (
+                "stop_words.txt".to_string(),
+                "# French\nle\n\nles\n".to_string(),
+            ),
+            ("synonyms.txt".to_string(), "voiture, auto\n".to_string()),
+        ]);
+        tokenizer_entry.inline_word_lists(&word_list_files).unwrap();
+        assert!(tokenizer_entry.word_list_paths().is_empty());
+
+        let TokenFilterType::StopWords(stop_words_options) = &tokenizer_entry.config.filters[2]
+        else {
+            panic!("expected stop words filter");
+        };
+        assert_eq!(stop_words_options.words, ["la", "le", "les"]);
+
+        let tokens: Vec<String> =
+            analyze_text("La Voiture les jeuxVideo", &tokenizer_entry.config, false)
+                .unwrap()
+                .into_iter()
+                .map(|token| token.text)
+                .collect();
+        assert_eq!(tokens, ["voiture", "auto", "jeux", "video"]);
+    }
 }
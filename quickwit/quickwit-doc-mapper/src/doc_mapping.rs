@@ -137,6 +137,20 @@ pub struct DocMapping {
     #[serde(default)]
     pub tag_fields: BTreeSet<String>,
 
+    /// Declares the numeric and datetime fast fields for which the min and max values are
+    /// recorded in the splits metadata to prune splits at search time.
+    #[schema(value_type = Vec<String>)]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub zone_map_fields: BTreeSet<String>,
+
+    /// Declares the high cardinality fields for which a bloom filter over the terms is stored
+    /// in the splits to prune splits on exact-match lookups.
+    #[schema(value_type = Vec<String>)]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub bloom_filter_fields: BTreeSet<String>,
+
     /// Expresses via a "mini-DSL" how to route documents to split partitions.
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -219,6 +233,8 @@ mod tests {
             ],
             timestamp_field: Some("timestamp".to_string()),
             tag_fields: BTreeSet::from_iter(["level".to_string()]),
+            zone_map_fields: BTreeSet::from_iter(["severity_number".to_string()]),
+            bloom_filter_fields: BTreeSet::from_iter(["trace_id".to_string()]),
             partition_key: Some("tenant_id".to_string()),
             max_num_partitions: NonZeroU32::new(100).unwrap(),
             index_field_presence: true,
@@ -249,6 +265,8 @@ mod tests {
         assert!(doc_mapping.field_mappings.is_empty());
         assert_eq!(doc_mapping.timestamp_field, None);
         assert!(doc_mapping.tag_fields.is_empty());
+        assert!(doc_mapping.zone_map_fields.is_empty());
+        assert!(doc_mapping.bloom_filter_fields.is_empty());
         assert_eq!(doc_mapping.partition_key, None);
         assert_eq!(
             doc_mapping.max_num_partitions,
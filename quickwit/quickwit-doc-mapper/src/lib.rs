@@ -31,18 +31,25 @@ mod error;
 mod query_builder;
 mod routing_expression;
 
+/// Pruning bloom filters manipulation.
+pub mod bloom_filter_pruning;
+
 /// Pruning tags manipulation.
 pub mod tag_pruning;
 
+/// Pruning zone maps manipulation.
+pub mod zone_map_pruning;
+
 pub use doc_mapper::{
-    analyze_text, BinaryFormat, DocMapper, DocMapperBuilder, FieldMappingEntry, FieldMappingType,
-    JsonObject, NamedField, QuickwitBytesOptions, QuickwitJsonOptions, TermRange, TokenizerConfig,
-    TokenizerEntry, WarmupInfo,
+    analyze_text, BinaryFormat, DocMapper, DocMapperBuilder, FastFieldOptions, FieldMappingEntry,
+    FieldMappingType, JsonObject, NamedField, QuickwitBytesOptions, QuickwitJsonOptions,
+    RuntimeField, RuntimeFieldType, TermRange, TokenizerConfig, TokenizerEntry, WarmupInfo,
 };
 use doc_mapper::{
-    FastFieldOptions, FieldMappingEntryForSerialization, IndexRecordOptionSchema,
-    NgramTokenizerOption, QuickwitTextNormalizer, QuickwitTextTokenizer, RegexTokenizerOption,
-    TokenFilterType, TokenizerType,
+    FieldMappingEntryForSerialization, IndexRecordOptionSchema, NgramTokenizerOption,
+    QuickwitTextNormalizer, QuickwitTextTokenizer, RegexTokenizerOption, StemmerFilterOption,
+    StopWordsFilterOption, SynonymsFilterOption, TokenFilterLanguage, TokenFilterType,
+    TokenizerType, WordDelimiterFilterOption,
 };
 pub use doc_mapping::{DocMapping, Mode, ModeType};
 pub use error::{DocParsingError, QueryParserError};
@@ -59,11 +66,15 @@ pub const DYNAMIC_FIELD_NAME: &str = "_dynamic";
 /// Field name reserved for storing the length of source document.
 pub const DOCUMENT_SIZE_FIELD_NAME: &str = "_doc_length";
 
+/// Field name reserved as the root of the fields storing the elements of nested fields.
+const NESTED_FIELD_ROOT_NAME: &str = "_nested";
+
 /// Quickwit reserved field names.
 const QW_RESERVED_FIELD_NAMES: &[&str] = &[
     DOCUMENT_SIZE_FIELD_NAME,
     DYNAMIC_FIELD_NAME,
     FIELD_PRESENCE_FIELD_NAME,
+    NESTED_FIELD_ROOT_NAME,
     SOURCE_FIELD_NAME,
 ];
 
@@ -88,10 +99,15 @@ pub enum Cardinality {
     QuickwitTextNormalizer,
     QuickwitTextTokenizer,
     RegexTokenizerOption,
+    StemmerFilterOption,
+    StopWordsFilterOption,
+    SynonymsFilterOption,
+    TokenFilterLanguage,
     TokenFilterType,
     TokenizerConfig,
     TokenizerEntry,
     TokenizerType,
+    WordDelimiterFilterOption,
 )))]
 /// Schema used for the OpenAPI generation which are apart of this crate.
 pub struct DocMapperApiSchemas;
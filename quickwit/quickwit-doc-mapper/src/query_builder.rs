@@ -22,8 +22,8 @@ use std::convert::Infallible;
 use std::ops::Bound;
 
 use quickwit_query::query_ast::{
-    FieldPresenceQuery, FullTextQuery, PhrasePrefixQuery, QueryAst, QueryAstVisitor, RangeQuery,
-    TermSetQuery, WildcardQuery,
+    FieldPresenceQuery, FullTextQuery, NestedQuery, PhrasePrefixQuery, QueryAst, QueryAstVisitor,
+    RangeQuery, TermSetQuery, WildcardQuery,
 };
 use quickwit_query::tokenizers::TokenizerManager;
 use quickwit_query::{find_field_or_hit_dynamic, InvalidQuery};
@@ -68,6 +68,23 @@ impl<'a> QueryAstVisitor<'a> for ExistsQueryFields {
     }
 }
 
+#[derive(Default)]
+struct NestedQueryFields {
+    nested_field_names: HashSet<String>,
+}
+
+impl<'a> QueryAstVisitor<'a> for NestedQueryFields {
+    type Err = Infallible;
+
+    fn visit_nested(&mut self, nested_query: &'a NestedQuery) -> Result<(), Infallible> {
+        // The elements of the nested field are read from its fast field to check the inner query
+        // element by element.
+        self.nested_field_names
+            .insert(nested_query.nested_field_name());
+        self.visit(&nested_query.query)
+    }
+}
+
 /// Build a `Query` with field resolution & forbidding range clauses.
 pub(crate) fn build_query(
     query_ast: &QueryAst,
@@ -84,8 +101,13 @@ pub(crate) fn build_query(
     // This cannot fail. The error type is Infallible.
     let _: Result<(), Infallible> = exists_query_fields.visit(query_ast);
 
+    let mut nested_query_fields = NestedQueryFields::default();
+    // This cannot fail. The error type is Infallible.
+    let _: Result<(), Infallible> = nested_query_fields.visit(query_ast);
+
     let mut fast_field_names = HashSet::new();
     fast_field_names.extend(range_query_fields.range_query_field_names);
+    fast_field_names.extend(nested_query_fields.nested_field_names);
     fast_field_names.extend(
         exists_query_fields
             .exists_query_field_names
@@ -231,9 +253,7 @@ impl<'a, 'b: 'a> QueryAstVisitor<'a> for ExtractPrefixTermRanges<'b> {
     type Err = InvalidQuery;
 
     fn visit_full_text(&mut self, full_text_query: &'a FullTextQuery) -> Result<(), Self::Err> {
-        if let Some(prefix_term) =
-            full_text_query.get_prefix_term(self.schema, self.tokenizer_manager)
-        {
+        for prefix_term in full_text_query.get_prefix_terms(self.schema, self.tokenizer_manager) {
             // the max_expansion expansion of a bool prefix query is used for the fuzzy part of the
             // query, not for the expension to a range request.
             // see https://github.com/elastic/elasticsearch/blob/6ad48306d029e6e527c0481e2e9880bd2f06b239/docs/reference/query-dsl/match-bool-prefix-query.asciidoc#parameters
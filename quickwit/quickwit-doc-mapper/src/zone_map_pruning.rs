@@ -0,0 +1,462 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Bound;
+
+use quickwit_query::query_ast::QueryAst;
+use quickwit_query::{InterpretUserInput, JsonLiteral};
+use serde::{Deserialize, Serialize};
+use tantivy::schema::{FieldType, Schema};
+use tantivy::DateTime;
+
+/// A value recorded in a zone map or used as a bound of a zone map filter.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ZoneMapValue {
+    /// Value of a `u64` field.
+    U64(u64),
+    /// Value of an `i64` field.
+    I64(i64),
+    /// Value of an `f64` field. NaN and infinite values are never recorded.
+    F64(f64),
+    /// Value of a `datetime` field, expressed in nanoseconds since the Unix epoch.
+    Datetime(i64),
+}
+
+// Zone maps are never built from NaN values, so the equality is reflexive.
+impl Eq for ZoneMapValue {}
+
+impl ZoneMapValue {
+    /// Returns the name of the type of the value, as serialized in the split metadata.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ZoneMapValue::U64(_) => "u64",
+            ZoneMapValue::I64(_) => "i64",
+            ZoneMapValue::F64(_) => "f64",
+            ZoneMapValue::Datetime(_) => "datetime",
+        }
+    }
+
+    /// Compares two values of the same type. Returns `None` if the types differ, which happens
+    /// when the type of a field changes after a doc mapping update.
+    fn compare(&self, other: &ZoneMapValue) -> Option<Ordering> {
+        match (self, other) {
+            (ZoneMapValue::U64(left), ZoneMapValue::U64(right)) => Some(left.cmp(right)),
+            (ZoneMapValue::I64(left), ZoneMapValue::I64(right)) => Some(left.cmp(right)),
+            (ZoneMapValue::F64(left), ZoneMapValue::F64(right)) => left.partial_cmp(right),
+            (ZoneMapValue::Datetime(left), ZoneMapValue::Datetime(right)) => Some(left.cmp(right)),
+            _ => None,
+        }
+    }
+}
+
+/// Minimum and maximum values of a fast field within a split.
+///
+/// Zone maps are recorded in the split metadata for each field registered in the
+/// [`DocMapping`](crate::DocMapping) `zone_map_fields` attribute and containing at least one
+/// value. Both values are of the same type.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ZoneMap {
+    /// Minimum value of the field in the split.
+    pub min: ZoneMapValue,
+    /// Maximum value of the field in the split.
+    pub max: ZoneMapValue,
+}
+
+impl ZoneMap {
+    /// Returns false if and only if no value within the zone map can lie within the bounds.
+    pub fn may_intersect(
+        &self,
+        lower_bound: &Bound<ZoneMapValue>,
+        upper_bound: &Bound<ZoneMapValue>,
+    ) -> bool {
+        let above_lower_bound = match lower_bound {
+            Bound::Included(value) => self.max.compare(value) != Some(Ordering::Less),
+            Bound::Excluded(value) => !matches!(
+                self.max.compare(value),
+                Some(Ordering::Less | Ordering::Equal)
+            ),
+            Bound::Unbounded => true,
+        };
+        let below_upper_bound = match upper_bound {
+            Bound::Included(value) => self.min.compare(value) != Some(Ordering::Greater),
+            Bound::Excluded(value) => !matches!(
+                self.min.compare(value),
+                Some(Ordering::Greater | Ordering::Equal)
+            ),
+            Bound::Unbounded => true,
+        };
+        above_lower_bound && below_upper_bound
+    }
+}
+
+/// Represents a range predicate over the zone map of a field, used for split pruning.
+///
+/// The bounds are expressed in the type of the field.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ZoneMapFilter {
+    /// Name of the field.
+    pub field_name: String,
+    /// Lower bound of the range.
+    pub lower_bound: Bound<ZoneMapValue>,
+    /// Upper bound of the range.
+    pub upper_bound: Bound<ZoneMapValue>,
+}
+
+impl ZoneMapFilter {
+    /// Evaluates the filter predicate over the zone maps of a split.
+    ///
+    /// Splits without a zone map for the field always match.
+    pub fn evaluate(&self, zone_maps: &BTreeMap<String, ZoneMap>) -> bool {
+        let Some(zone_map) = zone_maps.get(&self.field_name) else {
+            return true;
+        };
+        zone_map.may_intersect(&self.lower_bound, &self.upper_bound)
+    }
+}
+
+/// Extracts from a user query the range predicates over the fields registered in
+/// `zone_map_field_names`.
+///
+/// If any of the returned filters evaluates to false for the zone maps associated
+/// with a split, we are guaranteed that no documents in the split match the query.
+pub fn extract_zone_map_filters_from_query(
+    query_ast: &QueryAst,
+    schema: &Schema,
+    zone_map_field_names: &BTreeSet<String>,
+) -> Vec<ZoneMapFilter> {
+    let mut zone_map_filters = Vec::new();
+
+    if !zone_map_field_names.is_empty() {
+        collect_zone_map_filters(
+            query_ast,
+            schema,
+            zone_map_field_names,
+            &mut zone_map_filters,
+        );
+    }
+    zone_map_filters
+}
+
+fn collect_zone_map_filters(
+    query_ast: &QueryAst,
+    schema: &Schema,
+    zone_map_field_names: &BTreeSet<String>,
+    zone_map_filters: &mut Vec<ZoneMapFilter>,
+) {
+    match query_ast {
+        QueryAst::Bool(bool_query) => {
+            // Only the `must` and `filter` clauses are required to match.
+            for child_ast in bool_query.must.iter().chain(&bool_query.filter) {
+                collect_zone_map_filters(child_ast, schema, zone_map_field_names, zone_map_filters);
+            }
+        }
+        QueryAst::Range(range_query) => {
+            if let Some(zone_map_filter) = build_zone_map_filter(
+                &range_query.field,
+                &range_query.lower_bound,
+                &range_query.upper_bound,
+                schema,
+                zone_map_field_names,
+            ) {
+                zone_map_filters.push(zone_map_filter);
+            }
+        }
+        QueryAst::Term(term_query) => {
+            // Unlike range queries, term queries do not truncate datetimes to the precision of
+            // the field, so we leave them out.
+            let is_datetime_field = schema
+                .get_field(&term_query.field)
+                .is_ok_and(|field| schema.get_field_entry(field).field_type().is_date());
+            if is_datetime_field {
+                return;
+            }
+            let value = Bound::Included(JsonLiteral::String(term_query.value.clone()));
+
+            if let Some(zone_map_filter) = build_zone_map_filter(
+                &term_query.field,
+                &value,
+                &value,
+                schema,
+                zone_map_field_names,
+            ) {
+                zone_map_filters.push(zone_map_filter);
+            }
+        }
+        QueryAst::Boost { underlying, .. } => {
+            collect_zone_map_filters(underlying, schema, zone_map_field_names, zone_map_filters);
+        }
+        _ => {}
+    }
+}
+
+fn build_zone_map_filter(
+    field_name: &str,
+    lower_bound: &Bound<JsonLiteral>,
+    upper_bound: &Bound<JsonLiteral>,
+    schema: &Schema,
+    zone_map_field_names: &BTreeSet<String>,
+) -> Option<ZoneMapFilter> {
+    if !zone_map_field_names.contains(field_name) {
+        return None;
+    }
+    let field = schema.get_field(field_name).ok()?;
+    let field_type = schema.get_field_entry(field).field_type();
+    let lower_bound = convert_bound(lower_bound, field_type)?;
+    let upper_bound = convert_bound(upper_bound, field_type)?;
+
+    if lower_bound == Bound::Unbounded && upper_bound == Bound::Unbounded {
+        return None;
+    }
+    let zone_map_filter = ZoneMapFilter {
+        field_name: field_name.to_string(),
+        lower_bound,
+        upper_bound,
+    };
+    Some(zone_map_filter)
+}
+
+fn convert_bound(
+    bound: &Bound<JsonLiteral>,
+    field_type: &FieldType,
+) -> Option<Bound<ZoneMapValue>> {
+    let converted_bound = match bound {
+        Bound::Included(value) => Bound::Included(convert_value(value, field_type)?),
+        Bound::Excluded(value) => Bound::Excluded(convert_value(value, field_type)?),
+        Bound::Unbounded => Bound::Unbounded,
+    };
+    Some(converted_bound)
+}
+
+fn convert_value(value: &JsonLiteral, field_type: &FieldType) -> Option<ZoneMapValue> {
+    match field_type {
+        FieldType::U64(_) => u64::interpret_json(value).map(ZoneMapValue::U64),
+        FieldType::I64(_) => i64::interpret_json(value).map(ZoneMapValue::I64),
+        FieldType::F64(_) => f64::interpret_json(value)
+            .filter(|value| value.is_finite())
+            .map(ZoneMapValue::F64),
+        FieldType::Date(date_options) => {
+            // Range queries truncate their bounds to the precision of the field, so we do the
+            // same to avoid pruning splits that match.
+            let datetime = DateTime::interpret_json(value)?;
+            let truncated_datetime = datetime.truncate(date_options.get_precision());
+            Some(ZoneMapValue::Datetime(
+                truncated_datetime.into_timestamp_nanos(),
+            ))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_query::query_ast::{BoolQuery, RangeQuery, TermQuery};
+    use tantivy::schema::{DateOptions, DateTimePrecision, FAST, STRING};
+
+    use super::*;
+
+    fn test_schema() -> Schema {
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_u64_field("status_code", FAST);
+        schema_builder.add_i64_field("tenant_id", FAST);
+        schema_builder.add_f64_field("latency_ms", FAST);
+        schema_builder.add_date_field(
+            "received_at",
+            DateOptions::from(FAST).set_precision(DateTimePrecision::Seconds),
+        );
+        schema_builder.add_text_field("service", STRING);
+        schema_builder.build()
+    }
+
+    fn zone_map_field_names() -> BTreeSet<String> {
+        ["status_code", "tenant_id", "latency_ms", "received_at"]
+            .into_iter()
+            .map(|field_name| field_name.to_string())
+            .collect()
+    }
+
+    fn term_query(field: &str, value: &str) -> QueryAst {
+        TermQuery {
+            field: field.to_string(),
+            value: value.to_string(),
+        }
+        .into()
+    }
+
+    fn range_query(
+        field: &str,
+        lower_bound: Bound<JsonLiteral>,
+        upper_bound: Bound<JsonLiteral>,
+    ) -> QueryAst {
+        RangeQuery {
+            field: field.to_string(),
+            lower_bound,
+            upper_bound,
+        }
+        .into()
+    }
+
+    #[test]
+    fn test_zone_map_may_intersect() {
+        let zone_map = ZoneMap {
+            min: ZoneMapValue::U64(200),
+            max: ZoneMapValue::U64(404),
+        };
+        let u64_bound = |value: u64| Bound::Included(ZoneMapValue::U64(value));
+
+        assert!(zone_map.may_intersect(&u64_bound(404), &Bound::Unbounded));
+        assert!(!zone_map.may_intersect(&u64_bound(500), &Bound::Unbounded));
+        assert!(
+            !zone_map.may_intersect(&Bound::Excluded(ZoneMapValue::U64(404)), &Bound::Unbounded)
+        );
+        assert!(zone_map.may_intersect(&Bound::Unbounded, &u64_bound(200)));
+        assert!(!zone_map.may_intersect(&Bound::Unbounded, &u64_bound(100)));
+        assert!(
+            !zone_map.may_intersect(&Bound::Unbounded, &Bound::Excluded(ZoneMapValue::U64(200)))
+        );
+        // The type of the field changed: the zone map is uninformative.
+        assert!(zone_map.may_intersect(&Bound::Included(ZoneMapValue::I64(500)), &Bound::Unbounded));
+    }
+
+    #[test]
+    fn test_zone_map_filter_evaluate() {
+        let zone_map_filter = ZoneMapFilter {
+            field_name: "status_code".to_string(),
+            lower_bound: Bound::Included(ZoneMapValue::U64(500)),
+            upper_bound: Bound::Unbounded,
+        };
+        let mut zone_maps = BTreeMap::new();
+        assert!(zone_map_filter.evaluate(&zone_maps));
+
+        zone_maps.insert(
+            "status_code".to_string(),
+            ZoneMap {
+                min: ZoneMapValue::U64(200),
+                max: ZoneMapValue::U64(404),
+            },
+        );
+        assert!(!zone_map_filter.evaluate(&zone_maps));
+
+        zone_maps.insert(
+            "status_code".to_string(),
+            ZoneMap {
+                min: ZoneMapValue::U64(200),
+                max: ZoneMapValue::U64(503),
+            },
+        );
+        assert!(zone_map_filter.evaluate(&zone_maps));
+    }
+
+    #[test]
+    fn test_extract_zone_map_filters_from_query() {
+        let schema = test_schema();
+        let zone_map_field_names = zone_map_field_names();
+
+        let query_ast: QueryAst = BoolQuery {
+            must: vec![range_query(
+                "status_code",
+                Bound::Included(JsonLiteral::String("500".to_string())),
+                Bound::Unbounded,
+            )],
+            filter: vec![term_query("tenant_id", "-7")],
+            should: vec![range_query(
+                "latency_ms",
+                Bound::Excluded(JsonLiteral::String("2000".to_string())),
+                Bound::Unbounded,
+            )],
+            must_not: vec![term_query("status_code", "503")],
+            ..Default::default()
+        }
+        .into();
+        let zone_map_filters =
+            extract_zone_map_filters_from_query(&query_ast, &schema, &zone_map_field_names);
+        assert_eq!(
+            zone_map_filters,
+            [
+                ZoneMapFilter {
+                    field_name: "status_code".to_string(),
+                    lower_bound: Bound::Included(ZoneMapValue::U64(500)),
+                    upper_bound: Bound::Unbounded,
+                },
+                ZoneMapFilter {
+                    field_name: "tenant_id".to_string(),
+                    lower_bound: Bound::Included(ZoneMapValue::I64(-7)),
+                    upper_bound: Bound::Included(ZoneMapValue::I64(-7)),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_zone_map_filters_from_query_datetime() {
+        let schema = test_schema();
+        let zone_map_field_names = zone_map_field_names();
+
+        let query_ast = range_query(
+            "received_at",
+            Bound::Included(JsonLiteral::String("2024-01-01T00:00:00.500Z".to_string())),
+            Bound::Unbounded,
+        );
+        let zone_map_filters =
+            extract_zone_map_filters_from_query(&query_ast, &schema, &zone_map_field_names);
+        assert_eq!(
+            zone_map_filters,
+            [ZoneMapFilter {
+                field_name: "received_at".to_string(),
+                lower_bound: Bound::Included(ZoneMapValue::Datetime(1_704_067_200_000_000_000)),
+                upper_bound: Bound::Unbounded,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_extract_zone_map_filters_from_query_uninformative() {
+        let schema = test_schema();
+        let zone_map_field_names = zone_map_field_names();
+
+        // Not a zone map field.
+        let query_ast = term_query("service", "api");
+        assert!(
+            extract_zone_map_filters_from_query(&query_ast, &schema, &zone_map_field_names)
+                .is_empty()
+        );
+        // Invalid bound.
+        let query_ast = range_query(
+            "status_code",
+            Bound::Included(JsonLiteral::String("abc".to_string())),
+            Bound::Unbounded,
+        );
+        assert!(
+            extract_zone_map_filters_from_query(&query_ast, &schema, &zone_map_field_names)
+                .is_empty()
+        );
+        // Optional clause.
+        let query_ast: QueryAst = BoolQuery {
+            should: vec![term_query("status_code", "500")],
+            ..Default::default()
+        }
+        .into();
+        assert!(
+            extract_zone_map_filters_from_query(&query_ast, &schema, &zone_map_field_names)
+                .is_empty()
+        );
+    }
+}
@@ -21,12 +21,13 @@ use std::collections::{HashMap, HashSet};
 use std::path::Path;
 use std::time::Duration;
 
+use anyhow::Context;
 use futures_util::StreamExt;
 use itertools::Itertools;
 use quickwit_common::fs::{empty_dir, get_cache_directory_path};
 use quickwit_common::pretty::PrettySample;
 use quickwit_common::rate_limited_error;
-use quickwit_config::{validate_identifier, IndexConfig, SourceConfig};
+use quickwit_config::{build_doc_mapper, validate_identifier, IndexConfig, SourceConfig};
 use quickwit_indexing::check_source_connectivity;
 use quickwit_metastore::{
     AddSourceRequestExt, CreateIndexResponseExt, IndexMetadata, IndexMetadataResponseExt,
@@ -117,15 +118,22 @@ impl IndexService {
         self.metastore.clone()
     }
 
+    pub fn storage_resolver(&self) -> StorageResolver {
+        self.storage_resolver.clone()
+    }
+
     /// Creates an index from `IndexConfig`.
     pub async fn create_index(
         &mut self,
-        index_config: IndexConfig,
+        mut index_config: IndexConfig,
         overwrite: bool,
     ) -> Result<IndexMetadata, IndexServiceError> {
         validate_storage_uri(&self.storage_resolver, &index_config)
             .await
             .map_err(IndexServiceError::InvalidConfig)?;
+        inline_tokenizer_word_lists(&self.storage_resolver, &mut index_config)
+            .await
+            .map_err(IndexServiceError::InvalidConfig)?;
 
         // Delete existing index if it exists.
         if overwrite {
@@ -526,11 +534,49 @@ pub async fn validate_storage_uri(
     Ok(())
 }
 
+/// Inlines the word lists (stop words, synonyms) read from the files referenced by the custom
+/// tokenizers of the index. The paths of the files are relative to the index URI.
+pub async fn inline_tokenizer_word_lists(
+    storage_resolver: &StorageResolver,
+    index_config: &mut IndexConfig,
+) -> anyhow::Result<()> {
+    let word_list_paths: HashSet<String> = index_config
+        .doc_mapping
+        .tokenizers
+        .iter()
+        .flat_map(|tokenizer_entry| tokenizer_entry.word_list_paths())
+        .collect();
+
+    if word_list_paths.is_empty() {
+        return Ok(());
+    }
+    let storage = storage_resolver.resolve(&index_config.index_uri).await?;
+    let mut word_list_files = HashMap::with_capacity(word_list_paths.len());
+
+    for word_list_path in word_list_paths {
+        let word_list_bytes = storage
+            .get_all(Path::new(&word_list_path))
+            .await
+            .with_context(|| format!("failed to read word list file `{word_list_path}`"))?;
+        let word_list_file = String::from_utf8(word_list_bytes.to_vec())
+            .with_context(|| format!("word list file `{word_list_path}` is not valid UTF-8"))?;
+        word_list_files.insert(word_list_path, word_list_file);
+    }
+    for tokenizer_entry in &mut index_config.doc_mapping.tokenizers {
+        tokenizer_entry.inline_word_lists(&word_list_files)?;
+    }
+    build_doc_mapper(&index_config.doc_mapping, &index_config.search_settings)?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
 
     use quickwit_common::uri::Uri;
-    use quickwit_config::{IndexConfig, CLI_SOURCE_ID, INGEST_API_SOURCE_ID, INGEST_V2_SOURCE_ID};
+    use quickwit_config::{
+        load_index_config_from_user_config, ConfigFormat, IndexConfig, CLI_SOURCE_ID,
+        INGEST_API_SOURCE_ID, INGEST_V2_SOURCE_ID,
+    };
     use quickwit_metastore::{
         metastore_for_test, MetastoreServiceExt, SplitMetadata, StageSplitsRequestExt,
     };
@@ -584,6 +630,64 @@ mod tests {
         assert!(index_metadata_0.index_uid != index_metadata_1.index_uid);
     }
 
+    #[tokio::test]
+    async fn test_create_index_inlines_word_lists() {
+        let metastore = metastore_for_test();
+        let storage_resolver = StorageResolver::for_test();
+        let storage = storage_resolver
+            .resolve(&Uri::for_test("ram://indexes/test-index"))
+            .await
+            .unwrap();
+        let mut index_service = IndexService::new(metastore.clone(), storage_resolver);
+        let index_config_yaml = r#"
+            version: 0.8
+            index_id: test-index
+            index_uri: ram://indexes/test-index
+            doc_mapping:
+              tokenizers:
+                - name: french
+                  type: simple
+                  filters:
+                    - lower_caser
+                    - stop_words:
+                        words_path: stop_words.txt
+              field_mappings:
+                - name: body
+                  type: text
+                  tokenizer: french
+        "#;
+        let index_config = load_index_config_from_user_config(
+            ConfigFormat::Yaml,
+            index_config_yaml.as_bytes(),
+            &Uri::for_test("ram://indexes"),
+        )
+        .unwrap();
+
+        let error = index_service
+            .create_index(index_config.clone(), false)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, IndexServiceError::InvalidConfig(_)));
+
+        storage
+            .put(
+                Path::new("stop_words.txt"),
+                Box::new(b"le\nla\nles\n".to_vec()),
+            )
+            .await
+            .unwrap();
+        let index_metadata = index_service
+            .create_index(index_config, false)
+            .await
+            .unwrap();
+        let tokenizer_entry = &index_metadata.index_config.doc_mapping.tokenizers[0];
+        assert!(tokenizer_entry.word_list_paths().is_empty());
+
+        let doc_mapping_json =
+            serde_utils::to_json_str(&index_metadata.index_config.doc_mapping).unwrap();
+        assert!(doc_mapping_json.contains(r#""words":["le","la","les"]"#));
+    }
+
     #[tokio::test]
     async fn test_delete_index() {
         let mut metastore = metastore_for_test();
@@ -21,4 +21,7 @@ mod garbage_collection;
 mod index;
 
 pub use garbage_collection::{run_garbage_collect, GcMetrics};
-pub use index::{clear_cache_directory, validate_storage_uri, IndexService, IndexServiceError};
+pub use index::{
+    clear_cache_directory, inline_tokenizer_word_lists, validate_storage_uri, IndexService,
+    IndexServiceError,
+};
@@ -56,6 +56,7 @@ use ulid::Ulid;
 
 use crate::actors::cooperative_indexing::{CooperativeIndexingCycle, CooperativeIndexingPeriod};
 use crate::actors::IndexSerializer;
+use crate::index_sorter::IndexSorter;
 use crate::models::{
     CommitTrigger, EmptySplit, IndexedSplitBatchBuilder, IndexedSplitBuilder, NewPublishLock,
     NewPublishToken, ProcessedDoc, ProcessedDocBatch, PublishLock,
@@ -127,6 +128,8 @@ impl IndexerState {
             .set_kill_switch(ctx.kill_switch().clone())
             .set_component("indexer");
 
+        let index_sorter_opt = IndexSorter::new(&self.indexing_settings.sort_by, &self.schema)?;
+
         let indexed_split = IndexedSplitBuilder::new_in_dir(
             self.pipeline_id.clone(),
             partition_id,
@@ -134,6 +137,7 @@ impl IndexerState {
             self.doc_mapping_uid,
             self.indexing_directory.clone(),
             index_builder,
+            index_sorter_opt,
             io_controls,
         )?;
         info!(
@@ -320,7 +324,7 @@ impl IndexerState {
                 counters,
                 ctx,
             )?;
-            let mem_usage_before = indexed_split.index_writer.mem_usage() as u64;
+            let mem_usage_before = indexed_split.mem_usage() as u64;
             if split_created {
                 // The split was just created. We need to account for the initial index writer's
                 // memory usage.
@@ -333,10 +337,9 @@ impl IndexerState {
             }
             let _protect_guard = ctx.protect_zone();
             indexed_split
-                .index_writer
-                .add_document(doc)
+                .add_document(doc, num_bytes)
                 .context("failed to add document")?;
-            let mem_usage_after = indexed_split.index_writer.mem_usage() as u64;
+            let mem_usage_after = indexed_split.mem_usage() as u64;
             memory_usage_delta += mem_usage_after as i64 - mem_usage_before as i64;
             ctx.record_progress();
         }
@@ -712,11 +715,13 @@ mod tests {
     use std::time::Duration;
 
     use quickwit_actors::Universe;
+    use quickwit_config::IndexSortField;
     use quickwit_doc_mapper::{default_doc_mapper_for_test, DocMapper};
     use quickwit_metastore::checkpoint::SourceCheckpointDelta;
     use quickwit_proto::metastore::{
         EmptyResponse, LastDeleteOpstampResponse, MockMetastoreService,
     };
+    use quickwit_proto::search::SortOrder;
     use quickwit_proto::types::{IndexUid, NodeId, PipelineUid};
     use tantivy::{doc, DateTime};
 
@@ -1209,6 +1214,93 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_indexer_sorts_documents() -> anyhow::Result<()> {
+        let universe = Universe::with_accelerated_time();
+        let pipeline_id = IndexingPipelineId {
+            index_uid: IndexUid::new_with_random_ulid("test-index"),
+            source_id: "test-source".to_string(),
+            node_id: NodeId::from("test-node"),
+            pipeline_uid: PipelineUid::default(),
+        };
+        let doc_mapper = Arc::new(default_doc_mapper_for_test());
+        let schema = doc_mapper.schema();
+        let body_field = schema.get_field("body").unwrap();
+        let timestamp_field = schema.get_field("timestamp").unwrap();
+        let indexing_directory = TempDirectory::for_test();
+        let mut indexing_settings = IndexingSettings::for_test();
+        indexing_settings.sort_by = vec![IndexSortField {
+            field: "timestamp".to_string(),
+            order: SortOrder::Desc,
+        }];
+        let (index_serializer_mailbox, index_serializer_inbox) = universe.create_test_mailbox();
+        let mut mock_metastore = MockMetastoreService::new();
+        mock_metastore
+            .expect_last_delete_opstamp()
+            .once()
+            .returning(move |_last_delete_opstamp_request| Ok(LastDeleteOpstampResponse::new(10)));
+        mock_metastore.expect_publish_splits().never();
+        let indexer = Indexer::new(
+            pipeline_id,
+            doc_mapper,
+            MetastoreServiceClient::from_mock(mock_metastore),
+            indexing_directory,
+            indexing_settings,
+            None,
+            index_serializer_mailbox,
+        );
+        let (indexer_mailbox, indexer_handle) = universe.spawn_builder().spawn(indexer);
+        let processed_docs = [1_662_529_435, 1_662_529_437, 1_662_529_436]
+            .into_iter()
+            .map(|timestamp_secs| ProcessedDoc {
+                doc: doc!(
+                    body_field=>format!("this is a test document {timestamp_secs}"),
+                    timestamp_field=>DateTime::from_timestamp_secs(timestamp_secs)
+                ),
+                timestamp_opt: Some(DateTime::from_timestamp_secs(timestamp_secs)),
+                partition: 1,
+                num_bytes: 30,
+            })
+            .collect();
+        indexer_mailbox
+            .send_message(ProcessedDocBatch::new(
+                processed_docs,
+                SourceCheckpointDelta::from_range(8..11),
+                false,
+            ))
+            .await
+            .unwrap();
+        universe.send_exit_with_success(&indexer_mailbox).await?;
+        let (exit_status, _indexer_counters) = indexer_handle.join().await;
+        assert!(exit_status.is_success());
+
+        let mut output_messages: Vec<IndexedSplitBatchBuilder> =
+            index_serializer_inbox.drain_for_test_typed();
+        assert_eq!(output_messages.len(), 1);
+        let split_builder = output_messages[0].splits.pop().unwrap();
+        let indexed_split = split_builder.finalize()?;
+        assert_eq!(
+            indexed_split.split_attrs.index_sort,
+            [IndexSortField {
+                field: "timestamp".to_string(),
+                order: SortOrder::Desc,
+            }]
+        );
+        let searcher = indexed_split.index.reader()?.searcher();
+        let timestamp_column = searcher.segment_reader(0).fast_fields().date("timestamp")?;
+        let timestamps: Vec<i64> = (0..3)
+            .map(|doc_id| {
+                timestamp_column
+                    .first(doc_id)
+                    .unwrap()
+                    .into_timestamp_secs()
+            })
+            .collect();
+        assert_eq!(timestamps, [1_662_529_437, 1_662_529_436, 1_662_529_435]);
+        universe.assert_quit().await;
+        Ok(())
+    }
+
     const DOCMAPPER_WITH_PARTITION_JSON: &str = r#"{
         "tag_fields": ["tenant"],
         "partition_key": "tenant",
@@ -385,7 +385,15 @@ impl IndexingPipeline {
 
         // Packager
         let tag_fields = self.params.doc_mapper.tag_named_fields()?;
-        let packager = Packager::new("Packager", tag_fields, uploader_mailbox);
+        let zone_map_fields = self.params.doc_mapper.zone_map_named_fields()?;
+        let bloom_filter_fields = self.params.doc_mapper.bloom_filter_named_fields()?;
+        let packager = Packager::new(
+            "Packager",
+            tag_fields,
+            zone_map_fields,
+            bloom_filter_fields,
+            uploader_mailbox,
+        );
         let (packager_mailbox, packager_handle) = ctx
             .spawn_actor()
             .set_kill_switch(self.kill_switch.clone())
@@ -913,6 +921,7 @@ mod tests {
             split_store: split_store.clone(),
             merge_policy: default_merge_policy(),
             retention_policy: None,
+            index_sort: Vec::new(),
             max_concurrent_split_uploads: 2,
             merge_io_throughput_limiter_opt: None,
             merge_scheduler_service: universe.get_or_spawn_one(),
@@ -303,6 +303,7 @@ impl IndexingService {
             merge_scheduler_service: self.merge_scheduler_service.clone(),
             merge_policy: merge_policy.clone(),
             retention_policy: retention_policy.clone(),
+            index_sort: index_config.indexing_settings.sort_by.clone(),
             merge_io_throughput_limiter_opt: self.merge_io_throughput_limiter_opt.clone(),
             max_concurrent_split_uploads: self.max_concurrent_split_uploads,
             event_broker: self.event_broker.clone(),
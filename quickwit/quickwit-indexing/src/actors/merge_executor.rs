@@ -17,22 +17,25 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::BTreeSet;
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, BinaryHeap};
 use std::ops::RangeInclusive;
 use std::path::Path;
 use std::sync::Arc;
 use std::time::Instant;
 
-use anyhow::{anyhow, Context};
+use anyhow::{anyhow, bail, Context};
 use async_trait::async_trait;
 use fail::fail_point;
 use itertools::Itertools;
 use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox, QueueCapacity};
 use quickwit_common::io::IoControls;
 use quickwit_common::runtimes::RuntimeType;
+use quickwit_common::shared_consts::FIELD_PRESENCE_FIELD_NAME;
 use quickwit_common::temp_dir::TempDirectory;
+use quickwit_config::{IndexSortField, TransformConfig};
 use quickwit_directories::UnionDirectory;
-use quickwit_doc_mapper::DocMapper;
+use quickwit_doc_mapper::{DocMapper, DOCUMENT_SIZE_FIELD_NAME, SOURCE_FIELD_NAME};
 use quickwit_metastore::SplitMetadata;
 use quickwit_proto::indexing::MergePipelineId;
 use quickwit_proto::metastore::{
@@ -42,15 +45,24 @@ use quickwit_proto::metastore::{
 use quickwit_proto::types::{NodeId, SplitId};
 use quickwit_query::get_quickwit_fastfield_normalizer_manager;
 use quickwit_query::query_ast::QueryAst;
+use serde_json::Value as JsonValue;
+use tantivy::columnar::Column;
 use tantivy::directory::{Advice, DirectoryClone, MmapDirectory, RamDirectory};
 use tantivy::index::SegmentId;
+use tantivy::schema::Schema;
 use tantivy::tokenizer::TokenizerManager;
-use tantivy::{DateTime, Directory, Index, IndexMeta, IndexWriter, SegmentReader};
+use tantivy::{
+    DateTime, Directory, DocAddress, DocId, Document, Index, IndexBuilder, IndexMeta, IndexReader,
+    IndexSettings, IndexWriter, ReloadPolicy, Searcher, SegmentOrdinal, SegmentReader,
+    TantivyDocument,
+};
 use tokio::runtime::Handle;
 use tracing::{debug, info, instrument, warn};
 
 use crate::actors::Packager;
 use crate::controlled_directory::ControlledDirectory;
+use crate::index_sorter::{IndexSorter, SegmentSortKeys, SortKey};
+use crate::json_doc_transform::JsonDocTransform;
 use crate::merge_policy::MergeOperationType;
 use crate::models::{IndexedSplit, IndexedSplitBatch, MergeScratch, PublishLock, SplitAttrs};
 
@@ -61,6 +73,9 @@ pub struct MergeExecutor {
     doc_mapper: Arc<DocMapper>,
     io_controls: IoControls,
     merge_packager_mailbox: Mailbox<Packager>,
+    index_sort: Vec<IndexSortField>,
+    /// VRL transform applied to the documents of the splits undergoing a reindex operation.
+    reindex_transform_opt: Option<TransformConfig>,
 }
 
 #[async_trait]
@@ -122,6 +137,23 @@ impl Handler<MergeScratch> for MergeExecutor {
                 )
                 .await?
             }
+            MergeOperationType::Reindex => {
+                assert_eq!(
+                    merge_task.splits.len(),
+                    1,
+                    "Reindex operations can be applied only on one split."
+                );
+                assert_eq!(merge_scratch.tantivy_dirs.len(), 1);
+                let split_to_reindex = merge_task.splits[0].clone();
+                self.process_reindex(
+                    merge_task.merge_split_id.clone(),
+                    split_to_reindex,
+                    merge_scratch.tantivy_dirs,
+                    merge_scratch.merge_scratch_directory,
+                    ctx,
+                )
+                .await?
+            }
         };
         if let Some(indexed_split) = indexed_split_opt {
             info!(
@@ -273,6 +305,7 @@ pub fn merge_split_attrs(
         uncompressed_docs_size_in_bytes,
         delete_opstamp,
         num_merge_ops: max_merge_ops(splits) + 1,
+        index_sort: Vec::new(),
     })
 }
 
@@ -298,9 +331,30 @@ impl MergeExecutor {
             doc_mapper,
             io_controls,
             merge_packager_mailbox,
+            index_sort: Vec::new(),
+            reindex_transform_opt: None,
         }
     }
 
+    /// Sorts the documents of the merged splits by the given fast fields.
+    pub fn with_index_sort(mut self, index_sort: Vec<IndexSortField>) -> Self {
+        self.index_sort = index_sort;
+        self
+    }
+
+    /// Sets the VRL transform applied to the documents of the splits undergoing a reindex
+    /// operation.
+    pub fn with_reindex_transform(
+        mut self,
+        transform_config_opt: Option<TransformConfig>,
+    ) -> anyhow::Result<Self> {
+        if cfg!(not(feature = "vrl")) && transform_config_opt.is_some() {
+            bail!("VRL is not enabled: please recompile with the `vrl` feature")
+        }
+        self.reindex_transform_opt = transform_config_opt;
+        Ok(self)
+    }
+
     async fn process_merge(
         &mut self,
         merge_split_id: SplitId,
@@ -313,18 +367,37 @@ impl MergeExecutor {
             &tantivy_dirs,
             self.doc_mapper.tokenizer_manager().tantivy_manager(),
         )?;
+        let index_sorter_opt = IndexSorter::new(&self.index_sort, &self.doc_mapper.schema())?;
+
         // TODO it would be nice if tantivy could let us run the merge in the current thread.
         fail_point!("before-merge-split");
-        let controlled_directory = self
-            .merge_split_directories(
-                union_index_meta,
-                split_directories,
-                Vec::new(),
-                None,
+        let sorted_merge_opt = if let Some(index_sorter) = &index_sorter_opt {
+            self.sorted_merge_split_directories(
+                &splits,
+                &split_directories,
+                index_sorter,
                 merge_scratch_directory.path(),
                 ctx,
-            )
-            .await?;
+            )?
+        } else {
+            None
+        };
+        let (controlled_directory, index_sort) =
+            if let Some(controlled_directory) = sorted_merge_opt {
+                (controlled_directory, self.index_sort.clone())
+            } else {
+                let controlled_directory = self
+                    .merge_split_directories(
+                        union_index_meta,
+                        split_directories,
+                        Vec::new(),
+                        None,
+                        merge_scratch_directory.path(),
+                        ctx,
+                    )
+                    .await?;
+                (controlled_directory, Vec::new())
+            };
         fail_point!("after-merge-split");
 
         // This will have the side effect of deleting the directory containing the downloaded
@@ -335,7 +408,8 @@ impl MergeExecutor {
         )?;
         ctx.record_progress();
 
-        let split_attrs = merge_split_attrs(self.pipeline_id.clone(), merge_split_id, &splits)?;
+        let mut split_attrs = merge_split_attrs(self.pipeline_id.clone(), merge_split_id, &splits)?;
+        split_attrs.index_sort = index_sort;
         Ok(IndexedSplit {
             split_attrs,
             index: merged_index,
@@ -456,6 +530,8 @@ impl MergeExecutor {
                 uncompressed_docs_size_in_bytes,
                 delete_opstamp: last_delete_opstamp,
                 num_merge_ops: split.num_merge_ops,
+                // Deleting documents does not change the order of the remaining ones.
+                index_sort: split.index_sort,
             },
             index: merged_index,
             split_scratch_directory: merge_scratch_directory,
@@ -464,6 +540,308 @@ impl MergeExecutor {
         Ok(Some(indexed_split))
     }
 
+    /// Merges the splits by adding their documents, rebuilt from their source, to a new split in
+    /// the order of the index sort. Returns `None` if the documents of the splits cannot be
+    /// rebuilt, in which case the splits should be merged without sorting them.
+    fn sorted_merge_split_directories(
+        &self,
+        splits: &[SplitMetadata],
+        split_directories: &[Box<dyn Directory>],
+        index_sorter: &IndexSorter,
+        output_path: &Path,
+        ctx: &ActorContext<MergeExecutor>,
+    ) -> anyhow::Result<Option<ControlledDirectory>> {
+        // The documents are rebuilt with the doc mapper of the pipeline, which must be the one
+        // that created the splits.
+        if splits
+            .iter()
+            .any(|split| split.doc_mapping_uid != self.doc_mapper.doc_mapping_uid())
+        {
+            return Ok(None);
+        }
+        let tokenizer_manager = self.doc_mapper.tokenizer_manager().tantivy_manager();
+        let index_sort = index_sorter.index_sort();
+        let mut split_searchers: Vec<Searcher> = Vec::with_capacity(split_directories.len());
+        let mut sorted_runs: Vec<SortedRun> = Vec::new();
+        let mut index_settings_opt: Option<IndexSettings> = None;
+
+        for (split, split_directory) in splits.iter().zip(split_directories) {
+            let split_index = open_index(split_directory.clone(), tokenizer_manager)?;
+            let split_reader: IndexReader = split_index
+                .reader_builder()
+                .reload_policy(ReloadPolicy::Manual)
+                .try_into()?;
+            let split_searcher = split_reader.searcher();
+
+            if split_searcher
+                .schema()
+                .get_field(SOURCE_FIELD_NAME)
+                .is_err()
+            {
+                info!(
+                    split_id=%split.split_id(),
+                    "split does not store the source of its documents, merging without sorting"
+                );
+                return Ok(None);
+            }
+            // The merged split keeps the docstore settings of the merged splits.
+            index_settings_opt.get_or_insert_with(|| split_index.settings().clone());
+
+            // The documents of the splits sorted with the same index sort are already in order.
+            let is_split_sorted = split.index_sort == index_sort;
+
+            for (segment_ord, segment_reader) in split_searcher.segment_readers().iter().enumerate()
+            {
+                let segment_sort_keys = index_sorter.segment_sort_keys(segment_reader)?;
+                let mut doc_ids: Vec<DocId> = segment_reader.doc_ids_alive().collect();
+
+                if !is_split_sorted {
+                    doc_ids.sort_by_cached_key(|doc_id| segment_sort_keys.sort_key(*doc_id));
+                }
+                let doc_length_column_opt = segment_reader
+                    .fast_fields()
+                    .u64(DOCUMENT_SIZE_FIELD_NAME)
+                    .ok();
+                sorted_runs.push(SortedRun {
+                    split_ord: split_searchers.len(),
+                    segment_ord: segment_ord as SegmentOrdinal,
+                    doc_ids,
+                    position: 0,
+                    segment_sort_keys,
+                    doc_length_column_opt,
+                });
+            }
+            split_searchers.push(split_searcher);
+        }
+        let Some(index_settings) = index_settings_opt else {
+            return Ok(None);
+        };
+        let index_builder = IndexBuilder::new()
+            .settings(index_settings)
+            .schema(self.doc_mapper.schema())
+            .tokenizers(tokenizer_manager.clone())
+            .fast_field_tokenizers(
+                get_quickwit_fastfield_normalizer_manager()
+                    .tantivy_manager()
+                    .clone(),
+            );
+        let output_directory = ControlledDirectory::new(
+            Box::new(MmapDirectory::open(output_path)?),
+            self.io_controls
+                .clone()
+                .set_kill_switch(ctx.kill_switch().clone())
+                .set_progress(ctx.progress().clone()),
+        );
+        let mut index_writer =
+            index_builder.single_segment_index_writer(output_directory.clone(), 15_000_000)?;
+
+        ctx.record_progress();
+        let _protect_guard = ctx.protect_zone();
+
+        // K-way merge of the sorted runs. Documents with the same sort key are taken from the
+        // runs in order.
+        let mut sorted_runs_heap: BinaryHeap<Reverse<(SortKey, usize)>> = sorted_runs
+            .iter()
+            .enumerate()
+            .filter_map(|(run_ord, sorted_run)| {
+                let sort_key = sorted_run.peek_sort_key()?;
+                Some(Reverse((sort_key, run_ord)))
+            })
+            .collect();
+
+        while let Some(Reverse((_sort_key, run_ord))) = sorted_runs_heap.pop() {
+            let sorted_run = &mut sorted_runs[run_ord];
+            let doc_id = sorted_run.doc_ids[sorted_run.position];
+            sorted_run.position += 1;
+
+            let split_searcher = &split_searchers[sorted_run.split_ord];
+            let split_doc: TantivyDocument =
+                split_searcher.doc(DocAddress::new(sorted_run.segment_ord, doc_id))?;
+            let named_doc = split_doc.to_named_doc(split_searcher.schema());
+            let mut json_doc = self.doc_mapper.doc_to_json(named_doc.0)?;
+
+            let Some(JsonValue::Object(source_json_doc)) = json_doc.remove(SOURCE_FIELD_NAME)
+            else {
+                bail!(
+                    "document `{doc_id}` of split `{}` does not have a source",
+                    splits[sorted_run.split_ord].split_id()
+                );
+            };
+            let doc_length = sorted_run
+                .doc_length_column_opt
+                .as_ref()
+                .and_then(|doc_length_column| doc_length_column.first(doc_id))
+                .unwrap_or(0);
+            let (_partition, doc) = self
+                .doc_mapper
+                .doc_from_json_obj(source_json_doc, doc_length)
+                .with_context(|| {
+                    format!(
+                        "failed to rebuild document `{doc_id}` of split `{}`",
+                        splits[sorted_run.split_ord].split_id()
+                    )
+                })?;
+            index_writer.add_document(doc)?;
+
+            if let Some(sort_key) = sorted_run.peek_sort_key() {
+                sorted_runs_heap.push(Reverse((sort_key, run_ord)));
+            }
+            ctx.record_progress();
+        }
+        index_writer.finalize()?;
+        Ok(Some(output_directory))
+    }
+
+    /// Rebuilds a split with the current doc mapping: the documents are read back from the
+    /// docstore, optionally transformed, and indexed again into a new split replacing the
+    /// original one.
+    async fn process_reindex(
+        &mut self,
+        merge_split_id: SplitId,
+        split: SplitMetadata,
+        tantivy_dirs: Vec<Box<dyn Directory>>,
+        merge_scratch_directory: TempDirectory,
+        ctx: &ActorContext<Self>,
+    ) -> anyhow::Result<Option<IndexedSplit>> {
+        let tokenizer_manager = self.doc_mapper.tokenizer_manager().tantivy_manager();
+        let split_dir = tantivy_dirs
+            .into_iter()
+            .next()
+            .expect("There is exactly one split directory.");
+        let split_index = open_index(split_dir, tokenizer_manager)?;
+        let split_reader = split_index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        let split_searcher = split_reader.searcher();
+        check_split_is_reindexable(split_searcher.schema(), &self.doc_mapper)
+            .with_context(|| format!("failed to reindex split `{}`", split.split_id()))?;
+
+        let mut json_doc_transform_opt = self
+            .reindex_transform_opt
+            .clone()
+            .map(JsonDocTransform::try_from_transform_config)
+            .transpose()?;
+
+        // The new split keeps the docstore settings of the original split.
+        let index_builder = IndexBuilder::new()
+            .settings(split_index.settings().clone())
+            .schema(self.doc_mapper.schema())
+            .tokenizers(tokenizer_manager.clone())
+            .fast_field_tokenizers(
+                get_quickwit_fastfield_normalizer_manager()
+                    .tantivy_manager()
+                    .clone(),
+            );
+        let controlled_directory = ControlledDirectory::new(
+            Box::new(MmapDirectory::open(merge_scratch_directory.path())?),
+            self.io_controls
+                .clone()
+                .set_kill_switch(ctx.kill_switch().clone())
+                .set_progress(ctx.progress().clone()),
+        );
+        let mut index_writer =
+            index_builder.single_segment_index_writer(controlled_directory.clone(), 15_000_000)?;
+        let index_sorter_opt = IndexSorter::new(&self.index_sort, &self.doc_mapper.schema())?;
+        let mut sorted_docs: Vec<(SortKey, TantivyDocument)> = Vec::new();
+        let mut uncompressed_docs_size_in_bytes = 0u64;
+
+        for (segment_ord, segment_reader) in split_searcher.segment_readers().iter().enumerate() {
+            for doc_id in segment_reader.doc_ids_alive() {
+                let doc_address = DocAddress::new(segment_ord as u32, doc_id);
+                let split_doc: TantivyDocument = split_searcher.doc(doc_address)?;
+                let named_doc = split_doc.to_named_doc(split_searcher.schema());
+                let mut json_doc = self.doc_mapper.doc_to_json(named_doc.0)?;
+
+                // The original document is preferred over the one rebuilt from the stored fields
+                // when it is available.
+                if let Some(serde_json::Value::Object(source_json_doc)) =
+                    json_doc.remove(SOURCE_FIELD_NAME)
+                {
+                    json_doc = source_json_doc;
+                }
+                if let Some(json_doc_transform) = json_doc_transform_opt.as_mut() {
+                    json_doc = json_doc_transform.transform(json_doc)?;
+                }
+                let json_doc_bytes = serde_json::to_vec(&json_doc)?;
+                let num_bytes = json_doc_bytes.len() as u64;
+
+                let (_partition, doc) = self
+                    .doc_mapper
+                    .doc_from_json_obj(json_doc, num_bytes)
+                    .with_context(|| {
+                        format!(
+                            "failed to reindex document of split `{}` with the current doc mapping",
+                            split.split_id()
+                        )
+                    })?;
+                if let Some(index_sorter) = &index_sorter_opt {
+                    sorted_docs.push((index_sorter.sort_key_from_doc(&doc), doc));
+                } else {
+                    index_writer.add_document(doc)?;
+                }
+                uncompressed_docs_size_in_bytes += num_bytes;
+            }
+            ctx.record_progress();
+        }
+        // The reindexed documents are sorted like the documents of a new split.
+        sorted_docs
+            .sort_by(|(left_sort_key, _), (right_sort_key, _)| left_sort_key.cmp(right_sort_key));
+        for (_sort_key, doc) in sorted_docs {
+            index_writer.add_document(doc)?;
+        }
+        let reindexed_index = index_writer.finalize()?;
+        ctx.record_progress();
+
+        let Some(reindexed_segment) = reindexed_index.searchable_segments()?.into_iter().next()
+        else {
+            info!("split `{}` does not contain any document", split.split_id());
+            let mark_splits_for_deletion_request = MarkSplitsForDeletionRequest::new(
+                split.index_uid.clone(),
+                vec![split.split_id.clone()],
+            );
+            self.metastore
+                .mark_splits_for_deletion(mark_splits_for_deletion_request)
+                .await?;
+            return Ok(None);
+        };
+        let reindexed_segment_reader = SegmentReader::open(&reindexed_segment)?;
+        let num_docs = reindexed_segment_reader.num_docs() as u64;
+        let time_range = if let Some(timestamp_field_name) = self.doc_mapper.timestamp_field_name()
+        {
+            let reader = reindexed_segment_reader
+                .fast_fields()
+                .date(timestamp_field_name)?;
+            Some(reader.min_value()..=reader.max_value())
+        } else {
+            None
+        };
+        let indexed_split = IndexedSplit {
+            split_attrs: SplitAttrs {
+                node_id: NodeId::new(split.node_id),
+                index_uid: split.index_uid,
+                source_id: split.source_id,
+                doc_mapping_uid: self.doc_mapper.doc_mapping_uid(),
+                split_id: merge_split_id,
+                // The documents are not routed again: the split keeps its partition.
+                partition_id: split.partition_id,
+                replaced_split_ids: vec![split.split_id.clone()],
+                time_range,
+                num_docs,
+                uncompressed_docs_size_in_bytes,
+                delete_opstamp: split.delete_opstamp,
+                num_merge_ops: split.num_merge_ops,
+                index_sort: index_sorter_opt
+                    .map(|index_sorter| index_sorter.index_sort())
+                    .unwrap_or_default(),
+            },
+            index: reindexed_index,
+            split_scratch_directory: merge_scratch_directory,
+            controlled_directory_opt: Some(controlled_directory),
+        };
+        Ok(Some(indexed_split))
+    }
+
     async fn merge_split_directories(
         &self,
         union_index_meta: IndexMeta,
@@ -551,6 +929,59 @@ impl MergeExecutor {
     }
 }
 
+/// Documents of a segment to merge, in the order of the index sort.
+struct SortedRun {
+    split_ord: usize,
+    segment_ord: SegmentOrdinal,
+    doc_ids: Vec<DocId>,
+    position: usize,
+    segment_sort_keys: SegmentSortKeys,
+    doc_length_column_opt: Option<Column<u64>>,
+}
+
+impl SortedRun {
+    fn peek_sort_key(&self) -> Option<SortKey> {
+        let doc_id = *self.doc_ids.get(self.position)?;
+        Some(self.segment_sort_keys.sort_key(doc_id))
+    }
+}
+
+/// Checks that the documents of a split can be rebuilt from its docstore without losing the
+/// values of the fields of the current doc mapping.
+///
+/// When the split does not store the original documents in the `_source` field, the documents
+/// are rebuilt from the stored fields only: the values of the fields that were indexed but not
+/// stored would be silently dropped from the reindexed split.
+fn check_split_is_reindexable(split_schema: &Schema, doc_mapper: &DocMapper) -> anyhow::Result<()> {
+    if split_schema.get_field(SOURCE_FIELD_NAME).is_ok() {
+        return Ok(());
+    }
+    let current_schema = doc_mapper.schema();
+    let non_stored_field_names: Vec<&str> = split_schema
+        .fields()
+        .filter(|(_, field_entry)| !field_entry.is_stored())
+        .map(|(_, field_entry)| field_entry.name())
+        // Those fields are computed by Quickwit when the documents are indexed.
+        .filter(|field_name| {
+            *field_name != FIELD_PRESENCE_FIELD_NAME && *field_name != DOCUMENT_SIZE_FIELD_NAME
+        })
+        // The fields removed from the doc mapping are dropped anyway and the values of the
+        // concatenate fields are rebuilt from the other fields.
+        .filter(|field_name| {
+            current_schema.get_field(field_name).is_ok()
+                && !doc_mapper.is_concatenate_field(field_name)
+        })
+        .collect();
+    if !non_stored_field_names.is_empty() {
+        bail!(
+            "split does not store the `{SOURCE_FIELD_NAME}` field and the values of the fields \
+             [{}] are not stored",
+            non_stored_field_names.join(", ")
+        );
+    }
+    Ok(())
+}
+
 fn open_index<T: Into<Box<dyn Directory>>>(
     directory: T,
     tokenizer_manager: &TokenizerManager,
@@ -575,8 +1006,7 @@ mod tests {
     use quickwit_proto::metastore::{
         DeleteQuery, ListSplitsRequest, PublishSplitsRequest, StageSplitsRequest,
     };
-    use serde_json::Value as JsonValue;
-    use tantivy::{Document, ReloadPolicy, TantivyDocument};
+    use quickwit_proto::search::SortOrder;
 
     use super::*;
     use crate::merge_policy::{MergeOperation, MergeTask};
@@ -672,6 +1102,127 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_merge_executor_sorts_documents() -> anyhow::Result<()> {
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+              - name: ts
+                type: datetime
+                input_formats:
+                - unix_timestamp
+                fast: true
+            timestamp_field: ts
+            store_source: true
+        "#;
+        let indexing_settings_yaml = r#"
+            sort_by:
+              - field: ts
+                order: desc
+        "#;
+        let test_sandbox = TestSandbox::create(
+            "test-index",
+            doc_mapping_yaml,
+            indexing_settings_yaml,
+            &["body"],
+        )
+        .await?;
+        for timestamps in [[1631072710u64, 1631072730], [1631072740, 1631072720]] {
+            let docs = timestamps.into_iter().map(
+                |timestamp| serde_json::json!({"body": format!("doc{timestamp}"), "ts": timestamp}),
+            );
+            test_sandbox.add_documents(docs).await?;
+        }
+        let metastore = test_sandbox.metastore();
+        let index_uid = test_sandbox.index_uid();
+        let list_splits_request = ListSplitsRequest::try_from_index_uid(index_uid.clone()).unwrap();
+        let split_metas: Vec<SplitMetadata> = metastore
+            .list_splits(list_splits_request)
+            .await
+            .unwrap()
+            .collect_splits_metadata()
+            .await
+            .unwrap();
+        assert_eq!(split_metas.len(), 2);
+
+        let index_sort = vec![IndexSortField {
+            field: "ts".to_string(),
+            order: SortOrder::Desc,
+        }];
+        for split_meta in &split_metas {
+            assert_eq!(split_meta.index_sort, index_sort);
+        }
+        let merge_scratch_directory = TempDirectory::for_test();
+        let downloaded_splits_directory =
+            merge_scratch_directory.named_temp_child("downloaded-splits-")?;
+        let mut tantivy_dirs: Vec<Box<dyn Directory>> = Vec::new();
+        for split_meta in &split_metas {
+            let split_filename = split_file(split_meta.split_id());
+            let dest_filepath = downloaded_splits_directory.path().join(&split_filename);
+            test_sandbox
+                .storage()
+                .copy_to_file(Path::new(&split_filename), &dest_filepath)
+                .await?;
+            tantivy_dirs.push(get_tantivy_directory_from_split_bundle(&dest_filepath).unwrap())
+        }
+        let merge_operation = MergeOperation::new_merge_operation(split_metas);
+        let merge_task = MergeTask::from_merge_operation_for_test(merge_operation);
+        let merge_scratch = MergeScratch {
+            merge_task,
+            tantivy_dirs,
+            merge_scratch_directory,
+            downloaded_splits_directory,
+        };
+        let pipeline_id = MergePipelineId {
+            node_id: test_sandbox.node_id(),
+            index_uid,
+            source_id: test_sandbox.source_id(),
+        };
+        let (merge_packager_mailbox, merge_packager_inbox) =
+            test_sandbox.universe().create_test_mailbox();
+        let merge_executor = MergeExecutor::new(
+            pipeline_id,
+            test_sandbox.metastore(),
+            test_sandbox.doc_mapper(),
+            IoControls::default(),
+            merge_packager_mailbox,
+        )
+        .with_index_sort(index_sort.clone());
+        let (merge_executor_mailbox, merge_executor_handle) = test_sandbox
+            .universe()
+            .spawn_builder()
+            .spawn(merge_executor);
+        merge_executor_mailbox.send_message(merge_scratch).await?;
+        merge_executor_handle.process_pending_and_observe().await;
+        let packager_msgs: Vec<IndexedSplitBatch> = merge_packager_inbox.drain_for_test_typed();
+        assert_eq!(packager_msgs.len(), 1);
+        let split_attrs_after_merge = &packager_msgs[0].splits[0].split_attrs;
+        assert_eq!(split_attrs_after_merge.num_docs, 4);
+        assert_eq!(split_attrs_after_merge.index_sort, index_sort);
+
+        let reader = packager_msgs[0].splits[0]
+            .index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        let searcher = reader.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let ts_column = searcher.segment_reader(0).fast_fields().date("ts")?;
+        let timestamps: Vec<i64> = (0..4)
+            .map(|doc_id| ts_column.first(doc_id).unwrap().into_timestamp_secs())
+            .collect();
+        assert_eq!(timestamps, [1631072740, 1631072730, 1631072720, 1631072710]);
+        // The documents are rebuilt from their source.
+        let first_doc: TantivyDocument = searcher.doc(DocAddress::new(0, 0))?;
+        let first_json_doc = test_sandbox
+            .doc_mapper()
+            .doc_to_json(first_doc.to_named_doc(searcher.schema()).0)?;
+        assert_eq!(first_json_doc["body"], "doc1631072740");
+        test_sandbox.assert_quit().await;
+        Ok(())
+    }
+
     #[test]
     fn test_combine_partition_ids_singleton_unchanged() {
         assert_eq!(combine_partition_ids_aux([17]), 17);
@@ -701,6 +1252,49 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_check_split_is_reindexable() {
+        let split_doc_mapper = serde_json::from_str::<DocMapper>(
+            r#"{
+                "field_mappings": [
+                    {"name": "body", "type": "text", "stored": false},
+                    {"name": "severity", "type": "text"},
+                    {"name": "all", "type": "concatenate", "concatenate_fields": ["severity"]}
+                ],
+                "store_source": false
+            }"#,
+        )
+        .unwrap();
+        let split_schema = split_doc_mapper.schema();
+        let error = check_split_is_reindexable(&split_schema, &split_doc_mapper).unwrap_err();
+        assert!(error.to_string().contains("[body]"));
+
+        // The values of the fields removed from the current doc mapping can be dropped.
+        let current_doc_mapper = serde_json::from_str::<DocMapper>(
+            r#"{
+                "field_mappings": [
+                    {"name": "severity", "type": "text"},
+                    {"name": "all", "type": "concatenate", "concatenate_fields": ["severity"]}
+                ]
+            }"#,
+        )
+        .unwrap();
+        check_split_is_reindexable(&split_schema, &current_doc_mapper).unwrap();
+
+        // The original documents are read from the `_source` field.
+        let split_doc_mapper_with_source = serde_json::from_str::<DocMapper>(
+            r#"{
+                "field_mappings": [
+                    {"name": "body", "type": "text", "stored": false}
+                ],
+                "store_source": true
+            }"#,
+        )
+        .unwrap();
+        check_split_is_reindexable(&split_doc_mapper_with_source.schema(), &split_doc_mapper)
+            .unwrap();
+    }
+
     async fn aux_test_delete_and_merge_executor(
         index_id: &str,
         docs: Vec<JsonValue>,
@@ -29,7 +29,7 @@ use quickwit_common::io::{IoControls, Limiter};
 use quickwit_common::pubsub::EventBroker;
 use quickwit_common::temp_dir::TempDirectory;
 use quickwit_common::KillSwitch;
-use quickwit_config::RetentionPolicy;
+use quickwit_config::{IndexSortField, RetentionPolicy};
 use quickwit_doc_mapper::DocMapper;
 use quickwit_metastore::{
     ListSplitsQuery, ListSplitsRequestExt, MetastoreServiceStreamSplitsExt, SplitMetadata,
@@ -300,7 +300,15 @@ impl MergePipeline {
 
         // Merge Packager
         let tag_fields = self.params.doc_mapper.tag_named_fields()?;
-        let merge_packager = Packager::new("MergePackager", tag_fields, merge_uploader_mailbox);
+        let zone_map_fields = self.params.doc_mapper.zone_map_named_fields()?;
+        let bloom_filter_fields = self.params.doc_mapper.bloom_filter_named_fields()?;
+        let merge_packager = Packager::new(
+            "MergePackager",
+            tag_fields,
+            zone_map_fields,
+            bloom_filter_fields,
+            merge_uploader_mailbox,
+        );
         let (merge_packager_mailbox, merge_packager_handle) = ctx
             .spawn_actor()
             .set_kill_switch(self.kill_switch.clone())
@@ -321,7 +329,8 @@ impl MergePipeline {
             self.params.doc_mapper.clone(),
             merge_executor_io_controls,
             merge_packager_mailbox,
-        );
+        )
+        .with_index_sort(self.params.index_sort.clone());
         let (merge_executor_mailbox, merge_executor_handle) = ctx
             .spawn_actor()
             .set_kill_switch(self.kill_switch.clone())
@@ -575,6 +584,7 @@ pub struct MergePipelineParams {
     pub split_store: IndexingSplitStore,
     pub merge_policy: Arc<dyn MergePolicy>,
     pub retention_policy: Option<RetentionPolicy>,
+    pub index_sort: Vec<IndexSortField>,
     pub max_concurrent_split_uploads: usize, //< TODO share with the indexing pipeline.
     pub merge_io_throughput_limiter_opt: Option<Limiter>,
     pub event_broker: EventBroker,
@@ -639,6 +649,7 @@ mod tests {
             split_store,
             merge_policy: default_merge_policy(),
             retention_policy: None,
+            index_sort: Vec::new(),
             max_concurrent_split_uploads: 2,
             merge_io_throughput_limiter_opt: None,
             event_broker: Default::default(),
@@ -33,7 +33,7 @@ mod publisher;
 mod sequencer;
 mod uploader;
 #[cfg(feature = "vrl")]
-mod vrl_processing;
+pub(crate) mod vrl_processing;
 
 pub use doc_processor::{DocProcessor, DocProcessorCounters};
 pub use index_serializer::IndexSerializer;
@@ -17,7 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::io;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -28,16 +28,21 @@ use fail::fail_point;
 use itertools::Itertools;
 use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox, QueueCapacity};
 use quickwit_common::runtimes::RuntimeType;
+use quickwit_common::shared_consts::SPLIT_BLOOM_FILTERS_FILE_NAME;
 use quickwit_common::temp_dir::TempDirectory;
 use quickwit_directories::write_hotcache;
+use quickwit_doc_mapper::bloom_filter_pruning::{BloomFilter, SplitBloomFilters};
 use quickwit_doc_mapper::tag_pruning::append_to_tag_set;
+use quickwit_doc_mapper::zone_map_pruning::{ZoneMap, ZoneMapValue};
 use quickwit_doc_mapper::NamedField;
 use quickwit_proto::search::{
     serialize_split_fields, ListFieldType, ListFields, ListFieldsEntryResponse,
 };
+use tantivy::columnar::{DynamicColumn, HasAssociatedColumnType};
+use tantivy::fastfield::Column;
 use tantivy::index::FieldMetadata;
 use tantivy::schema::{FieldType, Type};
-use tantivy::{InvertedIndexReader, ReloadPolicy, SegmentMeta};
+use tantivy::{DateTime, InvertedIndexReader, ReloadPolicy, SegmentMeta, SegmentReader};
 use tokio::runtime::Handle;
 use tracing::{debug, info, instrument, warn};
 
@@ -59,6 +64,8 @@ use crate::models::{
 /// This includes the following steps:
 /// - commit: this step is CPU heavy
 /// - identifying the list of tags for the splits, and labelling it accordingly
+/// - computing the zone maps of the split
+/// - building the bloom filters of the split
 /// - creating a bundle file
 /// - computing the hotcache
 /// - appending it to the split file.
@@ -70,18 +77,26 @@ pub struct Packager {
     uploader_mailbox: Mailbox<Uploader>,
     /// List of tag fields ([`Vec<NamedField>`]) defined in the index config.
     tag_fields: Vec<NamedField>,
+    /// List of zone map fields ([`Vec<NamedField>`]) defined in the index config.
+    zone_map_fields: Vec<NamedField>,
+    /// List of bloom filter fields ([`Vec<NamedField>`]) defined in the index config.
+    bloom_filter_fields: Vec<NamedField>,
 }
 
 impl Packager {
     pub fn new(
         actor_name: &'static str,
         tag_fields: Vec<NamedField>,
+        zone_map_fields: Vec<NamedField>,
+        bloom_filter_fields: Vec<NamedField>,
         uploader_mailbox: Mailbox<Uploader>,
     ) -> Packager {
         Packager {
             actor_name,
             uploader_mailbox,
             tag_fields,
+            zone_map_fields,
+            bloom_filter_fields,
         }
     }
 
@@ -92,8 +107,14 @@ impl Packager {
     ) -> anyhow::Result<PackagedSplit> {
         let segment_metas = split.index.searchable_segment_metas()?;
         assert_eq!(segment_metas.len(), 1);
-        let packaged_split =
-            create_packaged_split(&segment_metas[..], split, &self.tag_fields, ctx)?;
+        let packaged_split = create_packaged_split(
+            &segment_metas[..],
+            split,
+            &self.tag_fields,
+            &self.zone_map_fields,
+            &self.bloom_filter_fields,
+            ctx,
+        )?;
         Ok(packaged_split)
     }
 }
@@ -273,14 +294,104 @@ fn try_extract_terms(
     Ok(terms)
 }
 
+/// Computes the min and max values of a fast field over the segments of a split.
+///
+/// Returns `None` if the field has no value in the split, or if it contains NaN or infinite
+/// values.
+fn try_extract_zone_map(
+    named_field: &NamedField,
+    segment_readers: &[SegmentReader],
+) -> anyhow::Result<Option<ZoneMap>> {
+    let min_max_opt = match named_field.field_type {
+        FieldType::U64(_) => column_min_max::<u64>(&named_field.name, segment_readers)?
+            .map(|(min, max)| (ZoneMapValue::U64(min), ZoneMapValue::U64(max))),
+        FieldType::I64(_) => column_min_max::<i64>(&named_field.name, segment_readers)?
+            .map(|(min, max)| (ZoneMapValue::I64(min), ZoneMapValue::I64(max))),
+        FieldType::F64(_) => column_min_max::<f64>(&named_field.name, segment_readers)?
+            .filter(|(min, max)| min.is_finite() && max.is_finite())
+            .map(|(min, max)| (ZoneMapValue::F64(min), ZoneMapValue::F64(max))),
+        FieldType::Date(_) => {
+            column_min_max::<DateTime>(&named_field.name, segment_readers)?.map(|(min, max)| {
+                (
+                    ZoneMapValue::Datetime(min.into_timestamp_nanos()),
+                    ZoneMapValue::Datetime(max.into_timestamp_nanos()),
+                )
+            })
+        }
+        _ => bail!(
+            "zone maps are not supported on `{}` fields",
+            named_field.field_type.value_type().name().to_lowercase()
+        ),
+    };
+    let zone_map_opt = min_max_opt.map(|(min, max)| ZoneMap { min, max });
+    Ok(zone_map_opt)
+}
+
+/// Builds a bloom filter over the terms of a field, as stored in its term dictionary.
+///
+/// Returns `None` if the field has no term in the split.
+fn try_build_bloom_filter(
+    inv_indexes: &[Arc<InvertedIndexReader>],
+) -> anyhow::Result<Option<BloomFilter>> {
+    let num_terms = inv_indexes
+        .iter()
+        .map(|inv_index| inv_index.terms().num_terms())
+        .sum::<usize>();
+    if num_terms == 0 {
+        return Ok(None);
+    }
+    // Terms present in several segments are counted several times, so the bloom filter may be
+    // slightly oversized.
+    let mut bloom_filter = BloomFilter::with_num_items(num_terms);
+    for inv_index in inv_indexes {
+        let mut terms_streamer = inv_index.terms().stream()?;
+        while let Some((term_data, _)) = terms_streamer.next() {
+            bloom_filter.insert(term_data);
+        }
+    }
+    Ok(Some(bloom_filter))
+}
+
+fn column_min_max<T>(
+    field_name: &str,
+    segment_readers: &[SegmentReader],
+) -> anyhow::Result<Option<(T, T)>>
+where
+    T: HasAssociatedColumnType,
+    DynamicColumn: Into<Option<Column<T>>>,
+{
+    let mut min_max_opt: Option<(T, T)> = None;
+
+    for segment_reader in segment_readers {
+        let Some(column) = segment_reader.fast_fields().column_opt::<T>(field_name)? else {
+            continue;
+        };
+        if column.values.num_vals() == 0 {
+            continue;
+        }
+        let (column_min, column_max) = (column.min_value(), column.max_value());
+
+        min_max_opt = Some(match min_max_opt {
+            Some((min, max)) => (
+                if column_min < min { column_min } else { min },
+                if column_max > max { column_max } else { max },
+            ),
+            None => (column_min, column_max),
+        });
+    }
+    Ok(min_max_opt)
+}
+
 fn create_packaged_split(
     segment_metas: &[SegmentMeta],
     split: IndexedSplit,
     tag_fields: &[NamedField],
+    zone_map_fields: &[NamedField],
+    bloom_filter_fields: &[NamedField],
     ctx: &ActorContext<Packager>,
 ) -> anyhow::Result<PackagedSplit> {
     debug!(split_id = split.split_id(), "create-packaged-split");
-    let split_files = list_split_files(segment_metas, &split.split_scratch_directory)?;
+    let mut split_files = list_split_files(segment_metas, &split.split_scratch_directory)?;
 
     // Extracts tag values from inverted indexes only when a field cardinality is less
     // than `MAX_VALUES_PER_TAG_FIELD`.
@@ -312,6 +423,53 @@ fn create_packaged_split(
         }
     }
 
+    debug!(split_id = split.split_id(), zone_map_fields =? zone_map_fields, "extract-zone-maps");
+    let mut zone_maps = BTreeMap::default();
+    let searcher = index_reader.searcher();
+
+    for named_field in zone_map_fields {
+        match try_extract_zone_map(named_field, searcher.segment_readers()) {
+            Ok(Some(zone_map)) => {
+                zone_maps.insert(named_field.name.clone(), zone_map);
+            }
+            Ok(None) => {}
+            Err(zone_map_extraction_error) => {
+                warn!(err=?zone_map_extraction_error, "no zone map will be registered in the split metadata");
+            }
+        }
+    }
+
+    ctx.record_progress();
+
+    debug!(split_id = split.split_id(), bloom_filter_fields =? bloom_filter_fields, "build-bloom-filters");
+    let mut split_bloom_filters = SplitBloomFilters::default();
+
+    for named_field in bloom_filter_fields {
+        let inverted_indexes = searcher
+            .segment_readers()
+            .iter()
+            .map(|segment| segment.inverted_index(named_field.field))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        match try_build_bloom_filter(&inverted_indexes) {
+            Ok(Some(bloom_filter)) => {
+                split_bloom_filters.insert(named_field.name.clone(), bloom_filter);
+            }
+            Ok(None) => {}
+            Err(bloom_filter_error) => {
+                warn!(err=?bloom_filter_error, "no bloom filter will be stored in the split");
+            }
+        }
+    }
+    if !split_bloom_filters.is_empty() {
+        // The bloom filters are bundled in the split file along with the index files.
+        let bloom_filters_path = split
+            .split_scratch_directory
+            .path()
+            .join(SPLIT_BLOOM_FILTERS_FILE_NAME);
+        std::fs::write(&bloom_filters_path, split_bloom_filters.serialize())?;
+        split_files.push(bloom_filters_path);
+    }
     ctx.record_progress();
 
     debug!(split_id = split.split_id(), "build-hotcache");
@@ -326,6 +484,7 @@ fn create_packaged_split(
         split_attrs: split.split_attrs,
         split_scratch_directory: split.split_scratch_directory,
         tags,
+        zone_maps,
         split_files,
         hotcache_bytes,
     };
@@ -462,6 +621,8 @@ mod tests {
             schema_builder.add_f64_field("tag_f64", NumericOptions::default().set_indexed());
         let tag_bool =
             schema_builder.add_bool_field("tag_bool", NumericOptions::default().set_indexed());
+        let status_code = schema_builder.add_u64_field("status_code", FAST);
+        let latency_ms = schema_builder.add_f64_field("latency_ms", FAST);
         let schema = schema_builder.build();
         let index_builder = IndexBuilder::new()
             .settings(IndexSettings::default())
@@ -492,6 +653,8 @@ mod tests {
                     tag_i64 => -42i64,
                     tag_f64 => -42.02f64,
                     tag_bool => true,
+                    status_code => 200 + num as u64,
+                    latency_ms => num as f64 / 2.0,
                 );
                 index_writer.add_document(doc)?;
                 num_docs += 1;
@@ -528,6 +691,7 @@ mod tests {
                 replaced_split_ids: Vec::new(),
                 delete_opstamp: 0,
                 num_merge_ops: 0,
+                index_sort: Vec::new(),
             },
             index,
             split_scratch_directory,
@@ -566,7 +730,17 @@ mod tests {
                 "tag_str", "tag_many", "tag_u64", "tag_i64", "tag_f64", "tag_bool",
             ],
         );
-        let packager = Packager::new("TestPackager", tag_fields, mailbox);
+        let zone_map_fields =
+            get_tag_fields(indexed_split.index.schema(), &["status_code", "latency_ms"]);
+        let bloom_filter_fields =
+            get_tag_fields(indexed_split.index.schema(), &["tag_many", "tag_u64"]);
+        let packager = Packager::new(
+            "TestPackager",
+            tag_fields,
+            zone_map_fields,
+            bloom_filter_fields,
+            mailbox,
+        );
         let (packager_mailbox, packager_handle) = universe.spawn_builder().spawn(packager);
         packager_mailbox
             .send_message(IndexedSplitBatch {
@@ -610,6 +784,36 @@ mod tests {
                     ..=DateTime::from_timestamp_secs(1628203640)
             )
         );
+        assert_eq!(
+            split.zone_maps,
+            BTreeMap::from([
+                (
+                    "latency_ms".to_string(),
+                    ZoneMap {
+                        min: ZoneMapValue::F64(0.5),
+                        max: ZoneMapValue::F64(4.5),
+                    }
+                ),
+                (
+                    "status_code".to_string(),
+                    ZoneMap {
+                        min: ZoneMapValue::U64(201),
+                        max: ZoneMapValue::U64(209),
+                    }
+                ),
+            ])
+        );
+        let bloom_filters_path = split.split_files.last().unwrap();
+        assert!(bloom_filters_path.ends_with(SPLIT_BLOOM_FILTERS_FILE_NAME));
+        let split_bloom_filters =
+            SplitBloomFilters::deserialize(&std::fs::read(bloom_filters_path)?)?;
+        let tag_many_bloom_filter = split_bloom_filters.get("tag_many").unwrap();
+        for num in 1..10 {
+            assert!(tag_many_bloom_filter.may_contain(format!("many-{num}").as_bytes()));
+        }
+        let tag_u64_bloom_filter = split_bloom_filters.get("tag_u64").unwrap();
+        assert!(tag_u64_bloom_filter.may_contain(&42u64.to_be_bytes()));
+        assert!(split_bloom_filters.get("tag_str").is_none());
         universe.assert_quit().await;
         Ok(())
     }
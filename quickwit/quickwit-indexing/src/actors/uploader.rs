@@ -66,6 +66,7 @@ pub enum UploaderType {
     IndexUploader,
     MergeUploader,
     DeleteUploader,
+    ReindexUploader,
 }
 
 /// [`SplitsUpdateMailbox`] wraps either a [`Mailbox<Sequencer>`] or [`Mailbox<Publisher>`].
@@ -218,7 +219,7 @@ impl Uploader {
                         .available_concurrent_upload_permits
                         .with_label_values(["merger"]),
                 ),
-                UploaderType::DeleteUploader => (
+                UploaderType::DeleteUploader | UploaderType::ReindexUploader => (
                     &CONCURRENT_UPLOAD_PERMITS_MERGE,
                     INDEXER_METRICS
                         .available_concurrent_upload_permits
@@ -333,6 +334,7 @@ impl Handler<PackagedSplitBatch> for Uploader {
                         retention_policy.as_ref(),
                         &packaged_split.split_attrs,
                         packaged_split.tags.clone(),
+                        packaged_split.zone_maps.clone(),
                         split_streamer.footer_range.start..split_streamer.footer_range.end,
                     );
 
@@ -573,10 +575,12 @@ mod tests {
                         split_id: "test-split".to_string(),
                         delete_opstamp: 10,
                         num_merge_ops: 0,
+                        index_sort: Vec::new(),
                     },
                     serialized_split_fields: Vec::new(),
                     split_scratch_directory,
                     tags: Default::default(),
+                    zone_maps: Default::default(),
                     hotcache_bytes: Vec::new(),
                     split_files: Vec::new(),
                 }],
@@ -687,10 +691,12 @@ mod tests {
                 ],
                 delete_opstamp: 0,
                 num_merge_ops: 0,
+                index_sort: Vec::new(),
             },
             serialized_split_fields: Vec::new(),
             split_scratch_directory: split_scratch_directory_1,
             tags: Default::default(),
+            zone_maps: Default::default(),
             split_files: Vec::new(),
             hotcache_bytes: Vec::new(),
         };
@@ -714,10 +720,12 @@ mod tests {
                 ],
                 delete_opstamp: 0,
                 num_merge_ops: 0,
+                index_sort: Vec::new(),
             },
             serialized_split_fields: Vec::new(),
             split_scratch_directory: split_scratch_directory_2,
             tags: Default::default(),
+            zone_maps: Default::default(),
             split_files: Vec::new(),
             hotcache_bytes: Vec::new(),
         };
@@ -834,10 +842,12 @@ mod tests {
                         replaced_split_ids: Vec::new(),
                         delete_opstamp: 10,
                         num_merge_ops: 0,
+                        index_sort: Vec::new(),
                     },
                     serialized_split_fields: Vec::new(),
                     split_scratch_directory,
                     tags: Default::default(),
+                    zone_maps: Default::default(),
                     hotcache_bytes: Vec::new(),
                     split_files: Vec::new(),
                 }],
@@ -1016,10 +1026,12 @@ mod tests {
                         split_id: SPLIT_ULID_STR.to_string(),
                         delete_opstamp: 10,
                         num_merge_ops: 0,
+                        index_sort: Vec::new(),
                     },
                     serialized_split_fields: Vec::new(),
                     split_scratch_directory,
                     tags: Default::default(),
+                    zone_maps: Default::default(),
                     hotcache_bytes: Vec::new(),
                     split_files: Vec::new(),
                 }],
@@ -29,7 +29,7 @@ pub use vrl::value::{Secrets as VrlSecrets, Value as VrlValue};
 
 use super::doc_processor::DocProcessorError;
 
-pub(super) struct VrlDoc {
+pub(crate) struct VrlDoc {
     pub vrl_value: VrlValue,
     pub num_bytes: usize,
 }
@@ -43,7 +43,7 @@ impl VrlDoc {
     }
 }
 
-pub(super) struct VrlProgram {
+pub(crate) struct VrlProgram {
     program: Program,
     timezone: TimeZone,
     runtime: Runtime,
@@ -0,0 +1,260 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use anyhow::{bail, Context};
+use quickwit_config::IndexSortField;
+use quickwit_proto::search::SortOrder;
+use tantivy::columnar::{Column, MonotonicallyMappableToU64};
+use tantivy::schema::{DateTimePrecision, Field, FieldType, Schema, Value};
+use tantivy::{DocId, SegmentReader, TantivyDocument};
+
+/// Sorts the documents of a split by the fast fields of the `sort_by` indexing setting.
+///
+/// Documents are compared on the `u64` representation of their fast field values, which
+/// preserves the order of the original values, so that keys computed from a document being
+/// indexed and keys read from the fast fields of a split are consistent.
+#[derive(Clone, Debug)]
+pub(crate) struct IndexSorter {
+    sort_fields: Vec<IndexSorterField>,
+}
+
+#[derive(Clone, Debug)]
+struct IndexSorterField {
+    field: Field,
+    field_name: String,
+    value_type: SortValueType,
+    order: SortOrder,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum SortValueType {
+    U64,
+    I64,
+    F64,
+    Bool,
+    DateTime(DateTimePrecision),
+}
+
+/// Sort key of a document. Sort keys compare in the order defined by the index sort.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub(crate) struct SortKey(Vec<SortKeyValue>);
+
+/// Documents missing a value come last, whatever the sort order.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+enum SortKeyValue {
+    Present(u64),
+    Missing,
+}
+
+impl SortKeyValue {
+    fn new(value_opt: Option<u64>, order: SortOrder) -> Self {
+        match (value_opt, order) {
+            (Some(value), SortOrder::Asc) => SortKeyValue::Present(value),
+            (Some(value), SortOrder::Desc) => SortKeyValue::Present(!value),
+            (None, _) => SortKeyValue::Missing,
+        }
+    }
+}
+
+impl IndexSorter {
+    /// Returns `None` if the documents are not sorted, i.e. `sort_by` is empty.
+    pub fn new(sort_by: &[IndexSortField], schema: &Schema) -> anyhow::Result<Option<Self>> {
+        if sort_by.is_empty() {
+            return Ok(None);
+        }
+        let mut sort_fields = Vec::with_capacity(sort_by.len());
+
+        for sort_field in sort_by {
+            let field = schema
+                .get_field(&sort_field.field)
+                .with_context(|| format!("unknown index sort field `{}`", sort_field.field))?;
+            let value_type = match schema.get_field_entry(field).field_type() {
+                FieldType::U64(_) => SortValueType::U64,
+                FieldType::I64(_) => SortValueType::I64,
+                FieldType::F64(_) => SortValueType::F64,
+                FieldType::Bool(_) => SortValueType::Bool,
+                FieldType::Date(date_options) => {
+                    SortValueType::DateTime(date_options.get_precision())
+                }
+                _ => bail!(
+                    "index sort field `{}` must be of type `u64`, `i64`, `f64`, `bool`, or \
+                     `datetime`",
+                    sort_field.field
+                ),
+            };
+            sort_fields.push(IndexSorterField {
+                field,
+                field_name: sort_field.field.clone(),
+                value_type,
+                order: sort_field.order,
+            });
+        }
+        Ok(Some(Self { sort_fields }))
+    }
+
+    /// Returns the sort fields, as recorded in the metadata of the sorted splits.
+    pub fn index_sort(&self) -> Vec<IndexSortField> {
+        self.sort_fields
+            .iter()
+            .map(|sort_field| IndexSortField {
+                field: sort_field.field_name.clone(),
+                order: sort_field.order,
+            })
+            .collect()
+    }
+
+    /// Computes the sort key of a document about to be indexed. Multivalued fields are sorted by
+    /// their first value, like their fast field.
+    pub fn sort_key_from_doc(&self, doc: &TantivyDocument) -> SortKey {
+        let sort_key_values = self
+            .sort_fields
+            .iter()
+            .map(|sort_field| {
+                let value_opt = doc.get_first(sort_field.field).and_then(|value| {
+                    match sort_field.value_type {
+                        SortValueType::U64 => value.as_u64(),
+                        SortValueType::I64 => value.as_i64().map(i64::to_u64),
+                        SortValueType::F64 => value.as_f64().map(f64::to_u64),
+                        SortValueType::Bool => value.as_bool().map(bool::to_u64),
+                        // Fast fields store datetimes truncated to their precision.
+                        SortValueType::DateTime(precision) => value
+                            .as_datetime()
+                            .map(|datetime| datetime.truncate(precision).to_u64()),
+                    }
+                });
+                SortKeyValue::new(value_opt, sort_field.order)
+            })
+            .collect();
+        SortKey(sort_key_values)
+    }
+
+    /// Opens the fast fields of the sort fields of a segment in order to compute the sort keys of
+    /// its documents.
+    pub fn segment_sort_keys(
+        &self,
+        segment_reader: &SegmentReader,
+    ) -> anyhow::Result<SegmentSortKeys> {
+        let mut columns = Vec::with_capacity(self.sort_fields.len());
+
+        for sort_field in &self.sort_fields {
+            let column_opt = segment_reader
+                .fast_fields()
+                .u64_lenient(&sort_field.field_name)?
+                .map(|(column, _column_type)| column);
+            columns.push((column_opt, sort_field.order));
+        }
+        Ok(SegmentSortKeys { columns })
+    }
+}
+
+/// Computes the sort keys of the documents of a segment from their fast fields.
+pub(crate) struct SegmentSortKeys {
+    columns: Vec<(Option<Column<u64>>, SortOrder)>,
+}
+
+impl SegmentSortKeys {
+    pub fn sort_key(&self, doc_id: DocId) -> SortKey {
+        let sort_key_values = self
+            .columns
+            .iter()
+            .map(|(column_opt, order)| {
+                let value_opt = column_opt.as_ref().and_then(|column| column.first(doc_id));
+                SortKeyValue::new(value_opt, *order)
+            })
+            .collect();
+        SortKey(sort_key_values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::schema::{DateOptions, NumericOptions, FAST};
+    use tantivy::{doc, DateTime, Index};
+
+    use super::*;
+
+    fn sort_field(field: &str, order: SortOrder) -> IndexSortField {
+        IndexSortField {
+            field: field.to_string(),
+            order,
+        }
+    }
+
+    #[test]
+    fn test_index_sorter_sort_keys() {
+        let mut schema_builder = Schema::builder();
+        let severity_field = schema_builder.add_i64_field("severity", NumericOptions::from(FAST));
+        let timestamp_field = schema_builder.add_date_field(
+            "timestamp",
+            DateOptions::from(FAST).set_precision(DateTimePrecision::Seconds),
+        );
+        let schema = schema_builder.build();
+
+        let index_sorter = IndexSorter::new(
+            &[
+                sort_field("severity", SortOrder::Asc),
+                sort_field("timestamp", SortOrder::Desc),
+            ],
+            &schema,
+        )
+        .unwrap()
+        .unwrap();
+
+        let docs = [
+            doc!(severity_field => 3i64, timestamp_field => DateTime::from_timestamp_millis(1_000)),
+            doc!(timestamp_field => DateTime::from_timestamp_millis(5_000)),
+            doc!(severity_field => -2i64, timestamp_field => DateTime::from_timestamp_millis(1_000)),
+            doc!(severity_field => 3i64, timestamp_field => DateTime::from_timestamp_millis(2_000)),
+            // Same second as the first document: the timestamps compare equal.
+            doc!(severity_field => 3i64, timestamp_field => DateTime::from_timestamp_millis(1_999)),
+        ];
+        let mut doc_sort_keys: Vec<(SortKey, usize)> = docs
+            .iter()
+            .enumerate()
+            .map(|(doc_ord, doc)| (index_sorter.sort_key_from_doc(doc), doc_ord))
+            .collect();
+        doc_sort_keys.sort();
+        let doc_ords: Vec<usize> = doc_sort_keys
+            .iter()
+            .map(|(_sort_key, doc_ord)| *doc_ord)
+            .collect();
+        assert_eq!(doc_ords, [2, 3, 0, 4, 1]);
+
+        let index = Index::create_in_ram(schema);
+        let mut index_writer = index.writer_for_tests().unwrap();
+        for doc in docs.iter() {
+            index_writer.add_document(doc.clone()).unwrap();
+        }
+        index_writer.commit().unwrap();
+        let searcher = index.reader().unwrap().searcher();
+        let segment_sort_keys = index_sorter
+            .segment_sort_keys(searcher.segment_reader(0))
+            .unwrap();
+
+        for (doc_sort_key, doc_ord) in &doc_sort_keys {
+            assert_eq!(&segment_sort_keys.sort_key(*doc_ord as DocId), doc_sort_key);
+        }
+    }
+
+    #[test]
+    fn test_index_sorter_without_sort_fields() {
+        let schema = Schema::builder().build();
+        assert!(IndexSorter::new(&[], &schema).unwrap().is_none());
+    }
+}
@@ -0,0 +1,62 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use quickwit_config::TransformConfig;
+use quickwit_doc_mapper::JsonObject;
+
+#[cfg(feature = "vrl")]
+use crate::actors::vrl_processing::{VrlDoc, VrlProgram, VrlValue};
+
+/// Applies the VRL transform of a [`TransformConfig`] to JSON documents rewritten outside of an
+/// indexing pipeline, for instance by reindex operations.
+pub struct JsonDocTransform {
+    #[cfg(feature = "vrl")]
+    vrl_program: VrlProgram,
+}
+
+impl JsonDocTransform {
+    #[cfg(feature = "vrl")]
+    pub fn try_from_transform_config(transform_config: TransformConfig) -> anyhow::Result<Self> {
+        let vrl_program = VrlProgram::try_from_transform_config(transform_config)?;
+        Ok(Self { vrl_program })
+    }
+
+    #[cfg(not(feature = "vrl"))]
+    pub fn try_from_transform_config(_transform_config: TransformConfig) -> anyhow::Result<Self> {
+        anyhow::bail!("VRL is not enabled: please recompile with the `vrl` feature")
+    }
+
+    #[cfg(feature = "vrl")]
+    pub fn transform(&mut self, json_doc: JsonObject) -> anyhow::Result<JsonObject> {
+        let json_doc_bytes = serde_json::to_vec(&json_doc)?;
+        let vrl_value = serde_json::from_slice::<VrlValue>(&json_doc_bytes)?;
+        let vrl_doc = self
+            .vrl_program
+            .transform_doc(VrlDoc::new(vrl_value, json_doc_bytes.len()))?;
+        match serde_json::to_value(vrl_doc.vrl_value)? {
+            serde_json::Value::Object(json_doc) => Ok(json_doc),
+            _ => anyhow::bail!("transformed document is not a JSON object"),
+        }
+    }
+
+    #[cfg(not(feature = "vrl"))]
+    pub fn transform(&mut self, json_doc: JsonObject) -> anyhow::Result<JsonObject> {
+        Ok(json_doc)
+    }
+}
@@ -35,11 +35,14 @@ pub use crate::actors::{
     IndexingPipelineParams, IndexingService, PublisherType, Sequencer, SplitsUpdateMailbox,
 };
 pub use crate::controlled_directory::ControlledDirectory;
+pub use crate::json_doc_transform::JsonDocTransform;
 use crate::models::IndexingStatistics;
 pub use crate::split_store::{get_tantivy_directory_from_split_bundle, IndexingSplitStore};
 
 pub mod actors;
 mod controlled_directory;
+mod index_sorter;
+mod json_doc_transform;
 pub mod merge_policy;
 mod metrics;
 pub mod models;
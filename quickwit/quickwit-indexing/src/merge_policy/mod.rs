@@ -20,6 +20,7 @@
 mod const_write_amplification;
 mod nop_merge_policy;
 mod stable_log_merge_policy;
+mod time_partitioned_merge_policy;
 
 use std::fmt;
 use std::ops::Deref;
@@ -35,6 +36,7 @@ use quickwit_proto::types::SplitId;
 use serde::Serialize;
 pub(crate) use stable_log_merge_policy::StableLogMergePolicy;
 use tantivy::TrackedObject;
+pub(crate) use time_partitioned_merge_policy::TimePartitionedMergePolicy;
 use tracing::{info_span, Span};
 
 use crate::actors::MergePermit;
@@ -44,6 +46,7 @@ use crate::new_split_id;
 pub enum MergeOperationType {
     Merge,
     DeleteAndMerge,
+    Reindex,
 }
 
 impl fmt::Display for MergeOperationType {
@@ -123,6 +126,17 @@ impl MergeOperation {
         }
     }
 
+    pub fn new_reindex_operation(split: SplitMetadata) -> Self {
+        let merge_split_id = new_split_id();
+        let merge_parent_span = info_span!("reindex", merge_split_id=%merge_split_id, split_ids=?split.split_id(), typ=%MergeOperationType::Reindex);
+        Self {
+            merge_parent_span,
+            merge_split_id,
+            splits: vec![split],
+            operation_type: MergeOperationType::Reindex,
+        }
+    }
+
     pub fn splits_as_slice(&self) -> &[SplitMetadata] {
         self.splits.as_slice()
     }
@@ -189,6 +203,11 @@ pub fn merge_policy_from_settings(settings: &IndexingSettings) -> Arc<dyn MergeP
             let merge_policy = StableLogMergePolicy::new(config, settings.split_num_docs_target);
             Arc::new(merge_policy)
         }
+        MergePolicyConfig::TimePartitioned(config) => {
+            let merge_policy =
+                TimePartitionedMergePolicy::new(config, settings.split_num_docs_target);
+            Arc::new(merge_policy)
+        }
     }
 }
 
@@ -220,7 +239,7 @@ fn splits_short_debug(splits: &[SplitMetadata]) -> Vec<SplitShortDebug> {
 pub mod tests {
 
     use std::collections::hash_map::DefaultHasher;
-    use std::collections::{BTreeSet, HashMap};
+    use std::collections::{BTreeMap, BTreeSet, HashMap};
     use std::hash::Hasher;
     use std::ops::RangeInclusive;
 
@@ -396,7 +415,14 @@ pub mod tests {
             source_id: "test_source".to_string(),
         };
         let split_attrs = merge_split_attrs(pipeline_id, merged_split_id, splits).unwrap();
-        create_split_metadata(merge_policy, None, &split_attrs, tags, 0..0)
+        create_split_metadata(
+            merge_policy,
+            None,
+            &split_attrs,
+            tags,
+            BTreeMap::new(),
+            0..0,
+        )
     }
 
     fn apply_merge(
@@ -0,0 +1,339 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+
+use quickwit_config::merge_policy_config::{
+    ConstWriteAmplificationMergePolicyConfig, TimePartitionedMergePolicyConfig,
+};
+use quickwit_metastore::{SplitMaturity, SplitMetadata};
+use time::OffsetDateTime;
+
+use super::{ConstWriteAmplificationMergePolicy, MergeOperation};
+use crate::merge_policy::MergePolicy;
+
+/// Bucket a split belongs to.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+enum SplitBucket {
+    /// The split does not have a time range (the index has no timestamp field).
+    NoTimeRange,
+    /// All the documents of the split belong to the same time bucket.
+    Bucket(i64),
+    /// The split time range overlaps the time buckets from `start` to `end`. Such splits are only
+    /// merged with the splits spanning the same buckets.
+    Spanning { start: i64, end: i64 },
+}
+
+/// The `TimePartitionedMergePolicy` has been designed for time series data with
+/// late-arriving documents.
+///
+/// Splits are grouped by calendar bucket (hour or day, in UTC) based on their
+/// `time_range`, and a merge operation never involves splits belonging to different buckets.
+/// This keeps the time range of merged splits tight, which preserves time-range pruning
+/// at search time and lets the retention policy delete data as soon as a bucket expires.
+///
+/// Splits whose time range already spans several buckets, for instance because they contain
+/// late-arriving documents, are only merged with the splits spanning exactly the same buckets, so
+/// that their wide time range neither leaks into the buckets nor keeps growing with each merge.
+///
+/// Within a bucket, splits are merged following the same logic as the
+/// `ConstWriteAmplificationMergePolicy`: only splits with the same number of merge
+/// operations are merged together, up to `max_merge_ops` merges.
+#[derive(Debug, Clone)]
+pub struct TimePartitionedMergePolicy {
+    config: TimePartitionedMergePolicyConfig,
+    bucket_merge_policy: ConstWriteAmplificationMergePolicy,
+}
+
+impl TimePartitionedMergePolicy {
+    pub fn new(config: TimePartitionedMergePolicyConfig, split_num_docs_target: usize) -> Self {
+        let bucket_merge_policy_config = ConstWriteAmplificationMergePolicyConfig {
+            merge_factor: config.merge_factor,
+            max_merge_factor: config.max_merge_factor,
+            max_merge_ops: config.max_merge_ops,
+            maturation_period: config.maturation_period,
+            max_finalize_merge_operations: 0,
+            max_finalize_split_num_docs: None,
+        };
+        let bucket_merge_policy = ConstWriteAmplificationMergePolicy::new(
+            bucket_merge_policy_config,
+            split_num_docs_target,
+        );
+        TimePartitionedMergePolicy {
+            config,
+            bucket_merge_policy,
+        }
+    }
+
+    #[cfg(test)]
+    fn for_test() -> TimePartitionedMergePolicy {
+        use std::time::Duration;
+
+        use quickwit_config::merge_policy_config::TimeBucket;
+
+        let config = TimePartitionedMergePolicyConfig {
+            time_bucket: TimeBucket::Hour,
+            merge_factor: 3,
+            max_merge_factor: 5,
+            max_merge_ops: 3,
+            maturation_period: Duration::from_secs(3600),
+        };
+        Self::new(config, 10_000_000)
+    }
+
+    fn split_bucket(&self, split: &SplitMetadata) -> SplitBucket {
+        let Some(time_range) = &split.time_range else {
+            return SplitBucket::NoTimeRange;
+        };
+        let bucket_secs = self.config.time_bucket.as_secs();
+        let start_bucket = time_range.start().div_euclid(bucket_secs);
+        let end_bucket = time_range.end().div_euclid(bucket_secs);
+
+        if start_bucket == end_bucket {
+            SplitBucket::Bucket(start_bucket)
+        } else {
+            SplitBucket::Spanning {
+                start: start_bucket,
+                end: end_bucket,
+            }
+        }
+    }
+}
+
+impl MergePolicy for TimePartitionedMergePolicy {
+    fn operations(&self, splits: &mut Vec<SplitMetadata>) -> Vec<MergeOperation> {
+        let mut group_by_bucket: HashMap<SplitBucket, Vec<SplitMetadata>> = HashMap::default();
+        let mut excluded_splits = Vec::new();
+        let now = OffsetDateTime::now_utc();
+
+        for split in splits.drain(..) {
+            if split.is_mature(now) {
+                excluded_splits.push(split);
+                continue;
+            }
+            let bucket = self.split_bucket(&split);
+            group_by_bucket.entry(bucket).or_default().push(split);
+        }
+        splits.extend(excluded_splits);
+
+        let mut merge_operations = Vec::new();
+        for splits_in_bucket in group_by_bucket.values_mut() {
+            let merge_ops = self.bucket_merge_policy.operations(splits_in_bucket);
+            merge_operations.extend(merge_ops);
+            // we readd the splits that are not used in a merge operation into the splits vector.
+            splits.append(splits_in_bucket);
+        }
+        merge_operations
+    }
+
+    fn split_maturity(&self, split_num_docs: usize, split_num_merge_ops: usize) -> SplitMaturity {
+        self.bucket_merge_policy
+            .split_maturity(split_num_docs, split_num_merge_ops)
+    }
+
+    #[cfg(test)]
+    fn check_is_valid(&self, merge_op: &MergeOperation, remaining_splits: &[SplitMetadata]) {
+        use std::collections::HashSet;
+
+        let buckets: HashSet<SplitBucket> = merge_op
+            .splits_as_slice()
+            .iter()
+            .map(|split| self.split_bucket(split))
+            .collect();
+        assert_eq!(buckets.len(), 1);
+        self.bucket_merge_policy
+            .check_is_valid(merge_op, remaining_splits);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::RangeInclusive;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use quickwit_metastore::{SplitMaturity, SplitMetadata};
+    use time::OffsetDateTime;
+
+    use super::TimePartitionedMergePolicy;
+    use crate::MergePolicy;
+
+    const HOUR: i64 = 3_600;
+
+    fn create_split(
+        merge_policy: &TimePartitionedMergePolicy,
+        split_ord: usize,
+        time_range: Option<RangeInclusive<i64>>,
+    ) -> SplitMetadata {
+        SplitMetadata {
+            split_id: format!("split_{split_ord:02}"),
+            num_docs: 1_000,
+            time_range,
+            create_timestamp: OffsetDateTime::now_utc().unix_timestamp(),
+            maturity: merge_policy.split_maturity(1_000, 0),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_time_partitioned_merge_policy_split_maturity() {
+        let merge_policy = TimePartitionedMergePolicy::for_test();
+        assert_eq!(
+            merge_policy.split_maturity(1_000, 0),
+            SplitMaturity::Immature {
+                maturation_period: Duration::from_secs(3600)
+            }
+        );
+        assert_eq!(
+            merge_policy.split_maturity(10_000_000, 0),
+            SplitMaturity::Mature
+        );
+        assert_eq!(merge_policy.split_maturity(1_000, 3), SplitMaturity::Mature);
+    }
+
+    #[test]
+    fn test_time_partitioned_merge_policy_empty() {
+        let merge_policy = TimePartitionedMergePolicy::for_test();
+        let mut splits = Vec::new();
+        assert!(merge_policy.operations(&mut splits).is_empty());
+    }
+
+    #[test]
+    fn test_time_partitioned_merge_policy_merges_within_bucket() {
+        let merge_policy = TimePartitionedMergePolicy::for_test();
+        let mut splits: Vec<SplitMetadata> = (0..3)
+            .map(|split_ord| {
+                let start = 10 * HOUR + split_ord as i64 * 60;
+                create_split(&merge_policy, split_ord, Some(start..=start + 30))
+            })
+            .collect();
+        let merge_ops = merge_policy.operations(&mut splits);
+        assert!(splits.is_empty());
+        assert_eq!(merge_ops.len(), 1);
+        assert_eq!(merge_ops[0].splits_as_slice().len(), 3);
+    }
+
+    #[test]
+    fn test_time_partitioned_merge_policy_never_merges_across_buckets() {
+        let merge_policy = TimePartitionedMergePolicy::for_test();
+        // Two splits per hour: no bucket reaches the merge factor.
+        let mut splits: Vec<SplitMetadata> = (0..6)
+            .map(|split_ord| {
+                let start = (split_ord as i64 % 3) * HOUR;
+                create_split(&merge_policy, split_ord, Some(start..=start + 60))
+            })
+            .collect();
+        assert!(merge_policy.operations(&mut splits).is_empty());
+        assert_eq!(splits.len(), 6);
+
+        // A late split lands in the first hour, which now reaches the merge factor.
+        splits.push(create_split(&merge_policy, 6, Some(HOUR - 60..=HOUR - 1)));
+        let merge_ops = merge_policy.operations(&mut splits);
+        assert_eq!(merge_ops.len(), 1);
+        let mut merged_split_ids: Vec<&str> = merge_ops[0]
+            .splits_as_slice()
+            .iter()
+            .map(|split| split.split_id())
+            .collect();
+        merged_split_ids.sort();
+        assert_eq!(merged_split_ids, &["split_00", "split_03", "split_06"]);
+        assert_eq!(splits.len(), 4);
+    }
+
+    #[test]
+    fn test_time_partitioned_merge_policy_merges_spanning_splits_together() {
+        let merge_policy = TimePartitionedMergePolicy::for_test();
+        // Two splits in the first hour, and three splits spanning the first two hours.
+        let mut splits: Vec<SplitMetadata> = vec![
+            create_split(&merge_policy, 0, Some(60..=120)),
+            create_split(&merge_policy, 1, Some(180..=240)),
+            create_split(&merge_policy, 2, Some(HOUR - 60..=HOUR + 60)),
+            create_split(&merge_policy, 3, Some(0..=2 * HOUR - 1)),
+            create_split(&merge_policy, 4, Some(HOUR - 1..=HOUR)),
+        ];
+        let merge_ops = merge_policy.operations(&mut splits);
+        assert_eq!(merge_ops.len(), 1);
+        let mut merged_split_ids: Vec<&str> = merge_ops[0]
+            .splits_as_slice()
+            .iter()
+            .map(|split| split.split_id())
+            .collect();
+        merged_split_ids.sort();
+        assert_eq!(merged_split_ids, &["split_02", "split_03", "split_04"]);
+        assert_eq!(splits.len(), 2);
+    }
+
+    #[test]
+    fn test_time_partitioned_merge_policy_never_merges_distant_spanning_splits() {
+        let merge_policy = TimePartitionedMergePolicy::for_test();
+        // Spanning splits overlapping different buckets, for instance a split with a few late
+        // documents from the previous day.
+        let mut splits: Vec<SplitMetadata> = vec![
+            create_split(&merge_policy, 0, Some(HOUR - 60..=HOUR + 60)),
+            create_split(&merge_policy, 1, Some(HOUR - 30..=HOUR + 30)),
+            create_split(&merge_policy, 2, Some(HOUR - 60..=24 * HOUR + 60)),
+            create_split(&merge_policy, 3, Some(30 * HOUR - 60..=30 * HOUR + 60)),
+        ];
+        assert!(merge_policy.operations(&mut splits).is_empty());
+        assert_eq!(splits.len(), 4);
+    }
+
+    #[test]
+    fn test_time_partitioned_merge_policy_splits_without_time_range() {
+        let merge_policy = TimePartitionedMergePolicy::for_test();
+        let mut splits: Vec<SplitMetadata> = (0..3)
+            .map(|split_ord| create_split(&merge_policy, split_ord, None))
+            .collect();
+        let merge_ops = merge_policy.operations(&mut splits);
+        assert_eq!(merge_ops.len(), 1);
+        assert_eq!(merge_ops[0].splits_as_slice().len(), 3);
+    }
+
+    #[test]
+    fn test_time_partitioned_merge_policy_proptest() {
+        let merge_policy = TimePartitionedMergePolicy::for_test();
+        crate::merge_policy::tests::proptest_merge_policy(&merge_policy);
+    }
+
+    #[tokio::test]
+    async fn test_simulate_time_partitioned_merge_policy() -> anyhow::Result<()> {
+        let merge_policy = TimePartitionedMergePolicy::for_test();
+        let vals = vec![1; 500]; //< 500 splits with a single doc each.
+
+        // The simulated splits cover 1_000 seconds each: some of them span two consecutive hours.
+        // Merged splits must not span more hours than the splits they were merged from.
+        let check_merged_splits_within_bucket = |splits: &[SplitMetadata]| {
+            for split in splits {
+                let time_range = split.time_range.as_ref().unwrap();
+                let num_spanned_hours =
+                    time_range.end().div_euclid(HOUR) - time_range.start().div_euclid(HOUR) + 1;
+                assert!(num_spanned_hours <= 2);
+            }
+        };
+        let final_splits = crate::merge_policy::tests::aux_test_simulate_merge_planner_num_docs(
+            Arc::new(merge_policy.clone()),
+            &vals[..],
+            &check_merged_splits_within_bucket,
+        )
+        .await?;
+        check_merged_splits_within_bucket(&final_splits);
+        assert!(final_splits.len() < vals.len());
+        Ok(())
+    }
+}
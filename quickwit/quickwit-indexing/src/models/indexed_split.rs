@@ -27,21 +27,31 @@ use quickwit_metastore::checkpoint::IndexCheckpointDelta;
 use quickwit_proto::indexing::IndexingPipelineId;
 use quickwit_proto::types::{DocMappingUid, IndexUid, PublishToken};
 use tantivy::directory::MmapDirectory;
-use tantivy::IndexBuilder;
+use tantivy::{IndexBuilder, TantivyDocument};
 use tracing::{instrument, Span};
 
 use crate::controlled_directory::ControlledDirectory;
+use crate::index_sorter::{IndexSorter, SortKey};
 use crate::merge_policy::MergeTask;
 use crate::models::{PublishLock, SplitAttrs};
 use crate::new_split_id;
 
 pub struct IndexedSplitBuilder {
     pub split_attrs: SplitAttrs,
-    pub index_writer: tantivy::SingleSegmentIndexWriter,
+    index_writer: tantivy::SingleSegmentIndexWriter,
+    sorted_docs_opt: Option<SortedDocs>,
     pub split_scratch_directory: TempDirectory,
     pub controlled_directory_opt: Option<ControlledDirectory>,
 }
 
+/// Documents of a split whose index sorts its documents. They are buffered until the split is
+/// finalized and then added to the index writer in the order of the index sort.
+struct SortedDocs {
+    index_sorter: IndexSorter,
+    docs: Vec<(SortKey, TantivyDocument)>,
+    num_bytes: usize,
+}
+
 pub struct IndexedSplit {
     pub split_attrs: SplitAttrs,
     pub index: tantivy::Index,
@@ -85,6 +95,7 @@ impl IndexedSplitBuilder {
         doc_mapping_uid: DocMappingUid,
         scratch_directory: TempDirectory,
         index_builder: IndexBuilder,
+        index_sorter_opt: Option<IndexSorter>,
         io_controls: IoControls,
     ) -> anyhow::Result<Self> {
         // We avoid intermediary merge, and instead merge all segments in the packager.
@@ -101,6 +112,15 @@ impl IndexedSplitBuilder {
 
         let index_writer =
             index_builder.single_segment_index_writer(controlled_directory.clone(), 15_000_000)?;
+        let index_sort = index_sorter_opt
+            .as_ref()
+            .map(|index_sorter| index_sorter.index_sort())
+            .unwrap_or_default();
+        let sorted_docs_opt = index_sorter_opt.map(|index_sorter| SortedDocs {
+            index_sorter,
+            docs: Vec::new(),
+            num_bytes: 0,
+        });
         Ok(Self {
             split_attrs: SplitAttrs {
                 node_id: pipeline_id.node_id,
@@ -115,8 +135,10 @@ impl IndexedSplitBuilder {
                 time_range: None,
                 delete_opstamp: last_delete_opstamp,
                 num_merge_ops: 0,
+                index_sort,
             },
             index_writer,
+            sorted_docs_opt,
             split_scratch_directory,
             controlled_directory_opt: Some(controlled_directory),
         })
@@ -137,7 +159,20 @@ impl IndexedSplitBuilder {
         )
     )]
     pub fn finalize(self) -> anyhow::Result<IndexedSplit> {
-        let index = self.index_writer.finalize()?;
+        let mut index_writer = self.index_writer;
+
+        if let Some(mut sorted_docs) = self.sorted_docs_opt {
+            // The sort is stable: documents with the same sort key keep their indexing order.
+            sorted_docs
+                .docs
+                .sort_by(|(left_sort_key, _), (right_sort_key, _)| {
+                    left_sort_key.cmp(right_sort_key)
+                });
+            for (_sort_key, doc) in sorted_docs.docs {
+                index_writer.add_document(doc)?;
+            }
+        }
+        let index = index_writer.finalize()?;
         Ok(IndexedSplit {
             split_attrs: self.split_attrs,
             index,
@@ -146,6 +181,27 @@ impl IndexedSplitBuilder {
         })
     }
 
+    pub fn add_document(&mut self, doc: TantivyDocument, num_bytes: usize) -> tantivy::Result<()> {
+        if let Some(sorted_docs) = &mut self.sorted_docs_opt {
+            let sort_key = sorted_docs.index_sorter.sort_key_from_doc(&doc);
+            sorted_docs.docs.push((sort_key, doc));
+            sorted_docs.num_bytes += num_bytes;
+            return Ok(());
+        }
+        self.index_writer.add_document(doc)
+    }
+
+    /// Returns the memory used by the index writer, plus the size of the documents buffered in
+    /// order to be sorted.
+    pub fn mem_usage(&self) -> usize {
+        let buffered_num_bytes = self
+            .sorted_docs_opt
+            .as_ref()
+            .map(|sorted_docs| sorted_docs.num_bytes)
+            .unwrap_or(0);
+        self.index_writer.mem_usage() + buffered_num_bytes
+    }
+
     pub fn path(&self) -> &Path {
         self.split_scratch_directory.path()
     }
@@ -17,12 +17,13 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::path::PathBuf;
 
 use itertools::Itertools;
 use quickwit_common::temp_dir::TempDirectory;
+use quickwit_doc_mapper::zone_map_pruning::ZoneMap;
 use quickwit_metastore::checkpoint::IndexCheckpointDelta;
 use quickwit_proto::types::{IndexUid, PublishToken, SplitId};
 use tracing::Span;
@@ -35,6 +36,7 @@ pub struct PackagedSplit {
     pub split_attrs: SplitAttrs,
     pub split_scratch_directory: TempDirectory,
     pub tags: BTreeSet<String>,
+    pub zone_maps: BTreeMap<String, ZoneMap>,
     pub split_files: Vec<PathBuf>,
     pub hotcache_bytes: Vec<u8>,
 }
@@ -55,6 +57,7 @@ impl fmt::Debug for PackagedSplit {
             .field("split_attrs", &self.split_attrs)
             .field("split_scratch_directory", &self.split_scratch_directory)
             .field("tags", &self.tags)
+            .field("zone_maps", &self.zone_maps)
             .field("split_files", &self.split_files)
             .finish()
     }
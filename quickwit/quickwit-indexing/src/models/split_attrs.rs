@@ -17,12 +17,14 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::ops::{Range, RangeInclusive};
 use std::sync::Arc;
 use std::time::Duration;
 
+use quickwit_config::IndexSortField;
+use quickwit_doc_mapper::zone_map_pruning::ZoneMap;
 use quickwit_metastore::{SplitMaturity, SplitMetadata};
 use quickwit_proto::types::{DocMappingUid, IndexUid, NodeId, SourceId, SplitId};
 use tantivy::DateTime;
@@ -72,6 +74,10 @@ pub struct SplitAttrs {
 
     // Number of merge operation the split has been through so far.
     pub num_merge_ops: usize,
+
+    /// Fast fields by which the documents of the split are sorted. Empty if the documents are
+    /// not sorted.
+    pub index_sort: Vec<IndexSortField>,
 }
 
 impl fmt::Debug for SplitAttrs {
@@ -87,6 +93,7 @@ impl fmt::Debug for SplitAttrs {
             )
             .field("num_docs", &self.num_docs)
             .field("num_merge_ops", &self.num_merge_ops)
+            .field("index_sort", &self.index_sort)
             .finish()
     }
 }
@@ -96,6 +103,7 @@ pub fn create_split_metadata(
     retention_policy: Option<&quickwit_config::RetentionPolicy>,
     split_attrs: &SplitAttrs,
     tags: BTreeSet<String>,
+    zone_maps: BTreeMap<String, ZoneMap>,
     footer_offsets: Range<u64>,
 ) -> SplitMetadata {
     let create_timestamp = OffsetDateTime::now_utc().unix_timestamp();
@@ -127,9 +135,11 @@ pub fn create_split_metadata(
         create_timestamp,
         maturity,
         tags,
+        zone_maps,
         footer_offsets,
         delete_opstamp: split_attrs.delete_opstamp,
         num_merge_ops: split_attrs.num_merge_ops,
+        index_sort: split_attrs.index_sort.clone(),
     }
 }
 
@@ -134,6 +134,9 @@ impl From<IngestFailure> for IngestServiceError {
             IngestFailureReason::CircuitBreaker => {
                 IngestServiceError::RateLimited(RateLimitingCause::CircuitBreaker)
             }
+            IngestFailureReason::QuotaExceeded => {
+                IngestServiceError::RateLimited(RateLimitingCause::QuotaExceeded)
+            }
         }
     }
 }
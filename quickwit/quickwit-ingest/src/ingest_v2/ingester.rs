@@ -26,6 +26,7 @@ use std::time::{Duration, Instant};
 
 use anyhow::Context;
 use async_trait::async_trait;
+use bytes::Bytes;
 use bytesize::ByteSize;
 use futures::stream::FuturesUnordered;
 use futures::StreamExt;
@@ -36,6 +37,7 @@ use quickwit_common::metrics::{GaugeGuard, MEMORY_METRICS};
 use quickwit_common::pretty::PrettyDisplay;
 use quickwit_common::pubsub::{EventBroker, EventSubscriber};
 use quickwit_common::rate_limiter::{RateLimiter, RateLimiterSettings};
+use quickwit_common::thread_pool::run_cpu_intensive;
 use quickwit_common::tower::Pool;
 use quickwit_common::{rate_limited_error, rate_limited_warn, ServiceStream};
 use quickwit_proto::control_plane::{
@@ -50,7 +52,8 @@ use quickwit_proto::ingest::ingester::{
     OpenReplicationStreamRequest, OpenReplicationStreamResponse, PersistFailure,
     PersistFailureReason, PersistRequest, PersistResponse, PersistSuccess, ReplicateFailureReason,
     ReplicateSubrequest, RetainShardsForSource, RetainShardsRequest, RetainShardsResponse,
-    SynReplicationMessage, TruncateShardsRequest, TruncateShardsResponse,
+    SearchShardsFailure, SearchShardsFailureReason, SearchShardsRequest, SearchShardsResponse,
+    SearchShardsSuccess, SynReplicationMessage, TruncateShardsRequest, TruncateShardsResponse,
 };
 use quickwit_proto::ingest::{
     CommitTypeV2, DocBatchV2, IngestV2Error, IngestV2Result, ParseFailure, Shard, ShardIds,
@@ -59,6 +62,7 @@ use quickwit_proto::ingest::{
 use quickwit_proto::types::{
     queue_id, split_queue_id, IndexUid, NodeId, Position, QueueId, ShardId, SourceId, SubrequestId,
 };
+use quickwit_query::query_ast::QueryAst;
 use serde_json::{json, Value as JsonValue};
 use tokio::sync::Semaphore;
 use tokio::time::{sleep, timeout};
@@ -70,6 +74,7 @@ use super::fetch::FetchStreamTask;
 use super::idle::CloseIdleShardsTask;
 use super::metrics::INGEST_V2_METRICS;
 use super::models::IngesterShard;
+use super::mrecord::MRecord;
 use super::mrecordlog_utils::{
     append_non_empty_doc_batch, check_enough_capacity, AppendDocBatchError,
 };
@@ -78,6 +83,10 @@ use super::replication::{
     ReplicationClient, ReplicationStreamTask, ReplicationStreamTaskHandle, ReplicationTask,
     SYN_REPLICATION_STREAM_CAPACITY,
 };
+use super::search::{
+    search_shard_docs, SearchShardsParams, MAX_SEARCHED_BYTES_PER_REQUEST,
+    MAX_SEARCHED_RECORDS_PER_REQUEST,
+};
 use super::state::{IngesterState, InnerIngesterState, WeakIngesterState};
 use super::IngesterPool;
 use crate::ingest_v2::doc_mapper::get_or_try_build_doc_mapper;
@@ -1053,6 +1062,146 @@ impl Ingester {
         Ok(DecommissionResponse {})
     }
 
+    async fn search_shards_inner(
+        &self,
+        search_shards_request: SearchShardsRequest,
+    ) -> IngestV2Result<SearchShardsResponse> {
+        let query_ast: Arc<QueryAst> = serde_json::from_str(&search_shards_request.query_ast)
+            .map(Arc::new)
+            .map_err(|error| {
+                IngestV2Error::Internal(format!("failed to deserialize query AST: {error}"))
+            })?;
+        let search_params = SearchShardsParams::from(&search_shards_request);
+
+        let subrequest_ids: Vec<SubrequestId> = search_shards_request
+            .subrequests
+            .iter()
+            .map(|subrequest| subrequest.subrequest_id)
+            .collect();
+        let mut successes = Vec::with_capacity(subrequest_ids.len());
+        let mut failures = Vec::new();
+
+        let mut num_records_read: usize = 0;
+        let mut num_bytes_read: u64 = 0;
+
+        for subrequest in search_shards_request.subrequests {
+            let queue_id = subrequest.queue_id();
+            let state_guard =
+                with_lock_metrics!(self.state.lock_partially(), "search_shards", "read").await?;
+
+            // Only leaders hold a doc mapper for their shards.
+            let Some(doc_mapper) = state_guard
+                .shards
+                .get(&queue_id)
+                .and_then(|shard| shard.doc_mapper_opt.clone())
+            else {
+                let failure = SearchShardsFailure {
+                    subrequest_id: subrequest.subrequest_id,
+                    reason: format!("shard `{queue_id}` not found or not searchable"),
+                    reason_code: SearchShardsFailureReason::ShardNotFound as i32,
+                };
+                failures.push(failure);
+                continue;
+            };
+            drop(state_guard);
+
+            let from_position_inclusive = subrequest
+                .publish_position_inclusive()
+                .as_u64()
+                .map(|offset| offset + 1)
+                .unwrap_or_default();
+
+            let mrecordlog = self.state.mrecordlog();
+            let mrecordlog_guard =
+                with_lock_metrics!(mrecordlog.read().await, "search_shards", "read");
+
+            let Ok(records) = mrecordlog_guard
+                .as_ref()
+                .expect("mrecordlog should be initialized")
+                .range(&queue_id, from_position_inclusive..)
+            else {
+                let failure = SearchShardsFailure {
+                    subrequest_id: subrequest.subrequest_id,
+                    reason: format!("shard `{queue_id}` not found or not searchable"),
+                    reason_code: SearchShardsFailureReason::ShardNotFound as i32,
+                };
+                failures.push(failure);
+                continue;
+            };
+            let mut docs: Vec<(u64, Bytes)> = Vec::new();
+
+            for record in records {
+                num_records_read += 1;
+                num_bytes_read += record.payload.len() as u64;
+
+                // The whole request fails: the searcher cannot return partial real-time results.
+                if num_records_read > MAX_SEARCHED_RECORDS_PER_REQUEST
+                    || num_bytes_read > MAX_SEARCHED_BYTES_PER_REQUEST.as_u64()
+                {
+                    let failures = subrequest_ids
+                        .into_iter()
+                        .map(|subrequest_id| SearchShardsFailure {
+                            subrequest_id,
+                            reason: format!(
+                                "the records to search exceed the limit of \
+                                 {MAX_SEARCHED_RECORDS_PER_REQUEST} records or \
+                                 {MAX_SEARCHED_BYTES_PER_REQUEST} per request"
+                            ),
+                            reason_code: SearchShardsFailureReason::LimitExceeded as i32,
+                        })
+                        .collect();
+                    let response = SearchShardsResponse {
+                        successes: Vec::new(),
+                        failures,
+                    };
+                    return Ok(response);
+                }
+                if let Some(MRecord::Doc(doc)) = MRecord::decode(&record.payload[..]) {
+                    docs.push((record.position, doc));
+                }
+            }
+            drop(mrecordlog_guard);
+
+            let query_ast = query_ast.clone();
+            let search_result = run_cpu_intensive(move || {
+                search_shard_docs(&doc_mapper, &query_ast, docs, search_params)
+            })
+            .await;
+
+            match search_result {
+                Ok(Ok((num_hits, hits))) => {
+                    let success = SearchShardsSuccess {
+                        subrequest_id: subrequest.subrequest_id,
+                        num_hits,
+                        hits,
+                    };
+                    successes.push(success);
+                }
+                Ok(Err(error)) => {
+                    let failure = SearchShardsFailure {
+                        subrequest_id: subrequest.subrequest_id,
+                        reason: format!("failed to search shard `{queue_id}`: {error}"),
+                        reason_code: SearchShardsFailureReason::Internal as i32,
+                    };
+                    failures.push(failure);
+                }
+                Err(panicked) => {
+                    let failure = SearchShardsFailure {
+                        subrequest_id: subrequest.subrequest_id,
+                        reason: format!("failed to search shard `{queue_id}`: {panicked}"),
+                        reason_code: SearchShardsFailureReason::Internal as i32,
+                    };
+                    failures.push(failure);
+                }
+            }
+        }
+        let response = SearchShardsResponse {
+            successes,
+            failures,
+        };
+        Ok(response)
+    }
+
     pub async fn debug_info(&self) -> JsonValue {
         let state_guard = match self.state.lock_fully().await {
             Ok(state_guard) => state_guard,
@@ -1210,6 +1359,13 @@ impl IngesterService for Ingester {
     ) -> IngestV2Result<DecommissionResponse> {
         self.decommission_inner(decommission_request).await
     }
+
+    async fn search_shards(
+        &self,
+        search_shards_request: SearchShardsRequest,
+    ) -> IngestV2Result<SearchShardsResponse> {
+        self.search_shards_inner(search_shards_request).await
+    }
 }
 
 #[async_trait]
@@ -1296,7 +1452,6 @@ mod tests {
     use std::net::SocketAddr;
     use std::sync::atomic::{AtomicU16, Ordering};
 
-    use bytes::Bytes;
     use quickwit_cluster::{create_cluster_for_test_with_id, ChannelTransport};
     use quickwit_common::shared_consts::INGESTER_PRIMARY_SHARDS_PREFIX;
     use quickwit_common::tower::ConstantRate;
@@ -1304,7 +1459,7 @@ mod tests {
     use quickwit_proto::control_plane::{AdviseResetShardsResponse, MockControlPlaneService};
     use quickwit_proto::ingest::ingester::{
         IngesterServiceGrpcServer, IngesterServiceGrpcServerAdapter, InitShardSubrequest,
-        PersistSubrequest, TruncateShardsSubrequest,
+        PersistSubrequest, SearchShardsSubrequest, TruncateShardsSubrequest,
     };
     use quickwit_proto::ingest::{
         DocBatchV2, ParseFailureReason, ShardIdPosition, ShardIdPositions, ShardIds, ShardPKey,
@@ -1319,7 +1474,6 @@ mod tests {
     use crate::ingest_v2::doc_mapper::try_build_doc_mapper;
     use crate::ingest_v2::fetch::tests::{into_fetch_eof, into_fetch_payload};
     use crate::ingest_v2::DEFAULT_IDLE_SHARD_TIMEOUT;
-    use crate::MRecord;
 
     const MAX_GRPC_MESSAGE_SIZE: ByteSize = ByteSize::mib(1);
 
@@ -3541,6 +3695,200 @@ mod tests {
         assert_eq!(fetch_eof.eof_position(), Position::Beginning.as_eof());
     }
 
+    #[tokio::test]
+    async fn test_ingester_search_shards() {
+        let (_ingester_ctx, ingester) = IngesterForTest::default().build().await;
+
+        let index_uid: IndexUid = IndexUid::for_test("test-index", 0);
+        let doc_mapping_uid = DocMappingUid::random();
+        let doc_mapping_json = format!(
+            r#"{{
+                "doc_mapping_uid": "{doc_mapping_uid}",
+                "field_mappings": [{{
+                    "name": "message",
+                    "type": "text"
+                }}]
+            }}"#
+        );
+        let shard = Shard {
+            index_uid: Some(index_uid.clone()),
+            source_id: "test-source".to_string(),
+            shard_id: Some(ShardId::from(1)),
+            shard_state: ShardState::Open as i32,
+            doc_mapping_uid: Some(doc_mapping_uid),
+            ..Default::default()
+        };
+        let queue_id = queue_id(&index_uid, "test-source", &ShardId::from(1));
+
+        let mut state_guard = ingester.state.lock_fully().await.unwrap();
+
+        ingester
+            .init_primary_shard(
+                &mut state_guard.inner,
+                &mut state_guard.mrecordlog,
+                shard,
+                &doc_mapping_json,
+                Instant::now(),
+                true,
+            )
+            .await
+            .unwrap();
+
+        let records = [
+            MRecord::new_doc(r#"{"message": "hello foo"}"#).encode(),
+            MRecord::new_doc(r#"{"message": "hello bar"}"#).encode(),
+            MRecord::Commit.encode(),
+            MRecord::new_doc(r#"{"message": "goodbye"}"#).encode(),
+        ]
+        .into_iter();
+
+        state_guard
+            .mrecordlog
+            .append_records(&queue_id, None, records)
+            .await
+            .unwrap();
+
+        drop(state_guard);
+
+        let search_shards_request = SearchShardsRequest {
+            query_ast: quickwit_query::query_ast::qast_json_helper("hello", &["message"]),
+            subrequests: vec![
+                SearchShardsSubrequest {
+                    subrequest_id: 0,
+                    index_uid: Some(index_uid.clone()),
+                    source_id: "test-source".to_string(),
+                    shard_id: Some(ShardId::from(1)),
+                    publish_position_inclusive: Some(Position::Beginning),
+                },
+                SearchShardsSubrequest {
+                    subrequest_id: 1,
+                    index_uid: Some(index_uid.clone()),
+                    source_id: "test-source".to_string(),
+                    shard_id: Some(ShardId::from(1)),
+                    publish_position_inclusive: Some(Position::offset(0u64)),
+                },
+                SearchShardsSubrequest {
+                    subrequest_id: 2,
+                    index_uid: Some(index_uid.clone()),
+                    source_id: "test-source".to_string(),
+                    shard_id: Some(ShardId::from(1337)),
+                    publish_position_inclusive: Some(Position::Beginning),
+                },
+            ],
+            max_hits: 10,
+            start_timestamp: None,
+            end_timestamp: None,
+            sort_ascending: false,
+        };
+        let search_shards_response = ingester.search_shards(search_shards_request).await.unwrap();
+        assert_eq!(search_shards_response.successes.len(), 2);
+        assert_eq!(search_shards_response.failures.len(), 1);
+
+        let success_0 = &search_shards_response.successes[0];
+        assert_eq!(success_0.subrequest_id, 0);
+        assert_eq!(success_0.num_hits, 2);
+        assert_eq!(success_0.hits[0].position, Some(Position::offset(1u64)));
+        assert_eq!(success_0.hits[0].json, r#"{"message": "hello bar"}"#);
+        assert_eq!(success_0.hits[1].position, Some(Position::offset(0u64)));
+
+        let success_1 = &search_shards_response.successes[1];
+        assert_eq!(success_1.subrequest_id, 1);
+        assert_eq!(success_1.num_hits, 1);
+        assert_eq!(success_1.hits[0].position, Some(Position::offset(1u64)));
+
+        let failure = &search_shards_response.failures[0];
+        assert_eq!(failure.subrequest_id, 2);
+        assert_eq!(
+            failure.reason_code(),
+            SearchShardsFailureReason::ShardNotFound
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ingester_search_shards_limit_exceeded() {
+        let (_ingester_ctx, ingester) = IngesterForTest::default().build().await;
+
+        let index_uid: IndexUid = IndexUid::for_test("test-index", 0);
+        let doc_mapping_uid = DocMappingUid::random();
+        let doc_mapping_json = format!(
+            r#"{{
+                "doc_mapping_uid": "{doc_mapping_uid}",
+                "field_mappings": [{{
+                    "name": "message",
+                    "type": "text"
+                }}]
+            }}"#
+        );
+        let shard = Shard {
+            index_uid: Some(index_uid.clone()),
+            source_id: "test-source".to_string(),
+            shard_id: Some(ShardId::from(1)),
+            shard_state: ShardState::Open as i32,
+            doc_mapping_uid: Some(doc_mapping_uid),
+            ..Default::default()
+        };
+        let queue_id = queue_id(&index_uid, "test-source", &ShardId::from(1));
+
+        let mut state_guard = ingester.state.lock_fully().await.unwrap();
+
+        ingester
+            .init_primary_shard(
+                &mut state_guard.inner,
+                &mut state_guard.mrecordlog,
+                shard,
+                &doc_mapping_json,
+                Instant::now(),
+                true,
+            )
+            .await
+            .unwrap();
+
+        let records = (0..MAX_SEARCHED_RECORDS_PER_REQUEST + 1)
+            .map(|_| MRecord::new_doc(r#"{"message": "hello"}"#).encode());
+
+        state_guard
+            .mrecordlog
+            .append_records(&queue_id, None, records)
+            .await
+            .unwrap();
+
+        drop(state_guard);
+
+        let search_shards_request = SearchShardsRequest {
+            query_ast: quickwit_query::query_ast::qast_json_helper("hello", &["message"]),
+            subrequests: vec![
+                SearchShardsSubrequest {
+                    subrequest_id: 0,
+                    index_uid: Some(index_uid.clone()),
+                    source_id: "test-source".to_string(),
+                    shard_id: Some(ShardId::from(1)),
+                    publish_position_inclusive: Some(Position::Beginning),
+                },
+                SearchShardsSubrequest {
+                    subrequest_id: 1,
+                    index_uid: Some(index_uid.clone()),
+                    source_id: "test-source".to_string(),
+                    shard_id: Some(ShardId::from(1337)),
+                    publish_position_inclusive: Some(Position::Beginning),
+                },
+            ],
+            max_hits: 10,
+            start_timestamp: None,
+            end_timestamp: None,
+            sort_ascending: false,
+        };
+        let search_shards_response = ingester.search_shards(search_shards_request).await.unwrap();
+        assert!(search_shards_response.successes.is_empty());
+        assert_eq!(search_shards_response.failures.len(), 2);
+
+        for failure in &search_shards_response.failures {
+            assert_eq!(
+                failure.reason_code(),
+                SearchShardsFailureReason::LimitExceeded
+            );
+        }
+    }
+
     #[tokio::test]
     async fn test_ingester_open_observation_stream() {
         let (ingester_ctx, ingester) = IngesterForTest::default().build().await;
@@ -32,6 +32,7 @@ mod rate_meter;
 mod replication;
 mod router;
 mod routing_table;
+mod search;
 mod state;
 mod workbench;
 
@@ -27,6 +27,7 @@ use futures::stream::FuturesUnordered;
 use futures::{Future, StreamExt};
 use quickwit_common::metrics::{GaugeGuard, MEMORY_METRICS};
 use quickwit_common::pubsub::{EventBroker, EventSubscriber};
+use quickwit_common::tenant_quotas::TenantQuotas;
 use quickwit_common::{rate_limited_error, rate_limited_warn};
 use quickwit_proto::control_plane::{
     ControlPlaneService, ControlPlaneServiceClient, GetOrCreateOpenShardsRequest,
@@ -102,6 +103,7 @@ pub struct IngestRouter {
     // Limits the number of ingest requests in-flight to some capacity in bytes.
     ingest_semaphore: Arc<Semaphore>,
     event_broker: EventBroker,
+    tenant_quotas: TenantQuotas,
 }
 
 struct RouterState {
@@ -146,9 +148,16 @@ impl IngestRouter {
             replication_factor,
             ingest_semaphore,
             event_broker,
+            tenant_quotas: TenantQuotas::default(),
         }
     }
 
+    /// Sets the per-index and per-tenant quotas enforced by the router.
+    pub fn with_tenant_quotas(mut self, tenant_quotas: TenantQuotas) -> Self {
+        self.tenant_quotas = tenant_quotas;
+        self
+    }
+
     pub fn subscribe(&self) {
         let weak_router_state = WeakRouterState(Arc::downgrade(&self.state));
         self.event_broker
@@ -467,6 +476,8 @@ impl IngestRouter {
         } else {
             IngestWorkbench::new(ingest_request.subrequests, max_num_attempts)
         };
+        self.enforce_tenant_quotas(&mut workbench);
+
         while !workbench.is_complete() {
             workbench.new_attempt();
             self.batch_persist(&mut workbench, commit_type).await;
@@ -474,6 +485,34 @@ impl IngestRouter {
         workbench.into_ingest_result().await
     }
 
+    /// Fails the subrequests targeting indexes whose ingest throughput or storage quota is
+    /// exceeded. Those failures are not retried.
+    fn enforce_tenant_quotas(&self, workbench: &mut IngestWorkbench) {
+        if self.tenant_quotas.is_empty() {
+            return;
+        }
+        let mut quota_exceeded_subrequest_ids: Vec<SubrequestId> = Vec::new();
+
+        for subrequest in pending_subrequests(&workbench.subworkbenches) {
+            let num_bytes = subrequest.num_bytes() as u64;
+
+            if let Err(quota_exceeded) = self
+                .tenant_quotas
+                .acquire_ingest(&subrequest.index_id, num_bytes)
+            {
+                rate_limited_warn!(
+                    limit_per_min = 6,
+                    index_id = %subrequest.index_id,
+                    "rejecting ingest subrequest: {quota_exceeded}"
+                );
+                quota_exceeded_subrequest_ids.push(subrequest.subrequest_id);
+            }
+        }
+        for subrequest_id in quota_exceeded_subrequest_ids {
+            workbench.record_quota_exceeded(subrequest_id);
+        }
+    }
+
     async fn ingest_timeout(
         &self,
         ingest_request: IngestRequestV2,
@@ -542,6 +581,9 @@ fn update_ingest_metrics(ingest_result: &IngestV2Result<IngestResponseV2>, num_s
                         ingest_results_metrics.router_load_shedding.inc()
                     }
                     IngestFailureReason::LoadShedding => ingest_results_metrics.load_shedding.inc(),
+                    IngestFailureReason::QuotaExceeded => {
+                        ingest_results_metrics.quota_exceeded.inc()
+                    }
                 }
             }
         }
@@ -568,6 +610,11 @@ fn update_ingest_metrics(ingest_result: &IngestV2Result<IngestResponseV2>, num_s
                         .shard_rate_limited
                         .inc_by(num_subrequests);
                 }
+                RateLimitingCause::QuotaExceeded => {
+                    ingest_results_metrics
+                        .quota_exceeded
+                        .inc_by(num_subrequests);
+                }
                 RateLimitingCause::Unknown => {
                     ingest_results_metrics.unspecified.inc_by(num_subrequests);
                 }
@@ -697,6 +744,7 @@ mod tests {
     use std::collections::BTreeSet;
 
     use mockall::Sequence;
+    use quickwit_common::tenant_quotas::QuotaSettings;
     use quickwit_proto::control_plane::{
         GetOrCreateOpenShardsFailure, GetOrCreateOpenShardsFailureReason,
         GetOrCreateOpenShardsResponse, GetOrCreateOpenShardsSuccess, MockControlPlaneService,
@@ -1056,6 +1104,49 @@ mod tests {
         ));
     }
 
+    #[tokio::test]
+    async fn test_router_ingest_quota_exceeded() {
+        let self_node_id = "test-router".into();
+        let control_plane = ControlPlaneServiceClient::from_mock(MockControlPlaneService::new());
+        let ingester_pool = IngesterPool::default();
+        let replication_factor = 1;
+        let tenant_quotas = TenantQuotas::new([QuotaSettings {
+            index_id_pattern: "acme-*".to_string(),
+            ingest_rate_limiter_settings_opt: None,
+            max_storage_bytes_opt: Some(1_000),
+            max_concurrent_searches_opt: None,
+            max_scanned_bytes_per_query_opt: None,
+        }]);
+        tenant_quotas.update_storage_usage([("acme-logs", 1_000)]);
+
+        let router = IngestRouter::new(
+            self_node_id,
+            control_plane,
+            ingester_pool,
+            replication_factor,
+            EventBroker::default(),
+        )
+        .with_tenant_quotas(tenant_quotas);
+
+        let ingest_request = IngestRequestV2 {
+            subrequests: vec![IngestSubrequest {
+                subrequest_id: 0,
+                index_id: "acme-logs".to_string(),
+                source_id: "test-source".to_string(),
+                doc_batch: Some(DocBatchV2::for_test(["test-doc-foo"])),
+            }],
+            commit_type: CommitTypeV2::Auto as i32,
+        };
+        let ingest_response = router.ingest(ingest_request).await.unwrap();
+        assert!(ingest_response.successes.is_empty());
+        assert_eq!(ingest_response.failures.len(), 1);
+
+        let failure = &ingest_response.failures[0];
+        assert_eq!(failure.subrequest_id, 0);
+        assert_eq!(failure.index_id, "acme-logs");
+        assert_eq!(failure.reason(), IngestFailureReason::QuotaExceeded);
+    }
+
     #[tokio::test]
     async fn test_router_batch_persist_records_no_shards_available_empty_routing_table() {
         let self_node_id = "test-router".into();
@@ -0,0 +1,307 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::cmp::Reverse;
+
+use bytes::Bytes;
+use bytesize::ByteSize;
+use quickwit_doc_mapper::DocMapper;
+use quickwit_proto::ingest::ingester::{SearchShardsRequest, ShardHit};
+use quickwit_proto::types::Position;
+use quickwit_query::get_quickwit_fastfield_normalizer_manager;
+use quickwit_query::query_ast::QueryAst;
+use tantivy::collector::DocSetCollector;
+use tantivy::directory::RamDirectory;
+use tantivy::{IndexBuilder, IndexReader, ReloadPolicy};
+
+/// Maximum number of documents indexed in a single in-RAM index while searching a shard.
+const SEARCH_BATCH_NUM_DOCS: usize = 10_000;
+
+/// Memory budget of the in-RAM index writer. Tantivy requires at least 15MB.
+const SEARCH_BATCH_MEMORY_BUDGET_BYTES: usize = 30_000_000;
+
+/// Maximum number of records read from the shards by a single search request. The records are
+/// searched by brute force on each request, so the requests reading more records are rejected.
+pub(super) const MAX_SEARCHED_RECORDS_PER_REQUEST: usize = if cfg!(test) { 10 } else { 200_000 };
+
+/// Maximum number of bytes read from the shards by a single search request.
+pub(super) const MAX_SEARCHED_BYTES_PER_REQUEST: ByteSize =
+    ByteSize::mib(if cfg!(test) { 1 } else { 128 });
+
+/// Parameters shared by all the subrequests of a [`SearchShardsRequest`].
+#[derive(Debug, Clone, Copy)]
+pub(super) struct SearchShardsParams {
+    pub max_hits: usize,
+    pub start_timestamp_opt: Option<i64>,
+    pub end_timestamp_opt: Option<i64>,
+    pub sort_ascending: bool,
+}
+
+impl From<&SearchShardsRequest> for SearchShardsParams {
+    fn from(search_shards_request: &SearchShardsRequest) -> Self {
+        Self {
+            max_hits: search_shards_request.max_hits as usize,
+            start_timestamp_opt: search_shards_request.start_timestamp,
+            end_timestamp_opt: search_shards_request.end_timestamp,
+            sort_ascending: search_shards_request.sort_ascending,
+        }
+    }
+}
+
+impl SearchShardsParams {
+    fn timestamp_matches(&self, timestamp_secs_opt: Option<i64>) -> bool {
+        if self.start_timestamp_opt.is_none() && self.end_timestamp_opt.is_none() {
+            return true;
+        }
+        // Like in splits, documents without a timestamp never match a time range.
+        let Some(timestamp_secs) = timestamp_secs_opt else {
+            return false;
+        };
+        if let Some(start_timestamp) = self.start_timestamp_opt {
+            if timestamp_secs < start_timestamp {
+                return false;
+            }
+        }
+        if let Some(end_timestamp) = self.end_timestamp_opt {
+            if timestamp_secs >= end_timestamp {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[derive(Debug)]
+struct ShardHitCandidate {
+    timestamp_nanos_opt: Option<i64>,
+    position: u64,
+    doc: Bytes,
+}
+
+impl ShardHitCandidate {
+    fn into_shard_hit(self) -> ShardHit {
+        ShardHit {
+            position: Some(Position::offset(self.position)),
+            json: String::from_utf8_lossy(&self.doc).into_owned(),
+            timestamp_nanos: self.timestamp_nanos_opt,
+        }
+    }
+}
+
+/// Searches a set of documents read from a shard's mrecordlog queue. The documents are indexed in
+/// bounded batches in short-lived in-RAM indexes, so the query is evaluated exactly as it would be
+/// on a split. Documents that cannot be parsed by the doc mapper are skipped.
+///
+/// Returns the total number of hits and the top `max_hits` hits sorted by timestamp, or by
+/// position if the index does not have a timestamp field.
+pub(super) fn search_shard_docs(
+    doc_mapper: &DocMapper,
+    query_ast: &QueryAst,
+    docs: Vec<(u64, Bytes)>,
+    params: SearchShardsParams,
+) -> anyhow::Result<(u64, Vec<ShardHit>)> {
+    let schema = doc_mapper.schema();
+    let (query, _) = doc_mapper.query(schema.clone(), query_ast, true)?;
+    let timestamp_field_name_opt = doc_mapper.timestamp_field_name();
+
+    let mut num_hits: u64 = 0;
+    let mut candidates: Vec<ShardHitCandidate> = Vec::new();
+
+    for batch in docs.chunks(SEARCH_BATCH_NUM_DOCS) {
+        let index_builder = IndexBuilder::new()
+            .schema(schema.clone())
+            .tokenizers(doc_mapper.tokenizer_manager().tantivy_manager().clone())
+            .fast_field_tokenizers(
+                get_quickwit_fastfield_normalizer_manager()
+                    .tantivy_manager()
+                    .clone(),
+            );
+        let mut index_writer = index_builder.single_segment_index_writer(
+            RamDirectory::create(),
+            SEARCH_BATCH_MEMORY_BUDGET_BYTES,
+        )?;
+        // The single segment writer assigns doc IDs in insertion order, so the doc ID of a
+        // document is its index in `indexed_docs`.
+        let mut indexed_docs: Vec<&(u64, Bytes)> = Vec::with_capacity(batch.len());
+
+        for doc in batch {
+            let Ok((_partition, document)) = doc_mapper.doc_from_json_bytes(&doc.1) else {
+                continue;
+            };
+            index_writer.add_document(document)?;
+            indexed_docs.push(doc);
+        }
+        if indexed_docs.is_empty() {
+            continue;
+        }
+        let index = index_writer.finalize()?;
+        let reader: IndexReader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        let searcher = reader.searcher();
+        let doc_addresses = searcher.search(&query, &DocSetCollector)?;
+
+        let timestamp_column_opt = timestamp_field_name_opt.and_then(|timestamp_field_name| {
+            searcher
+                .segment_reader(0)
+                .fast_fields()
+                .date(timestamp_field_name)
+                .ok()
+        });
+        for doc_address in doc_addresses {
+            let timestamp_opt = timestamp_column_opt
+                .as_ref()
+                .and_then(|timestamp_column| timestamp_column.first(doc_address.doc_id));
+            let timestamp_secs_opt = timestamp_opt.map(|timestamp| timestamp.into_timestamp_secs());
+
+            if !params.timestamp_matches(timestamp_secs_opt) {
+                continue;
+            }
+            num_hits += 1;
+
+            let (position, doc) = indexed_docs[doc_address.doc_id as usize];
+            let candidate = ShardHitCandidate {
+                timestamp_nanos_opt: timestamp_opt
+                    .map(|timestamp| timestamp.into_timestamp_nanos()),
+                position: *position,
+                doc: doc.clone(),
+            };
+            candidates.push(candidate);
+        }
+        // Keep the memory footprint bounded by only retaining the top hits between batches.
+        sort_candidates(&mut candidates, params.sort_ascending);
+        candidates.truncate(params.max_hits);
+    }
+    let hits = candidates
+        .into_iter()
+        .map(ShardHitCandidate::into_shard_hit)
+        .collect();
+    Ok((num_hits, hits))
+}
+
+fn sort_candidates(candidates: &mut [ShardHitCandidate], sort_ascending: bool) {
+    if sort_ascending {
+        candidates.sort_by_key(|candidate| (candidate.timestamp_nanos_opt, candidate.position));
+    } else {
+        candidates.sort_by_key(|candidate| {
+            (
+                Reverse(candidate.timestamp_nanos_opt),
+                Reverse(candidate.position),
+            )
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_query::query_ast::qast_helper;
+
+    use super::*;
+    use crate::ingest_v2::doc_mapper::try_build_doc_mapper;
+
+    fn docs(jsons: &[&str]) -> Vec<(u64, Bytes)> {
+        jsons
+            .iter()
+            .enumerate()
+            .map(|(position, json)| (position as u64, Bytes::from(json.to_string())))
+            .collect()
+    }
+
+    #[test]
+    fn test_search_shard_docs() {
+        let doc_mapping_json = r#"{
+            "field_mappings": [
+                {"name": "message", "type": "text"},
+                {"name": "ts", "type": "datetime", "fast": true}
+            ],
+            "timestamp_field": "ts"
+        }"#;
+        let doc_mapper = try_build_doc_mapper(doc_mapping_json).unwrap();
+        let docs = docs(&[
+            r#"{"message": "hello world", "ts": 1000}"#,
+            r#"{"message": "goodbye world", "ts": 3000}"#,
+            r#"not a json object"#,
+            r#"{"message": "hello again", "ts": 2000}"#,
+        ]);
+        let query_ast = qast_helper("hello", &["message"]);
+        let params = SearchShardsParams {
+            max_hits: 10,
+            start_timestamp_opt: None,
+            end_timestamp_opt: None,
+            sort_ascending: false,
+        };
+        let (num_hits, hits) =
+            search_shard_docs(&doc_mapper, &query_ast, docs.clone(), params).unwrap();
+        assert_eq!(num_hits, 2);
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].position, Some(Position::offset(3u64)));
+        assert_eq!(hits[0].json, r#"{"message": "hello again", "ts": 2000}"#);
+        assert_eq!(hits[0].timestamp_nanos, Some(2_000_000_000_000));
+        assert_eq!(hits[1].position, Some(Position::offset(0u64)));
+
+        let params = SearchShardsParams {
+            max_hits: 1,
+            start_timestamp_opt: None,
+            end_timestamp_opt: None,
+            sort_ascending: true,
+        };
+        let (num_hits, hits) =
+            search_shard_docs(&doc_mapper, &query_ast, docs.clone(), params).unwrap();
+        assert_eq!(num_hits, 2);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].position, Some(Position::offset(0u64)));
+
+        let query_ast = qast_helper("world", &["message"]);
+        let params = SearchShardsParams {
+            max_hits: 10,
+            start_timestamp_opt: Some(1500),
+            end_timestamp_opt: Some(3000),
+            sort_ascending: false,
+        };
+        let (num_hits, hits) = search_shard_docs(&doc_mapper, &query_ast, docs, params).unwrap();
+        assert_eq!(num_hits, 0);
+        assert!(hits.is_empty());
+    }
+
+    #[test]
+    fn test_search_shard_docs_without_timestamp_field() {
+        let doc_mapping_json = r#"{
+            "field_mappings": [{"name": "message", "type": "text"}]
+        }"#;
+        let doc_mapper = try_build_doc_mapper(doc_mapping_json).unwrap();
+        let docs = docs(&[
+            r#"{"message": "hello"}"#,
+            r#"{"message": "hello"}"#,
+            r#"{"message": "bye"}"#,
+        ]);
+        let query_ast = qast_helper("hello", &["message"]);
+        let params = SearchShardsParams {
+            max_hits: 10,
+            start_timestamp_opt: None,
+            end_timestamp_opt: None,
+            sort_ascending: false,
+        };
+        let (num_hits, hits) = search_shard_docs(&doc_mapper, &query_ast, docs, params).unwrap();
+        assert_eq!(num_hits, 2);
+        assert_eq!(hits[0].position, Some(Position::offset(1u64)));
+        assert_eq!(hits[0].timestamp_nanos, None);
+        assert_eq!(hits[1].position, Some(Position::offset(0u64)));
+    }
+}
@@ -241,6 +241,10 @@ impl IngestWorkbench {
         subworkbench.last_failure_opt = Some(failure);
     }
 
+    pub fn record_quota_exceeded(&mut self, subrequest_id: SubrequestId) {
+        self.record_failure(subrequest_id, SubworkbenchFailure::QuotaExceeded);
+    }
+
     pub fn record_no_shards_available(&mut self, subrequest_id: SubrequestId) {
         self.record_failure(subrequest_id, SubworkbenchFailure::NoShardsAvailable);
     }
@@ -339,6 +343,8 @@ pub(super) enum SubworkbenchFailure {
     Unavailable,
     // The ingester is rate limited.
     RateLimited(RateLimitingCause),
+    // The index or tenant quota is exceeded.
+    QuotaExceeded,
 }
 
 impl SubworkbenchFailure {
@@ -358,9 +364,11 @@ impl SubworkbenchFailure {
                 RateLimitingCause::WalFull => IngestFailureReason::WalFull,
                 RateLimitingCause::CircuitBreaker => IngestFailureReason::CircuitBreaker,
                 RateLimitingCause::ShardRateLimiting => IngestFailureReason::ShardRateLimited,
+                RateLimitingCause::QuotaExceeded => IngestFailureReason::QuotaExceeded,
                 RateLimitingCause::Unknown => IngestFailureReason::Unspecified,
             },
             Self::Persist(persist_failure_reason) => (*persist_failure_reason).into(),
+            Self::QuotaExceeded => IngestFailureReason::QuotaExceeded,
         }
     }
 }
@@ -399,6 +407,7 @@ impl IngestSubworkbench {
             Some(SubworkbenchFailure::Persist(_)) => true,
             Some(SubworkbenchFailure::Unavailable) => true,
             Some(SubworkbenchFailure::RateLimited(_)) => true,
+            Some(SubworkbenchFailure::QuotaExceeded) => false,
             None => true,
         }
     }
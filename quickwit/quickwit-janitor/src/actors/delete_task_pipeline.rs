@@ -192,7 +192,15 @@ impl DeleteTaskPipeline {
         let doc_mapper =
             build_doc_mapper(&index_config.doc_mapping, &index_config.search_settings)?;
         let tag_fields = doc_mapper.tag_named_fields()?;
-        let packager = Packager::new("MergePackager", tag_fields, uploader_mailbox);
+        let zone_map_fields = doc_mapper.zone_map_named_fields()?;
+        let bloom_filter_fields = doc_mapper.bloom_filter_named_fields()?;
+        let packager = Packager::new(
+            "MergePackager",
+            tag_fields,
+            zone_map_fields,
+            bloom_filter_fields,
+            uploader_mailbox,
+        );
         let (packager_mailbox, packager_supervisor_handler) = ctx.spawn_actor().supervise(packager);
         let pipeline_id = MergePipelineId {
             node_id: NodeId::from("unknown"),
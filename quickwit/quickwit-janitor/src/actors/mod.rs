@@ -21,8 +21,15 @@ mod delete_task_pipeline;
 mod delete_task_planner;
 mod delete_task_service;
 mod garbage_collector;
+mod reindex_task_pipeline;
+mod reindex_task_planner;
+mod reindex_task_service;
 mod retention_policy_executor;
+mod rollup_executor;
 
 pub use delete_task_service::{DeleteTaskService, DELETE_SERVICE_TASK_DIR_NAME};
 pub use garbage_collector::GarbageCollector;
+pub use reindex_task_planner::failed_reindex_split_ids;
+pub use reindex_task_service::{ReindexTaskService, REINDEX_SERVICE_TASK_DIR_NAME};
 pub use retention_policy_executor::RetentionPolicyExecutor;
+pub use rollup_executor::RollupExecutor;
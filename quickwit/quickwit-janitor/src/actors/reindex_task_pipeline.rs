@@ -0,0 +1,427 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use quickwit_actors::{
+    Actor, ActorContext, ActorExitStatus, ActorHandle, Handler, Mailbox, Supervisor,
+    SupervisorState,
+};
+use quickwit_common::io::{IoControls, Limiter};
+use quickwit_common::pubsub::EventBroker;
+use quickwit_common::temp_dir::{self};
+use quickwit_config::{build_doc_mapper, REINDEX_TASK_SOURCE_ID};
+use quickwit_indexing::actors::{
+    MergeExecutor, MergeSchedulerService, MergeSplitDownloader, Packager, Publisher,
+    PublisherCounters, Uploader, UploaderCounters, UploaderType,
+};
+use quickwit_indexing::merge_policy::merge_policy_from_settings;
+use quickwit_indexing::{IndexingSplitStore, PublisherType, SplitsUpdateMailbox};
+use quickwit_metastore::IndexMetadataResponseExt;
+use quickwit_proto::indexing::MergePipelineId;
+use quickwit_proto::metastore::{IndexMetadataRequest, MetastoreService, MetastoreServiceClient};
+use quickwit_proto::types::{IndexUid, NodeId};
+use quickwit_storage::Storage;
+use serde::Serialize;
+use tokio::join;
+use tracing::info;
+
+use super::reindex_task_planner::{ReindexTaskPlanner, ReindexTaskPlannerState};
+
+const OBSERVE_PIPELINE_INTERVAL: Duration = if cfg!(any(test, feature = "testsuite")) {
+    Duration::from_millis(500)
+} else {
+    // 1 minute.
+    // This is only for observation purpose, not supervision.
+    Duration::from_secs(60)
+};
+
+struct ReindexPipelineHandle {
+    pub reindex_task_planner: ActorHandle<Supervisor<ReindexTaskPlanner>>,
+    pub downloader: ActorHandle<Supervisor<MergeSplitDownloader>>,
+    pub reindex_task_executor: ActorHandle<Supervisor<MergeExecutor>>,
+    pub packager: ActorHandle<Supervisor<Packager>>,
+    pub uploader: ActorHandle<Supervisor<Uploader>>,
+    pub publisher: ActorHandle<Supervisor<Publisher>>,
+}
+
+/// A Struct to hold all statistical data about reindex tasks.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ReindexTaskPipelineState {
+    pub reindex_task_planner: SupervisorState<ReindexTaskPlannerState>,
+    pub downloader: SupervisorState<()>,
+    pub reindex_task_executor: SupervisorState<()>,
+    pub packager: SupervisorState<()>,
+    pub uploader: SupervisorState<UploaderCounters>,
+    pub publisher: SupervisorState<PublisherCounters>,
+}
+
+/// Pipeline rebuilding the splits of an index with its current doc mapping.
+///
+/// The pipeline is bound to the doc mapping of the index at the time it is spawned: the
+/// `ReindexTaskService` respawns it whenever the doc mapping of the index is updated.
+pub struct ReindexTaskPipeline {
+    index_uid: IndexUid,
+    metastore: MetastoreServiceClient,
+    index_storage: Arc<dyn Storage>,
+    reindex_service_task_dir: PathBuf,
+    handles: Option<ReindexPipelineHandle>,
+    max_concurrent_split_uploads: usize,
+    io_throughput_limiter_opt: Option<Limiter>,
+    state: ReindexTaskPipelineState,
+    merge_scheduler_service: Mailbox<MergeSchedulerService>,
+    event_broker: EventBroker,
+}
+
+#[async_trait]
+impl Actor for ReindexTaskPipeline {
+    type ObservableState = ReindexTaskPipelineState;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.state.clone()
+    }
+
+    fn name(&self) -> String {
+        "ReindexTaskPipeline".to_string()
+    }
+
+    async fn initialize(&mut self, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        self.spawn_pipeline(ctx).await?;
+        self.handle(Observe, ctx).await?;
+        Ok(())
+    }
+
+    async fn finalize(
+        &mut self,
+        _exit_status: &ActorExitStatus,
+        _ctx: &ActorContext<Self>,
+    ) -> anyhow::Result<()> {
+        if let Some(handles) = self.handles.take() {
+            join!(
+                handles.reindex_task_planner.quit(),
+                handles.downloader.quit(),
+                handles.reindex_task_executor.quit(),
+                handles.packager.quit(),
+                handles.uploader.quit(),
+                handles.publisher.quit(),
+            );
+        };
+        Ok(())
+    }
+}
+
+impl ReindexTaskPipeline {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        index_uid: IndexUid,
+        metastore: MetastoreServiceClient,
+        index_storage: Arc<dyn Storage>,
+        reindex_service_task_dir: PathBuf,
+        max_concurrent_split_uploads: usize,
+        io_throughput_limiter_opt: Option<Limiter>,
+        merge_scheduler_service: Mailbox<MergeSchedulerService>,
+        event_broker: EventBroker,
+    ) -> Self {
+        Self {
+            index_uid,
+            metastore,
+            index_storage,
+            reindex_service_task_dir,
+            handles: Default::default(),
+            max_concurrent_split_uploads,
+            io_throughput_limiter_opt,
+            state: ReindexTaskPipelineState::default(),
+            merge_scheduler_service,
+            event_broker,
+        }
+    }
+
+    pub async fn spawn_pipeline(&mut self, ctx: &ActorContext<Self>) -> anyhow::Result<()> {
+        info!(
+            index_uid=%self.index_uid,
+            root_dir=%self.reindex_service_task_dir.to_str().unwrap(),
+            "spawning reindex task pipeline",
+        );
+        let mut index_metadata = self
+            .metastore
+            .index_metadata(IndexMetadataRequest::for_index_uid(self.index_uid.clone()))
+            .await?
+            .deserialize_index_metadata()?;
+        let reindex_task_source_config = index_metadata
+            .sources
+            .remove(REINDEX_TASK_SOURCE_ID)
+            .ok_or_else(|| anyhow::anyhow!("index has no ongoing reindex task"))?;
+        let index_config = index_metadata.into_index_config();
+        let publisher = Publisher::new(
+            PublisherType::MergePublisher,
+            self.metastore.clone(),
+            None,
+            None,
+        );
+        let (publisher_mailbox, publisher_supervisor_handler) =
+            ctx.spawn_actor().supervise(publisher);
+        let split_store =
+            IndexingSplitStore::create_without_local_store_for_test(self.index_storage.clone());
+        let merge_policy = merge_policy_from_settings(&index_config.indexing_settings);
+        let uploader = Uploader::new(
+            UploaderType::ReindexUploader,
+            self.metastore.clone(),
+            merge_policy,
+            index_config.retention_policy_opt.clone(),
+            split_store.clone(),
+            SplitsUpdateMailbox::Publisher(publisher_mailbox),
+            self.max_concurrent_split_uploads,
+            self.event_broker.clone(),
+        );
+        let (uploader_mailbox, uploader_supervisor_handler) = ctx.spawn_actor().supervise(uploader);
+
+        let doc_mapper =
+            build_doc_mapper(&index_config.doc_mapping, &index_config.search_settings)?;
+        let tag_fields = doc_mapper.tag_named_fields()?;
+        let zone_map_fields = doc_mapper.zone_map_named_fields()?;
+        let bloom_filter_fields = doc_mapper.bloom_filter_named_fields()?;
+        let packager = Packager::new(
+            "ReindexPackager",
+            tag_fields,
+            zone_map_fields,
+            bloom_filter_fields,
+            uploader_mailbox,
+        );
+        let (packager_mailbox, packager_supervisor_handler) = ctx.spawn_actor().supervise(packager);
+        let pipeline_id = MergePipelineId {
+            node_id: NodeId::from("unknown"),
+            index_uid: self.index_uid.clone(),
+            source_id: "unknown".to_string(),
+        };
+        let split_download_io_controls = IoControls::default()
+            .set_throughput_limiter_opt(self.io_throughput_limiter_opt.clone())
+            .set_component("split_downloader_reindex");
+        let reindex_executor_io_controls = split_download_io_controls
+            .clone()
+            .set_component("reindexer");
+        let reindex_executor = MergeExecutor::new(
+            pipeline_id,
+            self.metastore.clone(),
+            doc_mapper.clone(),
+            reindex_executor_io_controls,
+            packager_mailbox,
+        )
+        .with_index_sort(index_config.indexing_settings.sort_by.clone())
+        .with_reindex_transform(reindex_task_source_config.transform_config)?;
+        let (reindex_executor_mailbox, task_executor_supervisor_handler) =
+            ctx.spawn_actor().supervise(reindex_executor);
+        let scratch_directory = temp_dir::Builder::default()
+            .join(&self.index_uid.index_id)
+            .join(&self.index_uid.incarnation_id.to_string())
+            .tempdir_in(&self.reindex_service_task_dir)?;
+        let merge_split_downloader = MergeSplitDownloader {
+            scratch_directory,
+            split_store,
+            executor_mailbox: reindex_executor_mailbox,
+            io_controls: split_download_io_controls,
+        };
+        let (downloader_mailbox, downloader_supervisor_handler) =
+            ctx.spawn_actor().supervise(merge_split_downloader);
+        let task_planner = ReindexTaskPlanner::new(
+            self.index_uid.clone(),
+            doc_mapper.doc_mapping_uid(),
+            self.metastore.clone(),
+            downloader_mailbox,
+            self.merge_scheduler_service.clone(),
+        );
+        let (_, task_planner_supervisor_handler) = ctx.spawn_actor().supervise(task_planner);
+        self.handles = Some(ReindexPipelineHandle {
+            reindex_task_planner: task_planner_supervisor_handler,
+            downloader: downloader_supervisor_handler,
+            reindex_task_executor: task_executor_supervisor_handler,
+            packager: packager_supervisor_handler,
+            uploader: uploader_supervisor_handler,
+            publisher: publisher_supervisor_handler,
+        });
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct Observe;
+
+#[async_trait]
+impl Handler<Observe> for ReindexTaskPipeline {
+    type Reply = ();
+    async fn handle(
+        &mut self,
+        _: Observe,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        if let Some(handles) = &self.handles {
+            handles.reindex_task_planner.refresh_observe();
+            handles.downloader.refresh_observe();
+            handles.reindex_task_executor.refresh_observe();
+            handles.packager.refresh_observe();
+            handles.uploader.refresh_observe();
+            handles.publisher.refresh_observe();
+            self.state = ReindexTaskPipelineState {
+                reindex_task_planner: handles.reindex_task_planner.last_observation().clone(),
+                downloader: handles.downloader.last_observation().clone(),
+                reindex_task_executor: handles.reindex_task_executor.last_observation().clone(),
+                packager: handles.packager.last_observation().clone(),
+                uploader: handles.uploader.last_observation().clone(),
+                publisher: handles.publisher.last_observation().clone(),
+            }
+        }
+        ctx.schedule_self_msg(OBSERVE_PIPELINE_INTERVAL, Observe);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_actors::Universe;
+    use quickwit_common::temp_dir::TempDirectory;
+    use quickwit_config::{ConfigFormat, SourceConfig};
+    use quickwit_doc_mapper::DocMapping;
+    use quickwit_indexing::TestSandbox;
+    use quickwit_metastore::{
+        AddSourceRequestExt, ListSplitsRequestExt, MetastoreServiceStreamSplitsExt, SplitState,
+        UpdateIndexRequestExt,
+    };
+    use quickwit_proto::metastore::{AddSourceRequest, ListSplitsRequest, UpdateIndexRequest};
+    use quickwit_proto::types::DocMappingUid;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reindex_pipeline_simple() -> anyhow::Result<()> {
+        quickwit_common::setup_logging_for_tests();
+        let index_id = "test-reindex-pipeline-simple";
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+              - name: ts
+                type: i64
+                fast: true
+        "#;
+        let indexing_settings_yaml = r#"
+            merge_policy:
+                type: no_merge
+        "#;
+        let test_sandbox = TestSandbox::create(
+            index_id,
+            doc_mapping_yaml,
+            indexing_settings_yaml,
+            &["body"],
+        )
+        .await?;
+        let universe: &Universe = test_sandbox.universe();
+        let merge_scheduler_service = universe.get_or_spawn_one::<MergeSchedulerService>();
+        let index_uid = test_sandbox.index_uid();
+        let docs = vec![
+            serde_json::json!({"body": "info", "ts": 0, "severity": "INFO" }),
+            serde_json::json!({"body": "info", "ts": 1, "severity": "INFO" }),
+            serde_json::json!({"body": "error", "ts": 2, "severity": "ERROR" }),
+        ];
+        test_sandbox.add_documents(docs).await?;
+        let metastore = test_sandbox.metastore();
+
+        // The `severity` field becomes a fast field.
+        let index_config = metastore
+            .index_metadata(IndexMetadataRequest::for_index_uid(index_uid.clone()))
+            .await?
+            .deserialize_index_metadata()?
+            .into_index_config();
+        let new_doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+              - name: ts
+                type: i64
+                fast: true
+              - name: severity
+                type: text
+                tokenizer: raw
+                fast: true
+        "#;
+        let mut new_doc_mapping: DocMapping =
+            ConfigFormat::Yaml.parse(new_doc_mapping_yaml.as_bytes())?;
+        new_doc_mapping.doc_mapping_uid = DocMappingUid::random();
+        let update_index_request = UpdateIndexRequest::try_from_updates(
+            index_uid.clone(),
+            &index_config.search_settings,
+            &index_config.retention_policy_opt,
+            &index_config.indexing_settings,
+            &new_doc_mapping,
+        )?;
+        metastore.update_index(update_index_request).await?;
+        let add_source_request = AddSourceRequest::try_from_source_config(
+            index_uid.clone(),
+            &SourceConfig::reindex_task(None),
+        )?;
+        metastore.add_source(add_source_request).await?;
+
+        let reindex_service_task_dir = TempDirectory::for_test();
+        let pipeline = ReindexTaskPipeline::new(
+            index_uid.clone(),
+            metastore.clone(),
+            test_sandbox.storage(),
+            reindex_service_task_dir.path().into(),
+            4,
+            None,
+            merge_scheduler_service,
+            EventBroker::default(),
+        );
+        let (_pipeline_mailbox, pipeline_handler) = universe.spawn_builder().spawn(pipeline);
+        let _ = pipeline_handler.process_pending_and_observe().await.state;
+        // Waits for the reindexed split to be published and for the planner to notice that the
+        // task is completed.
+        universe.sleep(Duration::from_secs(30)).await;
+        let pipeline_state = pipeline_handler.process_pending_and_observe().await.state;
+        assert_eq!(pipeline_state.reindex_task_executor.metrics.num_errors, 0);
+        assert_eq!(pipeline_state.publisher.metrics.num_errors, 0);
+
+        let splits = metastore
+            .list_splits(ListSplitsRequest::try_from_index_uid(index_uid.clone()).unwrap())
+            .await?
+            .collect_splits()
+            .await?;
+        assert_eq!(splits.len(), 2);
+        let published_split = splits
+            .iter()
+            .find(|split| split.split_state == SplitState::Published)
+            .unwrap();
+        assert_eq!(published_split.split_metadata.num_docs, 3);
+        assert_eq!(
+            published_split.split_metadata.doc_mapping_uid,
+            new_doc_mapping.doc_mapping_uid
+        );
+        // All the splits are reindexed: the task is completed.
+        let index_metadata = metastore
+            .index_metadata(IndexMetadataRequest::for_index_uid(index_uid))
+            .await?
+            .deserialize_index_metadata()?;
+        assert!(!index_metadata.sources.contains_key(REINDEX_TASK_SOURCE_ID));
+
+        pipeline_handler.quit().await;
+        test_sandbox.assert_quit().await;
+        Ok(())
+    }
+}
@@ -0,0 +1,622 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use itertools::Itertools;
+use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox, QueueCapacity};
+use quickwit_config::REINDEX_TASK_SOURCE_ID;
+use quickwit_indexing::actors::{schedule_merge, MergeSchedulerService, MergeSplitDownloader};
+use quickwit_indexing::merge_policy::MergeOperation;
+use quickwit_metastore::checkpoint::{IndexCheckpointDelta, PartitionId, SourceCheckpointDelta};
+use quickwit_metastore::{
+    IndexMetadata, IndexMetadataResponseExt, ListSplitsQuery, ListSplitsRequestExt,
+    MetastoreServiceStreamSplitsExt, SplitMetadata, SplitState,
+};
+use quickwit_proto::metastore::{
+    DeleteSourceRequest, IndexMetadataRequest, ListSplitsRequest, MetastoreService,
+    MetastoreServiceClient, PublishSplitsRequest,
+};
+use quickwit_proto::types::{DocMappingUid, IndexUid, Position, SplitId};
+use serde::Serialize;
+use tantivy::Inventory;
+use time::OffsetDateTime;
+use tracing::{debug, info, warn};
+
+use crate::metrics::JANITOR_METRICS;
+
+const PLANNER_REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Maximum number of splits of an index undergoing a reindex operation at the same time.
+/// Reindex operations also compete with merges for the permits of the merge scheduler, which
+/// gives them a lower priority than regular merges.
+const MAX_CONCURRENT_REINDEX_OPERATIONS: usize = 2;
+
+/// Number of failed reindex operations after which a split is marked as failed and skipped by
+/// the reindex task.
+const MAX_REINDEX_ATTEMPTS: usize = 3;
+
+/// Returns the IDs of the splits that could not be reindexed by the ongoing reindex task of the
+/// index.
+///
+/// The failed splits are recorded as the partitions of the checkpoint of the reindex task
+/// source, so they are forgotten when the task is deleted or completed.
+pub fn failed_reindex_split_ids(index_metadata: &IndexMetadata) -> HashSet<SplitId> {
+    index_metadata
+        .checkpoint
+        .source_checkpoint(REINDEX_TASK_SOURCE_ID)
+        .map(|source_checkpoint| {
+            source_checkpoint
+                .iter()
+                .map(|(partition_id, _position)| partition_id.as_str().to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The `ReindexTaskPlanner` plans reindex operations on the splits of a given index.
+///
+/// A split needs to be reindexed when it was built with a doc mapping other than the current
+/// doc mapping of the index, `doc_mapping_uid`. For each of these splits, the planner sends
+/// a reindex [`MergeOperation`] to the `MergeExecutor`, which rebuilds the split from its
+/// docstore and publishes it in place of the original split.
+///
+/// The progress of the task is not recorded anywhere: the splits that remain to be reindexed
+/// are listed from the metastore on each planning pass, which makes the task naturally
+/// resumable. Once all the splits of the index have been reindexed, the planner deletes the
+/// reindex task source of the index, which marks the task as completed.
+///
+/// Like delete operations, reindex operations only run on mature splits to avoid conflicting
+/// with the merge pipeline.
+///
+/// A split that still needs to be reindexed once its reindex operation is over has failed to be
+/// reindexed. After `MAX_REINDEX_ATTEMPTS` failures, the split is recorded as failed in the
+/// metastore and skipped by the task. Once only failed splits remain, the task stays idle so the
+/// failures remain visible until the task is deleted.
+pub struct ReindexTaskPlanner {
+    index_uid: IndexUid,
+    doc_mapping_uid: DocMappingUid,
+    metastore: MetastoreServiceClient,
+    merge_split_downloader_mailbox: Mailbox<MergeSplitDownloader>,
+    merge_scheduler_service: Mailbox<MergeSchedulerService>,
+    /// Inventory of ongoing reindex operations. A reindex operation is dropped after the publish
+    /// of the reindexed split.
+    /// The inventory is used to avoid sending twice the same reindex operation.
+    ongoing_reindex_operations_inventory: Inventory<MergeOperation>,
+    /// Splits for which a reindex operation was sent, used to detect the failed operations.
+    planned_split_ids: HashSet<SplitId>,
+    /// Number of failed reindex operations per split.
+    num_failed_attempts: HashMap<SplitId, usize>,
+    num_remaining_splits: usize,
+    num_failed_splits: usize,
+}
+
+#[async_trait]
+impl Actor for ReindexTaskPlanner {
+    type ObservableState = ReindexTaskPlannerState;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        let ongoing_reindex_operations = self
+            .ongoing_reindex_operations_inventory
+            .list()
+            .iter()
+            .map(|tracked_operation| tracked_operation.as_ref().clone())
+            .collect_vec();
+        ReindexTaskPlannerState {
+            ongoing_reindex_operations,
+            num_remaining_splits: self.num_remaining_splits,
+            num_failed_splits: self.num_failed_splits,
+        }
+    }
+
+    fn name(&self) -> String {
+        "ReindexTaskPlanner".to_string()
+    }
+
+    fn queue_capacity(&self) -> QueueCapacity {
+        QueueCapacity::Bounded(0)
+    }
+
+    async fn initialize(&mut self, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        self.handle(PlanReindexLoop, ctx).await
+    }
+}
+
+impl ReindexTaskPlanner {
+    pub fn new(
+        index_uid: IndexUid,
+        doc_mapping_uid: DocMappingUid,
+        metastore: MetastoreServiceClient,
+        merge_split_downloader_mailbox: Mailbox<MergeSplitDownloader>,
+        merge_scheduler_service: Mailbox<MergeSchedulerService>,
+    ) -> Self {
+        Self {
+            index_uid,
+            doc_mapping_uid,
+            metastore,
+            merge_split_downloader_mailbox,
+            merge_scheduler_service,
+            ongoing_reindex_operations_inventory: Inventory::new(),
+            planned_split_ids: HashSet::new(),
+            num_failed_attempts: HashMap::new(),
+            num_remaining_splits: 0,
+            num_failed_splits: 0,
+        }
+    }
+
+    /// Sends reindex operations for the splits built with an outdated doc mapping.
+    async fn send_reindex_operations(&mut self, ctx: &ActorContext<Self>) -> anyhow::Result<()> {
+        let index_metadata_request = IndexMetadataRequest::for_index_uid(self.index_uid.clone());
+        let index_metadata = ctx
+            .protect_future(self.metastore.index_metadata(index_metadata_request))
+            .await?
+            .deserialize_index_metadata()?;
+
+        if !index_metadata.sources.contains_key(REINDEX_TASK_SOURCE_ID)
+            || index_metadata.index_config.doc_mapping.doc_mapping_uid != self.doc_mapping_uid
+        {
+            // The task was cancelled or the doc mapping was updated in the meantime: the
+            // reindex task service is about to stop or respawn this pipeline.
+            debug!(index_id=%self.index_uid.index_id, "reindex task is outdated");
+            return Ok(());
+        }
+        let mut failed_split_ids = failed_reindex_split_ids(&index_metadata);
+        let mut outdated_splits = self.list_outdated_splits(ctx).await?;
+
+        let ongoing_reindex_operations = self.ongoing_reindex_operations_inventory.list();
+        let ongoing_split_ids: HashSet<&str> = ongoing_reindex_operations
+            .iter()
+            .flat_map(|operation| operation.splits.iter().map(|split| split.split_id()))
+            .collect();
+        let outdated_split_ids: HashSet<&str> = outdated_splits
+            .iter()
+            .map(|split| split.split_id())
+            .collect();
+
+        // The splits of the operations that are over but still need to be reindexed have failed
+        // to be reindexed.
+        let mut newly_failed_split_ids = Vec::new();
+        for split_id in std::mem::take(&mut self.planned_split_ids) {
+            if ongoing_split_ids.contains(split_id.as_str()) {
+                self.planned_split_ids.insert(split_id);
+                continue;
+            }
+            if !outdated_split_ids.contains(split_id.as_str()) {
+                self.num_failed_attempts.remove(&split_id);
+                continue;
+            }
+            let num_failed_attempts = self
+                .num_failed_attempts
+                .entry(split_id.clone())
+                .or_default();
+            *num_failed_attempts += 1;
+
+            if *num_failed_attempts >= MAX_REINDEX_ATTEMPTS {
+                warn!(
+                    index_id=%self.index_uid.index_id,
+                    split_id=%split_id,
+                    num_attempts=*num_failed_attempts,
+                    "failed to reindex split, skipping it"
+                );
+                self.num_failed_attempts.remove(&split_id);
+                newly_failed_split_ids.push(split_id);
+            } else {
+                warn!(
+                    index_id=%self.index_uid.index_id,
+                    split_id=%split_id,
+                    num_attempts=*num_failed_attempts,
+                    "failed to reindex split, retrying"
+                );
+            }
+        }
+
+        if !newly_failed_split_ids.is_empty() {
+            self.record_failed_splits(&newly_failed_split_ids, ctx)
+                .await?;
+            failed_split_ids.extend(newly_failed_split_ids);
+        }
+        outdated_splits.retain(|split| !failed_split_ids.contains(split.split_id()));
+        self.num_remaining_splits = outdated_splits.len();
+        self.num_failed_splits = failed_split_ids.len();
+
+        if outdated_splits.is_empty() && ongoing_reindex_operations.is_empty() {
+            if !failed_split_ids.is_empty() {
+                debug!(
+                    index_id=%self.index_uid.index_id,
+                    num_failed_splits=failed_split_ids.len(),
+                    "reindex task is over, some splits failed to be reindexed"
+                );
+                return Ok(());
+            }
+            info!(index_id=%self.index_uid.index_id, "reindex-task-completed");
+            let delete_source_request = DeleteSourceRequest {
+                index_uid: Some(self.index_uid.clone()),
+                source_id: REINDEX_TASK_SOURCE_ID.to_string(),
+            };
+            ctx.protect_future(self.metastore.delete_source(delete_source_request))
+                .await?;
+            return Ok(());
+        }
+        let now = OffsetDateTime::now_utc();
+        let num_operations_to_send =
+            MAX_CONCURRENT_REINDEX_OPERATIONS.saturating_sub(ongoing_reindex_operations.len());
+        let splits_to_reindex: Vec<SplitMetadata> = outdated_splits
+            .into_iter()
+            .filter(|split| split.is_mature(now))
+            .filter(|split| {
+                !ongoing_reindex_operations.iter().any(|operation| {
+                    operation
+                        .splits
+                        .first()
+                        .unwrap() // <- This is safe as we know for sure that an operation is on one split.
+                        .split_id()
+                        == split.split_id()
+                })
+            })
+            .take(num_operations_to_send)
+            .collect();
+        drop(ongoing_reindex_operations);
+
+        for split in splits_to_reindex {
+            self.planned_split_ids.insert(split.split_id.clone());
+            let reindex_operation = MergeOperation::new_reindex_operation(split);
+            info!(reindex_operation=?reindex_operation, "planned reindex operation");
+            let tracked_reindex_operation = self
+                .ongoing_reindex_operations_inventory
+                .track(reindex_operation);
+            schedule_merge(
+                &self.merge_scheduler_service,
+                tracked_reindex_operation,
+                self.merge_split_downloader_mailbox.clone(),
+            )
+            .await?;
+        }
+        let index_label = quickwit_common::metrics::index_label(self.index_uid.index_id.as_str());
+        JANITOR_METRICS
+            .ongoing_num_reindex_operations_total
+            .with_label_values([index_label])
+            .set(self.ongoing_reindex_operations_inventory.list().len() as i64);
+        Ok(())
+    }
+
+    /// Records the splits that failed to be reindexed in the checkpoint of the reindex task
+    /// source.
+    async fn record_failed_splits(
+        &self,
+        split_ids: &[SplitId],
+        ctx: &ActorContext<Self>,
+    ) -> anyhow::Result<()> {
+        let mut source_delta = SourceCheckpointDelta::default();
+
+        for split_id in split_ids {
+            source_delta.record_partition_delta(
+                PartitionId::from(split_id.as_str()),
+                Position::Beginning,
+                Position::Beginning.as_eof(),
+            )?;
+        }
+        let index_checkpoint_delta = IndexCheckpointDelta {
+            source_id: REINDEX_TASK_SOURCE_ID.to_string(),
+            source_delta,
+        };
+        let publish_splits_request = PublishSplitsRequest {
+            index_uid: Some(self.index_uid.clone()),
+            staged_split_ids: Vec::new(),
+            replaced_split_ids: Vec::new(),
+            index_checkpoint_delta_json_opt: Some(serde_json::to_string(&index_checkpoint_delta)?),
+            publish_token_opt: None,
+        };
+        ctx.protect_future(self.metastore.publish_splits(publish_splits_request))
+            .await?;
+        Ok(())
+    }
+
+    /// Lists the published splits of the index that were not built with the current doc
+    /// mapping.
+    async fn list_outdated_splits(
+        &self,
+        ctx: &ActorContext<Self>,
+    ) -> anyhow::Result<Vec<SplitMetadata>> {
+        let query = ListSplitsQuery::for_index(self.index_uid.clone())
+            .with_split_state(SplitState::Published);
+        let list_splits_request = ListSplitsRequest::try_from_list_splits_query(&query)?;
+        let outdated_splits = ctx
+            .protect_future(self.metastore.list_splits(list_splits_request))
+            .await?
+            .collect_splits_metadata()
+            .await?
+            .into_iter()
+            .filter(|split| split.doc_mapping_uid != self.doc_mapping_uid)
+            .collect();
+        Ok(outdated_splits)
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ReindexTaskPlannerState {
+    ongoing_reindex_operations: Vec<MergeOperation>,
+    num_remaining_splits: usize,
+    num_failed_splits: usize,
+}
+
+#[derive(Debug)]
+struct PlanReindexOperations;
+
+#[async_trait]
+impl Handler<PlanReindexOperations> for ReindexTaskPlanner {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _: PlanReindexOperations,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        self.send_reindex_operations(ctx).await?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct PlanReindexLoop;
+
+#[async_trait]
+impl Handler<PlanReindexLoop> for ReindexTaskPlanner {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _: PlanReindexLoop,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        self.handle(PlanReindexOperations, ctx).await?;
+        ctx.schedule_self_msg(PLANNER_REFRESH_INTERVAL, PlanReindexLoop);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use quickwit_actors::Universe;
+    use quickwit_common::ServiceStream;
+    use quickwit_config::{IndexConfig, SourceConfig};
+    use quickwit_indexing::merge_policy::MergeTask;
+    use quickwit_metastore::{IndexMetadata, ListSplitsResponseExt, Split};
+    use quickwit_proto::metastore::{
+        EmptyResponse, IndexMetadataResponse, ListSplitsResponse, MockMetastoreService,
+    };
+
+    use super::*;
+
+    fn make_split(split_id: &str, doc_mapping_uid: DocMappingUid) -> Split {
+        Split {
+            split_metadata: SplitMetadata {
+                split_id: split_id.to_string(),
+                doc_mapping_uid,
+                footer_offsets: 5..20,
+                ..Default::default()
+            },
+            split_state: SplitState::Published,
+            update_timestamp: 0,
+            publish_timestamp: Some(100),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reindex_task_planner() {
+        let universe = Universe::with_accelerated_time();
+        let old_doc_mapping_uid = DocMappingUid::for_test(1);
+        let index_config = IndexConfig::for_test("test-index", "ram:///indexes/test-index");
+        let current_doc_mapping_uid = index_config.doc_mapping.doc_mapping_uid;
+        let mut index_metadata = IndexMetadata::new(index_config);
+        index_metadata
+            .add_source(SourceConfig::reindex_task(None))
+            .unwrap();
+        let index_uid = index_metadata.index_uid.clone();
+
+        let splits = Arc::new(Mutex::new(vec![
+            make_split("split-1", old_doc_mapping_uid),
+            make_split("split-2", old_doc_mapping_uid),
+            make_split("split-3", old_doc_mapping_uid),
+            make_split("split-4", current_doc_mapping_uid),
+        ]));
+        let splits_clone = splits.clone();
+        let source_deleted = Arc::new(AtomicBool::new(false));
+        let source_deleted_clone = source_deleted.clone();
+
+        let mut mock_metastore = MockMetastoreService::new();
+        mock_metastore.expect_index_metadata().returning(move |_| {
+            let mut index_metadata = index_metadata.clone();
+            if source_deleted_clone.load(Ordering::Relaxed) {
+                index_metadata.sources.remove(REINDEX_TASK_SOURCE_ID);
+            }
+            Ok(IndexMetadataResponse::try_from_index_metadata(&index_metadata).unwrap())
+        });
+        mock_metastore.expect_list_splits().returning(move |_| {
+            let splits = splits_clone.lock().unwrap().clone();
+            let splits_response = ListSplitsResponse::try_from_splits(splits).unwrap();
+            Ok(ServiceStream::from(vec![Ok(splits_response)]))
+        });
+        mock_metastore
+            .expect_delete_source()
+            .times(1)
+            .returning(move |delete_source_request| {
+                assert_eq!(delete_source_request.source_id, REINDEX_TASK_SOURCE_ID);
+                source_deleted.store(true, Ordering::Relaxed);
+                Ok(EmptyResponse {})
+            });
+        let merge_scheduler_mailbox = universe.get_or_spawn_one();
+        let (merge_split_downloader_mailbox, merge_split_downloader_inbox) =
+            universe.create_test_mailbox();
+        let reindex_planner = ReindexTaskPlanner::new(
+            index_uid,
+            current_doc_mapping_uid,
+            MetastoreServiceClient::from_mock(mock_metastore),
+            merge_split_downloader_mailbox,
+            merge_scheduler_mailbox,
+        );
+        let (reindex_planner_mailbox, reindex_planner_handle) =
+            universe.spawn_builder().spawn(reindex_planner);
+        reindex_planner_handle.process_pending_and_observe().await;
+
+        // Only `MAX_CONCURRENT_REINDEX_OPERATIONS` operations are planned at once.
+        let mut downloader_msgs: Vec<MergeTask> =
+            merge_split_downloader_inbox.drain_for_test_typed();
+        assert_eq!(downloader_msgs.len(), 2);
+        assert_eq!(downloader_msgs[0].splits[0].split_id(), "split-1");
+        assert_eq!(downloader_msgs[1].splits[0].split_id(), "split-2");
+        let reindex_planner_state = reindex_planner_handle.observe().await;
+        assert_eq!(reindex_planner_state.num_remaining_splits, 3);
+        assert_eq!(reindex_planner_state.ongoing_reindex_operations.len(), 2);
+
+        // The first split is reindexed.
+        splits.lock().unwrap().remove(0);
+        drop(downloader_msgs.remove(0));
+
+        reindex_planner_mailbox
+            .ask(PlanReindexOperations)
+            .await
+            .unwrap();
+        let new_downloader_msgs: Vec<MergeTask> =
+            merge_split_downloader_inbox.drain_for_test_typed();
+        assert_eq!(new_downloader_msgs.len(), 1);
+        assert_eq!(new_downloader_msgs[0].splits[0].split_id(), "split-3");
+
+        // All the splits are reindexed: the task is completed.
+        splits
+            .lock()
+            .unwrap()
+            .retain(|split| split.split_metadata.doc_mapping_uid == current_doc_mapping_uid);
+        drop(downloader_msgs);
+        drop(new_downloader_msgs);
+        reindex_planner_mailbox
+            .ask(PlanReindexOperations)
+            .await
+            .unwrap();
+        let reindex_planner_state = reindex_planner_handle.observe().await;
+        assert_eq!(reindex_planner_state.num_remaining_splits, 0);
+        assert!(merge_split_downloader_inbox.drain_for_test().is_empty());
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_reindex_task_planner_skips_failed_splits() {
+        let universe = Universe::with_accelerated_time();
+        let old_doc_mapping_uid = DocMappingUid::for_test(1);
+        let index_config = IndexConfig::for_test("test-index", "ram:///indexes/test-index");
+        let current_doc_mapping_uid = index_config.doc_mapping.doc_mapping_uid;
+        let mut index_metadata = IndexMetadata::new(index_config);
+        index_metadata
+            .add_source(SourceConfig::reindex_task(None))
+            .unwrap();
+        let index_uid = index_metadata.index_uid.clone();
+        let index_metadata = Arc::new(Mutex::new(index_metadata));
+        let index_metadata_clone = index_metadata.clone();
+
+        let mut mock_metastore = MockMetastoreService::new();
+        mock_metastore.expect_index_metadata().returning(move |_| {
+            let index_metadata = index_metadata_clone.lock().unwrap();
+            Ok(IndexMetadataResponse::try_from_index_metadata(&index_metadata).unwrap())
+        });
+        mock_metastore.expect_list_splits().returning(move |_| {
+            // The split is never reindexed successfully.
+            let splits = vec![make_split("split-1", old_doc_mapping_uid)];
+            let splits_response = ListSplitsResponse::try_from_splits(splits).unwrap();
+            Ok(ServiceStream::from(vec![Ok(splits_response)]))
+        });
+        mock_metastore
+            .expect_publish_splits()
+            .times(1)
+            .returning(move |publish_splits_request| {
+                assert!(publish_splits_request.staged_split_ids.is_empty());
+                assert!(publish_splits_request.replaced_split_ids.is_empty());
+                let index_checkpoint_delta: IndexCheckpointDelta = serde_json::from_str(
+                    &publish_splits_request
+                        .index_checkpoint_delta_json_opt
+                        .unwrap(),
+                )
+                .unwrap();
+                assert_eq!(index_checkpoint_delta.source_id, REINDEX_TASK_SOURCE_ID);
+                index_metadata
+                    .lock()
+                    .unwrap()
+                    .checkpoint
+                    .try_apply_delta(index_checkpoint_delta)
+                    .unwrap();
+                Ok(EmptyResponse {})
+            });
+        mock_metastore.expect_delete_source().never();
+
+        let merge_scheduler_mailbox = universe.get_or_spawn_one();
+        let (merge_split_downloader_mailbox, merge_split_downloader_inbox) =
+            universe.create_test_mailbox();
+        let reindex_planner = ReindexTaskPlanner::new(
+            index_uid,
+            current_doc_mapping_uid,
+            MetastoreServiceClient::from_mock(mock_metastore),
+            merge_split_downloader_mailbox,
+            merge_scheduler_mailbox,
+        );
+        let (reindex_planner_mailbox, reindex_planner_handle) =
+            universe.spawn_builder().spawn(reindex_planner);
+        reindex_planner_handle.process_pending_and_observe().await;
+
+        for _ in 1..MAX_REINDEX_ATTEMPTS {
+            let downloader_msgs: Vec<MergeTask> =
+                merge_split_downloader_inbox.drain_for_test_typed();
+            assert_eq!(downloader_msgs.len(), 1);
+            assert_eq!(downloader_msgs[0].splits[0].split_id(), "split-1");
+            // The reindex operation fails.
+            drop(downloader_msgs);
+
+            reindex_planner_mailbox
+                .ask(PlanReindexOperations)
+                .await
+                .unwrap();
+        }
+        let downloader_msgs: Vec<MergeTask> = merge_split_downloader_inbox.drain_for_test_typed();
+        assert_eq!(downloader_msgs.len(), 1);
+        drop(downloader_msgs);
+
+        // The split is marked as failed after `MAX_REINDEX_ATTEMPTS` failed operations.
+        reindex_planner_mailbox
+            .ask(PlanReindexOperations)
+            .await
+            .unwrap();
+        let reindex_planner_state = reindex_planner_handle.observe().await;
+        assert_eq!(reindex_planner_state.num_remaining_splits, 0);
+        assert_eq!(reindex_planner_state.num_failed_splits, 1);
+        assert!(merge_split_downloader_inbox.drain_for_test().is_empty());
+
+        // The failed split is skipped by the following planning passes.
+        reindex_planner_mailbox
+            .ask(PlanReindexOperations)
+            .await
+            .unwrap();
+        let reindex_planner_state = reindex_planner_handle.observe().await;
+        assert_eq!(reindex_planner_state.num_failed_splits, 1);
+        assert!(reindex_planner_state.ongoing_reindex_operations.is_empty());
+        assert!(merge_split_downloader_inbox.drain_for_test().is_empty());
+        universe.assert_quit().await;
+    }
+}
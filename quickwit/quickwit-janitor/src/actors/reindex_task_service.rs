@@ -0,0 +1,305 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use quickwit_actors::{Actor, ActorContext, ActorExitStatus, ActorHandle, Handler, Mailbox};
+use quickwit_common::io::Limiter;
+use quickwit_common::pubsub::EventBroker;
+use quickwit_common::temp_dir::{self};
+use quickwit_config::{SourceConfig, REINDEX_TASK_SOURCE_ID};
+use quickwit_indexing::actors::MergeSchedulerService;
+use quickwit_metastore::{IndexMetadata, ListIndexesMetadataResponseExt};
+use quickwit_proto::metastore::{
+    ListIndexesMetadataRequest, MetastoreService, MetastoreServiceClient,
+};
+use quickwit_proto::types::{DocMappingUid, IndexUid};
+use quickwit_storage::StorageResolver;
+use serde::Serialize;
+use tracing::{error, info, warn};
+
+use super::reindex_task_pipeline::ReindexTaskPipeline;
+
+pub const REINDEX_SERVICE_TASK_DIR_NAME: &str = "reindex_task_service";
+
+const UPDATE_PIPELINES_INTERVAL: Duration = if cfg!(any(test, feature = "testsuite")) {
+    Duration::from_millis(200)
+} else {
+    Duration::from_secs(30)
+};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReindexTaskServiceState {
+    pub num_running_pipelines: usize,
+}
+
+/// A reindex task pipeline is bound to the doc mapping of the index and to the reindex task
+/// source it was spawned for.
+struct ReindexTaskPipelineEntry {
+    doc_mapping_uid: DocMappingUid,
+    reindex_task_source_config: SourceConfig,
+    pipeline_handle: ActorHandle<ReindexTaskPipeline>,
+}
+
+/// The `ReindexTaskService` runs a [`ReindexTaskPipeline`] for each index with an ongoing reindex
+/// task, that is, each index with a reindex task source.
+pub struct ReindexTaskService {
+    metastore: MetastoreServiceClient,
+    storage_resolver: StorageResolver,
+    reindex_service_task_dir: PathBuf,
+    pipelines_by_index_uid: HashMap<IndexUid, ReindexTaskPipelineEntry>,
+    max_concurrent_split_uploads: usize,
+    io_throughput_limiter_opt: Option<Limiter>,
+    event_broker: EventBroker,
+    merge_scheduler_service: Mailbox<MergeSchedulerService>,
+}
+
+impl ReindexTaskService {
+    pub async fn new(
+        metastore: MetastoreServiceClient,
+        storage_resolver: StorageResolver,
+        data_dir_path: PathBuf,
+        max_concurrent_split_uploads: usize,
+        io_throughput_limiter_opt: Option<Limiter>,
+        merge_scheduler_service: Mailbox<MergeSchedulerService>,
+        event_broker: EventBroker,
+    ) -> anyhow::Result<Self> {
+        let reindex_service_task_path = data_dir_path.join(REINDEX_SERVICE_TASK_DIR_NAME);
+        let reindex_service_task_dir =
+            temp_dir::create_or_purge_directory(reindex_service_task_path.as_path()).await?;
+        Ok(Self {
+            metastore,
+            storage_resolver,
+            reindex_service_task_dir,
+            pipelines_by_index_uid: Default::default(),
+            max_concurrent_split_uploads,
+            io_throughput_limiter_opt,
+            merge_scheduler_service,
+            event_broker,
+        })
+    }
+}
+
+#[async_trait]
+impl Actor for ReindexTaskService {
+    type ObservableState = ReindexTaskServiceState;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        ReindexTaskServiceState {
+            num_running_pipelines: self.pipelines_by_index_uid.len(),
+        }
+    }
+
+    fn name(&self) -> String {
+        "ReindexTaskService".to_string()
+    }
+
+    async fn initialize(&mut self, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        self.handle(UpdatePipelines, ctx).await?;
+        Ok(())
+    }
+}
+
+impl ReindexTaskService {
+    pub async fn update_pipeline_handles(
+        &mut self,
+        ctx: &ActorContext<Self>,
+    ) -> anyhow::Result<()> {
+        let mut indexes_with_reindex_task: HashMap<IndexUid, IndexMetadata> = self
+            .metastore
+            .list_indexes_metadata(ListIndexesMetadataRequest::all())
+            .await?
+            .deserialize_indexes_metadata()
+            .await?
+            .into_iter()
+            .filter(|index_metadata| index_metadata.sources.contains_key(REINDEX_TASK_SOURCE_ID))
+            .map(|index_metadata| (index_metadata.index_uid.clone(), index_metadata))
+            .collect();
+
+        // Stop the pipelines of deleted indexes, of completed or cancelled tasks, and of tasks
+        // that are now outdated because the doc mapping of the index was updated or the task was
+        // restarted.
+        let index_uids_to_stop: Vec<IndexUid> = self
+            .pipelines_by_index_uid
+            .iter()
+            .filter(|(index_uid, pipeline_entry)| {
+                let Some(index_metadata) = indexes_with_reindex_task.get(*index_uid) else {
+                    return true;
+                };
+                index_metadata.index_config.doc_mapping.doc_mapping_uid
+                    != pipeline_entry.doc_mapping_uid
+                    || index_metadata.sources.get(REINDEX_TASK_SOURCE_ID)
+                        != Some(&pipeline_entry.reindex_task_source_config)
+            })
+            .map(|(index_uid, _)| index_uid.clone())
+            .collect();
+
+        for index_uid in index_uids_to_stop {
+            info!(index_id=%index_uid.index_id, "stopping reindex task pipeline");
+            let pipeline_entry = self
+                .pipelines_by_index_uid
+                .remove(&index_uid)
+                .expect("pipeline entry should be present");
+            // Kill the pipeline, this avoids to wait a long time for a reindex operation to
+            // finish. The splits of an interrupted operation are left untouched.
+            pipeline_entry.pipeline_handle.kill().await;
+        }
+
+        // Start new pipelines.
+        indexes_with_reindex_task
+            .retain(|index_uid, _| !self.pipelines_by_index_uid.contains_key(index_uid));
+
+        for (index_uid, index_metadata) in indexes_with_reindex_task {
+            if let Err(error) = self.spawn_pipeline(index_metadata, ctx).await {
+                warn!(index_id=%index_uid.index_id, %error, "failed to spawn reindex task pipeline");
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn spawn_pipeline(
+        &mut self,
+        index_metadata: IndexMetadata,
+        ctx: &ActorContext<Self>,
+    ) -> anyhow::Result<()> {
+        let index_storage = self
+            .storage_resolver
+            .resolve(index_metadata.index_uri())
+            .await?;
+        let reindex_task_source_config = index_metadata
+            .sources
+            .get(REINDEX_TASK_SOURCE_ID)
+            .cloned()
+            .expect("index should have a reindex task source");
+        let pipeline = ReindexTaskPipeline::new(
+            index_metadata.index_uid.clone(),
+            self.metastore.clone(),
+            index_storage,
+            self.reindex_service_task_dir.clone(),
+            self.max_concurrent_split_uploads,
+            self.io_throughput_limiter_opt.clone(),
+            self.merge_scheduler_service.clone(),
+            self.event_broker.clone(),
+        );
+        let (_pipeline_mailbox, pipeline_handle) = ctx.spawn_actor().spawn(pipeline);
+        let pipeline_entry = ReindexTaskPipelineEntry {
+            doc_mapping_uid: index_metadata.index_config.doc_mapping.doc_mapping_uid,
+            reindex_task_source_config,
+            pipeline_handle,
+        };
+        self.pipelines_by_index_uid
+            .insert(index_metadata.index_uid, pipeline_entry);
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct UpdatePipelines;
+
+#[async_trait]
+impl Handler<UpdatePipelines> for ReindexTaskService {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _: UpdatePipelines,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        let result = self.update_pipeline_handles(ctx).await;
+        if let Err(error) = result {
+            error!(error=%error, "reindex task pipelines update failed");
+        }
+        ctx.schedule_self_msg(UPDATE_PIPELINES_INTERVAL, UpdatePipelines);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_actors::Universe;
+    use quickwit_indexing::TestSandbox;
+    use quickwit_metastore::AddSourceRequestExt;
+    use quickwit_proto::metastore::{AddSourceRequest, DeleteSourceRequest};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reindex_task_service() -> anyhow::Result<()> {
+        quickwit_common::setup_logging_for_tests();
+        let index_id = "test-reindex-task-service-index";
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+        "#;
+        let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"]).await?;
+        let index_uid = test_sandbox.index_uid();
+        let metastore = test_sandbox.metastore();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let data_dir_path = temp_dir.path().to_path_buf();
+        let universe: &Universe = test_sandbox.universe();
+        let reindex_task_service = ReindexTaskService::new(
+            metastore.clone(),
+            test_sandbox.storage_resolver(),
+            data_dir_path,
+            4,
+            None,
+            universe.get_or_spawn_one(),
+            EventBroker::default(),
+        )
+        .await
+        .unwrap();
+        let (_reindex_task_service_mailbox, reindex_task_service_handler) =
+            universe.spawn_builder().spawn(reindex_task_service);
+        let state = reindex_task_service_handler
+            .process_pending_and_observe()
+            .await;
+        assert_eq!(state.num_running_pipelines, 0);
+
+        // Adding the reindex task source starts a pipeline.
+        let add_source_request = AddSourceRequest::try_from_source_config(
+            index_uid.clone(),
+            &SourceConfig::reindex_task(None),
+        )?;
+        metastore.add_source(add_source_request).await?;
+        universe.sleep(UPDATE_PIPELINES_INTERVAL * 2).await;
+        let state = reindex_task_service_handler
+            .process_pending_and_observe()
+            .await;
+        assert_eq!(state.num_running_pipelines, 1);
+
+        // Deleting it cancels the task and stops the pipeline.
+        let delete_source_request = DeleteSourceRequest {
+            index_uid: Some(index_uid.clone()),
+            source_id: REINDEX_TASK_SOURCE_ID.to_string(),
+        };
+        // The planner may have already completed the task, as there is nothing to reindex.
+        let _ = metastore.delete_source(delete_source_request).await;
+        universe.sleep(UPDATE_PIPELINES_INTERVAL * 2).await;
+        let state = reindex_task_service_handler
+            .process_pending_and_observe()
+            .await;
+        assert_eq!(state.num_running_pipelines, 0);
+        test_sandbox.assert_quit().await;
+        Ok(())
+    }
+}
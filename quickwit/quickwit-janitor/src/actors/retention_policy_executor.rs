@@ -216,6 +216,7 @@ impl Handler<Execute> for RetentionPolicyExecutor {
             message.index_uid.clone(),
             self.metastore.clone(),
             retention_policy,
+            index_config.rollup_opt.as_ref(),
             ctx,
         )
         .await;
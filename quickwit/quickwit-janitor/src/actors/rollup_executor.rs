@@ -0,0 +1,289 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use quickwit_actors::{Actor, ActorContext, Handler};
+use quickwit_config::IndexConfig;
+use quickwit_metastore::ListIndexesMetadataResponseExt;
+use quickwit_proto::ingest::router::IngestRouterServiceClient;
+use quickwit_proto::metastore::{
+    ListIndexesMetadataRequest, MetastoreService, MetastoreServiceClient,
+};
+use quickwit_proto::types::IndexUid;
+use quickwit_search::{ClusterClient, SearcherContext};
+use serde::Serialize;
+use tracing::{debug, error, info};
+
+use crate::rollup_execution::run_execute_rollup;
+
+const RUN_INTERVAL: Duration = Duration::from_secs(60 * 60); // 1 hours
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct RollupExecutorCounters {
+    /// The number of refresh the config passes.
+    pub num_refresh_passes: usize,
+
+    /// The number of execution passes.
+    pub num_execution_passes: usize,
+
+    /// The number of rollup documents ingested.
+    pub num_rollup_docs: usize,
+}
+
+#[derive(Debug)]
+struct Loop;
+
+#[derive(Debug)]
+struct Execute {
+    index_uid: IndexUid,
+}
+
+/// An actor for scheduling rollup jobs on all indexes.
+/// It keeps a list of indexes that have a rollup configured
+/// in a cache and periodically update this list.
+pub struct RollupExecutor {
+    metastore: MetastoreServiceClient,
+    searcher_context: Arc<SearcherContext>,
+    cluster_client: ClusterClient,
+    ingest_router: IngestRouterServiceClient,
+    /// A map of index_id to index config of the indexes managed by this executor.
+    index_configs: HashMap<String, IndexConfig>,
+    counters: RollupExecutorCounters,
+}
+
+impl RollupExecutor {
+    pub fn new(
+        metastore: MetastoreServiceClient,
+        searcher_context: Arc<SearcherContext>,
+        cluster_client: ClusterClient,
+        ingest_router: IngestRouterServiceClient,
+    ) -> Self {
+        Self {
+            metastore,
+            searcher_context,
+            cluster_client,
+            ingest_router,
+            index_configs: HashMap::new(),
+            counters: RollupExecutorCounters::default(),
+        }
+    }
+
+    /// Indexes refresh Loop handler logic.
+    /// Should not return an error to prevent the actor from crashing.
+    async fn handle_refresh_loop(&mut self, ctx: &ActorContext<Self>) {
+        debug!("loading indexes from the metastore");
+        self.counters.num_refresh_passes += 1;
+
+        let response = match self
+            .metastore
+            .list_indexes_metadata(ListIndexesMetadataRequest::all())
+            .await
+        {
+            Ok(response) => response,
+            Err(error) => {
+                error!(%error, "failed to list indexes from the metastore");
+                return;
+            }
+        };
+        let indexes = match response.deserialize_indexes_metadata().await {
+            Ok(indexes) => indexes,
+            Err(error) => {
+                error!(%error, "failed to deserialize indexes metadata");
+                return;
+            }
+        };
+        // Indexes that were deleted or no longer have a rollup configured are removed from the
+        // cache. Their pending `Execute` messages become no-ops.
+        self.index_configs.retain(|index_id, _| {
+            indexes.iter().any(|index_metadata| {
+                index_metadata.index_id() == index_id
+                    && index_metadata.index_config.rollup_opt.is_some()
+            })
+        });
+        for index_metadata in indexes {
+            let index_uid = index_metadata.index_uid.clone();
+            let index_config = index_metadata.into_index_config();
+
+            let Some(rollup_config) = &index_config.rollup_opt else {
+                continue;
+            };
+            if let Some(value) = self.index_configs.get_mut(&index_config.index_id) {
+                *value = index_config;
+                continue;
+            }
+            match rollup_config.duration_until_next_execution() {
+                Ok(next_interval) => {
+                    info!(index_id=%index_config.index_id, scheduled_in=?next_interval, "rollup-schedule-operation");
+                    self.index_configs
+                        .insert(index_config.index_id.clone(), index_config);
+                    ctx.schedule_self_msg(next_interval, Execute { index_uid });
+                }
+                Err(error) => {
+                    error!(index_id=%index_config.index_id, %error, "failed to compute the next rollup execution time");
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Actor for RollupExecutor {
+    type ObservableState = RollupExecutorCounters;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.counters.clone()
+    }
+
+    fn name(&self) -> String {
+        "RollupExecutor".to_string()
+    }
+
+    async fn initialize(
+        &mut self,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), quickwit_actors::ActorExitStatus> {
+        self.handle(Loop, ctx).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<Loop> for RollupExecutor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _: Loop,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), quickwit_actors::ActorExitStatus> {
+        self.handle_refresh_loop(ctx).await;
+        ctx.schedule_self_msg(RUN_INTERVAL, Loop);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<Execute> for RollupExecutor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        message: Execute,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), quickwit_actors::ActorExitStatus> {
+        info!(index_id=%message.index_uid.index_id, "rollup-execute-operation");
+        self.counters.num_execution_passes += 1;
+
+        let Some(index_config) = self.index_configs.get(&message.index_uid.index_id) else {
+            debug!(index_id=%message.index_uid.index_id, "the index might have been deleted");
+            return Ok(());
+        };
+        let execution_result = run_execute_rollup(
+            message.index_uid.clone(),
+            index_config,
+            self.metastore.clone(),
+            &self.searcher_context,
+            &self.cluster_client,
+            self.ingest_router.clone(),
+            ctx,
+        )
+        .await;
+        match execution_result {
+            Ok(num_rollup_docs) => self.counters.num_rollup_docs += num_rollup_docs,
+            Err(error) => {
+                error!(index_id=%message.index_uid.index_id, error=?error, "failed to execute the rollup job on the index");
+            }
+        }
+        let rollup_config = index_config
+            .rollup_opt
+            .as_ref()
+            .expect("index should have a rollup configured");
+
+        if let Ok(next_interval) = rollup_config.duration_until_next_execution() {
+            info!(index_id=%message.index_uid.index_id, scheduled_in=?next_interval, "rollup-schedule-operation");
+            ctx.schedule_self_msg(next_interval, message);
+        } else {
+            // The index is removed from the cache so that it gets scheduled again by the next
+            // refresh loop.
+            self.index_configs.remove(&message.index_uid.index_id);
+            error!(index_id=%message.index_uid.index_id, "couldn't extract the rollup next schedule interval");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_config::{RollupConfig, SearcherConfig};
+    use quickwit_metastore::IndexMetadata;
+    use quickwit_proto::ingest::router::MockIngestRouterService;
+    use quickwit_proto::metastore::{ListIndexesMetadataResponse, MockMetastoreService};
+    use quickwit_search::{searcher_pool_for_test, SearchJobPlacer};
+
+    use super::*;
+
+    fn make_index_metadata(index_id: &str, with_rollup: bool) -> IndexMetadata {
+        let mut index_config =
+            IndexConfig::for_test(index_id, &format!("ram:///indexes/{index_id}"));
+        if with_rollup {
+            index_config.rollup_opt = Some(RollupConfig {
+                target_index_id: format!("{index_id}-rollup"),
+                interval: "1m".to_string(),
+                group_by: Vec::new(),
+                max_terms: 1_000,
+                metrics: Vec::new(),
+                delay: "10m".to_string(),
+                schedule: "hourly".to_string(),
+            });
+        }
+        IndexMetadata::new(index_config)
+    }
+
+    #[tokio::test]
+    async fn test_rollup_executor_refresh_loop() {
+        let universe = quickwit_actors::Universe::with_accelerated_time();
+        let mut mock_metastore = MockMetastoreService::new();
+        mock_metastore
+            .expect_list_indexes_metadata()
+            .returning(|_| {
+                let indexes_metadata = vec![
+                    make_index_metadata("index-with-rollup", true),
+                    make_index_metadata("index-without-rollup", false),
+                ];
+                Ok(ListIndexesMetadataResponse::for_test(indexes_metadata))
+            });
+        let searcher_pool = searcher_pool_for_test([]);
+        let rollup_executor = RollupExecutor::new(
+            MetastoreServiceClient::from_mock(mock_metastore),
+            Arc::new(SearcherContext::new(SearcherConfig::default(), None)),
+            ClusterClient::new(SearchJobPlacer::new(searcher_pool)),
+            IngestRouterServiceClient::from_mock(MockIngestRouterService::new()),
+        );
+        let (_mailbox, handle) = universe.spawn_builder().spawn(rollup_executor);
+
+        let counters = handle.process_pending_and_observe().await.state;
+        assert_eq!(counters.num_refresh_passes, 1);
+        assert_eq!(counters.num_execution_passes, 0);
+        universe.assert_quit().await;
+    }
+}
@@ -19,13 +19,17 @@
 
 #![deny(clippy::disallowed_methods)]
 
+use std::sync::Arc;
+
 use quickwit_actors::{Mailbox, Universe};
+use quickwit_common::io;
 use quickwit_common::pubsub::EventBroker;
 use quickwit_config::NodeConfig;
 use quickwit_indexing::actors::MergeSchedulerService;
 use quickwit_metastore::SplitInfo;
+use quickwit_proto::ingest::router::IngestRouterServiceClient;
 use quickwit_proto::metastore::MetastoreServiceClient;
-use quickwit_search::SearchJobPlacer;
+use quickwit_search::{ClusterClient, SearchJobPlacer, SearcherContext};
 use quickwit_storage::StorageResolver;
 use tracing::info;
 
@@ -34,10 +38,14 @@ pub mod error;
 mod janitor_service;
 mod metrics;
 mod retention_policy_execution;
+mod rollup_execution;
 
 pub use janitor_service::JanitorService;
 
-use crate::actors::{DeleteTaskService, GarbageCollector, RetentionPolicyExecutor};
+use crate::actors::{
+    DeleteTaskService, GarbageCollector, ReindexTaskService, RetentionPolicyExecutor,
+    RollupExecutor,
+};
 
 #[derive(utoipa::OpenApi)]
 #[openapi(components(schemas(SplitInfo)))]
@@ -50,6 +58,7 @@ pub async fn start_janitor_service(
     metastore: MetastoreServiceClient,
     search_job_placer: SearchJobPlacer,
     storage_resolver: StorageResolver,
+    ingest_router_opt: Option<IngestRouterServiceClient>,
     event_broker: EventBroker,
     run_delete_task_service: bool,
 ) -> anyhow::Result<Mailbox<JanitorService>> {
@@ -60,6 +69,38 @@ pub async fn start_janitor_service(
     let retention_policy_executor = RetentionPolicyExecutor::new(metastore.clone());
     let (_, retention_policy_executor_handle) =
         universe.spawn_builder().spawn(retention_policy_executor);
+
+    // Rollup jobs write their documents through the ingest router: they cannot run without one.
+    let rollup_executor_handle_opt = if let Some(ingest_router) = ingest_router_opt {
+        // Rollup searches are planned on this node like any root search, but do not go through
+        // the searcher service: they get a searcher context of their own.
+        let rollup_searcher_context = SearcherContext::new(config.searcher_config.clone(), None);
+        let rollup_executor = RollupExecutor::new(
+            metastore.clone(),
+            Arc::new(rollup_searcher_context),
+            ClusterClient::new(search_job_placer.clone()),
+            ingest_router,
+        );
+        let (_, rollup_executor_handle) = universe.spawn_builder().spawn(rollup_executor);
+        Some(rollup_executor_handle)
+    } else {
+        None
+    };
+    let reindex_task_service = ReindexTaskService::new(
+        metastore.clone(),
+        storage_resolver.clone(),
+        config.data_dir_path.clone(),
+        config.indexer_config.max_concurrent_split_uploads,
+        config
+            .indexer_config
+            .max_merge_write_throughput
+            .map(io::limiter),
+        universe.get_or_spawn_one::<MergeSchedulerService>(),
+        event_broker.clone(),
+    )
+    .await?;
+    let (_, reindex_task_service_handle) = universe.spawn_builder().spawn(reindex_task_service);
+
     let delete_task_service_handle = if run_delete_task_service {
         let delete_task_service = DeleteTaskService::new(
             metastore,
@@ -82,6 +123,8 @@ pub async fn start_janitor_service(
         delete_task_service_handle,
         garbage_collector_handle,
         retention_policy_executor_handle,
+        rollup_executor_handle_opt,
+        reindex_task_service_handle,
     );
     let (janitor_service_mailbox, _janitor_service_handle) =
         universe.spawn_builder().spawn(janitor_service);
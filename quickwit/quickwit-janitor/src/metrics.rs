@@ -24,6 +24,7 @@ use quickwit_common::metrics::{
 
 pub struct JanitorMetrics {
     pub ongoing_num_delete_operations_total: IntGaugeVec<1>,
+    pub ongoing_num_reindex_operations_total: IntGaugeVec<1>,
     pub gc_deleted_splits: IntCounterVec<1>,
     pub gc_deleted_bytes: IntCounter,
     pub gc_runs: IntCounterVec<1>,
@@ -42,6 +43,13 @@ impl Default for JanitorMetrics {
                 &[],
                 ["index"],
             ),
+            ongoing_num_reindex_operations_total: new_gauge_vec(
+                "ongoing_num_reindex_operations_total",
+                "Num of ongoing reindex operations (per index).",
+                "quickwit_janitor",
+                &[],
+                ["index"],
+            ),
             gc_deleted_splits: new_counter_vec(
                 "gc_deleted_splits_total",
                 "Total number of splits deleted by the garbage collector.",
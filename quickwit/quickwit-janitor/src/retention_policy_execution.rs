@@ -19,19 +19,21 @@
 
 use quickwit_actors::ActorContext;
 use quickwit_common::pretty::PrettySample;
-use quickwit_config::RetentionPolicy;
+use quickwit_config::{RetentionPolicy, RollupConfig};
 use quickwit_metastore::{
-    ListSplitsQuery, ListSplitsRequestExt, MetastoreServiceStreamSplitsExt, SplitMetadata,
-    SplitState,
+    IndexMetadataResponseExt, ListSplitsQuery, ListSplitsRequestExt,
+    MetastoreServiceStreamSplitsExt, SplitMetadata, SplitState,
 };
 use quickwit_proto::metastore::{
-    ListSplitsRequest, MarkSplitsForDeletionRequest, MetastoreService, MetastoreServiceClient,
+    IndexMetadataRequest, ListSplitsRequest, MarkSplitsForDeletionRequest, MetastoreService,
+    MetastoreServiceClient,
 };
 use quickwit_proto::types::{IndexUid, SplitId};
 use time::OffsetDateTime;
 use tracing::{info, warn};
 
 use crate::actors::RetentionPolicyExecutor;
+use crate::rollup_execution::rollup_checkpoint;
 
 /// Detect all expired splits based a retention policy and
 /// only mark them as `MarkedForDeletion`. Actual split deletion
@@ -40,17 +42,37 @@ use crate::actors::RetentionPolicyExecutor;
 /// * `index_id` - The target index id.
 /// * `metastore` - The metastore managing the target index.
 /// * `retention_policy` - The retention policy to used to evaluate the splits.
+/// * `rollup_config_opt` - The rollup of the index, if any. Splits are not marked for deletion
+///   before they have been rolled up.
 /// * `ctx_opt` - A context for reporting progress (only useful within quickwit actor).
 pub async fn run_execute_retention_policy(
     index_uid: IndexUid,
     metastore: MetastoreServiceClient,
     retention_policy: &RetentionPolicy,
+    rollup_config_opt: Option<&RollupConfig>,
     ctx: &ActorContext<RetentionPolicyExecutor>,
 ) -> anyhow::Result<Vec<SplitMetadata>> {
     // Select splits that are published and older than the retention period.
     let retention_period = retention_policy.retention_period()?;
     let current_timestamp = OffsetDateTime::now_utc().unix_timestamp();
-    let max_retention_timestamp = current_timestamp - retention_period.as_secs() as i64;
+    let mut max_retention_timestamp = current_timestamp - retention_period.as_secs() as i64;
+
+    if let Some(rollup_config) = rollup_config_opt {
+        let index_metadata_request = IndexMetadataRequest::for_index_uid(index_uid.clone());
+        let index_metadata = ctx
+            .protect_future(metastore.index_metadata(index_metadata_request))
+            .await?
+            .deserialize_index_metadata()?;
+        let Some(rollup_checkpoint) = rollup_checkpoint(&index_metadata, rollup_config) else {
+            info!(
+                index_id=%index_uid.index_id,
+                "Retention policy postponed until the index has been rolled up."
+            );
+            return Ok(Vec::new());
+        };
+        // The rollup checkpoint is exclusive.
+        max_retention_timestamp = max_retention_timestamp.min(rollup_checkpoint - 1);
+    }
     let query = ListSplitsQuery::for_index(index_uid.clone())
         .with_split_state(SplitState::Published)
         .with_time_range_end_lte(max_retention_timestamp);
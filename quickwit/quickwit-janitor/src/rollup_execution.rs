@@ -0,0 +1,1212 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeSet, HashMap};
+use std::hash::Hasher;
+use std::ops::{Range, RangeInclusive};
+
+use anyhow::Context;
+use quickwit_actors::ActorContext;
+use quickwit_config::{
+    IndexConfig, RollupConfig, RollupMetric, SourceConfig, INGEST_V2_SOURCE_ID, ROLLUP_SOURCE_ID,
+};
+use quickwit_ingest::{CommitType, JsonDocBatchV2Builder};
+use quickwit_metastore::checkpoint::{IndexCheckpointDelta, PartitionId, SourceCheckpointDelta};
+use quickwit_metastore::{
+    AddSourceRequestExt, IndexMetadata, IndexMetadataResponseExt, ListSplitsQuery,
+    ListSplitsRequestExt, MetastoreServiceStreamSplitsExt, SplitState,
+};
+use quickwit_proto::ingest::router::{
+    IngestRequestV2, IngestRouterService, IngestRouterServiceClient, IngestSubrequest,
+};
+use quickwit_proto::metastore::{
+    AddSourceRequest, DeleteQuery, IndexMetadataRequest, ListSplitsRequest, MetastoreService,
+    MetastoreServiceClient, PublishSplitsRequest,
+};
+use quickwit_proto::search::SearchRequest;
+use quickwit_proto::types::{DocUidGenerator, IndexUid, Position};
+use quickwit_query::query_ast::{BoolQuery, QueryAst, TermQuery, TermSetQuery};
+use quickwit_search::{root_search, ClusterClient, SearcherContext};
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+use siphasher::sip128::{Hasher128, SipHasher};
+use time::OffsetDateTime;
+use tracing::info;
+
+use crate::actors::RollupExecutor;
+
+/// Name of the date histogram aggregation of the rollup search requests.
+const ROLLUP_AGGREGATION_NAME: &str = "rollup";
+
+/// Maximum number of date histogram buckets computed by a single rollup search request.
+const MAX_NUM_INTERVALS_PER_SEARCH: i64 = 60;
+
+/// Maximum number of rollup documents sent in a single ingest request.
+const MAX_NUM_DOCS_PER_INGEST_REQUEST: usize = 1_000;
+
+/// Name of the rollup document field holding the ID of the document, derived from its time bucket
+/// and group.
+const ROLLUP_ID_FIELD: &str = "rollup_id";
+
+/// Name of the rollup document field holding the number of documents rolled up.
+const DOC_COUNT_FIELD: &str = "doc_count";
+
+/// Value of the group by fields of the rollup documents counting the documents of the groups
+/// beyond `max_terms`.
+const OTHER_GROUP_KEY: &str = "__other__";
+
+/// Suffix of the partition of the rollup source checkpoint recording the update timestamp from
+/// which published splits are checked for late documents.
+const LATE_SPLITS_PARTITION_SUFFIX: &str = ":late-splits";
+
+/// Margin subtracted from the late splits watermark to account for the clock skew between the
+/// janitor and the metastore. Checking a split twice is harmless.
+const LATE_SPLITS_WATERMARK_MARGIN_SECS: i64 = 60;
+
+/// Rolls up the time buckets of an index closed since the last execution, and writes the
+/// resulting documents into the rollup target index.
+///
+/// The progress of the rollup job is recorded in the metastore, in the checkpoint of the reserved
+/// rollup source of the rolled up index. Each time window is checkpointed once its rollup
+/// documents have been ingested and published. Ingesting the documents and recording the
+/// checkpoint are not atomic: if the job fails in between, the time window is rolled up again on
+/// the next execution. Rollup documents have deterministic IDs, so the documents already present
+/// in the target index are not ingested twice.
+///
+/// The time windows already rolled up which received late documents, i.e. which overlap the time
+/// range of splits published since the previous execution, are rolled up again. The rollup
+/// documents whose doc count changed are ingested again, and their outdated versions are deleted.
+///
+/// Returns the number of rollup documents ingested.
+pub async fn run_execute_rollup(
+    index_uid: IndexUid,
+    index_config: &IndexConfig,
+    metastore: MetastoreServiceClient,
+    searcher_context: &SearcherContext,
+    cluster_client: &ClusterClient,
+    ingest_router: IngestRouterServiceClient,
+    ctx: &ActorContext<RollupExecutor>,
+) -> anyhow::Result<usize> {
+    let rollup_config = index_config
+        .rollup_opt
+        .as_ref()
+        .context("index does not have a rollup configured")?;
+    let timestamp_field = index_config
+        .doc_mapping
+        .timestamp_field
+        .as_deref()
+        .context("rollup requires a timestamp field")?;
+    let interval_secs = rollup_config.interval()?.as_secs() as i64;
+    let delay_secs = rollup_config.delay()?.as_secs() as i64;
+    let current_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+    let closed_until = (current_timestamp - delay_secs).div_euclid(interval_secs) * interval_secs;
+
+    let index_metadata_request = IndexMetadataRequest::for_index_uid(index_uid.clone());
+    let index_metadata = ctx
+        .protect_future(metastore.index_metadata(index_metadata_request))
+        .await?
+        .deserialize_index_metadata()?;
+
+    if !index_metadata.sources.contains_key(ROLLUP_SOURCE_ID) {
+        let add_source_request =
+            AddSourceRequest::try_from_source_config(index_uid.clone(), &SourceConfig::rollup())?;
+        ctx.protect_future(metastore.add_source(add_source_request))
+            .await?;
+    }
+    let mut checkpoint_opt = rollup_checkpoint(&index_metadata, rollup_config);
+    let late_splits_watermark_opt = late_splits_watermark(&index_metadata, rollup_config);
+    let mut num_rollup_docs = 0;
+
+    if let (Some(checkpoint), Some(late_splits_watermark)) =
+        (checkpoint_opt, late_splits_watermark_opt)
+    {
+        let late_split_time_ranges =
+            late_split_time_ranges(&index_uid, late_splits_watermark, &metastore, ctx).await?;
+
+        for time_window in late_time_windows(late_split_time_ranges, checkpoint, interval_secs) {
+            let num_window_docs = rollup_time_window(
+                &index_uid,
+                timestamp_field,
+                rollup_config,
+                time_window.clone(),
+                searcher_context,
+                &metastore,
+                cluster_client,
+                &ingest_router,
+                ctx,
+            )
+            .await?;
+            info!(
+                index_id=%index_uid.index_id,
+                target_index_id=%rollup_config.target_index_id,
+                window_start=time_window.start,
+                window_end=time_window.end,
+                num_rollup_docs=num_window_docs,
+                "rolled up time window with late documents"
+            );
+            num_rollup_docs += num_window_docs;
+        }
+    }
+    let mut window_start = match checkpoint_opt {
+        Some(checkpoint) => checkpoint,
+        None => {
+            let Some(start_timestamp) =
+                published_splits_start_timestamp(&index_uid, &metastore, ctx).await?
+            else {
+                return Ok(0);
+            };
+            start_timestamp.div_euclid(interval_secs) * interval_secs
+        }
+    };
+    while window_start < closed_until {
+        let window_end =
+            (window_start + interval_secs * MAX_NUM_INTERVALS_PER_SEARCH).min(closed_until);
+        let num_window_docs = rollup_time_window(
+            &index_uid,
+            timestamp_field,
+            rollup_config,
+            window_start..window_end,
+            searcher_context,
+            &metastore,
+            cluster_client,
+            &ingest_router,
+            ctx,
+        )
+        .await?;
+        publish_rollup_source_position(
+            &index_uid,
+            rollup_checkpoint_partition_id(rollup_config),
+            checkpoint_opt,
+            window_end,
+            &metastore,
+            ctx,
+        )
+        .await?;
+        info!(
+            index_id=%index_uid.index_id,
+            target_index_id=%rollup_config.target_index_id,
+            window_start,
+            window_end,
+            num_rollup_docs=num_window_docs,
+            "rolled up time window"
+        );
+        num_rollup_docs += num_window_docs;
+        checkpoint_opt = Some(window_end);
+        window_start = window_end;
+    }
+    // The splits published from now on are checked for late documents on the next execution.
+    let new_late_splits_watermark = current_timestamp - LATE_SPLITS_WATERMARK_MARGIN_SECS;
+
+    if late_splits_watermark_opt.map_or(true, |watermark| watermark < new_late_splits_watermark) {
+        publish_rollup_source_position(
+            &index_uid,
+            late_splits_partition_id(rollup_config),
+            late_splits_watermark_opt,
+            new_late_splits_watermark,
+            &metastore,
+            ctx,
+        )
+        .await?;
+    }
+    Ok(num_rollup_docs)
+}
+
+/// Rolls up the documents of a time window, and ingests the rollup documents missing from the
+/// target index or whose doc count changed since they were ingested. Returns the number of rollup
+/// documents ingested.
+#[allow(clippy::too_many_arguments)]
+async fn rollup_time_window(
+    index_uid: &IndexUid,
+    timestamp_field: &str,
+    rollup_config: &RollupConfig,
+    time_window: Range<i64>,
+    searcher_context: &SearcherContext,
+    metastore: &MetastoreServiceClient,
+    cluster_client: &ClusterClient,
+    ingest_router: &IngestRouterServiceClient,
+    ctx: &ActorContext<RollupExecutor>,
+) -> anyhow::Result<usize> {
+    let search_request = build_rollup_search_request(
+        &index_uid.index_id,
+        timestamp_field,
+        rollup_config,
+        time_window,
+    )?;
+    let search_response = ctx
+        .protect_future(root_search(
+            searcher_context,
+            search_request,
+            metastore.clone(),
+            cluster_client,
+        ))
+        .await?;
+    let rollup_docs = if let Some(aggregation_json) = search_response.aggregation {
+        let aggregation: JsonValue = serde_json::from_str(&aggregation_json)
+            .context("failed to parse rollup aggregation")?;
+        rollup_docs_from_aggregation(&aggregation, timestamp_field, rollup_config)?
+    } else {
+        Vec::new()
+    };
+    let ingested_doc_counts = fetch_ingested_rollup_doc_counts(
+        &rollup_config.target_index_id,
+        &rollup_docs,
+        searcher_context,
+        metastore,
+        cluster_client,
+        ctx,
+    )
+    .await?;
+    let (rollup_docs, stale_rollup_docs) = reconcile_rollup_docs(rollup_docs, &ingested_doc_counts);
+    let num_rollup_docs = rollup_docs.len();
+
+    ingest_rollup_docs(
+        &rollup_config.target_index_id,
+        rollup_docs,
+        ingest_router,
+        ctx,
+    )
+    .await?;
+    // Delete tasks only apply to the splits published before their creation: the outdated
+    // versions are deleted once their replacements are published, which the delete queries do
+    // not match since their doc counts differ.
+    delete_stale_rollup_docs(
+        &rollup_config.target_index_id,
+        stale_rollup_docs,
+        metastore,
+        ctx,
+    )
+    .await?;
+    Ok(num_rollup_docs)
+}
+
+fn rollup_checkpoint_partition_id(rollup_config: &RollupConfig) -> PartitionId {
+    PartitionId::from(rollup_config.target_index_id.as_str())
+}
+
+fn late_splits_partition_id(rollup_config: &RollupConfig) -> PartitionId {
+    PartitionId::from(format!(
+        "{}{LATE_SPLITS_PARTITION_SUFFIX}",
+        rollup_config.target_index_id
+    ))
+}
+
+fn rollup_source_position(
+    index_metadata: &IndexMetadata,
+    partition_id: &PartitionId,
+) -> Option<i64> {
+    index_metadata
+        .checkpoint
+        .source_checkpoint(ROLLUP_SOURCE_ID)?
+        .position_for_partition(partition_id)?
+        .as_u64()
+        .map(|timestamp| timestamp as i64)
+}
+
+/// Returns the timestamp up to which (exclusive) the index has been rolled up, if the rollup job
+/// ran at least once.
+pub(crate) fn rollup_checkpoint(
+    index_metadata: &IndexMetadata,
+    rollup_config: &RollupConfig,
+) -> Option<i64> {
+    rollup_source_position(
+        index_metadata,
+        &rollup_checkpoint_partition_id(rollup_config),
+    )
+}
+
+/// Returns the update timestamp from which published splits are checked for late documents.
+fn late_splits_watermark(
+    index_metadata: &IndexMetadata,
+    rollup_config: &RollupConfig,
+) -> Option<i64> {
+    rollup_source_position(index_metadata, &late_splits_partition_id(rollup_config))
+}
+
+/// Returns the time ranges of the splits published or updated since the late splits watermark.
+/// Merged splits are returned too: they may hold late documents published after the watermark.
+async fn late_split_time_ranges(
+    index_uid: &IndexUid,
+    late_splits_watermark: i64,
+    metastore: &MetastoreServiceClient,
+    ctx: &ActorContext<RollupExecutor>,
+) -> anyhow::Result<Vec<RangeInclusive<i64>>> {
+    let query = ListSplitsQuery::for_index(index_uid.clone())
+        .with_split_state(SplitState::Published)
+        .with_update_timestamp_gte(late_splits_watermark);
+    let list_splits_request = ListSplitsRequest::try_from_list_splits_query(&query)?;
+    let time_ranges = ctx
+        .protect_future(metastore.list_splits(list_splits_request))
+        .await?
+        .collect_splits_metadata()
+        .await?
+        .into_iter()
+        .filter_map(|split_metadata| split_metadata.time_range)
+        .collect();
+    Ok(time_ranges)
+}
+
+/// Returns the time windows, rolled up already, overlapping the time ranges of the given splits,
+/// aligned on the rollup interval and small enough to be rolled up with a single search request.
+fn late_time_windows(
+    split_time_ranges: Vec<RangeInclusive<i64>>,
+    checkpoint: i64,
+    interval_secs: i64,
+) -> Vec<Range<i64>> {
+    let mut split_windows: Vec<Range<i64>> = split_time_ranges
+        .into_iter()
+        .filter(|time_range| *time_range.start() < checkpoint)
+        .map(|time_range| {
+            let window_start = time_range.start().div_euclid(interval_secs) * interval_secs;
+            let window_end =
+                ((time_range.end().div_euclid(interval_secs) + 1) * interval_secs).min(checkpoint);
+            window_start..window_end
+        })
+        .collect();
+    split_windows.sort_by_key(|split_window| split_window.start);
+
+    let mut merged_windows: Vec<Range<i64>> = Vec::new();
+
+    for split_window in split_windows {
+        match merged_windows.last_mut() {
+            Some(merged_window) if split_window.start <= merged_window.end => {
+                merged_window.end = merged_window.end.max(split_window.end);
+            }
+            _ => merged_windows.push(split_window),
+        }
+    }
+    let max_window_len = interval_secs * MAX_NUM_INTERVALS_PER_SEARCH;
+
+    merged_windows
+        .into_iter()
+        .flat_map(|merged_window| {
+            (merged_window.start..merged_window.end)
+                .step_by(max_window_len as usize)
+                .map(move |window_start| {
+                    window_start..(window_start + max_window_len).min(merged_window.end)
+                })
+        })
+        .collect()
+}
+
+async fn published_splits_start_timestamp(
+    index_uid: &IndexUid,
+    metastore: &MetastoreServiceClient,
+    ctx: &ActorContext<RollupExecutor>,
+) -> anyhow::Result<Option<i64>> {
+    let query =
+        ListSplitsQuery::for_index(index_uid.clone()).with_split_state(SplitState::Published);
+    let list_splits_request = ListSplitsRequest::try_from_list_splits_query(&query)?;
+    let start_timestamp_opt = ctx
+        .protect_future(metastore.list_splits(list_splits_request))
+        .await?
+        .collect_splits_metadata()
+        .await?
+        .into_iter()
+        .filter_map(|split_metadata| split_metadata.time_range)
+        .map(|time_range| *time_range.start())
+        .min();
+    Ok(start_timestamp_opt)
+}
+
+async fn publish_rollup_source_position(
+    index_uid: &IndexUid,
+    partition_id: PartitionId,
+    position_opt: Option<i64>,
+    new_position: i64,
+    metastore: &MetastoreServiceClient,
+    ctx: &ActorContext<RollupExecutor>,
+) -> anyhow::Result<()> {
+    let from_position = position_opt
+        .map(|position| Position::offset(position as u64))
+        .unwrap_or_default();
+    let to_position = Position::offset(new_position as u64);
+    let source_delta =
+        SourceCheckpointDelta::from_partition_delta(partition_id, from_position, to_position)?;
+    let index_checkpoint_delta = IndexCheckpointDelta {
+        source_id: ROLLUP_SOURCE_ID.to_string(),
+        source_delta,
+    };
+    let index_checkpoint_delta_json = serde_json::to_string(&index_checkpoint_delta)
+        .context("failed to serialize `IndexCheckpointDelta`")?;
+    let publish_splits_request = PublishSplitsRequest {
+        index_uid: Some(index_uid.clone()),
+        staged_split_ids: Vec::new(),
+        replaced_split_ids: Vec::new(),
+        index_checkpoint_delta_json_opt: Some(index_checkpoint_delta_json),
+        publish_token_opt: None,
+    };
+    ctx.protect_future(metastore.publish_splits(publish_splits_request))
+        .await
+        .context("failed to publish rollup checkpoint")?;
+    Ok(())
+}
+
+/// Returns the doc counts of the rollup documents present in the target index, per rollup ID. The
+/// rollup documents of a time bucket rolled up again are present in several versions until the
+/// outdated ones are deleted.
+async fn fetch_ingested_rollup_doc_counts(
+    target_index_id: &str,
+    rollup_docs: &[JsonMap<String, JsonValue>],
+    searcher_context: &SearcherContext,
+    metastore: &MetastoreServiceClient,
+    cluster_client: &ClusterClient,
+    ctx: &ActorContext<RollupExecutor>,
+) -> anyhow::Result<HashMap<String, BTreeSet<u64>>> {
+    let mut ingested_doc_counts: HashMap<String, BTreeSet<u64>> = HashMap::new();
+
+    for rollup_docs_chunk in rollup_docs.chunks(MAX_NUM_DOCS_PER_INGEST_REQUEST) {
+        let rollup_ids: BTreeSet<String> = rollup_docs_chunk
+            .iter()
+            .filter_map(|rollup_doc| rollup_doc.get(ROLLUP_ID_FIELD)?.as_str())
+            .map(ToString::to_string)
+            .collect();
+        let term_set_query = TermSetQuery {
+            terms_per_field: HashMap::from_iter([(ROLLUP_ID_FIELD.to_string(), rollup_ids)]),
+        };
+        let query_ast_json = serde_json::to_string(&QueryAst::from(term_set_query))?;
+        let mut start_offset = 0;
+
+        loop {
+            let search_request = SearchRequest {
+                index_id_patterns: vec![target_index_id.to_string()],
+                query_ast: query_ast_json.clone(),
+                max_hits: MAX_NUM_DOCS_PER_INGEST_REQUEST as u64,
+                start_offset,
+                ..Default::default()
+            };
+            let search_response = ctx
+                .protect_future(root_search(
+                    searcher_context,
+                    search_request,
+                    metastore.clone(),
+                    cluster_client,
+                ))
+                .await
+                .with_context(|| {
+                    format!("failed to search rollup documents in index `{target_index_id}`")
+                })?;
+            let num_hits = search_response.hits.len() as u64;
+
+            for hit in search_response.hits {
+                let doc: JsonValue =
+                    serde_json::from_str(&hit.json).context("failed to parse rollup document")?;
+
+                if let (Some(rollup_id), Some(doc_count)) =
+                    (doc[ROLLUP_ID_FIELD].as_str(), doc[DOC_COUNT_FIELD].as_u64())
+                {
+                    ingested_doc_counts
+                        .entry(rollup_id.to_string())
+                        .or_default()
+                        .insert(doc_count);
+                }
+            }
+            start_offset += num_hits;
+
+            if num_hits == 0 || start_offset >= search_response.num_hits {
+                break;
+            }
+        }
+    }
+    Ok(ingested_doc_counts)
+}
+
+/// Splits the rollup documents of a time window into the documents to ingest, missing from the
+/// target index or whose doc count changed, and the outdated versions present in the target index,
+/// as `(rollup ID, doc count)` pairs.
+fn reconcile_rollup_docs(
+    rollup_docs: Vec<JsonMap<String, JsonValue>>,
+    ingested_doc_counts: &HashMap<String, BTreeSet<u64>>,
+) -> (Vec<JsonMap<String, JsonValue>>, Vec<(String, u64)>) {
+    let mut rollup_docs_to_ingest = Vec::new();
+    let mut stale_rollup_docs = Vec::new();
+
+    for rollup_doc in rollup_docs {
+        let rollup_id = rollup_doc
+            .get(ROLLUP_ID_FIELD)
+            .and_then(JsonValue::as_str)
+            .unwrap_or_default()
+            .to_string();
+        let doc_count_opt = rollup_doc.get(DOC_COUNT_FIELD).and_then(JsonValue::as_u64);
+
+        let Some(ingested_counts) = ingested_doc_counts.get(&rollup_id) else {
+            rollup_docs_to_ingest.push(rollup_doc);
+            continue;
+        };
+        let is_ingested =
+            doc_count_opt.is_some_and(|doc_count| ingested_counts.contains(&doc_count));
+
+        for ingested_count in ingested_counts {
+            if Some(*ingested_count) != doc_count_opt {
+                stale_rollup_docs.push((rollup_id.clone(), *ingested_count));
+            }
+        }
+        if !is_ingested {
+            rollup_docs_to_ingest.push(rollup_doc);
+        }
+    }
+    (rollup_docs_to_ingest, stale_rollup_docs)
+}
+
+/// Creates delete tasks removing the outdated versions of rollup documents from the target index.
+async fn delete_stale_rollup_docs(
+    target_index_id: &str,
+    stale_rollup_docs: Vec<(String, u64)>,
+    metastore: &MetastoreServiceClient,
+    ctx: &ActorContext<RollupExecutor>,
+) -> anyhow::Result<()> {
+    if stale_rollup_docs.is_empty() {
+        return Ok(());
+    }
+    let index_metadata_request = IndexMetadataRequest::for_index_id(target_index_id.to_string());
+    let target_index_uid = ctx
+        .protect_future(metastore.index_metadata(index_metadata_request))
+        .await?
+        .deserialize_index_metadata()?
+        .index_uid;
+
+    for stale_rollup_docs_chunk in stale_rollup_docs.chunks(MAX_NUM_DOCS_PER_INGEST_REQUEST) {
+        let query_ast = stale_rollup_docs_query_ast(stale_rollup_docs_chunk);
+        let delete_query = DeleteQuery {
+            index_uid: Some(target_index_uid.clone()),
+            start_timestamp: None,
+            end_timestamp: None,
+            query_ast: serde_json::to_string(&query_ast)?,
+        };
+        ctx.protect_future(metastore.create_delete_task(delete_query))
+            .await
+            .with_context(|| {
+                format!("failed to delete outdated rollup documents in index `{target_index_id}`")
+            })?;
+    }
+    info!(
+        target_index_id=%target_index_id,
+        num_stale_rollup_docs=stale_rollup_docs.len(),
+        "deleting outdated rollup documents"
+    );
+    Ok(())
+}
+
+/// Builds the query matching the given versions of rollup documents.
+fn stale_rollup_docs_query_ast(stale_rollup_docs: &[(String, u64)]) -> QueryAst {
+    let should = stale_rollup_docs
+        .iter()
+        .map(|(rollup_id, doc_count)| {
+            let must = vec![
+                TermQuery {
+                    field: ROLLUP_ID_FIELD.to_string(),
+                    value: rollup_id.clone(),
+                }
+                .into(),
+                TermQuery {
+                    field: DOC_COUNT_FIELD.to_string(),
+                    value: doc_count.to_string(),
+                }
+                .into(),
+            ];
+            BoolQuery {
+                must,
+                ..Default::default()
+            }
+            .into()
+        })
+        .collect();
+    BoolQuery {
+        should,
+        ..Default::default()
+    }
+    .into()
+}
+
+/// Ingests the rollup documents into the target index, and waits for them to be published, so that
+/// a later execution finds them.
+async fn ingest_rollup_docs(
+    target_index_id: &str,
+    rollup_docs: Vec<JsonMap<String, JsonValue>>,
+    ingest_router: &IngestRouterServiceClient,
+    ctx: &ActorContext<RollupExecutor>,
+) -> anyhow::Result<()> {
+    let mut doc_uid_generator = DocUidGenerator::default();
+
+    for rollup_docs_chunk in rollup_docs.chunks(MAX_NUM_DOCS_PER_INGEST_REQUEST) {
+        let mut doc_batch_builder = JsonDocBatchV2Builder::default();
+
+        for rollup_doc in rollup_docs_chunk {
+            doc_batch_builder.add_doc(doc_uid_generator.next_doc_uid(), rollup_doc)?;
+        }
+        let subrequest = IngestSubrequest {
+            subrequest_id: 0,
+            index_id: target_index_id.to_string(),
+            source_id: INGEST_V2_SOURCE_ID.to_string(),
+            doc_batch: Some(doc_batch_builder.build()),
+        };
+        let request = IngestRequestV2 {
+            commit_type: CommitType::WaitFor.into(),
+            subrequests: vec![subrequest],
+        };
+        let response = ctx.protect_future(ingest_router.ingest(request)).await?;
+
+        if let Some(failure) = response.failures.first() {
+            anyhow::bail!(
+                "failed to ingest rollup documents into index `{target_index_id}`: {:?}",
+                failure.reason()
+            );
+        }
+    }
+    Ok(())
+}
+
+fn group_by_aggregation_name(depth: usize) -> String {
+    format!("group_by_{depth}")
+}
+
+fn stats_aggregation_name(metric: &RollupMetric) -> String {
+    format!("{}_stats", metric.field)
+}
+
+fn percentiles_aggregation_name(metric: &RollupMetric) -> String {
+    format!("{}_percentiles", metric.field)
+}
+
+/// Returns the name of the rollup document field holding a given percentile: `latency_p99_9`.
+fn percentile_field_name(metric: &RollupMetric, percentile: f64) -> String {
+    format!(
+        "{}_p{}",
+        metric.field,
+        percentile.to_string().replace('.', "_")
+    )
+}
+
+/// Builds the date histogram + terms aggregation computing the rollup documents of a time window.
+fn build_rollup_aggregation(timestamp_field: &str, rollup_config: &RollupConfig) -> JsonValue {
+    let interval_secs = rollup_config
+        .interval()
+        .expect("rollup interval should have been validated")
+        .as_secs();
+    let mut sub_aggregations = JsonMap::new();
+
+    for metric in &rollup_config.metrics {
+        sub_aggregations.insert(
+            stats_aggregation_name(metric),
+            json!({ "stats": { "field": metric.field } }),
+        );
+        if !metric.percentiles.is_empty() {
+            sub_aggregations.insert(
+                percentiles_aggregation_name(metric),
+                json!({
+                    "percentiles": {
+                        "field": metric.field,
+                        "percents": metric.percentiles,
+                        "keyed": false,
+                    }
+                }),
+            );
+        }
+    }
+    for (depth, group_by_field) in rollup_config.group_by.iter().enumerate().rev() {
+        let mut terms_aggregation = json!({
+            "terms": {
+                "field": group_by_field,
+                "size": rollup_config.max_terms,
+            }
+        });
+        if !sub_aggregations.is_empty() {
+            terms_aggregation["aggs"] = JsonValue::Object(sub_aggregations);
+        }
+        sub_aggregations =
+            JsonMap::from_iter([(group_by_aggregation_name(depth), terms_aggregation)]);
+    }
+    let mut date_histogram_aggregation = json!({
+        "date_histogram": {
+            "field": timestamp_field,
+            "fixed_interval": format!("{interval_secs}s"),
+        }
+    });
+    if !sub_aggregations.is_empty() {
+        date_histogram_aggregation["aggs"] = JsonValue::Object(sub_aggregations);
+    }
+    json!({ ROLLUP_AGGREGATION_NAME: date_histogram_aggregation })
+}
+
+fn build_rollup_search_request(
+    index_id: &str,
+    timestamp_field: &str,
+    rollup_config: &RollupConfig,
+    time_window: Range<i64>,
+) -> anyhow::Result<SearchRequest> {
+    let aggregation = build_rollup_aggregation(timestamp_field, rollup_config);
+    let search_request = SearchRequest {
+        index_id_patterns: vec![index_id.to_string()],
+        query_ast: serde_json::to_string(&QueryAst::MatchAll)?,
+        start_timestamp: Some(time_window.start),
+        end_timestamp: Some(time_window.end),
+        max_hits: 0,
+        aggregation_request: Some(serde_json::to_string(&aggregation)?),
+        ..Default::default()
+    };
+    Ok(search_request)
+}
+
+/// Returns the ID of a rollup document, derived from its time bucket and group so that rolling up
+/// the same time bucket twice produces the same IDs.
+fn rollup_id(
+    rollup_doc: &JsonMap<String, JsonValue>,
+    timestamp_field: &str,
+    rollup_config: &RollupConfig,
+) -> String {
+    let mut hasher = SipHasher::new();
+
+    for field_name in
+        std::iter::once(timestamp_field).chain(rollup_config.group_by.iter().map(String::as_str))
+    {
+        let field_value = rollup_doc.get(field_name).unwrap_or(&JsonValue::Null);
+        hasher.write(field_value.to_string().as_bytes());
+        // Separates the field values.
+        hasher.write_u8(0);
+    }
+    format!("{:032x}", hasher.finish128().as_u128())
+}
+
+/// Flattens the buckets of the rollup aggregation into rollup documents: one document per time
+/// bucket and group.
+fn rollup_docs_from_aggregation(
+    aggregation: &JsonValue,
+    timestamp_field: &str,
+    rollup_config: &RollupConfig,
+) -> anyhow::Result<Vec<JsonMap<String, JsonValue>>> {
+    let date_histogram_buckets = aggregation[ROLLUP_AGGREGATION_NAME]["buckets"]
+        .as_array()
+        .context("rollup aggregation should have buckets")?;
+    let mut rollup_docs = Vec::new();
+
+    for date_histogram_bucket in date_histogram_buckets {
+        // Date histogram keys are expressed in milliseconds.
+        let key_millis = date_histogram_bucket["key"]
+            .as_f64()
+            .context("date histogram bucket should have a numeric key")?;
+        let mut rollup_doc = JsonMap::new();
+        rollup_doc.insert(
+            timestamp_field.to_string(),
+            JsonValue::from((key_millis / 1_000.0) as i64),
+        );
+        collect_rollup_docs(
+            date_histogram_bucket,
+            0,
+            rollup_config,
+            &mut rollup_doc,
+            &mut rollup_docs,
+        )?;
+    }
+    for rollup_doc in &mut rollup_docs {
+        let rollup_id = rollup_id(rollup_doc, timestamp_field, rollup_config);
+        rollup_doc.insert(ROLLUP_ID_FIELD.to_string(), JsonValue::String(rollup_id));
+    }
+    Ok(rollup_docs)
+}
+
+fn collect_rollup_docs(
+    bucket: &JsonValue,
+    depth: usize,
+    rollup_config: &RollupConfig,
+    rollup_doc: &mut JsonMap<String, JsonValue>,
+    rollup_docs: &mut Vec<JsonMap<String, JsonValue>>,
+) -> anyhow::Result<()> {
+    if bucket["doc_count"].as_u64().unwrap_or(0) == 0 {
+        return Ok(());
+    }
+    let Some(group_by_field) = rollup_config.group_by.get(depth) else {
+        let mut rollup_doc = rollup_doc.clone();
+        rollup_doc.insert(DOC_COUNT_FIELD.to_string(), bucket["doc_count"].clone());
+
+        for metric in &rollup_config.metrics {
+            let stats = &bucket[stats_aggregation_name(metric)];
+
+            for stat_name in ["min", "max", "sum", "avg"] {
+                let stat_value = &stats[stat_name];
+
+                if !stat_value.is_null() {
+                    rollup_doc.insert(format!("{}_{stat_name}", metric.field), stat_value.clone());
+                }
+            }
+            let percentile_values = bucket[percentiles_aggregation_name(metric)]["values"]
+                .as_array()
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+
+            for (percentile, percentile_value) in metric.percentiles.iter().zip(percentile_values) {
+                let value = &percentile_value["value"];
+
+                if !value.is_null() {
+                    rollup_doc.insert(percentile_field_name(metric, *percentile), value.clone());
+                }
+            }
+        }
+        rollup_docs.push(rollup_doc);
+        return Ok(());
+    };
+    let terms_aggregation = &bucket[group_by_aggregation_name(depth)];
+    let terms_buckets = terms_aggregation["buckets"]
+        .as_array()
+        .context("terms aggregation should have buckets")?;
+
+    let sum_other_doc_count = terms_aggregation["sum_other_doc_count"]
+        .as_u64()
+        .unwrap_or(0);
+
+    if sum_other_doc_count > 0 {
+        // The documents of the groups beyond `max_terms` are counted in a rollup document of their
+        // own. The terms aggregation does not provide their metrics.
+        let mut other_rollup_doc = rollup_doc.clone();
+
+        for other_group_by_field in &rollup_config.group_by[depth..] {
+            other_rollup_doc.insert(
+                other_group_by_field.clone(),
+                JsonValue::from(OTHER_GROUP_KEY),
+            );
+        }
+        other_rollup_doc.insert(
+            DOC_COUNT_FIELD.to_string(),
+            JsonValue::from(sum_other_doc_count),
+        );
+        rollup_docs.push(other_rollup_doc);
+    }
+    for terms_bucket in terms_buckets {
+        rollup_doc.insert(group_by_field.clone(), terms_bucket["key"].clone());
+        collect_rollup_docs(
+            terms_bucket,
+            depth + 1,
+            rollup_config,
+            rollup_doc,
+            rollup_docs,
+        )?;
+    }
+    rollup_doc.remove(group_by_field);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rollup_config_for_test() -> RollupConfig {
+        RollupConfig {
+            target_index_id: "test-index-rollup".to_string(),
+            interval: "1m".to_string(),
+            group_by: vec!["service".to_string()],
+            max_terms: 10,
+            metrics: vec![RollupMetric {
+                field: "latency".to_string(),
+                percentiles: vec![50.0, 99.9],
+            }],
+            delay: "10m".to_string(),
+            schedule: "hourly".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_build_rollup_search_request() {
+        let rollup_config = rollup_config_for_test();
+        let search_request =
+            build_rollup_search_request("test-index", "timestamp", &rollup_config, 60..180)
+                .unwrap();
+        assert_eq!(search_request.index_id_patterns, ["test-index"]);
+        assert_eq!(search_request.start_timestamp, Some(60));
+        assert_eq!(search_request.end_timestamp, Some(180));
+        assert_eq!(search_request.max_hits, 0);
+
+        let aggregation: JsonValue =
+            serde_json::from_str(&search_request.aggregation_request.unwrap()).unwrap();
+        let expected_aggregation = json!({
+            "rollup": {
+                "date_histogram": {
+                    "field": "timestamp",
+                    "fixed_interval": "60s",
+                },
+                "aggs": {
+                    "group_by_0": {
+                        "terms": {
+                            "field": "service",
+                            "size": 10,
+                        },
+                        "aggs": {
+                            "latency_stats": {
+                                "stats": { "field": "latency" }
+                            },
+                            "latency_percentiles": {
+                                "percentiles": {
+                                    "field": "latency",
+                                    "percents": [50.0, 99.9],
+                                    "keyed": false,
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        });
+        assert_eq!(aggregation, expected_aggregation);
+    }
+
+    #[test]
+    fn test_rollup_docs_from_aggregation() {
+        let rollup_config = rollup_config_for_test();
+        let aggregation = json!({
+            "rollup": {
+                "buckets": [
+                    {
+                        "key": 60000.0,
+                        "doc_count": 3,
+                        "group_by_0": {
+                            "buckets": [
+                                {
+                                    "key": "api",
+                                    "doc_count": 2,
+                                    "latency_stats": {
+                                        "count": 2, "min": 1.0, "max": 3.0, "sum": 4.0, "avg": 2.0
+                                    },
+                                    "latency_percentiles": {
+                                        "values": [
+                                            { "key": 50.0, "value": 2.0 },
+                                            { "key": 99.9, "value": 3.0 }
+                                        ]
+                                    }
+                                },
+                                {
+                                    "key": "db",
+                                    "doc_count": 1,
+                                    "latency_stats": {
+                                        "count": 0, "min": null, "max": null, "sum": 0.0, "avg": null
+                                    },
+                                    "latency_percentiles": {
+                                        "values": [
+                                            { "key": 50.0, "value": null },
+                                            { "key": 99.9, "value": null }
+                                        ]
+                                    }
+                                }
+                            ],
+                            "sum_other_doc_count": 0
+                        }
+                    },
+                    {
+                        "key": 120000.0,
+                        "doc_count": 0,
+                        "group_by_0": { "buckets": [], "sum_other_doc_count": 0 }
+                    }
+                ]
+            }
+        });
+        let mut rollup_docs =
+            rollup_docs_from_aggregation(&aggregation, "timestamp", &rollup_config).unwrap();
+        let rollup_ids: Vec<JsonValue> = rollup_docs
+            .iter_mut()
+            .map(|rollup_doc| rollup_doc.remove(ROLLUP_ID_FIELD).unwrap())
+            .collect();
+        assert_ne!(rollup_ids[0], rollup_ids[1]);
+
+        // Rolling up the same time bucket again produces the same IDs.
+        let same_rollup_ids: Vec<JsonValue> =
+            rollup_docs_from_aggregation(&aggregation, "timestamp", &rollup_config)
+                .unwrap()
+                .into_iter()
+                .map(|mut rollup_doc| rollup_doc.remove(ROLLUP_ID_FIELD).unwrap())
+                .collect();
+        assert_eq!(rollup_ids, same_rollup_ids);
+
+        let rollup_docs: Vec<JsonValue> = rollup_docs.into_iter().map(JsonValue::Object).collect();
+        let expected_rollup_docs = vec![
+            json!({
+                "timestamp": 60,
+                "service": "api",
+                "doc_count": 2,
+                "latency_min": 1.0,
+                "latency_max": 3.0,
+                "latency_sum": 4.0,
+                "latency_avg": 2.0,
+                "latency_p50": 2.0,
+                "latency_p99_9": 3.0,
+            }),
+            json!({
+                "timestamp": 60,
+                "service": "db",
+                "doc_count": 1,
+                "latency_sum": 0.0,
+            }),
+        ];
+        assert_eq!(rollup_docs, expected_rollup_docs);
+    }
+
+    #[test]
+    fn test_rollup_docs_from_aggregation_beyond_max_terms() {
+        let mut rollup_config = rollup_config_for_test();
+        rollup_config.group_by = vec!["service".to_string(), "host".to_string()];
+        rollup_config.max_terms = 1;
+        rollup_config.metrics.clear();
+
+        let aggregation = json!({
+            "rollup": {
+                "buckets": [
+                    {
+                        "key": 60000.0,
+                        "doc_count": 10,
+                        "group_by_0": {
+                            "buckets": [
+                                {
+                                    "key": "api",
+                                    "doc_count": 6,
+                                    "group_by_1": {
+                                        "buckets": [{ "key": "host-1", "doc_count": 4 }],
+                                        "sum_other_doc_count": 2
+                                    }
+                                }
+                            ],
+                            "sum_other_doc_count": 4
+                        }
+                    }
+                ]
+            }
+        });
+        let rollup_docs: Vec<JsonValue> =
+            rollup_docs_from_aggregation(&aggregation, "timestamp", &rollup_config)
+                .unwrap()
+                .into_iter()
+                .map(|mut rollup_doc| {
+                    rollup_doc.remove(ROLLUP_ID_FIELD).unwrap();
+                    JsonValue::Object(rollup_doc)
+                })
+                .collect();
+        let expected_rollup_docs = vec![
+            json!({
+                "timestamp": 60,
+                "service": "__other__",
+                "host": "__other__",
+                "doc_count": 4,
+            }),
+            json!({
+                "timestamp": 60,
+                "service": "api",
+                "host": "__other__",
+                "doc_count": 2,
+            }),
+            json!({
+                "timestamp": 60,
+                "service": "api",
+                "host": "host-1",
+                "doc_count": 4,
+            }),
+        ];
+        assert_eq!(rollup_docs, expected_rollup_docs);
+    }
+
+    #[test]
+    fn test_late_time_windows() {
+        assert!(late_time_windows(Vec::new(), 600, 60).is_empty());
+
+        // Splits starting after the checkpoint are rolled up for the first time.
+        assert!(late_time_windows(vec![600..=700], 600, 60).is_empty());
+
+        let time_windows = late_time_windows(vec![130..=150, 61..=125, 550..=650], 600, 60);
+        assert_eq!(time_windows, vec![60..180, 540..600]);
+
+        // Long time windows are rolled up with several search requests.
+        let time_windows = late_time_windows(vec![0..=10_000], 7_200, 60);
+        assert_eq!(time_windows, vec![0..3_600, 3_600..7_200]);
+    }
+
+    #[test]
+    fn test_reconcile_rollup_docs() {
+        let rollup_doc = |rollup_id: &str, doc_count: u64| {
+            let rollup_doc = json!({ "rollup_id": rollup_id, "doc_count": doc_count });
+            rollup_doc.as_object().unwrap().clone()
+        };
+        let rollup_docs = vec![
+            rollup_doc("new", 1),
+            rollup_doc("unchanged", 2),
+            rollup_doc("late", 5),
+            rollup_doc("replaced", 7),
+        ];
+        let ingested_doc_counts = HashMap::from_iter([
+            ("unchanged".to_string(), BTreeSet::from_iter([2])),
+            ("late".to_string(), BTreeSet::from_iter([3])),
+            // The new version was ingested, but the outdated one is not deleted yet.
+            ("replaced".to_string(), BTreeSet::from_iter([6, 7])),
+        ]);
+        let (rollup_docs_to_ingest, stale_rollup_docs) =
+            reconcile_rollup_docs(rollup_docs, &ingested_doc_counts);
+        assert_eq!(
+            rollup_docs_to_ingest,
+            vec![rollup_doc("new", 1), rollup_doc("late", 5)]
+        );
+        assert_eq!(
+            stale_rollup_docs,
+            vec![("late".to_string(), 3), ("replaced".to_string(), 6)]
+        );
+    }
+
+    #[test]
+    fn test_stale_rollup_docs_query_ast() {
+        let query_ast = stale_rollup_docs_query_ast(&[("late".to_string(), 3)]);
+        let expected_query_ast: QueryAst = BoolQuery {
+            should: vec![BoolQuery {
+                must: vec![
+                    TermQuery {
+                        field: "rollup_id".to_string(),
+                        value: "late".to_string(),
+                    }
+                    .into(),
+                    TermQuery {
+                        field: "doc_count".to_string(),
+                        value: "3".to_string(),
+                    }
+                    .into(),
+                ],
+                ..Default::default()
+            }
+            .into()],
+            ..Default::default()
+        }
+        .into();
+        assert_eq!(query_ast, expected_query_ast);
+    }
+
+    #[test]
+    fn test_rollup_checkpoint() {
+        let rollup_config = rollup_config_for_test();
+        let index_config = IndexConfig::for_test("test-index", "ram:///indexes/test-index");
+        let mut index_metadata = IndexMetadata::new(index_config);
+        assert_eq!(rollup_checkpoint(&index_metadata, &rollup_config), None);
+
+        let source_delta = SourceCheckpointDelta::from_partition_delta(
+            PartitionId::from("test-index-rollup"),
+            Position::Beginning,
+            Position::offset(1_700_000_040u64),
+        )
+        .unwrap();
+        index_metadata
+            .checkpoint
+            .try_apply_delta(IndexCheckpointDelta {
+                source_id: ROLLUP_SOURCE_ID.to_string(),
+                source_delta,
+            })
+            .unwrap();
+        assert_eq!(
+            rollup_checkpoint(&index_metadata, &rollup_config),
+            Some(1_700_000_040)
+        );
+        assert_eq!(late_splits_watermark(&index_metadata, &rollup_config), None);
+    }
+}
@@ -78,6 +78,7 @@ pub(super) async fn create_empty_cluster(
         grpc_advertise_addr: config.grpc_advertise_addr,
         indexing_tasks: Vec::new(),
         indexing_cpu_capacity: CpuCapacity::zero(),
+        availability_zone_opt: None,
     };
     let cluster = Cluster::join(
         config.cluster_id.clone(),
@@ -87,6 +88,7 @@ pub(super) async fn create_empty_cluster(
         config.gossip_interval,
         FailureDetectorConfig::default(),
         &ChannelTransport::default(),
+        None,
     )
     .await?;
     Ok(cluster)
@@ -258,6 +260,7 @@ pub(super) async fn spawn_services(
                 metastore,
                 SearchJobPlacer::default(),
                 storage_resolver,
+                None,
                 event_broker,
                 false,
             )
@@ -702,6 +702,14 @@ fn split_query_predicate(split: &&Split, query: &ListSplitsQuery) -> bool {
         return false;
     }
 
+    if !query
+        .zone_maps
+        .iter()
+        .all(|zone_map_filter| zone_map_filter.evaluate(&split.split_metadata.zone_maps))
+    {
+        return false;
+    }
+
     if !query.split_states.is_empty() && !query.split_states.contains(&split.split_state) {
         return false;
     }
@@ -762,9 +770,11 @@ fn split_query_predicate(split: &&Split, query: &ListSplitsQuery) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::BTreeSet;
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::ops::Bound;
 
     use quickwit_doc_mapper::tag_pruning::TagFilterAst;
+    use quickwit_doc_mapper::zone_map_pruning::{ZoneMap, ZoneMapFilter, ZoneMapValue};
     use quickwit_proto::ingest::Shard;
     use quickwit_proto::metastore::ListShardsSubrequest;
     use quickwit_proto::types::{IndexUid, SourceId};
@@ -801,6 +811,13 @@ mod tests {
                     delete_opstamp: 9,
                     time_range: Some(32..=40),
                     tags: BTreeSet::from(["tag-1".to_string()]),
+                    zone_maps: BTreeMap::from([(
+                        "status_code".to_string(),
+                        ZoneMap {
+                            min: ZoneMapValue::U64(200),
+                            max: ZoneMapValue::U64(404),
+                        },
+                    )]),
                     create_timestamp: 12,
                     ..Default::default()
                 },
@@ -827,6 +844,13 @@ mod tests {
                     delete_opstamp: 0,
                     time_range: Some(0..=90),
                     tags: BTreeSet::from(["tag-2".to_string(), "tag-4".to_string()]),
+                    zone_maps: BTreeMap::from([(
+                        "status_code".to_string(),
+                        ZoneMap {
+                            min: ZoneMapValue::U64(200),
+                            max: ZoneMapValue::U64(503),
+                        },
+                    )]),
                     create_timestamp: 64,
                     ..Default::default()
                 },
@@ -892,6 +916,16 @@ mod tests {
         assert!(split_query_predicate(&&split_1, &query));
         assert!(!split_query_predicate(&&split_2, &query));
         assert!(!split_query_predicate(&&split_3, &query));
+
+        let query = ListSplitsQuery::for_index(IndexUid::new_with_random_ulid("test-index"))
+            .with_zone_map_filter(ZoneMapFilter {
+                field_name: "status_code".to_string(),
+                lower_bound: Bound::Included(ZoneMapValue::U64(500)),
+                upper_bound: Bound::Unbounded,
+            });
+        assert!(!split_query_predicate(&&split_1, &query));
+        assert!(split_query_predicate(&&split_2, &query));
+        assert!(split_query_predicate(&&split_3, &query));
     }
 
     #[test]
@@ -38,6 +38,7 @@ use quickwit_config::{
     SourceConfig, SourceParams,
 };
 use quickwit_doc_mapper::tag_pruning::TagFilterAst;
+use quickwit_doc_mapper::zone_map_pruning::ZoneMapFilter;
 use quickwit_proto::metastore::{
     serde_utils, AddSourceRequest, CreateIndexRequest, CreateIndexResponse, DeleteTask,
     IndexMetadataFailure, IndexMetadataRequest, IndexMetadataResponse, IndexesMetadataResponse,
@@ -616,6 +617,9 @@ pub struct ListSplitsQuery {
     /// A specific set of tag(s) to filter by.
     pub tags: Option<TagFilterAst>,
 
+    /// A set of zone map filters that the splits must all match.
+    pub zone_maps: Vec<ZoneMapFilter>,
+
     /// The time range to filter by.
     pub time_range: FilterRange<i64>,
 
@@ -684,6 +688,7 @@ impl ListSplitsQuery {
             offset: None,
             split_states: Vec::new(),
             tags: None,
+            zone_maps: Vec::new(),
             time_range: Default::default(),
             delete_opstamp: Default::default(),
             update_timestamp: Default::default(),
@@ -707,6 +712,7 @@ impl ListSplitsQuery {
             offset: None,
             split_states: Vec::new(),
             tags: None,
+            zone_maps: Vec::new(),
             time_range: Default::default(),
             delete_opstamp: Default::default(),
             update_timestamp: Default::default(),
@@ -726,6 +732,7 @@ impl ListSplitsQuery {
             offset: None,
             split_states: Vec::new(),
             tags: None,
+            zone_maps: Vec::new(),
             time_range: Default::default(),
             delete_opstamp: Default::default(),
             update_timestamp: Default::default(),
@@ -772,6 +779,12 @@ impl ListSplitsQuery {
         self
     }
 
+    /// Selects splits whose zone maps may match the given filter.
+    pub fn with_zone_map_filter(mut self, zone_map_filter: ZoneMapFilter) -> Self {
+        self.zone_maps.push(zone_map_filter);
+        self
+    }
+
     /// Sets the field's lower bound to match values that are
     /// *less than or equal to* the provided value.
     pub fn with_time_range_end_lte(mut self, v: i64) -> Self {
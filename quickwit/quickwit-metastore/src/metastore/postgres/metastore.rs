@@ -1796,9 +1796,12 @@ impl crate::tests::DefaultForTest for PostgresqlMetastore {
 
 #[cfg(test)]
 mod tests {
+    use std::ops::Bound;
+
     use async_trait::async_trait;
     use quickwit_common::uri::Protocol;
     use quickwit_doc_mapper::tag_pruning::TagFilterAst;
+    use quickwit_doc_mapper::zone_map_pruning::{ZoneMapFilter, ZoneMapValue};
     use quickwit_proto::ingest::Shard;
     use quickwit_proto::metastore::MetastoreService;
     use quickwit_proto::types::{IndexUid, SourceId};
@@ -2095,6 +2098,21 @@ mod tests {
             sql.to_string(PostgresQueryBuilder),
             r#"SELECT * FROM "splits" WHERE "split_state" IN ('Staged')"#
         );
+
+        let mut select_statement = Query::select();
+        let sql = select_statement.column(Asterisk).from(Splits::Table);
+
+        let query = ListSplitsQuery::for_all_indexes().with_zone_map_filter(ZoneMapFilter {
+            field_name: "status_code".to_string(),
+            lower_bound: Bound::Included(ZoneMapValue::U64(500)),
+            upper_bound: Bound::Unbounded,
+        });
+        append_query_filters_and_order_by(sql, &query);
+
+        assert_eq!(
+            sql.to_string(PostgresQueryBuilder),
+            r#"SELECT * FROM "splits" WHERE NOT COALESCE((split_metadata_json::jsonb -> 'zone_maps' -> 'status_code' -> 'max' ->> 'u64')::numeric < '500'::numeric, FALSE)"#
+        );
     }
 
     #[test]
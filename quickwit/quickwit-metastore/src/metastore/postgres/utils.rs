@@ -23,6 +23,7 @@ use std::str::FromStr;
 use std::time::Duration;
 
 use quickwit_common::uri::Uri;
+use quickwit_doc_mapper::zone_map_pruning::{ZoneMapFilter, ZoneMapValue};
 use quickwit_proto::metastore::{MetastoreError, MetastoreResult};
 use sea_query::{any, Expr, Func, Order, SelectStatement};
 use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
@@ -127,6 +128,10 @@ pub(super) fn append_query_filters_and_order_by(
         sql.cond_where(generate_sql_condition(tags));
     };
 
+    for zone_map_filter in &query.zone_maps {
+        append_zone_map_filter(sql, zone_map_filter);
+    }
+
     match query.time_range.start {
         Bound::Included(v) => {
             sql.cond_where(any![
@@ -225,6 +230,46 @@ pub(super) fn append_query_filters_and_order_by(
     }
 }
 
+/// Extends an existing SQL string with the conditions excluding the splits whose zone map cannot
+/// match the filter. Splits without a zone map for the field, or with a zone map of a different
+/// type, are kept.
+fn append_zone_map_filter(sql: &mut SelectStatement, zone_map_filter: &ZoneMapFilter) {
+    let zone_map_bound_condition = |zone_map_key: &str, operator: &str, value: &ZoneMapValue| {
+        let value_str = match value {
+            ZoneMapValue::U64(value) => value.to_string(),
+            ZoneMapValue::I64(value) | ZoneMapValue::Datetime(value) => value.to_string(),
+            ZoneMapValue::F64(value) => value.to_string(),
+        };
+        let condition_str = format!(
+            "NOT COALESCE((split_metadata_json::jsonb -> 'zone_maps' -> $1 -> '{zone_map_key}' \
+             ->> $2)::numeric {operator} $3::numeric, FALSE)"
+        );
+        Expr::cust_with_values(
+            condition_str,
+            [
+                zone_map_filter.field_name.clone(),
+                value.type_name().to_string(),
+                value_str,
+            ],
+        )
+    };
+    if let Bound::Included(value) = &zone_map_filter.lower_bound {
+        sql.cond_where(zone_map_bound_condition("max", "<", value));
+    };
+
+    if let Bound::Excluded(value) = &zone_map_filter.lower_bound {
+        sql.cond_where(zone_map_bound_condition("max", "<=", value));
+    };
+
+    if let Bound::Included(value) = &zone_map_filter.upper_bound {
+        sql.cond_where(zone_map_bound_condition("min", ">", value));
+    };
+
+    if let Bound::Excluded(value) = &zone_map_filter.upper_bound {
+        sql.cond_where(zone_map_bound_condition("min", ">=", value));
+    };
+}
+
 /// Returns the unix timestamp at which the split becomes mature.
 /// If the split is mature (`SplitMaturity::Mature`), we return 0
 /// as we don't want the maturity to depend on datetime.
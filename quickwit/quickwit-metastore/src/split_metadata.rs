@@ -17,7 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::ops::{Range, RangeInclusive};
 use std::path::PathBuf;
@@ -25,6 +25,8 @@ use std::str::FromStr;
 use std::time::Duration;
 
 use bytesize::ByteSize;
+use quickwit_config::IndexSortField;
+use quickwit_doc_mapper::zone_map_pruning::ZoneMap;
 use quickwit_proto::types::{DocMappingUid, IndexUid, SourceId, SplitId};
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DurationMilliSeconds};
@@ -120,6 +122,16 @@ pub struct SplitMetadata {
     /// [`MAX_VALUES_PER_TAG_FIELD`]: https://github.com/quickwit-oss/quickwit/blob/main/quickwit-indexing/src/actors/packager.rs#L36
     pub tags: BTreeSet<String>,
 
+    /// Min and max values of the fields registered in the
+    /// [`DocMapping`](quickwit_config::DocMapping) `zone_map_fields` attribute, keyed by field
+    /// name. Fields without any value in the split have no zone map.
+    pub zone_maps: BTreeMap<String, ZoneMap>,
+
+    /// Fast fields by which the documents of the split are sorted, following the
+    /// [`IndexingSettings`](quickwit_config::IndexingSettings) `sort_by` attribute in effect when
+    /// the split was created. Empty if the documents are not sorted.
+    pub index_sort: Vec<IndexSortField>,
+
     /// Contains the range of bytes of the footer that needs to be downloaded
     /// in order to open a split.
     ///
@@ -178,6 +190,12 @@ impl fmt::Debug for SplitMetadata {
             tags_str.push('}');
             debug_struct.field("tags", &tags_str);
         }
+        if !self.zone_maps.is_empty() {
+            debug_struct.field("zone_maps", &self.zone_maps);
+        }
+        if !self.index_sort.is_empty() {
+            debug_struct.field("index_sort", &self.index_sort);
+        }
         debug_struct.field("footer_offsets", &self.footer_offsets);
         debug_struct.field("delete_opstamp", &self.delete_opstamp);
         debug_struct.field("num_merge_ops", &self.num_merge_ops);
@@ -268,6 +286,8 @@ pub struct SplitInfo {
 #[cfg(any(test, feature = "testsuite"))]
 impl quickwit_config::TestableForRegression for SplitMetadata {
     fn sample_for_regression() -> Self {
+        use quickwit_doc_mapper::zone_map_pruning::ZoneMapValue;
+
         SplitMetadata {
             split_id: "split".to_string(),
             index_uid: IndexUid::for_test("my-index", 1),
@@ -283,9 +303,20 @@ impl quickwit_config::TestableForRegression for SplitMetadata {
                 maturation_period: Duration::from_secs(4),
             },
             tags: ["234".to_string(), "aaa".to_string()].into_iter().collect(),
+            zone_maps: BTreeMap::from_iter([(
+                "status_code".to_string(),
+                ZoneMap {
+                    min: ZoneMapValue::U64(200),
+                    max: ZoneMapValue::U64(503),
+                },
+            )]),
             footer_offsets: 1000..2000,
             num_merge_ops: 3,
             doc_mapping_uid: DocMappingUid::default(),
+            index_sort: vec![IndexSortField {
+                field: "timestamp".to_string(),
+                order: quickwit_proto::search::SortOrder::Desc,
+            }],
         }
     }
 
@@ -422,10 +453,12 @@ mod tests {
                 tags.insert("😿".to_string());
                 tags
             },
+            zone_maps: BTreeMap::new(),
             footer_offsets: 0..1024,
             delete_opstamp: 0,
             num_merge_ops: 0,
             doc_mapping_uid: DocMappingUid::default(),
+            index_sort: Vec::new(),
         };
 
         let expected_output = "SplitMetadata { split_id: \"split-1\", index_uid: IndexUid { \
@@ -17,9 +17,11 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::ops::{Range, RangeInclusive};
 
+use quickwit_config::IndexSortField;
+use quickwit_doc_mapper::zone_map_pruning::ZoneMap;
 use quickwit_proto::types::{DocMappingUid, IndexUid, SplitId};
 use serde::{Deserialize, Serialize};
 
@@ -78,6 +80,17 @@ pub(crate) struct SplitMetadataV0_8 {
     /// A set of tags for categorizing and searching group of splits.
     pub tags: BTreeSet<String>,
 
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    #[schema(value_type = Object)]
+    /// Min and max values of the zone map fields.
+    pub zone_maps: BTreeMap<String, ZoneMap>,
+
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    /// Fast fields by which the documents of the split are sorted.
+    pub index_sort: Vec<IndexSortField>,
+
     #[schema(value_type = Object)]
     /// Contains the range of bytes of the footer that needs to be downloaded
     /// in order to open a split.
@@ -130,6 +143,8 @@ impl From<SplitMetadataV0_8> for SplitMetadata {
             create_timestamp: v8.create_timestamp,
             maturity: v8.maturity,
             tags: v8.tags,
+            zone_maps: v8.zone_maps,
+            index_sort: v8.index_sort,
             footer_offsets: v8.footer_offsets,
             num_merge_ops: v8.num_merge_ops,
             doc_mapping_uid: v8.doc_mapping_uid,
@@ -152,6 +167,8 @@ impl From<SplitMetadata> for SplitMetadataV0_8 {
             create_timestamp: split.create_timestamp,
             maturity: split.maturity,
             tags: split.tags,
+            zone_maps: split.zone_maps,
+            index_sort: split.index_sort,
             footer_offsets: split.footer_offsets,
             num_merge_ops: split.num_merge_ops,
             doc_mapping_uid: split.doc_mapping_uid,
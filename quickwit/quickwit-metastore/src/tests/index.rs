@@ -171,6 +171,7 @@ pub async fn test_metastore_update_search_settings<
             index_uid.clone(),
             &SearchSettings {
                 default_search_fields: loop_search_settings.clone(),
+                ..Default::default()
             },
             &index_config.retention_policy_opt,
             &index_config.indexing_settings,
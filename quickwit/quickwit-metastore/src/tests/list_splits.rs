@@ -17,6 +17,8 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::BTreeMap;
+use std::ops::Bound;
 use std::time::Duration;
 
 use futures::TryStreamExt;
@@ -24,6 +26,7 @@ use itertools::Itertools;
 use quickwit_common::rand::append_random_suffix;
 use quickwit_config::IndexConfig;
 use quickwit_doc_mapper::tag_pruning::{no_tag, tag, TagFilterAst};
+use quickwit_doc_mapper::zone_map_pruning::{ZoneMap, ZoneMapFilter, ZoneMapValue};
 use quickwit_proto::metastore::{
     CreateIndexRequest, ListSplitsRequest, ListStaleSplitsRequest, MarkSplitsForDeletionRequest,
     PublishSplitsRequest, StageSplitsRequest,
@@ -961,6 +964,99 @@ pub async fn test_metastore_list_splits_by_node_id<
     assert_eq!(splits[0].split_metadata.node_id, "test-node-1");
 }
 
+pub async fn test_metastore_list_splits_by_zone_maps<
+    MetastoreToTest: MetastoreServiceExt + DefaultForTest,
+>() {
+    let metastore = MetastoreToTest::default_for_test().await;
+
+    let index_id = append_random_suffix("test-list-splits-by-zone-maps");
+    let index_uri = format!("ram:///indexes/{index_id}");
+    let index_config = IndexConfig::for_test(&index_id, &index_uri);
+
+    let create_index_request = CreateIndexRequest::try_from_index_config(&index_config).unwrap();
+    let index_uid: IndexUid = metastore
+        .create_index(create_index_request)
+        .await
+        .unwrap()
+        .index_uid
+        .unwrap();
+
+    let status_code_zone_map = |min: u64, max: u64| {
+        BTreeMap::from([(
+            "status_code".to_string(),
+            ZoneMap {
+                min: ZoneMapValue::U64(min),
+                max: ZoneMapValue::U64(max),
+            },
+        )])
+    };
+    let split_id_1 = format!("{index_id}--split-1");
+    let split_metadata_1 = SplitMetadata {
+        split_id: split_id_1.clone(),
+        index_uid: index_uid.clone(),
+        zone_maps: status_code_zone_map(200, 404),
+        ..Default::default()
+    };
+    let split_id_2 = format!("{index_id}--split-2");
+    let split_metadata_2 = SplitMetadata {
+        split_id: split_id_2.clone(),
+        index_uid: index_uid.clone(),
+        zone_maps: status_code_zone_map(200, 503),
+        ..Default::default()
+    };
+    let split_id_3 = format!("{index_id}--split-3");
+    let split_metadata_3 = SplitMetadata {
+        split_id: split_id_3.clone(),
+        index_uid: index_uid.clone(),
+        ..Default::default()
+    };
+    let stage_splits_request = StageSplitsRequest::try_from_splits_metadata(
+        index_uid.clone(),
+        vec![split_metadata_1, split_metadata_2, split_metadata_3],
+    )
+    .unwrap();
+
+    metastore.stage_splits(stage_splits_request).await.unwrap();
+
+    let list_splits_query =
+        ListSplitsQuery::for_index(index_uid.clone()).with_zone_map_filter(ZoneMapFilter {
+            field_name: "status_code".to_string(),
+            lower_bound: Bound::Included(ZoneMapValue::U64(500)),
+            upper_bound: Bound::Unbounded,
+        });
+    let list_splits_request =
+        ListSplitsRequest::try_from_list_splits_query(&list_splits_query).unwrap();
+
+    let splits = metastore
+        .list_splits(list_splits_request)
+        .await
+        .unwrap()
+        .collect_splits()
+        .await
+        .unwrap();
+    let split_ids = collect_split_ids(&splits);
+    assert_eq!(split_ids, &[&split_id_2, &split_id_3]);
+
+    let list_splits_query =
+        ListSplitsQuery::for_index(index_uid.clone()).with_zone_map_filter(ZoneMapFilter {
+            field_name: "status_code".to_string(),
+            lower_bound: Bound::Unbounded,
+            upper_bound: Bound::Excluded(ZoneMapValue::U64(200)),
+        });
+    let list_splits_request =
+        ListSplitsRequest::try_from_list_splits_query(&list_splits_query).unwrap();
+
+    let splits = metastore
+        .list_splits(list_splits_request)
+        .await
+        .unwrap()
+        .collect_splits()
+        .await
+        .unwrap();
+    let split_ids = collect_split_ids(&splits);
+    assert_eq!(split_ids, &[&split_id_3]);
+}
+
 pub async fn test_metastore_list_stale_splits<
     MetastoreToTest: MetastoreServiceExt + DefaultForTest,
 >() {
@@ -360,6 +360,13 @@ macro_rules! metastore_test_suite {
                 $crate::tests::list_splits::test_metastore_list_splits_by_node_id::<$metastore_type>().await;
             }
 
+            #[tokio::test]
+            #[serial_test::file_serial]
+            async fn test_metastore_list_splits_by_zone_maps() {
+                let _ = tracing_subscriber::fmt::try_init();
+                $crate::tests::list_splits::test_metastore_list_splits_by_zone_maps::<$metastore_type>().await;
+            }
+
             #[tokio::test]
             #[serial_test::file_serial]
             async fn test_metastore_split_update_timestamp() {
@@ -420,6 +420,91 @@ pub struct DecommissionResponse {}
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SearchShardsRequest {
+    /// JSON-serialized `QueryAst`.
+    #[prost(string, tag = "1")]
+    pub query_ast: ::prost::alloc::string::String,
+    #[prost(message, repeated, tag = "2")]
+    pub subrequests: ::prost::alloc::vec::Vec<SearchShardsSubrequest>,
+    /// Maximum number of hits returned per subrequest.
+    #[prost(uint64, tag = "3")]
+    pub max_hits: u64,
+    /// If set, only the documents with a timestamp greater or equal to `start_timestamp`, expressed in seconds, match.
+    #[prost(int64, optional, tag = "4")]
+    pub start_timestamp: ::core::option::Option<i64>,
+    /// If set, only the documents with a timestamp lower than `end_timestamp`, expressed in seconds, match.
+    #[prost(int64, optional, tag = "5")]
+    pub end_timestamp: ::core::option::Option<i64>,
+    /// Hits are sorted by descending timestamp, or by descending position if the index has no timestamp field,
+    /// unless `sort_ascending` is set.
+    #[prost(bool, tag = "6")]
+    pub sort_ascending: bool,
+}
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SearchShardsSubrequest {
+    #[prost(uint32, tag = "1")]
+    pub subrequest_id: u32,
+    #[prost(message, optional, tag = "2")]
+    pub index_uid: ::core::option::Option<crate::types::IndexUid>,
+    #[prost(string, tag = "3")]
+    pub source_id: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "4")]
+    pub shard_id: ::core::option::Option<crate::types::ShardId>,
+    /// The records up to this position (inclusive) are already published in splits and are not searched.
+    #[prost(message, optional, tag = "5")]
+    pub publish_position_inclusive: ::core::option::Option<crate::types::Position>,
+}
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SearchShardsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub successes: ::prost::alloc::vec::Vec<SearchShardsSuccess>,
+    #[prost(message, repeated, tag = "2")]
+    pub failures: ::prost::alloc::vec::Vec<SearchShardsFailure>,
+}
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SearchShardsSuccess {
+    #[prost(uint32, tag = "1")]
+    pub subrequest_id: u32,
+    /// Number of documents matching the query.
+    #[prost(uint64, tag = "2")]
+    pub num_hits: u64,
+    #[prost(message, repeated, tag = "3")]
+    pub hits: ::prost::alloc::vec::Vec<ShardHit>,
+}
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ShardHit {
+    /// Position of the document in the shard.
+    #[prost(message, optional, tag = "1")]
+    pub position: ::core::option::Option<crate::types::Position>,
+    /// JSON-serialized document.
+    #[prost(string, tag = "2")]
+    pub json: ::prost::alloc::string::String,
+    /// Timestamp of the document in nanoseconds, if the index has a timestamp field.
+    #[prost(int64, optional, tag = "3")]
+    pub timestamp_nanos: ::core::option::Option<i64>,
+}
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SearchShardsFailure {
+    #[prost(uint32, tag = "1")]
+    pub subrequest_id: u32,
+    #[prost(string, tag = "2")]
+    pub reason: ::prost::alloc::string::String,
+    #[prost(enumeration = "SearchShardsFailureReason", tag = "3")]
+    pub reason_code: i32,
+}
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct OpenObservationStreamRequest {}
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -516,6 +601,47 @@ impl ReplicateFailureReason {
 #[serde(rename_all = "snake_case")]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
+pub enum SearchShardsFailureReason {
+    Unspecified = 0,
+    ShardNotFound = 1,
+    /// The records to search exceed the maximum number of records or bytes searched per request.
+    LimitExceeded = 2,
+    Internal = 3,
+}
+impl SearchShardsFailureReason {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            SearchShardsFailureReason::Unspecified => {
+                "SEARCH_SHARDS_FAILURE_REASON_UNSPECIFIED"
+            }
+            SearchShardsFailureReason::ShardNotFound => {
+                "SEARCH_SHARDS_FAILURE_REASON_SHARD_NOT_FOUND"
+            }
+            SearchShardsFailureReason::LimitExceeded => {
+                "SEARCH_SHARDS_FAILURE_REASON_LIMIT_EXCEEDED"
+            }
+            SearchShardsFailureReason::Internal => "SEARCH_SHARDS_FAILURE_REASON_INTERNAL",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "SEARCH_SHARDS_FAILURE_REASON_UNSPECIFIED" => Some(Self::Unspecified),
+            "SEARCH_SHARDS_FAILURE_REASON_SHARD_NOT_FOUND" => Some(Self::ShardNotFound),
+            "SEARCH_SHARDS_FAILURE_REASON_LIMIT_EXCEEDED" => Some(Self::LimitExceeded),
+            "SEARCH_SHARDS_FAILURE_REASON_INTERNAL" => Some(Self::Internal),
+            _ => None,
+        }
+    }
+}
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
 pub enum IngesterStatus {
     Unspecified = 0,
     /// The ingester is live but not ready yet to accept requests.
@@ -610,6 +736,11 @@ impl RpcName for DecommissionRequest {
         "decommission"
     }
 }
+impl RpcName for SearchShardsRequest {
+    fn rpc_name() -> &'static str {
+        "search_shards"
+    }
+}
 pub type IngesterServiceStream<T> = quickwit_common::ServiceStream<
     crate::ingest::IngestV2Result<T>,
 >;
@@ -663,6 +794,11 @@ pub trait IngesterService: std::fmt::Debug + Send + Sync + 'static {
         &self,
         request: DecommissionRequest,
     ) -> crate::ingest::IngestV2Result<DecommissionResponse>;
+    /// Searches the documents of a set of shards that are persisted but not indexed yet. This RPC is called by root searchers on leaders.
+    async fn search_shards(
+        &self,
+        request: SearchShardsRequest,
+    ) -> crate::ingest::IngestV2Result<SearchShardsResponse>;
 }
 #[derive(Debug, Clone)]
 pub struct IngesterServiceClient {
@@ -809,6 +945,12 @@ impl IngesterService for IngesterServiceClient {
     ) -> crate::ingest::IngestV2Result<DecommissionResponse> {
         self.inner.0.decommission(request).await
     }
+    async fn search_shards(
+        &self,
+        request: SearchShardsRequest,
+    ) -> crate::ingest::IngestV2Result<SearchShardsResponse> {
+        self.inner.0.search_shards(request).await
+    }
 }
 #[cfg(any(test, feature = "testsuite"))]
 pub mod mock_ingester_service {
@@ -877,6 +1019,12 @@ pub mod mock_ingester_service {
         ) -> crate::ingest::IngestV2Result<super::DecommissionResponse> {
             self.inner.lock().await.decommission(request).await
         }
+        async fn search_shards(
+            &self,
+            request: super::SearchShardsRequest,
+        ) -> crate::ingest::IngestV2Result<super::SearchShardsResponse> {
+            self.inner.lock().await.search_shards(request).await
+        }
     }
 }
 pub type BoxFuture<T, E> = std::pin::Pin<
@@ -1030,6 +1178,22 @@ impl tower::Service<DecommissionRequest> for InnerIngesterServiceClient {
         Box::pin(fut)
     }
 }
+impl tower::Service<SearchShardsRequest> for InnerIngesterServiceClient {
+    type Response = SearchShardsResponse;
+    type Error = crate::ingest::IngestV2Error;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+    fn call(&mut self, request: SearchShardsRequest) -> Self::Future {
+        let svc = self.clone();
+        let fut = async move { svc.0.search_shards(request).await };
+        Box::pin(fut)
+    }
+}
 /// A tower service stack is a set of tower services.
 #[derive(Debug)]
 struct IngesterServiceTowerServiceStack {
@@ -1080,6 +1244,11 @@ struct IngesterServiceTowerServiceStack {
         DecommissionResponse,
         crate::ingest::IngestV2Error,
     >,
+    search_shards_svc: quickwit_common::tower::BoxService<
+        SearchShardsRequest,
+        SearchShardsResponse,
+        crate::ingest::IngestV2Error,
+    >,
 }
 #[async_trait::async_trait]
 impl IngesterService for IngesterServiceTowerServiceStack {
@@ -1137,6 +1306,12 @@ impl IngesterService for IngesterServiceTowerServiceStack {
     ) -> crate::ingest::IngestV2Result<DecommissionResponse> {
         self.decommission_svc.clone().ready().await?.call(request).await
     }
+    async fn search_shards(
+        &self,
+        request: SearchShardsRequest,
+    ) -> crate::ingest::IngestV2Result<SearchShardsResponse> {
+        self.search_shards_svc.clone().ready().await?.call(request).await
+    }
 }
 type PersistLayer = quickwit_common::tower::BoxLayer<
     quickwit_common::tower::BoxService<
@@ -1228,6 +1403,16 @@ type DecommissionLayer = quickwit_common::tower::BoxLayer<
     DecommissionResponse,
     crate::ingest::IngestV2Error,
 >;
+type SearchShardsLayer = quickwit_common::tower::BoxLayer<
+    quickwit_common::tower::BoxService<
+        SearchShardsRequest,
+        SearchShardsResponse,
+        crate::ingest::IngestV2Error,
+    >,
+    SearchShardsRequest,
+    SearchShardsResponse,
+    crate::ingest::IngestV2Error,
+>;
 #[derive(Debug, Default)]
 pub struct IngesterServiceTowerLayerStack {
     persist_layers: Vec<PersistLayer>,
@@ -1239,6 +1424,7 @@ pub struct IngesterServiceTowerLayerStack {
     truncate_shards_layers: Vec<TruncateShardsLayer>,
     close_shards_layers: Vec<CloseShardsLayer>,
     decommission_layers: Vec<DecommissionLayer>,
+    search_shards_layers: Vec<SearchShardsLayer>,
 }
 impl IngesterServiceTowerLayerStack {
     pub fn stack_layer<L>(mut self, layer: L) -> Self
@@ -1472,6 +1658,31 @@ impl IngesterServiceTowerLayerStack {
                 crate::ingest::IngestV2Error,
             >,
         >>::Service as tower::Service<DecommissionRequest>>::Future: Send + 'static,
+        L: tower::Layer<
+                quickwit_common::tower::BoxService<
+                    SearchShardsRequest,
+                    SearchShardsResponse,
+                    crate::ingest::IngestV2Error,
+                >,
+            > + Clone + Send + Sync + 'static,
+        <L as tower::Layer<
+            quickwit_common::tower::BoxService<
+                SearchShardsRequest,
+                SearchShardsResponse,
+                crate::ingest::IngestV2Error,
+            >,
+        >>::Service: tower::Service<
+                SearchShardsRequest,
+                Response = SearchShardsResponse,
+                Error = crate::ingest::IngestV2Error,
+            > + Clone + Send + Sync + 'static,
+        <<L as tower::Layer<
+            quickwit_common::tower::BoxService<
+                SearchShardsRequest,
+                SearchShardsResponse,
+                crate::ingest::IngestV2Error,
+            >,
+        >>::Service as tower::Service<SearchShardsRequest>>::Future: Send + 'static,
     {
         self.persist_layers.push(quickwit_common::tower::BoxLayer::new(layer.clone()));
         self.open_replication_stream_layers
@@ -1490,6 +1701,8 @@ impl IngesterServiceTowerLayerStack {
             .push(quickwit_common::tower::BoxLayer::new(layer.clone()));
         self.decommission_layers
             .push(quickwit_common::tower::BoxLayer::new(layer.clone()));
+        self.search_shards_layers
+            .push(quickwit_common::tower::BoxLayer::new(layer.clone()));
         self
     }
     pub fn stack_persist_layer<L>(mut self, layer: L) -> Self
@@ -1669,6 +1882,25 @@ impl IngesterServiceTowerLayerStack {
         self.decommission_layers.push(quickwit_common::tower::BoxLayer::new(layer));
         self
     }
+    pub fn stack_search_shards_layer<L>(mut self, layer: L) -> Self
+    where
+        L: tower::Layer<
+                quickwit_common::tower::BoxService<
+                    SearchShardsRequest,
+                    SearchShardsResponse,
+                    crate::ingest::IngestV2Error,
+                >,
+            > + Send + Sync + 'static,
+        L::Service: tower::Service<
+                SearchShardsRequest,
+                Response = SearchShardsResponse,
+                Error = crate::ingest::IngestV2Error,
+            > + Clone + Send + Sync + 'static,
+        <L::Service as tower::Service<SearchShardsRequest>>::Future: Send + 'static,
+    {
+        self.search_shards_layers.push(quickwit_common::tower::BoxLayer::new(layer));
+        self
+    }
     pub fn build<T>(self, instance: T) -> IngesterServiceClient
     where
         T: IngesterService,
@@ -1797,6 +2029,14 @@ impl IngesterServiceTowerLayerStack {
                 quickwit_common::tower::BoxService::new(inner_client.clone()),
                 |svc, layer| layer.layer(svc),
             );
+        let search_shards_svc = self
+            .search_shards_layers
+            .into_iter()
+            .rev()
+            .fold(
+                quickwit_common::tower::BoxService::new(inner_client.clone()),
+                |svc, layer| layer.layer(svc),
+            );
         let tower_svc_stack = IngesterServiceTowerServiceStack {
             inner: inner_client,
             persist_svc,
@@ -1808,6 +2048,7 @@ impl IngesterServiceTowerLayerStack {
             truncate_shards_svc,
             close_shards_svc,
             decommission_svc,
+            search_shards_svc,
         };
         IngesterServiceClient::new(tower_svc_stack)
     }
@@ -1946,6 +2187,12 @@ where
             Response = DecommissionResponse,
             Error = crate::ingest::IngestV2Error,
             Future = BoxFuture<DecommissionResponse, crate::ingest::IngestV2Error>,
+        >
+        + tower::Service<
+            SearchShardsRequest,
+            Response = SearchShardsResponse,
+            Error = crate::ingest::IngestV2Error,
+            Future = BoxFuture<SearchShardsResponse, crate::ingest::IngestV2Error>,
         >,
 {
     async fn persist(
@@ -2002,6 +2249,12 @@ where
     ) -> crate::ingest::IngestV2Result<DecommissionResponse> {
         self.clone().call(request).await
     }
+    async fn search_shards(
+        &self,
+        request: SearchShardsRequest,
+    ) -> crate::ingest::IngestV2Result<SearchShardsResponse> {
+        self.clone().call(request).await
+    }
 }
 #[derive(Debug, Clone)]
 pub struct IngesterServiceGrpcClientAdapter<T> {
@@ -2187,6 +2440,20 @@ where
                 DecommissionRequest::rpc_name(),
             ))
     }
+    async fn search_shards(
+        &self,
+        request: SearchShardsRequest,
+    ) -> crate::ingest::IngestV2Result<SearchShardsResponse> {
+        self.inner
+            .clone()
+            .search_shards(request)
+            .await
+            .map(|response| response.into_inner())
+            .map_err(|status| crate::error::grpc_status_to_service_error(
+                status,
+                SearchShardsRequest::rpc_name(),
+            ))
+    }
 }
 #[derive(Debug)]
 pub struct IngesterServiceGrpcServerAdapter {
@@ -2322,6 +2589,17 @@ for IngesterServiceGrpcServerAdapter {
             .map(tonic::Response::new)
             .map_err(crate::error::grpc_error_to_grpc_status)
     }
+    async fn search_shards(
+        &self,
+        request: tonic::Request<SearchShardsRequest>,
+    ) -> Result<tonic::Response<SearchShardsResponse>, tonic::Status> {
+        self.inner
+            .0
+            .search_shards(request.into_inner())
+            .await
+            .map(tonic::Response::new)
+            .map_err(crate::error::grpc_error_to_grpc_status)
+    }
 }
 /// Generated client implementations.
 pub mod ingester_service_grpc_client {
@@ -2691,6 +2969,37 @@ pub mod ingester_service_grpc_client {
                 );
             self.inner.unary(req, path, codec).await
         }
+        /// Searches the documents of a set of shards that are persisted but not indexed yet. This RPC is called by root searchers on leaders.
+        pub async fn search_shards(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SearchShardsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::SearchShardsResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit.ingest.ingester.IngesterService/SearchShards",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "quickwit.ingest.ingester.IngesterService",
+                        "SearchShards",
+                    ),
+                );
+            self.inner.unary(req, path, codec).await
+        }
     }
 }
 /// Generated server implementations.
@@ -2789,6 +3098,14 @@ pub mod ingester_service_grpc_server {
             tonic::Response<super::DecommissionResponse>,
             tonic::Status,
         >;
+        /// Searches the documents of a set of shards that are persisted but not indexed yet. This RPC is called by root searchers on leaders.
+        async fn search_shards(
+            &self,
+            request: tonic::Request<super::SearchShardsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::SearchShardsResponse>,
+            tonic::Status,
+        >;
     }
     #[derive(Debug)]
     pub struct IngesterServiceGrpcServer<T: IngesterServiceGrpc> {
@@ -3286,6 +3603,52 @@ pub mod ingester_service_grpc_server {
                     };
                     Box::pin(fut)
                 }
+                "/quickwit.ingest.ingester.IngesterService/SearchShards" => {
+                    #[allow(non_camel_case_types)]
+                    struct SearchShardsSvc<T: IngesterServiceGrpc>(pub Arc<T>);
+                    impl<
+                        T: IngesterServiceGrpc,
+                    > tonic::server::UnaryService<super::SearchShardsRequest>
+                    for SearchShardsSvc<T> {
+                        type Response = super::SearchShardsResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SearchShardsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                (*inner).search_shards(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = SearchShardsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => {
                     Box::pin(async move {
                         Ok(
@@ -191,6 +191,27 @@ pub struct SearchRequest {
     pub search_after: ::core::option::Option<PartialHit>,
     #[prost(enumeration = "CountHits", tag = "17")]
     pub count_hits: i32,
+    /// If set, the documents persisted in the ingesters' write-ahead logs but not yet
+    /// indexed are searched as well (real-time search).
+    #[prost(bool, tag = "18")]
+    pub real_time: bool,
+    /// JSON-serialized list of runtime fields, computed at query time by evaluating a VRL
+    /// script over the stored documents. They complete or override the runtime fields
+    /// defined in the search settings of the indexes.
+    #[prost(string, optional, tag = "19")]
+    pub runtime_fields: ::core::option::Option<::prost::alloc::string::String>,
+    /// If set, the response contains the time spent and the bytes read by the leaf searchers
+    /// in each stage of the search of each split.
+    #[prost(bool, tag = "20")]
+    pub profile: bool,
+    /// User agent of the client that issued the request, recorded in the slow query log.
+    #[prost(string, optional, tag = "21")]
+    pub user_agent: ::core::option::Option<::prost::alloc::string::String>,
+    /// If not empty, only the splits with these IDs are searched. The splits marked for deletion
+    /// remain searchable until they are garbage collected, so a client can paginate through a
+    /// pinned set of splits while they are being merged.
+    #[prost(string, repeated, tag = "22")]
+    pub split_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[derive(Eq, Hash)]
@@ -241,6 +262,39 @@ pub struct SearchResponse {
     /// Total number of successful splits searched.
     #[prost(uint64, tag = "8")]
     pub num_successful_splits: u64,
+    /// Profiles of the searched splits (only set if `profile` was set in the request).
+    #[prost(message, repeated, tag = "9")]
+    pub split_profiles: ::prost::alloc::vec::Vec<SplitSearchProfile>,
+}
+/// Time spent and bytes read by a leaf searcher to search a split.
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SplitSearchProfile {
+    /// gRPC address of the leaf searcher.
+    #[prost(string, tag = "1")]
+    pub leaf_addr: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub split_id: ::prost::alloc::string::String,
+    /// Time spent opening the split and downloading the data required by the query
+    /// (term dictionaries, posting lists, fast fields, etc.), in microseconds.
+    #[prost(uint64, tag = "3")]
+    pub warmup_micros: u64,
+    /// Time spent executing the query, aggregation collection excluded, in microseconds.
+    #[prost(uint64, tag = "4")]
+    pub query_execution_micros: u64,
+    /// Time spent collecting the aggregations, in microseconds.
+    #[prost(uint64, tag = "5")]
+    pub aggregation_collection_micros: u64,
+    /// Time spent fetching the documents of the hits, in microseconds.
+    #[prost(uint64, tag = "6")]
+    pub fetch_docs_micros: u64,
+    /// Number of bytes fetched from the storage.
+    #[prost(uint64, tag = "7")]
+    pub storage_num_bytes: u64,
+    /// Number of bytes served by the split footer, fast field, and split caches.
+    #[prost(uint64, tag = "8")]
+    pub cache_num_bytes: u64,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -325,6 +379,9 @@ pub struct SplitIdAndFooterOffsets {
     /// The number of docs in the split
     #[prost(uint64, tag = "6")]
     pub num_docs: u64,
+    /// The fast fields by which the documents of the split are sorted, if any.
+    #[prost(message, repeated, tag = "7")]
+    pub index_sort: ::prost::alloc::vec::Vec<SortField>,
 }
 /// Hits returned by a FetchDocRequest.
 ///
@@ -457,6 +514,13 @@ pub struct LeafSearchResponse {
     pub intermediate_aggregation_result: ::core::option::Option<
         ::prost::alloc::vec::Vec<u8>,
     >,
+    /// Profiles of the searched splits (only set if `profile` was set in the search request).
+    #[prost(message, repeated, tag = "8")]
+    pub split_profiles: ::prost::alloc::vec::Vec<SplitSearchProfile>,
+    /// Number of bytes of the splits read to execute the search, either from the index storage or
+    /// from the caches.
+    #[prost(uint64, tag = "9")]
+    pub num_bytes_read: u64,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -488,6 +552,10 @@ pub struct FetchDocsRequest {
     /// `DocMapper` as json serialized trait.
     #[prost(string, tag = "6")]
     pub doc_mapper: ::prost::alloc::string::String,
+    /// If set, the response contains the time spent and the bytes read to fetch the documents
+    /// of each split.
+    #[prost(bool, tag = "8")]
+    pub profile: bool,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -496,6 +564,9 @@ pub struct FetchDocsResponse {
     /// List of complete hits.
     #[prost(message, repeated, tag = "1")]
     pub hits: ::prost::alloc::vec::Vec<LeafHit>,
+    /// Profiles of the splits (only set if `profile` was set in the request).
+    #[prost(message, repeated, tag = "2")]
+    pub split_profiles: ::prost::alloc::vec::Vec<SplitSearchProfile>,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -599,6 +670,10 @@ pub struct SearchStreamRequest {
     /// Fields to extract snippet on.
     #[prost(string, repeated, tag = "10")]
     pub snippet_fields: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Names of the fast fields to extract, one column per field.
+    /// Supersedes `fast_field` when not empty.
+    #[prost(string, repeated, tag = "12")]
+    pub fast_fields: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -790,6 +865,11 @@ pub enum OutputFormat {
     /// Format data by row in ClickHouse binary format.
     /// <https://clickhouse.tech/docs/en/interfaces/formats/#rowbinary>
     ClickHouseRowBinary = 1,
+    /// Apache Arrow IPC streaming format.
+    /// <https://arrow.apache.org/docs/format/Columnar.html#ipc-streaming-format>
+    ArrowIpc = 2,
+    /// Newline-delimited JSON, one JSON object per document.
+    Ndjson = 3,
 }
 impl OutputFormat {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -800,6 +880,8 @@ impl OutputFormat {
         match self {
             OutputFormat::Csv => "CSV",
             OutputFormat::ClickHouseRowBinary => "CLICK_HOUSE_ROW_BINARY",
+            OutputFormat::ArrowIpc => "ARROW_IPC",
+            OutputFormat::Ndjson => "NDJSON",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -807,6 +889,8 @@ impl OutputFormat {
         match value {
             "CSV" => Some(Self::Csv),
             "CLICK_HOUSE_ROW_BINARY" => Some(Self::ClickHouseRowBinary),
+            "ARROW_IPC" => Some(Self::ArrowIpc),
+            "NDJSON" => Some(Self::Ndjson),
             _ => None,
         }
     }
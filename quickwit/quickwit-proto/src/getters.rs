@@ -111,6 +111,7 @@ generate_getters! {
     ReplicateSubrequest,
     ReplicateSuccess,
     RetainShardsForSource,
+    SearchShardsSubrequest,
     Shard,
     ShardIdPositions,
     ShardIds,
@@ -190,6 +191,12 @@ generate_clone_getters! {
     TruncateShardsSubrequest
 }
 
+generate_clone_getters! {
+    impl fn publish_position_inclusive() -> Position {} for
+
+    SearchShardsSubrequest
+}
+
 // [`Shard`] getters
 generate_getters! {
     impl fn open_shard() -> &Shard {} for
@@ -219,6 +226,7 @@ generate_getters! {
     ReplicateFailure,
     ReplicateSubrequest,
     ReplicateSuccess,
+    SearchShardsSubrequest,
     Shard,
     ShardIdPosition,
     ShardPKey,
@@ -189,3 +189,9 @@ impl TruncateShardsSubrequest {
         queue_id(self.index_uid(), &self.source_id, self.shard_id())
     }
 }
+
+impl SearchShardsSubrequest {
+    pub fn queue_id(&self) -> QueueId {
+        queue_id(self.index_uid(), &self.source_id, self.shard_id())
+    }
+}
@@ -49,6 +49,8 @@ pub enum RateLimitingCause {
     CircuitBreaker,
     #[error("shard rate limiting")]
     ShardRateLimiting,
+    #[error("quota exceeded")]
+    QuotaExceeded,
     #[error("unknown")]
     Unknown,
 }
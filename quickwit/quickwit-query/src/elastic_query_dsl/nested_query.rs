@@ -0,0 +1,113 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use serde::Deserialize;
+
+use crate::elastic_query_dsl::{ConvertibleToQueryAst, ElasticQueryDslInner};
+use crate::not_nan_f32::NotNaNf32;
+use crate::query_ast::{self, QueryAst};
+
+#[derive(Deserialize, Debug, Default, Eq, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum ScoreMode {
+    #[default]
+    Avg,
+    Max,
+    Min,
+    None,
+    Sum,
+}
+
+/// # Unsupported features
+/// - score_mode: the score of a matching document is the score of the inner query computed on the
+///   flattened nested fields, regardless of the score mode.
+/// - inner_hits
+/// - ignore_unmapped
+#[derive(Deserialize, Debug, Eq, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct NestedQuery {
+    path: String,
+    query: Box<ElasticQueryDslInner>,
+    #[serde(default)]
+    score_mode: ScoreMode,
+    #[serde(default)]
+    boost: Option<NotNaNf32>,
+}
+
+impl ConvertibleToQueryAst for NestedQuery {
+    fn convert_to_query_ast(self) -> anyhow::Result<QueryAst> {
+        let inner_query_ast = self.query.convert_to_query_ast()?;
+        let nested_query_ast: QueryAst = query_ast::NestedQuery {
+            path: self.path,
+            query: Box::new(inner_query_ast),
+        }
+        .into();
+        Ok(nested_query_ast.boost(self.boost))
+    }
+}
+
+impl From<NestedQuery> for ElasticQueryDslInner {
+    fn from(nested_query: NestedQuery) -> Self {
+        Self::Nested(nested_query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elastic_query_dsl::ElasticQueryDsl;
+
+    #[test]
+    fn test_dsl_nested_query_deserialize_and_convert() {
+        let nested_query_json = r#"{
+            "nested": {
+                "path": "items",
+                "score_mode": "max",
+                "query": {
+                    "bool": {
+                        "must": [
+                            { "term": { "items.sku": "A" } },
+                            { "range": { "items.qty": { "gt": 5 } } }
+                        ]
+                    }
+                }
+            }
+        }"#;
+        let es_query_dsl: ElasticQueryDsl = serde_json::from_str(nested_query_json).unwrap();
+        let query_ast: QueryAst = es_query_dsl.try_into().unwrap();
+        let QueryAst::Nested(nested_query) = query_ast else {
+            panic!("expected a nested query, got {query_ast:?}");
+        };
+        assert_eq!(nested_query.path, "items");
+        let QueryAst::Bool(bool_query) = *nested_query.query else {
+            panic!("expected a bool query");
+        };
+        assert_eq!(bool_query.must.len(), 2);
+    }
+
+    #[test]
+    fn test_dsl_nested_query_rejects_unknown_fields() {
+        let nested_query_json = r#"{
+            "path": "items",
+            "query": { "match_all": {} },
+            "inner_hits": {}
+        }"#;
+        serde_json::from_str::<NestedQuery>(nested_query_json).unwrap_err();
+    }
+}
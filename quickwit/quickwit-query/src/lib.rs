@@ -46,7 +46,7 @@ pub use tantivy::query::Query as TantivyQuery;
 pub use tokenizers::MultiLangTokenizer;
 pub use tokenizers::{
     create_default_quickwit_tokenizer_manager, get_quickwit_fastfield_normalizer_manager,
-    CodeTokenizer, DEFAULT_REMOVE_TOKEN_LENGTH,
+    CodeTokenizer, SynonymFilter, WordDelimiterFilter, DEFAULT_REMOVE_TOKEN_LENGTH,
 };
 
 #[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, Eq, PartialEq)]
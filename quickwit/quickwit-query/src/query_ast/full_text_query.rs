@@ -59,7 +59,7 @@ impl FullTextParams {
             .as_deref()
             .unwrap_or(text_field_indexing.tokenizer());
         tokenizer_manager
-            .get_tokenizer(tokenizer_name)
+            .get_query_tokenizer(tokenizer_name)
             .with_context(|| format!("no tokenizer named `{}` is registered", tokenizer_name))
     }
 
@@ -117,11 +117,24 @@ impl FullTextParams {
             let term = terms.pop().unwrap().1;
             return Ok(TantivyTermQuery::new(term, IndexRecordOption::WithFreqs).into());
         }
+        // Token filters such as the synonym filter may emit several terms at the same position,
+        // which are alternatives to one another.
+        let mut positions = group_terms_by_position(terms);
+
+        if positions.len() == 1 {
+            let (_, position_terms) = positions.pop().unwrap();
+            return Ok(make_position_query(
+                position_terms,
+                IndexRecordOption::WithFreqs,
+            ));
+        }
         match self.mode {
             FullTextMode::Bool { operator } => {
-                let leaf_queries: Vec<TantivyQueryAst> = terms
+                let leaf_queries: Vec<TantivyQueryAst> = positions
                     .into_iter()
-                    .map(|(_, term)| TantivyTermQuery::new(term, index_record_option).into())
+                    .map(|(_, position_terms)| {
+                        make_position_query(position_terms, index_record_option)
+                    })
                     .collect();
                 Ok(TantivyBoolQuery::build_clause(operator, leaf_queries).into())
             }
@@ -129,16 +142,25 @@ impl FullTextParams {
                 operator,
                 max_expansions,
             } => {
-                let term_with_prefix = terms.pop();
-                let mut leaf_queries: Vec<TantivyQueryAst> = terms
+                let position_with_prefix = positions.pop();
+                let mut leaf_queries: Vec<TantivyQueryAst> = positions
                     .into_iter()
-                    .map(|(_, term)| TantivyTermQuery::new(term, index_record_option).into())
+                    .map(|(_, position_terms)| {
+                        make_position_query(position_terms, index_record_option)
+                    })
                     .collect();
-                if let Some(term_with_prefix) = term_with_prefix {
-                    let mut phrase_prefix_query =
-                        TantivyPhrasePrefixQuery::new_with_offset(vec![term_with_prefix]);
-                    phrase_prefix_query.set_max_expansions(max_expansions);
-                    leaf_queries.push(phrase_prefix_query.into());
+                if let Some((position, terms_with_prefix)) = position_with_prefix {
+                    let phrase_prefix_queries: Vec<TantivyQueryAst> = terms_with_prefix
+                        .into_iter()
+                        .map(|term_with_prefix| {
+                            let mut phrase_prefix_query = TantivyPhrasePrefixQuery::new_with_offset(
+                                vec![(position, term_with_prefix)],
+                            );
+                            phrase_prefix_query.set_max_expansions(max_expansions);
+                            phrase_prefix_query.into()
+                        })
+                        .collect();
+                    leaf_queries.push(disjunction(phrase_prefix_queries));
                 }
                 Ok(TantivyBoolQuery::build_clause(operator, leaf_queries).into())
             }
@@ -149,17 +171,17 @@ impl FullTextParams {
                             .to_string(),
                     ));
                 }
-                let mut phrase_query = TantivyPhraseQuery::new_with_offset(terms);
-                phrase_query.set_slop(slop);
-                Ok(phrase_query.into())
+                make_phrase_query(positions, slop)
             }
             FullTextMode::PhraseFallbackToIntersection => {
                 if index_record_option.has_positions() {
-                    Ok(TantivyPhraseQuery::new_with_offset(terms).into())
+                    make_phrase_query(positions, 0)
                 } else {
-                    let term_query: Vec<TantivyQueryAst> = terms
+                    let term_query: Vec<TantivyQueryAst> = positions
                         .into_iter()
-                        .map(|(_, term)| TantivyTermQuery::new(term, index_record_option).into())
+                        .map(|(_, position_terms)| {
+                            make_position_query(position_terms, index_record_option)
+                        })
                         .collect();
                     Ok(TantivyBoolQuery::build_clause(BooleanOperand::And, term_query).into())
                 }
@@ -168,6 +190,80 @@ impl FullTextParams {
     }
 }
 
+/// Maximum number of phrases a phrase query with several terms at the same position can be
+/// expanded into.
+const MAX_PHRASE_EXPANSIONS: usize = 64;
+
+/// Groups the consecutive terms emitted at the same position.
+fn group_terms_by_position(terms: Vec<(usize, Term)>) -> Vec<(usize, Vec<Term>)> {
+    let mut positions: Vec<(usize, Vec<Term>)> = Vec::with_capacity(terms.len());
+
+    for (position, term) in terms {
+        match positions.last_mut() {
+            Some((last_position, last_terms)) if *last_position == position => {
+                last_terms.push(term);
+            }
+            _ => positions.push((position, vec![term])),
+        }
+    }
+    positions
+}
+
+fn disjunction(mut queries: Vec<TantivyQueryAst>) -> TantivyQueryAst {
+    if queries.len() == 1 {
+        return queries.pop().unwrap();
+    }
+    TantivyBoolQuery::build_clause(BooleanOperand::Or, queries).into()
+}
+
+/// Builds the query matching any of the terms emitted at a given position.
+fn make_position_query(
+    position_terms: Vec<Term>,
+    index_record_option: IndexRecordOption,
+) -> TantivyQueryAst {
+    let term_queries: Vec<TantivyQueryAst> = position_terms
+        .into_iter()
+        .map(|term| TantivyTermQuery::new(term, index_record_option).into())
+        .collect();
+    disjunction(term_queries)
+}
+
+/// Builds a phrase query for each combination of the terms emitted at the same position, and
+/// returns their disjunction.
+fn make_phrase_query(
+    positions: Vec<(usize, Vec<Term>)>,
+    slop: u32,
+) -> Result<TantivyQueryAst, InvalidQuery> {
+    let mut phrases: Vec<Vec<(usize, Term)>> = vec![Vec::with_capacity(positions.len())];
+
+    for (position, position_terms) in positions {
+        if phrases.len() * position_terms.len() > MAX_PHRASE_EXPANSIONS {
+            return Err(InvalidQuery::Other(anyhow::anyhow!(
+                "phrase query expands into more than {MAX_PHRASE_EXPANSIONS} phrases"
+            )));
+        }
+        phrases = phrases
+            .into_iter()
+            .flat_map(|phrase| {
+                position_terms.iter().map(move |term| {
+                    let mut phrase = phrase.clone();
+                    phrase.push((position, term.clone()));
+                    phrase
+                })
+            })
+            .collect();
+    }
+    let phrase_queries: Vec<TantivyQueryAst> = phrases
+        .into_iter()
+        .map(|phrase_terms| {
+            let mut phrase_query = TantivyPhraseQuery::new_with_offset(phrase_terms);
+            phrase_query.set_slop(slop);
+            phrase_query.into()
+        })
+        .collect();
+    Ok(disjunction(phrase_queries))
+}
+
 fn is_zero(val: &u32) -> bool {
     *val == 0u32
 }
@@ -256,27 +352,36 @@ impl BuildTantivyAst for FullTextQuery {
 }
 
 impl FullTextQuery {
-    /// Returns the last term of the query assuming the query is targeting a string or a Json
-    /// field.
+    /// Returns the terms emitted at the last position of the query assuming the query is
+    /// targeting a string or a Json field. There are several such terms if the tokenizer emits
+    /// synonyms.
     ///
-    /// This strange method is used to identify which term range should be warmed up for
+    /// This strange method is used to identify which term ranges should be warmed up for
     /// phrase prefix queries.
-    pub fn get_prefix_term(
+    pub fn get_prefix_terms(
         &self,
         schema: &TantivySchema,
         tokenizer_manager: &TokenizerManager,
-    ) -> Option<Term> {
+    ) -> Vec<Term> {
         if !matches!(self.params.mode, FullTextMode::BoolPrefix { .. }) {
-            return None;
+            return Vec::new();
         };
+        self.get_prefix_terms_aux(schema, tokenizer_manager)
+            .unwrap_or_default()
+    }
 
+    fn get_prefix_terms_aux(
+        &self,
+        schema: &TantivySchema,
+        tokenizer_manager: &TokenizerManager,
+    ) -> Option<Vec<Term>> {
         let (field, field_entry, json_path) =
             find_field_or_hit_dynamic(&self.field, schema).ok()?;
         let field_type: &FieldType = field_entry.field_type();
         match field_type {
             FieldType::Str(text_options) => {
                 let text_field_indexing = text_options.get_indexing_options()?;
-                let mut terms = self
+                let terms = self
                     .params
                     .tokenize_text_into_terms(
                         field,
@@ -285,11 +390,11 @@ impl FullTextQuery {
                         tokenizer_manager,
                     )
                     .ok()?;
-                let (_pos, term) = terms.pop()?;
-                Some(term)
+                let (_pos, prefix_terms) = group_terms_by_position(terms).pop()?;
+                Some(prefix_terms)
             }
             FieldType::JsonObject(ref json_options) => {
-                let mut terms = self
+                let terms = self
                     .params
                     .tokenize_text_into_terms_json(
                         field,
@@ -299,8 +404,8 @@ impl FullTextQuery {
                         tokenizer_manager,
                     )
                     .ok()?;
-                let (_pos, term) = terms.pop()?;
-                Some(term)
+                let (_pos, prefix_terms) = group_terms_by_position(terms).pop()?;
+                Some(prefix_terms)
             }
             _ => None,
         }
@@ -309,7 +414,8 @@ impl FullTextQuery {
 
 #[cfg(test)]
 mod tests {
-    use tantivy::schema::{Schema, TEXT};
+    use tantivy::schema::{IndexRecordOption, Schema, TEXT};
+    use tantivy::Term;
 
     use crate::query_ast::tantivy_query_ast::TantivyQueryAst;
     use crate::query_ast::{BuildTantivyAst, FullTextMode, FullTextQuery};
@@ -428,4 +534,46 @@ mod tests {
         let bool_query = ast.as_bool_query().unwrap();
         assert_eq!(bool_query.must.len(), 2);
     }
+
+    #[test]
+    fn test_make_query_with_terms_at_same_position() {
+        let mut schema_builder = Schema::builder();
+        let field = schema_builder.add_text_field("body", TEXT);
+        // "my auto", where the synonym filter emitted "wagen" at the position of "auto".
+        let terms = vec![
+            (0, Term::from_field_text(field, "my")),
+            (1, Term::from_field_text(field, "auto")),
+            (1, Term::from_field_text(field, "wagen")),
+        ];
+        let full_text_params = |mode: FullTextMode| super::FullTextParams {
+            tokenizer: None,
+            mode,
+            zero_terms_query: crate::MatchAllOrNone::MatchNone,
+        };
+        let ast = full_text_params(BooleanOperand::And.into())
+            .make_query(terms.clone(), IndexRecordOption::WithFreqsAndPositions)
+            .unwrap();
+        let bool_query = ast.as_bool_query().unwrap();
+        assert_eq!(bool_query.must.len(), 2);
+        assert!(bool_query.must[0].as_leaf().is_some());
+        assert_eq!(bool_query.must[1].as_bool_query().unwrap().should.len(), 2);
+
+        let ast = full_text_params(FullTextMode::Phrase { slop: 0 })
+            .make_query(terms.clone(), IndexRecordOption::WithFreqsAndPositions)
+            .unwrap();
+        let bool_query = ast.as_bool_query().unwrap();
+        assert_eq!(bool_query.should.len(), 2);
+        assert_eq!(
+            &format!("{:?}", bool_query.should[1].as_leaf().unwrap()),
+            "PhraseQuery { field: Field(0), phrase_terms: [(0, Term(field=0, type=Str, \"my\")), \
+             (1, Term(field=0, type=Str, \"wagen\"))], slop: 0 }"
+        );
+        let ast = full_text_params(FullTextMode::Phrase { slop: 0 })
+            .make_query(
+                terms[1..].to_vec(),
+                IndexRecordOption::WithFreqsAndPositions,
+            )
+            .unwrap();
+        assert_eq!(ast.as_bool_query().unwrap().should.len(), 2);
+    }
 }
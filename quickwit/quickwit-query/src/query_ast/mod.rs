@@ -26,6 +26,7 @@ use crate::tokenizers::TokenizerManager;
 mod bool_query;
 mod field_presence;
 mod full_text_query;
+mod nested_query;
 mod phrase_prefix_query;
 mod range_query;
 mod tantivy_query_ast;
@@ -39,6 +40,7 @@ mod wildcard_query;
 pub use bool_query::BoolQuery;
 pub use field_presence::FieldPresenceQuery;
 pub use full_text_query::{FullTextMode, FullTextParams, FullTextQuery};
+pub use nested_query::NestedQuery;
 pub use phrase_prefix_query::PhrasePrefixQuery;
 pub use range_query::RangeQuery;
 use tantivy_query_ast::TantivyQueryAst;
@@ -63,6 +65,7 @@ pub enum QueryAst {
     Range(RangeQuery),
     UserInput(UserInputQuery),
     Wildcard(WildcardQuery),
+    Nested(NestedQuery),
     MatchAll,
     MatchNone,
     Boost {
@@ -109,6 +112,14 @@ impl QueryAst {
             QueryAst::UserInput(user_text_query) => {
                 user_text_query.parse_user_query(default_search_fields)
             }
+            QueryAst::Nested(NestedQuery { path, query }) => {
+                let query = query.parse_user_query(default_search_fields)?;
+                Ok(NestedQuery {
+                    path,
+                    query: Box::new(query),
+                }
+                .into())
+            }
             QueryAst::Boost { underlying, boost } => {
                 let underlying = underlying.parse_user_query(default_search_fields)?;
                 Ok(QueryAst::Boost {
@@ -249,6 +260,12 @@ impl BuildTantivyAst for QueryAst {
                 search_fields,
                 with_validation,
             ),
+            QueryAst::Nested(nested_query) => nested_query.build_tantivy_ast_call(
+                schema,
+                tokenizer_manager,
+                search_fields,
+                with_validation,
+            ),
         }
     }
 }
@@ -0,0 +1,1159 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::net::Ipv6Addr;
+use std::ops::Bound;
+
+use quickwit_common::shared_consts::NESTED_FIELD_PREFIX;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tantivy::columnar::BytesColumn;
+use tantivy::query::{EmptyScorer, EnableScoring, Explanation, Query, Scorer, Weight};
+use tantivy::schema::{FieldType, Schema as TantivySchema};
+use tantivy::tokenizer::TextAnalyzer;
+use tantivy::{DocId, DocSet, Score, SegmentReader, TantivyError, Term, TERMINATED};
+
+use crate::json_literal::InterpretUserInput;
+use crate::query_ast::tantivy_query_ast::TantivyQueryAst;
+use crate::query_ast::{BoolQuery, BuildTantivyAst, FullTextMode, QueryAst};
+use crate::tokenizers::TokenizerManager;
+use crate::{BooleanOperand, InvalidQuery, JsonLiteral, MatchAllOrNone};
+
+/// The nested query matches documents for which at least one element of the `nested` field
+/// located at `path` matches the inner query.
+///
+/// Contrary to a query on a regular object field, the conditions of the inner query must
+/// all hold on the same element: `items.sku:A AND items.qty:>5` does not match a document where
+/// `sku` is `A` on one element and `qty` is greater than 5 on another one.
+///
+/// The fields targeted by the inner query must be expressed with their full path, and must belong
+/// to the nested field.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct NestedQuery {
+    pub path: String,
+    pub query: Box<QueryAst>,
+}
+
+impl From<NestedQuery> for QueryAst {
+    fn from(nested_query: NestedQuery) -> Self {
+        QueryAst::Nested(nested_query)
+    }
+}
+
+impl NestedQuery {
+    /// Returns the name of the field storing the elements of the nested field.
+    pub fn nested_field_name(&self) -> String {
+        format!("{NESTED_FIELD_PREFIX}{}", self.path)
+    }
+}
+
+impl BuildTantivyAst for NestedQuery {
+    fn build_tantivy_ast_impl(
+        &self,
+        schema: &TantivySchema,
+        tokenizer_manager: &TokenizerManager,
+        search_fields: &[String],
+        with_validation: bool,
+    ) -> Result<TantivyQueryAst, InvalidQuery> {
+        let nested_field_name = self.nested_field_name();
+        if schema.get_field(&nested_field_name).is_err() {
+            return Err(InvalidQuery::SchemaError(format!(
+                "field `{}` is not a nested field",
+                self.path
+            )));
+        }
+        let element_predicate =
+            ElementPredicate::build(&self.query, &self.path, schema, tokenizer_manager)?;
+        // The inner query is run against the flattened fields first, without its `must_not`
+        // clauses. It matches a superset of the documents we are looking for, which we then
+        // refine by checking the predicate element by element.
+        let candidate_query: Box<dyn Query> = without_must_not_clauses(&self.query)
+            .build_tantivy_ast_call(schema, tokenizer_manager, search_fields, with_validation)?
+            .simplify()
+            .into();
+        let nested_element_query = NestedElementQuery {
+            candidate_query,
+            nested_field_name,
+            element_predicate,
+        };
+        Ok(nested_element_query.into())
+    }
+}
+
+/// Returns a query matching a superset of the documents matched by `query_ast` on the flattened
+/// fields of a nested field.
+///
+/// `must_not` clauses cannot be evaluated on the flattened fields: an element matching a
+/// `must_not` clause excludes the whole document from the results of the flattened query, even
+/// if another element of the document matches the query.
+fn without_must_not_clauses(query_ast: &QueryAst) -> QueryAst {
+    match query_ast {
+        QueryAst::Bool(bool_query) => {
+            let strip_all =
+                |query_asts: &[QueryAst]| query_asts.iter().map(without_must_not_clauses).collect();
+            let candidate_bool_query = BoolQuery {
+                must: strip_all(&bool_query.must),
+                must_not: Vec::new(),
+                should: strip_all(&bool_query.should),
+                filter: strip_all(&bool_query.filter),
+                minimum_should_match: bool_query.minimum_should_match,
+            };
+            if candidate_bool_query.must.is_empty()
+                && candidate_bool_query.should.is_empty()
+                && candidate_bool_query.filter.is_empty()
+            {
+                return QueryAst::MatchAll;
+            }
+            candidate_bool_query.into()
+        }
+        QueryAst::Boost { underlying, boost } => QueryAst::Boost {
+            underlying: Box::new(without_must_not_clauses(underlying)),
+            boost: *boost,
+        },
+        _ => query_ast.clone(),
+    }
+}
+
+/// Typed value extracted from a nested element, or from a query literal.
+///
+/// Values are only ever compared with values of the same variant, as both sides are parsed
+/// according to the type of the targeted field.
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+enum ElementValue {
+    Bool(bool),
+    Number(f64),
+    Date(i64),
+    IpAddr(Ipv6Addr),
+    Bytes(Vec<u8>),
+    Str(String),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum ElementValueType {
+    Bool,
+    Number,
+    Date,
+    IpAddr,
+    Bytes,
+    Str,
+}
+
+impl ElementValueType {
+    fn parse(&self, literal: &JsonLiteral) -> Option<ElementValue> {
+        match self {
+            ElementValueType::Bool => bool::interpret_json(literal).map(ElementValue::Bool),
+            ElementValueType::Number => f64::interpret_json(literal).map(ElementValue::Number),
+            ElementValueType::Date => tantivy::DateTime::interpret_json(literal)
+                .map(|date_time| ElementValue::Date(date_time.into_timestamp_nanos())),
+            ElementValueType::IpAddr => Ipv6Addr::interpret_json(literal).map(ElementValue::IpAddr),
+            ElementValueType::Bytes => Vec::<u8>::interpret_json(literal).map(ElementValue::Bytes),
+            ElementValueType::Str => match literal {
+                JsonLiteral::String(text) => Some(ElementValue::Str(text.clone())),
+                JsonLiteral::Number(number) => Some(ElementValue::Str(number.to_string())),
+                JsonLiteral::Bool(bool_val) => Some(ElementValue::Str(bool_val.to_string())),
+            },
+        }
+    }
+}
+
+fn json_to_literal(json_value: &JsonValue) -> Option<JsonLiteral> {
+    match json_value {
+        JsonValue::Bool(bool_val) => Some(JsonLiteral::Bool(*bool_val)),
+        JsonValue::Number(number) => Some(JsonLiteral::Number(number.clone())),
+        JsonValue::String(text) => Some(JsonLiteral::String(text.clone())),
+        JsonValue::Null | JsonValue::Array(_) | JsonValue::Object(_) => None,
+    }
+}
+
+/// Collects the values located at `path` in `json_value`, opening arrays along the way.
+fn collect_values_at_path<'a>(
+    json_value: &'a JsonValue,
+    path: &[String],
+    values: &mut Vec<&'a JsonValue>,
+) {
+    match json_value {
+        JsonValue::Array(json_values) => {
+            for json_value in json_values {
+                collect_values_at_path(json_value, path, values);
+            }
+        }
+        JsonValue::Object(json_obj) => {
+            if let Some((first_segment, sub_path)) = path.split_first() {
+                if let Some(child_json_value) = json_obj.get(first_segment) {
+                    collect_values_at_path(child_json_value, sub_path, values);
+                }
+            } else {
+                values.push(json_value);
+            }
+        }
+        JsonValue::Null => {}
+        _ => {
+            if path.is_empty() {
+                values.push(json_value);
+            }
+        }
+    }
+}
+
+/// Splits a field path into its segments. Dots escaped with `\` do not count as separators.
+fn split_field_path(field_path: &str) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut current_segment = String::new();
+    let mut escaped = false;
+    for chr in field_path.chars() {
+        if escaped {
+            current_segment.push(chr);
+            escaped = false;
+        } else if chr == '\\' {
+            escaped = true;
+        } else if chr == '.' {
+            segments.push(std::mem::take(&mut current_segment));
+        } else {
+            current_segment.push(chr);
+        }
+    }
+    if !current_segment.is_empty() {
+        segments.push(current_segment);
+    }
+    segments
+}
+
+fn tokenize(text_analyzer: &mut TextAnalyzer, text: &str) -> Vec<String> {
+    let mut token_stream = text_analyzer.token_stream(text);
+    let mut tokens = Vec::new();
+    token_stream.process(&mut |token| tokens.push(token.text.clone()));
+    tokens
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum TokenMatchMode {
+    All,
+    Any,
+    Phrase,
+    PhrasePrefix,
+    /// All the tokens but the last one are matched as terms, the last one as a prefix, and the
+    /// clauses are combined with the operator.
+    BoolPrefix(BooleanOperand),
+}
+
+/// Predicate evaluated against the JSON elements of a nested field.
+#[derive(Clone)]
+enum ElementPredicate {
+    Const(MatchAllOrNone),
+    Bool {
+        must: Vec<ElementPredicate>,
+        must_not: Vec<ElementPredicate>,
+        should: Vec<ElementPredicate>,
+        minimum_should_match: usize,
+    },
+    Tokens {
+        path: Vec<String>,
+        text_analyzer: TextAnalyzer,
+        tokens: Vec<String>,
+        mode: TokenMatchMode,
+    },
+    Values {
+        path: Vec<String>,
+        value_type: ElementValueType,
+        values: Vec<ElementValue>,
+    },
+    Range {
+        path: Vec<String>,
+        value_type: ElementValueType,
+        lower_bound: Bound<ElementValue>,
+        upper_bound: Bound<ElementValue>,
+    },
+    Exists {
+        path: Vec<String>,
+    },
+    Nested {
+        path: Vec<String>,
+        predicate: Box<ElementPredicate>,
+    },
+}
+
+impl fmt::Debug for ElementPredicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ElementPredicate::Const(match_all_or_none) => {
+                f.debug_tuple("Const").field(match_all_or_none).finish()
+            }
+            ElementPredicate::Bool {
+                must,
+                must_not,
+                should,
+                minimum_should_match,
+            } => f
+                .debug_struct("Bool")
+                .field("must", must)
+                .field("must_not", must_not)
+                .field("should", should)
+                .field("minimum_should_match", minimum_should_match)
+                .finish(),
+            ElementPredicate::Tokens {
+                path, tokens, mode, ..
+            } => f
+                .debug_struct("Tokens")
+                .field("path", path)
+                .field("tokens", tokens)
+                .field("mode", mode)
+                .finish(),
+            ElementPredicate::Values { path, values, .. } => f
+                .debug_struct("Values")
+                .field("path", path)
+                .field("values", values)
+                .finish(),
+            ElementPredicate::Range {
+                path,
+                lower_bound,
+                upper_bound,
+                ..
+            } => f
+                .debug_struct("Range")
+                .field("path", path)
+                .field("lower_bound", lower_bound)
+                .field("upper_bound", upper_bound)
+                .finish(),
+            ElementPredicate::Exists { path } => {
+                f.debug_struct("Exists").field("path", path).finish()
+            }
+            ElementPredicate::Nested { path, predicate } => f
+                .debug_struct("Nested")
+                .field("path", path)
+                .field("predicate", predicate)
+                .finish(),
+        }
+    }
+}
+
+/// Resolves the type of the field targeted by a clause of the inner query, as well as its path
+/// relative to the nested element.
+fn resolve_element_field<'a>(
+    full_path: &str,
+    nested_path: &str,
+    schema: &'a TantivySchema,
+) -> Result<(Vec<String>, &'a FieldType), InvalidQuery> {
+    let nested_path_len = split_field_path(nested_path).len();
+    let field_path = split_field_path(full_path);
+    if field_path.len() <= nested_path_len
+        || split_field_path(nested_path)[..] != field_path[..nested_path_len]
+    {
+        return Err(InvalidQuery::SchemaError(format!(
+            "field `{full_path}` does not belong to nested field `{nested_path}`"
+        )));
+    }
+    let field = schema
+        .get_field(full_path)
+        .map_err(|_| InvalidQuery::FieldDoesNotExist {
+            full_path: full_path.to_string(),
+        })?;
+    let field_type = schema.get_field_entry(field).field_type();
+    Ok((field_path[nested_path_len..].to_vec(), field_type))
+}
+
+fn element_value_type(field_type: &FieldType) -> Result<ElementValueType, InvalidQuery> {
+    let value_type = match field_type {
+        FieldType::Bool(_) => ElementValueType::Bool,
+        FieldType::U64(_) | FieldType::I64(_) | FieldType::F64(_) => ElementValueType::Number,
+        FieldType::Date(_) => ElementValueType::Date,
+        FieldType::IpAddr(_) => ElementValueType::IpAddr,
+        FieldType::Bytes(_) => ElementValueType::Bytes,
+        FieldType::Str(_) => ElementValueType::Str,
+        FieldType::JsonObject(_) | FieldType::Facet(_) => {
+            return Err(InvalidQuery::SchemaError(
+                "json and facet fields are not supported inside nested queries".to_string(),
+            ));
+        }
+    };
+    Ok(value_type)
+}
+
+fn field_text_analyzer(
+    field_type: &FieldType,
+    tokenizer_override: Option<&str>,
+    tokenizer_manager: &TokenizerManager,
+) -> Result<Option<TextAnalyzer>, InvalidQuery> {
+    let FieldType::Str(text_options) = field_type else {
+        return Ok(None);
+    };
+    let text_field_indexing = text_options.get_indexing_options().ok_or_else(|| {
+        InvalidQuery::SchemaError("field is not full-text searchable".to_string())
+    })?;
+    let tokenizer_name = tokenizer_override.unwrap_or(text_field_indexing.tokenizer());
+    let text_analyzer = tokenizer_manager
+        .get_query_tokenizer(tokenizer_name)
+        .ok_or_else(|| {
+            InvalidQuery::SchemaError(format!("no tokenizer named `{tokenizer_name}`"))
+        })?;
+    Ok(Some(text_analyzer))
+}
+
+impl ElementPredicate {
+    fn build(
+        query_ast: &QueryAst,
+        nested_path: &str,
+        schema: &TantivySchema,
+        tokenizer_manager: &TokenizerManager,
+    ) -> Result<ElementPredicate, InvalidQuery> {
+        let build_all = |query_asts: &[QueryAst]| -> Result<Vec<ElementPredicate>, InvalidQuery> {
+            query_asts
+                .iter()
+                .map(|query_ast| {
+                    ElementPredicate::build(query_ast, nested_path, schema, tokenizer_manager)
+                })
+                .collect()
+        };
+        match query_ast {
+            QueryAst::MatchAll => Ok(ElementPredicate::Const(MatchAllOrNone::MatchAll)),
+            QueryAst::MatchNone => Ok(ElementPredicate::Const(MatchAllOrNone::MatchNone)),
+            QueryAst::Boost { underlying, .. } => {
+                ElementPredicate::build(underlying, nested_path, schema, tokenizer_manager)
+            }
+            QueryAst::Bool(bool_query) => {
+                let mut must = build_all(&bool_query.must)?;
+                must.extend(build_all(&bool_query.filter)?);
+                let must_not = build_all(&bool_query.must_not)?;
+                let should = build_all(&bool_query.should)?;
+                let default_minimum_should_match = if must.is_empty() && !should.is_empty() {
+                    1
+                } else {
+                    0
+                };
+                let minimum_should_match = bool_query
+                    .minimum_should_match
+                    .unwrap_or(default_minimum_should_match);
+                Ok(ElementPredicate::Bool {
+                    must,
+                    must_not,
+                    should,
+                    minimum_should_match,
+                })
+            }
+            QueryAst::Term(term_query) => Self::build_term(
+                &term_query.field,
+                &term_query.value,
+                nested_path,
+                schema,
+                tokenizer_manager,
+            ),
+            QueryAst::TermSet(term_set_query) => {
+                let mut should = Vec::new();
+                for (field, values) in &term_set_query.terms_per_field {
+                    for value in values {
+                        should.push(Self::build_term(
+                            field,
+                            value,
+                            nested_path,
+                            schema,
+                            tokenizer_manager,
+                        )?);
+                    }
+                }
+                Ok(ElementPredicate::Bool {
+                    must: Vec::new(),
+                    must_not: Vec::new(),
+                    should,
+                    minimum_should_match: 1,
+                })
+            }
+            QueryAst::FullText(full_text_query) => {
+                let (path, field_type) =
+                    resolve_element_field(&full_text_query.field, nested_path, schema)?;
+                let Some(mut query_text_analyzer) = field_text_analyzer(
+                    field_type,
+                    full_text_query.params.tokenizer.as_deref(),
+                    tokenizer_manager,
+                )?
+                else {
+                    return Self::build_term(
+                        &full_text_query.field,
+                        &full_text_query.text,
+                        nested_path,
+                        schema,
+                        tokenizer_manager,
+                    );
+                };
+                let text_analyzer = field_text_analyzer(field_type, None, tokenizer_manager)?
+                    .expect("field should be a text field");
+                let tokens = tokenize(&mut query_text_analyzer, &full_text_query.text);
+                if tokens.is_empty() {
+                    return Ok(ElementPredicate::Const(
+                        full_text_query.params.zero_terms_query,
+                    ));
+                }
+                let mode = match full_text_query.params.mode {
+                    FullTextMode::Bool {
+                        operator: BooleanOperand::And,
+                    } => TokenMatchMode::All,
+                    FullTextMode::Bool {
+                        operator: BooleanOperand::Or,
+                    } => TokenMatchMode::Any,
+                    FullTextMode::BoolPrefix { operator, .. } => {
+                        TokenMatchMode::BoolPrefix(operator)
+                    }
+                    FullTextMode::Phrase { .. } | FullTextMode::PhraseFallbackToIntersection => {
+                        TokenMatchMode::Phrase
+                    }
+                };
+                Ok(ElementPredicate::Tokens {
+                    path,
+                    text_analyzer,
+                    tokens,
+                    mode,
+                })
+            }
+            QueryAst::PhrasePrefix(phrase_prefix_query) => {
+                let (path, field_type) =
+                    resolve_element_field(&phrase_prefix_query.field, nested_path, schema)?;
+                let Some(mut query_text_analyzer) = field_text_analyzer(
+                    field_type,
+                    phrase_prefix_query.params.tokenizer.as_deref(),
+                    tokenizer_manager,
+                )?
+                else {
+                    return Err(InvalidQuery::SchemaError(
+                        "trying to run a PhrasePrefix query on a non-text field".to_string(),
+                    ));
+                };
+                let text_analyzer = field_text_analyzer(field_type, None, tokenizer_manager)?
+                    .expect("field should be a text field");
+                let tokens = tokenize(&mut query_text_analyzer, &phrase_prefix_query.phrase);
+                if tokens.is_empty() {
+                    return Ok(ElementPredicate::Const(
+                        phrase_prefix_query.params.zero_terms_query,
+                    ));
+                }
+                Ok(ElementPredicate::Tokens {
+                    path,
+                    text_analyzer,
+                    tokens,
+                    mode: TokenMatchMode::PhrasePrefix,
+                })
+            }
+            QueryAst::Range(range_query) => {
+                let (path, field_type) =
+                    resolve_element_field(&range_query.field, nested_path, schema)?;
+                let value_type = element_value_type(field_type)?;
+                let convert_bound =
+                    |bound: &Bound<JsonLiteral>| -> Result<Bound<ElementValue>, InvalidQuery> {
+                        let invalid_boundary = || InvalidQuery::InvalidBoundary {
+                            expected_value_type: "value matching the field type",
+                            field_name: range_query.field.clone(),
+                        };
+                        match bound {
+                            Bound::Included(literal) => value_type
+                                .parse(literal)
+                                .map(Bound::Included)
+                                .ok_or_else(invalid_boundary),
+                            Bound::Excluded(literal) => value_type
+                                .parse(literal)
+                                .map(Bound::Excluded)
+                                .ok_or_else(invalid_boundary),
+                            Bound::Unbounded => Ok(Bound::Unbounded),
+                        }
+                    };
+                Ok(ElementPredicate::Range {
+                    path,
+                    value_type,
+                    lower_bound: convert_bound(&range_query.lower_bound)?,
+                    upper_bound: convert_bound(&range_query.upper_bound)?,
+                })
+            }
+            QueryAst::FieldPresence(field_presence_query) => {
+                let field_path = split_field_path(&field_presence_query.field);
+                let nested_path_segments = split_field_path(nested_path);
+                if field_path.len() <= nested_path_segments.len()
+                    || field_path[..nested_path_segments.len()] != nested_path_segments[..]
+                {
+                    return Err(InvalidQuery::SchemaError(format!(
+                        "field `{}` does not belong to nested field `{nested_path}`",
+                        field_presence_query.field
+                    )));
+                }
+                Ok(ElementPredicate::Exists {
+                    path: field_path[nested_path_segments.len()..].to_vec(),
+                })
+            }
+            QueryAst::Nested(nested_query) => {
+                let nested_path_segments = split_field_path(nested_path);
+                let inner_path_segments = split_field_path(&nested_query.path);
+                if inner_path_segments.len() <= nested_path_segments.len()
+                    || inner_path_segments[..nested_path_segments.len()] != nested_path_segments[..]
+                {
+                    return Err(InvalidQuery::SchemaError(format!(
+                        "nested path `{}` does not belong to nested field `{nested_path}`",
+                        nested_query.path
+                    )));
+                }
+                let predicate = ElementPredicate::build(
+                    &nested_query.query,
+                    &nested_query.path,
+                    schema,
+                    tokenizer_manager,
+                )?;
+                Ok(ElementPredicate::Nested {
+                    path: inner_path_segments[nested_path_segments.len()..].to_vec(),
+                    predicate: Box::new(predicate),
+                })
+            }
+            QueryAst::Wildcard(_) => Err(InvalidQuery::SchemaError(
+                "wildcard queries are not supported inside nested queries".to_string(),
+            )),
+            QueryAst::UserInput(_) => Err(InvalidQuery::UserQueryNotParsed),
+        }
+    }
+
+    fn build_term(
+        full_path: &str,
+        value: &str,
+        nested_path: &str,
+        schema: &TantivySchema,
+        tokenizer_manager: &TokenizerManager,
+    ) -> Result<ElementPredicate, InvalidQuery> {
+        let (path, field_type) = resolve_element_field(full_path, nested_path, schema)?;
+        // Just like `TermQuery`, the value itself is not tokenized, but it has to match one of the
+        // tokens emitted by the tokenizer of the field.
+        if let Some(text_analyzer) = field_text_analyzer(field_type, None, tokenizer_manager)? {
+            return Ok(ElementPredicate::Tokens {
+                path,
+                text_analyzer,
+                tokens: vec![value.to_string()],
+                mode: TokenMatchMode::Any,
+            });
+        }
+        let value_type = element_value_type(field_type)?;
+        let value = value_type
+            .parse(&JsonLiteral::String(value.to_string()))
+            .ok_or_else(|| InvalidQuery::InvalidSearchTerm {
+                expected_value_type: "value matching the field type",
+                field_name: full_path.to_string(),
+                value: value.to_string(),
+            })?;
+        Ok(ElementPredicate::Values {
+            path,
+            value_type,
+            values: vec![value],
+        })
+    }
+
+    /// Returns true if the element matches the predicate.
+    fn matches(&mut self, element: &JsonValue) -> bool {
+        match self {
+            ElementPredicate::Const(match_all_or_none) => !match_all_or_none.is_none(),
+            ElementPredicate::Bool {
+                must,
+                must_not,
+                should,
+                minimum_should_match,
+            } => {
+                if !must.iter_mut().all(|predicate| predicate.matches(element)) {
+                    return false;
+                }
+                if must_not
+                    .iter_mut()
+                    .any(|predicate| predicate.matches(element))
+                {
+                    return false;
+                }
+                if *minimum_should_match == 0 {
+                    return true;
+                }
+                let mut num_matching_should = 0;
+                for predicate in should.iter_mut() {
+                    if predicate.matches(element) {
+                        num_matching_should += 1;
+                        if num_matching_should >= *minimum_should_match {
+                            return true;
+                        }
+                    }
+                }
+                false
+            }
+            ElementPredicate::Tokens {
+                path,
+                text_analyzer,
+                tokens,
+                mode,
+            } => {
+                let mut json_values = Vec::new();
+                collect_values_at_path(element, path, &mut json_values);
+                json_values.into_iter().any(|json_value| {
+                    let Some(text) = json_value.as_str() else {
+                        return false;
+                    };
+                    let element_tokens = tokenize(text_analyzer, text);
+                    tokens_match(&element_tokens, tokens, *mode)
+                })
+            }
+            ElementPredicate::Values {
+                path,
+                value_type,
+                values,
+            } => {
+                let mut json_values = Vec::new();
+                collect_values_at_path(element, path, &mut json_values);
+                json_values.into_iter().any(|json_value| {
+                    json_to_literal(json_value)
+                        .and_then(|literal| value_type.parse(&literal))
+                        .map(|element_value| values.contains(&element_value))
+                        .unwrap_or(false)
+                })
+            }
+            ElementPredicate::Range {
+                path,
+                value_type,
+                lower_bound,
+                upper_bound,
+            } => {
+                let mut json_values = Vec::new();
+                collect_values_at_path(element, path, &mut json_values);
+                json_values.into_iter().any(|json_value| {
+                    let Some(element_value) =
+                        json_to_literal(json_value).and_then(|literal| value_type.parse(&literal))
+                    else {
+                        return false;
+                    };
+                    let above_lower_bound = match lower_bound {
+                        Bound::Included(lower) => &element_value >= lower,
+                        Bound::Excluded(lower) => &element_value > lower,
+                        Bound::Unbounded => true,
+                    };
+                    let below_upper_bound = match upper_bound {
+                        Bound::Included(upper) => &element_value <= upper,
+                        Bound::Excluded(upper) => &element_value < upper,
+                        Bound::Unbounded => true,
+                    };
+                    above_lower_bound && below_upper_bound
+                })
+            }
+            ElementPredicate::Exists { path } => {
+                let mut json_values = Vec::new();
+                collect_values_at_path(element, path, &mut json_values);
+                !json_values.is_empty()
+            }
+            ElementPredicate::Nested { path, predicate } => {
+                let mut json_values = Vec::new();
+                collect_values_at_path(element, path, &mut json_values);
+                json_values
+                    .into_iter()
+                    .any(|sub_element| predicate.matches(sub_element))
+            }
+        }
+    }
+}
+
+fn tokens_match(element_tokens: &[String], query_tokens: &[String], mode: TokenMatchMode) -> bool {
+    match mode {
+        TokenMatchMode::Any => query_tokens
+            .iter()
+            .any(|query_token| element_tokens.contains(query_token)),
+        TokenMatchMode::All => query_tokens
+            .iter()
+            .all(|query_token| element_tokens.contains(query_token)),
+        TokenMatchMode::Phrase => element_tokens
+            .windows(query_tokens.len())
+            .any(|window| window == query_tokens),
+        TokenMatchMode::PhrasePrefix => {
+            let Some((prefix, phrase)) = query_tokens.split_last() else {
+                return false;
+            };
+            if element_tokens.len() < query_tokens.len() {
+                return false;
+            }
+            element_tokens.windows(query_tokens.len()).any(|window| {
+                window[..phrase.len()] == *phrase
+                    && window[phrase.len()].starts_with(prefix.as_str())
+            })
+        }
+        TokenMatchMode::BoolPrefix(operator) => {
+            let Some((prefix, terms)) = query_tokens.split_last() else {
+                return false;
+            };
+            let prefix_match = element_tokens
+                .iter()
+                .any(|element_token| element_token.starts_with(prefix.as_str()));
+            let mut clause_matches = terms
+                .iter()
+                .map(|term| element_tokens.contains(term))
+                .chain(std::iter::once(prefix_match));
+            match operator {
+                BooleanOperand::And => clause_matches.all(|clause_match| clause_match),
+                BooleanOperand::Or => clause_matches.any(|clause_match| clause_match),
+            }
+        }
+    }
+}
+
+/// Tantivy query refining the documents matched by `candidate_query` by only keeping those
+/// that have at least one nested element matching `element_predicate`.
+///
+/// The elements are read from the fast field storing them, so that the query does not need to
+/// access the doc store.
+#[derive(Debug)]
+struct NestedElementQuery {
+    candidate_query: Box<dyn Query>,
+    nested_field_name: String,
+    element_predicate: ElementPredicate,
+}
+
+impl Clone for NestedElementQuery {
+    fn clone(&self) -> Self {
+        NestedElementQuery {
+            candidate_query: self.candidate_query.box_clone(),
+            nested_field_name: self.nested_field_name.clone(),
+            element_predicate: self.element_predicate.clone(),
+        }
+    }
+}
+
+impl Query for NestedElementQuery {
+    fn weight(&self, enable_scoring: EnableScoring<'_>) -> tantivy::Result<Box<dyn Weight>> {
+        let candidate_weight = self.candidate_query.weight(enable_scoring)?;
+        Ok(Box::new(NestedElementWeight {
+            candidate_weight,
+            nested_field_name: self.nested_field_name.clone(),
+            element_predicate: self.element_predicate.clone(),
+        }))
+    }
+
+    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
+        self.candidate_query.query_terms(visitor);
+    }
+}
+
+struct NestedElementWeight {
+    candidate_weight: Box<dyn Weight>,
+    nested_field_name: String,
+    element_predicate: ElementPredicate,
+}
+
+impl Weight for NestedElementWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> tantivy::Result<Box<dyn Scorer>> {
+        let Some(elements_column) = reader.fast_fields().bytes(&self.nested_field_name)? else {
+            return Ok(Box::new(EmptyScorer));
+        };
+        let candidate_scorer = self.candidate_weight.scorer(reader, boost)?;
+        let mut nested_element_scorer = NestedElementScorer {
+            candidate_scorer,
+            elements_column,
+            element_predicate: self.element_predicate.clone(),
+            buffer: Vec::new(),
+        };
+        nested_element_scorer.skip_non_matching();
+        Ok(Box::new(nested_element_scorer))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> tantivy::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(TantivyError::InvalidArgument(format!(
+                "document #({doc}) does not match"
+            )));
+        }
+        Ok(Explanation::new("NestedElementQuery", scorer.score()))
+    }
+}
+
+struct NestedElementScorer {
+    candidate_scorer: Box<dyn Scorer>,
+    elements_column: BytesColumn,
+    element_predicate: ElementPredicate,
+    buffer: Vec<u8>,
+}
+
+impl NestedElementScorer {
+    fn doc_matches(&mut self, doc: DocId) -> bool {
+        for term_ord in self.elements_column.term_ords(doc).collect::<Vec<u64>>() {
+            self.buffer.clear();
+            if !matches!(
+                self.elements_column
+                    .ord_to_bytes(term_ord, &mut self.buffer),
+                Ok(true)
+            ) {
+                continue;
+            }
+            let Ok(element) = serde_json::from_slice::<JsonValue>(&self.buffer) else {
+                continue;
+            };
+            if self.element_predicate.matches(&element) {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn skip_non_matching(&mut self) -> DocId {
+        let mut doc = self.candidate_scorer.doc();
+        while doc != TERMINATED && !self.doc_matches(doc) {
+            doc = self.candidate_scorer.advance();
+        }
+        doc
+    }
+}
+
+impl DocSet for NestedElementScorer {
+    fn advance(&mut self) -> DocId {
+        self.candidate_scorer.advance();
+        self.skip_non_matching()
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        self.candidate_scorer.seek(target);
+        self.skip_non_matching()
+    }
+
+    fn doc(&self) -> DocId {
+        self.candidate_scorer.doc()
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.candidate_scorer.size_hint()
+    }
+}
+
+impl Scorer for NestedElementScorer {
+    fn score(&mut self) -> Score {
+        self.candidate_scorer.score()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tantivy::schema::{Schema, FAST, INDEXED, STRING, TEXT};
+
+    use super::*;
+    use crate::create_default_quickwit_tokenizer_manager;
+    use crate::query_ast::{qast_helper, BoolQuery, RangeQuery, TermQuery};
+
+    fn make_schema() -> Schema {
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_text_field("items.sku", STRING);
+        schema_builder.add_text_field("items.description", TEXT);
+        schema_builder.add_u64_field("items.qty", INDEXED | FAST);
+        schema_builder.add_text_field("items.tags.name", STRING);
+        schema_builder.add_bytes_field("_nested.items", FAST);
+        schema_builder.add_bytes_field("_nested.items.tags", FAST);
+        schema_builder.build()
+    }
+
+    fn build_predicate(query_ast: &QueryAst, nested_path: &str) -> ElementPredicate {
+        ElementPredicate::build(
+            query_ast,
+            nested_path,
+            &make_schema(),
+            &create_default_quickwit_tokenizer_manager(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_nested_query_serialization() {
+        let nested_query: QueryAst = NestedQuery {
+            path: "items".to_string(),
+            query: Box::new(QueryAst::MatchAll),
+        }
+        .into();
+        let nested_query_json = serde_json::to_value(&nested_query).unwrap();
+        assert_eq!(
+            nested_query_json,
+            json!({
+                "type": "nested",
+                "path": "items",
+                "query": {"type": "match_all"}
+            })
+        );
+        let nested_query_deser: QueryAst = serde_json::from_value(nested_query_json).unwrap();
+        assert_eq!(nested_query_deser, nested_query);
+    }
+
+    #[test]
+    fn test_nested_query_requires_nested_field() {
+        let nested_query = NestedQuery {
+            path: "items.sku".to_string(),
+            query: Box::new(QueryAst::MatchAll),
+        };
+        let error = nested_query
+            .build_tantivy_ast_call(
+                &make_schema(),
+                &create_default_quickwit_tokenizer_manager(),
+                &[],
+                true,
+            )
+            .unwrap_err();
+        assert!(matches!(error, InvalidQuery::SchemaError(_)));
+    }
+
+    #[test]
+    fn test_element_predicate_conjunction_on_same_element() {
+        let query_ast = qast_helper("items.sku:A AND items.qty:>5", &[]);
+        let mut predicate = build_predicate(&query_ast, "items");
+        assert!(predicate.matches(&json!({"sku": "A", "qty": 6})));
+        assert!(!predicate.matches(&json!({"sku": "A", "qty": 5})));
+        assert!(!predicate.matches(&json!({"sku": "B", "qty": 6})));
+        assert!(!predicate.matches(&json!({"qty": 6})));
+    }
+
+    #[test]
+    fn test_element_predicate_full_text() {
+        let query_ast = qast_helper("items.description:\"red shoe\"", &[]);
+        let mut predicate = build_predicate(&query_ast, "items");
+        assert!(predicate.matches(&json!({"description": "A Red Shoe for running"})));
+        assert!(!predicate.matches(&json!({"description": "A red running shoe"})));
+
+        let query_ast = qast_helper("items.description:red items.description:shoe", &[]);
+        let mut predicate = build_predicate(&query_ast, "items");
+        assert!(predicate.matches(&json!({"description": "A red running shoe"})));
+        assert!(!predicate.matches(&json!({"description": "A red running sock"})));
+    }
+
+    #[test]
+    fn test_element_predicate_bool_and_range() {
+        let query_ast: QueryAst = BoolQuery {
+            must: vec![RangeQuery {
+                field: "items.qty".to_string(),
+                lower_bound: Bound::Included(JsonLiteral::Number(2u64.into())),
+                upper_bound: Bound::Excluded(JsonLiteral::String("4".to_string())),
+            }
+            .into()],
+            must_not: vec![TermQuery {
+                field: "items.sku".to_string(),
+                value: "C".to_string(),
+            }
+            .into()],
+            ..Default::default()
+        }
+        .into();
+        let mut predicate = build_predicate(&query_ast, "items");
+        assert!(predicate.matches(&json!({"sku": "A", "qty": 2})));
+        assert!(predicate.matches(&json!({"sku": "A", "qty": "3"})));
+        assert!(!predicate.matches(&json!({"sku": "A", "qty": 4})));
+        assert!(!predicate.matches(&json!({"sku": "C", "qty": 3})));
+    }
+
+    #[test]
+    fn test_element_predicate_exists_and_nested() {
+        let query_ast: QueryAst = NestedQuery {
+            path: "items.tags".to_string(),
+            query: Box::new(
+                TermQuery {
+                    field: "items.tags.name".to_string(),
+                    value: "sale".to_string(),
+                }
+                .into(),
+            ),
+        }
+        .into();
+        let mut predicate = build_predicate(&query_ast, "items");
+        assert!(predicate.matches(&json!({"tags": [{"name": "new"}, {"name": "sale"}]})));
+        assert!(!predicate.matches(&json!({"tags": [{"name": "new"}]})));
+
+        let query_ast = QueryAst::FieldPresence(crate::query_ast::FieldPresenceQuery {
+            field: "items.qty".to_string(),
+        });
+        let mut predicate = build_predicate(&query_ast, "items");
+        assert!(predicate.matches(&json!({"qty": 0})));
+        assert!(!predicate.matches(&json!({"qty": null})));
+        assert!(!predicate.matches(&json!({"sku": "A"})));
+    }
+
+    #[test]
+    fn test_element_predicate_rejects_foreign_fields() {
+        let query_ast = qast_helper("items.sku:A AND other:B", &[]);
+        let error = ElementPredicate::build(
+            &query_ast,
+            "items",
+            &make_schema(),
+            &create_default_quickwit_tokenizer_manager(),
+        )
+        .unwrap_err();
+        assert!(matches!(error, InvalidQuery::SchemaError(_)));
+    }
+
+    #[test]
+    fn test_tokens_match_phrase_prefix() {
+        let element_tokens = vec!["red".to_string(), "running".to_string(), "shoe".to_string()];
+        assert!(tokens_match(
+            &element_tokens,
+            &["red".to_string(), "run".to_string()],
+            TokenMatchMode::PhrasePrefix
+        ));
+        assert!(!tokens_match(
+            &element_tokens,
+            &["red".to_string(), "sh".to_string()],
+            TokenMatchMode::PhrasePrefix
+        ));
+    }
+
+    #[test]
+    fn test_tokens_match_bool_prefix() {
+        let element_tokens = vec!["red".to_string(), "running".to_string(), "shoe".to_string()];
+        assert!(tokens_match(
+            &element_tokens,
+            &["shoe".to_string(), "ru".to_string()],
+            TokenMatchMode::BoolPrefix(BooleanOperand::And)
+        ));
+        assert!(!tokens_match(
+            &element_tokens,
+            &["sock".to_string(), "ru".to_string()],
+            TokenMatchMode::BoolPrefix(BooleanOperand::And)
+        ));
+        assert!(tokens_match(
+            &element_tokens,
+            &["sock".to_string(), "ru".to_string()],
+            TokenMatchMode::BoolPrefix(BooleanOperand::Or)
+        ));
+        assert!(!tokens_match(
+            &element_tokens,
+            &["sock".to_string(), "bl".to_string()],
+            TokenMatchMode::BoolPrefix(BooleanOperand::Or)
+        ));
+    }
+
+    #[test]
+    fn test_without_must_not_clauses() {
+        let sku_query = |sku: &str| -> QueryAst {
+            TermQuery {
+                field: "items.sku".to_string(),
+                value: sku.to_string(),
+            }
+            .into()
+        };
+        let query_ast: QueryAst = BoolQuery {
+            must: vec![sku_query("A")],
+            must_not: vec![sku_query("C")],
+            ..Default::default()
+        }
+        .into();
+        let expected_query_ast: QueryAst = BoolQuery {
+            must: vec![sku_query("A")],
+            ..Default::default()
+        }
+        .into();
+        assert_eq!(without_must_not_clauses(&query_ast), expected_query_ast);
+
+        let query_ast: QueryAst = BoolQuery {
+            must_not: vec![sku_query("C")],
+            ..Default::default()
+        }
+        .into();
+        assert_eq!(without_must_not_clauses(&query_ast), QueryAst::MatchAll);
+    }
+}
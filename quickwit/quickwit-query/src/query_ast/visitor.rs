@@ -21,8 +21,8 @@ use crate::not_nan_f32::NotNaNf32;
 use crate::query_ast::field_presence::FieldPresenceQuery;
 use crate::query_ast::user_input_query::UserInputQuery;
 use crate::query_ast::{
-    BoolQuery, FullTextQuery, PhrasePrefixQuery, QueryAst, RangeQuery, TermQuery, TermSetQuery,
-    WildcardQuery,
+    BoolQuery, FullTextQuery, NestedQuery, PhrasePrefixQuery, QueryAst, RangeQuery, TermQuery,
+    TermSetQuery, WildcardQuery,
 };
 
 /// Simple trait to implement a Visitor over the QueryAst.
@@ -45,6 +45,7 @@ pub trait QueryAstVisitor<'a> {
             QueryAst::UserInput(user_text_query) => self.visit_user_text(user_text_query),
             QueryAst::FieldPresence(exists) => self.visit_exists(exists),
             QueryAst::Wildcard(wildcard) => self.visit_wildcard(wildcard),
+            QueryAst::Nested(nested_query) => self.visit_nested(nested_query),
         }
     }
 
@@ -111,6 +112,10 @@ pub trait QueryAstVisitor<'a> {
     fn visit_wildcard(&mut self, _wildcard_query: &'a WildcardQuery) -> Result<(), Self::Err> {
         Ok(())
     }
+
+    fn visit_nested(&mut self, nested_query: &'a NestedQuery) -> Result<(), Self::Err> {
+        self.visit(&nested_query.query)
+    }
 }
 
 /// Simple trait to implement a Visitor over the QueryAst.
@@ -133,6 +138,7 @@ pub trait QueryAstTransformer {
             QueryAst::UserInput(user_text_query) => self.transform_user_text(user_text_query),
             QueryAst::FieldPresence(exists) => self.transform_exists(exists),
             QueryAst::Wildcard(wildcard) => self.transform_wildcard(wildcard),
+            QueryAst::Nested(nested_query) => self.transform_nested(nested_query),
         }
     }
 
@@ -231,4 +237,19 @@ pub trait QueryAstTransformer {
     ) -> Result<Option<QueryAst>, Self::Err> {
         Ok(Some(QueryAst::Wildcard(wildcard_query)))
     }
+
+    fn transform_nested(
+        &mut self,
+        nested_query: NestedQuery,
+    ) -> Result<Option<QueryAst>, Self::Err> {
+        let NestedQuery { path, query } = nested_query;
+        self.transform(*query).map(|maybe_ast| {
+            maybe_ast.map(|query| {
+                QueryAst::Nested(NestedQuery {
+                    path,
+                    query: Box::new(query),
+                })
+            })
+        })
+    }
 }
@@ -21,7 +21,9 @@ mod chinese_compatible;
 mod code_tokenizer;
 #[cfg(feature = "multilang")]
 mod multilang;
+mod synonym_filter;
 mod tokenizer_manager;
+mod word_delimiter_filter;
 
 use once_cell::sync::Lazy;
 use tantivy::tokenizer::{
@@ -33,7 +35,9 @@ use self::chinese_compatible::ChineseTokenizer;
 pub use self::code_tokenizer::CodeTokenizer;
 #[cfg(feature = "multilang")]
 pub use self::multilang::MultiLangTokenizer;
+pub use self::synonym_filter::SynonymFilter;
 pub use self::tokenizer_manager::TokenizerManager;
+pub use self::word_delimiter_filter::WordDelimiterFilter;
 
 pub const DEFAULT_REMOVE_TOKEN_LENGTH: usize = 255;
 
@@ -0,0 +1,213 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::bail;
+use tantivy::tokenizer::{Token, TokenFilter, TokenStream, Tokenizer};
+
+/// A token filter replacing tokens with their synonyms, emitted at the same position.
+///
+/// The synonyms are defined by rules in the Solr format:
+/// - `car, automobile, auto` declares equivalent words: each of them is expanded into all of them.
+/// - `tv, television => television` declares an explicit mapping: the words on the left-hand side
+///   are replaced by the words on the right-hand side.
+///
+/// Only single-word synonyms are supported. Rules are matched against the tokens produced by the
+/// previous filters, so they should be lowercase if the filter follows a lowercaser.
+#[derive(Clone)]
+pub struct SynonymFilter {
+    synonyms: Arc<HashMap<String, Vec<String>>>,
+}
+
+impl SynonymFilter {
+    /// Builds a synonym filter from rules in the Solr format. Empty rules and rules starting with
+    /// `#` are ignored.
+    pub fn from_rules<'a>(rules: impl IntoIterator<Item = &'a str>) -> anyhow::Result<Self> {
+        let mut synonyms: HashMap<String, Vec<String>> = HashMap::new();
+
+        for rule in rules {
+            let rule = rule.trim();
+
+            if rule.is_empty() || rule.starts_with('#') {
+                continue;
+            }
+            let (words, replacements) = if let Some((lhs, rhs)) = rule.split_once("=>") {
+                (parse_words(lhs, rule)?, parse_words(rhs, rule)?)
+            } else {
+                let words = parse_words(rule, rule)?;
+                if words.len() < 2 {
+                    bail!("synonym rule `{rule}` must contain at least two words");
+                }
+                (words.clone(), words)
+            };
+            for word in words {
+                let word_synonyms = synonyms.entry(word).or_default();
+
+                for replacement in &replacements {
+                    if !word_synonyms.contains(replacement) {
+                        word_synonyms.push(replacement.clone());
+                    }
+                }
+            }
+        }
+        Ok(Self {
+            synonyms: Arc::new(synonyms),
+        })
+    }
+}
+
+fn parse_words(words_str: &str, rule: &str) -> anyhow::Result<Vec<String>> {
+    let mut words = Vec::new();
+
+    for word in words_str.split(',') {
+        let word = word.trim();
+
+        if word.is_empty() {
+            bail!("synonym rule `{rule}` contains an empty word");
+        }
+        if word.contains(char::is_whitespace) {
+            bail!("multi-word synonym `{word}` in rule `{rule}` is not supported");
+        }
+        words.push(word.to_string());
+    }
+    Ok(words)
+}
+
+impl TokenFilter for SynonymFilter {
+    type Tokenizer<T: Tokenizer> = SynonymFilterWrapper<T>;
+
+    fn transform<T: Tokenizer>(self, tokenizer: T) -> SynonymFilterWrapper<T> {
+        SynonymFilterWrapper {
+            inner: tokenizer,
+            synonyms: self.synonyms,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SynonymFilterWrapper<T> {
+    inner: T,
+    synonyms: Arc<HashMap<String, Vec<String>>>,
+}
+
+impl<T: Tokenizer> Tokenizer for SynonymFilterWrapper<T> {
+    type TokenStream<'a> = SynonymTokenStream<'a, T::TokenStream<'a>>;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> Self::TokenStream<'a> {
+        SynonymTokenStream {
+            tail: self.inner.token_stream(text),
+            synonyms: &self.synonyms,
+            replacements: Vec::new(),
+        }
+    }
+}
+
+pub struct SynonymTokenStream<'a, T> {
+    tail: T,
+    synonyms: &'a HashMap<String, Vec<String>>,
+    // The replacements of the current token, in reverse order.
+    replacements: Vec<Token>,
+}
+
+impl<T: TokenStream> TokenStream for SynonymTokenStream<'_, T> {
+    fn advance(&mut self) -> bool {
+        self.replacements.pop();
+
+        if !self.replacements.is_empty() {
+            return true;
+        }
+        if !self.tail.advance() {
+            return false;
+        }
+        let token = self.tail.token();
+
+        if let Some(synonyms) = self.synonyms.get(&token.text) {
+            self.replacements
+                .extend(synonyms.iter().rev().map(|synonym| Token {
+                    text: synonym.clone(),
+                    ..token.clone()
+                }));
+        }
+        true
+    }
+
+    fn token(&self) -> &Token {
+        self.replacements
+            .last()
+            .unwrap_or_else(|| self.tail.token())
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.replacements
+            .last_mut()
+            .unwrap_or_else(|| self.tail.token_mut())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::tokenizer::{LowerCaser, SimpleTokenizer, TextAnalyzer};
+
+    use super::*;
+
+    fn synonym_tokens(rules: &[&str], text: &str) -> Vec<(String, usize)> {
+        let synonym_filter = SynonymFilter::from_rules(rules.iter().copied()).unwrap();
+        let mut text_analyzer = TextAnalyzer::builder(SimpleTokenizer::default())
+            .filter(LowerCaser)
+            .filter(synonym_filter)
+            .build();
+        let mut token_stream = text_analyzer.token_stream(text);
+        let mut tokens = Vec::new();
+
+        while let Some(token) = token_stream.next() {
+            tokens.push((token.text.clone(), token.position));
+        }
+        tokens
+    }
+
+    #[test]
+    fn test_synonym_filter() {
+        let rules = [
+            "# vehicles",
+            "auto, wagen",
+            "",
+            "tv, fernseher => fernseher",
+        ];
+        assert_eq!(
+            synonym_tokens(&rules, "Mein Auto und TV"),
+            [
+                ("mein".to_string(), 0),
+                ("auto".to_string(), 1),
+                ("wagen".to_string(), 1),
+                ("und".to_string(), 2),
+                ("fernseher".to_string(), 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_synonym_filter_invalid_rules() {
+        SynonymFilter::from_rules(["auto"]).unwrap_err();
+        SynonymFilter::from_rules(["auto, , wagen"]).unwrap_err();
+        SynonymFilter::from_rules(["tv => "]).unwrap_err();
+        SynonymFilter::from_rules(["new york, nyc"]).unwrap_err();
+    }
+}
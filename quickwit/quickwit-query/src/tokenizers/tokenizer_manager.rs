@@ -35,6 +35,8 @@ const RAW_LOWERCASE_TOKENIZER_NAME: &str = "raw_lowercase";
 pub struct TokenizerManager {
     inner: TantivyTokenizerManager,
     is_lowercaser: Arc<RwLock<HashMap<String, bool>>>,
+    // Tokenizers used in place of the indexing tokenizers of the same name to tokenize queries.
+    query_tokenizers: Arc<RwLock<HashMap<String, TextAnalyzer>>>,
 }
 
 impl TokenizerManager {
@@ -43,6 +45,7 @@ impl TokenizerManager {
         let this = Self {
             inner: TantivyTokenizerManager::new(),
             is_lowercaser: Arc::new(RwLock::new(HashMap::new())),
+            query_tokenizers: Arc::new(RwLock::new(HashMap::new())),
         };
 
         // in practice these will almost always be overridden in
@@ -75,11 +78,29 @@ impl TokenizerManager {
             .insert(tokenizer_name.to_string(), does_lowercasing);
     }
 
+    /// Registers the tokenizer used to tokenize the queries targeting the fields indexed with
+    /// the tokenizer of the same name. The tokenizer must be registered first.
+    pub fn register_query_tokenizer(&self, tokenizer_name: &str, query_tokenizer: TextAnalyzer) {
+        self.query_tokenizers
+            .write()
+            .unwrap()
+            .insert(tokenizer_name.to_string(), query_tokenizer);
+    }
+
     /// Accessing a tokenizer given its name.
     pub fn get_tokenizer(&self, tokenizer_name: &str) -> Option<TextAnalyzer> {
         self.inner.get(tokenizer_name)
     }
 
+    /// Accessing the tokenizer used to tokenize queries, given the name of the indexing
+    /// tokenizer. Falls back to the indexing tokenizer if no query tokenizer is registered.
+    pub fn get_query_tokenizer(&self, tokenizer_name: &str) -> Option<TextAnalyzer> {
+        if let Some(query_tokenizer) = self.query_tokenizers.read().unwrap().get(tokenizer_name) {
+            return Some(query_tokenizer.clone());
+        }
+        self.get_tokenizer(tokenizer_name)
+    }
+
     /// Query whether a given tokenizer does lowercasing
     pub fn get_normalizer(&self, tokenizer_name: &str) -> Option<TextAnalyzer> {
         let use_lowercaser = self
@@ -0,0 +1,194 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use tantivy::tokenizer::{Token, TokenFilter, TokenStream, Tokenizer};
+
+use crate::CodeTokenizer;
+
+/// A token filter splitting tokens on case changes, on transitions between letters and digits,
+/// and on non-alphanumeric characters, the same way the [`CodeTokenizer`] does.
+///
+/// For instance, it splits `PowerShell2Go` as `[Power, Shell, 2, Go]`. The positions of the
+/// following tokens are shifted accordingly, so phrase queries keep working. Optionally, the
+/// original token is emitted as well, at the position of its first part.
+#[derive(Clone, Default)]
+pub struct WordDelimiterFilter {
+    preserve_original: bool,
+}
+
+impl WordDelimiterFilter {
+    pub fn new(preserve_original: bool) -> Self {
+        Self { preserve_original }
+    }
+}
+
+impl TokenFilter for WordDelimiterFilter {
+    type Tokenizer<T: Tokenizer> = WordDelimiterFilterWrapper<T>;
+
+    fn transform<T: Tokenizer>(self, tokenizer: T) -> WordDelimiterFilterWrapper<T> {
+        WordDelimiterFilterWrapper {
+            inner: tokenizer,
+            preserve_original: self.preserve_original,
+            code_tokenizer: CodeTokenizer::default(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct WordDelimiterFilterWrapper<T> {
+    inner: T,
+    preserve_original: bool,
+    code_tokenizer: CodeTokenizer,
+}
+
+impl<T: Tokenizer> Tokenizer for WordDelimiterFilterWrapper<T> {
+    type TokenStream<'a> = WordDelimiterTokenStream<'a, T::TokenStream<'a>>;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> Self::TokenStream<'a> {
+        WordDelimiterTokenStream {
+            tail: self.inner.token_stream(text),
+            preserve_original: self.preserve_original,
+            code_tokenizer: &mut self.code_tokenizer,
+            parts: Vec::new(),
+            position_shift: 0,
+        }
+    }
+}
+
+pub struct WordDelimiterTokenStream<'a, T> {
+    tail: T,
+    preserve_original: bool,
+    code_tokenizer: &'a mut CodeTokenizer,
+    // The parts of the current token, in reverse order.
+    parts: Vec<Token>,
+    position_shift: usize,
+}
+
+impl<T: TokenStream> WordDelimiterTokenStream<'_, T> {
+    fn split_tail_token(&mut self) {
+        let token = self.tail.token_mut();
+        token.position += self.position_shift;
+
+        let mut parts = Vec::new();
+        let mut part_stream = self.code_tokenizer.token_stream(&token.text);
+
+        while let Some(part) = part_stream.next() {
+            parts.push(part.clone());
+        }
+        if parts.len() < 2 {
+            return;
+        }
+        // Offsets can only be mapped to the parts if the previous filters did not change the
+        // length of the token.
+        let has_original_offsets = token.offset_to - token.offset_from == token.text.len();
+
+        if self.preserve_original {
+            self.parts.push(token.clone());
+        }
+        for (part_ord, part) in parts.iter().enumerate() {
+            let (offset_from, offset_to) = if has_original_offsets {
+                (
+                    token.offset_from + part.offset_from,
+                    token.offset_from + part.offset_to,
+                )
+            } else {
+                (token.offset_from, token.offset_to)
+            };
+            self.parts.push(Token {
+                offset_from,
+                offset_to,
+                position: token.position + part_ord,
+                text: part.text.clone(),
+                position_length: 1,
+            });
+        }
+        self.parts.reverse();
+        self.position_shift += parts.len() - 1;
+    }
+}
+
+impl<T: TokenStream> TokenStream for WordDelimiterTokenStream<'_, T> {
+    fn advance(&mut self) -> bool {
+        self.parts.pop();
+
+        if !self.parts.is_empty() {
+            return true;
+        }
+        if !self.tail.advance() {
+            return false;
+        }
+        self.split_tail_token();
+        true
+    }
+
+    fn token(&self) -> &Token {
+        self.parts.last().unwrap_or_else(|| self.tail.token())
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.parts
+            .last_mut()
+            .unwrap_or_else(|| self.tail.token_mut())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::tokenizer::{LowerCaser, TextAnalyzer, WhitespaceTokenizer};
+
+    use super::*;
+
+    fn split_tokens(preserve_original: bool, text: &str) -> Vec<(String, usize)> {
+        let mut text_analyzer = TextAnalyzer::builder(WhitespaceTokenizer::default())
+            .filter(WordDelimiterFilter::new(preserve_original))
+            .filter(LowerCaser)
+            .build();
+        let mut token_stream = text_analyzer.token_stream(text);
+        let mut tokens = Vec::new();
+
+        while let Some(token) = token_stream.next() {
+            tokens.push((token.text.clone(), token.position));
+        }
+        tokens
+    }
+
+    #[test]
+    fn test_word_delimiter_filter() {
+        assert_eq!(
+            split_tokens(false, "run PowerShell2Go now"),
+            [
+                ("run".to_string(), 0),
+                ("power".to_string(), 1),
+                ("shell".to_string(), 2),
+                ("2".to_string(), 3),
+                ("go".to_string(), 4),
+                ("now".to_string(), 5),
+            ]
+        );
+        assert_eq!(
+            split_tokens(true, "my_var x"),
+            [
+                ("my_var".to_string(), 0),
+                ("my".to_string(), 0),
+                ("var".to_string(), 1),
+                ("x".to_string(), 2),
+            ]
+        );
+    }
+}
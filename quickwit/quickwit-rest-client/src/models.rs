@@ -74,6 +74,93 @@ impl ApiResponse {
             Ok(object)
         }
     }
+
+    /// Checks status and returns the stream of the documents sent by a tail.
+    pub async fn into_tail_stream(self) -> Result<TailStream, Error> {
+        if self.inner.status().is_client_error() || self.inner.status().is_server_error() {
+            return Err(self.api_error().await);
+        }
+        Ok(TailStream {
+            inner: self.inner,
+            buffer: Vec::new(),
+        })
+    }
+}
+
+/// A document sent by a tail.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TailDocument {
+    /// Timestamp of the document in nanoseconds.
+    pub timestamp_nanos: i64,
+    /// JSON representation of the document.
+    pub json: String,
+}
+
+/// Stream of the documents sent by a tail as server-sent events.
+#[derive(Debug)]
+pub struct TailStream {
+    inner: reqwest::Response,
+    buffer: Vec<u8>,
+}
+
+impl TailStream {
+    /// Returns the next document sent by the tail, or `None` if the server closed the stream.
+    pub async fn next_document(&mut self) -> Result<Option<TailDocument>, Error> {
+        loop {
+            while let Some(event_end) = find_event_end(&self.buffer) {
+                let event_bytes: Vec<u8> = self.buffer.drain(..event_end).collect();
+                let event_str = std::str::from_utf8(&event_bytes)
+                    .map_err(|error| Error::Internal(format!("invalid tail event: {error}")))?;
+                if let Some(document) = parse_tail_event(event_str)? {
+                    return Ok(Some(document));
+                }
+            }
+            let Some(chunk) = self.inner.chunk().await? else {
+                return Ok(None);
+            };
+            self.buffer.extend_from_slice(&chunk);
+        }
+    }
+}
+
+/// Returns the position following the blank line ending the first event of the buffer.
+fn find_event_end(buffer: &[u8]) -> Option<usize> {
+    buffer
+        .windows(2)
+        .position(|window| window == b"\n\n")
+        .map(|position| position + 2)
+}
+
+/// Parses a server-sent event. Keep-alive comments and unknown events are ignored.
+fn parse_tail_event(event_str: &str) -> Result<Option<TailDocument>, Error> {
+    let mut event_type = "message";
+    let mut data_lines = Vec::new();
+    let mut id_opt = None;
+
+    for line in event_str.lines() {
+        if let Some(value) = line.strip_prefix("event:") {
+            event_type = value.trim_start();
+        } else if let Some(value) = line.strip_prefix("data:") {
+            data_lines.push(value.strip_prefix(' ').unwrap_or(value));
+        } else if let Some(value) = line.strip_prefix("id:") {
+            id_opt = Some(value.trim());
+        }
+    }
+    let data = data_lines.join("\n");
+
+    match event_type {
+        "document" => {
+            let timestamp_nanos = id_opt
+                .and_then(|id| id.parse::<i64>().ok())
+                .ok_or_else(|| Error::Internal("tail event has no valid timestamp".to_string()))?;
+            Ok(Some(TailDocument {
+                timestamp_nanos,
+                json: data,
+            }))
+        }
+        "error" => Err(Error::Internal(format!("failed to tail index: {data}"))),
+        _ => Ok(None),
+    }
 }
 
 #[derive(Clone)]
@@ -27,7 +27,10 @@ pub use quickwit_ingest::CommitType;
 use quickwit_metastore::{IndexMetadata, Split, SplitInfo};
 use quickwit_proto::ingest::Shard;
 use quickwit_search::SearchResponseRest;
-use quickwit_serve::{ListSplitsQueryParams, ListSplitsResponse, SearchRequestQueryString};
+use quickwit_serve::{
+    ListSplitsQueryParams, ListSplitsResponse, ReindexQueryParams, ReindexResponse,
+    SearchRequestQueryString, TailRequestQueryString,
+};
 use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
 use reqwest::{Client, ClientBuilder, Method, StatusCode, Url};
 use serde::Serialize;
@@ -35,7 +38,7 @@ use serde_json::json;
 use tracing::warn;
 
 use crate::error::Error;
-use crate::models::{ApiResponse, IngestSource, Timeout};
+use crate::models::{ApiResponse, IngestSource, TailStream, Timeout};
 use crate::BatchLineReader;
 
 pub const DEFAULT_BASE_URL: &str = "http://127.0.0.1:7280";
@@ -229,6 +232,52 @@ impl QuickwitClient {
         Ok(search_response)
     }
 
+    /// Streams the documents matching the query as they become searchable.
+    pub async fn tail(
+        &self,
+        index_id: &str,
+        tail_query: TailRequestQueryString,
+    ) -> Result<TailStream, Error> {
+        let path = format!("{index_id}/search/tail");
+        let tail_stream = self
+            .transport
+            .send(
+                Method::GET,
+                &path,
+                None,
+                Some(&tail_query),
+                None,
+                Timeout::none(),
+            )
+            .await?
+            .into_tail_stream()
+            .await?;
+        Ok(tail_stream)
+    }
+
+    /// Copies the documents matching the query of a reindex request from a source index into a
+    /// destination index using the Elasticsearch-compatible `_reindex` endpoint.
+    pub async fn reindex(
+        &self,
+        reindex_request: serde_json::Value,
+        reindex_query_params: ReindexQueryParams,
+    ) -> Result<ReindexResponse, Error> {
+        let body = Bytes::from(reindex_request.to_string());
+        let response = self
+            .transport
+            .send(
+                Method::POST,
+                "_elastic/_reindex",
+                None,
+                Some(&reindex_query_params),
+                Some(body),
+                self.ingest_timeout,
+            )
+            .await?;
+        let reindex_response = response.deserialize().await?;
+        Ok(reindex_response)
+    }
+
     pub fn indexes(&self) -> IndexClient {
         IndexClient::new(&self.transport, self.timeout)
     }
@@ -695,7 +744,10 @@ mod test {
     use quickwit_ingest::CommitType;
     use quickwit_metastore::IndexMetadata;
     use quickwit_search::SearchResponseRest;
-    use quickwit_serve::{ListSplitsQueryParams, ListSplitsResponse, SearchRequestQueryString};
+    use quickwit_serve::{
+        ListSplitsQueryParams, ListSplitsResponse, ReindexQueryParams, SearchRequestQueryString,
+        TailRequestQueryString,
+    };
     use reqwest::header::CONTENT_TYPE;
     use reqwest::{StatusCode, Url};
     use serde_json::json;
@@ -707,7 +759,7 @@ mod test {
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
     use crate::error::Error;
-    use crate::models::IngestSource;
+    use crate::models::{IngestSource, TailDocument};
     use crate::rest_client::QuickwitClientBuilder;
 
     #[tokio::test]
@@ -737,6 +789,7 @@ mod test {
             aggregations: None,
             elapsed_time_micros: 100,
             errors: Vec::new(),
+            profile: Vec::new(),
         };
         Mock::given(method("POST"))
             .and(path("/api/v1/my-index/search"))
@@ -755,6 +808,89 @@ mod test {
         );
     }
 
+    #[tokio::test]
+    async fn test_reindex_endpoint() {
+        let mock_server = MockServer::start().await;
+        let server_url = Url::parse(&mock_server.uri()).unwrap();
+        let qw_client = QuickwitClientBuilder::new(server_url).build();
+        let reindex_request = json!({
+            "source": {"index": "my-source-index", "size": 100},
+            "dest": {"index": "my-dest-index"},
+        });
+        Mock::given(method("POST"))
+            .and(path("/api/v1/_elastic/_reindex"))
+            .and(query_param("requests_per_second", "10"))
+            .and(body_json(reindex_request.clone()))
+            .respond_with(ResponseTemplate::new(StatusCode::OK).set_body_json(json!({
+                "took": 10,
+                "timed_out": false,
+                "total": 100,
+                "created": 100,
+                "batches": 1,
+                "retries": {"bulk": 0, "search": 0},
+                "throttled_millis": 0,
+                "requests_per_second": 10.0,
+                "failures": [],
+                "search_after": "split:00000000:00000063",
+            })))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        let reindex_query_params = ReindexQueryParams {
+            requests_per_second: Some(10.0),
+        };
+        let reindex_response = qw_client
+            .reindex(reindex_request, reindex_query_params)
+            .await
+            .unwrap();
+        assert_eq!(reindex_response.created, 100);
+        assert_eq!(
+            reindex_response.search_after.as_deref(),
+            Some("split:00000000:00000063")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tail_endpoint() {
+        let mock_server = MockServer::start().await;
+        let server_url = Url::parse(&mock_server.uri()).unwrap();
+        let qw_client = QuickwitClientBuilder::new(server_url).build();
+        let tail_query = TailRequestQueryString {
+            query: "body:hello".to_string(),
+            search_fields: None,
+            start_timestamp: Some(1_000),
+            max_hits: 100,
+        };
+        let body = concat!(
+            ":\n\n",
+            "event:document\ndata:{\"body\":\"hello\"}\nid:1000000000000\n\n",
+            "event:error\ndata:internal error\n\n",
+        );
+        Mock::given(method("GET"))
+            .and(path("/api/v1/my-index/search/tail"))
+            .and(query_param("query", "body:hello"))
+            .and(query_param("start_timestamp", "1000"))
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK)
+                    .insert_header("content-type", "text/event-stream")
+                    .set_body_string(body),
+            )
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        let mut tail_stream = qw_client.tail("my-index", tail_query).await.unwrap();
+        let document = tail_stream.next_document().await.unwrap().unwrap();
+        assert_eq!(
+            document,
+            TailDocument {
+                timestamp_nanos: 1_000_000_000_000,
+                json: r#"{"body":"hello"}"#.to_string(),
+            }
+        );
+        let error = tail_stream.next_document().await.unwrap_err();
+        assert!(error.to_string().contains("internal error"));
+    }
+
     fn get_ndjson_filepath(ndjson_dataset_filename: &str) -> String {
         format!(
             "{}/resources/tests/{}",
@@ -17,6 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::net::SocketAddr;
 use std::time::Duration;
 
 use base64::Engine;
@@ -25,7 +26,7 @@ use futures::{Future, StreamExt};
 use quickwit_proto::search::{
     FetchDocsRequest, FetchDocsResponse, GetKvRequest, LeafListFieldsRequest, LeafListTermsRequest,
     LeafListTermsResponse, LeafSearchRequest, LeafSearchResponse, LeafSearchStreamRequest,
-    LeafSearchStreamResponse, ListFieldsResponse, PutKvRequest,
+    LeafSearchStreamResponse, ListFieldsResponse, PutKvRequest, SplitSearchProfile,
 };
 use tantivy::aggregation::intermediate_agg_result::IntermediateAggregationResults;
 use tokio::sync::mpsc::error::SendError;
@@ -36,7 +37,7 @@ use tracing::{debug, error, info, warn};
 use crate::retry::search::LeafSearchRetryPolicy;
 use crate::retry::search_stream::{LeafSearchStreamRetryPolicy, SuccessfulSplitIds};
 use crate::retry::{retry_client, DefaultRetryPolicy, RetryPolicy};
-use crate::{SearchError, SearchJobPlacer, SearchServiceClient};
+use crate::{IngesterPool, SearchError, SearchJobPlacer, SearchServiceClient};
 
 /// Maximum number of put requests emitted to perform a replicated given PUT KV.
 const MAX_PUT_KV_ATTEMPTS: usize = 6;
@@ -53,12 +54,23 @@ const TARGET_NUM_REPLICATION: usize = 2;
 #[derive(Clone)]
 pub struct ClusterClient {
     pub(crate) search_job_placer: SearchJobPlacer,
+    /// Ingesters queried for the documents not yet indexed by real-time searches.
+    pub(crate) ingester_pool: IngesterPool,
 }
 
 impl ClusterClient {
     /// Instantiates [`ClusterClient`].
     pub fn new(search_job_placer: SearchJobPlacer) -> Self {
-        Self { search_job_placer }
+        Self {
+            search_job_placer,
+            ingester_pool: IngesterPool::default(),
+        }
+    }
+
+    /// Sets the pool of ingesters queried by real-time searches.
+    pub fn with_ingester_pool(mut self, ingester_pool: IngesterPool) -> Self {
+        self.ingester_pool = ingester_pool;
+        self
     }
 
     /// Fetches docs with retry on another node client.
@@ -67,7 +79,13 @@ impl ClusterClient {
         request: FetchDocsRequest,
         mut client: SearchServiceClient,
     ) -> crate::Result<FetchDocsResponse> {
-        let mut response_res = client.fetch_docs(request.clone()).await;
+        let mut response_res = client
+            .fetch_docs(request.clone())
+            .await
+            .map(|mut response| {
+                set_leaf_addr(&mut response.split_profiles, client.grpc_addr());
+                response
+            });
         let retry_policy = DefaultRetryPolicy {};
         if let Some(retry_request) = retry_policy.retry_request(request, &response_res) {
             assert!(!retry_request.split_offsets.is_empty());
@@ -81,7 +99,10 @@ impl ClusterClient {
                 "Fetch docs response error: `{:?}`. Retry once to execute {:?} with {:?}",
                 response_res, retry_request, client
             );
-            response_res = client.fetch_docs(retry_request).await;
+            response_res = client.fetch_docs(retry_request).await.map(|mut response| {
+                set_leaf_addr(&mut response.split_profiles, client.grpc_addr());
+                response
+            });
         }
         response_res
     }
@@ -92,7 +113,13 @@ impl ClusterClient {
         request: LeafSearchRequest,
         mut client: SearchServiceClient,
     ) -> crate::Result<LeafSearchResponse> {
-        let mut response_res = client.leaf_search(request.clone()).await;
+        let mut response_res = client
+            .leaf_search(request.clone())
+            .await
+            .map(|mut response| {
+                set_leaf_addr(&mut response.split_profiles, client.grpc_addr());
+                response
+            });
         let retry_policy = LeafSearchRetryPolicy {};
         // We retry only once.
         let Some(retry_request) = retry_policy.retry_request(request, &response_res) else {
@@ -122,7 +149,10 @@ impl ClusterClient {
             "Leaf search response error: `{:?}`. Retry once to execute {:?} with {:?}",
             response_res, retry_request, client
         );
-        let retry_result = client.leaf_search(retry_request).await;
+        let retry_result = client.leaf_search(retry_request).await.map(|mut response| {
+            set_leaf_addr(&mut response.split_profiles, client.grpc_addr());
+            response
+        });
         response_res = merge_original_with_retry_leaf_search_results(response_res, retry_result);
         response_res
     }
@@ -240,9 +270,20 @@ impl ClusterClient {
 
     /// Returns a search_after context
     pub async fn get_kv(&self, key: &[u8]) -> Option<Vec<u8>> {
-        let clients = self.search_job_placer.best_nodes_per_affinity(key).await;
         // On the read side, we attempt to contact up to 6 nodes.
-        for mut client in clients.take(MAX_GET_KV_ATTEMPTS) {
+        self.get_kv_aux(key, MAX_GET_KV_ATTEMPTS).await
+    }
+
+    /// Returns a value that may legitimately be absent, such as a cached search response. Only the
+    /// nodes the value is replicated to in the absence of failures are contacted, so that misses
+    /// remain cheap.
+    pub async fn get_kv_from_replicas(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.get_kv_aux(key, TARGET_NUM_REPLICATION).await
+    }
+
+    async fn get_kv_aux(&self, key: &[u8], max_attempts: usize) -> Option<Vec<u8>> {
+        let clients = self.search_job_placer.best_nodes_per_affinity(key).await;
+        for mut client in clients.take(max_attempts) {
             let get_request = GetKvRequest { key: key.to_vec() };
             if let Ok(Some(search_after_resp)) = client.get_kv(get_request.clone()).await {
                 return Some(search_after_resp);
@@ -326,9 +367,22 @@ fn merge_original_with_retry_leaf_search_response(
         partial_hits: original_response.partial_hits,
         num_successful_splits: original_response.num_successful_splits
             + retry_response.num_successful_splits,
+        split_profiles: original_response
+            .split_profiles
+            .into_iter()
+            .chain(retry_response.split_profiles)
+            .collect(),
+        num_bytes_read: original_response.num_bytes_read + retry_response.num_bytes_read,
     })
 }
 
+/// Sets the address of the leaf node that profiled the splits.
+fn set_leaf_addr(split_profiles: &mut [SplitSearchProfile], leaf_addr: SocketAddr) {
+    for split_profile in split_profiles {
+        split_profile.leaf_addr = leaf_addr.to_string();
+    }
+}
+
 // Merge initial leaf search results with results obtained from a retry.
 fn merge_original_with_retry_leaf_search_results(
     left_search_response_result: crate::Result<LeafSearchResponse>,
@@ -406,6 +460,7 @@ mod tests {
                 timestamp_start: None,
                 timestamp_end: None,
                 num_docs: 0,
+                index_sort: Vec::new(),
             }],
             ..Default::default()
         }
@@ -433,6 +488,7 @@ mod tests {
                         timestamp_start: None,
                         timestamp_end: None,
                         num_docs: 0,
+                        index_sort: Vec::new(),
                     },
                     SplitIdAndFooterOffsets {
                         split_id: "split_2".to_string(),
@@ -441,6 +497,7 @@ mod tests {
                         timestamp_start: None,
                         timestamp_end: None,
                         num_docs: 0,
+                        index_sort: Vec::new(),
                     },
                 ],
             }],
@@ -457,6 +514,7 @@ mod tests {
             fast_field: "fast".to_string(),
             output_format: 0,
             partition_by_field: None,
+            fast_fields: Vec::new(),
         };
         LeafSearchStreamRequest {
             request: Some(search_request),
@@ -470,6 +528,7 @@ mod tests {
                     timestamp_start: None,
                     timestamp_end: None,
                     num_docs: 0,
+                    index_sort: Vec::new(),
                 },
                 SplitIdAndFooterOffsets {
                     split_id: "split_2".to_string(),
@@ -478,6 +537,7 @@ mod tests {
                     timestamp_start: None,
                     timestamp_end: None,
                     num_docs: 0,
+                    index_sort: Vec::new(),
                 },
             ],
         }
@@ -489,7 +549,10 @@ mod tests {
         let mut mock_search_service = MockSearchService::new();
         mock_search_service.expect_fetch_docs().return_once(
             |_: quickwit_proto::search::FetchDocsRequest| {
-                Ok(quickwit_proto::search::FetchDocsResponse { hits: Vec::new() })
+                Ok(quickwit_proto::search::FetchDocsResponse {
+                    hits: Vec::new(),
+                    split_profiles: Vec::new(),
+                })
             },
         );
         let searcher_pool = searcher_pool_for_test([("127.0.0.1:1001", mock_search_service)]);
@@ -518,7 +581,10 @@ mod tests {
         let mut mock_search_service_2 = MockSearchService::new();
         mock_search_service_2.expect_fetch_docs().return_once(
             |_: quickwit_proto::search::FetchDocsRequest| {
-                Ok(quickwit_proto::search::FetchDocsResponse { hits: Vec::new() })
+                Ok(quickwit_proto::search::FetchDocsResponse {
+                    hits: Vec::new(),
+                    split_profiles: Vec::new(),
+                })
             },
         );
         let searcher_pool = searcher_pool_for_test([
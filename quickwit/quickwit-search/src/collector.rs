@@ -20,13 +20,16 @@
 use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::sync::Arc;
+use std::time::Instant;
 
 use itertools::Itertools;
 use quickwit_common::binary_heap::{SortKeyMapper, TopK};
 use quickwit_doc_mapper::WarmupInfo;
 use quickwit_proto::search::{
-    LeafSearchResponse, PartialHit, SearchRequest, SortByValue, SortOrder, SortValue,
-    SplitSearchError,
+    CountHits, LeafSearchResponse, PartialHit, SearchRequest, SortByValue, SortField, SortOrder,
+    SortValue, SplitSearchError, SplitSearchProfile,
 };
 use quickwit_proto::types::SplitId;
 use serde::Deserialize;
@@ -36,10 +39,19 @@ use tantivy::aggregation::{AggregationLimitsGuard, AggregationSegmentCollector};
 use tantivy::collector::{Collector, SegmentCollector};
 use tantivy::columnar::{ColumnType, MonotonicallyMappableToU64};
 use tantivy::fastfield::Column;
-use tantivy::{DateTime, DocId, Score, SegmentOrdinal, SegmentReader, TantivyError};
+use tantivy::query::Weight;
+use tantivy::{
+    DateTime, DocId, DocSet, Score, SegmentOrdinal, SegmentReader, TantivyError, TERMINATED,
+};
 
 use crate::find_trace_ids_collector::{FindTraceIdsCollector, FindTraceIdsSegmentCollector, Span};
-use crate::top_k_collector::{specialized_top_k_segment_collector, QuickwitSegmentTopKCollector};
+use crate::nested_aggregation::{
+    IntermediateNestedAggregationsResult, NestedAggregations, NestedAggregationsSegmentCollector,
+};
+use crate::top_k_collector::{
+    specialized_top_k_segment_collector, QuickwitSegmentTopKCollector,
+    SortedSegmentEarlyTermination,
+};
 use crate::GlobalDocAddress;
 
 #[derive(Clone, Debug)]
@@ -474,6 +486,7 @@ fn get_score_extractor(
 #[allow(clippy::large_enum_variant)]
 enum AggregationSegmentCollectors {
     FindTraceIdsSegmentCollector(Box<FindTraceIdsSegmentCollector>),
+    NestedAggregationsSegmentCollector(Box<NestedAggregationsSegmentCollector>),
     TantivyAggregationSegmentCollector(AggregationSegmentCollector),
 }
 
@@ -482,6 +495,29 @@ pub struct QuickwitSegmentCollector {
     segment_top_k_collector: Option<Box<dyn QuickwitSegmentTopKCollector>>,
     aggregation: Option<AggregationSegmentCollectors>,
     num_hits: u64,
+    aggregation_collection_nanos_opt: Option<Arc<AtomicU64>>,
+}
+
+impl QuickwitSegmentCollector {
+    /// Returns the instant the aggregation collection started at if it is profiled.
+    #[inline]
+    fn start_aggregation_collection(&self) -> Option<Instant> {
+        if self.aggregation.is_some() && self.aggregation_collection_nanos_opt.is_some() {
+            Some(Instant::now())
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn end_aggregation_collection(&self, start_opt: Option<Instant>) {
+        if let (Some(start), Some(aggregation_collection_nanos)) =
+            (start_opt, &self.aggregation_collection_nanos_opt)
+        {
+            aggregation_collection_nanos
+                .fetch_add(start.elapsed().as_nanos() as u64, AtomicOrdering::Relaxed);
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -537,16 +573,21 @@ impl SegmentCollector for QuickwitSegmentCollector {
         if let Some(segment_top_k_collector) = self.segment_top_k_collector.as_mut() {
             segment_top_k_collector.collect_top_k_block(filtered_docs);
         }
+        let aggregation_collection_start_opt = self.start_aggregation_collection();
 
         match self.aggregation.as_mut() {
             Some(AggregationSegmentCollectors::FindTraceIdsSegmentCollector(collector)) => {
                 collector.collect_block(filtered_docs)
             }
+            Some(AggregationSegmentCollectors::NestedAggregationsSegmentCollector(collector)) => {
+                collector.collect_block(filtered_docs)
+            }
             Some(AggregationSegmentCollectors::TantivyAggregationSegmentCollector(collector)) => {
                 collector.collect_block(filtered_docs)
             }
             None => (),
         }
+        self.end_aggregation_collection(aggregation_collection_start_opt);
     }
 
     #[inline]
@@ -555,16 +596,21 @@ impl SegmentCollector for QuickwitSegmentCollector {
         if let Some(segment_top_k_collector) = self.segment_top_k_collector.as_mut() {
             segment_top_k_collector.collect_top_k(doc_id, score);
         }
+        let aggregation_collection_start_opt = self.start_aggregation_collection();
 
         match self.aggregation.as_mut() {
             Some(AggregationSegmentCollectors::FindTraceIdsSegmentCollector(collector)) => {
                 collector.collect(doc_id, score)
             }
+            Some(AggregationSegmentCollectors::NestedAggregationsSegmentCollector(collector)) => {
+                collector.collect(doc_id)
+            }
             Some(AggregationSegmentCollectors::TantivyAggregationSegmentCollector(collector)) => {
                 collector.collect(doc_id, score)
             }
             None => (),
         }
+        self.end_aggregation_collection(aggregation_collection_start_opt);
     }
 
     fn harvest(self) -> Self::Fruit {
@@ -572,6 +618,10 @@ impl SegmentCollector for QuickwitSegmentCollector {
         if let Some(segment_top_k_collector) = self.segment_top_k_collector {
             partial_hits = segment_top_k_collector.get_top_k();
         }
+        let aggregation_collection_start_opt = self
+            .aggregation_collection_nanos_opt
+            .is_some()
+            .then(Instant::now);
 
         let intermediate_aggregation_result = match self.aggregation {
             Some(AggregationSegmentCollectors::FindTraceIdsSegmentCollector(collector)) => {
@@ -580,6 +630,11 @@ impl SegmentCollector for QuickwitSegmentCollector {
                     postcard::to_allocvec(&fruit).expect("Collector fruit should be serializable.");
                 Some(serialized)
             }
+            Some(AggregationSegmentCollectors::NestedAggregationsSegmentCollector(collector)) => {
+                let serialized = postcard::to_allocvec(&collector.harvest()?)
+                    .expect("Collector fruit should be serializable.");
+                Some(serialized)
+            }
             Some(AggregationSegmentCollectors::TantivyAggregationSegmentCollector(collector)) => {
                 let serialized = postcard::to_allocvec(&collector.harvest()?)
                     .expect("Collector fruit should be serializable.");
@@ -587,6 +642,13 @@ impl SegmentCollector for QuickwitSegmentCollector {
             }
             None => None,
         };
+        if let (Some(start), Some(aggregation_collection_nanos)) = (
+            aggregation_collection_start_opt,
+            &self.aggregation_collection_nanos_opt,
+        ) {
+            aggregation_collection_nanos
+                .fetch_add(start.elapsed().as_nanos() as u64, AtomicOrdering::Relaxed);
+        }
         Ok(LeafSearchResponse {
             intermediate_aggregation_result,
             num_hits: self.num_hits,
@@ -594,6 +656,8 @@ impl SegmentCollector for QuickwitSegmentCollector {
             failed_splits: Vec::new(),
             num_attempted_splits: 1,
             num_successful_splits: 1,
+            split_profiles: Vec::new(),
+            num_bytes_read: 0,
         })
     }
 }
@@ -605,6 +669,9 @@ pub enum QuickwitAggregations {
     /// Aggregation used by the Jaeger service to find trace IDs that match a
     /// [`quickwit_proto::jaeger::storage::v1::FindTraceIDsRequest`].
     FindTraceIdsAggregation(FindTraceIdsCollector),
+    /// Aggregations over the elements of nested fields, used to answer the Elasticsearch
+    /// `nested` aggregations.
+    NestedAggregations(NestedAggregations),
     /// Your classic Tantivy aggregation.
     TantivyAggregations(Aggregations),
 }
@@ -616,6 +683,9 @@ impl QuickwitAggregations {
             QuickwitAggregations::FindTraceIdsAggregation(collector) => {
                 collector.fast_field_names()
             }
+            QuickwitAggregations::NestedAggregations(nested_aggregations) => {
+                nested_aggregations.fast_field_names()
+            }
             QuickwitAggregations::TantivyAggregations(aggregations) => {
                 get_fast_field_names(aggregations)
             }
@@ -627,6 +697,9 @@ impl QuickwitAggregations {
             QuickwitAggregations::FindTraceIdsAggregation(aggreg) => {
                 QuickwitIncrementalAggregations::FindTraceIdsAggregation(aggreg.clone(), Vec::new())
             }
+            QuickwitAggregations::NestedAggregations(aggreg) => {
+                QuickwitIncrementalAggregations::NestedAggregations(aggreg.clone(), Vec::new())
+            }
             QuickwitAggregations::TantivyAggregations(aggreg) => {
                 QuickwitIncrementalAggregations::TantivyAggregations(aggreg.clone(), Vec::new())
             }
@@ -637,6 +710,7 @@ impl QuickwitAggregations {
 #[derive(Clone)]
 enum QuickwitIncrementalAggregations {
     FindTraceIdsAggregation(FindTraceIdsCollector, Vec<Vec<Span>>),
+    NestedAggregations(NestedAggregations, Vec<Vec<u8>>),
     TantivyAggregations(Aggregations, Vec<Vec<u8>>),
     NoAggregation,
 }
@@ -653,7 +727,8 @@ impl QuickwitIncrementalAggregations {
                     state.push(new_state);
                 }
             }
-            QuickwitIncrementalAggregations::TantivyAggregations(_, state) => {
+            QuickwitIncrementalAggregations::NestedAggregations(_, state)
+            | QuickwitIncrementalAggregations::TantivyAggregations(_, state) => {
                 state.push(intermediate_result);
             }
             QuickwitIncrementalAggregations::NoAggregation => (),
@@ -682,6 +757,7 @@ impl QuickwitIncrementalAggregations {
                 }
                 None
             }
+            QuickwitIncrementalAggregations::NestedAggregations(_, _) => None,
             QuickwitIncrementalAggregations::TantivyAggregations(_, _) => None,
             QuickwitIncrementalAggregations::NoAggregation => None,
         }
@@ -698,6 +774,12 @@ impl QuickwitIncrementalAggregations {
                 let serialized = postcard::to_allocvec(&merged_fruit).map_err(map_error)?;
                 Ok(Some(serialized))
             }
+            QuickwitIncrementalAggregations::NestedAggregations(aggregation, state) => {
+                merge_intermediate_aggregation_result(
+                    &Some(QuickwitAggregations::NestedAggregations(aggregation)),
+                    state.iter().map(|vec| vec.as_slice()),
+                )
+            }
             QuickwitIncrementalAggregations::TantivyAggregations(aggregation, state) => {
                 merge_intermediate_aggregation_result(
                     &Some(QuickwitAggregations::TantivyAggregations(aggregation)),
@@ -722,6 +804,11 @@ pub(crate) struct QuickwitCollector {
     pub aggregation: Option<QuickwitAggregations>,
     pub aggregation_limits: AggregationLimitsGuard,
     search_after: Option<PartialHit>,
+    /// Time spent collecting the aggregations. Only measured if the search is profiled.
+    pub aggregation_collection_nanos_opt: Option<Arc<AtomicU64>>,
+    /// First field of the index sort of the split, if the collection of its documents may stop
+    /// early.
+    early_termination_sort_field_opt: Option<SortField>,
 }
 
 impl QuickwitCollector {
@@ -737,6 +824,11 @@ impl QuickwitCollector {
         self.start_offset = search_request.start_offset as usize;
         self.search_after.clone_from(&search_request.search_after);
     }
+    /// Disables the early termination of the collection, for documents not sorted by the index
+    /// sort of the split.
+    pub fn disable_early_termination(&mut self) {
+        self.early_termination_sort_field_opt = None;
+    }
     pub fn fast_field_names(&self) -> HashSet<String> {
         let mut fast_field_names = HashSet::default();
         self.sort_by.first.add_fast_field(&mut fast_field_names);
@@ -756,6 +848,39 @@ impl QuickwitCollector {
             ..WarmupInfo::default()
         }
     }
+
+    /// Returns the early termination of the collection of a segment, if its documents are sorted
+    /// by the first sort field of the request and only the top hits are needed.
+    fn sorted_segment_early_termination(
+        &self,
+        segment_reader: &SegmentReader,
+    ) -> tantivy::Result<Option<SortedSegmentEarlyTermination>> {
+        let Some(early_termination_sort_field) = &self.early_termination_sort_field_opt else {
+            return Ok(None);
+        };
+        let leaf_max_hits = self.max_hits + self.start_offset;
+
+        if leaf_max_hits == 0 || self.aggregation.is_some() || self.search_after.is_some() {
+            return Ok(None);
+        }
+        let SortByComponent::FastField { field_name, order } = &self.sort_by.first else {
+            return Ok(None);
+        };
+        if *field_name != early_termination_sort_field.field_name
+            || *order != early_termination_sort_field.sort_order()
+        {
+            return Ok(None);
+        }
+        let Some((sort_column, _column_type)) =
+            segment_reader.fast_fields().u64_lenient(field_name)?
+        else {
+            return Ok(None);
+        };
+        Ok(Some(SortedSegmentEarlyTermination::new(
+            sort_column,
+            leaf_max_hits,
+        )))
+    }
 }
 
 impl Collector for QuickwitCollector {
@@ -777,6 +902,16 @@ impl Collector for QuickwitCollector {
                     Box::new(collector.for_segment(0, segment_reader)?),
                 ))
             }
+            Some(QuickwitAggregations::NestedAggregations(nested_aggregations)) => Some(
+                AggregationSegmentCollectors::NestedAggregationsSegmentCollector(Box::new(
+                    NestedAggregationsSegmentCollector::from_agg_req_and_reader(
+                        nested_aggregations,
+                        segment_reader,
+                        segment_ord,
+                        &self.aggregation_limits,
+                    )?,
+                )),
+            ),
             Some(QuickwitAggregations::TantivyAggregations(aggs)) => Some(
                 AggregationSegmentCollectors::TantivyAggregationSegmentCollector(
                     AggregationSegmentCollector::from_agg_req_and_reader(
@@ -811,9 +946,78 @@ impl Collector for QuickwitCollector {
             num_hits: 0,
             segment_top_k_collector,
             aggregation,
+            aggregation_collection_nanos_opt: self.aggregation_collection_nanos_opt.clone(),
         })
     }
 
+    fn collect_segment(
+        &self,
+        weight: &dyn Weight,
+        segment_ord: SegmentOrdinal,
+        segment_reader: &SegmentReader,
+    ) -> tantivy::Result<tantivy::Result<LeafSearchResponse>> {
+        let mut segment_collector = self.for_segment(segment_ord, segment_reader)?;
+        let alive_bitset_opt = segment_reader.alive_bitset();
+        let requires_scoring = self.requires_scoring();
+
+        let Some(mut early_termination) = self.sorted_segment_early_termination(segment_reader)?
+        else {
+            // Same as the default implementation of `Collector::collect_segment`.
+            match (alive_bitset_opt, requires_scoring) {
+                (Some(alive_bitset), true) => {
+                    weight.for_each(segment_reader, &mut |doc_id, score| {
+                        if alive_bitset.is_alive(doc_id) {
+                            segment_collector.collect(doc_id, score);
+                        }
+                    })?;
+                }
+                (Some(alive_bitset), false) => {
+                    weight.for_each_no_score(segment_reader, &mut |doc_ids| {
+                        for doc_id in doc_ids.iter().copied() {
+                            if alive_bitset.is_alive(doc_id) {
+                                segment_collector.collect(doc_id, 0.0);
+                            }
+                        }
+                    })?;
+                }
+                (None, true) => {
+                    weight.for_each(segment_reader, &mut |doc_id, score| {
+                        segment_collector.collect(doc_id, score);
+                    })?;
+                }
+                (None, false) => {
+                    weight.for_each_no_score(segment_reader, &mut |doc_ids| {
+                        segment_collector.collect_block(doc_ids);
+                    })?;
+                }
+            }
+            return Ok(segment_collector.harvest());
+        };
+        // The documents of the segment are visited in the order of the first sort field: the
+        // collection stops once the top hits are known.
+        let mut scorer = weight.scorer(segment_reader, 1.0)?;
+        let mut doc_id = scorer.doc();
+
+        while doc_id != TERMINATED {
+            let is_alive = alive_bitset_opt
+                .map(|alive_bitset| alive_bitset.is_alive(doc_id))
+                .unwrap_or(true);
+            if is_alive {
+                if early_termination.is_terminated(doc_id) {
+                    break;
+                }
+                let score = if requires_scoring {
+                    scorer.score()
+                } else {
+                    0.0
+                };
+                segment_collector.collect(doc_id, score);
+            }
+            doc_id = scorer.advance();
+        }
+        Ok(segment_collector.harvest())
+    }
+
     fn requires_scoring(&self) -> bool {
         // We do not need BM25 scoring in Quickwit if it is not opted-in.
         // By returning false, we inform tantivy that it does not need to decompress
@@ -880,6 +1084,25 @@ fn merge_intermediate_aggregation_result<'a>(
             let serialized = postcard::to_allocvec(&merged_fruit).map_err(map_error)?;
             Some(serialized)
         }
+        Some(QuickwitAggregations::NestedAggregations(_)) => {
+            let fruits: Vec<IntermediateNestedAggregationsResult> =
+                intermediate_aggregation_results
+                    .map(|intermediate_aggregation_result| {
+                        postcard::from_bytes(intermediate_aggregation_result).map_err(map_error)
+                    })
+                    .collect::<Result<_, _>>()?;
+
+            let mut fruit_iter = fruits.into_iter();
+            if let Some(mut merged_fruit) = fruit_iter.next() {
+                for fruit in fruit_iter {
+                    merged_fruit.merge_fruits(fruit)?;
+                }
+                let serialized = postcard::to_allocvec(&merged_fruit).map_err(map_error)?;
+                Some(serialized)
+            } else {
+                None
+            }
+        }
         Some(QuickwitAggregations::TantivyAggregations(_)) => {
             let fruits: Vec<IntermediateAggregationResults> = intermediate_aggregation_results
                 .map(|intermediate_aggregation_result| {
@@ -943,6 +1166,14 @@ fn merge_leaf_responses(
         .flat_map(|leaf_response| leaf_response.failed_splits.iter())
         .cloned()
         .collect_vec();
+    let split_profiles = leaf_responses
+        .iter_mut()
+        .flat_map(|leaf_response| std::mem::take(&mut leaf_response.split_profiles))
+        .collect_vec();
+    let num_bytes_read: u64 = leaf_responses
+        .iter()
+        .map(|leaf_response| leaf_response.num_bytes_read)
+        .sum();
     let all_partial_hits: Vec<PartialHit> = leaf_responses
         .into_iter()
         .flat_map(|leaf_response| leaf_response.partial_hits)
@@ -960,6 +1191,8 @@ fn merge_leaf_responses(
         failed_splits,
         num_attempted_splits,
         num_successful_splits,
+        split_profiles,
+        num_bytes_read,
     })
 }
 
@@ -1020,9 +1253,12 @@ pub(crate) fn sort_by_from_request(search_request: &SearchRequest) -> SortByPair
 }
 
 /// Builds the QuickwitCollector, in function of the information that was requested by the user.
+///
+/// `index_sort` is the sort of the documents of the split, empty if they are not sorted.
 pub(crate) fn make_collector_for_split(
     split_id: SplitId,
     search_request: &SearchRequest,
+    index_sort: &[SortField],
     aggregation_limits: AggregationLimitsGuard,
 ) -> crate::Result<QuickwitCollector> {
     let aggregation = match &search_request.aggregation_request {
@@ -1030,6 +1266,13 @@ pub(crate) fn make_collector_for_split(
         None => None,
     };
     let sort_by = sort_by_from_request(search_request);
+    // Stopping the collection early undercounts the hits.
+    let early_termination_sort_field_opt =
+        if search_request.count_hits() == CountHits::Underestimate {
+            index_sort.first().cloned()
+        } else {
+            None
+        };
     Ok(QuickwitCollector {
         split_id,
         start_offset: search_request.start_offset as usize,
@@ -1038,6 +1281,8 @@ pub(crate) fn make_collector_for_split(
         aggregation,
         aggregation_limits,
         search_after: search_request.search_after.clone(),
+        aggregation_collection_nanos_opt: search_request.profile.then(Default::default),
+        early_termination_sort_field_opt,
     })
 }
 
@@ -1059,6 +1304,8 @@ pub(crate) fn make_merge_collector(
         aggregation,
         aggregation_limits: aggregation_limits.clone(),
         search_after: search_request.search_after.clone(),
+        aggregation_collection_nanos_opt: None,
+        early_termination_sort_field_opt: None,
     })
 }
 
@@ -1183,6 +1430,8 @@ pub(crate) struct IncrementalCollector {
     num_attempted_splits: u64,
     num_successful_splits: u64,
     start_offset: usize,
+    split_profiles: Vec<SplitSearchProfile>,
+    num_bytes_read: u64,
 }
 
 impl IncrementalCollector {
@@ -1203,6 +1452,8 @@ impl IncrementalCollector {
             failed_splits: Vec::new(),
             num_attempted_splits: 0,
             num_successful_splits: 0,
+            split_profiles: Vec::new(),
+            num_bytes_read: 0,
         }
     }
 
@@ -1215,6 +1466,8 @@ impl IncrementalCollector {
             num_attempted_splits,
             intermediate_aggregation_result,
             num_successful_splits,
+            split_profiles,
+            num_bytes_read,
         } = leaf_response;
 
         self.num_hits += num_hits;
@@ -1222,6 +1475,8 @@ impl IncrementalCollector {
         self.failed_splits.extend(failed_splits);
         self.num_attempted_splits += num_attempted_splits;
         self.num_successful_splits += num_successful_splits;
+        self.split_profiles.extend(split_profiles);
+        self.num_bytes_read += num_bytes_read;
         if let Some(intermediate_aggregation_result) = intermediate_aggregation_result {
             self.incremental_aggregation
                 .add(intermediate_aggregation_result)?;
@@ -1266,6 +1521,8 @@ impl IncrementalCollector {
             num_attempted_splits: self.num_attempted_splits,
             num_successful_splits: self.num_successful_splits,
             intermediate_aggregation_result,
+            split_profiles: self.split_profiles,
+            num_bytes_read: self.num_bytes_read,
         })
     }
 }
@@ -1435,6 +1692,87 @@ mod tests {
         index
     }
 
+    #[test]
+    fn test_single_split_sorted_by_index_sort_early_termination() {
+        use quickwit_proto::search::CountHits;
+        use tantivy::schema::{NumericOptions, Schema};
+        use tantivy::Index;
+
+        // The documents are sorted by `sort1` in descending order, missing values last.
+        let mut dataset = sort_dataset();
+        dataset.sort_by_key(|(val1, _)| (val1.is_none(), std::cmp::Reverse(*val1)));
+
+        let mut schema_builder = Schema::builder();
+        let opts = NumericOptions::default().set_fast();
+        let field1 = schema_builder.add_u64_field("sort1", opts.clone());
+        let field2 = schema_builder.add_u64_field("sort2", opts);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 15_000_000).unwrap();
+
+        for (val1, val2) in &dataset {
+            let mut doc = TantivyDocument::new();
+            if let Some(val1) = val1 {
+                doc.add_u64(field1, *val1);
+            }
+            if let Some(val2) = val2 {
+                doc.add_u64(field2, *val2);
+            }
+            index_writer.add_document(doc).unwrap();
+        }
+        index_writer.commit().unwrap();
+        let searcher = index.reader().unwrap().searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+
+        let index_sort = [SortField {
+            field_name: "sort1".to_string(),
+            sort_order: SortOrder::Desc.into(),
+            sort_datetime_format: None,
+        }];
+        for sort_str in ["sort1", "sort1,sort2", "sort1,-sort2", "-sort1"] {
+            for max_hits in 0..dataset.len() as u64 {
+                let search = |count_hits: CountHits| {
+                    let mut request = make_request(max_hits, sort_str);
+                    request.set_count_hits(count_hits);
+                    let collector = super::make_collector_for_split(
+                        "fake_split_id".to_string(),
+                        &request,
+                        &index_sort,
+                        Default::default(),
+                    )
+                    .unwrap();
+                    searcher
+                        .search(&tantivy::query::AllQuery, &collector)
+                        .unwrap()
+                };
+                let exact_response = search(CountHits::CountAll);
+                let early_terminated_response = search(CountHits::Underestimate);
+
+                assert_eq!(
+                    early_terminated_response.partial_hits, exact_response.partial_hits,
+                    "mismatch for \"{sort_str}\":{max_hits}"
+                );
+                assert_eq!(exact_response.num_hits, dataset.len() as u64);
+                assert!(early_terminated_response.num_hits <= exact_response.num_hits);
+            }
+        }
+        // The top hit has the value 2, like the first 5 documents: the collection stops at the
+        // sixth one.
+        let mut request = make_request(1, "sort1");
+        request.set_count_hits(CountHits::Underestimate);
+        let collector = super::make_collector_for_split(
+            "fake_split_id".to_string(),
+            &request,
+            &index_sort,
+            Default::default(),
+        )
+        .unwrap();
+        let response = searcher
+            .search(&tantivy::query::AllQuery, &collector)
+            .unwrap();
+        assert_eq!(response.num_hits, 5);
+        assert_eq!(response.partial_hits.len(), 1);
+    }
+
     #[test]
     fn test_single_split_sorting() {
         let index = make_index();
@@ -1551,6 +1889,7 @@ mod tests {
                 let collector = super::make_collector_for_split(
                     "fake_split_id".to_string(),
                     &make_request(slice_len as u64, sort_str),
+                    &[],
                     Default::default(),
                 )
                 .unwrap();
@@ -1647,6 +1986,7 @@ mod tests {
             let collector = super::make_collector_for_split(
                 "fake_split_id".to_string(),
                 &request,
+                &[],
                 Default::default(),
             )
             .unwrap();
@@ -1685,6 +2025,7 @@ mod tests {
             let collector = super::make_collector_for_split(
                 "fake_split_id1".to_string(),
                 &request,
+                &[],
                 Default::default(),
             )
             .unwrap();
@@ -1699,6 +2040,7 @@ mod tests {
             let collector = super::make_collector_for_split(
                 "fake_split_id2".to_string(),
                 &request,
+                &[],
                 Default::default(),
             )
             .unwrap();
@@ -1712,6 +2054,7 @@ mod tests {
             let collector = super::make_collector_for_split(
                 "fake_split_id3".to_string(),
                 &request,
+                &[],
                 Default::default(),
             )
             .unwrap();
@@ -1772,6 +2115,8 @@ mod tests {
                 num_attempted_splits: 3,
                 num_successful_splits: 3,
                 intermediate_aggregation_result: None,
+                split_profiles: Vec::new(),
+                num_bytes_read: 0,
             }],
         );
 
@@ -1789,7 +2134,9 @@ mod tests {
                 failed_splits: Vec::new(),
                 num_attempted_splits: 3,
                 num_successful_splits: 3,
-                intermediate_aggregation_result: None
+                intermediate_aggregation_result: None,
+                split_profiles: Vec::new(),
+                num_bytes_read: 0,
             }
         );
 
@@ -1828,6 +2175,8 @@ mod tests {
                     num_attempted_splits: 3,
                     num_successful_splits: 3,
                     intermediate_aggregation_result: None,
+                    split_profiles: Vec::new(),
+                    num_bytes_read: 0,
                 },
                 LeafSearchResponse {
                     num_hits: 10,
@@ -1846,6 +2195,8 @@ mod tests {
                     num_attempted_splits: 2,
                     num_successful_splits: 1,
                     intermediate_aggregation_result: None,
+                    split_profiles: Vec::new(),
+                    num_bytes_read: 0,
                 },
             ],
         );
@@ -1877,7 +2228,9 @@ mod tests {
                 }],
                 num_attempted_splits: 5,
                 num_successful_splits: 4,
-                intermediate_aggregation_result: None
+                intermediate_aggregation_result: None,
+                split_profiles: Vec::new(),
+                num_bytes_read: 0,
             }
         );
 
@@ -1917,6 +2270,8 @@ mod tests {
                     num_attempted_splits: 3,
                     num_successful_splits: 3,
                     intermediate_aggregation_result: None,
+                    split_profiles: Vec::new(),
+                    num_bytes_read: 0,
                 },
                 LeafSearchResponse {
                     num_hits: 10,
@@ -1935,6 +2290,8 @@ mod tests {
                     num_attempted_splits: 2,
                     num_successful_splits: 1,
                     intermediate_aggregation_result: None,
+                    split_profiles: Vec::new(),
+                    num_bytes_read: 0,
                 },
             ],
         );
@@ -1966,7 +2323,9 @@ mod tests {
                 }],
                 num_attempted_splits: 5,
                 num_successful_splits: 4,
-                intermediate_aggregation_result: None
+                intermediate_aggregation_result: None,
+                split_profiles: Vec::new(),
+                num_bytes_read: 0,
             }
         );
         // TODO would be nice to test aggregation too.
@@ -19,6 +19,7 @@
 
 use itertools::Itertools;
 use quickwit_common::rate_limited_error;
+use quickwit_common::tenant_quotas::QuotaExceeded;
 use quickwit_doc_mapper::QueryParserError;
 use quickwit_proto::error::grpc_error_to_grpc_status;
 use quickwit_proto::metastore::{EntityKind, MetastoreError};
@@ -45,6 +46,8 @@ pub enum SearchError {
     InvalidArgument(String),
     #[error("{0}")]
     InvalidQuery(String),
+    #[error("too many requests: {0}")]
+    QuotaExceeded(QuotaExceeded),
     #[error("storage not found: `{0}`)")]
     StorageResolver(#[from] StorageResolverError),
     #[error("request timed out: {0}")]
@@ -83,6 +86,7 @@ impl ServiceError for SearchError {
             Self::InvalidAggregationRequest(_) => ServiceErrorCode::BadRequest,
             Self::InvalidArgument(_) => ServiceErrorCode::BadRequest,
             Self::InvalidQuery(_) => ServiceErrorCode::BadRequest,
+            Self::QuotaExceeded(_) => ServiceErrorCode::TooManyRequests,
             Self::StorageResolver(storage_err) => {
                 rate_limited_error!(
                     limit_per_min = 6,
@@ -128,6 +132,12 @@ pub fn parse_grpc_error(grpc_error: &tonic::Status) -> SearchError {
         .unwrap_or_else(|_| SearchError::Internal(grpc_error.message().to_string()))
 }
 
+impl From<QuotaExceeded> for SearchError {
+    fn from(quota_exceeded: QuotaExceeded) -> Self {
+        SearchError::QuotaExceeded(quota_exceeded)
+    }
+}
+
 impl From<TantivyError> for SearchError {
     fn from(tantivy_error: TantivyError) -> Self {
         SearchError::Internal(format!("tantivy error: {tantivy_error}"))
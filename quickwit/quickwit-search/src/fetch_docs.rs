@@ -19,13 +19,14 @@
 
 use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
+use std::time::Instant;
 
 use anyhow::{Context, Ok};
 use futures::{StreamExt, TryStreamExt};
 use itertools::Itertools;
 use quickwit_doc_mapper::DocMapper;
 use quickwit_proto::search::{
-    FetchDocsResponse, PartialHit, SnippetRequest, SplitIdAndFooterOffsets,
+    FetchDocsResponse, PartialHit, SnippetRequest, SplitIdAndFooterOffsets, SplitSearchProfile,
 };
 use quickwit_storage::Storage;
 use tantivy::query::Query;
@@ -35,14 +36,14 @@ use tantivy::snippet::SnippetGenerator;
 use tantivy::{ReloadPolicy, Score, Searcher, Term};
 use tracing::{error, Instrument};
 
-use crate::leaf::open_index_with_caches;
+use crate::leaf::{open_index_with_caches, SplitReadCounters};
 use crate::service::SearcherContext;
 use crate::{convert_document_to_json_string, GlobalDocAddress};
 
 const SNIPPET_MAX_NUM_CHARS: usize = 150;
 
 /// Given a list of global doc address, fetches all the documents and
-/// returns them as a hashmap.
+/// returns them as a hashmap, along with the profiles of the splits if `profile` is set.
 async fn fetch_docs_to_map(
     searcher_context: Arc<SearcherContext>,
     mut global_doc_addrs: Vec<GlobalDocAddress>,
@@ -50,7 +51,8 @@ async fn fetch_docs_to_map(
     splits: &[SplitIdAndFooterOffsets],
     doc_mapper: Arc<DocMapper>,
     snippet_request_opt: Option<&SnippetRequest>,
-) -> anyhow::Result<HashMap<GlobalDocAddress, Document>> {
+    profile: bool,
+) -> anyhow::Result<(HashMap<GlobalDocAddress, Document>, Vec<SplitSearchProfile>)> {
     let mut split_fetch_docs_futures = Vec::new();
 
     let split_offsets_map: HashMap<&str, &SplitIdAndFooterOffsets> = splits
@@ -70,39 +72,62 @@ async fn fetch_docs_to_map(
         let split_and_offset = split_offsets_map
             .get(split_id)
             .ok_or_else(|| anyhow::anyhow!("failed to find offset for split {}", split_id))?;
-        split_fetch_docs_futures.push(fetch_docs_in_split(
-            searcher_context.clone(),
-            global_doc_addrs,
-            index_storage.clone(),
-            split_and_offset,
-            doc_mapper.clone(),
-            snippet_request_opt,
-        ));
+        let searcher_context = searcher_context.clone();
+        let index_storage = index_storage.clone();
+        let doc_mapper = doc_mapper.clone();
+
+        split_fetch_docs_futures.push(async move {
+            let read_counters_opt = profile.then(SplitReadCounters::default);
+            let fetch_docs_start = Instant::now();
+            let docs = fetch_docs_in_split(
+                searcher_context,
+                global_doc_addrs,
+                index_storage,
+                split_and_offset,
+                doc_mapper,
+                snippet_request_opt,
+                read_counters_opt.as_ref(),
+            )
+            .await?;
+            let split_profile_opt = read_counters_opt.map(|read_counters| SplitSearchProfile {
+                // The address of the leaf is set by the cluster client.
+                leaf_addr: String::new(),
+                split_id: split_and_offset.split_id.clone(),
+                fetch_docs_micros: fetch_docs_start.elapsed().as_micros() as u64,
+                storage_num_bytes: read_counters.storage_num_bytes(),
+                cache_num_bytes: read_counters.cache_num_bytes(),
+                ..Default::default()
+            });
+            Ok((docs, split_profile_opt))
+        });
     }
 
-    let split_fetch_docs: Vec<Vec<(GlobalDocAddress, Document)>> = futures::future::try_join_all(
-        split_fetch_docs_futures,
-    )
-    .await
-    .map_err(|error| {
-        let split_ids = splits
-            .iter()
-            .map(|split| split.split_id.clone())
-            .collect_vec();
-        error!(split_ids = ?split_ids, error = ?error, "error when fetching docs in splits");
-        anyhow::anyhow!(
-            "error when fetching docs for splits {:?}: {:?}",
-            split_ids,
-            error
-        )
-    })?;
+    let split_fetch_docs: Vec<(
+        Vec<(GlobalDocAddress, Document)>,
+        Option<SplitSearchProfile>,
+    )> = futures::future::try_join_all(split_fetch_docs_futures)
+        .await
+        .map_err(|error| {
+            let split_ids = splits
+                .iter()
+                .map(|split| split.split_id.clone())
+                .collect_vec();
+            error!(split_ids = ?split_ids, error = ?error, "error when fetching docs in splits");
+            anyhow::anyhow!(
+                "error when fetching docs for splits {:?}: {:?}",
+                split_ids,
+                error
+            )
+        })?;
 
-    let global_doc_addr_to_doc_json: HashMap<GlobalDocAddress, Document> = split_fetch_docs
-        .into_iter()
-        .flat_map(|docs| docs.into_iter())
-        .collect();
+    let mut global_doc_addr_to_doc_json: HashMap<GlobalDocAddress, Document> = HashMap::new();
+    let mut split_profiles: Vec<SplitSearchProfile> = Vec::new();
 
-    Ok(global_doc_addr_to_doc_json)
+    for (docs, split_profile_opt) in split_fetch_docs {
+        global_doc_addr_to_doc_json.extend(docs);
+        split_profiles.extend(split_profile_opt);
+    }
+    Ok((global_doc_addr_to_doc_json, split_profiles))
 }
 
 /// `fetch_docs` step of search.
@@ -110,6 +135,8 @@ async fn fetch_docs_to_map(
 /// This function takes a list of partial hits (possibly from different splits)
 /// and the storage associated to an index, fetches the document from
 /// the split document stores, and returns the full hits.
+///
+/// If `profile` is set, the response also holds the profiles of the splits.
 pub async fn fetch_docs(
     searcher_context: Arc<SearcherContext>,
     partial_hits: Vec<PartialHit>,
@@ -117,19 +144,21 @@ pub async fn fetch_docs(
     splits: &[SplitIdAndFooterOffsets],
     doc_mapper: Arc<DocMapper>,
     snippet_request_opt: Option<&SnippetRequest>,
+    profile: bool,
 ) -> anyhow::Result<FetchDocsResponse> {
     let global_doc_addrs: Vec<GlobalDocAddress> = partial_hits
         .iter()
         .map(GlobalDocAddress::from_partial_hit)
         .collect();
 
-    let mut global_doc_addr_to_doc_json = fetch_docs_to_map(
+    let (mut global_doc_addr_to_doc_json, split_profiles) = fetch_docs_to_map(
         searcher_context,
         global_doc_addrs,
         index_storage,
         splits,
         doc_mapper,
         snippet_request_opt,
+        profile,
     )
     .await?;
 
@@ -149,7 +178,10 @@ pub async fn fetch_docs(
             }
         })
         .collect();
-    Ok(FetchDocsResponse { hits })
+    Ok(FetchDocsResponse {
+        hits,
+        split_profiles,
+    })
 }
 
 // number of concurrent fetch allowed for a single split.
@@ -170,6 +202,7 @@ async fn fetch_docs_in_split(
     split: &SplitIdAndFooterOffsets,
     doc_mapper: Arc<DocMapper>,
     snippet_request_opt: Option<&SnippetRequest>,
+    read_counters_opt: Option<&SplitReadCounters>,
 ) -> anyhow::Result<Vec<(GlobalDocAddress, Document)>> {
     global_doc_addrs.sort_by_key(|doc| doc.doc_addr);
     // Opens the index without the ephemeral unbounded cache, this cache is indeed not useful
@@ -180,6 +213,7 @@ async fn fetch_docs_in_split(
         split,
         Some(doc_mapper.tokenizer_manager()),
         false,
+        read_counters_opt,
     )
     .await
     .context("open-index-for-split")?;
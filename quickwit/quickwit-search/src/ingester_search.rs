@@ -0,0 +1,728 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use futures::future::join_all;
+use quickwit_config::INGEST_V2_SOURCE_ID;
+use quickwit_proto::ingest::ingester::{
+    IngesterService, SearchShardsFailureReason, SearchShardsRequest, SearchShardsSubrequest,
+    ShardHit,
+};
+use quickwit_proto::ingest::Shard;
+use quickwit_proto::metastore::{
+    ListShardsRequest, ListShardsSubrequest, MetastoreService, MetastoreServiceClient,
+};
+use quickwit_proto::search::{
+    Hit, PartialHit, SearchRequest, SearchResponse, SortByValue, SortOrder, SortValue,
+};
+use quickwit_proto::types::{IndexId, IndexUid, NodeId, Position, QueueId};
+
+use crate::root::{convert_sort_datetime_value, get_sort_field_datetime_format};
+use crate::{IngesterPool, SearchError};
+
+/// Checks that a real-time search request only uses features supported by the ingesters.
+pub(crate) fn validate_real_time_request(
+    search_request: &SearchRequest,
+    timestamp_field_opt: Option<&str>,
+) -> crate::Result<()> {
+    if search_request.scroll_ttl_secs.is_some() {
+        return Err(SearchError::InvalidArgument(
+            "real-time search does not support scroll".to_string(),
+        ));
+    }
+    if search_request.search_after.is_some() {
+        return Err(SearchError::InvalidArgument(
+            "real-time search does not support `search_after`".to_string(),
+        ));
+    }
+    if search_request.runtime_fields.is_some() {
+        return Err(SearchError::InvalidArgument(
+            "real-time search does not support runtime fields".to_string(),
+        ));
+    }
+    // The documents held by the ingesters are not aggregated, so the aggregations would silently
+    // miss them.
+    if search_request.aggregation_request.is_some() {
+        return Err(SearchError::InvalidArgument(
+            "real-time search does not support aggregations".to_string(),
+        ));
+    }
+    if search_request.start_offset > 0 {
+        return Err(SearchError::InvalidArgument(
+            "real-time search does not support `start_offset`".to_string(),
+        ));
+    }
+    if let Some(sort_field) = search_request.sort_fields.first() {
+        if Some(sort_field.field_name.as_str()) != timestamp_field_opt {
+            return Err(SearchError::InvalidArgument(format!(
+                "real-time search only supports sorting by the timestamp field, got `{}`",
+                sort_field.field_name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Maximum number of times the splits are listed while looking for a consistent snapshot of the
+/// splits and the shard publish positions.
+pub(crate) const MAX_SNAPSHOT_ATTEMPTS: usize = 3;
+
+/// Lists the shards of the ingest source of the indexes.
+pub(crate) async fn list_ingest_shards(
+    metastore: &mut MetastoreServiceClient,
+    index_uids: &[IndexUid],
+) -> crate::Result<Vec<Shard>> {
+    let list_shards_subrequests = index_uids
+        .iter()
+        .map(|index_uid| ListShardsSubrequest {
+            index_uid: Some(index_uid.clone()),
+            source_id: INGEST_V2_SOURCE_ID.to_string(),
+            shard_state: None,
+        })
+        .collect();
+    let list_shards_request = ListShardsRequest {
+        subrequests: list_shards_subrequests,
+    };
+    let shards = metastore
+        .list_shards(list_shards_request)
+        .await?
+        .subresponses
+        .into_iter()
+        .flat_map(|list_shards_subresponse| list_shards_subresponse.shards)
+        .collect();
+    Ok(shards)
+}
+
+/// Returns whether the shards listed before and after listing the splits have the same publish
+/// positions.
+///
+/// Splits and the publish positions of their shards are updated in the same metastore
+/// transaction, and publish positions only move forward. If no publish position changed, no
+/// split was published in between, so the records up to the publish position of each shard are
+/// exactly the records of the splits listed. A shard created in between must not have published
+/// records, and a shard deleted in between must have been fully published already.
+pub(crate) fn is_consistent_shards_snapshot(
+    shards_before: &[Shard],
+    shards_after: &[Shard],
+) -> bool {
+    let mut publish_positions_before: HashMap<QueueId, Position> = shards_before
+        .iter()
+        .map(|shard| (shard.queue_id(), shard.publish_position_inclusive()))
+        .collect();
+
+    for shard in shards_after {
+        let publish_position = shard.publish_position_inclusive();
+
+        match publish_positions_before.remove(&shard.queue_id()) {
+            Some(publish_position_before) if publish_position_before == publish_position => {}
+            None if publish_position == Position::Beginning => {}
+            _ => return false,
+        }
+    }
+    publish_positions_before
+        .values()
+        .all(|publish_position| publish_position.is_eof())
+}
+
+/// Searches the documents persisted by the ingesters but not published in splits yet and merges
+/// them into the `search_response` computed over the splits.
+///
+/// The `shards` and the splits searched must come from a consistent snapshot, see
+/// [`is_consistent_shards_snapshot`]: each record is then returned either by a split or by an
+/// ingester, never both. Snippets are not computed over the documents held by the ingesters.
+///
+/// The real-time part of the search fails if the records to search on an ingester exceed the
+/// limits of the ingester.
+pub(crate) async fn search_ingesters_and_merge(
+    ingester_pool: &IngesterPool,
+    search_request: &SearchRequest,
+    shards: Vec<Shard>,
+    search_response: &mut SearchResponse,
+) -> crate::Result<()> {
+    // The index ID of each subrequest, indexed by subrequest ID.
+    let mut subrequest_index_ids: Vec<IndexId> = Vec::new();
+    let mut per_leader_subrequests: HashMap<NodeId, Vec<SearchShardsSubrequest>> = HashMap::new();
+
+    for shard in shards {
+        let publish_position_inclusive = shard.publish_position_inclusive();
+
+        // All the records of the shard have been indexed.
+        if publish_position_inclusive.is_eof() {
+            continue;
+        }
+        let subrequest_id = subrequest_index_ids.len() as u32;
+        subrequest_index_ids.push(shard.index_uid().index_id.clone());
+
+        let search_shards_subrequest = SearchShardsSubrequest {
+            subrequest_id,
+            index_uid: shard.index_uid,
+            source_id: shard.source_id,
+            shard_id: shard.shard_id,
+            publish_position_inclusive: Some(publish_position_inclusive),
+        };
+        per_leader_subrequests
+            .entry(NodeId::from(shard.leader_id))
+            .or_default()
+            .push(search_shards_subrequest);
+    }
+    let sort_ascending = search_request
+        .sort_fields
+        .first()
+        .map(|sort_field| sort_field.sort_order() == SortOrder::Asc)
+        .unwrap_or(false);
+    let mut search_shards_futures = Vec::with_capacity(per_leader_subrequests.len());
+
+    for (leader_id, subrequests) in per_leader_subrequests {
+        let Some(ingester) = ingester_pool.get(&leader_id) else {
+            search_response.errors.push(format!(
+                "failed to search ingester `{leader_id}`: ingester not found"
+            ));
+            continue;
+        };
+        let search_shards_request = SearchShardsRequest {
+            query_ast: search_request.query_ast.clone(),
+            subrequests,
+            max_hits: search_request.max_hits,
+            start_timestamp: search_request.start_timestamp,
+            end_timestamp: search_request.end_timestamp,
+            sort_ascending,
+        };
+        let search_shards_future = async move {
+            let search_shards_result = ingester.search_shards(search_shards_request).await;
+            (leader_id, search_shards_result)
+        };
+        search_shards_futures.push(search_shards_future);
+    }
+    let mut num_hits: u64 = 0;
+    let mut shard_hits: Vec<(IndexId, ShardHit)> = Vec::new();
+
+    for (leader_id, search_shards_result) in join_all(search_shards_futures).await {
+        let search_shards_response = match search_shards_result {
+            Ok(search_shards_response) => search_shards_response,
+            Err(error) => {
+                search_response
+                    .errors
+                    .push(format!("failed to search ingester `{leader_id}`: {error}"));
+                continue;
+            }
+        };
+        for success in search_shards_response.successes {
+            let Some(index_id) = subrequest_index_ids.get(success.subrequest_id as usize) else {
+                continue;
+            };
+            num_hits += success.num_hits;
+            shard_hits.extend(
+                success
+                    .hits
+                    .into_iter()
+                    .map(|shard_hit| (index_id.clone(), shard_hit)),
+            );
+        }
+        for failure in search_shards_response.failures {
+            if failure.reason_code() == SearchShardsFailureReason::LimitExceeded {
+                return Err(SearchError::Unavailable(format!(
+                    "failed to search ingester `{leader_id}`: {}",
+                    failure.reason
+                )));
+            }
+            search_response.errors.push(format!(
+                "failed to search ingester `{leader_id}`: {}",
+                failure.reason
+            ));
+        }
+    }
+    merge_shard_hits(search_request, num_hits, shard_hits, search_response)
+}
+
+/// Merges the hits returned by the ingesters into the hits returned by the splits. When the
+/// request is sorted by timestamp, the hits are interleaved. Otherwise, the hits from the
+/// ingesters, which are the most recent documents, come first.
+fn merge_shard_hits(
+    search_request: &SearchRequest,
+    num_hits: u64,
+    mut shard_hits: Vec<(IndexId, ShardHit)>,
+    search_response: &mut SearchResponse,
+) -> crate::Result<()> {
+    search_response.num_hits += num_hits;
+
+    if shard_hits.is_empty() {
+        return Ok(());
+    }
+    // The hits of each shard are sorted but the shards are not sorted relative to each other.
+    shard_hits.sort_by_key(|(_, shard_hit)| Reverse(shard_hit.timestamp_nanos));
+
+    let sort_field_opt = search_request.sort_fields.first();
+    let datetime_format_opt = get_sort_field_datetime_format(sort_field_opt)?;
+
+    let mut hits: Vec<Hit> = Vec::with_capacity(shard_hits.len() + search_response.hits.len());
+
+    for (index_id, shard_hit) in shard_hits {
+        let partial_hit_opt = if sort_field_opt.is_some() {
+            let mut sort_value_opt = shard_hit.timestamp_nanos.map(SortValue::I64);
+
+            if let (Some(sort_value), Some(datetime_format)) =
+                (sort_value_opt.as_mut(), datetime_format_opt)
+            {
+                convert_sort_datetime_value(sort_value, datetime_format)?;
+            }
+            let partial_hit = PartialHit {
+                sort_value: sort_value_opt.map(SortByValue::from),
+                ..Default::default()
+            };
+            Some(partial_hit)
+        } else {
+            None
+        };
+        let hit = Hit {
+            json: shard_hit.json,
+            partial_hit: partial_hit_opt,
+            snippet: None,
+            index_id,
+        };
+        hits.push(hit);
+    }
+    match sort_field_opt.map(|sort_field| sort_field.sort_order()) {
+        Some(SortOrder::Asc) => {
+            hits.append(&mut search_response.hits);
+            // Hits without a timestamp come last whatever the sort order.
+            hits.sort_by_key(|hit| {
+                let timestamp_opt = hit_timestamp(hit);
+                (timestamp_opt.is_none(), timestamp_opt)
+            });
+        }
+        Some(SortOrder::Desc) => {
+            hits.append(&mut search_response.hits);
+            hits.sort_by_key(|hit| Reverse(hit_timestamp(hit)));
+        }
+        None => {
+            hits.append(&mut search_response.hits);
+        }
+    }
+    hits.truncate(search_request.max_hits as usize);
+    search_response.hits = hits;
+    Ok(())
+}
+
+fn hit_timestamp(hit: &Hit) -> Option<i64> {
+    let sort_value = hit
+        .partial_hit
+        .as_ref()?
+        .sort_value
+        .as_ref()?
+        .sort_value
+        .as_ref()?;
+    match sort_value {
+        SortValue::I64(timestamp) => Some(*timestamp),
+        SortValue::U64(timestamp) => Some(*timestamp as i64),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_proto::ingest::ingester::{
+        IngesterServiceClient, MockIngesterService, SearchShardsFailure, SearchShardsResponse,
+        SearchShardsSuccess,
+    };
+    use quickwit_proto::ingest::ShardState;
+    use quickwit_proto::metastore::{
+        ListShardsResponse, ListShardsSubresponse, MockMetastoreService,
+    };
+    use quickwit_proto::search::{SortDatetimeFormat, SortField};
+    use quickwit_proto::types::ShardId;
+
+    use super::*;
+
+    fn split_hit(json: &str, timestamp_millis: i64) -> Hit {
+        Hit {
+            json: json.to_string(),
+            partial_hit: Some(PartialHit {
+                sort_value: Some(SortValue::I64(timestamp_millis).into()),
+                split_id: "test-split".to_string(),
+                ..Default::default()
+            }),
+            snippet: None,
+            index_id: "test-index".to_string(),
+        }
+    }
+
+    fn shard_hit(json: &str, timestamp_secs: i64) -> ShardHit {
+        ShardHit {
+            position: Some(Position::offset(0u64)),
+            json: json.to_string(),
+            timestamp_nanos: Some(timestamp_secs * 1_000_000_000),
+        }
+    }
+
+    fn sort_by_timestamp(sort_order: SortOrder) -> Vec<SortField> {
+        vec![SortField {
+            field_name: "ts".to_string(),
+            sort_order: sort_order as i32,
+            sort_datetime_format: Some(SortDatetimeFormat::UnixTimestampMillis as i32),
+        }]
+    }
+
+    #[test]
+    fn test_validate_real_time_request() {
+        let search_request = SearchRequest {
+            real_time: true,
+            sort_fields: sort_by_timestamp(SortOrder::Desc),
+            ..Default::default()
+        };
+        validate_real_time_request(&search_request, Some("ts")).unwrap();
+
+        let error = validate_real_time_request(&search_request, None).unwrap_err();
+        assert!(matches!(error, SearchError::InvalidArgument(_)));
+
+        let search_request = SearchRequest {
+            real_time: true,
+            start_offset: 10,
+            ..Default::default()
+        };
+        let error = validate_real_time_request(&search_request, Some("ts")).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid argument: real-time search does not support `start_offset`"
+        );
+
+        let search_request = SearchRequest {
+            real_time: true,
+            scroll_ttl_secs: Some(30),
+            ..Default::default()
+        };
+        let error = validate_real_time_request(&search_request, Some("ts")).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid argument: real-time search does not support scroll"
+        );
+
+        let search_request = SearchRequest {
+            real_time: true,
+            aggregation_request: Some(r#"{"count": {"value_count": {"field": "ts"}}}"#.to_string()),
+            ..Default::default()
+        };
+        let error = validate_real_time_request(&search_request, Some("ts")).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid argument: real-time search does not support aggregations"
+        );
+    }
+
+    #[test]
+    fn test_merge_shard_hits_sorted_by_timestamp() {
+        let search_request = SearchRequest {
+            max_hits: 3,
+            sort_fields: sort_by_timestamp(SortOrder::Desc),
+            ..Default::default()
+        };
+        let mut search_response = SearchResponse {
+            num_hits: 2,
+            hits: vec![split_hit("split-3", 3_000), split_hit("split-1", 1_000)],
+            ..Default::default()
+        };
+        let shard_hits = vec![
+            ("test-index".to_string(), shard_hit("shard-2", 2)),
+            ("test-index".to_string(), shard_hit("shard-4", 4)),
+        ];
+        merge_shard_hits(&search_request, 2, shard_hits, &mut search_response).unwrap();
+
+        assert_eq!(search_response.num_hits, 4);
+        let jsons: Vec<&str> = search_response
+            .hits
+            .iter()
+            .map(|hit| hit.json.as_str())
+            .collect();
+        assert_eq!(jsons, ["shard-4", "split-3", "shard-2"]);
+        assert_eq!(hit_timestamp(&search_response.hits[0]), Some(4_000));
+
+        let search_request = SearchRequest {
+            max_hits: 3,
+            sort_fields: sort_by_timestamp(SortOrder::Asc),
+            ..Default::default()
+        };
+        let mut search_response = SearchResponse {
+            num_hits: 2,
+            hits: vec![split_hit("split-1", 1_000), split_hit("split-3", 3_000)],
+            ..Default::default()
+        };
+        let shard_hits = vec![("test-index".to_string(), shard_hit("shard-2", 2))];
+        merge_shard_hits(&search_request, 1, shard_hits, &mut search_response).unwrap();
+
+        assert_eq!(search_response.num_hits, 3);
+        let jsons: Vec<&str> = search_response
+            .hits
+            .iter()
+            .map(|hit| hit.json.as_str())
+            .collect();
+        assert_eq!(jsons, ["split-1", "shard-2", "split-3"]);
+    }
+
+    #[test]
+    fn test_merge_shard_hits_unsorted() {
+        let search_request = SearchRequest {
+            max_hits: 2,
+            ..Default::default()
+        };
+        let mut search_response = SearchResponse {
+            num_hits: 1,
+            hits: vec![split_hit("split-1", 1_000)],
+            ..Default::default()
+        };
+        let shard_hits = vec![
+            ("test-index".to_string(), shard_hit("shard-2", 2)),
+            ("test-index".to_string(), shard_hit("shard-4", 4)),
+        ];
+        merge_shard_hits(&search_request, 2, shard_hits, &mut search_response).unwrap();
+
+        assert_eq!(search_response.num_hits, 3);
+        let jsons: Vec<&str> = search_response
+            .hits
+            .iter()
+            .map(|hit| hit.json.as_str())
+            .collect();
+        assert_eq!(jsons, ["shard-4", "shard-2"]);
+        assert!(search_response.hits[0].partial_hit.is_none());
+    }
+
+    fn shard(shard_id: u64, leader_id: &str, publish_position_inclusive: Position) -> Shard {
+        Shard {
+            index_uid: Some(IndexUid::for_test("test-index", 0)),
+            source_id: INGEST_V2_SOURCE_ID.to_string(),
+            shard_id: Some(ShardId::from(shard_id)),
+            shard_state: ShardState::Open as i32,
+            leader_id: leader_id.to_string(),
+            publish_position_inclusive: Some(publish_position_inclusive),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_ingest_shards() {
+        let index_uid = IndexUid::for_test("test-index", 0);
+
+        let mut mock_metastore = MockMetastoreService::new();
+        mock_metastore
+            .expect_list_shards()
+            .once()
+            .returning(|list_shards_request| {
+                assert_eq!(list_shards_request.subrequests.len(), 1);
+                let subrequest = &list_shards_request.subrequests[0];
+                assert_eq!(subrequest.source_id, INGEST_V2_SOURCE_ID);
+
+                let response = ListShardsResponse {
+                    subresponses: vec![ListShardsSubresponse {
+                        index_uid: subrequest.index_uid.clone(),
+                        source_id: INGEST_V2_SOURCE_ID.to_string(),
+                        shards: vec![shard(1, "test-ingester", Position::offset(42u64))],
+                    }],
+                };
+                Ok(response)
+            });
+        let mut metastore = MetastoreServiceClient::from_mock(mock_metastore);
+
+        let shards = list_ingest_shards(&mut metastore, &[index_uid])
+            .await
+            .unwrap();
+        assert_eq!(shards.len(), 1);
+        assert_eq!(shards[0].shard_id(), &ShardId::from(1));
+    }
+
+    #[test]
+    fn test_is_consistent_shards_snapshot() {
+        let shards_before = vec![
+            shard(1, "test-ingester", Position::offset(42u64)),
+            shard(2, "test-ingester", Position::eof(42u64)),
+        ];
+        assert!(is_consistent_shards_snapshot(
+            &shards_before,
+            &shards_before
+        ));
+
+        // A split was published in between.
+        let shards_after = vec![
+            shard(1, "test-ingester", Position::offset(43u64)),
+            shard(2, "test-ingester", Position::eof(42u64)),
+        ];
+        assert!(!is_consistent_shards_snapshot(
+            &shards_before,
+            &shards_after
+        ));
+
+        // A fully published shard was deleted and a new shard was opened in between.
+        let shards_after = vec![
+            shard(1, "test-ingester", Position::offset(42u64)),
+            shard(3, "test-ingester", Position::Beginning),
+        ];
+        assert!(is_consistent_shards_snapshot(&shards_before, &shards_after));
+
+        // A new shard published a split in between.
+        let shards_after = vec![
+            shard(1, "test-ingester", Position::offset(42u64)),
+            shard(3, "test-ingester", Position::offset(0u64)),
+        ];
+        assert!(!is_consistent_shards_snapshot(
+            &shards_before,
+            &shards_after
+        ));
+
+        // A shard that was not fully published was deleted in between.
+        let shards_after = vec![shard(2, "test-ingester", Position::eof(42u64))];
+        assert!(!is_consistent_shards_snapshot(
+            &shards_before,
+            &shards_after
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_search_ingesters_and_merge() {
+        let shards = vec![
+            shard(1, "test-ingester-foo", Position::offset(42u64)),
+            shard(2, "test-ingester-foo", Position::eof(42u64)),
+            shard(3, "test-ingester-bar", Position::Beginning),
+        ];
+        let mut mock_ingester = MockIngesterService::new();
+        mock_ingester
+            .expect_search_shards()
+            .once()
+            .returning(|search_shards_request| {
+                assert_eq!(search_shards_request.subrequests.len(), 1);
+                assert_eq!(search_shards_request.max_hits, 10);
+
+                let subrequest = &search_shards_request.subrequests[0];
+                assert_eq!(subrequest.shard_id(), &ShardId::from(1));
+                assert_eq!(
+                    subrequest.publish_position_inclusive(),
+                    Position::offset(42u64)
+                );
+                let response = SearchShardsResponse {
+                    successes: vec![SearchShardsSuccess {
+                        subrequest_id: subrequest.subrequest_id,
+                        num_hits: 1,
+                        hits: vec![shard_hit("shard-1", 1)],
+                    }],
+                    failures: Vec::new(),
+                };
+                Ok(response)
+            });
+        let ingester_pool = IngesterPool::default();
+        ingester_pool.insert(
+            "test-ingester-foo".into(),
+            IngesterServiceClient::from_mock(mock_ingester),
+        );
+
+        let mut mock_ingester = MockIngesterService::new();
+        mock_ingester
+            .expect_search_shards()
+            .once()
+            .returning(|search_shards_request| {
+                let response = SearchShardsResponse {
+                    successes: Vec::new(),
+                    failures: vec![SearchShardsFailure {
+                        subrequest_id: search_shards_request.subrequests[0].subrequest_id,
+                        reason: "shard not found".to_string(),
+                        reason_code: SearchShardsFailureReason::ShardNotFound as i32,
+                    }],
+                };
+                Ok(response)
+            });
+        ingester_pool.insert(
+            "test-ingester-bar".into(),
+            IngesterServiceClient::from_mock(mock_ingester),
+        );
+
+        let search_request = SearchRequest {
+            max_hits: 10,
+            real_time: true,
+            ..Default::default()
+        };
+        let mut search_response = SearchResponse {
+            num_hits: 1,
+            hits: vec![split_hit("split-1", 1_000)],
+            ..Default::default()
+        };
+        search_ingesters_and_merge(
+            &ingester_pool,
+            &search_request,
+            shards,
+            &mut search_response,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(search_response.num_hits, 2);
+        assert_eq!(search_response.hits.len(), 2);
+        assert_eq!(search_response.hits[0].json, "shard-1");
+        assert_eq!(search_response.hits[0].index_id, "test-index");
+        assert_eq!(search_response.hits[1].json, "split-1");
+        assert_eq!(search_response.errors.len(), 1);
+        assert_eq!(
+            search_response.errors[0],
+            "failed to search ingester `test-ingester-bar`: shard not found"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_ingesters_and_merge_limit_exceeded() {
+        let shards = vec![shard(1, "test-ingester", Position::offset(42u64))];
+
+        let mut mock_ingester = MockIngesterService::new();
+        mock_ingester
+            .expect_search_shards()
+            .once()
+            .returning(|search_shards_request| {
+                let response = SearchShardsResponse {
+                    successes: Vec::new(),
+                    failures: vec![SearchShardsFailure {
+                        subrequest_id: search_shards_request.subrequests[0].subrequest_id,
+                        reason: "too many records".to_string(),
+                        reason_code: SearchShardsFailureReason::LimitExceeded as i32,
+                    }],
+                };
+                Ok(response)
+            });
+        let ingester_pool = IngesterPool::default();
+        ingester_pool.insert(
+            "test-ingester".into(),
+            IngesterServiceClient::from_mock(mock_ingester),
+        );
+        let search_request = SearchRequest {
+            max_hits: 10,
+            real_time: true,
+            ..Default::default()
+        };
+        let mut search_response = SearchResponse {
+            num_hits: 1,
+            hits: vec![split_hit("split-1", 1_000)],
+            ..Default::default()
+        };
+        let error = search_ingesters_and_merge(
+            &ingester_pool,
+            &search_request,
+            shards,
+            &mut search_response,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(error, SearchError::Unavailable(_)));
+        assert_eq!(search_response.num_hits, 1);
+        assert_eq!(search_response.hits.len(), 1);
+    }
+}
@@ -19,24 +19,28 @@
 
 use std::collections::{HashMap, HashSet};
 use std::ops::Bound;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
+use std::time::Instant;
 
 use anyhow::Context;
 use futures::future::try_join_all;
 use quickwit_common::pretty::PrettySample;
+use quickwit_common::shared_consts::SPLIT_BLOOM_FILTERS_FILE_NAME;
 use quickwit_directories::{CachingDirectory, HotDirectory, StorageDirectory};
+use quickwit_doc_mapper::bloom_filter_pruning::{extract_bloom_filter_query, SplitBloomFilters};
 use quickwit_doc_mapper::{DocMapper, TermRange, WarmupInfo};
 use quickwit_proto::search::{
     CountHits, LeafSearchRequest, LeafSearchResponse, PartialHit, SearchRequest, SortOrder,
-    SortValue, SplitIdAndFooterOffsets, SplitSearchError,
+    SortValue, SplitIdAndFooterOffsets, SplitSearchError, SplitSearchProfile,
 };
 use quickwit_query::query_ast::{BoolQuery, QueryAst, QueryAstTransformer, RangeQuery, TermQuery};
 use quickwit_query::tokenizers::TokenizerManager;
 use quickwit_storage::{
-    wrap_storage_with_cache, BundleStorage, MemorySizedCache, OwnedBytes, SplitCache, Storage,
-    StorageResolver, TimeoutAndRetryStorage,
+    wrap_storage_with_cache, BundleStorage, ByteCountingStorage, MemorySizedCache, OwnedBytes,
+    SplitCache, Storage, StorageResolver, TimeoutAndRetryStorage,
 };
 use tantivy::aggregation::agg_req::{AggregationVariants, Aggregations};
 use tantivy::aggregation::AggregationLimitsGuard;
@@ -50,6 +54,7 @@ use tracing::*;
 use crate::collector::{make_collector_for_split, make_merge_collector, IncrementalCollector};
 use crate::metrics::SEARCH_METRICS;
 use crate::root::is_metadata_count_request_with_ast;
+use crate::runtime_fields::{build_runtime_fields_index, references_runtime_fields};
 use crate::search_permit_provider::SearchPermit;
 use crate::service::{deserialize_doc_mapper, SearcherContext};
 use crate::{QuickwitAggregations, SearchError};
@@ -124,10 +129,95 @@ pub(crate) async fn open_split_bundle(
     Ok((hotcache_bytes, bundle_storage))
 }
 
+/// Returns the bloom filters of the split, or `None` if the split has none, with a cache layer:
+/// - A split bloom filters cache given by `SearcherContext.split_bloom_filters_cache`.
+///
+/// The bytes read from the split are recorded in `read_counters`.
+#[instrument(skip_all, fields(split_id=split_and_footer_offsets.split_id))]
+async fn get_split_bloom_filters_from_cache_or_fetch(
+    searcher_context: &SearcherContext,
+    index_storage: Arc<dyn Storage>,
+    split_and_footer_offsets: &SplitIdAndFooterOffsets,
+    read_counters: &SplitReadCounters,
+) -> anyhow::Result<Option<SplitBloomFilters>> {
+    let bloom_filters_cache = &searcher_context.split_bloom_filters_cache;
+
+    let serialized_bloom_filters = if let Some(serialized_bloom_filters) =
+        bloom_filters_cache.get(&split_and_footer_offsets.split_id)
+    {
+        serialized_bloom_filters
+    } else {
+        let footer_num_bytes =
+            split_and_footer_offsets.split_footer_end - split_and_footer_offsets.split_footer_start;
+        read_counters
+            .split_num_bytes
+            .fetch_add(footer_num_bytes, Ordering::Relaxed);
+        let index_storage: Arc<dyn Storage> = Arc::new(ByteCountingStorage::new(
+            index_storage,
+            read_counters.storage_num_bytes.clone(),
+        ));
+        let (_, bundle_storage) =
+            open_split_bundle(searcher_context, index_storage, split_and_footer_offsets).await?;
+        let bloom_filters_path = Path::new(SPLIT_BLOOM_FILTERS_FILE_NAME);
+
+        // Splits created before the bloom filters were configured do not have any. We cache
+        // an empty payload for them so we do not look them up again.
+        let serialized_bloom_filters = if bundle_storage
+            .iter_files()
+            .any(|file_path| file_path == bloom_filters_path)
+        {
+            bundle_storage.get_all(bloom_filters_path).await?
+        } else {
+            OwnedBytes::empty()
+        };
+        bloom_filters_cache.put(
+            split_and_footer_offsets.split_id.to_owned(),
+            serialized_bloom_filters.clone(),
+        );
+        serialized_bloom_filters
+    };
+    read_counters
+        .split_num_bytes
+        .fetch_add(serialized_bloom_filters.len() as u64, Ordering::Relaxed);
+    if serialized_bloom_filters.is_empty() {
+        return Ok(None);
+    }
+    let split_bloom_filters = SplitBloomFilters::deserialize(serialized_bloom_filters.as_slice())
+        .context("failed to deserialize split bloom filters")?;
+    Ok(Some(split_bloom_filters))
+}
+
+/// Counts the bytes read while searching a split, in order to profile the search and to report
+/// the bytes scanned by the search.
+#[derive(Clone, Default)]
+pub(crate) struct SplitReadCounters {
+    /// Bytes fetched from the index storage.
+    storage_num_bytes: Arc<AtomicU64>,
+    /// Bytes of the split read, either from the index storage or from the caches.
+    split_num_bytes: Arc<AtomicU64>,
+}
+
+impl SplitReadCounters {
+    pub fn split_num_bytes(&self) -> u64 {
+        self.split_num_bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn storage_num_bytes(&self) -> u64 {
+        self.storage_num_bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn cache_num_bytes(&self) -> u64 {
+        self.split_num_bytes()
+            .saturating_sub(self.storage_num_bytes())
+    }
+}
+
 /// Opens a `tantivy::Index` for the given split with several cache layers:
 /// - A split footer cache given by `SearcherContext.split_footer_cache`.
 /// - A fast fields cache given by `SearcherContext.storage_long_term_cache`.
 /// - An ephemeral unbounded cache directory whose lifetime is tied to the returned `Index`.
+///
+/// If `read_counters_opt` is set, the bytes read from the split are recorded in the counters.
 #[instrument(skip_all, fields(split_footer_start=split_and_footer_offsets.split_footer_start, split_footer_end=split_and_footer_offsets.split_footer_end))]
 pub(crate) async fn open_index_with_caches(
     searcher_context: &SearcherContext,
@@ -135,7 +225,16 @@ pub(crate) async fn open_index_with_caches(
     split_and_footer_offsets: &SplitIdAndFooterOffsets,
     tokenizer_manager: Option<&TokenizerManager>,
     ephemeral_unbounded_cache: bool,
+    read_counters_opt: Option<&SplitReadCounters>,
 ) -> anyhow::Result<Index> {
+    let index_storage: Arc<dyn Storage> = if let Some(read_counters) = read_counters_opt {
+        Arc::new(ByteCountingStorage::new(
+            index_storage,
+            read_counters.storage_num_bytes.clone(),
+        ))
+    } else {
+        index_storage
+    };
     // Let's add a storage proxy to retry `get_slice` requests if they are taking too long,
     // if configured in the searcher config.
     //
@@ -159,10 +258,23 @@ pub(crate) async fn open_index_with_caches(
     )
     .await?;
 
-    let bundle_storage_with_cache = wrap_storage_with_cache(
+    let mut bundle_storage_with_cache = wrap_storage_with_cache(
         searcher_context.fast_fields_cache.clone(),
         Arc::new(bundle_storage),
     );
+    if let Some(read_counters) = read_counters_opt {
+        // The footer, which includes the hotcache, is read whether it comes from the footer cache
+        // or from the index storage.
+        let footer_num_bytes =
+            split_and_footer_offsets.split_footer_end - split_and_footer_offsets.split_footer_start;
+        read_counters
+            .split_num_bytes
+            .fetch_add(footer_num_bytes, Ordering::Relaxed);
+        bundle_storage_with_cache = Arc::new(ByteCountingStorage::new(
+            bundle_storage_with_cache,
+            read_counters.split_num_bytes.clone(),
+        ));
+    }
 
     let directory = StorageDirectory::new(bundle_storage_with_cache);
 
@@ -355,6 +467,36 @@ async fn warm_up_fieldnorms(searcher: &Searcher, requires_scoring: bool) -> anyh
     Ok(())
 }
 
+/// Builds the response of a split whose search was skipped altogether, either because its metadata
+/// was enough to answer the request or because its bloom filters ruled it out. If the search is
+/// profiled, the response holds the profile of the split, with the time spent before skipping the
+/// search accounted as warmup.
+fn get_skipped_split_leaf_resp(
+    count: u64,
+    split_id: &str,
+    warmup_start: Instant,
+    read_counters: SplitReadCounters,
+    profile: bool,
+) -> LeafSearchResponse {
+    let mut leaf_search_response = get_leaf_resp_from_count(count);
+    leaf_search_response.num_bytes_read = read_counters.split_num_bytes();
+
+    if profile {
+        leaf_search_response.split_profiles = vec![SplitSearchProfile {
+            // The address of the leaf is set by the cluster client.
+            leaf_addr: String::new(),
+            split_id: split_id.to_string(),
+            warmup_micros: warmup_start.elapsed().as_micros() as u64,
+            query_execution_micros: 0,
+            aggregation_collection_micros: 0,
+            fetch_docs_micros: 0,
+            storage_num_bytes: read_counters.storage_num_bytes(),
+            cache_num_bytes: read_counters.cache_num_bytes(),
+        }];
+    }
+    leaf_search_response
+}
+
 fn get_leaf_resp_from_count(count: u64) -> LeafSearchResponse {
     LeafSearchResponse {
         num_hits: count,
@@ -363,6 +505,8 @@ fn get_leaf_resp_from_count(count: u64) -> LeafSearchResponse {
         num_attempted_splits: 1,
         num_successful_splits: 1,
         intermediate_aggregation_result: None,
+        split_profiles: Vec::new(),
+        num_bytes_read: 0,
     }
 }
 
@@ -381,34 +525,84 @@ async fn leaf_search_single_split(
         &split,
         doc_mapper.timestamp_field_name(),
     );
-    if let Some(cached_answer) = searcher_context
-        .leaf_search_cache
-        .get(split.clone(), search_request.clone())
-    {
-        return Ok(cached_answer);
+    // Profiled searches bypass the cache so that the split is actually searched.
+    if !search_request.profile {
+        if let Some(cached_answer) = searcher_context.leaf_search_cache.get(
+            split.clone(),
+            search_request.clone(),
+            doc_mapper.runtime_fields(),
+        ) {
+            // Answering from the cache does not read anything from the split.
+            return Ok(LeafSearchResponse {
+                num_bytes_read: 0,
+                ..cached_answer
+            });
+        }
     }
 
     let query_ast: QueryAst = serde_json::from_str(search_request.query_ast.as_str())
         .map_err(|err| SearchError::InvalidQuery(err.to_string()))?;
 
+    let split_id = split.split_id.to_string();
+    let read_counters = SplitReadCounters::default();
+    let warmup_start = Instant::now();
+
     // CanSplitDoBetter or rewrite_request may have changed the request to be a count only request
     // This may be the case for AllQuery with a sort by date and time filter, where the current
     // split can't have better results.
     //
     if is_metadata_count_request_with_ast(&query_ast, &search_request) {
-        return Ok(get_leaf_resp_from_count(split.num_docs));
+        return Ok(get_skipped_split_leaf_resp(
+            split.num_docs,
+            &split_id,
+            warmup_start,
+            read_counters,
+            search_request.profile,
+        ));
+    }
+
+    // The bloom filters of the split may tell us that the split does not contain the terms
+    // required by the query, in which case we can skip the warmup and the search altogether.
+    let bloom_filter_query = extract_bloom_filter_query(
+        &query_ast,
+        &doc_mapper.schema(),
+        doc_mapper.bloom_filter_field_names(),
+    );
+    if !bloom_filter_query.is_empty() {
+        match get_split_bloom_filters_from_cache_or_fetch(
+            searcher_context,
+            storage.clone(),
+            &split,
+            &read_counters,
+        )
+        .await
+        {
+            Ok(Some(split_bloom_filters)) if !bloom_filter_query.evaluate(&split_bloom_filters) => {
+                return Ok(get_skipped_split_leaf_resp(
+                    0,
+                    &split_id,
+                    warmup_start,
+                    read_counters,
+                    search_request.profile,
+                ));
+            }
+            Ok(_) => {}
+            Err(error) => {
+                // Bloom filters are only an optimization: we search the split anyway.
+                warn!(split_id=%split.split_id, error=?error, "failed to read split bloom filters");
+            }
+        }
     }
 
-    let split_id = split.split_id.to_string();
     let index = open_index_with_caches(
         searcher_context,
         storage,
         &split,
         Some(doc_mapper.tokenizer_manager()),
         true,
+        Some(&read_counters),
     )
     .await?;
-    let split_schema = index.schema();
 
     let reader = index
         .reader_builder()
@@ -416,42 +610,81 @@ async fn leaf_search_single_split(
         .try_into()?;
     let searcher = reader.searcher();
 
-    let mut collector =
-        make_collector_for_split(split_id.clone(), &search_request, aggregations_limits)?;
+    let mut collector = make_collector_for_split(
+        split_id.clone(),
+        &search_request,
+        &split.index_sort,
+        aggregations_limits,
+    )?;
 
-    let (query, mut warmup_info) = doc_mapper.query(split_schema.clone(), &query_ast, false)?;
+    // The runtime fields are not part of the split: when the request references them, it is
+    // executed against an ephemeral index holding the documents of the split along with their
+    // runtime field values. Building this index is expensive, so we only do it when necessary.
+    let collector_fast_field_names = collector.fast_field_names();
+    let (searcher, query_doc_mapper, runtime_fields_index_opt) = if references_runtime_fields(
+        doc_mapper.runtime_fields(),
+        &query_ast,
+        &collector_fast_field_names,
+    ) {
+        let runtime_fields_index = build_runtime_fields_index(
+            &searcher,
+            &doc_mapper,
+            &query_ast,
+            &collector_fast_field_names,
+        )
+        .await?;
+        (
+            runtime_fields_index.searcher.clone(),
+            runtime_fields_index.doc_mapper.clone(),
+            Some(runtime_fields_index),
+        )
+    } else {
+        (searcher, doc_mapper.clone(), None)
+    };
+    if runtime_fields_index_opt.is_some() {
+        // The documents of the ephemeral index are not sorted.
+        collector.disable_early_termination();
+    }
+    let split_schema = searcher.schema().clone();
+
+    let (query, mut warmup_info) =
+        query_doc_mapper.query(split_schema.clone(), &query_ast, false)?;
 
     let collector_warmup_info = collector.warmup_info();
     warmup_info.merge(collector_warmup_info);
     warmup_info.simplify();
 
     warmup(&searcher, &warmup_info).await?;
+    let warmup_micros = warmup_start.elapsed().as_micros() as u64;
+    let aggregation_collection_nanos_opt = collector.aggregation_collection_nanos_opt.clone();
     let span = info_span!("tantivy_search");
 
-    let (search_request, leaf_search_response) = {
+    let (search_request, mut leaf_search_response, query_execution_micros) = {
         let split = split.clone();
 
         crate::search_thread_pool()
             .run_cpu_intensive(move || {
                 let _span_guard = span.enter();
+                let query_execution_start = Instant::now();
                 // Our search execution has been scheduled, let's check if we can improve the
                 // request based on the results of the preceding searches
                 check_optimize_search_request(&mut search_request, &split, &split_filter);
                 collector.update_search_param(&search_request);
-                if is_metadata_count_request_with_ast(&query_ast, &search_request) {
-                    return Ok((
-                        search_request,
-                        get_leaf_resp_from_count(searcher.num_docs() as u64),
-                    ));
-                }
-                if collector.is_count_only() {
-                    let count = query.count(&searcher)? as u64;
-                    Ok((search_request, get_leaf_resp_from_count(count)))
-                } else {
-                    searcher
-                        .search(&query, &collector)
-                        .map(|resp| (search_request, resp))
-                }
+                let leaf_search_response =
+                    if is_metadata_count_request_with_ast(&query_ast, &search_request) {
+                        get_leaf_resp_from_count(searcher.num_docs() as u64)
+                    } else if collector.is_count_only() {
+                        let count = query.count(&searcher)? as u64;
+                        get_leaf_resp_from_count(count)
+                    } else {
+                        searcher.search(&query, &collector)?
+                    };
+                let query_execution_micros = query_execution_start.elapsed().as_micros() as u64;
+                Ok::<_, tantivy::TantivyError>((
+                    search_request,
+                    leaf_search_response,
+                    query_execution_micros,
+                ))
             })
             .await
             .map_err(|_| {
@@ -459,9 +692,38 @@ async fn leaf_search_single_split(
             })??
     };
 
-    searcher_context
-        .leaf_search_cache
-        .put(split, search_request, leaf_search_response.clone());
+    if let Some(runtime_fields_index) = runtime_fields_index_opt {
+        runtime_fields_index.remap_partial_hits(&mut leaf_search_response);
+    }
+
+    leaf_search_response.num_bytes_read = read_counters.split_num_bytes();
+
+    if search_request.profile {
+        let aggregation_collection_micros = aggregation_collection_nanos_opt
+            .map(|aggregation_collection_nanos| {
+                aggregation_collection_nanos.load(Ordering::Relaxed) / 1_000
+            })
+            .unwrap_or_default();
+        leaf_search_response.split_profiles = vec![SplitSearchProfile {
+            // The address of the leaf is set by the cluster client.
+            leaf_addr: String::new(),
+            split_id,
+            warmup_micros,
+            query_execution_micros: query_execution_micros
+                .saturating_sub(aggregation_collection_micros),
+            aggregation_collection_micros,
+            fetch_docs_micros: 0,
+            storage_num_bytes: read_counters.storage_num_bytes(),
+            cache_num_bytes: read_counters.cache_num_bytes(),
+        }];
+        return Ok(leaf_search_response);
+    }
+    searcher_context.leaf_search_cache.put(
+        split,
+        search_request,
+        doc_mapper.runtime_fields(),
+        leaf_search_response.clone(),
+    );
     Ok(leaf_search_response)
 }
 
@@ -17,9 +17,12 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use std::ops::Bound;
 
 use prost::Message;
+use quickwit_doc_mapper::RuntimeField;
 use quickwit_proto::search::{
     CountHits, LeafSearchResponse, SearchRequest, SplitIdAndFooterOffsets,
 };
@@ -59,8 +62,9 @@ impl LeafSearchCache {
         &self,
         split_info: SplitIdAndFooterOffsets,
         search_request: SearchRequest,
+        runtime_fields: &[RuntimeField],
     ) -> Option<LeafSearchResponse> {
-        let key = CacheKey::from_split_meta_and_request(split_info, search_request);
+        let key = CacheKey::from_split_meta_and_request(split_info, search_request, runtime_fields);
         let encoded_result = self.content.get(&key)?;
         // this should never fail
         LeafSearchResponse::decode(&*encoded_result).ok()
@@ -70,9 +74,10 @@ impl LeafSearchCache {
         &self,
         split_info: SplitIdAndFooterOffsets,
         search_request: SearchRequest,
+        runtime_fields: &[RuntimeField],
         result: LeafSearchResponse,
     ) {
-        let key = CacheKey::from_split_meta_and_request(split_info, search_request);
+        let key = CacheKey::from_split_meta_and_request(split_info, search_request, runtime_fields);
 
         let encoded_result = result.encode_to_vec();
         self.content.put(key, OwnedBytes::new(encoded_result));
@@ -89,12 +94,16 @@ struct CacheKey {
     /// The effective time range of the request, that is, the intersection of the timerange
     /// requested, and the timerange covered by the split.
     merged_time_range: Range,
+    /// Hash of the runtime fields of the doc mapper. Runtime fields defined in the doc mapping of
+    /// the index are not part of the request, but changing their scripts changes the results.
+    runtime_fields_hash: u64,
 }
 
 impl CacheKey {
     fn from_split_meta_and_request(
         split_info: SplitIdAndFooterOffsets,
         mut search_request: SearchRequest,
+        runtime_fields: &[RuntimeField],
     ) -> Self {
         let split_time_range = Range::from_bounds(split_info.time_range());
         let request_time_range = Range::from_bounds(search_request.time_range());
@@ -106,10 +115,15 @@ impl CacheKey {
         // single split: either we did process it and got everything, or we didn't.
         search_request.count_hits = CountHits::CountAll.into();
 
+        let mut hasher = DefaultHasher::new();
+        runtime_fields.hash(&mut hasher);
+        let runtime_fields_hash = hasher.finish();
+
         CacheKey {
             split_id: split_info.split_id,
             request: search_request,
             merged_time_range,
+            runtime_fields_hash,
         }
     }
 }
@@ -191,6 +205,7 @@ impl std::ops::RangeBounds<i64> for Range {
 
 #[cfg(test)]
 mod tests {
+    use quickwit_doc_mapper::{RuntimeField, RuntimeFieldType};
     use quickwit_proto::search::{
         LeafSearchResponse, PartialHit, SearchRequest, SortValue, SplitIdAndFooterOffsets,
     };
@@ -208,6 +223,7 @@ mod tests {
             timestamp_start: None,
             timestamp_end: None,
             num_docs: 0,
+            index_sort: Vec::new(),
         };
 
         let split_2 = SplitIdAndFooterOffsets {
@@ -217,6 +233,7 @@ mod tests {
             timestamp_start: None,
             timestamp_end: None,
             num_docs: 0,
+            index_sort: Vec::new(),
         };
 
         let query_1 = SearchRequest {
@@ -252,14 +269,43 @@ mod tests {
                 sort_value2: None,
                 split_id: "split_1".to_string(),
             }],
+            split_profiles: Vec::new(),
+            num_bytes_read: 0,
         };
 
-        assert!(cache.get(split_1.clone(), query_1.clone()).is_none());
-
-        cache.put(split_1.clone(), query_1.clone(), result.clone());
-        assert_eq!(cache.get(split_1.clone(), query_1.clone()).unwrap(), result);
-        assert!(cache.get(split_2, query_1).is_none());
-        assert!(cache.get(split_1, query_2).is_none());
+        assert!(cache.get(split_1.clone(), query_1.clone(), &[]).is_none());
+
+        cache.put(split_1.clone(), query_1.clone(), &[], result.clone());
+        assert_eq!(
+            cache.get(split_1.clone(), query_1.clone(), &[]).unwrap(),
+            result
+        );
+        assert!(cache.get(split_2, query_1.clone(), &[]).is_none());
+        assert!(cache.get(split_1.clone(), query_2, &[]).is_none());
+
+        // Changing the script of a runtime field of the doc mapping invalidates the entry.
+        let runtime_fields = vec![RuntimeField {
+            name: "status_class".to_string(),
+            field_type: RuntimeFieldType::I64,
+            script: ".status / 100".to_string(),
+        }];
+        cache.put(
+            split_1.clone(),
+            query_1.clone(),
+            &runtime_fields,
+            result.clone(),
+        );
+        assert_eq!(
+            cache
+                .get(split_1.clone(), query_1.clone(), &runtime_fields)
+                .unwrap(),
+            result
+        );
+        let mut updated_runtime_fields = runtime_fields.clone();
+        updated_runtime_fields[0].script = ".status / 10".to_string();
+        assert!(cache
+            .get(split_1, query_1, &updated_runtime_fields)
+            .is_none());
     }
 
     #[test]
@@ -273,6 +319,7 @@ mod tests {
             timestamp_start: Some(100),
             timestamp_end: Some(199),
             num_docs: 0,
+            index_sort: Vec::new(),
         };
         let split_2 = SplitIdAndFooterOffsets {
             split_id: "split_2".to_string(),
@@ -281,6 +328,7 @@ mod tests {
             timestamp_start: Some(150),
             timestamp_end: Some(249),
             num_docs: 0,
+            index_sort: Vec::new(),
         };
         let split_3 = SplitIdAndFooterOffsets {
             split_id: "split_3".to_string(),
@@ -289,6 +337,7 @@ mod tests {
             timestamp_start: Some(150),
             timestamp_end: Some(249),
             num_docs: 0,
+            index_sort: Vec::new(),
         };
 
         let query_1 = SearchRequest {
@@ -342,31 +391,35 @@ mod tests {
                 sort_value2: None,
                 split_id: "split_1".to_string(),
             }],
+            split_profiles: Vec::new(),
+            num_bytes_read: 0,
         };
 
         // for split_1, 1 and 1bis cover different timestamp ranges
-        cache.put(split_1.clone(), query_1.clone(), result.clone());
-        assert!(cache.get(split_1.clone(), query_1.clone()).is_some());
-        assert!(cache.get(split_1.clone(), query_1bis.clone()).is_none());
+        cache.put(split_1.clone(), query_1.clone(), &[], result.clone());
+        assert!(cache.get(split_1.clone(), query_1.clone(), &[]).is_some());
+        assert!(cache
+            .get(split_1.clone(), query_1bis.clone(), &[])
+            .is_none());
 
         // for split_2, both 1 and 1bis cover everything, so it should cache-hit
-        cache.put(split_2.clone(), query_1.clone(), result.clone());
-        assert!(cache.get(split_2.clone(), query_1).is_some());
-        assert!(cache.get(split_2.clone(), query_1bis).is_some());
+        cache.put(split_2.clone(), query_1.clone(), &[], result.clone());
+        assert!(cache.get(split_2.clone(), query_1, &[]).is_some());
+        assert!(cache.get(split_2.clone(), query_1bis, &[]).is_some());
 
         // for split_1, both 1 and 1bis cover everything, so it should cache-hit
-        cache.put(split_1.clone(), query_2.clone(), result.clone());
-        assert!(cache.get(split_1.clone(), query_2.clone()).is_some());
-        assert!(cache.get(split_1, query_2bis.clone()).is_some());
+        cache.put(split_1.clone(), query_2.clone(), &[], result.clone());
+        assert!(cache.get(split_1.clone(), query_2.clone(), &[]).is_some());
+        assert!(cache.get(split_1, query_2bis.clone(), &[]).is_some());
 
         // for split_2, 2 covers everything, but 2bis cover only a subrange
-        cache.put(split_2.clone(), query_2.clone(), result.clone());
-        assert!(cache.get(split_2.clone(), query_2.clone()).is_some());
-        assert!(cache.get(split_2, query_2bis.clone()).is_none());
+        cache.put(split_2.clone(), query_2.clone(), &[], result.clone());
+        assert!(cache.get(split_2.clone(), query_2.clone(), &[]).is_some());
+        assert!(cache.get(split_2, query_2bis.clone(), &[]).is_none());
 
         // same for split_3, but we try caching the bounded request and query for the unbounded one
-        cache.put(split_3.clone(), query_2bis.clone(), result);
-        assert!(cache.get(split_3.clone(), query_2).is_none());
-        assert!(cache.get(split_3, query_2bis).is_some());
+        cache.put(split_3.clone(), query_2bis.clone(), &[], result);
+        assert!(cache.get(split_3.clone(), query_2, &[]).is_none());
+        assert!(cache.get(split_3, query_2bis, &[]).is_some());
     }
 }
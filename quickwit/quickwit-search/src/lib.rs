@@ -29,18 +29,23 @@ mod error;
 mod fetch_docs;
 mod filters;
 mod find_trace_ids_collector;
+mod ingester_search;
 mod leaf;
 mod leaf_cache;
 mod list_fields;
 mod list_fields_cache;
 mod list_terms;
+mod nested_aggregation;
 mod retry;
 mod root;
+mod root_cache;
+mod runtime_fields;
 mod scroll_context;
 mod search_job_placer;
 mod search_response_rest;
 mod search_stream;
 mod service;
+mod slow_query_log;
 pub(crate) mod top_k_collector;
 
 mod metrics;
@@ -62,20 +67,27 @@ use tantivy::schema::NamedFieldDocument;
 /// Refer to this as `crate::Result<T>`.
 pub type Result<T> = std::result::Result<T, SearchError>;
 
+use std::collections::HashSet;
 use std::net::{Ipv4Addr, SocketAddr};
 use std::sync::{Arc, OnceLock};
 
 pub use find_trace_ids_collector::FindTraceIdsCollector;
+use itertools::Itertools;
 use quickwit_config::SearcherConfig;
 use quickwit_doc_mapper::tag_pruning::TagFilterAst;
+use quickwit_doc_mapper::zone_map_pruning::ZoneMapFilter;
 use quickwit_metastore::{
     IndexMetadata, ListIndexesMetadataResponseExt, ListSplitsQuery, ListSplitsRequestExt,
     MetastoreServiceStreamSplitsExt, SplitMetadata, SplitState,
 };
-use quickwit_proto::search::{PartialHit, SearchRequest, SearchResponse, SplitIdAndFooterOffsets};
-use quickwit_proto::types::IndexUid;
+use quickwit_proto::ingest::ingester::IngesterServiceClient;
+use quickwit_proto::search::{
+    PartialHit, SearchRequest, SearchResponse, SortField, SplitIdAndFooterOffsets,
+};
+use quickwit_proto::types::{IndexUid, NodeId};
 use quickwit_storage::StorageResolver;
 pub use service::SearcherContext;
+pub use slow_query_log::SlowQueryLog;
 use tantivy::DocAddress;
 
 pub use crate::client::{
@@ -84,11 +96,12 @@ pub use crate::client::{
 pub use crate::cluster_client::ClusterClient;
 pub use crate::error::{parse_grpc_error, SearchError};
 use crate::fetch_docs::fetch_docs;
+pub use crate::nested_aggregation::{ElementAggregation, NestedAggregation, NestedAggregations};
 pub use crate::root::{
     check_all_index_metadata_found, jobs_to_leaf_request, root_search, search_plan,
     IndexMetasForLeafSearch, SearchJob,
 };
-pub use crate::search_job_placer::{Job, SearchJobPlacer};
+pub use crate::search_job_placer::{Job, SearchJobPlacer, SearcherZonePool};
 pub use crate::search_response_rest::{SearchPlanResponseRest, SearchResponseRest};
 pub use crate::search_stream::root_search_stream;
 pub use crate::service::{MockSearchService, SearchService, SearchServiceImpl};
@@ -96,6 +109,9 @@ pub use crate::service::{MockSearchService, SearchService, SearchServiceImpl};
 /// A pool of searcher clients identified by their gRPC socket address.
 pub type SearcherPool = Pool<SocketAddr, SearchServiceClient>;
 
+/// A pool of ingester clients identified by their node ID.
+pub type IngesterPool = Pool<NodeId, IngesterServiceClient>;
+
 fn search_thread_pool() -> &'static ThreadPool {
     static SEARCH_THREAD_POOL: OnceLock<ThreadPool> = OnceLock::new();
     SEARCH_THREAD_POOL.get_or_init(|| ThreadPool::new("search", None))
@@ -175,6 +191,15 @@ fn extract_split_and_footer_offsets(split_metadata: &SplitMetadata) -> SplitIdAn
             .as_ref()
             .map(|time_range| *time_range.end()),
         num_docs: split_metadata.num_docs as u64,
+        index_sort: split_metadata
+            .index_sort
+            .iter()
+            .map(|index_sort_field| SortField {
+                field_name: index_sort_field.field.clone(),
+                sort_order: index_sort_field.order as i32,
+                sort_datetime_format: None,
+            })
+            .collect(),
     }
 }
 
@@ -183,7 +208,7 @@ pub async fn list_all_splits(
     index_uids: Vec<IndexUid>,
     metastore: &mut MetastoreServiceClient,
 ) -> crate::Result<Vec<SplitMetadata>> {
-    list_relevant_splits(index_uids, None, None, None, metastore).await
+    list_relevant_splits(index_uids, None, None, None, Vec::new(), metastore).await
 }
 
 /// Extract the list of relevant splits for a given request.
@@ -192,6 +217,7 @@ pub async fn list_relevant_splits(
     start_timestamp: Option<i64>,
     end_timestamp: Option<i64>,
     tags_filter_opt: Option<TagFilterAst>,
+    zone_map_filters: Vec<ZoneMapFilter>,
     metastore: &mut MetastoreServiceClient,
 ) -> crate::Result<Vec<SplitMetadata>> {
     let Some(mut query) = ListSplitsQuery::try_from_index_uids(index_uids) else {
@@ -208,6 +234,9 @@ pub async fn list_relevant_splits(
     if let Some(tags_filter) = tags_filter_opt {
         query = query.with_tags_filter(tags_filter);
     }
+    for zone_map_filter in zone_map_filters {
+        query = query.with_zone_map_filter(zone_map_filter);
+    }
     let list_splits_request = ListSplitsRequest::try_from_list_splits_query(&query)?;
     let splits_metadata: Vec<SplitMetadata> = metastore
         .list_splits(list_splits_request)
@@ -217,6 +246,41 @@ pub async fn list_relevant_splits(
     Ok(splits_metadata)
 }
 
+/// Lists the splits with the given IDs, pinned by a client paginating through a consistent set of
+/// splits. The splits marked for deletion are listed as well, as they remain searchable until they
+/// are garbage collected.
+///
+/// Returns an error if some of the splits do not exist anymore.
+pub async fn list_pinned_splits(
+    index_uids: Vec<IndexUid>,
+    split_ids: &[String],
+    metastore: &mut MetastoreServiceClient,
+) -> crate::Result<Vec<SplitMetadata>> {
+    let Some(mut query) = ListSplitsQuery::try_from_index_uids(index_uids) else {
+        return Ok(Vec::new());
+    };
+    query = query.with_split_states([SplitState::Published, SplitState::MarkedForDeletion]);
+
+    let list_splits_request = ListSplitsRequest::try_from_list_splits_query(&query)?;
+    let mut missing_split_ids: HashSet<&str> = split_ids.iter().map(String::as_str).collect();
+    let splits_metadata: Vec<SplitMetadata> = metastore
+        .list_splits(list_splits_request)
+        .await?
+        .collect_splits_metadata()
+        .await?
+        .into_iter()
+        .filter(|split_metadata| missing_split_ids.remove(split_metadata.split_id()))
+        .collect();
+    if !missing_split_ids.is_empty() {
+        let missing_split_ids = missing_split_ids.into_iter().sorted().join(", ");
+        return Err(SearchError::InvalidArgument(format!(
+            "splits [{missing_split_ids}] do not exist anymore: they were most likely merged and \
+             garbage collected"
+        )));
+    }
+    Ok(splits_metadata)
+}
+
 /// Resolve index patterns and returns IndexMetadata for found indices.
 /// Patterns follow the elastic search patterns.
 pub async fn resolve_index_patterns(
@@ -262,9 +326,10 @@ pub async fn start_searcher_service(
     metastore: MetastoreServiceClient,
     storage_resolver: StorageResolver,
     search_job_placer: SearchJobPlacer,
+    ingester_pool: IngesterPool,
     searcher_context: Arc<SearcherContext>,
 ) -> anyhow::Result<Arc<dyn SearchService>> {
-    let cluster_client = ClusterClient::new(search_job_placer);
+    let cluster_client = ClusterClient::new(search_job_placer).with_ingester_pool(ingester_pool);
     let search_service = Arc::new(SearchServiceImpl::new(
         metastore,
         storage_resolver,
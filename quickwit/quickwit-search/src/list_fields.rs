@@ -319,6 +319,7 @@ pub async fn root_list_fields(
         list_fields_req.start_timestamp,
         list_fields_req.end_timestamp,
         None,
+        Vec::new(),
         &mut metastore,
     )
     .await?;
@@ -88,6 +88,7 @@ mod tests {
             timestamp_start: None,
             timestamp_end: None,
             num_docs: 0,
+            index_sort: Vec::new(),
         };
 
         let split_2 = SplitIdAndFooterOffsets {
@@ -97,6 +98,7 @@ mod tests {
             timestamp_start: None,
             timestamp_end: None,
             num_docs: 0,
+            index_sort: Vec::new(),
         };
 
         let result = ListFieldsEntryResponse {
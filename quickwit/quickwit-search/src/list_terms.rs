@@ -216,7 +216,7 @@ async fn leaf_list_terms_single_split(
     storage: Arc<dyn Storage>,
     split: SplitIdAndFooterOffsets,
 ) -> crate::Result<LeafListTermsResponse> {
-    let index = open_index_with_caches(searcher_context, storage, &split, None, true).await?;
+    let index = open_index_with_caches(searcher_context, storage, &split, None, true, None).await?;
     let split_schema = index.schema();
     let reader = index
         .reader_builder()
@@ -0,0 +1,899 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use quickwit_common::shared_consts::NESTED_FIELD_PREFIX;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+use tantivy::aggregation::agg_req::{get_fast_field_names, Aggregations};
+use tantivy::aggregation::agg_result::AggregationResults;
+use tantivy::aggregation::intermediate_agg_result::IntermediateAggregationResults;
+use tantivy::aggregation::{AggregationLimitsGuard, AggregationSegmentCollector};
+use tantivy::collector::SegmentCollector;
+use tantivy::columnar::{BytesColumn, MonotonicallyMappableToU64};
+use tantivy::schema::Schema;
+use tantivy::{DocId, SegmentOrdinal, SegmentReader, TantivyError};
+
+/// Aggregations computed over the elements of the nested fields of the matching documents, along
+/// with the regular aggregations of the request.
+///
+/// The elements of a nested field are read from its `_nested.<path>` fast field, which holds one
+/// JSON object per element: the sub-aggregations of a [`NestedAggregation`] count elements rather
+/// than documents, and never mix up the values of different elements.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NestedAggregations {
+    /// Aggregations over the elements of nested fields, by name.
+    pub nested_aggregations: BTreeMap<String, NestedAggregation>,
+    /// Regular aggregations over the matching documents.
+    #[serde(default)]
+    pub aggregations: Aggregations,
+}
+
+/// Aggregation over the elements of the nested field located at `path`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NestedAggregation {
+    /// Path of the nested field.
+    pub path: String,
+    /// Sub-aggregations computed over the elements, by name.
+    #[serde(default)]
+    pub aggs: BTreeMap<String, ElementAggregation>,
+}
+
+/// Aggregation computed over the elements of a nested field.
+///
+/// Fields are designated by their full path, which starts with the path of the nested field.
+/// Metric aggregations only consider numeric values.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ElementAggregation {
+    /// Buckets the elements by the values of `field`, and returns the `size` largest buckets.
+    Terms {
+        /// Field to bucket the elements by.
+        field: String,
+        /// Number of buckets to return.
+        size: usize,
+        /// Sub-aggregations computed over the elements of each bucket, by name.
+        #[serde(default)]
+        aggs: BTreeMap<String, ElementAggregation>,
+    },
+    /// Counts the values of `field`.
+    ValueCount {
+        /// Field to count the values of.
+        field: String,
+    },
+    /// Returns the smallest value of `field`.
+    Min {
+        /// Field to aggregate.
+        field: String,
+    },
+    /// Returns the largest value of `field`.
+    Max {
+        /// Field to aggregate.
+        field: String,
+    },
+    /// Returns the sum of the values of `field`.
+    Sum {
+        /// Field to aggregate.
+        field: String,
+    },
+    /// Returns the average of the values of `field`.
+    Avg {
+        /// Field to aggregate.
+        field: String,
+    },
+    /// Returns the count, min, max, average, and sum of the values of `field`.
+    Stats {
+        /// Field to aggregate.
+        field: String,
+    },
+    /// Counts the documents the elements belong to.
+    ReverseNested,
+}
+
+impl NestedAggregations {
+    /// Returns the list of fast fields that should be loaded for the aggregations.
+    pub(crate) fn fast_field_names(&self) -> HashSet<String> {
+        let mut fast_field_names = get_fast_field_names(&self.aggregations);
+        fast_field_names.extend(
+            self.nested_aggregations
+                .values()
+                .map(NestedAggregation::nested_field_name),
+        );
+        fast_field_names
+    }
+
+    /// Checks that the nested aggregations target nested fields of `schema`, and that their
+    /// sub-aggregations target subfields of these nested fields.
+    pub(crate) fn validate(&self, schema: &Schema) -> Result<(), String> {
+        for (name, nested_aggregation) in &self.nested_aggregations {
+            if schema
+                .get_field(&nested_aggregation.nested_field_name())
+                .is_err()
+            {
+                return Err(format!(
+                    "field `{}` of nested aggregation `{name}` is not a nested field",
+                    nested_aggregation.path
+                ));
+            }
+            resolve_element_aggregations(&nested_aggregation.aggs, &nested_aggregation.path)
+                .map_err(|error| error.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+impl NestedAggregation {
+    /// Returns the name of the fast field holding the elements of the nested field.
+    pub fn nested_field_name(&self) -> String {
+        format!("{NESTED_FIELD_PREFIX}{}", self.path)
+    }
+}
+
+/// Splits a field path into its segments. Dots escaped with `\` do not count as separators.
+fn split_field_path(field_path: &str) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut current_segment = String::new();
+    let mut escaped = false;
+    for chr in field_path.chars() {
+        if escaped {
+            current_segment.push(chr);
+            escaped = false;
+        } else if chr == '\\' {
+            escaped = true;
+        } else if chr == '.' {
+            segments.push(std::mem::take(&mut current_segment));
+        } else {
+            current_segment.push(chr);
+        }
+    }
+    if !current_segment.is_empty() {
+        segments.push(current_segment);
+    }
+    segments
+}
+
+/// Returns the path of `field` relative to the elements of the nested field located at
+/// `nested_path`.
+fn element_path(field: &str, nested_path: &str) -> tantivy::Result<Vec<String>> {
+    let nested_path = split_field_path(nested_path);
+    let field_path = split_field_path(field);
+    if field_path.len() <= nested_path.len() || field_path[..nested_path.len()] != nested_path[..] {
+        return Err(TantivyError::InvalidArgument(format!(
+            "field `{field}` does not belong to nested field `{}`",
+            nested_path.join(".")
+        )));
+    }
+    Ok(field_path[nested_path.len()..].to_vec())
+}
+
+/// Collects the values located at `path` in `json_value`, opening arrays along the way.
+fn collect_values_at_path<'a>(
+    json_value: &'a JsonValue,
+    path: &[String],
+    values: &mut Vec<&'a JsonValue>,
+) {
+    match json_value {
+        JsonValue::Array(json_values) => {
+            for json_value in json_values {
+                collect_values_at_path(json_value, path, values);
+            }
+        }
+        JsonValue::Object(json_obj) => {
+            if let Some((first_segment, sub_path)) = path.split_first() {
+                if let Some(child_json_value) = json_obj.get(first_segment) {
+                    collect_values_at_path(child_json_value, sub_path, values);
+                }
+            }
+        }
+        JsonValue::Null => {}
+        _ => {
+            if path.is_empty() {
+                values.push(json_value);
+            }
+        }
+    }
+}
+
+/// Key of a terms bucket. Numbers are stored as monotonically mapped `f64`s so that keys can be
+/// ordered and compared exactly.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+enum TermKey {
+    Bool(bool),
+    Number(u64),
+    Str(String),
+}
+
+impl TermKey {
+    fn from_json(json_value: &JsonValue) -> Option<TermKey> {
+        match json_value {
+            JsonValue::Bool(bool_val) => Some(TermKey::Bool(*bool_val)),
+            JsonValue::Number(number) => number.as_f64().map(|val| TermKey::Number(val.to_u64())),
+            JsonValue::String(text) => Some(TermKey::Str(text.clone())),
+            JsonValue::Null | JsonValue::Array(_) | JsonValue::Object(_) => None,
+        }
+    }
+
+    /// Adds the `key` (and `key_as_string` for booleans) of the bucket, formatted like
+    /// Elasticsearch does.
+    fn add_to_bucket(self, bucket_json: &mut JsonMap<String, JsonValue>) {
+        match self {
+            TermKey::Bool(bool_val) => {
+                bucket_json.insert("key".to_string(), json!(bool_val as u64));
+                bucket_json.insert("key_as_string".to_string(), json!(bool_val.to_string()));
+            }
+            TermKey::Number(mapped_val) => {
+                let val = f64::from_u64(mapped_val);
+                let key = if val.fract() == 0.0 && val.abs() < (1u64 << 53) as f64 {
+                    json!(val as i64)
+                } else {
+                    json!(val)
+                };
+                bucket_json.insert("key".to_string(), key);
+            }
+            TermKey::Str(text) => {
+                bucket_json.insert("key".to_string(), json!(text));
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct IntermediateStats {
+    count: u64,
+    sum: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Default for IntermediateStats {
+    fn default() -> Self {
+        IntermediateStats {
+            count: 0,
+            sum: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+}
+
+impl IntermediateStats {
+    fn collect(&mut self, val: f64) {
+        self.count += 1;
+        self.sum += val;
+        self.min = self.min.min(val);
+        self.max = self.max.max(val);
+    }
+
+    fn merge(&mut self, other: IntermediateStats) {
+        self.count += other.count;
+        self.sum += other.sum;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    fn min_opt(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.min)
+    }
+
+    fn max_opt(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.max)
+    }
+
+    fn avg_opt(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum / self.count as f64)
+    }
+}
+
+/// Intermediate result of the aggregations of a set of elements: all the elements of a nested
+/// field, or the elements of a terms bucket.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+struct IntermediateElementsResult {
+    /// Number of elements.
+    doc_count: u64,
+    /// Number of documents the elements belong to, returned by `reverse_nested` aggregations.
+    parent_doc_count: u64,
+    aggs: BTreeMap<String, IntermediateElementAggregationResult>,
+    /// Sequence number of the last document an element of which was collected, used to count
+    /// each document once.
+    #[serde(skip)]
+    last_doc_seq: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum IntermediateElementAggregationResult {
+    Terms(BTreeMap<TermKey, IntermediateElementsResult>),
+    Stats(IntermediateStats),
+    ReverseNested,
+}
+
+/// Element aggregation whose field path has been resolved relative to the elements.
+struct ResolvedElementAggregation {
+    name: String,
+    aggregation: ElementAggregation,
+    element_path: Vec<String>,
+    sub_aggregations: Vec<ResolvedElementAggregation>,
+}
+
+fn resolve_element_aggregations(
+    aggs: &BTreeMap<String, ElementAggregation>,
+    nested_path: &str,
+) -> tantivy::Result<Vec<ResolvedElementAggregation>> {
+    let mut resolved_aggregations = Vec::with_capacity(aggs.len());
+    for (name, aggregation) in aggs {
+        let (element_path, sub_aggregations) = match aggregation {
+            ElementAggregation::Terms { field, aggs, .. } => (
+                element_path(field, nested_path)?,
+                resolve_element_aggregations(aggs, nested_path)?,
+            ),
+            ElementAggregation::ValueCount { field }
+            | ElementAggregation::Min { field }
+            | ElementAggregation::Max { field }
+            | ElementAggregation::Sum { field }
+            | ElementAggregation::Avg { field }
+            | ElementAggregation::Stats { field } => {
+                (element_path(field, nested_path)?, Vec::new())
+            }
+            ElementAggregation::ReverseNested => (Vec::new(), Vec::new()),
+        };
+        resolved_aggregations.push(ResolvedElementAggregation {
+            name: name.clone(),
+            aggregation: aggregation.clone(),
+            element_path,
+            sub_aggregations,
+        });
+    }
+    Ok(resolved_aggregations)
+}
+
+impl IntermediateElementsResult {
+    fn new(resolved_aggregations: &[ResolvedElementAggregation]) -> Self {
+        let aggs = resolved_aggregations
+            .iter()
+            .map(|resolved_aggregation| {
+                let agg_result = match &resolved_aggregation.aggregation {
+                    ElementAggregation::Terms { .. } => {
+                        IntermediateElementAggregationResult::Terms(BTreeMap::new())
+                    }
+                    ElementAggregation::ReverseNested => {
+                        IntermediateElementAggregationResult::ReverseNested
+                    }
+                    _ => IntermediateElementAggregationResult::Stats(IntermediateStats::default()),
+                };
+                (resolved_aggregation.name.clone(), agg_result)
+            })
+            .collect();
+        IntermediateElementsResult {
+            aggs,
+            ..Default::default()
+        }
+    }
+
+    fn collect_element(
+        &mut self,
+        element: &JsonValue,
+        resolved_aggregations: &[ResolvedElementAggregation],
+        doc_seq: u64,
+    ) {
+        self.doc_count += 1;
+        if self.last_doc_seq != doc_seq {
+            self.last_doc_seq = doc_seq;
+            self.parent_doc_count += 1;
+        }
+        let mut values = Vec::new();
+        for resolved_aggregation in resolved_aggregations {
+            let Some(agg_result) = self.aggs.get_mut(&resolved_aggregation.name) else {
+                continue;
+            };
+            values.clear();
+            collect_values_at_path(element, &resolved_aggregation.element_path, &mut values);
+
+            match (agg_result, &resolved_aggregation.aggregation) {
+                (IntermediateElementAggregationResult::Terms(buckets), _) => {
+                    let term_keys: BTreeSet<TermKey> = values
+                        .iter()
+                        .copied()
+                        .filter_map(TermKey::from_json)
+                        .collect();
+                    for term_key in term_keys {
+                        buckets
+                            .entry(term_key)
+                            .or_insert_with(|| {
+                                IntermediateElementsResult::new(
+                                    &resolved_aggregation.sub_aggregations,
+                                )
+                            })
+                            .collect_element(
+                                element,
+                                &resolved_aggregation.sub_aggregations,
+                                doc_seq,
+                            );
+                    }
+                }
+                (
+                    IntermediateElementAggregationResult::Stats(stats),
+                    ElementAggregation::ValueCount { .. },
+                ) => {
+                    stats.count += values.len() as u64;
+                }
+                (IntermediateElementAggregationResult::Stats(stats), _) => {
+                    for val in values.iter().filter_map(|value| value.as_f64()) {
+                        stats.collect(val);
+                    }
+                }
+                (IntermediateElementAggregationResult::ReverseNested, _) => {}
+            }
+        }
+    }
+
+    fn merge(&mut self, other: IntermediateElementsResult) {
+        self.doc_count += other.doc_count;
+        self.parent_doc_count += other.parent_doc_count;
+        for (name, other_agg_result) in other.aggs {
+            match self.aggs.entry(name) {
+                Entry::Vacant(entry) => {
+                    entry.insert(other_agg_result);
+                }
+                Entry::Occupied(mut entry) => entry.get_mut().merge(other_agg_result),
+            }
+        }
+    }
+
+    fn into_final_result(
+        mut self,
+        aggs: &BTreeMap<String, ElementAggregation>,
+    ) -> JsonMap<String, JsonValue> {
+        let mut result_json = JsonMap::new();
+        result_json.insert("doc_count".to_string(), json!(self.doc_count));
+
+        for (name, aggregation) in aggs {
+            let agg_result_opt = self.aggs.remove(name);
+            let agg_result_json = element_aggregation_final_result(
+                aggregation,
+                agg_result_opt,
+                self.parent_doc_count,
+            );
+            result_json.insert(name.clone(), agg_result_json);
+        }
+        result_json
+    }
+}
+
+impl IntermediateElementAggregationResult {
+    fn merge(&mut self, other: IntermediateElementAggregationResult) {
+        match (self, other) {
+            (
+                IntermediateElementAggregationResult::Terms(buckets),
+                IntermediateElementAggregationResult::Terms(other_buckets),
+            ) => {
+                for (term_key, other_bucket) in other_buckets {
+                    match buckets.entry(term_key) {
+                        Entry::Vacant(entry) => {
+                            entry.insert(other_bucket);
+                        }
+                        Entry::Occupied(mut entry) => entry.get_mut().merge(other_bucket),
+                    }
+                }
+            }
+            (
+                IntermediateElementAggregationResult::Stats(stats),
+                IntermediateElementAggregationResult::Stats(other_stats),
+            ) => stats.merge(other_stats),
+            _ => {}
+        }
+    }
+}
+
+fn element_aggregation_final_result(
+    aggregation: &ElementAggregation,
+    agg_result_opt: Option<IntermediateElementAggregationResult>,
+    parent_doc_count: u64,
+) -> JsonValue {
+    let stats = match &agg_result_opt {
+        Some(IntermediateElementAggregationResult::Stats(stats)) => *stats,
+        _ => IntermediateStats::default(),
+    };
+    match aggregation {
+        ElementAggregation::Terms { size, aggs, .. } => {
+            let buckets = match agg_result_opt {
+                Some(IntermediateElementAggregationResult::Terms(buckets)) => buckets,
+                _ => BTreeMap::new(),
+            };
+            let mut buckets: Vec<(TermKey, IntermediateElementsResult)> =
+                buckets.into_iter().collect();
+            // Buckets are sorted by decreasing count, then by increasing key.
+            buckets.sort_by(|(left_key, left_bucket), (right_key, right_bucket)| {
+                right_bucket
+                    .doc_count
+                    .cmp(&left_bucket.doc_count)
+                    .then_with(|| left_key.cmp(right_key))
+            });
+            let sum_other_doc_count: u64 = buckets
+                .iter()
+                .skip(*size)
+                .map(|(_, bucket)| bucket.doc_count)
+                .sum();
+            buckets.truncate(*size);
+
+            let buckets_json: Vec<JsonValue> = buckets
+                .into_iter()
+                .map(|(term_key, bucket)| {
+                    let mut bucket_json = JsonMap::new();
+                    term_key.add_to_bucket(&mut bucket_json);
+                    bucket_json.extend(bucket.into_final_result(aggs));
+                    JsonValue::Object(bucket_json)
+                })
+                .collect();
+            json!({
+                "doc_count_error_upper_bound": 0,
+                "sum_other_doc_count": sum_other_doc_count,
+                "buckets": buckets_json,
+            })
+        }
+        ElementAggregation::ValueCount { .. } => json!({ "value": stats.count }),
+        ElementAggregation::Min { .. } => json!({ "value": stats.min_opt() }),
+        ElementAggregation::Max { .. } => json!({ "value": stats.max_opt() }),
+        ElementAggregation::Sum { .. } => json!({ "value": stats.sum }),
+        ElementAggregation::Avg { .. } => json!({ "value": stats.avg_opt() }),
+        ElementAggregation::Stats { .. } => json!({
+            "count": stats.count,
+            "min": stats.min_opt(),
+            "max": stats.max_opt(),
+            "avg": stats.avg_opt(),
+            "sum": stats.sum,
+        }),
+        ElementAggregation::ReverseNested => json!({ "doc_count": parent_doc_count }),
+    }
+}
+
+/// Intermediate result of [`NestedAggregations`], merged across segments, splits, and leaves.
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct IntermediateNestedAggregationsResult {
+    aggregations: IntermediateAggregationResults,
+    nested_aggregations: BTreeMap<String, IntermediateElementsResult>,
+}
+
+impl IntermediateNestedAggregationsResult {
+    pub(crate) fn merge_fruits(
+        &mut self,
+        other: IntermediateNestedAggregationsResult,
+    ) -> tantivy::Result<()> {
+        self.aggregations.merge_fruits(other.aggregations)?;
+
+        for (name, other_result) in other.nested_aggregations {
+            match self.nested_aggregations.entry(name) {
+                Entry::Vacant(entry) => {
+                    entry.insert(other_result);
+                }
+                Entry::Occupied(mut entry) => entry.get_mut().merge(other_result),
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the final result of the aggregations, formatted like Elasticsearch does.
+    pub(crate) fn into_final_result(
+        mut self,
+        nested_aggregations: NestedAggregations,
+        limits: AggregationLimitsGuard,
+    ) -> tantivy::Result<JsonValue> {
+        let mut result_json = JsonMap::new();
+
+        if !nested_aggregations.aggregations.is_empty() {
+            let aggregation_results: AggregationResults = self
+                .aggregations
+                .into_final_result(nested_aggregations.aggregations, limits)?;
+            let JsonValue::Object(aggregation_results_json) =
+                serde_json::to_value(aggregation_results)
+                    .map_err(|error| TantivyError::InternalError(error.to_string()))?
+            else {
+                return Err(TantivyError::InternalError(
+                    "aggregation results should serialize to a JSON object".to_string(),
+                ));
+            };
+            result_json.extend(aggregation_results_json);
+        }
+        for (name, nested_aggregation) in nested_aggregations.nested_aggregations {
+            let elements_result = self.nested_aggregations.remove(&name).unwrap_or_default();
+            let nested_result_json = elements_result.into_final_result(&nested_aggregation.aggs);
+            result_json.insert(name, JsonValue::Object(nested_result_json));
+        }
+        Ok(JsonValue::Object(result_json))
+    }
+}
+
+struct NestedAggregationSegmentCollector {
+    name: String,
+    resolved_aggregations: Vec<ResolvedElementAggregation>,
+    elements_column_opt: Option<BytesColumn>,
+    elements_result: IntermediateElementsResult,
+    buffer: Vec<u8>,
+}
+
+impl NestedAggregationSegmentCollector {
+    fn collect(&mut self, doc: DocId, doc_seq: u64) {
+        let Some(elements_column) = &self.elements_column_opt else {
+            return;
+        };
+        for term_ord in elements_column.term_ords(doc) {
+            self.buffer.clear();
+            if !matches!(
+                elements_column.ord_to_bytes(term_ord, &mut self.buffer),
+                Ok(true)
+            ) {
+                continue;
+            }
+            let Ok(element) = serde_json::from_slice::<JsonValue>(&self.buffer) else {
+                continue;
+            };
+            self.elements_result
+                .collect_element(&element, &self.resolved_aggregations, doc_seq);
+        }
+    }
+}
+
+/// Segment collector of [`NestedAggregations`].
+pub(crate) struct NestedAggregationsSegmentCollector {
+    aggregation_collector_opt: Option<AggregationSegmentCollector>,
+    nested_aggregation_collectors: Vec<NestedAggregationSegmentCollector>,
+    /// Sequence number of the last collected document, starting at 1.
+    doc_seq: u64,
+}
+
+impl NestedAggregationsSegmentCollector {
+    pub(crate) fn from_agg_req_and_reader(
+        nested_aggregations: &NestedAggregations,
+        segment_reader: &SegmentReader,
+        segment_ord: SegmentOrdinal,
+        limits: &AggregationLimitsGuard,
+    ) -> tantivy::Result<Self> {
+        let aggregation_collector_opt = if nested_aggregations.aggregations.is_empty() {
+            None
+        } else {
+            Some(AggregationSegmentCollector::from_agg_req_and_reader(
+                &nested_aggregations.aggregations,
+                segment_reader,
+                segment_ord,
+                limits,
+            )?)
+        };
+        let mut nested_aggregation_collectors =
+            Vec::with_capacity(nested_aggregations.nested_aggregations.len());
+
+        for (name, nested_aggregation) in &nested_aggregations.nested_aggregations {
+            let resolved_aggregations =
+                resolve_element_aggregations(&nested_aggregation.aggs, &nested_aggregation.path)?;
+            let elements_column_opt = segment_reader
+                .fast_fields()
+                .bytes(&nested_aggregation.nested_field_name())?;
+            let elements_result = IntermediateElementsResult::new(&resolved_aggregations);
+
+            nested_aggregation_collectors.push(NestedAggregationSegmentCollector {
+                name: name.clone(),
+                resolved_aggregations,
+                elements_column_opt,
+                elements_result,
+                buffer: Vec::new(),
+            });
+        }
+        Ok(NestedAggregationsSegmentCollector {
+            aggregation_collector_opt,
+            nested_aggregation_collectors,
+            doc_seq: 0,
+        })
+    }
+
+    #[inline]
+    pub(crate) fn collect(&mut self, doc: DocId) {
+        if let Some(aggregation_collector) = self.aggregation_collector_opt.as_mut() {
+            aggregation_collector.collect(doc, 0.0);
+        }
+        self.collect_elements(doc);
+    }
+
+    #[inline]
+    pub(crate) fn collect_block(&mut self, docs: &[DocId]) {
+        if let Some(aggregation_collector) = self.aggregation_collector_opt.as_mut() {
+            aggregation_collector.collect_block(docs);
+        }
+        for &doc in docs {
+            self.collect_elements(doc);
+        }
+    }
+
+    fn collect_elements(&mut self, doc: DocId) {
+        self.doc_seq += 1;
+
+        for nested_aggregation_collector in &mut self.nested_aggregation_collectors {
+            nested_aggregation_collector.collect(doc, self.doc_seq);
+        }
+    }
+
+    pub(crate) fn harvest(self) -> tantivy::Result<IntermediateNestedAggregationsResult> {
+        let aggregations = if let Some(aggregation_collector) = self.aggregation_collector_opt {
+            aggregation_collector.harvest()?
+        } else {
+            IntermediateAggregationResults::default()
+        };
+        let nested_aggregations = self
+            .nested_aggregation_collectors
+            .into_iter()
+            .map(|collector| (collector.name, collector.elements_result))
+            .collect();
+        Ok(IntermediateNestedAggregationsResult {
+            aggregations,
+            nested_aggregations,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect_elements(
+        nested_aggregation: &NestedAggregation,
+        docs: &[JsonValue],
+    ) -> IntermediateElementsResult {
+        let resolved_aggregations =
+            resolve_element_aggregations(&nested_aggregation.aggs, &nested_aggregation.path)
+                .unwrap();
+        let mut elements_result = IntermediateElementsResult::new(&resolved_aggregations);
+        for (doc_seq, doc) in docs.iter().enumerate() {
+            for element in doc.as_array().unwrap() {
+                elements_result.collect_element(
+                    element,
+                    &resolved_aggregations,
+                    doc_seq as u64 + 1,
+                );
+            }
+        }
+        elements_result
+    }
+
+    fn order_items_aggregation() -> NestedAggregation {
+        serde_json::from_value(json!({
+            "path": "items",
+            "aggs": {
+                "by_sku": {
+                    "terms": {
+                        "field": "items.sku",
+                        "size": 2,
+                        "aggs": {
+                            "quantity": { "sum": { "field": "items.quantity" } },
+                            "orders": "reverse_nested"
+                        }
+                    }
+                },
+                "quantity_stats": { "stats": { "field": "items.quantity" } },
+                "orders": "reverse_nested"
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_nested_aggregations_serialization() {
+        let nested_aggregations_json = json!({
+            "nested_aggregations": { "items": order_items_aggregation() },
+            "aggregations": { "count": { "value_count": { "field": "timestamp" } } }
+        });
+        let nested_aggregations: NestedAggregations =
+            serde_json::from_value(nested_aggregations_json).unwrap();
+        assert_eq!(nested_aggregations.nested_aggregations.len(), 1);
+        assert_eq!(nested_aggregations.aggregations.len(), 1);
+        assert_eq!(
+            nested_aggregations.fast_field_names(),
+            HashSet::from_iter(["timestamp".to_string(), "_nested.items".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_nested_aggregation_counts_elements() {
+        let nested_aggregation = order_items_aggregation();
+        let docs = [
+            json!([
+                { "sku": "apple", "quantity": 3 },
+                { "sku": "pear", "quantity": 1 },
+                { "sku": "apple", "quantity": 2 },
+            ]),
+            json!([{ "sku": "apple", "quantity": 5 }, { "sku": "kiwi" }]),
+            json!([{ "sku": "pear", "quantity": 4 }]),
+        ];
+        // Collect the documents in two batches, as two segments would, and merge the results.
+        let mut elements_result = collect_elements(&nested_aggregation, &docs[..1]);
+        let intermediate_bytes =
+            postcard::to_allocvec(&collect_elements(&nested_aggregation, &docs[1..])).unwrap();
+        elements_result.merge(postcard::from_bytes(&intermediate_bytes).unwrap());
+
+        let result_json = elements_result.into_final_result(&nested_aggregation.aggs);
+        assert_eq!(
+            JsonValue::Object(result_json),
+            json!({
+                "doc_count": 6,
+                "orders": { "doc_count": 3 },
+                "quantity_stats": { "count": 5, "min": 1.0, "max": 5.0, "avg": 3.0, "sum": 15.0 },
+                "by_sku": {
+                    "doc_count_error_upper_bound": 0,
+                    "sum_other_doc_count": 1,
+                    "buckets": [
+                        {
+                            "key": "apple",
+                            "doc_count": 3,
+                            "quantity": { "value": 10.0 },
+                            "orders": { "doc_count": 2 }
+                        },
+                        {
+                            "key": "pear",
+                            "doc_count": 2,
+                            "quantity": { "value": 5.0 },
+                            "orders": { "doc_count": 2 }
+                        },
+                    ]
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_nested_aggregation_rejects_foreign_fields() {
+        let nested_aggregation: NestedAggregation = serde_json::from_value(json!({
+            "path": "items",
+            "aggs": { "max_price": { "max": { "field": "price" } } }
+        }))
+        .unwrap();
+        let error =
+            resolve_element_aggregations(&nested_aggregation.aggs, &nested_aggregation.path)
+                .err()
+                .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "An invalid argument was passed: 'field `price` does not belong to nested field \
+             `items`'"
+        );
+    }
+
+    #[test]
+    fn test_term_key_json() {
+        let mut bucket_json = JsonMap::new();
+        TermKey::from_json(&json!(42))
+            .unwrap()
+            .add_to_bucket(&mut bucket_json);
+        assert_eq!(bucket_json["key"], json!(42));
+
+        let mut bucket_json = JsonMap::new();
+        TermKey::from_json(&json!(1.5))
+            .unwrap()
+            .add_to_bucket(&mut bucket_json);
+        assert_eq!(bucket_json["key"], json!(1.5));
+
+        let mut bucket_json = JsonMap::new();
+        TermKey::from_json(&json!(true))
+            .unwrap()
+            .add_to_bucket(&mut bucket_json);
+        assert_eq!(bucket_json["key"], json!(1));
+        assert_eq!(bucket_json["key_as_string"], json!("true"));
+
+        assert!(TermKey::from_json(&json!(null)).is_none());
+        assert!(TermKey::from_json(&json!({ "a": 1 })).is_none());
+    }
+}
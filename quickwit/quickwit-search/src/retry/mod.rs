@@ -102,8 +102,7 @@ mod tests {
     #[test]
     fn test_should_not_retry_if_result_is_ok() {
         let retry_policy = DefaultRetryPolicy {};
-        let response_res =
-            crate::Result::<FetchDocsResponse>::Ok(FetchDocsResponse { hits: Vec::new() });
+        let response_res = crate::Result::<FetchDocsResponse>::Ok(FetchDocsResponse::default());
         assert!(retry_policy.retry_request((), &response_res).is_none());
     }
 
@@ -134,6 +133,7 @@ mod tests {
             timestamp_start: None,
             timestamp_end: None,
             num_docs: 0,
+            index_sort: Vec::new(),
         };
         let client_for_retry = retry_client(
             &search_job_placer,
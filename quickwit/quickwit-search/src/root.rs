@@ -17,8 +17,9 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
-use std::sync::OnceLock;
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 
 use anyhow::Context;
@@ -29,8 +30,10 @@ use quickwit_common::shared_consts;
 use quickwit_common::uri::Uri;
 use quickwit_config::build_doc_mapper;
 use quickwit_doc_mapper::tag_pruning::extract_tags_from_query;
+use quickwit_doc_mapper::zone_map_pruning::{extract_zone_map_filters_from_query, ZoneMapFilter};
 use quickwit_doc_mapper::DYNAMIC_FIELD_NAME;
 use quickwit_metastore::{IndexMetadata, ListIndexesMetadataResponseExt, SplitMetadata};
+use quickwit_proto::ingest::Shard;
 use quickwit_proto::metastore::{
     ListIndexesMetadataRequest, MetastoreService, MetastoreServiceClient,
 };
@@ -38,6 +41,7 @@ use quickwit_proto::search::{
     FetchDocsRequest, FetchDocsResponse, Hit, LeafHit, LeafRequestRef, LeafSearchRequest,
     LeafSearchResponse, PartialHit, SearchPlanResponse, SearchRequest, SearchResponse,
     SnippetRequest, SortDatetimeFormat, SortField, SortValue, SplitIdAndFooterOffsets,
+    SplitSearchProfile,
 };
 use quickwit_proto::types::{IndexUid, SplitId};
 use quickwit_query::query_ast::{
@@ -48,20 +52,28 @@ use tantivy::aggregation::agg_result::AggregationResults;
 use tantivy::aggregation::intermediate_agg_result::IntermediateAggregationResults;
 use tantivy::collector::Collector;
 use tantivy::schema::{Field, FieldEntry, FieldType, Schema};
+use tantivy::time::OffsetDateTime;
 use tantivy::TantivyError;
-use tracing::{debug, info, info_span, instrument};
+use tracing::{debug, info, info_span, instrument, warn};
 
 use crate::cluster_client::ClusterClient;
 use crate::collector::{make_merge_collector, QuickwitAggregations};
 use crate::find_trace_ids_collector::Span;
+use crate::ingester_search::{
+    is_consistent_shards_snapshot, list_ingest_shards, search_ingesters_and_merge,
+    validate_real_time_request, MAX_SNAPSHOT_ATTEMPTS,
+};
 use crate::metrics::SEARCH_METRICS;
+use crate::nested_aggregation::IntermediateNestedAggregationsResult;
+use crate::root_cache::{cache_search_response, get_cached_search_response, root_search_cache_key};
+use crate::runtime_fields::{parse_runtime_fields, validate_runtime_fields};
 use crate::scroll_context::{ScrollContext, ScrollKeyAndStartOffset};
 use crate::search_job_placer::{group_by, group_jobs_by_index_id, Job};
 use crate::search_response_rest::StorageRequestCount;
 use crate::service::SearcherContext;
 use crate::{
-    extract_split_and_footer_offsets, list_relevant_splits, SearchError, SearchJobPlacer,
-    SearchPlanResponseRest, SearchServiceClient,
+    extract_split_and_footer_offsets, list_pinned_splits, list_relevant_splits, SearchError,
+    SearchJobPlacer, SearchPlanResponseRest, SearchServiceClient,
 };
 
 /// Maximum accepted scroll TTL.
@@ -171,6 +183,7 @@ struct RequestMetadata {
     query_ast_resolved: QueryAst,
     indexes_meta_for_leaf_search: IndexesMetasForLeafSearch,
     sort_fields_is_datetime: HashMap<String, bool>,
+    zone_map_filters: Vec<ZoneMapFilter>,
 }
 
 /// Validates request against each index's doc mapper and ensures that:
@@ -198,15 +211,39 @@ fn validate_request_and_build_metadata(
     let mut query_ast_resolved_opt: Option<QueryAst> = None;
     let mut timestamp_field_opt: Option<String> = None;
     let mut sort_fields_is_datetime: HashMap<String, bool> = HashMap::new();
+    let mut zone_map_filters_opt: Option<Vec<ZoneMapFilter>> = None;
+    let runtime_fields = parse_runtime_fields(search_request)?;
 
     for index_metadata in indexes_metadata {
-        let doc_mapper = build_doc_mapper(
+        let mut doc_mapper = build_doc_mapper(
             &index_metadata.index_config.doc_mapping,
             &index_metadata.index_config.search_settings,
         )
         .map_err(|err| {
             SearchError::Internal(format!("failed to build doc mapper. cause: {err}"))
         })?;
+        if !runtime_fields.is_empty() {
+            doc_mapper = doc_mapper
+                .with_runtime_fields(runtime_fields.clone())
+                .map(Arc::new)
+                .map_err(|err| SearchError::InvalidArgument(err.to_string()))?;
+        }
+        // The request is validated against the schema in which the runtime fields are regular
+        // fast fields.
+        let validation_doc_mapper = if doc_mapper.runtime_fields().is_empty() {
+            doc_mapper.clone()
+        } else {
+            validate_runtime_fields(&doc_mapper)?;
+            warn!(
+                index_id=%index_metadata.index_id(),
+                "runtime fields are evaluated over every document matching the rest of the query, \
+                 expect slow searches"
+            );
+            let runtime_doc_mapper = doc_mapper
+                .runtime_doc_mapper()
+                .map_err(|err| SearchError::InvalidArgument(err.to_string()))?;
+            Arc::new(runtime_doc_mapper)
+        };
         let query_ast_resolved_for_index = query_ast
             .clone()
             .parse_user_query(doc_mapper.default_search_fields())
@@ -226,6 +263,19 @@ fn validate_request_and_build_metadata(
             query_ast_resolved_opt = Some(query_ast_resolved_for_index.clone());
         }
 
+        // Only the zone map filters that apply to all indexes can be used to prune splits.
+        let zone_map_filters_for_index = extract_zone_map_filters_from_query(
+            &query_ast_resolved_for_index,
+            &doc_mapper.schema(),
+            doc_mapper.zone_map_field_names(),
+        );
+        if let Some(zone_map_filters) = &mut zone_map_filters_opt {
+            zone_map_filters
+                .retain(|zone_map_filter| zone_map_filters_for_index.contains(zone_map_filter));
+        } else {
+            zone_map_filters_opt = Some(zone_map_filters_for_index);
+        }
+
         // Validate uniqueness of timestamp field if any.
         if let Some(timestamp_field_for_index) = doc_mapper.timestamp_field_name() {
             match timestamp_field_opt {
@@ -243,7 +293,7 @@ fn validate_request_and_build_metadata(
         }
 
         // Validate request against the current index schema.
-        let schema = doc_mapper.schema();
+        let schema = validation_doc_mapper.schema();
         validate_request(&schema, &doc_mapper.timestamp_field_name(), search_request)?;
 
         validate_sort_field_types(
@@ -253,7 +303,11 @@ fn validate_request_and_build_metadata(
         )?;
 
         // Validates the query by effectively building it against the current schema.
-        doc_mapper.query(doc_mapper.schema(), &query_ast_resolved_for_index, true)?;
+        validation_doc_mapper.query(
+            validation_doc_mapper.schema(),
+            &query_ast_resolved_for_index,
+            true,
+        )?;
 
         let index_metadata_for_leaf_search = IndexMetasForLeafSearch {
             index_uri: index_metadata.index_uri().clone(),
@@ -278,6 +332,7 @@ fn validate_request_and_build_metadata(
         query_ast_resolved,
         indexes_meta_for_leaf_search,
         sort_fields_is_datetime,
+        zone_map_filters: zone_map_filters_opt.unwrap_or_default(),
     })
 }
 
@@ -370,6 +425,11 @@ fn simplify_search_request_for_scroll_api(req: &SearchRequest) -> crate::Result<
         // request is simplified after initial query, and we cache the hit count, so we don't need
         // to recompute it afterward.
         count_hits: quickwit_proto::search::CountHits::Underestimate as i32,
+        real_time: false,
+        runtime_fields: req.runtime_fields.clone(),
+        profile: false,
+        user_agent: None,
+        split_ids: Vec::new(),
     })
 }
 
@@ -519,6 +579,12 @@ fn validate_request(
             SearchError::InvalidAggregationRequest(err.to_string())
         })?;
 
+        if let QuickwitAggregations::NestedAggregations(nested_aggregations) = &aggs {
+            nested_aggregations
+                .validate(schema)
+                .map_err(SearchError::InvalidAggregationRequest)?;
+        }
+
         // ensure that the required fast fields are indeed configured as fast fields.
         let fast_field_names = aggs.fast_field_names();
         let dynamic_field = schema.get_field(DYNAMIC_FIELD_NAME).ok();
@@ -683,6 +749,8 @@ pub fn get_count_from_metadata(split_metadatas: &[SplitMetadata]) -> Vec<LeafSea
             num_attempted_splits: 1,
             num_successful_splits: 1,
             intermediate_aggregation_result: None,
+            split_profiles: Vec::new(),
+            num_bytes_read: 0,
         })
         .collect()
 }
@@ -760,6 +828,8 @@ pub(crate) fn get_snippet_request(search_request: &SearchRequest) -> Option<Snip
     })
 }
 
+/// Fetches the documents of the hits. Returns the hits along with the profiles of the splits if
+/// the search request is profiled.
 #[instrument(skip_all, fields(partial_hits_num=partial_hits.len()))]
 pub(crate) async fn fetch_docs_phase(
     indexes_metas_for_leaf_search: &IndexesMetasForLeafSearch,
@@ -767,7 +837,7 @@ pub(crate) async fn fetch_docs_phase(
     split_metadatas: &[SplitMetadata],
     search_request: &SearchRequest,
     cluster_client: &ClusterClient,
-) -> crate::Result<Vec<Hit>> {
+) -> crate::Result<(Vec<Hit>, Vec<SplitSearchProfile>)> {
     let snippet_request: Option<SnippetRequest> = get_snippet_request(search_request);
     let hit_order: HashMap<(String, u32, u32), usize> = partial_hits
         .iter()
@@ -793,6 +863,7 @@ pub(crate) async fn fetch_docs_phase(
     for (client, client_jobs) in assigned_fetch_docs_jobs {
         let fetch_jobs_requests = jobs_to_fetch_docs_requests(
             snippet_request.clone(),
+            search_request.profile,
             indexes_metas_for_leaf_search,
             client_jobs,
         )?;
@@ -803,9 +874,13 @@ pub(crate) async fn fetch_docs_phase(
     let fetch_docs_responses: Vec<FetchDocsResponse> = try_join_all(fetch_docs_tasks).await?;
 
     // Merge the fetched docs.
-    let leaf_hits = fetch_docs_responses
-        .into_iter()
-        .flat_map(|response| response.hits.into_iter());
+    let mut leaf_hits = Vec::new();
+    let mut split_profiles = Vec::new();
+
+    for fetch_docs_response in fetch_docs_responses {
+        leaf_hits.extend(fetch_docs_response.hits);
+        split_profiles.extend(fetch_docs_response.split_profiles);
+    }
 
     // Build map of Split ID > index ID to add the index ID to the hits.
     // Used for ES compatibility.
@@ -824,6 +899,7 @@ pub(crate) async fn fetch_docs_phase(
     let sort_field_2_datetime_format_opt: Option<SortDatetimeFormat> =
         get_sort_field_datetime_format(sort_field_iter.next())?;
     let mut hits_with_position: Vec<(usize, Hit)> = leaf_hits
+        .into_iter()
         .map(|leaf_hit| {
             build_hit_with_position(
                 leaf_hit,
@@ -841,7 +917,7 @@ pub(crate) async fn fetch_docs_phase(
         .map(|(_position, hit)| hit)
         .collect();
 
-    Ok(hits)
+    Ok((hits, split_profiles))
 }
 
 fn build_hit_with_position(
@@ -895,7 +971,7 @@ fn build_hit_with_position(
     ))
 }
 
-fn get_sort_field_datetime_format(
+pub(crate) fn get_sort_field_datetime_format(
     sort_field: Option<&SortField>,
 ) -> crate::Result<Option<SortDatetimeFormat>> {
     if let Some(sort_field) = sort_field {
@@ -913,7 +989,8 @@ fn get_sort_field_datetime_format(
 /// 1. Sends leaf request over gRPC to multiple leaf nodes.
 /// 2. Merges the search results.
 /// 3. Sends fetch docs requests to multiple leaf nodes.
-/// 4. Builds the response with docs and returns.
+/// 4. Builds the response with docs and returns it along with the number of bytes of the splits
+///    read by the leaf searchers.
 #[instrument(skip_all, fields(num_splits=%split_metadatas.len()))]
 async fn root_search_aux(
     searcher_context: &SearcherContext,
@@ -921,7 +998,7 @@ async fn root_search_aux(
     search_request: SearchRequest,
     split_metadatas: Vec<SplitMetadata>,
     cluster_client: &ClusterClient,
-) -> crate::Result<SearchResponse> {
+) -> crate::Result<(SearchResponse, u64)> {
     debug!(split_metadatas = ?PrettySample::new(&split_metadatas, 5));
     let (first_phase_result, scroll_key_and_start_offset_opt): (
         LeafSearchResponse,
@@ -935,7 +1012,7 @@ async fn root_search_aux(
     )
     .await?;
 
-    let hits = fetch_docs_phase(
+    let (hits, fetch_docs_split_profiles) = fetch_docs_phase(
         indexes_metas_for_leaf_search,
         &first_phase_result.partial_hits,
         &split_metadatas[..],
@@ -943,6 +1020,8 @@ async fn root_search_aux(
         cluster_client,
     )
     .await?;
+    let split_profiles =
+        merge_split_profiles(first_phase_result.split_profiles, fetch_docs_split_profiles);
 
     let mut aggregation_result_json_opt = finalize_aggregation_if_any(
         &search_request,
@@ -954,7 +1033,7 @@ async fn root_search_aux(
         aggregation_result_json_opt = None;
     }
 
-    Ok(SearchResponse {
+    let search_response = SearchResponse {
         aggregation: aggregation_result_json_opt,
         num_hits: first_phase_result.num_hits,
         hits,
@@ -965,7 +1044,49 @@ async fn root_search_aux(
             .map(ToString::to_string),
         failed_splits: first_phase_result.failed_splits,
         num_successful_splits: first_phase_result.num_successful_splits,
-    })
+        split_profiles,
+    };
+    Ok((search_response, first_phase_result.num_bytes_read))
+}
+
+/// Merges the profiles of the search and fetch docs phases, so that each split searched on a leaf
+/// has a single profile.
+fn merge_split_profiles(
+    search_split_profiles: Vec<SplitSearchProfile>,
+    fetch_docs_split_profiles: Vec<SplitSearchProfile>,
+) -> Vec<SplitSearchProfile> {
+    let mut merged_split_profiles: HashMap<(String, String), SplitSearchProfile> = HashMap::new();
+
+    for split_profile in search_split_profiles
+        .into_iter()
+        .chain(fetch_docs_split_profiles)
+    {
+        let key = (
+            split_profile.leaf_addr.clone(),
+            split_profile.split_id.clone(),
+        );
+        match merged_split_profiles.entry(key) {
+            Entry::Occupied(mut entry) => {
+                let merged_split_profile = entry.get_mut();
+                merged_split_profile.warmup_micros += split_profile.warmup_micros;
+                merged_split_profile.query_execution_micros += split_profile.query_execution_micros;
+                merged_split_profile.aggregation_collection_micros +=
+                    split_profile.aggregation_collection_micros;
+                merged_split_profile.fetch_docs_micros += split_profile.fetch_docs_micros;
+                merged_split_profile.storage_num_bytes += split_profile.storage_num_bytes;
+                merged_split_profile.cache_num_bytes += split_profile.cache_num_bytes;
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(split_profile);
+            }
+        }
+    }
+    merged_split_profiles
+        .into_values()
+        .sorted_by(|left, right| {
+            (&left.leaf_addr, &left.split_id).cmp(&(&right.leaf_addr, &right.split_id))
+        })
+        .collect()
 }
 
 fn finalize_aggregation(
@@ -984,6 +1105,24 @@ fn finalize_aggregation(
             let aggs: Vec<Span> = postcard::from_bytes(&intermediate_aggregation_result_bytes)?;
             serde_json::to_string(&aggs)?
         }
+        QuickwitAggregations::NestedAggregations(nested_aggregations) => {
+            let intermediate_aggregation_results =
+                if let Some(intermediate_aggregation_result_bytes) =
+                    intermediate_aggregation_result_bytes_opt
+                {
+                    let intermediate_aggregation_results: IntermediateNestedAggregationsResult =
+                        postcard::from_bytes(&intermediate_aggregation_result_bytes)?;
+                    intermediate_aggregation_results
+                } else {
+                    // Default, to return correct structure
+                    Default::default()
+                };
+            let final_aggregation_results = intermediate_aggregation_results.into_final_result(
+                nested_aggregations,
+                searcher_context.get_aggregation_limits(),
+            )?;
+            serde_json::to_string(&final_aggregation_results)?
+        }
         QuickwitAggregations::TantivyAggregations(aggregations) => {
             let intermediate_aggregation_results =
                 if let Some(intermediate_aggregation_result_bytes) =
@@ -1064,31 +1203,37 @@ pub fn check_all_index_metadata_found(
 async fn refine_and_list_matches(
     metastore: &mut MetastoreServiceClient,
     search_request: &mut SearchRequest,
-    indexes_metadata: Vec<IndexMetadata>,
-    query_ast_resolved: QueryAst,
-    sort_fields_is_datetime: HashMap<String, bool>,
-    timestamp_field_opt: Option<String>,
+    indexes_metadata: &[IndexMetadata],
+    request_metadata: &RequestMetadata,
 ) -> crate::Result<Vec<SplitMetadata>> {
     let index_uids = indexes_metadata
         .iter()
         .map(|index_metadata| index_metadata.index_uid.clone())
         .collect_vec();
-    search_request.query_ast = serde_json::to_string(&query_ast_resolved)?;
+    search_request.query_ast = serde_json::to_string(&request_metadata.query_ast_resolved)?;
 
     // convert search_after datetime values from input datetime format to nanos.
-    convert_search_after_datetime_values(search_request, &sort_fields_is_datetime)?;
+    convert_search_after_datetime_values(
+        search_request,
+        &request_metadata.sort_fields_is_datetime,
+    )?;
 
     // update_search_after_datetime_in_nanos(&mut search_request)?;
-    if let Some(timestamp_field) = &timestamp_field_opt {
+    if let Some(timestamp_field) = &request_metadata.timestamp_field_opt {
         refine_start_end_timestamp_from_ast(
-            &query_ast_resolved,
+            &request_metadata.query_ast_resolved,
             timestamp_field,
             &mut search_request.start_timestamp,
             &mut search_request.end_timestamp,
         );
     }
-    let tag_filter_ast = extract_tags_from_query(query_ast_resolved);
+    let tag_filter_ast = extract_tags_from_query(request_metadata.query_ast_resolved.clone());
 
+    if !search_request.split_ids.is_empty() {
+        // The splits are pinned by the client. They are not pruned, as the leaf searchers apply
+        // the time range and the query to their documents anyway.
+        return list_pinned_splits(index_uids, &search_request.split_ids, metastore).await;
+    }
     // TODO if search after is set, we sort by timestamp and we don't want to count all results,
     // we can refine more here. Same if we sort by _shard_doc
     let split_metadatas: Vec<SplitMetadata> = list_relevant_splits(
@@ -1096,6 +1241,7 @@ async fn refine_and_list_matches(
         search_request.start_timestamp,
         search_request.end_timestamp,
         tag_filter_ast,
+        request_metadata.zone_map_filters.clone(),
         metastore,
     )
     .await?;
@@ -1131,7 +1277,7 @@ pub async fn root_search(
         // We go through root_search_aux instead of directly
         // returning an empty response to make sure we generate
         // a (pretty useless) scroll id if requested.
-        let mut search_response = root_search_aux(
+        let (mut search_response, _) = root_search_aux(
             searcher_context,
             &HashMap::default(),
             search_request,
@@ -1144,15 +1290,103 @@ pub async fn root_search(
     }
 
     let request_metadata = validate_request_and_build_metadata(&indexes_metadata, &search_request)?;
-    let split_metadatas = refine_and_list_matches(
-        &mut metastore,
-        &mut search_request,
-        indexes_metadata,
-        request_metadata.query_ast_resolved,
-        request_metadata.sort_fields_is_datetime,
-        request_metadata.timestamp_field_opt,
-    )
-    .await?;
+
+    if search_request.real_time {
+        validate_real_time_request(
+            &search_request,
+            request_metadata.timestamp_field_opt.as_deref(),
+        )?;
+    }
+    // The permits are held until the search completes.
+    let _search_quota_permits = searcher_context.tenant_quotas.acquire_search_permits(
+        indexes_metadata
+            .iter()
+            .map(|index_metadata| index_metadata.index_id()),
+    )?;
+
+    let index_uids: Vec<IndexUid> = indexes_metadata
+        .iter()
+        .map(|index_metadata| index_metadata.index_uid.clone())
+        .collect();
+    let mut split_metadatas;
+    let mut ingest_shards_result_opt: Option<crate::Result<Vec<Shard>>> = None;
+    let mut num_attempts = 0;
+
+    // In real-time mode, the ingesters are searched from the publish positions of the shards
+    // consistent with the splits listed, see `is_consistent_shards_snapshot`.
+    loop {
+        num_attempts += 1;
+
+        let shards_before_result_opt = if search_request.real_time {
+            Some(list_ingest_shards(&mut metastore, &index_uids).await)
+        } else {
+            None
+        };
+        split_metadatas = refine_and_list_matches(
+            &mut metastore,
+            &mut search_request,
+            &indexes_metadata,
+            &request_metadata,
+        )
+        .await?;
+
+        let shards_before = match shards_before_result_opt {
+            Some(Ok(shards_before)) => shards_before,
+            Some(Err(search_error)) => {
+                ingest_shards_result_opt = Some(Err(search_error));
+                break;
+            }
+            None => break,
+        };
+        let shards_after = match list_ingest_shards(&mut metastore, &index_uids).await {
+            Ok(shards_after) => shards_after,
+            Err(search_error) => {
+                ingest_shards_result_opt = Some(Err(search_error));
+                break;
+            }
+        };
+        if is_consistent_shards_snapshot(&shards_before, &shards_after) {
+            ingest_shards_result_opt = Some(Ok(shards_after));
+            break;
+        }
+        if num_attempts == MAX_SNAPSHOT_ATTEMPTS {
+            let search_error = SearchError::Unavailable(
+                "splits kept being published while listing them".to_string(),
+            );
+            ingest_shards_result_opt = Some(Err(search_error));
+            break;
+        }
+    }
+
+    searcher_context
+        .tenant_quotas
+        .check_scanned_bytes(split_metadatas.iter().map(|split| {
+            (
+                split.index_uid.index_id.as_str(),
+                split.uncompressed_docs_size_in_bytes,
+            )
+        }))?;
+
+    let root_search_cache_key_opt =
+        searcher_context
+            .searcher_config
+            .root_search_cache
+            .and_then(|_| {
+                root_search_cache_key(
+                    &search_request,
+                    &request_metadata.indexes_meta_for_leaf_search,
+                    &split_metadatas,
+                    OffsetDateTime::now_utc(),
+                )
+            });
+    if let Some(root_search_cache_key) = &root_search_cache_key_opt {
+        if let Some(mut search_response) =
+            get_cached_search_response(cluster_client, root_search_cache_key).await
+        {
+            search_response.elapsed_time_micros = start_instant.elapsed().as_micros() as u64;
+            return Ok(search_response);
+        }
+    }
 
     let num_docs: usize = split_metadatas.iter().map(|split| split.num_docs).sum();
     let num_splits = split_metadatas.len();
@@ -1160,17 +1394,75 @@ pub async fn root_search(
     current_span.record("num_docs", num_docs);
     current_span.record("num_splits", num_splits);
 
-    let mut search_response_result = root_search_aux(
+    let real_time_search_request_opt = search_request.real_time.then(|| search_request.clone());
+    let slow_query_log_request_opt = searcher_context
+        .slow_query_log_opt
+        .as_ref()
+        .map(|_| search_request.clone());
+    let (mut search_response_result, num_bytes_scanned) = match root_search_aux(
         searcher_context,
         &request_metadata.indexes_meta_for_leaf_search,
         search_request,
         split_metadatas,
         cluster_client,
     )
-    .await;
+    .await
+    {
+        Ok((search_response, num_bytes_read)) => (Ok(search_response), num_bytes_read),
+        Err(search_error) => (Err(search_error), 0),
+    };
+
+    if let (Some(real_time_search_request), Some(ingest_shards_result), Ok(search_response)) = (
+        &real_time_search_request_opt,
+        ingest_shards_result_opt,
+        &mut search_response_result,
+    ) {
+        let search_ingesters_result = match ingest_shards_result {
+            Ok(ingest_shards) => {
+                search_ingesters_and_merge(
+                    &cluster_client.ingester_pool,
+                    real_time_search_request,
+                    ingest_shards,
+                    search_response,
+                )
+                .await
+            }
+            Err(search_error) => Err(search_error),
+        };
+        if let Err(search_error) = search_ingesters_result {
+            search_response
+                .errors
+                .push(format!("failed to search ingesters: {search_error}"));
+        }
+    }
+    if let (Some(root_search_cache_config), Some(root_search_cache_key), Ok(search_response)) = (
+        &searcher_context.searcher_config.root_search_cache,
+        &root_search_cache_key_opt,
+        &search_response_result,
+    ) {
+        cache_search_response(
+            cluster_client,
+            root_search_cache_key,
+            search_response,
+            root_search_cache_config.ttl(),
+        );
+    }
+    let elapsed = start_instant.elapsed();
 
     if let Ok(search_response) = &mut search_response_result {
-        search_response.elapsed_time_micros = start_instant.elapsed().as_micros() as u64;
+        search_response.elapsed_time_micros = elapsed.as_micros() as u64;
+    }
+    if let (Some(slow_query_log), Some(slow_query_log_request)) = (
+        &searcher_context.slow_query_log_opt,
+        &slow_query_log_request_opt,
+    ) {
+        slow_query_log.record(
+            slow_query_log_request,
+            num_splits,
+            num_bytes_scanned,
+            elapsed,
+            &search_response_result,
+        );
     }
     let label_values = if search_response_result.is_ok() {
         ["success"]
@@ -1220,10 +1512,8 @@ pub async fn search_plan(
     let split_metadatas = refine_and_list_matches(
         &mut metastore,
         &mut search_request,
-        indexes_metadata,
-        request_metadata.query_ast_resolved.clone(),
-        request_metadata.sort_fields_is_datetime,
-        request_metadata.timestamp_field_opt,
+        &indexes_metadata,
+        &request_metadata,
     )
     .await?;
 
@@ -1388,7 +1678,7 @@ fn convert_sort_datetime_value_into_nanos(
 
 /// Convert sort values from nanoseconds to the requested output format.
 /// The conversion is done only for U64 and I64 sort values, an error is returned for other types.
-fn convert_sort_datetime_value(
+pub(crate) fn convert_sort_datetime_value(
     sort_value: &mut SortValue,
     output_format: SortDatetimeFormat,
 ) -> crate::Result<()> {
@@ -1670,6 +1960,7 @@ pub fn jobs_to_leaf_request(
 /// Builds a list of [`FetchDocsRequest`], one per index, from a list of [`FetchDocsJob`].
 pub fn jobs_to_fetch_docs_requests(
     snippet_request_opt: Option<SnippetRequest>,
+    profile: bool,
     indexes_metas_for_leaf_search: &IndexesMetasForLeafSearch,
     jobs: Vec<FetchDocsJob>,
 ) -> crate::Result<Vec<FetchDocsRequest>> {
@@ -1702,6 +1993,7 @@ pub fn jobs_to_fetch_docs_requests(
                 index_uri: index_meta.index_uri.to_string(),
                 snippet_request: snippet_request_opt.clone(),
                 doc_mapper: index_meta.doc_mapper_str.clone(),
+                profile,
             };
             fetch_docs_requests.push(fetch_docs_req);
 
@@ -1718,8 +2010,12 @@ mod tests {
     use std::sync::{Arc, RwLock};
 
     use quickwit_common::shared_consts::SCROLL_BATCH_LEN;
+    use quickwit_common::tenant_quotas::{QuotaExceeded, QuotaSettings, TenantQuotas};
     use quickwit_common::ServiceStream;
-    use quickwit_config::{DocMapping, IndexConfig, IndexingSettings, SearchSettings};
+    use quickwit_config::{
+        DocMapping, IndexConfig, IndexingSettings, RootSearchCacheConfig, SearchSettings,
+        SearcherConfig,
+    };
     use quickwit_indexing::MockSplitBuilder;
     use quickwit_metastore::{IndexMetadata, ListSplitsRequestExt, ListSplitsResponseExt};
     use quickwit_proto::metastore::{
@@ -1728,10 +2024,12 @@ mod tests {
     use quickwit_proto::search::{
         ScrollRequest, SortByValue, SortOrder, SortValue, SplitSearchError,
     };
+    use quickwit_proto::{ServiceError, ServiceErrorCode};
     use quickwit_query::query_ast::{qast_helper, qast_json_helper, query_ast_from_user_text};
     use tantivy::schema::{FAST, STORED, TEXT};
 
     use super::*;
+    use crate::root_cache::RootSearchCache;
     use crate::{searcher_pool_for_test, MockSearchService};
 
     #[track_caller]
@@ -1812,6 +2110,7 @@ mod tests {
         let indexing_settings = IndexingSettings::default();
         let search_settings = SearchSettings {
             default_search_fields: vec!["body".to_string()],
+            ..Default::default()
         };
         IndexMetadata::new(IndexConfig {
             index_id: index_id.to_string(),
@@ -1820,6 +2119,7 @@ mod tests {
             indexing_settings,
             search_settings,
             retention_policy_opt: Default::default(),
+            rollup_opt: None,
         })
     }
 
@@ -1984,6 +2284,7 @@ mod tests {
         let indexing_settings = IndexingSettings::default();
         let search_settings = SearchSettings {
             default_search_fields: vec!["body".to_string()],
+            ..Default::default()
         };
         IndexMetadata::new(IndexConfig {
             index_id: index_id.to_string(),
@@ -1992,6 +2293,7 @@ mod tests {
             indexing_settings,
             search_settings,
             retention_policy_opt: Default::default(),
+            rollup_opt: None,
         })
     }
 
@@ -2538,6 +2840,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -2560,6 +2863,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -2630,6 +2934,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -2651,6 +2956,178 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_root_search_quota_exceeded() {
+        let search_request = quickwit_proto::search::SearchRequest {
+            index_id_patterns: vec!["test-index".to_string()],
+            query_ast: qast_json_helper("test", &["body"]),
+            max_hits: 10,
+            ..Default::default()
+        };
+        let mut mock_metastore = MockMetastoreService::new();
+        let index_metadata = IndexMetadata::for_test("test-index", "ram:///test-index");
+        let index_uid = index_metadata.index_uid.clone();
+        mock_metastore
+            .expect_list_indexes_metadata()
+            .returning(move |_index_ids_query| {
+                Ok(ListIndexesMetadataResponse::for_test(vec![
+                    index_metadata.clone()
+                ]))
+            });
+        mock_metastore
+            .expect_list_splits()
+            .returning(move |_list_splits_request| {
+                // Each mock split weighs 256 bytes.
+                let splits = vec![
+                    MockSplitBuilder::new("split1")
+                        .with_index_uid(&index_uid)
+                        .build(),
+                    MockSplitBuilder::new("split2")
+                        .with_index_uid(&index_uid)
+                        .build(),
+                ];
+                let splits_response = ListSplitsResponse::try_from_splits(splits).unwrap();
+                Ok(ServiceStream::from(vec![Ok(splits_response)]))
+            });
+        let metastore = MetastoreServiceClient::from_mock(mock_metastore);
+        let searcher_pool = searcher_pool_for_test([("127.0.0.1:1001", MockSearchService::new())]);
+        let search_job_placer = SearchJobPlacer::new(searcher_pool);
+        let cluster_client = ClusterClient::new(search_job_placer.clone());
+
+        let tenant_quotas = TenantQuotas::new([QuotaSettings {
+            index_id_pattern: "test-*".to_string(),
+            ingest_rate_limiter_settings_opt: None,
+            max_storage_bytes_opt: None,
+            max_concurrent_searches_opt: Some(1),
+            max_scanned_bytes_per_query_opt: Some(300),
+        }]);
+        let searcher_context =
+            SearcherContext::for_test().with_tenant_quotas(tenant_quotas.clone());
+
+        let search_error = root_search(
+            &searcher_context,
+            search_request.clone(),
+            metastore.clone(),
+            &cluster_client,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            search_error,
+            SearchError::QuotaExceeded(QuotaExceeded::ScannedBytes)
+        ));
+        assert_eq!(search_error.error_code(), ServiceErrorCode::TooManyRequests);
+
+        let _search_quota_permits = tenant_quotas
+            .acquire_search_permits(["test-index"])
+            .unwrap();
+        let search_error = root_search(
+            &searcher_context,
+            search_request,
+            metastore,
+            &cluster_client,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            search_error,
+            SearchError::QuotaExceeded(QuotaExceeded::ConcurrentSearches)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_root_search_cache() {
+        let search_request = quickwit_proto::search::SearchRequest {
+            index_id_patterns: vec!["test-index".to_string()],
+            query_ast: qast_json_helper("test", &["body"]),
+            aggregation_request: Some(
+                r#"{"max_response_time": {"max": {"field": "response_time"}}}"#.to_string(),
+            ),
+            ..Default::default()
+        };
+        let mut mock_metastore = MockMetastoreService::new();
+        let index_metadata = IndexMetadata::for_test("test-index", "ram:///test-index");
+        let index_uid = index_metadata.index_uid.clone();
+        mock_metastore
+            .expect_list_indexes_metadata()
+            .returning(move |_index_ids_query| {
+                Ok(ListIndexesMetadataResponse::for_test(vec![
+                    index_metadata.clone()
+                ]))
+            });
+        mock_metastore
+            .expect_list_splits()
+            .returning(move |_list_splits_request| {
+                let splits = vec![MockSplitBuilder::new("split1")
+                    .with_index_uid(&index_uid)
+                    .build()];
+                let splits_response = ListSplitsResponse::try_from_splits(splits).unwrap();
+                Ok(ServiceStream::from(vec![Ok(splits_response)]))
+            });
+        let metastore = MetastoreServiceClient::from_mock(mock_metastore);
+
+        let root_search_cache = Arc::new(RootSearchCache::new(1_000_000));
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service.expect_leaf_search().once().returning(
+            |_leaf_search_req: quickwit_proto::search::LeafSearchRequest| {
+                Ok(quickwit_proto::search::LeafSearchResponse {
+                    num_hits: 3,
+                    num_attempted_splits: 1,
+                    ..Default::default()
+                })
+            },
+        );
+        let root_search_cache_clone = root_search_cache.clone();
+        mock_search_service.expect_put_kv().once().returning(
+            move |put_kv_req: quickwit_proto::search::PutKvRequest| {
+                root_search_cache_clone.put(
+                    put_kv_req.key,
+                    put_kv_req.payload,
+                    Duration::from_secs(put_kv_req.ttl_secs as u64),
+                );
+            },
+        );
+        mock_search_service.expect_get_kv().times(2).returning(
+            move |get_kv_req: quickwit_proto::search::GetKvRequest| {
+                root_search_cache.get(&get_kv_req.key)
+            },
+        );
+        let searcher_pool = searcher_pool_for_test([("127.0.0.1:1001", mock_search_service)]);
+        let search_job_placer = SearchJobPlacer::new(searcher_pool);
+        let cluster_client = ClusterClient::new(search_job_placer.clone());
+
+        let searcher_config = SearcherConfig {
+            root_search_cache: Some(RootSearchCacheConfig::default()),
+            ..Default::default()
+        };
+        let searcher_context = SearcherContext::new(searcher_config, None);
+
+        let search_response = root_search(
+            &searcher_context,
+            search_request.clone(),
+            metastore.clone(),
+            &cluster_client,
+        )
+        .await
+        .unwrap();
+        assert_eq!(search_response.num_hits, 3);
+
+        // The second search is served from the cache.
+        let cached_search_response = root_search(
+            &searcher_context,
+            search_request,
+            metastore,
+            &cluster_client,
+        )
+        .await
+        .unwrap();
+        assert_eq!(cached_search_response.num_hits, 3);
+        assert_eq!(
+            cached_search_response.aggregation,
+            search_response.aggregation
+        );
+    }
+
     #[tokio::test]
     async fn test_root_search_multiple_splits() -> anyhow::Result<()> {
         let search_request = quickwit_proto::search::SearchRequest {
@@ -2702,6 +3179,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -2721,6 +3199,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -2808,6 +3287,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -2896,6 +3376,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -2937,6 +3418,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -3078,6 +3560,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -3119,6 +3602,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -3264,6 +3748,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -3291,6 +3776,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -3388,6 +3874,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -3431,6 +3918,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -3513,6 +4001,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -3641,6 +4130,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -3731,6 +4221,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -4365,6 +4856,7 @@ mod tests {
                 assert!(fetch_docs_req.partial_hits.len() <= MAX_HITS_PER_PAGE);
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -4374,6 +4866,7 @@ mod tests {
                 assert!(fetch_docs_req.partial_hits.len() <= MAX_HITS_PER_PAGE);
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -4566,6 +5059,7 @@ mod tests {
                 assert!(fetch_docs_req.partial_hits.len() <= MAX_HITS_PER_PAGE_LARGE);
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -4640,6 +5134,7 @@ mod tests {
                 assert!(fetch_docs_req.partial_hits.len() <= MAX_HITS_PER_PAGE_LARGE);
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -4839,6 +5334,7 @@ mod tests {
             .returning(|fetch_docs_req| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             });
         let searcher_pool = searcher_pool_for_test([("127.0.0.1:1001", mock_search_service_1)]);
@@ -4960,6 +5456,7 @@ mod tests {
             .returning(|fetch_docs_req| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             });
         let searcher_pool = searcher_pool_for_test([("127.0.0.1:1001", mock_search_service_1)]);
@@ -0,0 +1,304 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
+
+use itertools::Itertools;
+use prost::Message;
+use quickwit_metastore::SplitMetadata;
+use quickwit_proto::search::{SearchRequest, SearchResponse};
+use quickwit_storage::{MemorySizedCache, OwnedBytes};
+use siphasher::sip128::{Hasher128, SipHasher};
+use tantivy::time::OffsetDateTime;
+
+use crate::cluster_client::ClusterClient;
+use crate::root::IndexesMetasForLeafSearch;
+
+/// Prefix of the keys of the root search cache entries. The entries are stored on the searchers
+/// via the KV API, next to the scroll contexts.
+const ROOT_SEARCH_CACHE_KEY_PREFIX: &[u8] = b"root_search_cache:";
+
+/// A cache of the responses returned by the root searchers.
+///
+/// An entry is stored on the searchers elected by rendezvous hashing on its key, so the cache is
+/// shared by all the root searchers of the cluster.
+pub struct RootSearchCache {
+    content: MemorySizedCache<Vec<u8>>,
+}
+
+impl RootSearchCache {
+    pub fn new(capacity: usize) -> RootSearchCache {
+        RootSearchCache {
+            content: MemorySizedCache::with_capacity_in_bytes(
+                capacity,
+                &quickwit_storage::STORAGE_METRICS.root_search_cache,
+            ),
+        }
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let encoded_entry = self.content.get(key)?;
+
+        if encoded_entry.len() < 8 {
+            return None;
+        }
+        let (expiration_timestamp_bytes, payload) = encoded_entry.split_at(8);
+        let expiration_timestamp =
+            i64::from_le_bytes(expiration_timestamp_bytes.try_into().unwrap());
+
+        // Expired entries are not removed: they are evicted eventually by the entries inserted
+        // later on.
+        if expiration_timestamp <= OffsetDateTime::now_utc().unix_timestamp() {
+            return None;
+        }
+        Some(payload.to_vec())
+    }
+
+    pub fn put(&self, key: Vec<u8>, payload: Vec<u8>, ttl: Duration) {
+        let expiration_timestamp =
+            OffsetDateTime::now_utc().unix_timestamp() + ttl.as_secs() as i64;
+
+        let mut encoded_entry = Vec::with_capacity(8 + payload.len());
+        encoded_entry.extend_from_slice(&expiration_timestamp.to_le_bytes());
+        encoded_entry.extend_from_slice(&payload);
+        self.content.put(key, OwnedBytes::new(encoded_entry));
+    }
+}
+
+/// Returns true if the key belongs to the root search cache rather than to a scroll context.
+pub(crate) fn is_root_search_cache_key(key: &[u8]) -> bool {
+    key.starts_with(ROOT_SEARCH_CACHE_KEY_PREFIX)
+}
+
+/// Returns the key of the entry caching the response to the search request, or `None` if the
+/// request cannot be cached.
+///
+/// Only aggregation-only requests targeting mature splits are cached. Mature splits are never
+/// merged, so the set of splits targeted by such a request only changes when splits are published
+/// or deleted. Since the key covers the targeted splits, these events invalidate the entries
+/// of the requests they affect.
+pub(crate) fn root_search_cache_key(
+    search_request: &SearchRequest,
+    indexes_metas_for_leaf_search: &IndexesMetasForLeafSearch,
+    split_metadatas: &[SplitMetadata],
+    now: OffsetDateTime,
+) -> Option<Vec<u8>> {
+    if search_request.max_hits > 0
+        || search_request.scroll_ttl_secs.is_some()
+        || search_request.real_time
+        || search_request.profile
+    {
+        return None;
+    }
+    // Re-serializing the aggregation request makes the key insensitive to formatting.
+    let aggregation_request: serde_json::Value =
+        serde_json::from_str(search_request.aggregation_request.as_ref()?).ok()?;
+
+    if !split_metadatas
+        .iter()
+        .all(|split_metadata| split_metadata.is_mature(now))
+    {
+        return None;
+    }
+    let mut normalized_search_request = search_request.clone();
+    // The indexes are identified by their doc mappings and splits, which are hashed below.
+    normalized_search_request.index_id_patterns.clear();
+    normalized_search_request.aggregation_request = Some(aggregation_request.to_string());
+    // These parameters have no effect when no hits are requested.
+    normalized_search_request.start_offset = 0;
+    normalized_search_request.snippet_fields.clear();
+    normalized_search_request.sort_fields.clear();
+    normalized_search_request.search_after = None;
+    // The user agent is only used by the slow query log.
+    normalized_search_request.user_agent = None;
+
+    let mut hasher = SipHasher::new();
+    hasher.write(&normalized_search_request.encode_to_vec());
+
+    for (index_uid, index_metas) in indexes_metas_for_leaf_search
+        .iter()
+        .sorted_by(|(left, _), (right, _)| left.cmp(right))
+    {
+        index_uid.hash(&mut hasher);
+        index_metas.doc_mapper_str.hash(&mut hasher);
+    }
+    for split_id in split_metadatas
+        .iter()
+        .map(|split_metadata| &split_metadata.split_id)
+        .sorted()
+    {
+        split_id.hash(&mut hasher);
+    }
+    let mut key = ROOT_SEARCH_CACHE_KEY_PREFIX.to_vec();
+    key.extend_from_slice(&hasher.finish128().as_bytes());
+    Some(key)
+}
+
+/// Fetches the cached response of a search request from the searchers replicating it.
+pub(crate) async fn get_cached_search_response(
+    cluster_client: &ClusterClient,
+    key: &[u8],
+) -> Option<SearchResponse> {
+    let payload = cluster_client.get_kv_from_replicas(key).await?;
+    SearchResponse::decode(&payload[..]).ok()
+}
+
+/// Caches the response of a search request in the background, so as not to delay the response.
+/// Partial responses are not cached.
+pub(crate) fn cache_search_response(
+    cluster_client: &ClusterClient,
+    key: &[u8],
+    search_response: &SearchResponse,
+    ttl: Duration,
+) {
+    if !search_response.errors.is_empty() || !search_response.failed_splits.is_empty() {
+        return;
+    }
+    let cluster_client = cluster_client.clone();
+    let key = key.to_vec();
+    let payload = search_response.encode_to_vec();
+    tokio::spawn(async move {
+        cluster_client.put_kv(&key, &payload, ttl).await;
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use quickwit_common::uri::Uri;
+    use quickwit_metastore::SplitMaturity;
+    use quickwit_proto::search::SortField;
+    use quickwit_proto::types::IndexUid;
+
+    use super::*;
+    use crate::root::IndexMetasForLeafSearch;
+
+    fn mature_split(split_id: &str) -> SplitMetadata {
+        SplitMetadata {
+            split_id: split_id.to_string(),
+            maturity: SplitMaturity::Mature,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_root_search_cache_key() {
+        let mut indexes_metas_for_leaf_search = HashMap::new();
+        indexes_metas_for_leaf_search.insert(
+            IndexUid::for_test("test-index", 0),
+            IndexMetasForLeafSearch {
+                index_uri: Uri::for_test("ram:///test-index"),
+                doc_mapper_str: "{}".to_string(),
+            },
+        );
+        let search_request = SearchRequest {
+            index_id_patterns: vec!["test-index".to_string()],
+            query_ast: "{}".to_string(),
+            aggregation_request: Some(r#"{"count": {"value_count": {"field": "id"}}}"#.to_string()),
+            ..Default::default()
+        };
+        let splits = vec![mature_split("split-1"), mature_split("split-2")];
+        let now = OffsetDateTime::now_utc();
+
+        let key = root_search_cache_key(
+            &search_request,
+            &indexes_metas_for_leaf_search,
+            &splits,
+            now,
+        )
+        .unwrap();
+        assert!(is_root_search_cache_key(&key));
+        {
+            let mut equivalent_search_request = search_request.clone();
+            equivalent_search_request.index_id_patterns = vec!["test-*".to_string()];
+            equivalent_search_request.aggregation_request =
+                Some(r#"{"count":{"value_count":{"field":"id"}}}"#.to_string());
+            equivalent_search_request.sort_fields = vec![SortField {
+                field_name: "timestamp".to_string(),
+                ..Default::default()
+            }];
+            let reversed_splits = vec![mature_split("split-2"), mature_split("split-1")];
+            let equivalent_key = root_search_cache_key(
+                &equivalent_search_request,
+                &indexes_metas_for_leaf_search,
+                &reversed_splits,
+                now,
+            )
+            .unwrap();
+            assert_eq!(equivalent_key, key);
+        }
+        {
+            let new_splits = vec![
+                mature_split("split-1"),
+                mature_split("split-2"),
+                mature_split("split-3"),
+            ];
+            let new_key = root_search_cache_key(
+                &search_request,
+                &indexes_metas_for_leaf_search,
+                &new_splits,
+                now,
+            )
+            .unwrap();
+            assert_ne!(new_key, key);
+        }
+        {
+            let mut search_request_with_hits = search_request.clone();
+            search_request_with_hits.max_hits = 10;
+            assert!(root_search_cache_key(
+                &search_request_with_hits,
+                &indexes_metas_for_leaf_search,
+                &splits,
+                now,
+            )
+            .is_none());
+        }
+        {
+            let mut immature_splits = splits.clone();
+            immature_splits[0].create_timestamp = now.unix_timestamp();
+            immature_splits[0].maturity = SplitMaturity::Immature {
+                maturation_period: Duration::from_secs(3600),
+            };
+            assert!(root_search_cache_key(
+                &search_request,
+                &indexes_metas_for_leaf_search,
+                &immature_splits,
+                now,
+            )
+            .is_none());
+        }
+    }
+
+    #[test]
+    fn test_root_search_cache_ttl() {
+        let root_search_cache = RootSearchCache::new(1_000);
+        root_search_cache.put(
+            b"key-1".to_vec(),
+            b"value-1".to_vec(),
+            Duration::from_secs(60),
+        );
+        root_search_cache.put(b"key-2".to_vec(), b"value-2".to_vec(), Duration::ZERO);
+
+        assert_eq!(root_search_cache.get(b"key-1").unwrap(), b"value-1");
+        assert!(root_search_cache.get(b"key-2").is_none());
+        assert!(root_search_cache.get(b"key-3").is_none());
+    }
+}
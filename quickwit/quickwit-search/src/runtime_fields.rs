@@ -0,0 +1,741 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Runtime fields are not part of the splits. To search them, the documents of a split matching
+//! the part of the query that does not involve runtime fields are fetched from the doc store,
+//! the runtime field scripts are evaluated over them, and they are re-indexed into an ephemeral
+//! in-RAM index in which the runtime fields are regular fast fields. The search request is then
+//! executed against this index, and the hits are mapped back to the documents of the split.
+//!
+//! The documents are rebuilt from their source if it is stored, and from their stored fields
+//! otherwise, in which case the requests reading fields that are not stored are rejected.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use futures::stream::{self, StreamExt, TryStreamExt};
+use quickwit_doc_mapper::{
+    DocMapper, JsonObject, RuntimeField, DOCUMENT_SIZE_FIELD_NAME, DYNAMIC_FIELD_NAME,
+    SOURCE_FIELD_NAME,
+};
+use quickwit_proto::search::{LeafSearchResponse, SearchRequest};
+use quickwit_query::query_ast::{
+    BoolQuery, FieldPresenceQuery, FullTextQuery, PhrasePrefixQuery, QueryAst, QueryAstVisitor,
+    RangeQuery, TermQuery, TermSetQuery, WildcardQuery,
+};
+use tantivy::collector::{Count, DocSetCollector};
+use tantivy::schema::{Document as DocumentTrait, Schema, TantivyDocument};
+use tantivy::{DocAddress, Index, ReloadPolicy, Searcher, SingleSegmentIndexWriter};
+use tracing::warn;
+
+use crate::leaf::warmup;
+use crate::SearchError;
+
+/// Number of documents fetched from the doc store and re-indexed in a row.
+const DOC_BATCH_SIZE: usize = 1_000;
+
+/// Number of concurrent doc store requests.
+const NUM_CONCURRENT_DOC_REQUESTS: usize = 30;
+
+/// Memory budget of the writer of the ephemeral index.
+const EPHEMERAL_INDEX_MEMORY_BUDGET_IN_BYTES: usize = 50_000_000;
+
+/// Maximum number of documents of a split re-indexed into the ephemeral index.
+const MAX_NUM_REINDEXED_DOCS_PER_SPLIT: usize = 100_000;
+
+/// Parses the runtime fields defined in a search request.
+pub(crate) fn parse_runtime_fields(
+    search_request: &SearchRequest,
+) -> crate::Result<Vec<RuntimeField>> {
+    let Some(runtime_fields_json) = &search_request.runtime_fields else {
+        return Ok(Vec::new());
+    };
+    serde_json::from_str(runtime_fields_json).map_err(|error| {
+        SearchError::InvalidArgument(format!("failed to parse runtime fields: {error}"))
+    })
+}
+
+/// Checks that the runtime fields of the doc mapper, if any, can be evaluated by this node.
+pub(crate) fn validate_runtime_fields(doc_mapper: &DocMapper) -> crate::Result<()> {
+    if doc_mapper.runtime_fields().is_empty() {
+        return Ok(());
+    }
+    RuntimeFieldsEvaluator::try_new(doc_mapper.runtime_fields())?;
+    Ok(())
+}
+
+/// Returns whether the query or the collector, through the fast fields it reads for sorting and
+/// aggregating, reference one of the `runtime_fields`.
+pub(crate) fn references_runtime_fields(
+    runtime_fields: &[RuntimeField],
+    query_ast: &QueryAst,
+    collector_fast_field_names: &HashSet<String>,
+) -> bool {
+    if runtime_fields.is_empty() {
+        return false;
+    }
+    let runtime_field_names = runtime_field_names(runtime_fields);
+    let mut runtime_field_finder = FieldFinder {
+        is_target_field: |field: &str| runtime_field_names.contains(field),
+    };
+    if runtime_field_finder.visit(query_ast).is_err() {
+        return true;
+    }
+    collector_fast_field_names
+        .iter()
+        .any(|fast_field_name| runtime_field_finder.check_field(fast_field_name).is_err())
+}
+
+/// The ephemeral index holding the documents of a split along with the values of their runtime
+/// fields.
+pub(crate) struct RuntimeFieldsIndex {
+    /// Searcher over the ephemeral index.
+    pub searcher: Searcher,
+    /// Doc mapper of the ephemeral index, in which the runtime fields are regular fast fields.
+    pub doc_mapper: Arc<DocMapper>,
+    /// Address in the split of each document of the ephemeral index, indexed by doc ID.
+    split_doc_addrs: Vec<DocAddress>,
+}
+
+impl RuntimeFieldsIndex {
+    /// Rewrites the addresses of the hits of the ephemeral index into addresses of the split, so
+    /// that they can be fetched by the fetch docs phase.
+    pub fn remap_partial_hits(&self, leaf_search_response: &mut LeafSearchResponse) {
+        for partial_hit in &mut leaf_search_response.partial_hits {
+            if let Some(split_doc_addr) = self.split_doc_addrs.get(partial_hit.doc_id as usize) {
+                partial_hit.segment_ord = split_doc_addr.segment_ord;
+                partial_hit.doc_id = split_doc_addr.doc_id;
+            }
+        }
+    }
+}
+
+/// Returns the first field read by the query or the collector that is not stored in the split.
+/// Unless the source of the documents is stored, the values of such a field are missing from the
+/// documents re-indexed into the ephemeral index.
+fn find_non_stored_field(
+    split_schema: &Schema,
+    doc_mapper: &DocMapper,
+    query_ast: &QueryAst,
+    collector_fast_field_names: &HashSet<String>,
+) -> Option<String> {
+    let runtime_field_names = runtime_field_names(doc_mapper.runtime_fields());
+    let dynamic_field_opt = split_schema.get_field(DYNAMIC_FIELD_NAME).ok();
+    let mut non_stored_field_finder = FieldFinder {
+        is_target_field: |field_name: &str| {
+            // The runtime fields and the concatenate fields are computed again when the
+            // documents are re-indexed.
+            if runtime_field_names.contains(field_name)
+                || doc_mapper.is_concatenate_field(field_name)
+            {
+                return false;
+            }
+            let Some((field, _path)) =
+                split_schema.find_field_with_default(field_name, dynamic_field_opt)
+            else {
+                return false;
+            };
+            !split_schema.get_field_entry(field).is_stored()
+        },
+    };
+    if let Err(field_name) = non_stored_field_finder.visit(query_ast) {
+        return Some(field_name);
+    }
+    collector_fast_field_names
+        .iter()
+        .find_map(|fast_field_name| non_stored_field_finder.check_field(fast_field_name).err())
+}
+
+/// Builds the ephemeral index of the runtime fields of `doc_mapper` for the split opened by
+/// `split_searcher`.
+///
+/// Only the documents that may match `query_ast` are re-indexed, but this still requires fetching
+/// them from the doc store and running every script over each of them, which is orders of
+/// magnitude slower than searching an indexed field. The request is rejected if more than
+/// [`MAX_NUM_REINDEXED_DOCS_PER_SPLIT`] documents would have to be re-indexed, or if it reads
+/// fields that cannot be rebuilt from the doc store.
+pub(crate) async fn build_runtime_fields_index(
+    split_searcher: &Searcher,
+    doc_mapper: &DocMapper,
+    query_ast: &QueryAst,
+    collector_fast_field_names: &HashSet<String>,
+) -> crate::Result<RuntimeFieldsIndex> {
+    let split_schema = split_searcher.schema();
+
+    if split_schema.get_field(SOURCE_FIELD_NAME).is_err() {
+        if let Some(field_name) = find_non_stored_field(
+            split_schema,
+            doc_mapper,
+            query_ast,
+            collector_fast_field_names,
+        ) {
+            return Err(SearchError::InvalidQuery(format!(
+                "field `{field_name}` is not stored and cannot be read along with runtime fields: \
+                 store the field or the source of the documents"
+            )));
+        }
+    }
+    let evaluator = RuntimeFieldsEvaluator::try_new(doc_mapper.runtime_fields())?;
+    let runtime_doc_mapper = Arc::new(doc_mapper.runtime_doc_mapper().map_err(|error| {
+        SearchError::Internal(format!(
+            "failed to build runtime fields doc mapper. cause: {error}"
+        ))
+    })?);
+    let prefilter_query_ast = prefilter_query_ast(query_ast, doc_mapper.runtime_fields());
+    let (prefilter_query, warmup_info) =
+        doc_mapper.query(split_searcher.schema().clone(), &prefilter_query_ast, false)?;
+    warmup(split_searcher, &warmup_info).await?;
+
+    let moved_split_searcher = split_searcher.clone();
+    let split_doc_addrs: Vec<DocAddress> = crate::search_thread_pool()
+        .run_cpu_intensive(move || {
+            let num_docs = moved_split_searcher.search(&prefilter_query, &Count)?;
+
+            if num_docs > MAX_NUM_REINDEXED_DOCS_PER_SPLIT {
+                return Err(SearchError::InvalidQuery(format!(
+                    "runtime fields require re-indexing {num_docs} documents of a split, more \
+                     than the maximum of {MAX_NUM_REINDEXED_DOCS_PER_SPLIT}: narrow down the \
+                     query with clauses on regular fields"
+                )));
+            }
+            let mut split_doc_addrs: Vec<DocAddress> = moved_split_searcher
+                .search(&prefilter_query, &DocSetCollector)?
+                .into_iter()
+                .collect();
+            // The ephemeral index is sorted like the split so that hits sorted by doc address
+            // keep the same order once remapped.
+            split_doc_addrs.sort_unstable();
+            Ok::<_, SearchError>(split_doc_addrs)
+        })
+        .await
+        .map_err(|_| SearchError::Internal("runtime fields prefilter panicked".to_string()))??;
+
+    let mut runtime_index = Index::create_in_ram(runtime_doc_mapper.schema());
+    runtime_index.set_tokenizers(
+        runtime_doc_mapper
+            .tokenizer_manager()
+            .tantivy_manager()
+            .clone(),
+    );
+    runtime_index.set_fast_field_tokenizers(
+        quickwit_query::get_quickwit_fastfield_normalizer_manager()
+            .tantivy_manager()
+            .clone(),
+    );
+    let index_writer =
+        SingleSegmentIndexWriter::new(runtime_index, EPHEMERAL_INDEX_MEMORY_BUDGET_IN_BYTES)?;
+    let mut index_builder = RuntimeFieldsIndexBuilder {
+        split_doc_mapper: doc_mapper.clone(),
+        runtime_doc_mapper: runtime_doc_mapper.clone(),
+        evaluator,
+        index_writer,
+        split_doc_addrs: Vec::with_capacity(split_doc_addrs.len()),
+        num_evaluation_errors: 0,
+        num_indexing_errors: 0,
+    };
+    for doc_addrs in split_doc_addrs.chunks(DOC_BATCH_SIZE) {
+        let docs: Vec<TantivyDocument> = stream::iter(doc_addrs.iter().copied())
+            .map(|doc_addr| split_searcher.doc_async(doc_addr))
+            .buffered(NUM_CONCURRENT_DOC_REQUESTS)
+            .try_collect()
+            .await?;
+        let doc_addrs = doc_addrs.to_vec();
+        let moved_split_searcher = split_searcher.clone();
+
+        index_builder = crate::search_thread_pool()
+            .run_cpu_intensive(move || {
+                for (doc_addr, doc) in doc_addrs.into_iter().zip(docs) {
+                    index_builder.add_doc(&moved_split_searcher, doc_addr, doc)?;
+                }
+                Ok::<_, SearchError>(index_builder)
+            })
+            .await
+            .map_err(|_| {
+                SearchError::Internal("runtime fields evaluation panicked".to_string())
+            })??;
+    }
+    if index_builder.num_evaluation_errors > 0 || index_builder.num_indexing_errors > 0 {
+        warn!(
+            num_evaluation_errors = index_builder.num_evaluation_errors,
+            num_indexing_errors = index_builder.num_indexing_errors,
+            "failed to evaluate or index some runtime field values"
+        );
+    }
+    let split_doc_addrs = index_builder.split_doc_addrs;
+    let runtime_index = index_builder.index_writer.finalize()?;
+    let searcher = runtime_index
+        .reader_builder()
+        .reload_policy(ReloadPolicy::Manual)
+        .try_into()?
+        .searcher();
+
+    Ok(RuntimeFieldsIndex {
+        searcher,
+        doc_mapper: runtime_doc_mapper,
+        split_doc_addrs,
+    })
+}
+
+struct RuntimeFieldsIndexBuilder {
+    split_doc_mapper: DocMapper,
+    runtime_doc_mapper: Arc<DocMapper>,
+    evaluator: RuntimeFieldsEvaluator,
+    index_writer: SingleSegmentIndexWriter,
+    split_doc_addrs: Vec<DocAddress>,
+    num_evaluation_errors: usize,
+    num_indexing_errors: usize,
+}
+
+impl RuntimeFieldsIndexBuilder {
+    fn add_doc(
+        &mut self,
+        split_searcher: &Searcher,
+        doc_addr: DocAddress,
+        doc: TantivyDocument,
+    ) -> crate::Result<()> {
+        let named_doc = doc.to_named_doc(split_searcher.schema());
+        let mut doc_json = self.split_doc_mapper.doc_to_json(named_doc.0)?;
+
+        // The source holds the values of all the fields, including those that are not stored.
+        // Either way, it is rebuilt by the doc mapper of the ephemeral index if needed.
+        let doc_json = match doc_json.remove(SOURCE_FIELD_NAME) {
+            Some(serde_json::Value::Object(source_json)) => source_json,
+            _ => doc_json,
+        };
+
+        let doc_len = if self
+            .runtime_doc_mapper
+            .schema()
+            .get_field(DOCUMENT_SIZE_FIELD_NAME)
+            .is_ok()
+        {
+            serde_json::to_vec(&doc_json)?.len() as u64
+        } else {
+            0
+        };
+        let mut doc_with_runtime_values_json = doc_json.clone();
+        self.num_evaluation_errors += self.evaluator.evaluate(&mut doc_with_runtime_values_json);
+
+        let runtime_doc = match self
+            .runtime_doc_mapper
+            .doc_from_json_obj(doc_with_runtime_values_json, doc_len)
+        {
+            Ok((_partition, runtime_doc)) => runtime_doc,
+            Err(_) => {
+                // The values returned by the scripts do not match the types of the runtime
+                // fields: the document is indexed without them.
+                self.num_indexing_errors += 1;
+
+                match self.runtime_doc_mapper.doc_from_json_obj(doc_json, doc_len) {
+                    Ok((_partition, runtime_doc)) => runtime_doc,
+                    Err(_) => return Ok(()),
+                }
+            }
+        };
+        self.index_writer.add_document(runtime_doc)?;
+        self.split_doc_addrs.push(doc_addr);
+        Ok(())
+    }
+}
+
+/// Returns a query matching a superset of the documents matched by `query_ast`, which does not
+/// reference any of the `runtime_fields`.
+fn prefilter_query_ast(query_ast: &QueryAst, runtime_fields: &[RuntimeField]) -> QueryAst {
+    let runtime_field_names = runtime_field_names(runtime_fields);
+    let mut runtime_field_finder = FieldFinder {
+        is_target_field: |field: &str| runtime_field_names.contains(field),
+    };
+    if runtime_field_finder.visit(query_ast).is_ok() {
+        return query_ast.clone();
+    }
+    let QueryAst::Bool(bool_query) = query_ast else {
+        return QueryAst::MatchAll;
+    };
+    // Dropping the `should` clauses and the clauses referencing runtime fields can only widen
+    // the set of matching documents.
+    let mut keep_clause = |clause: &&QueryAst| runtime_field_finder.visit(clause).is_ok();
+    let prefilter_bool_query = BoolQuery {
+        must: Vec::new(),
+        must_not: bool_query
+            .must_not
+            .iter()
+            .filter(&mut keep_clause)
+            .cloned()
+            .collect(),
+        should: Vec::new(),
+        filter: bool_query
+            .must
+            .iter()
+            .chain(&bool_query.filter)
+            .filter(&mut keep_clause)
+            .cloned()
+            .collect(),
+        minimum_should_match: None,
+    };
+    if prefilter_bool_query.must_not.is_empty() && prefilter_bool_query.filter.is_empty() {
+        return QueryAst::MatchAll;
+    }
+    QueryAst::Bool(prefilter_bool_query)
+}
+
+fn runtime_field_names(runtime_fields: &[RuntimeField]) -> HashSet<&str> {
+    runtime_fields
+        .iter()
+        .map(|runtime_field| runtime_field.name.as_str())
+        .collect()
+}
+
+/// Visitor failing with the name of the field on the first leaf query referencing a target
+/// field.
+struct FieldFinder<F> {
+    is_target_field: F,
+}
+
+impl<F: Fn(&str) -> bool> FieldFinder<F> {
+    fn check_field(&self, field: &str) -> Result<(), String> {
+        if (self.is_target_field)(field) {
+            return Err(field.to_string());
+        }
+        Ok(())
+    }
+}
+
+impl<'a, F: Fn(&str) -> bool> QueryAstVisitor<'a> for FieldFinder<F> {
+    type Err = String;
+
+    fn visit_term(&mut self, term_query: &'a TermQuery) -> Result<(), String> {
+        self.check_field(&term_query.field)
+    }
+
+    fn visit_term_set(&mut self, term_set_query: &'a TermSetQuery) -> Result<(), String> {
+        for field in term_set_query.terms_per_field.keys() {
+            self.check_field(field)?;
+        }
+        Ok(())
+    }
+
+    fn visit_full_text(&mut self, full_text_query: &'a FullTextQuery) -> Result<(), String> {
+        self.check_field(&full_text_query.field)
+    }
+
+    fn visit_phrase_prefix(
+        &mut self,
+        phrase_prefix_query: &'a PhrasePrefixQuery,
+    ) -> Result<(), String> {
+        self.check_field(&phrase_prefix_query.field)
+    }
+
+    fn visit_range(&mut self, range_query: &'a RangeQuery) -> Result<(), String> {
+        self.check_field(&range_query.field)
+    }
+
+    fn visit_exists(&mut self, exists_query: &'a FieldPresenceQuery) -> Result<(), String> {
+        self.check_field(&exists_query.field)
+    }
+
+    fn visit_wildcard(&mut self, wildcard_query: &'a WildcardQuery) -> Result<(), String> {
+        self.check_field(&wildcard_query.field)
+    }
+}
+
+#[cfg(feature = "vrl")]
+struct RuntimeFieldsEvaluator {
+    programs: Vec<(String, vrl::compiler::Program)>,
+    runtime: vrl::compiler::runtime::Runtime,
+    timezone: vrl::compiler::TimeZone,
+}
+
+#[cfg(feature = "vrl")]
+impl RuntimeFieldsEvaluator {
+    fn try_new(runtime_fields: &[RuntimeField]) -> crate::Result<Self> {
+        let mut programs = Vec::with_capacity(runtime_fields.len());
+
+        for runtime_field in runtime_fields {
+            let program =
+                quickwit_config::compile_vrl_script(&runtime_field.script).map_err(|error| {
+                    SearchError::InvalidArgument(format!(
+                        "invalid script for runtime field `{}`: {error}",
+                        runtime_field.name
+                    ))
+                })?;
+            programs.push((runtime_field.name.clone(), program));
+        }
+        let runtime =
+            vrl::compiler::runtime::Runtime::new(vrl::compiler::state::RuntimeState::default());
+        // Like transforms, runtime fields are evaluated in UTC.
+        let timezone = vrl::compiler::TimeZone::parse("UTC").unwrap_or_default();
+
+        Ok(Self {
+            programs,
+            runtime,
+            timezone,
+        })
+    }
+
+    /// Evaluates the runtime fields over the document and adds their values to it. Returns the
+    /// number of runtime fields that failed to evaluate.
+    fn evaluate(&mut self, doc_json: &mut JsonObject) -> usize {
+        use vrl::value::{Secrets, Value as VrlValue};
+
+        let Ok(doc_vrl_value) =
+            serde_json::from_value::<VrlValue>(serde_json::Value::Object(doc_json.clone()))
+        else {
+            return self.programs.len();
+        };
+        let mut num_evaluation_errors = 0;
+
+        for (field_name, program) in &self.programs {
+            let mut vrl_value = doc_vrl_value.clone();
+            let mut metadata = VrlValue::Object(Default::default());
+            let mut secrets = Secrets::default();
+            let mut target = vrl::compiler::TargetValueRef {
+                value: &mut vrl_value,
+                metadata: &mut metadata,
+                secrets: &mut secrets,
+            };
+            let runtime_res = self.runtime.resolve(&mut target, program, &self.timezone);
+            self.runtime.clear();
+
+            let Ok(field_vrl_value) = runtime_res else {
+                num_evaluation_errors += 1;
+                continue;
+            };
+            match serde_json::to_value(field_vrl_value) {
+                Ok(serde_json::Value::Null) => {}
+                Ok(field_value) => {
+                    doc_json.insert(field_name.clone(), field_value);
+                }
+                Err(_) => {
+                    num_evaluation_errors += 1;
+                }
+            }
+        }
+        num_evaluation_errors
+    }
+}
+
+#[cfg(not(feature = "vrl"))]
+enum RuntimeFieldsEvaluator {}
+
+#[cfg(not(feature = "vrl"))]
+impl RuntimeFieldsEvaluator {
+    fn try_new(_runtime_fields: &[RuntimeField]) -> crate::Result<Self> {
+        Err(SearchError::InvalidArgument(
+            "runtime fields require Quickwit to be built with the `vrl` feature".to_string(),
+        ))
+    }
+
+    fn evaluate(&mut self, _doc_json: &mut JsonObject) -> usize {
+        match *self {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_doc_mapper::{DocMapperBuilder, RuntimeFieldType};
+    use quickwit_proto::search::PartialHit;
+    use quickwit_query::query_ast::qast_helper;
+
+    use super::*;
+
+    fn runtime_fields() -> Vec<RuntimeField> {
+        vec![RuntimeField {
+            name: "status".to_string(),
+            field_type: RuntimeFieldType::U64,
+            script: ".status".to_string(),
+        }]
+    }
+
+    #[test]
+    fn test_references_runtime_fields() {
+        let runtime_fields = runtime_fields();
+        let no_fast_field_names = HashSet::new();
+
+        let query_ast = qast_helper("level:error", &[]);
+        assert!(!references_runtime_fields(
+            &[],
+            &query_ast,
+            &no_fast_field_names
+        ));
+        assert!(!references_runtime_fields(
+            &runtime_fields,
+            &query_ast,
+            &no_fast_field_names
+        ));
+        let sort_fast_field_names = HashSet::from(["status".to_string()]);
+        assert!(references_runtime_fields(
+            &runtime_fields,
+            &query_ast,
+            &sort_fast_field_names
+        ));
+        let query_ast = qast_helper("level:error AND status:500", &[]);
+        assert!(references_runtime_fields(
+            &runtime_fields,
+            &query_ast,
+            &no_fast_field_names
+        ));
+    }
+
+    #[test]
+    fn test_prefilter_query_ast() {
+        let runtime_fields = runtime_fields();
+        {
+            let query_ast = qast_helper("level:error", &[]);
+            assert_eq!(prefilter_query_ast(&query_ast, &runtime_fields), query_ast);
+        }
+        {
+            let query_ast = qast_helper("status:500", &[]);
+            assert_eq!(
+                prefilter_query_ast(&query_ast, &runtime_fields),
+                QueryAst::MatchAll
+            );
+        }
+        {
+            let query_ast = qast_helper("status:500 OR level:error", &[]);
+            assert_eq!(
+                prefilter_query_ast(&query_ast, &runtime_fields),
+                QueryAst::MatchAll
+            );
+        }
+        {
+            let query_ast = qast_helper("status:500 AND level:error AND NOT host:foo", &[]);
+            let expected_query_ast = QueryAst::Bool(BoolQuery {
+                filter: vec![qast_helper("level:error", &[])],
+                must_not: vec![qast_helper("host:foo", &[])],
+                ..Default::default()
+            });
+            assert_eq!(
+                prefilter_query_ast(&query_ast, &runtime_fields),
+                expected_query_ast
+            );
+        }
+        {
+            let query_ast = qast_helper("level:error AND NOT status:500", &[]);
+            let expected_query_ast = QueryAst::Bool(BoolQuery {
+                filter: vec![qast_helper("level:error", &[])],
+                ..Default::default()
+            });
+            assert_eq!(
+                prefilter_query_ast(&query_ast, &runtime_fields),
+                expected_query_ast
+            );
+        }
+    }
+
+    #[test]
+    fn test_find_non_stored_field() {
+        let doc_mapper: DocMapper = serde_json::from_value(serde_json::json!({
+            "field_mappings": [
+                {"name": "level", "type": "text", "tokenizer": "raw", "fast": true},
+                {"name": "host", "type": "text", "tokenizer": "raw", "fast": true, "stored": false},
+                {"name": "all", "type": "concatenate", "concatenate_fields": ["level", "host"]}
+            ]
+        }))
+        .unwrap();
+        let doc_mapper = doc_mapper.with_runtime_fields(runtime_fields()).unwrap();
+        let schema = doc_mapper.schema();
+        let no_fast_field_names = HashSet::new();
+
+        let query_ast = qast_helper("level:error AND status:500 AND all:foo", &[]);
+        assert_eq!(
+            find_non_stored_field(&schema, &doc_mapper, &query_ast, &no_fast_field_names),
+            None
+        );
+        let query_ast = qast_helper("status:500 AND NOT host:foo", &[]);
+        assert_eq!(
+            find_non_stored_field(&schema, &doc_mapper, &query_ast, &no_fast_field_names),
+            Some("host".to_string())
+        );
+        let query_ast = qast_helper("status:500", &[]);
+        let sort_fast_field_names = HashSet::from(["host".to_string()]);
+        assert_eq!(
+            find_non_stored_field(&schema, &doc_mapper, &query_ast, &sort_fast_field_names),
+            Some("host".to_string())
+        );
+    }
+
+    #[test]
+    fn test_remap_partial_hits() {
+        let schema = tantivy::schema::Schema::builder().build();
+        let index = Index::create_in_ram(schema);
+        let searcher = index.reader().unwrap().searcher();
+        let runtime_fields_index = RuntimeFieldsIndex {
+            searcher,
+            doc_mapper: Arc::new(DocMapperBuilder::default().try_build().unwrap()),
+            split_doc_addrs: vec![DocAddress::new(0, 3), DocAddress::new(1, 7)],
+        };
+        let mut leaf_search_response = LeafSearchResponse {
+            partial_hits: vec![
+                PartialHit {
+                    split_id: "split".to_string(),
+                    segment_ord: 0,
+                    doc_id: 1,
+                    ..Default::default()
+                },
+                PartialHit {
+                    split_id: "split".to_string(),
+                    segment_ord: 0,
+                    doc_id: 0,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        runtime_fields_index.remap_partial_hits(&mut leaf_search_response);
+
+        let doc_addrs: Vec<(u32, u32)> = leaf_search_response
+            .partial_hits
+            .iter()
+            .map(|partial_hit| (partial_hit.segment_ord, partial_hit.doc_id))
+            .collect();
+        assert_eq!(doc_addrs, vec![(1, 7), (0, 3)]);
+    }
+
+    #[cfg(feature = "vrl")]
+    #[test]
+    fn test_runtime_fields_evaluator() {
+        let runtime_fields = vec![
+            RuntimeField {
+                name: "status".to_string(),
+                field_type: RuntimeFieldType::U64,
+                script: "parse_int!(parse_regex!(.message, r'status=(?P<status>\\d+)').status)"
+                    .to_string(),
+            },
+            RuntimeField {
+                name: "host".to_string(),
+                field_type: RuntimeFieldType::Text,
+                script: "upcase!(.host)".to_string(),
+            },
+        ];
+        let mut evaluator = RuntimeFieldsEvaluator::try_new(&runtime_fields).unwrap();
+
+        let mut doc_json: JsonObject = serde_json::from_str(
+            r#"{"message": "request served status=404 in 3ms", "host": "node-1"}"#,
+        )
+        .unwrap();
+        assert_eq!(evaluator.evaluate(&mut doc_json), 0);
+        assert_eq!(doc_json["status"], 404);
+        assert_eq!(doc_json["host"], "NODE-1");
+
+        let mut doc_json: JsonObject =
+            serde_json::from_str(r#"{"message": "no status here"}"#).unwrap();
+        assert_eq!(evaluator.evaluate(&mut doc_json), 2);
+        assert!(!doc_json.contains_key("status"));
+    }
+}
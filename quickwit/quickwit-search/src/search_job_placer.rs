@@ -27,6 +27,7 @@ use anyhow::bail;
 use async_trait::async_trait;
 use quickwit_common::pubsub::EventSubscriber;
 use quickwit_common::rendezvous_hasher::{node_affinity, sort_by_rendez_vous_hash};
+use quickwit_common::tower::Pool;
 use quickwit_proto::search::{ReportSplit, ReportSplitsRequest};
 use tracing::{info, warn};
 
@@ -56,12 +57,25 @@ pub trait Job {
     }
 }
 
+/// Availability zones of the searchers, keyed by their gRPC address.
+pub type SearcherZonePool = Pool<SocketAddr, String>;
+
+/// When the availability zone of the node is known, jobs are preferably assigned to searchers
+/// located in the same zone. We stop assigning jobs to a searcher of the local zone once its load
+/// exceeds this factor times the load it would have if the jobs were spread evenly across all the
+/// searchers of the cluster.
+const MAX_LOCAL_ZONE_LOAD_FACTOR: usize = 3;
+
 /// Search job placer.
 /// It assigns jobs to search clients.
 #[derive(Clone, Default)]
 pub struct SearchJobPlacer {
     /// Search clients pool.
     searcher_pool: SearcherPool,
+    /// Availability zones of the searchers.
+    searcher_zone_pool: SearcherZonePool,
+    /// Availability zone of the node running the placer, if known.
+    self_zone_opt: Option<String>,
 }
 
 #[async_trait]
@@ -72,19 +86,28 @@ impl EventSubscriber<ReportSplitsRequest> for SearchJobPlacer {
         if nodes.is_empty() {
             return;
         }
+        // Each zone maintains its own split cache, so we report the splits to the node with the
+        // highest affinity within each zone.
+        let mut nodes_per_zone: HashMap<Option<String>, Vec<SocketAddr>> = HashMap::new();
+        for node_addr in nodes.keys() {
+            let zone_opt = self.searcher_zone_pool.get(node_addr);
+            nodes_per_zone.entry(zone_opt).or_default().push(*node_addr);
+        }
         let mut splits_per_node: HashMap<SocketAddr, Vec<ReportSplit>> =
             HashMap::with_capacity(nodes.len().min(evt.report_splits.len()));
         for report_split in evt.report_splits {
-            let node_addr = nodes
-                .keys()
-                .max_by_key(|node_addr| node_affinity(*node_addr, &report_split.split_id))
-                // This actually never happens thanks to the if-condition at the
-                // top of this function.
-                .expect("`nodes` should not be empty");
-            splits_per_node
-                .entry(*node_addr)
-                .or_default()
-                .push(report_split);
+            for zone_node_addrs in nodes_per_zone.values() {
+                let node_addr = zone_node_addrs
+                    .iter()
+                    .max_by_key(|node_addr| node_affinity(*node_addr, &report_split.split_id))
+                    // This actually never happens thanks to the if-condition at the
+                    // top of this function.
+                    .expect("`nodes` should not be empty");
+                splits_per_node
+                    .entry(*node_addr)
+                    .or_default()
+                    .push(report_split.clone());
+            }
         }
         for (node_addr, report_splits) in splits_per_node {
             if let Some(search_client) = nodes.get_mut(&node_addr) {
@@ -104,7 +127,50 @@ impl fmt::Debug for SearchJobPlacer {
 impl SearchJobPlacer {
     /// Returns an [`SearchJobPlacer`] from a search service client pool.
     pub fn new(searcher_pool: SearcherPool) -> Self {
-        Self { searcher_pool }
+        Self {
+            searcher_pool,
+            searcher_zone_pool: SearcherZonePool::default(),
+            self_zone_opt: None,
+        }
+    }
+
+    /// Makes the placer prefer searchers located in the availability zone `self_zone`. The zones
+    /// of the searchers are looked up in `searcher_zone_pool`.
+    pub fn with_availability_zone(
+        mut self,
+        self_zone: String,
+        searcher_zone_pool: SearcherZonePool,
+    ) -> Self {
+        self.self_zone_opt = Some(self_zone);
+        self.searcher_zone_pool = searcher_zone_pool;
+        self
+    }
+
+    /// Returns whether the searcher listening on `grpc_addr` is located in the same availability
+    /// zone as this node. Searchers are all considered local if the zone of this node is unknown.
+    fn is_in_local_zone(&self, grpc_addr: &SocketAddr) -> bool {
+        let Some(self_zone) = &self.self_zone_opt else {
+            return true;
+        };
+        self.searcher_zone_pool
+            .get(grpc_addr)
+            .is_some_and(|zone| zone == *self_zone)
+    }
+
+    /// Splits `nodes` into the nodes located in the local zone and the others. If no node is
+    /// located in the local zone, all the nodes are considered local.
+    fn partition_by_zone<T>(
+        &self,
+        nodes: Vec<T>,
+        grpc_addr_fn: impl Fn(&T) -> &SocketAddr,
+    ) -> (Vec<T>, Vec<T>) {
+        let (local_nodes, remote_nodes): (Vec<T>, Vec<T>) = nodes
+            .into_iter()
+            .partition(|node| self.is_in_local_zone(grpc_addr_fn(node)));
+        if local_nodes.is_empty() {
+            return (remote_nodes, Vec::new());
+        }
+        (local_nodes, remote_nodes)
     }
 }
 
@@ -126,7 +192,7 @@ impl SearchJobPlacer {
         &self,
         affinity_key: &[u8],
     ) -> impl Iterator<Item = SearchServiceClient> {
-        let mut nodes: Vec<SocketAddrAndClient> = self
+        let nodes: Vec<SocketAddrAndClient> = self
             .searcher_pool
             .pairs()
             .into_iter()
@@ -135,9 +201,14 @@ impl SearchJobPlacer {
                 client,
             })
             .collect();
-        sort_by_rendez_vous_hash(&mut nodes[..], affinity_key);
-        nodes
+        // Nodes of the local zone come first.
+        let (mut local_nodes, mut remote_nodes) =
+            self.partition_by_zone(nodes, |node| &node.socket_addr);
+        sort_by_rendez_vous_hash(&mut local_nodes[..], affinity_key);
+        sort_by_rendez_vous_hash(&mut remote_nodes[..], affinity_key);
+        local_nodes
             .into_iter()
+            .chain(remote_nodes)
             .map(|socket_addr_and_client| socket_addr_and_client.client)
     }
 
@@ -174,7 +245,7 @@ impl SearchJobPlacer {
                 all_nodes.len()
             );
         }
-        let mut candidate_nodes: Vec<_> = all_nodes
+        let candidate_nodes: Vec<_> = all_nodes
             .into_iter()
             .map(|(grpc_addr, client)| CandidateNode {
                 grpc_addr,
@@ -182,10 +253,15 @@ impl SearchJobPlacer {
                 load: 0,
             })
             .collect();
+        let num_nodes = candidate_nodes.len();
 
-        jobs.sort_unstable_by(Job::compare_cost);
+        // Jobs are assigned in priority to the nodes of the local zone, and rendez-vous hashing is
+        // computed within each group so that the split cache of each zone remains effective.
+        let (mut local_nodes, mut remote_nodes) =
+            self.partition_by_zone(candidate_nodes, |node| &node.grpc_addr);
+        let num_local_nodes = local_nodes.len();
 
-        let num_nodes = candidate_nodes.len();
+        jobs.sort_unstable_by(Job::compare_cost);
 
         let mut job_assignments: HashMap<SocketAddr, (SearchServiceClient, Vec<J>)> =
             HashMap::with_capacity(num_nodes);
@@ -201,18 +277,38 @@ impl SearchJobPlacer {
         // for now i went with the mock_split_meta() changes.
         const ALLOWED_DIFFERENCE: usize = 105;
         let target_load = (total_load * ALLOWED_DIFFERENCE).div_ceil(num_nodes * 100);
+        // When some nodes are located in other zones, the local nodes accept more load than their
+        // fair share, up to `MAX_LOCAL_ZONE_LOAD_FACTOR` times. Past that, we fall back to the
+        // other zones.
+        let local_target_load = if remote_nodes.is_empty() {
+            target_load
+        } else {
+            (total_load * ALLOWED_DIFFERENCE)
+                .div_ceil(num_local_nodes * 100)
+                .min(target_load * MAX_LOCAL_ZONE_LOAD_FACTOR)
+        };
         for job in jobs {
-            sort_by_rendez_vous_hash(&mut candidate_nodes, job.split_id());
+            sort_by_rendez_vous_hash(&mut local_nodes, job.split_id());
 
-            let (chosen_node_idx, chosen_node) = if let Some((idx, node)) = candidate_nodes
+            let (chosen_node_idx, chosen_node) = if let Some((idx, node)) = local_nodes
                 .iter_mut()
                 .enumerate()
-                .find(|(_pos, node)| node.load < target_load)
+                .find(|(_pos, node)| node.load < local_target_load)
             {
                 (idx, node)
             } else {
-                warn!("found no lightly loaded searcher for split, this should never happen");
-                (0, &mut candidate_nodes[0])
+                sort_by_rendez_vous_hash(&mut remote_nodes, job.split_id());
+
+                if let Some((idx, node)) = remote_nodes
+                    .iter_mut()
+                    .enumerate()
+                    .find(|(_pos, node)| node.load < target_load)
+                {
+                    (num_local_nodes + idx, node)
+                } else {
+                    warn!("found no lightly loaded searcher for split, this should never happen");
+                    (0, &mut local_nodes[0])
+                }
             };
             let metric_node_idx = match chosen_node_idx {
                 0 => "0",
@@ -505,4 +601,89 @@ mod tests {
             assert!(job_len <= 1050 / 5);
         }
     }
+
+    fn searcher_zone_pool_for_test(
+        iter: impl IntoIterator<Item = (&'static str, &'static str)>,
+    ) -> SearcherZonePool {
+        SearcherZonePool::from_iter(iter.into_iter().map(|(grpc_addr_str, zone)| {
+            let grpc_addr: SocketAddr = grpc_addr_str.parse().unwrap();
+            (grpc_addr, zone.to_string())
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_search_job_placer_prefers_local_zone() {
+        let searcher_pool = searcher_pool_for_test([
+            ("127.0.0.1:1001", MockSearchService::new()),
+            ("127.0.0.1:1002", MockSearchService::new()),
+            ("127.0.0.1:1003", MockSearchService::new()),
+            ("127.0.0.1:1004", MockSearchService::new()),
+        ]);
+        let searcher_zone_pool = searcher_zone_pool_for_test([
+            ("127.0.0.1:1001", "zone-a"),
+            ("127.0.0.1:1002", "zone-a"),
+            ("127.0.0.1:1003", "zone-b"),
+            ("127.0.0.1:1004", "zone-b"),
+        ]);
+        let search_job_placer = SearchJobPlacer::new(searcher_pool)
+            .with_availability_zone("zone-a".to_string(), searcher_zone_pool);
+        let jobs = (0..10)
+            .map(|id| SearchJob::for_test(&format!("split{id}"), 1))
+            .collect();
+        let assigned_addrs: HashSet<SocketAddr> = search_job_placer
+            .assign_jobs(jobs, &HashSet::default())
+            .await
+            .unwrap()
+            .map(|(client, _jobs)| client.grpc_addr())
+            .collect();
+        let expected_addrs: HashSet<SocketAddr> =
+            [([127, 0, 0, 1], 1001).into(), ([127, 0, 0, 1], 1002).into()]
+                .into_iter()
+                .collect();
+        assert_eq!(assigned_addrs, expected_addrs);
+
+        let best_node_addrs: Vec<SocketAddr> = search_job_placer
+            .best_nodes_per_affinity(b"split1")
+            .await
+            .map(|client| client.grpc_addr())
+            .collect();
+        assert_eq!(best_node_addrs.len(), 4);
+        assert!(expected_addrs.contains(&best_node_addrs[0]));
+        assert!(expected_addrs.contains(&best_node_addrs[1]));
+    }
+
+    #[tokio::test]
+    async fn test_search_job_placer_falls_back_to_other_zones() {
+        let searcher_pool = searcher_pool_for_test([
+            ("127.0.0.1:1001", MockSearchService::new()),
+            ("127.0.0.1:1002", MockSearchService::new()),
+            ("127.0.0.1:1003", MockSearchService::new()),
+            ("127.0.0.1:1004", MockSearchService::new()),
+            ("127.0.0.1:1005", MockSearchService::new()),
+            ("127.0.0.1:1006", MockSearchService::new()),
+        ]);
+        let searcher_zone_pool = searcher_zone_pool_for_test([
+            ("127.0.0.1:1001", "zone-a"),
+            ("127.0.0.1:1002", "zone-b"),
+            ("127.0.0.1:1003", "zone-b"),
+            ("127.0.0.1:1004", "zone-b"),
+            ("127.0.0.1:1005", "zone-b"),
+            ("127.0.0.1:1006", "zone-b"),
+        ]);
+        let search_job_placer = SearchJobPlacer::new(searcher_pool)
+            .with_availability_zone("zone-a".to_string(), searcher_zone_pool);
+        let jobs = (0..12)
+            .map(|id| SearchJob::for_test(&format!("split{id}"), 1))
+            .collect();
+        let assigned_jobs: HashMap<SocketAddr, usize> = search_job_placer
+            .assign_jobs(jobs, &HashSet::default())
+            .await
+            .unwrap()
+            .map(|(client, jobs)| (client.grpc_addr(), jobs.len()))
+            .collect();
+        // The local searcher accepts up to 3 times its fair share of the load (2 jobs, +5%).
+        let local_addr: SocketAddr = ([127, 0, 0, 1], 1001).into();
+        assert_eq!(assigned_jobs[&local_addr], 9);
+        assert_eq!(assigned_jobs.values().sum::<usize>(), 12);
+    }
 }
@@ -20,7 +20,7 @@
 use std::convert::TryFrom;
 
 use quickwit_common::truncate_str;
-use quickwit_proto::search::SearchResponse;
+use quickwit_proto::search::{SearchResponse, SplitSearchProfile};
 use quickwit_query::query_ast::QueryAst;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
@@ -48,6 +48,10 @@ pub struct SearchResponseRest {
     #[schema(value_type = Object)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub aggregations: Option<JsonValue>,
+    /// Profiles of the searched splits, only returned for profiled searches.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub profile: Vec<SplitSearchProfile>,
 }
 
 impl TryFrom<SearchResponse> for SearchResponseRest {
@@ -98,6 +102,7 @@ impl TryFrom<SearchResponse> for SearchResponseRest {
             elapsed_time_micros: search_response.elapsed_time_micros,
             errors: search_response.errors,
             aggregations: aggregations_opt,
+            profile: search_response.split_profiles,
         })
     }
 }
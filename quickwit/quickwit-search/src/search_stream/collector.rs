@@ -20,12 +20,15 @@
 use std::collections::HashMap;
 use std::hash::Hash;
 use std::marker::PhantomData;
+use std::net::Ipv6Addr;
 
 use tantivy::collector::{Collector, SegmentCollector};
-use tantivy::columnar::{DynamicColumn, HasAssociatedColumnType};
-use tantivy::fastfield::Column;
-use tantivy::{DocId, Score, SegmentOrdinal, SegmentReader};
+use tantivy::columnar::{Cardinality, DynamicColumn, HasAssociatedColumnType};
+use tantivy::fastfield::{Column, FastFieldReaders};
+use tantivy::schema::Type;
+use tantivy::{DateTime, DocId, Score, SegmentOrdinal, SegmentReader, TantivyError};
 
+use super::fast_field_rows::{FastFieldRows, FastFieldValues};
 use crate::filters::{TimestampFilter, TimestampFilterBuilder};
 
 #[derive(Clone)]
@@ -244,3 +247,169 @@ impl<Item: HasAssociatedColumnType, PartitionItem: HasAssociatedColumnType + Has
         self.fast_field_values
     }
 }
+
+/// Collects the values of several fast fields, one row per matching document.
+#[derive(Clone)]
+pub struct FastFieldRowsCollector {
+    pub fast_fields_to_collect: Vec<(String, Type)>,
+    pub timestamp_filter_builder_opt: Option<TimestampFilterBuilder>,
+}
+
+impl Collector for FastFieldRowsCollector {
+    type Child = FastFieldRowsSegmentCollector;
+    type Fruit = Vec<FastFieldRows>;
+
+    fn for_segment(
+        &self,
+        _segment_ord: SegmentOrdinal,
+        segment_reader: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        let timestamp_filter_opt =
+            if let Some(timestamp_filter_builder) = &self.timestamp_filter_builder_opt {
+                timestamp_filter_builder.build(segment_reader)?
+            } else {
+                None
+            };
+        let columns = self
+            .fast_fields_to_collect
+            .iter()
+            .map(|(field_name, value_type)| {
+                FastFieldRowsColumn::open(segment_reader, field_name, *value_type)
+            })
+            .collect::<tantivy::Result<Vec<_>>>()?;
+
+        Ok(FastFieldRowsSegmentCollector {
+            num_rows: 0,
+            columns,
+            timestamp_filter_opt,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        // We do not need BM25 scoring in Quickwit.
+        false
+    }
+
+    fn merge_fruits(&self, segment_fruits: Vec<FastFieldRows>) -> tantivy::Result<Self::Fruit> {
+        Ok(segment_fruits)
+    }
+}
+
+pub struct FastFieldRowsSegmentCollector {
+    num_rows: usize,
+    columns: Vec<FastFieldRowsColumn>,
+    timestamp_filter_opt: Option<TimestampFilter>,
+}
+
+impl SegmentCollector for FastFieldRowsSegmentCollector {
+    type Fruit = FastFieldRows;
+
+    fn collect(&mut self, doc_id: DocId, _score: Score) {
+        if let Some(timestamp_filter) = &self.timestamp_filter_opt {
+            if !timestamp_filter.contains_doc_timestamp(doc_id) {
+                return;
+            }
+        }
+        for column in &mut self.columns {
+            column.collect(doc_id);
+        }
+        self.num_rows += 1;
+    }
+
+    fn harvest(self) -> FastFieldRows {
+        FastFieldRows {
+            num_rows: self.num_rows,
+            columns: self
+                .columns
+                .into_iter()
+                .map(FastFieldRowsColumn::into_values)
+                .collect(),
+        }
+    }
+}
+
+/// The column of a fast field in a segment, along with the values collected so far. The column is
+/// missing if no document of the segment has a value for the field.
+enum FastFieldRowsColumn {
+    Bool(Option<Column<bool>>, Vec<Option<bool>>),
+    Date(Option<Column<DateTime>>, Vec<Option<DateTime>>),
+    F64(Option<Column<f64>>, Vec<Option<f64>>),
+    I64(Option<Column<i64>>, Vec<Option<i64>>),
+    IpAddr(Option<Column<Ipv6Addr>>, Vec<Option<Ipv6Addr>>),
+    U64(Option<Column<u64>>, Vec<Option<u64>>),
+}
+
+impl FastFieldRowsColumn {
+    fn open(
+        segment_reader: &SegmentReader,
+        field_name: &str,
+        value_type: Type,
+    ) -> tantivy::Result<Self> {
+        let fast_fields = segment_reader.fast_fields();
+        let column = match value_type {
+            Type::Bool => Self::Bool(open_column(fast_fields, field_name)?, Vec::new()),
+            Type::Date => Self::Date(open_column(fast_fields, field_name)?, Vec::new()),
+            Type::F64 => Self::F64(open_column(fast_fields, field_name)?, Vec::new()),
+            Type::I64 => Self::I64(open_column(fast_fields, field_name)?, Vec::new()),
+            Type::IpAddr => Self::IpAddr(open_column(fast_fields, field_name)?, Vec::new()),
+            Type::U64 => Self::U64(open_column(fast_fields, field_name)?, Vec::new()),
+            _ => {
+                return Err(TantivyError::InvalidArgument(format!(
+                    "search stream does not support fast field of type `{value_type:?}`"
+                )))
+            }
+        };
+        Ok(column)
+    }
+
+    fn collect(&mut self, doc_id: DocId) {
+        match self {
+            Self::Bool(column_opt, values) => values.push(first_value(column_opt, doc_id)),
+            Self::Date(column_opt, values) => values.push(first_value(column_opt, doc_id)),
+            Self::F64(column_opt, values) => values.push(first_value(column_opt, doc_id)),
+            Self::I64(column_opt, values) => values.push(first_value(column_opt, doc_id)),
+            Self::IpAddr(column_opt, values) => values.push(first_value(column_opt, doc_id)),
+            Self::U64(column_opt, values) => values.push(first_value(column_opt, doc_id)),
+        }
+    }
+
+    fn into_values(self) -> FastFieldValues {
+        match self {
+            Self::Bool(_, values) => FastFieldValues::Bool(values),
+            Self::Date(_, values) => FastFieldValues::Date(values),
+            Self::F64(_, values) => FastFieldValues::F64(values),
+            Self::I64(_, values) => FastFieldValues::I64(values),
+            Self::IpAddr(_, values) => FastFieldValues::IpAddr(values),
+            Self::U64(_, values) => FastFieldValues::U64(values),
+        }
+    }
+}
+
+/// Opens the column of a fast field, rejecting multivalued columns: rows hold a single value per
+/// field, and keeping only the first value of each document would silently drop data.
+fn open_column<Item>(
+    fast_fields: &FastFieldReaders,
+    field_name: &str,
+) -> tantivy::Result<Option<Column<Item>>>
+where
+    Item: HasAssociatedColumnType,
+    DynamicColumn: Into<Option<Column<Item>>>,
+{
+    let column_opt: Option<Column<Item>> = fast_fields.column_opt(field_name)?;
+
+    if let Some(column) = &column_opt {
+        if column.index.get_cardinality() == Cardinality::Multivalued {
+            return Err(TantivyError::InvalidArgument(format!(
+                "search stream does not support multivalued fast field `{field_name}`"
+            )));
+        }
+    }
+    Ok(column_opt)
+}
+
+fn first_value<Item: HasAssociatedColumnType>(
+    column_opt: &Option<Column<Item>>,
+    doc_id: DocId,
+) -> Option<Item> {
+    column_opt.as_ref()?.first(doc_id)
+}
@@ -0,0 +1,485 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::io::Write;
+use std::net::{IpAddr, Ipv6Addr};
+use std::sync::Arc;
+
+use arrow::array::{
+    ArrayRef, BooleanArray, Float64Array, Int64Array, StringArray, TimestampMicrosecondArray,
+    UInt64Array,
+};
+use arrow::datatypes::{DataType, Field as ArrowField, Schema as ArrowSchema, TimeUnit};
+use arrow::ipc::writer::{write_message, DictionaryTracker, IpcDataGenerator, IpcWriteOptions};
+use arrow::record_batch::RecordBatch;
+use quickwit_datetime::DateTimeOutputFormat;
+use quickwit_proto::search::OutputFormat;
+use serde_json::Value as JsonValue;
+use tantivy::schema::Type;
+use tantivy::DateTime;
+
+/// Marks the end of an Arrow IPC stream: a continuation marker followed by a zero length.
+pub const ARROW_IPC_END_OF_STREAM: [u8; 8] = [0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00];
+
+/// Values of a fast field for a sequence of documents, `None` standing for documents without a
+/// value. Multivalued fields are not supported.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FastFieldValues {
+    Bool(Vec<Option<bool>>),
+    Date(Vec<Option<DateTime>>),
+    F64(Vec<Option<f64>>),
+    I64(Vec<Option<i64>>),
+    IpAddr(Vec<Option<Ipv6Addr>>),
+    U64(Vec<Option<u64>>),
+}
+
+impl FastFieldValues {
+    pub fn value_type(&self) -> Type {
+        match self {
+            Self::Bool(_) => Type::Bool,
+            Self::Date(_) => Type::Date,
+            Self::F64(_) => Type::F64,
+            Self::I64(_) => Type::I64,
+            Self::IpAddr(_) => Type::IpAddr,
+            Self::U64(_) => Type::U64,
+        }
+    }
+
+    fn write_csv_cell(&self, row_idx: usize, buffer: &mut Vec<u8>) -> anyhow::Result<()> {
+        match self {
+            Self::Bool(values) => write_display_opt(values[row_idx], buffer)?,
+            // Dates are written as timestamps in microseconds, like single fast field streams.
+            Self::Date(values) => write_display_opt(
+                values[row_idx].map(|date_time| date_time.into_timestamp_micros()),
+                buffer,
+            )?,
+            Self::F64(values) => write_display_opt(values[row_idx], buffer)?,
+            Self::I64(values) => write_display_opt(values[row_idx], buffer)?,
+            Self::IpAddr(values) => write_display_opt(values[row_idx].map(display_ip), buffer)?,
+            Self::U64(values) => write_display_opt(values[row_idx], buffer)?,
+        }
+        Ok(())
+    }
+
+    /// Writes the value as a ClickHouse `Nullable` value: a null marker byte, followed by the value
+    /// if it is not null.
+    fn write_row_binary_cell(&self, row_idx: usize, buffer: &mut Vec<u8>) {
+        match self {
+            Self::Bool(values) => write_nullable(values[row_idx], buffer, |value, buffer| {
+                buffer.push(value as u8)
+            }),
+            // `DateTime64(6)`
+            Self::Date(values) => write_nullable(values[row_idx], buffer, |value, buffer| {
+                buffer.extend(value.into_timestamp_micros().to_le_bytes())
+            }),
+            Self::F64(values) => write_nullable(values[row_idx], buffer, |value, buffer| {
+                buffer.extend(value.to_le_bytes())
+            }),
+            Self::I64(values) => write_nullable(values[row_idx], buffer, |value, buffer| {
+                buffer.extend(value.to_le_bytes())
+            }),
+            // `IPv6`, stored in network byte order.
+            Self::IpAddr(values) => write_nullable(values[row_idx], buffer, |value, buffer| {
+                buffer.extend(value.octets())
+            }),
+            Self::U64(values) => write_nullable(values[row_idx], buffer, |value, buffer| {
+                buffer.extend(value.to_le_bytes())
+            }),
+        }
+    }
+
+    fn json_value(&self, row_idx: usize) -> anyhow::Result<JsonValue> {
+        let json_value = match self {
+            Self::Bool(values) => values[row_idx].into(),
+            Self::Date(values) => match values[row_idx] {
+                Some(date_time) => DateTimeOutputFormat::Rfc3339
+                    .format_to_json(date_time)
+                    .map_err(anyhow::Error::msg)?,
+                None => JsonValue::Null,
+            },
+            Self::F64(values) => values[row_idx].into(),
+            Self::I64(values) => values[row_idx].into(),
+            Self::IpAddr(values) => values[row_idx].map(display_ip).into(),
+            Self::U64(values) => values[row_idx].into(),
+        };
+        Ok(json_value)
+    }
+
+    fn to_arrow_array(&self) -> ArrayRef {
+        match self {
+            Self::Bool(values) => Arc::new(BooleanArray::from_iter(values.iter())),
+            Self::Date(values) => {
+                Arc::new(
+                    TimestampMicrosecondArray::from_iter(values.iter().map(|value_opt| {
+                        value_opt.map(|date_time| date_time.into_timestamp_micros())
+                    }))
+                    .with_timezone("UTC"),
+                )
+            }
+            Self::F64(values) => Arc::new(Float64Array::from_iter(values.iter())),
+            Self::I64(values) => Arc::new(Int64Array::from_iter(values.iter())),
+            Self::IpAddr(values) => Arc::new(StringArray::from_iter(
+                values.iter().map(|value_opt| value_opt.map(display_ip)),
+            )),
+            Self::U64(values) => Arc::new(UInt64Array::from_iter(values.iter())),
+        }
+    }
+}
+
+/// Values of the requested fast fields for the documents of a segment, stored column by column.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FastFieldRows {
+    pub num_rows: usize,
+    pub columns: Vec<FastFieldValues>,
+}
+
+/// Returns the Arrow type of the column streamed for a fast field of type `value_type`, or `None`
+/// if search stream does not support the type.
+fn arrow_data_type(value_type: Type) -> Option<DataType> {
+    let data_type = match value_type {
+        Type::Bool => DataType::Boolean,
+        Type::Date => DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+        Type::F64 => DataType::Float64,
+        Type::I64 => DataType::Int64,
+        Type::IpAddr => DataType::Utf8,
+        Type::U64 => DataType::UInt64,
+        _ => return None,
+    };
+    Some(data_type)
+}
+
+/// Builds the Arrow schema of the rows streamed for the given fast fields.
+pub fn arrow_schema(fast_fields: &[(&str, Type)]) -> anyhow::Result<ArrowSchema> {
+    let mut arrow_fields = Vec::with_capacity(fast_fields.len());
+
+    for (field_name, value_type) in fast_fields {
+        let Some(data_type) = arrow_data_type(*value_type) else {
+            anyhow::bail!("search stream does not support fast field of type `{value_type:?}`");
+        };
+        arrow_fields.push(ArrowField::new(*field_name, data_type, true));
+    }
+    Ok(ArrowSchema::new(arrow_fields))
+}
+
+/// Serializes the schema message opening an Arrow IPC stream.
+pub fn serialize_arrow_ipc_schema(
+    arrow_schema: &ArrowSchema,
+    buffer: &mut Vec<u8>,
+) -> anyhow::Result<()> {
+    let write_options = IpcWriteOptions::default();
+    let encoded_schema = IpcDataGenerator::default().schema_to_bytes(arrow_schema, &write_options);
+    write_message(buffer, encoded_schema, &write_options)?;
+    Ok(())
+}
+
+/// Serializes rows of fast field values into the `buffer`, one row per document.
+///
+/// For the Arrow IPC format, only record batch messages are written: the root node is in charge of
+/// opening the stream with its schema and closing it, so that the responses of the leaves form a
+/// single stream.
+///
+/// Please note that the `buffer` is always cleared.
+pub fn serialize_rows(
+    field_names: &[&str],
+    rows: &[FastFieldRows],
+    buffer: &mut Vec<u8>,
+    format: OutputFormat,
+) -> anyhow::Result<()> {
+    buffer.clear();
+
+    match format {
+        OutputFormat::Csv => serialize_rows_csv(rows, buffer),
+        OutputFormat::ClickHouseRowBinary => {
+            serialize_rows_click_house_row_binary(rows, buffer);
+            Ok(())
+        }
+        OutputFormat::ArrowIpc => serialize_rows_arrow_ipc(field_names, rows, buffer),
+        OutputFormat::Ndjson => serialize_rows_ndjson(field_names, rows, buffer),
+    }
+}
+
+fn serialize_rows_csv(rows: &[FastFieldRows], buffer: &mut Vec<u8>) -> anyhow::Result<()> {
+    for segment_rows in rows {
+        for row_idx in 0..segment_rows.num_rows {
+            for (column_idx, column) in segment_rows.columns.iter().enumerate() {
+                if column_idx > 0 {
+                    buffer.push(b',');
+                }
+                column.write_csv_cell(row_idx, buffer)?;
+            }
+            buffer.push(b'\n');
+        }
+    }
+    Ok(())
+}
+
+fn serialize_rows_click_house_row_binary(rows: &[FastFieldRows], buffer: &mut Vec<u8>) {
+    for segment_rows in rows {
+        for row_idx in 0..segment_rows.num_rows {
+            for column in &segment_rows.columns {
+                column.write_row_binary_cell(row_idx, buffer);
+            }
+        }
+    }
+}
+
+fn serialize_rows_ndjson(
+    field_names: &[&str],
+    rows: &[FastFieldRows],
+    buffer: &mut Vec<u8>,
+) -> anyhow::Result<()> {
+    for segment_rows in rows {
+        for row_idx in 0..segment_rows.num_rows {
+            let mut json_row = serde_json::Map::with_capacity(field_names.len());
+
+            for (field_name, column) in field_names.iter().zip(&segment_rows.columns) {
+                json_row.insert(field_name.to_string(), column.json_value(row_idx)?);
+            }
+            serde_json::to_writer(&mut *buffer, &json_row)?;
+            buffer.push(b'\n');
+        }
+    }
+    Ok(())
+}
+
+fn serialize_rows_arrow_ipc(
+    field_names: &[&str],
+    rows: &[FastFieldRows],
+    buffer: &mut Vec<u8>,
+) -> anyhow::Result<()> {
+    let write_options = IpcWriteOptions::default();
+    let ipc_data_generator = IpcDataGenerator::default();
+    let mut dictionary_tracker = DictionaryTracker::new(false);
+
+    for segment_rows in rows {
+        if segment_rows.num_rows == 0 {
+            continue;
+        }
+        let fast_fields: Vec<(&str, Type)> = field_names
+            .iter()
+            .copied()
+            .zip(segment_rows.columns.iter().map(FastFieldValues::value_type))
+            .collect();
+        let arrow_schema = Arc::new(arrow_schema(&fast_fields)?);
+        let arrow_arrays = segment_rows
+            .columns
+            .iter()
+            .map(FastFieldValues::to_arrow_array)
+            .collect();
+        let record_batch = RecordBatch::try_new(arrow_schema, arrow_arrays)?;
+        let (_encoded_dictionaries, encoded_batch) = ipc_data_generator.encoded_batch(
+            &record_batch,
+            &mut dictionary_tracker,
+            &write_options,
+        )?;
+        write_message(&mut *buffer, encoded_batch, &write_options)?;
+    }
+    Ok(())
+}
+
+fn write_display_opt<T: std::fmt::Display>(
+    value_opt: Option<T>,
+    buffer: &mut Vec<u8>,
+) -> std::io::Result<()> {
+    if let Some(value) = value_opt {
+        write!(buffer, "{value}")?;
+    }
+    Ok(())
+}
+
+fn write_nullable<T>(value_opt: Option<T>, buffer: &mut Vec<u8>, write_value: fn(T, &mut Vec<u8>)) {
+    if let Some(value) = value_opt {
+        buffer.push(0);
+        write_value(value, buffer);
+    } else {
+        buffer.push(1);
+    }
+}
+
+/// Displays IPv4 addresses, which are stored as IPv4-mapped IPv6 addresses, in their usual form.
+fn display_ip(ip_addr: Ipv6Addr) -> String {
+    ip_addr
+        .to_ipv4_mapped()
+        .map(IpAddr::V4)
+        .unwrap_or(IpAddr::V6(ip_addr))
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use arrow::array::Array;
+    use arrow::ipc::reader::StreamReader;
+
+    use super::*;
+
+    fn test_rows() -> Vec<FastFieldRows> {
+        vec![FastFieldRows {
+            num_rows: 2,
+            columns: vec![
+                FastFieldValues::Date(vec![
+                    Some(DateTime::from_timestamp_micros(1_706_740_200_000_001)),
+                    None,
+                ]),
+                FastFieldValues::IpAddr(vec![
+                    Some(Ipv4Addr::new(192, 168, 0, 1).to_ipv6_mapped()),
+                    Some(Ipv6Addr::LOCALHOST),
+                ]),
+                FastFieldValues::Bool(vec![Some(true), Some(false)]),
+                FastFieldValues::I64(vec![None, Some(-3)]),
+            ],
+        }]
+    }
+
+    const FIELD_NAMES: [&str; 4] = ["ts", "ip", "ok", "count"];
+
+    #[test]
+    fn test_serialize_rows_csv() {
+        let mut buffer = Vec::new();
+        serialize_rows(&FIELD_NAMES, &test_rows(), &mut buffer, OutputFormat::Csv).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "1706740200000001,192.168.0.1,true,\n,::1,false,-3\n"
+        );
+    }
+
+    #[test]
+    fn test_serialize_rows_ndjson() {
+        let mut buffer = Vec::new();
+        serialize_rows(
+            &FIELD_NAMES,
+            &test_rows(),
+            &mut buffer,
+            OutputFormat::Ndjson,
+        )
+        .unwrap();
+        let json_rows: Vec<JsonValue> = buffer
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        assert_eq!(
+            json_rows,
+            [
+                serde_json::json!({
+                    "ts": "2024-01-31T22:30:00.000001Z",
+                    "ip": "192.168.0.1",
+                    "ok": true,
+                    "count": null,
+                }),
+                serde_json::json!({
+                    "ts": null,
+                    "ip": "::1",
+                    "ok": false,
+                    "count": -3,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_serialize_rows_click_house_row_binary() {
+        let mut buffer = Vec::new();
+        serialize_rows(
+            &FIELD_NAMES,
+            &test_rows(),
+            &mut buffer,
+            OutputFormat::ClickHouseRowBinary,
+        )
+        .unwrap();
+        let mut expected_buffer: Vec<u8> = Vec::new();
+        // First row.
+        expected_buffer.push(0);
+        expected_buffer.extend(1_706_740_200_000_001i64.to_le_bytes());
+        expected_buffer.push(0);
+        expected_buffer.extend(Ipv4Addr::new(192, 168, 0, 1).to_ipv6_mapped().octets());
+        expected_buffer.extend([0, 1]);
+        expected_buffer.push(1);
+        // Second row.
+        expected_buffer.push(1);
+        expected_buffer.push(0);
+        expected_buffer.extend(Ipv6Addr::LOCALHOST.octets());
+        expected_buffer.extend([0, 0]);
+        expected_buffer.push(0);
+        expected_buffer.extend((-3i64).to_le_bytes());
+        assert_eq!(buffer, expected_buffer);
+    }
+
+    #[test]
+    fn test_serialize_rows_arrow_ipc() {
+        let fast_fields: Vec<(&str, Type)> = FIELD_NAMES
+            .into_iter()
+            .zip([Type::Date, Type::IpAddr, Type::Bool, Type::I64])
+            .collect();
+        let arrow_schema = arrow_schema(&fast_fields).unwrap();
+
+        // Splits are serialized independently and stitched together by the root.
+        let mut stream = Vec::new();
+        serialize_arrow_ipc_schema(&arrow_schema, &mut stream).unwrap();
+
+        for _ in 0..2 {
+            let mut buffer = Vec::new();
+            serialize_rows(
+                &FIELD_NAMES,
+                &test_rows(),
+                &mut buffer,
+                OutputFormat::ArrowIpc,
+            )
+            .unwrap();
+            stream.extend(buffer);
+        }
+        stream.extend(ARROW_IPC_END_OF_STREAM);
+
+        let stream_reader = StreamReader::try_new(stream.as_slice(), None).unwrap();
+        assert_eq!(stream_reader.schema().as_ref(), &arrow_schema);
+
+        let record_batches: Vec<RecordBatch> = stream_reader
+            .map(|record_batch_res| record_batch_res.unwrap())
+            .collect();
+        assert_eq!(record_batches.len(), 2);
+
+        let record_batch = &record_batches[1];
+        assert_eq!(record_batch.num_rows(), 2);
+
+        let ts_column = record_batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<TimestampMicrosecondArray>()
+            .unwrap();
+        assert_eq!(ts_column.value(0), 1_706_740_200_000_001);
+        assert!(ts_column.is_null(1));
+
+        let ip_column = record_batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(ip_column.value(0), "192.168.0.1");
+
+        let ok_column = record_batch
+            .column(2)
+            .as_any()
+            .downcast_ref::<BooleanArray>()
+            .unwrap();
+        assert!(ok_column.value(0));
+
+        assert!(arrow_schema(&[("body", Type::Str)]).is_err());
+    }
+}
@@ -38,8 +38,9 @@ use tantivy::{DateTime, ReloadPolicy, Searcher};
 use tokio_stream::wrappers::UnboundedReceiverStream;
 use tracing::*;
 
-use super::collector::{PartionnedFastFieldCollector, PartitionValues};
-use super::FastFieldCollector;
+use super::collector::{FastFieldRowsCollector, PartionnedFastFieldCollector, PartitionValues};
+use super::fast_field_rows::{serialize_rows, FastFieldRows};
+use super::{requested_fast_field_names, FastFieldCollector};
 use crate::filters::{create_timestamp_filter_builder, TimestampFilterBuilder};
 use crate::leaf::{open_index_with_caches, rewrite_start_end_time_bounds, warmup};
 use crate::service::SearcherContext;
@@ -133,6 +134,7 @@ async fn leaf_search_stream_single_split(
         &split,
         Some(doc_mapper.tokenizer_manager()),
         true,
+        None,
     )
     .await?;
     let split_schema = index.schema();
@@ -155,6 +157,12 @@ async fn leaf_search_stream_single_split(
                 .to_string(),
         ));
     }
+    if request_fields.partition_by_fast_field.is_some() && request_fields.fast_fields.len() > 1 {
+        return Err(SearchError::InvalidArgument(
+            "a single fast field can be extracted when providing a partitioned-by field"
+                .to_string(),
+        ));
+    }
 
     let search_request = Arc::new(SearchRequest::try_from(stream_request.clone())?);
     let query_ast = serde_json::from_str(&search_request.query_ast)
@@ -198,6 +206,27 @@ async fn leaf_search_stream_single_split(
     let m_request_fields = request_fields.clone();
     let collect_handle = crate::search_thread_pool().run_cpu_intensive(move || {
         let mut buffer = Vec::new();
+
+        if !m_request_fields.streams_single_fast_field(output_format) {
+            let collected_rows = collect_rows(
+                &m_request_fields,
+                timestamp_filter_builder_opt,
+                &searcher,
+                &query,
+            )?;
+            serialize_rows(
+                &m_request_fields.fast_field_names(),
+                &collected_rows,
+                &mut buffer,
+                output_format,
+            )
+            .map_err(|error| {
+                SearchError::Internal(format!(
+                    "error when serializing rows during export: {error}"
+                ))
+            })?;
+            return Result::<Vec<u8>>::Ok(buffer);
+        }
         match m_request_fields.fast_field_types() {
             (Type::I64, None) => {
                 let collected_values = collect_values::<i64>(
@@ -312,6 +341,25 @@ where
     Ok(result)
 }
 
+fn collect_rows(
+    request_fields: &SearchStreamRequestFields,
+    timestamp_filter_builder_opt: Option<TimestampFilterBuilder>,
+    searcher: &Searcher,
+    query: &dyn Query,
+) -> crate::Result<Vec<FastFieldRows>> {
+    let fast_fields_to_collect = request_fields
+        .fast_field_names_and_types()
+        .into_iter()
+        .map(|(field_name, value_type)| (field_name.to_string(), value_type))
+        .collect();
+    let collector = FastFieldRowsCollector {
+        fast_fields_to_collect,
+        timestamp_filter_builder_opt,
+    };
+    let result = searcher.search(query, &collector)?;
+    Ok(result)
+}
+
 fn collect_partitioned_values<
     Item: HasAssociatedColumnType,
     TPartitionValue: HasAssociatedColumnType + Eq + Hash,
@@ -340,7 +388,7 @@ where
 #[derive(Debug)]
 // TODO move to owned values, implement Send + Sync
 struct SearchStreamRequestFields {
-    fast_field: Field,
+    fast_fields: Vec<Field>,
     partition_by_fast_field: Option<Field>,
     timestamp_field_name: Option<String>,
     schema: Schema,
@@ -348,7 +396,7 @@ struct SearchStreamRequestFields {
 
 impl std::fmt::Display for SearchStreamRequestFields {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "fast_field: {},", self.fast_field_name())?;
+        write!(f, "fast_fields: {},", self.fast_field_names().join(","))?;
         write!(
             f,
             "timestamp_field: {},",
@@ -368,13 +416,17 @@ impl SearchStreamRequestFields {
         schema: &Schema,
         doc_mapper: &DocMapper,
     ) -> crate::Result<SearchStreamRequestFields> {
-        let fast_field = schema.get_field(&stream_request.fast_field)?;
+        let mut fast_fields = Vec::new();
 
-        if !Self::is_fast_field(schema, &fast_field) {
-            return Err(SearchError::InvalidQuery(format!(
-                "field `{}` is not a fast field",
-                &stream_request.fast_field
-            )));
+        for fast_field_name in requested_fast_field_names(stream_request) {
+            let fast_field = schema.get_field(fast_field_name)?;
+
+            if !Self::is_fast_field(schema, &fast_field) {
+                return Err(SearchError::InvalidQuery(format!(
+                    "field `{fast_field_name}` is not a fast field"
+                )));
+            }
+            fast_fields.push(fast_field);
         }
 
         let timestamp_field_name = doc_mapper.timestamp_field_name().map(ToString::to_string);
@@ -394,16 +446,34 @@ impl SearchStreamRequestFields {
 
         Ok(SearchStreamRequestFields {
             schema: schema.to_owned(),
-            fast_field,
+            fast_fields,
             partition_by_fast_field,
             timestamp_field_name,
         })
     }
 
+    /// Returns true if the request streams every value of a single fast field, possibly partitioned
+    /// by another fast field, which is how search stream worked before it supported rows of
+    /// several fast fields.
+    fn streams_single_fast_field(&self, output_format: OutputFormat) -> bool {
+        if self.partition_by_fast_field.is_some() {
+            return true;
+        }
+        self.fast_fields.len() == 1
+            && matches!(
+                output_format,
+                OutputFormat::Csv | OutputFormat::ClickHouseRowBinary
+            )
+            && matches!(
+                self.fast_field_types().0,
+                Type::I64 | Type::U64 | Type::Date
+            )
+    }
+
     pub fn fast_field_types(&self) -> (Type, Option<Type>) {
         (
             self.schema
-                .get_field_entry(self.fast_field)
+                .get_field_entry(self.fast_fields[0])
                 .field_type()
                 .value_type(),
             self.partition_by_fast_field
@@ -416,7 +486,9 @@ impl SearchStreamRequestFields {
         timestamp_filter_builder_opt: Option<&TimestampFilterBuilder>,
     ) -> HashSet<String> {
         let mut set = HashSet::new();
-        set.insert(self.fast_field_name().to_string());
+        for fast_field_name in self.fast_field_names() {
+            set.insert(fast_field_name.to_string());
+        }
         if let Some(timestamp_filter_builder) = timestamp_filter_builder_opt {
             set.insert(timestamp_filter_builder.timestamp_field_name.clone());
         }
@@ -431,7 +503,24 @@ impl SearchStreamRequestFields {
     }
 
     pub fn fast_field_name(&self) -> &str {
-        self.schema.get_field_name(self.fast_field)
+        self.schema.get_field_name(self.fast_fields[0])
+    }
+
+    pub fn fast_field_names(&self) -> Vec<&str> {
+        self.fast_fields
+            .iter()
+            .map(|field| self.schema.get_field_name(*field))
+            .collect()
+    }
+
+    fn fast_field_names_and_types(&self) -> Vec<(&str, Type)> {
+        self.fast_fields
+            .iter()
+            .map(|field| {
+                let field_entry = self.schema.get_field_entry(*field);
+                (field_entry.name(), field_entry.field_type().value_type())
+            })
+            .collect()
     }
 
     pub fn partition_by_fast_field_name(&self) -> Option<&str> {
@@ -498,6 +587,7 @@ mod tests {
             fast_field: "ts".to_string(),
             output_format: 0,
             partition_by_field: None,
+            fast_fields: Vec::new(),
         };
         let splits = test_sandbox
             .metastore()
@@ -577,6 +667,7 @@ mod tests {
             fast_field: "ts".to_string(),
             output_format: 0,
             partition_by_field: None,
+            fast_fields: Vec::new(),
         };
         let splits = test_sandbox
             .metastore()
@@ -634,6 +725,7 @@ mod tests {
             fast_field: "app".to_string(),
             output_format: 0,
             partition_by_field: None,
+            fast_fields: Vec::new(),
         };
         let splits = test_sandbox
             .metastore()
@@ -661,6 +753,146 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_leaf_search_stream_multiple_fast_fields_to_ndjson_output() -> anyhow::Result<()> {
+        let index_id = "single-node-multiple-fast-fields";
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+              - name: ts
+                type: datetime
+                fast: true
+              - name: client_ip
+                type: ip
+                fast: true
+              - name: is_error
+                type: bool
+                fast: true
+              - name: latency
+                type: f64
+                fast: true
+            timestamp_field: ts
+        "#;
+        let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "", &["body"]).await?;
+        test_sandbox
+            .add_documents(vec![
+                json!({"body": "info", "ts": 1706740200, "client_ip": "192.168.0.1", "is_error": false, "latency": 0.5}),
+                json!({"body": "info", "ts": 1706740201, "client_ip": "::1", "is_error": true}),
+            ])
+            .await?;
+
+        let request = SearchStreamRequest {
+            index_id: index_id.to_string(),
+            query_ast: qast_json_helper("info", &["body"]),
+            snippet_fields: Vec::new(),
+            start_timestamp: None,
+            end_timestamp: None,
+            fast_field: String::new(),
+            output_format: OutputFormat::Ndjson as i32,
+            partition_by_field: None,
+            fast_fields: vec![
+                "ts".to_string(),
+                "client_ip".to_string(),
+                "is_error".to_string(),
+                "latency".to_string(),
+            ],
+        };
+        let splits = test_sandbox
+            .metastore()
+            .list_splits(ListSplitsRequest::try_from_index_uid(test_sandbox.index_uid()).unwrap())
+            .await?
+            .collect_splits()
+            .await?;
+        let splits_offsets = splits
+            .into_iter()
+            .map(|split| extract_split_and_footer_offsets(&split.split_metadata))
+            .collect();
+        let searcher_context = Arc::new(SearcherContext::for_test());
+        let mut single_node_stream = leaf_search_stream(
+            searcher_context,
+            request,
+            test_sandbox.storage(),
+            splits_offsets,
+            test_sandbox.doc_mapper(),
+        )
+        .await;
+        let res = single_node_stream.next().await.expect("no leaf result")?;
+        let rows: Vec<serde_json::Value> = from_utf8(&res.data)?
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<serde_json::Result<_>>()?;
+        assert_eq!(
+            rows,
+            [
+                json!({"ts": "2024-01-31T22:30:00Z", "client_ip": "192.168.0.1", "is_error": false, "latency": 0.5}),
+                json!({"ts": "2024-01-31T22:30:01Z", "client_ip": "::1", "is_error": true, "latency": null}),
+            ]
+        );
+        test_sandbox.assert_quit().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_leaf_search_stream_multivalued_fast_field_rows_should_return_proper_error(
+    ) -> anyhow::Result<()> {
+        let index_id = "single-node-multivalued-fast-field-rows";
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+              - name: latency
+                type: f64
+                fast: true
+              - name: status_codes
+                type: array<u64>
+                fast: true
+        "#;
+        let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "", &["body"]).await?;
+        test_sandbox
+            .add_documents(vec![
+                json!({"body": "info", "latency": 0.5, "status_codes": [200, 503]}),
+            ])
+            .await?;
+
+        let request = SearchStreamRequest {
+            index_id: index_id.to_string(),
+            query_ast: qast_json_helper("info", &["body"]),
+            snippet_fields: Vec::new(),
+            start_timestamp: None,
+            end_timestamp: None,
+            fast_field: String::new(),
+            output_format: OutputFormat::Ndjson as i32,
+            partition_by_field: None,
+            fast_fields: vec!["latency".to_string(), "status_codes".to_string()],
+        };
+        let splits = test_sandbox
+            .metastore()
+            .list_splits(ListSplitsRequest::try_from_index_uid(test_sandbox.index_uid()).unwrap())
+            .await?
+            .collect_splits()
+            .await?;
+        let splits_offsets = splits
+            .into_iter()
+            .map(|split| extract_split_and_footer_offsets(&split.split_metadata))
+            .collect();
+        let searcher_context = Arc::new(SearcherContext::for_test());
+        let mut single_node_stream = leaf_search_stream(
+            searcher_context,
+            request,
+            test_sandbox.storage(),
+            splits_offsets,
+            test_sandbox.doc_mapper(),
+        )
+        .await;
+        let res = single_node_stream.next().await.expect("no leaf result");
+        let error_message = res.unwrap_err().to_string();
+        assert!(error_message
+            .contains("search stream does not support multivalued fast field `status_codes`"));
+        test_sandbox.assert_quit().await;
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_leaf_search_stream_to_partitioned_clickhouse_binary_output_with_filtering(
     ) -> anyhow::Result<()> {
@@ -724,6 +956,7 @@ mod tests {
             fast_field: "fast_field".to_string(),
             output_format: 1,
             partition_by_field: Some(String::from("partition_by_fast_field")),
+            fast_fields: Vec::new(),
         };
         let splits = test_sandbox
             .metastore()
@@ -18,6 +18,7 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 mod collector;
+mod fast_field_rows;
 mod leaf;
 mod root;
 
@@ -27,7 +28,7 @@ use std::io::Write;
 
 pub use collector::FastFieldCollector;
 pub use leaf::leaf_search_stream;
-use quickwit_proto::search::OutputFormat;
+use quickwit_proto::search::{OutputFormat, SearchStreamRequest};
 pub use root::root_search_stream;
 use tantivy::columnar::MonotonicallyMappableToU64;
 
@@ -55,6 +56,19 @@ impl ToLittleEndian for f64 {
     }
 }
 
+/// Returns the names of the fast fields to extract, one column per field.
+fn requested_fast_field_names(stream_request: &SearchStreamRequest) -> Vec<&str> {
+    if stream_request.fast_fields.is_empty() {
+        vec![stream_request.fast_field.as_str()]
+    } else {
+        stream_request
+            .fast_fields
+            .iter()
+            .map(String::as_str)
+            .collect()
+    }
+}
+
 /// Serialize the values into the `buffer` as bytes.
 ///
 /// Please note that the `buffer` is always cleared.
@@ -66,6 +80,10 @@ pub fn serialize<T: ToLittleEndian + Display>(
     match format {
         OutputFormat::Csv => serialize_csv(values, buffer),
         OutputFormat::ClickHouseRowBinary => serialize_click_house_row_binary(values, buffer),
+        OutputFormat::ArrowIpc | OutputFormat::Ndjson => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("values must be serialized as rows in the {format:?} format"),
+        )),
     }
 }
 
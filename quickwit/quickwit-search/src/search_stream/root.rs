@@ -24,13 +24,19 @@ use futures::{StreamExt, TryStreamExt};
 use quickwit_common::uri::Uri;
 use quickwit_config::build_doc_mapper;
 use quickwit_doc_mapper::tag_pruning::extract_tags_from_query;
+use quickwit_doc_mapper::zone_map_pruning::extract_zone_map_filters_from_query;
 use quickwit_metastore::IndexMetadataResponseExt;
 use quickwit_proto::metastore::{IndexMetadataRequest, MetastoreService, MetastoreServiceClient};
-use quickwit_proto::search::{LeafSearchStreamRequest, SearchRequest, SearchStreamRequest};
+use quickwit_proto::search::{
+    LeafSearchStreamRequest, OutputFormat, SearchRequest, SearchStreamRequest,
+};
 use quickwit_query::query_ast::QueryAst;
+use tantivy::schema::{Schema, Type};
 use tokio_stream::StreamMap;
 use tracing::*;
 
+use super::fast_field_rows::{arrow_schema, serialize_arrow_ipc_schema, ARROW_IPC_END_OF_STREAM};
+use super::requested_fast_field_names;
 use crate::cluster_client::ClusterClient;
 use crate::root::{refine_start_end_timestamp_from_ast, SearchJob};
 use crate::{list_relevant_splits, SearchError};
@@ -62,6 +68,11 @@ pub async fn root_search_stream(
         .map_err(|err| SearchError::InvalidQuery(err.to_string()))?;
     let query_ast_resolved = query_ast.parse_user_query(doc_mapper.default_search_fields())?;
     let tags_filter_ast = extract_tags_from_query(query_ast_resolved.clone());
+    let zone_map_filters = extract_zone_map_filters_from_query(
+        &query_ast_resolved,
+        &doc_mapper.schema(),
+        doc_mapper.zone_map_field_names(),
+    );
 
     if let Some(timestamp_field) = doc_mapper.timestamp_field_name() {
         refine_start_end_timestamp_from_ast(
@@ -76,12 +87,27 @@ pub async fn root_search_stream(
     doc_mapper.query(doc_mapper.schema(), &query_ast_resolved, true)?;
     search_stream_request.query_ast = serde_json::to_string(&query_ast_resolved)?;
 
+    // Leaves only stream Arrow record batches: the root opens the stream with its schema and
+    // closes it.
+    let (stream_header_opt, stream_footer_opt) = if search_stream_request.output_format
+        == OutputFormat::ArrowIpc as i32
+    {
+        let stream_header = arrow_ipc_stream_header(&search_stream_request, &doc_mapper.schema())?;
+        (
+            Some(stream_header),
+            Some(Bytes::from_static(&ARROW_IPC_END_OF_STREAM)),
+        )
+    } else {
+        (None, None)
+    };
+
     let search_request = SearchRequest::try_from(search_stream_request.clone())?;
     let split_metadatas = list_relevant_splits(
         vec![index_uid],
         search_request.start_timestamp,
         search_request.end_timestamp,
         tags_filter_ast,
+        zone_map_filters,
         &mut metastore,
     )
     .await?;
@@ -110,9 +136,33 @@ pub async fn root_search_stream(
             .await;
         stream_map.insert(leaf_ord, leaf_stream);
     }
-    Ok(stream_map
+    let leaf_stream = stream_map
         .map(|(_leaf_ord, result)| result)
-        .map_ok(|leaf_response| Bytes::from(leaf_response.data)))
+        .map_ok(|leaf_response| Bytes::from(leaf_response.data));
+    Ok(futures::stream::iter(stream_header_opt.map(Ok))
+        .chain(leaf_stream)
+        .chain(futures::stream::iter(stream_footer_opt.map(Ok))))
+}
+
+/// Serializes the schema message opening the Arrow IPC stream of a search stream request.
+fn arrow_ipc_stream_header(
+    search_stream_request: &SearchStreamRequest,
+    schema: &Schema,
+) -> crate::Result<Bytes> {
+    let mut fast_fields: Vec<(&str, Type)> = Vec::new();
+
+    for fast_field_name in requested_fast_field_names(search_stream_request) {
+        let field = schema.get_field(fast_field_name)?;
+        let value_type = schema.get_field_entry(field).field_type().value_type();
+        fast_fields.push((fast_field_name, value_type));
+    }
+    let arrow_schema =
+        arrow_schema(&fast_fields).map_err(|error| SearchError::InvalidQuery(error.to_string()))?;
+    let mut buffer = Vec::new();
+    serialize_arrow_ipc_schema(&arrow_schema, &mut buffer).map_err(|error| {
+        SearchError::Internal(format!("failed to serialize Arrow schema: {error}"))
+    })?;
+    Ok(Bytes::from(buffer))
 }
 
 fn jobs_to_leaf_request(
@@ -202,6 +252,72 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_root_search_stream_arrow_ipc() -> anyhow::Result<()> {
+        let request = quickwit_proto::search::SearchStreamRequest {
+            index_id: "test-index".to_string(),
+            query_ast: qast_json_helper("test", &["body"]),
+            fast_fields: vec!["timestamp".to_string(), "response_time".to_string()],
+            output_format: OutputFormat::ArrowIpc as i32,
+            ..Default::default()
+        };
+        let mut mock_metastore = MockMetastoreService::new();
+        let index_metadata = IndexMetadata::for_test("test-index", "ram:///test-index");
+        let index_uid = index_metadata.index_uid.clone();
+        mock_metastore.expect_index_metadata().returning(move |_| {
+            Ok(IndexMetadataResponse::try_from_index_metadata(&index_metadata).unwrap())
+        });
+        mock_metastore.expect_list_splits().returning(move |_| {
+            let splits = vec![MockSplitBuilder::new("split1")
+                .with_index_uid(&index_uid)
+                .build()];
+            let splits = ListSplitsResponse::try_from_splits(splits).unwrap();
+            Ok(ServiceStream::from(vec![Ok(splits)]))
+        });
+        let mut mock_search_service = MockSearchService::new();
+        let (result_sender, result_receiver) = tokio::sync::mpsc::unbounded_channel();
+        result_sender.send(Ok(quickwit_proto::search::LeafSearchStreamResponse {
+            data: Vec::new(),
+            split_id: "split_1".to_string(),
+        }))?;
+        mock_search_service.expect_leaf_search_stream().return_once(
+            |_leaf_search_req: quickwit_proto::search::LeafSearchStreamRequest| {
+                Ok(UnboundedReceiverStream::new(result_receiver))
+            },
+        );
+        drop(result_sender);
+
+        let searcher_pool = searcher_pool_for_test([("127.0.0.1:1001", mock_search_service)]);
+        let search_job_placer = SearchJobPlacer::new(searcher_pool);
+        let cluster_client = ClusterClient::new(search_job_placer.clone());
+        let result: Vec<Bytes> = root_search_stream(
+            request,
+            MetastoreServiceClient::from_mock(mock_metastore),
+            cluster_client,
+        )
+        .await?
+        .try_collect()
+        .await?;
+        assert_eq!(result.len(), 3);
+        assert_eq!(&result[2], &ARROW_IPC_END_OF_STREAM[..]);
+
+        let stream: Vec<u8> = result.concat();
+        let stream_reader = arrow::ipc::reader::StreamReader::try_new(stream.as_slice(), None)?;
+        let arrow_schema = stream_reader.schema();
+        assert_eq!(arrow_schema.field(0).name(), "timestamp");
+        assert!(matches!(
+            arrow_schema.field(0).data_type(),
+            arrow::datatypes::DataType::Timestamp(..)
+        ));
+        assert_eq!(arrow_schema.field(1).name(), "response_time");
+        assert_eq!(
+            arrow_schema.field(1).data_type(),
+            &arrow::datatypes::DataType::Float64
+        );
+        assert_eq!(stream_reader.count(), 0);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_root_search_stream_single_split_partitioned() -> anyhow::Result<()> {
         let request = quickwit_proto::search::SearchStreamRequest {
@@ -24,6 +24,7 @@ use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use bytes::Bytes;
+use quickwit_common::tenant_quotas::TenantQuotas;
 use quickwit_common::uri::Uri;
 use quickwit_config::SearcherConfig;
 use quickwit_doc_mapper::DocMapper;
@@ -49,9 +50,11 @@ use crate::list_fields::{leaf_list_fields, root_list_fields};
 use crate::list_fields_cache::ListFieldsCache;
 use crate::list_terms::{leaf_list_terms, root_list_terms};
 use crate::root::fetch_docs_phase;
+use crate::root_cache::{is_root_search_cache_key, RootSearchCache};
 use crate::scroll_context::{MiniKV, ScrollContext, ScrollKeyAndStartOffset};
 use crate::search_permit_provider::SearchPermitProvider;
 use crate::search_stream::{leaf_search_stream, root_search_stream};
+use crate::slow_query_log::SlowQueryLog;
 use crate::{fetch_docs, root_search, search_plan, ClusterClient, SearchError};
 
 #[derive(Clone)]
@@ -229,6 +232,7 @@ impl SearchService for SearchServiceImpl {
             &fetch_docs_request.split_offsets,
             doc_mapper,
             snippet_request_opt,
+            fetch_docs_request.profile,
         )
         .await?;
 
@@ -311,12 +315,26 @@ impl SearchService for SearchServiceImpl {
 
     async fn put_kv(&self, put_request: PutKvRequest) {
         let ttl = Duration::from_secs(put_request.ttl_secs as u64);
+
+        if is_root_search_cache_key(&put_request.key) {
+            if let Some(root_search_cache) = &self.searcher_context.root_search_cache_opt {
+                root_search_cache.put(put_request.key, put_request.payload, ttl);
+            }
+            return;
+        }
         self.search_after_cache
             .put(put_request.key, put_request.payload, ttl)
             .await;
     }
 
     async fn get_kv(&self, get_request: GetKvRequest) -> Option<Vec<u8>> {
+        if is_root_search_cache_key(&get_request.key) {
+            return self
+                .searcher_context
+                .root_search_cache_opt
+                .as_ref()?
+                .get(&get_request.key);
+        }
         let payload: Vec<u8> = self.search_after_cache.get(&get_request.key).await?;
         Some(payload)
     }
@@ -405,7 +423,7 @@ pub(crate) async fn scroll(
     }
 
     // Fetch the actual documents.
-    let hits: Vec<Hit> = fetch_docs_phase(
+    let (hits, split_profiles): (Vec<Hit>, _) = fetch_docs_phase(
         &scroll_context.indexes_metas_for_leaf_search,
         &partial_hits[..],
         &scroll_context.split_metadatas[..],
@@ -439,6 +457,7 @@ pub(crate) async fn scroll(
         aggregation: None,
         failed_splits: scroll_context.failed_splits,
         num_successful_splits: scroll_context.num_successful_splits,
+        split_profiles,
     })
 }
 /// [`SearcherContext`] provides a common set of variables
@@ -453,6 +472,8 @@ pub struct SearcherContext {
     pub search_permit_provider: SearchPermitProvider,
     /// Split footer cache.
     pub split_footer_cache: MemorySizedCache<String>,
+    /// Split bloom filters cache.
+    pub split_bloom_filters_cache: MemorySizedCache<String>,
     /// Counting semaphore to limit concurrent split stream requests.
     pub split_stream_semaphore: Semaphore,
     /// Recent sub-query cache.
@@ -461,8 +482,15 @@ pub struct SearcherContext {
     pub split_cache_opt: Option<Arc<SplitCache>>,
     /// List fields cache. Caches the list fields response for a given split.
     pub list_fields_cache: ListFieldsCache,
+    /// Root search cache. Caches the final responses of aggregation-only search requests.
+    /// `None` if no root search cache is configured.
+    pub root_search_cache_opt: Option<RootSearchCache>,
     /// The aggregation limits are passed to limit the memory usage.
     pub aggregation_limit: AggregationLimitsGuard,
+    /// Per-index and per-tenant quotas enforced by the root searcher.
+    pub tenant_quotas: TenantQuotas,
+    /// Records the slow root searches. `None` if no slow query log is configured.
+    pub slow_query_log_opt: Option<SlowQueryLog>,
 }
 
 impl std::fmt::Debug for SearcherContext {
@@ -488,6 +516,10 @@ impl SearcherContext {
             capacity_in_bytes,
             &quickwit_storage::STORAGE_METRICS.split_footer_cache,
         );
+        let split_bloom_filters_cache = MemorySizedCache::with_capacity_in_bytes(
+            capacity_in_bytes,
+            &quickwit_storage::STORAGE_METRICS.split_bloom_filters_cache,
+        );
         let leaf_search_split_semaphore =
             SearchPermitProvider::new(searcher_config.max_num_concurrent_split_searches);
         let split_stream_semaphore =
@@ -498,6 +530,12 @@ impl SearcherContext {
             LeafSearchCache::new(searcher_config.partial_request_cache_capacity.as_u64() as usize);
         let list_fields_cache =
             ListFieldsCache::new(searcher_config.partial_request_cache_capacity.as_u64() as usize);
+        let root_search_cache_opt =
+            searcher_config
+                .root_search_cache
+                .map(|root_search_cache_config| {
+                    RootSearchCache::new(root_search_cache_config.capacity.as_u64() as usize)
+                });
         let aggregation_limit = AggregationLimitsGuard::new(
             Some(searcher_config.aggregation_memory_limit.as_u64()),
             Some(searcher_config.aggregation_bucket_limit),
@@ -508,14 +546,30 @@ impl SearcherContext {
             fast_fields_cache: storage_long_term_cache,
             search_permit_provider: leaf_search_split_semaphore,
             split_footer_cache: global_split_footer_cache,
+            split_bloom_filters_cache,
             split_stream_semaphore,
             leaf_search_cache,
             list_fields_cache,
+            root_search_cache_opt,
             split_cache_opt,
             aggregation_limit,
+            tenant_quotas: TenantQuotas::default(),
+            slow_query_log_opt: None,
         }
     }
 
+    /// Sets the per-index and per-tenant quotas enforced by the root searcher.
+    pub fn with_tenant_quotas(mut self, tenant_quotas: TenantQuotas) -> Self {
+        self.tenant_quotas = tenant_quotas;
+        self
+    }
+
+    /// Sets the log recording the slow root searches.
+    pub fn with_slow_query_log(mut self, slow_query_log: SlowQueryLog) -> Self {
+        self.slow_query_log_opt = Some(slow_query_log);
+        self
+    }
+
     /// Returns the shared instance to track the aggregation memory usage.
     pub fn get_aggregation_limits(&self) -> AggregationLimitsGuard {
         self.aggregation_limit.clone()
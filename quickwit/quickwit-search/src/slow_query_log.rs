@@ -0,0 +1,433 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::time::Duration;
+
+use quickwit_common::uri::Uri;
+use quickwit_common::{rate_limited_warn, spawn_named_task};
+use quickwit_config::{
+    load_index_config_from_user_config, ConfigFormat, IndexConfig, SlowQueryLogConfig,
+    INGEST_V2_SOURCE_ID,
+};
+use quickwit_ingest::{CommitType, JsonDocBatchV2Builder};
+use quickwit_proto::ingest::router::{
+    IngestRequestV2, IngestRouterService, IngestRouterServiceClient, IngestSubrequest,
+};
+use quickwit_proto::search::{SearchRequest, SearchResponse};
+use quickwit_proto::types::DocUidGenerator;
+use rand::Rng;
+use serde::Serialize;
+use tantivy::time::OffsetDateTime;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::time::Instant;
+use tracing::warn;
+
+/// Maximum number of records waiting to be ingested. Records are dropped when the buffer is full.
+const RECORDS_BUFFER_CAPACITY: usize = 1_000;
+
+/// Maximum number of records ingested in a single request.
+const MAX_NUM_RECORDS_PER_BATCH: usize = 100;
+
+/// Maximum time a record waits for other records before being ingested.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+const SLOW_QUERY_LOG_INDEX_CONFIG: &str = r#"
+version: 0.8
+
+index_id: ${INDEX_ID}
+
+doc_mapping:
+  mode: strict
+  field_mappings:
+    - name: timestamp
+      type: datetime
+      input_formats: [unix_timestamp]
+      output_format: unix_timestamp_secs
+      fast: true
+      fast_precision: seconds
+    - name: index_id_patterns
+      type: array<text>
+      tokenizer: raw
+      fast: true
+    - name: query_ast
+      type: text
+      tokenizer: default
+    - name: aggregation_request
+      type: text
+      tokenizer: default
+    - name: latency_millis
+      type: u64
+      fast: true
+    - name: num_splits
+      type: u64
+      fast: true
+    - name: num_bytes_scanned
+      type: u64
+      fast: true
+    - name: num_hits
+      type: u64
+      fast: true
+    - name: user_agent
+      type: text
+      tokenizer: raw
+      fast: true
+    - name: error
+      type: text
+      tokenizer: default
+
+  timestamp_field: timestamp
+
+indexing_settings:
+  commit_timeout_secs: 10
+
+search_settings:
+  default_search_fields: [query_ast]
+
+retention:
+  period: 30 days
+  schedule: daily
+"#;
+
+/// A slow search request, as recorded in the slow query log index.
+#[derive(Debug, Serialize)]
+struct SlowQueryRecord {
+    timestamp: i64,
+    index_id_patterns: Vec<String>,
+    query_ast: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    aggregation_request: Option<String>,
+    latency_millis: u64,
+    num_splits: u64,
+    num_bytes_scanned: u64,
+    num_hits: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user_agent: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Records the root searches exceeding the configured latency threshold into a dedicated
+/// Quickwit index, through the ingest router. The records are buffered and ingested in batches by
+/// a background task.
+#[derive(Clone)]
+pub struct SlowQueryLog {
+    config: SlowQueryLogConfig,
+    records_tx: mpsc::Sender<SlowQueryRecord>,
+}
+
+impl SlowQueryLog {
+    pub fn new(config: SlowQueryLogConfig, ingest_router: IngestRouterServiceClient) -> Self {
+        let (records_tx, records_rx) = mpsc::channel(RECORDS_BUFFER_CAPACITY);
+        spawn_named_task(
+            ingest_records_loop(config.index_id.clone(), ingest_router, records_rx),
+            "slow_query_log",
+        );
+        Self { config, records_tx }
+    }
+
+    /// Returns the config of the index storing the slow query log.
+    pub fn index_config(
+        slow_query_log_config: &SlowQueryLogConfig,
+        default_index_root_uri: &Uri,
+    ) -> anyhow::Result<IndexConfig> {
+        let index_config_str =
+            SLOW_QUERY_LOG_INDEX_CONFIG.replace("${INDEX_ID}", &slow_query_log_config.index_id);
+        let index_config = load_index_config_from_user_config(
+            ConfigFormat::Yaml,
+            index_config_str.as_bytes(),
+            default_index_root_uri,
+        )?;
+        Ok(index_config)
+    }
+
+    /// Records the search request in the background if its latency exceeds the threshold and it
+    /// is picked by the sampling. Searches targeting the slow query log index itself are never
+    /// recorded.
+    ///
+    /// `num_bytes_scanned` is the number of bytes of the splits read by the leaf searchers, either
+    /// from the index storage or from the caches.
+    pub(crate) fn record(
+        &self,
+        search_request: &SearchRequest,
+        num_splits: usize,
+        num_bytes_scanned: u64,
+        latency: Duration,
+        search_response_result: &crate::Result<SearchResponse>,
+    ) {
+        let Some(slow_query_record) = self.slow_query_record(
+            search_request,
+            num_splits,
+            num_bytes_scanned,
+            latency,
+            search_response_result,
+        ) else {
+            return;
+        };
+        if let Err(TrySendError::Full(_)) = self.records_tx.try_send(slow_query_record) {
+            rate_limited_warn!(
+                limit_per_min = 10,
+                "slow query log buffer is full, dropping slow search request record"
+            );
+        }
+    }
+
+    fn slow_query_record(
+        &self,
+        search_request: &SearchRequest,
+        num_splits: usize,
+        num_bytes_scanned: u64,
+        latency: Duration,
+        search_response_result: &crate::Result<SearchResponse>,
+    ) -> Option<SlowQueryRecord> {
+        if latency < self.config.threshold() {
+            return None;
+        }
+        if search_request
+            .index_id_patterns
+            .iter()
+            .any(|index_id_pattern| *index_id_pattern == self.config.index_id)
+        {
+            return None;
+        }
+        if self.config.sample_percent < 100
+            && rand::thread_rng().gen_range(0..100) >= self.config.sample_percent
+        {
+            return None;
+        }
+        let (num_hits, error) = match search_response_result {
+            Ok(search_response) => (search_response.num_hits, None),
+            Err(search_error) => (0, Some(search_error.to_string())),
+        };
+        let slow_query_record = SlowQueryRecord {
+            timestamp: OffsetDateTime::now_utc().unix_timestamp(),
+            index_id_patterns: search_request.index_id_patterns.clone(),
+            query_ast: search_request.query_ast.clone(),
+            aggregation_request: search_request.aggregation_request.clone(),
+            latency_millis: latency.as_millis() as u64,
+            num_splits: num_splits as u64,
+            num_bytes_scanned,
+            num_hits,
+            user_agent: search_request.user_agent.clone(),
+            error,
+        };
+        Some(slow_query_record)
+    }
+}
+
+/// Ingests the records received through `records_rx` in batches of up to
+/// `MAX_NUM_RECORDS_PER_BATCH` records, waiting at most `FLUSH_INTERVAL` for a batch to fill up.
+/// Returns once all the senders are dropped and the remaining records are ingested.
+async fn ingest_records_loop(
+    index_id: String,
+    ingest_router: IngestRouterServiceClient,
+    mut records_rx: mpsc::Receiver<SlowQueryRecord>,
+) {
+    let mut records = Vec::with_capacity(MAX_NUM_RECORDS_PER_BATCH);
+
+    loop {
+        if records_rx
+            .recv_many(&mut records, MAX_NUM_RECORDS_PER_BATCH)
+            .await
+            == 0
+        {
+            return;
+        }
+        let flush_deadline = Instant::now() + FLUSH_INTERVAL;
+
+        while records.len() < MAX_NUM_RECORDS_PER_BATCH {
+            let num_records_limit = MAX_NUM_RECORDS_PER_BATCH - records.len();
+
+            match tokio::time::timeout_at(
+                flush_deadline,
+                records_rx.recv_many(&mut records, num_records_limit),
+            )
+            .await
+            {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+        }
+        let num_records = records.len();
+
+        if let Err(error) = ingest_records(&index_id, &ingest_router, records.drain(..)).await {
+            warn!(%error, num_records, "failed to record slow search requests");
+        }
+    }
+}
+
+async fn ingest_records(
+    index_id: &str,
+    ingest_router: &IngestRouterServiceClient,
+    records: impl Iterator<Item = SlowQueryRecord>,
+) -> anyhow::Result<()> {
+    let mut doc_batch_builder = JsonDocBatchV2Builder::default();
+    let mut doc_uid_generator = DocUidGenerator::default();
+
+    for record in records {
+        doc_batch_builder.add_doc(doc_uid_generator.next_doc_uid(), record)?;
+    }
+    let subrequest = IngestSubrequest {
+        subrequest_id: 0,
+        index_id: index_id.to_string(),
+        source_id: INGEST_V2_SOURCE_ID.to_string(),
+        doc_batch: Some(doc_batch_builder.build()),
+    };
+    let request = IngestRequestV2 {
+        commit_type: CommitType::Auto.into(),
+        subrequests: vec![subrequest],
+    };
+    let response = ingest_router.ingest(request).await?;
+
+    if let Some(failure) = response.failures.first() {
+        anyhow::bail!(
+            "failed to ingest slow query records: {:?}",
+            failure.reason()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU64;
+
+    use quickwit_proto::ingest::router::{
+        IngestResponseV2, IngestSuccess, MockIngestRouterService,
+    };
+
+    use super::*;
+    use crate::SearchError;
+
+    #[test]
+    fn test_slow_query_log_index_config() {
+        let slow_query_log_config = SlowQueryLogConfig::default();
+        let index_config =
+            SlowQueryLog::index_config(&slow_query_log_config, &Uri::for_test("ram:///indexes"))
+                .unwrap();
+        assert_eq!(index_config.index_id, slow_query_log_config.index_id);
+        assert!(index_config.retention_policy_opt.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_slow_query_log_record() {
+        let slow_query_log_config = SlowQueryLogConfig {
+            threshold_millis: NonZeroU64::new(100).unwrap(),
+            ..Default::default()
+        };
+        let ingest_router = IngestRouterServiceClient::from_mock(MockIngestRouterService::new());
+        let slow_query_log = SlowQueryLog::new(slow_query_log_config, ingest_router);
+
+        let search_request = SearchRequest {
+            index_id_patterns: vec!["test-index".to_string()],
+            query_ast: "{}".to_string(),
+            user_agent: Some("curl/8.0".to_string()),
+            ..Default::default()
+        };
+        let search_response_result = Err(SearchError::Internal("timeout".to_string()));
+
+        assert!(slow_query_log
+            .slow_query_record(
+                &search_request,
+                3,
+                1_000,
+                Duration::from_millis(50),
+                &search_response_result,
+            )
+            .is_none());
+
+        let slow_query_log_request = SearchRequest {
+            index_id_patterns: vec!["slow-query-log-v0_1".to_string()],
+            ..search_request.clone()
+        };
+        assert!(slow_query_log
+            .slow_query_record(
+                &slow_query_log_request,
+                3,
+                1_000,
+                Duration::from_millis(150),
+                &search_response_result,
+            )
+            .is_none());
+
+        let slow_query_record = slow_query_log
+            .slow_query_record(
+                &search_request,
+                3,
+                1_000,
+                Duration::from_millis(150),
+                &search_response_result,
+            )
+            .unwrap();
+        assert_eq!(slow_query_record.latency_millis, 150);
+        assert_eq!(slow_query_record.num_bytes_scanned, 1_000);
+    }
+
+    #[tokio::test]
+    async fn test_slow_query_log_ingest_records_loop() {
+        let mut mock_ingest_router = MockIngestRouterService::new();
+        mock_ingest_router
+            .expect_ingest()
+            .once()
+            .returning(|request| {
+                assert_eq!(request.subrequests.len(), 1);
+
+                let subrequest = &request.subrequests[0];
+                assert_eq!(subrequest.index_id, "slow-query-log-v0_1");
+
+                let doc_batch = subrequest.doc_batch.as_ref().unwrap();
+                assert_eq!(doc_batch.num_docs(), 2);
+
+                let doc: serde_json::Value =
+                    serde_json::from_slice(doc_batch.docs().next().unwrap().1.as_ref()).unwrap();
+                assert_eq!(doc["index_id_patterns"], serde_json::json!(["test-index"]));
+                assert_eq!(doc["latency_millis"], 150);
+                assert_eq!(doc["num_splits"], 3);
+                assert_eq!(doc["num_bytes_scanned"], 1_000);
+                assert_eq!(doc["user_agent"], "curl/8.0");
+                assert_eq!(doc["error"], "internal error: `timeout`");
+
+                Ok(IngestResponseV2 {
+                    successes: vec![IngestSuccess::default()],
+                    failures: Vec::new(),
+                })
+            });
+        let ingest_router = IngestRouterServiceClient::from_mock(mock_ingest_router);
+        let (records_tx, records_rx) = mpsc::channel(RECORDS_BUFFER_CAPACITY);
+
+        for _ in 0..2 {
+            let slow_query_record = SlowQueryRecord {
+                timestamp: 0,
+                index_id_patterns: vec!["test-index".to_string()],
+                query_ast: "{}".to_string(),
+                aggregation_request: None,
+                latency_millis: 150,
+                num_splits: 3,
+                num_bytes_scanned: 1_000,
+                num_hits: 0,
+                user_agent: Some("curl/8.0".to_string()),
+                error: Some("internal error: `timeout`".to_string()),
+            };
+            records_tx.try_send(slow_query_record).unwrap();
+        }
+        // The remaining records are ingested in a single batch once the senders are dropped.
+        drop(records_tx);
+        ingest_records_loop("slow-query-log-v0_1".to_string(), ingest_router, records_rx).await;
+    }
+}
@@ -25,6 +25,7 @@ use quickwit_doc_mapper::tag_pruning::extract_tags_from_query;
 use quickwit_doc_mapper::DocMapper;
 use quickwit_indexing::TestSandbox;
 use quickwit_opentelemetry::otlp::TraceId;
+use quickwit_proto::metastore::MarkSplitsForDeletionRequest;
 use quickwit_proto::search::{
     LeafListTermsResponse, ListTermsRequest, SearchRequest, SortByValue, SortField, SortOrder,
     SortValue,
@@ -87,6 +88,62 @@ async fn test_single_node_simple() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_single_node_profile() -> anyhow::Result<()> {
+    let index_id = "single-node-profile";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+              - name: status_code
+                type: u64
+                fast: true
+        "#;
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"]).await?;
+    let docs = vec![
+        json!({"body": "hello happy tax payer", "status_code": 200}),
+        json!({"body": "hello tax payer", "status_code": 404}),
+    ];
+    test_sandbox.add_documents(docs).await?;
+    let search_request = SearchRequest {
+        index_id_patterns: vec![index_id.to_string()],
+        query_ast: qast_json_helper("hello", &["body"]),
+        max_hits: 2,
+        aggregation_request: Some(
+            r#"{"status_codes": {"terms": {"field": "status_code"}}}"#.to_string(),
+        ),
+        profile: true,
+        ..Default::default()
+    };
+    let single_node_result = single_node_search(
+        search_request.clone(),
+        test_sandbox.metastore(),
+        test_sandbox.storage_resolver(),
+    )
+    .await?;
+    assert_eq!(single_node_result.num_hits, 2);
+    assert_eq!(single_node_result.split_profiles.len(), 1);
+
+    let split_profile = &single_node_result.split_profiles[0];
+    assert!(!split_profile.leaf_addr.is_empty());
+    assert!(!split_profile.split_id.is_empty());
+    assert!(split_profile.storage_num_bytes + split_profile.cache_num_bytes > 0);
+
+    let unprofiled_search_request = SearchRequest {
+        profile: false,
+        ..search_request
+    };
+    let single_node_result = single_node_search(
+        unprofiled_search_request,
+        test_sandbox.metastore(),
+        test_sandbox.storage_resolver(),
+    )
+    .await?;
+    assert!(single_node_result.split_profiles.is_empty());
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_single_node_termset() -> anyhow::Result<()> {
     let index_id = "single-node-termset-1";
@@ -987,6 +1044,7 @@ async fn test_single_node_split_pruning_by_tags() -> anyhow::Result<()> {
         None,
         None,
         extract_tags_from_query(query_ast),
+        Vec::new(),
         &mut test_sandbox.metastore(),
     )
     .await?;
@@ -999,6 +1057,7 @@ async fn test_single_node_split_pruning_by_tags() -> anyhow::Result<()> {
         None,
         None,
         extract_tags_from_query(query_ast),
+        Vec::new(),
         &mut test_sandbox.metastore(),
     )
     .await?;
@@ -1011,6 +1070,7 @@ async fn test_single_node_split_pruning_by_tags() -> anyhow::Result<()> {
         None,
         None,
         extract_tags_from_query(query_ast),
+        Vec::new(),
         &mut test_sandbox.metastore(),
     )
     .await?;
@@ -1030,6 +1090,172 @@ async fn test_single_node_split_pruning_by_tags() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_single_node_search_pinned_splits() -> anyhow::Result<()> {
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+        "#;
+    let index_id = "single-node-search-pinned-splits";
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"]).await?;
+    let index_uid = test_sandbox.index_uid();
+
+    for body in ["foo", "bar"] {
+        test_sandbox
+            .add_documents(vec![json!({"body": body})])
+            .await?;
+    }
+    let splits = list_all_splits(vec![index_uid.clone()], &mut test_sandbox.metastore()).await?;
+    assert_eq!(splits.len(), 2);
+    let pinned_split_id = splits[0].split_id.clone();
+
+    let search_request = SearchRequest {
+        index_id_patterns: vec![index_id.to_string()],
+        query_ast: qast_json_helper("*", &[]),
+        max_hits: 10,
+        split_ids: vec![pinned_split_id.clone()],
+        ..Default::default()
+    };
+    let search_response = single_node_search(
+        search_request.clone(),
+        test_sandbox.metastore(),
+        test_sandbox.storage_resolver(),
+    )
+    .await?;
+    assert_eq!(search_response.num_hits, 1);
+    assert_eq!(
+        search_response.hits[0]
+            .partial_hit
+            .as_ref()
+            .unwrap()
+            .split_id,
+        pinned_split_id
+    );
+
+    // The pinned splits remain searchable once they are marked for deletion.
+    let mark_splits_for_deletion_request =
+        MarkSplitsForDeletionRequest::new(index_uid, vec![pinned_split_id.clone()]);
+    test_sandbox
+        .metastore()
+        .mark_splits_for_deletion(mark_splits_for_deletion_request)
+        .await?;
+    let search_response = single_node_search(
+        search_request.clone(),
+        test_sandbox.metastore(),
+        test_sandbox.storage_resolver(),
+    )
+    .await?;
+    assert_eq!(search_response.num_hits, 1);
+
+    let search_request = SearchRequest {
+        split_ids: vec!["unknown-split".to_string()],
+        ..search_request
+    };
+    let search_error = single_node_search(
+        search_request,
+        test_sandbox.metastore(),
+        test_sandbox.storage_resolver(),
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(search_error, SearchError::InvalidArgument(_)));
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_single_node_split_pruning_by_bloom_filters() -> anyhow::Result<()> {
+    let doc_mapping_yaml = r#"
+            bloom_filter_fields:
+              - request_id
+            field_mappings:
+              - name: request_id
+                type: text
+                tokenizer: raw
+        "#;
+    let index_id = "single-node-pruning-by-bloom-filters";
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &[]).await?;
+
+    let owners = ["paul", "adrien"];
+    for owner in owners {
+        let mut docs = Vec::new();
+        for i in 0..10 {
+            docs.push(json!({"request_id": format!("req_{owner}_{i}")}));
+        }
+        test_sandbox.add_documents(docs).await?;
+    }
+    let splits = test_sandbox
+        .metastore()
+        .list_splits(ListSplitsRequest::try_from_index_uid(test_sandbox.index_uid()).unwrap())
+        .await?
+        .collect_splits()
+        .await?;
+    let splits_offsets: Vec<_> = splits
+        .iter()
+        .map(|split| extract_split_and_footer_offsets(&split.split_metadata))
+        .collect();
+    let searcher_context = Arc::new(SearcherContext::for_test());
+
+    for (query, expected_num_hits) in [
+        ("request_id:req_paul_3", 1),
+        ("request_id:req_francois_3", 0),
+        ("request_id:req_paul_3 OR request_id:req_adrien_7", 2),
+    ] {
+        let search_request = Arc::new(SearchRequest {
+            index_id_patterns: vec![index_id.to_string()],
+            query_ast: qast_json_helper(query, &[]),
+            max_hits: 10,
+            ..Default::default()
+        });
+        let leaf_search_response = leaf_search(
+            searcher_context.clone(),
+            search_request,
+            test_sandbox.storage(),
+            splits_offsets.clone(),
+            test_sandbox.doc_mapper(),
+            searcher_context.get_aggregation_limits(),
+        )
+        .await?;
+        assert_eq!(leaf_search_response.num_hits, expected_num_hits);
+        assert_eq!(leaf_search_response.num_successful_splits, 2);
+    }
+    // The bloom filters of both splits were consulted.
+    for split in &splits {
+        let serialized_bloom_filters = searcher_context
+            .split_bloom_filters_cache
+            .get(split.split_id())
+            .unwrap();
+        assert!(!serialized_bloom_filters.is_empty());
+    }
+    // Splits skipped thanks to their bloom filters are profiled too.
+    let search_request = Arc::new(SearchRequest {
+        index_id_patterns: vec![index_id.to_string()],
+        query_ast: qast_json_helper("request_id:req_francois_3", &[]),
+        max_hits: 10,
+        profile: true,
+        ..Default::default()
+    });
+    let leaf_search_response = leaf_search(
+        searcher_context.clone(),
+        search_request,
+        test_sandbox.storage(),
+        splits_offsets.clone(),
+        test_sandbox.doc_mapper(),
+        searcher_context.get_aggregation_limits(),
+    )
+    .await?;
+    assert_eq!(leaf_search_response.num_hits, 0);
+    assert_eq!(leaf_search_response.split_profiles.len(), 2);
+
+    for split_profile in &leaf_search_response.split_profiles {
+        assert_eq!(split_profile.query_execution_micros, 0);
+        assert!(split_profile.cache_num_bytes > 0);
+    }
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
 async fn test_search_util(test_sandbox: &TestSandbox, query: &str) -> Vec<u32> {
     let splits = test_sandbox
         .metastore()
@@ -1467,6 +1693,118 @@ async fn test_single_node_aggregation_missing_fast_field() {
     test_sandbox.assert_quit().await;
 }
 
+#[tokio::test]
+async fn test_single_node_nested_aggregation() -> anyhow::Result<()> {
+    let index_id = "single-node-nested-agg";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: customer
+                type: text
+                tokenizer: raw
+                fast: true
+              - name: items
+                type: nested
+                field_mappings:
+                  - name: sku
+                    type: text
+                    tokenizer: raw
+                  - name: qty
+                    type: u64
+        "#;
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["customer"]).await?;
+    let docs = vec![
+        json!({"customer": "alice", "items": [{"sku": "A", "qty": 1}, {"sku": "B", "qty": 10}]}),
+        json!({"customer": "bob", "items": [{"sku": "A", "qty": 5}, {"sku": "A", "qty": 2}]}),
+        json!({"customer": "carol", "items": [{"sku": "C", "qty": 3}]}),
+    ];
+    test_sandbox.add_documents(docs).await?;
+
+    let agg_req = json!({
+        "nested_aggregations": {
+            "items": {
+                "path": "items",
+                "aggs": {
+                    "by_sku": {
+                        "terms": {
+                            "field": "items.sku",
+                            "size": 2,
+                            "aggs": {
+                                "qty": { "sum": { "field": "items.qty" } },
+                                "orders": "reverse_nested"
+                            }
+                        }
+                    }
+                }
+            }
+        },
+        "aggregations": {
+            "customers": { "terms": { "field": "customer" } }
+        }
+    });
+    let search_request = SearchRequest {
+        index_id_patterns: vec![index_id.to_string()],
+        query_ast: qast_json_helper("*", &[]),
+        aggregation_request: Some(agg_req.to_string()),
+        ..Default::default()
+    };
+    let single_node_result = single_node_search(
+        search_request,
+        test_sandbox.metastore(),
+        test_sandbox.storage_resolver(),
+    )
+    .await?;
+    let agg_res_json: JsonValue = serde_json::from_str(&single_node_result.aggregation.unwrap())?;
+    assert_eq!(
+        agg_res_json["customers"]["buckets"]
+            .as_array()
+            .unwrap()
+            .len(),
+        3
+    );
+    assert_eq!(
+        agg_res_json["items"],
+        json!({
+            "doc_count": 5,
+            "by_sku": {
+                "doc_count_error_upper_bound": 0,
+                "sum_other_doc_count": 1,
+                "buckets": [
+                    { "key": "A", "doc_count": 3, "qty": { "value": 8.0 }, "orders": { "doc_count": 2 } },
+                    { "key": "B", "doc_count": 1, "qty": { "value": 10.0 }, "orders": { "doc_count": 1 } },
+                ]
+            }
+        })
+    );
+
+    let agg_req = json!({
+        "nested_aggregations": {
+            "customers": { "path": "customer", "aggs": {} }
+        }
+    });
+    let search_request = SearchRequest {
+        index_id_patterns: vec![index_id.to_string()],
+        query_ast: qast_json_helper("*", &[]),
+        aggregation_request: Some(agg_req.to_string()),
+        ..Default::default()
+    };
+    let single_node_error = single_node_search(
+        search_request,
+        test_sandbox.metastore(),
+        test_sandbox.storage_resolver(),
+    )
+    .await
+    .unwrap_err();
+    let SearchError::InvalidAggregationRequest(error_msg) = single_node_error else {
+        panic!();
+    };
+    assert_eq!(
+        error_msg,
+        "field `customer` of nested aggregation `customers` is not a nested field"
+    );
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_single_node_with_ip_field() -> anyhow::Result<()> {
     let index_id = "single-node-with-ip-field";
@@ -1889,6 +2227,56 @@ async fn test_search_in_text_field_with_custom_tokenizer() -> anyhow::Result<()>
     Ok(())
 }
 
+#[tokio::test]
+async fn test_search_with_query_time_synonyms() -> anyhow::Result<()> {
+    let doc_mapping_yaml = r#"
+            tokenizers:
+              - name: synonyms_tokenizer
+                type: simple
+                filters:
+                  - lower_caser
+                  - synonyms:
+                      synonyms:
+                        - auto, wagen
+                      query_time: true
+            field_mappings:
+              - name: body
+                type: text
+                tokenizer: synonyms_tokenizer
+                record: position
+        "#;
+    let test_sandbox =
+        TestSandbox::create("search_query_time_synonyms", doc_mapping_yaml, "{}", &[])
+            .await
+            .unwrap();
+    let docs = vec![
+        json!({"body": "my auto is red"}),
+        json!({"body": "my wagen is red"}),
+        json!({"body": "my bike is red"}),
+    ];
+    test_sandbox.add_documents(docs).await.unwrap();
+
+    // The synonyms emitted at the same position as the query token are alternatives to it, in
+    // full text, boolean and phrase queries alike.
+    for query in [
+        "body:auto",
+        "body:WAGEN",
+        "body:auto AND body:red",
+        r#"body:"my auto is red""#,
+        r#"body:"wagen is""#,
+    ] {
+        let mut docs = test_search_util(&test_sandbox, query).await;
+        docs.sort();
+        assert_eq!(&docs[..], &[0u32, 1u32], "query: {query}");
+    }
+    {
+        let docs = test_search_util(&test_sandbox, r#"body:"auto red""#).await;
+        assert!(docs.is_empty());
+    }
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
 #[test]
 fn test_global_doc_address_ser_deser() {
     let doc_address = GlobalDocAddress {
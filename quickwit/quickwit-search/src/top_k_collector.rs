@@ -24,6 +24,7 @@ use std::marker::PhantomData;
 use quickwit_common::binary_heap::TopK;
 use quickwit_proto::search::{PartialHit, SortOrder};
 use quickwit_proto::types::SplitId;
+use tantivy::fastfield::Column;
 use tantivy::{DocId, Score};
 
 use crate::collector::{
@@ -876,3 +877,40 @@ impl SearchAfterSegment {
         })
     }
 }
+
+/// Stops the collection of a segment whose documents are sorted by the first sort field of the
+/// request, as soon as no remaining document can enter the top K.
+///
+/// Once K documents have been collected, the documents sharing the sort value of the K-th
+/// document are still collected, since the top K breaks ties on the second sort field or the doc
+/// id. The first document with another sort value ends the collection.
+pub(crate) struct SortedSegmentEarlyTermination {
+    sort_column: Column<u64>,
+    num_hits_to_collect: usize,
+    num_hits: usize,
+    last_top_k_sort_value_opt: Option<Option<u64>>,
+}
+
+impl SortedSegmentEarlyTermination {
+    pub fn new(sort_column: Column<u64>, num_hits_to_collect: usize) -> Self {
+        Self {
+            sort_column,
+            num_hits_to_collect,
+            num_hits: 0,
+            last_top_k_sort_value_opt: None,
+        }
+    }
+
+    /// Returns `true` if the document, and all the following ones in the segment, can be skipped.
+    /// Must be called on the documents that are about to be collected, in doc id order.
+    pub fn is_terminated(&mut self, doc_id: DocId) -> bool {
+        if self.num_hits < self.num_hits_to_collect {
+            self.num_hits += 1;
+            if self.num_hits == self.num_hits_to_collect {
+                self.last_top_k_sort_value_opt = Some(self.sort_column.first(doc_id));
+            }
+            return false;
+        }
+        self.last_top_k_sort_value_opt != Some(self.sort_column.first(doc_id))
+    }
+}
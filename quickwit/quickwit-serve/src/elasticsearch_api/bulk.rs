@@ -24,8 +24,12 @@ use hyper::StatusCode;
 use quickwit_config::{disable_ingest_v1, enable_ingest_v2};
 use quickwit_ingest::{
     CommitType, DocBatchBuilder, IngestRequest, IngestService, IngestServiceClient,
+    IngestServiceError,
 };
 use quickwit_proto::ingest::router::IngestRouterServiceClient;
+use quickwit_proto::metastore::{
+    FindIndexTemplateMatchesRequest, MetastoreService, MetastoreServiceClient,
+};
 use quickwit_proto::types::IndexId;
 use warp::{Filter, Rejection};
 
@@ -42,13 +46,24 @@ use crate::{with_arg, Body};
 pub fn es_compat_bulk_handler(
     ingest_service: IngestServiceClient,
     ingest_router: IngestRouterServiceClient,
+    metastore: MetastoreServiceClient,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
     elastic_bulk_filter()
         .and(with_arg(ingest_service))
         .and(with_arg(ingest_router))
-        .then(|body, bulk_options, ingest_service, ingest_router| {
-            elastic_ingest_bulk(None, body, bulk_options, ingest_service, ingest_router)
-        })
+        .and(with_arg(metastore))
+        .then(
+            |body, bulk_options, ingest_service, ingest_router, metastore| {
+                elastic_ingest_bulk(
+                    None,
+                    body,
+                    bulk_options,
+                    ingest_service,
+                    ingest_router,
+                    metastore,
+                )
+            },
+        )
         .and(extract_format_from_qs())
         .map(make_elastic_api_response)
         .recover(recover_fn)
@@ -58,18 +73,21 @@ pub fn es_compat_bulk_handler(
 pub fn es_compat_index_bulk_handler(
     ingest_service: IngestServiceClient,
     ingest_router: IngestRouterServiceClient,
+    metastore: MetastoreServiceClient,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
     elastic_index_bulk_filter()
         .and(with_arg(ingest_service))
         .and(with_arg(ingest_router))
+        .and(with_arg(metastore))
         .then(
-            |index_id, body, bulk_options, ingest_service, ingest_router| {
+            |index_id, body, bulk_options, ingest_service, ingest_router, metastore| {
                 elastic_ingest_bulk(
                     Some(index_id),
                     body,
                     bulk_options,
                     ingest_service,
                     ingest_router,
+                    metastore,
                 )
             },
         )
@@ -85,6 +103,7 @@ async fn elastic_ingest_bulk(
     bulk_options: ElasticBulkOptions,
     ingest_service: IngestServiceClient,
     ingest_router: IngestRouterServiceClient,
+    metastore: MetastoreServiceClient,
 ) -> Result<ElasticBulkResponse, ElasticsearchError> {
     if enable_ingest_v2() || bulk_options.enable_ingest_v2 {
         return elastic_bulk_ingest_v2(default_index_id, body, bulk_options, ingest_router).await;
@@ -134,6 +153,7 @@ async fn elastic_ingest_bulk(
 
         doc_batch_builder.ingest_doc(source);
     }
+    let index_ids: Vec<IndexId> = doc_batch_builders.keys().cloned().collect();
     let doc_batches = doc_batch_builders
         .into_values()
         .map(|builder| builder.build())
@@ -143,7 +163,19 @@ async fn elastic_ingest_bulk(
         doc_batches,
         commit: commit_type.into(),
     };
-    ingest_service.ingest(ingest_request).await?;
+    match ingest_service.ingest(ingest_request).await {
+        Ok(_) => {}
+        Err(ingest_error @ IngestServiceError::IndexNotFound { .. }) => {
+            // Indexes created from an index template are only reachable through ingest V2, so we
+            // hand the request over to the router, which auto-creates the missing indexes.
+            if matches_index_template(&metastore, index_ids).await? {
+                return elastic_bulk_ingest_v2(default_index_id, body, bulk_options, ingest_router)
+                    .await;
+            }
+            return Err(ingest_error.into());
+        }
+        Err(ingest_error) => return Err(ingest_error.into()),
+    }
 
     let took_millis = now.elapsed().as_millis() as u64;
     let errors = false;
@@ -155,6 +187,18 @@ async fn elastic_ingest_bulk(
     Ok(bulk_response)
 }
 
+/// Returns whether at least one of the index IDs matches an index template.
+async fn matches_index_template(
+    metastore: &MetastoreServiceClient,
+    index_ids: Vec<IndexId>,
+) -> Result<bool, ElasticsearchError> {
+    let find_index_template_matches_request = FindIndexTemplateMatchesRequest { index_ids };
+    let find_index_template_matches_response = metastore
+        .find_index_template_matches(find_index_template_matches_request)
+        .await?;
+    Ok(!find_index_template_matches_response.matches.is_empty())
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
@@ -189,7 +233,7 @@ mod tests {
             search_service,
             ingest_service,
             ingest_router,
-            MetastoreServiceClient::mocked(),
+            metastore_for_test(),
             index_service,
         );
         let payload = r#"
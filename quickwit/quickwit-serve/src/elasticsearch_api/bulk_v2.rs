@@ -273,6 +273,11 @@ fn make_elastic_bulk_response_v2(
                 format!("shard rate limiting [{}]", failure.index_id),
                 StatusCode::TOO_MANY_REQUESTS,
             ),
+            IngestFailureReason::QuotaExceeded => (
+                ElasticException::RateLimited,
+                format!("quota exceeded [{}]", failure.index_id),
+                StatusCode::TOO_MANY_REQUESTS,
+            ),
             reason => {
                 let pretty_reason = reason
                     .as_str_name()
@@ -24,8 +24,9 @@ use warp::reject::LengthRequired;
 use warp::{Filter, Rejection};
 
 use super::model::{
-    CatIndexQueryParams, DeleteQueryParams, FieldCapabilityQueryParams, FieldCapabilityRequestBody,
-    MultiSearchQueryParams, SearchQueryParamsCount,
+    CatIndexQueryParams, DeleteQueryParams, ElasticIndexTemplate, FieldCapabilityQueryParams,
+    FieldCapabilityRequestBody, MultiSearchQueryParams, PutIndexTemplateQueryParams,
+    ReindexQueryParams, ReindexRequestBody, SearchQueryParamsCount,
 };
 use crate::decompression::get_body_bytes;
 use crate::elasticsearch_api::model::{
@@ -199,6 +200,24 @@ pub(crate) fn elastic_index_stats_filter(
         .and(warp::get())
 }
 
+// No support for any query parameters for now.
+#[utoipa::path(get, tag = "Metadata", path = "/{index}/_mapping")]
+pub(crate) fn elastic_index_mapping_filter(
+) -> impl Filter<Extract = (Vec<String>,), Error = Rejection> + Clone {
+    warp::path!("_elastic" / String / "_mapping")
+        .and_then(extract_index_id_patterns)
+        .and(warp::get())
+}
+
+// No support for any query parameters for now.
+#[utoipa::path(get, tag = "Metadata", path = "/{index}/_settings")]
+pub(crate) fn elastic_index_settings_filter(
+) -> impl Filter<Extract = (Vec<String>,), Error = Rejection> + Clone {
+    warp::path!("_elastic" / String / "_settings")
+        .and_then(extract_index_id_patterns)
+        .and(warp::get())
+}
+
 #[utoipa::path(get, tag = "Search", path = "/_stats")]
 pub(crate) fn elastic_stats_filter() -> impl Filter<Extract = (), Error = Rejection> + Clone {
     warp::path!("_elastic" / "_stats").and(warp::get())
@@ -242,6 +261,46 @@ pub(crate) fn elastic_multi_search_filter(
         .and(serde_qs::warp::query(serde_qs::Config::default()))
 }
 
+#[utoipa::path(put, tag = "Templates", path = "/_index_template/{name}")]
+pub(crate) fn elastic_put_index_template_filter() -> impl Filter<
+    Extract = (String, PutIndexTemplateQueryParams, ElasticIndexTemplate),
+    Error = Rejection,
+> + Clone {
+    warp::path!("_elastic" / "_index_template" / String)
+        .and(warp::put().or(warp::post()).unify())
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+        .and(warp::body::content_length_limit(BODY_LENGTH_LIMIT.as_u64()))
+        .and(warp::body::json())
+}
+
+#[utoipa::path(get, tag = "Templates", path = "/_index_template/{name}")]
+pub(crate) fn elastic_get_index_template_filter(
+) -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+    warp::path!("_elastic" / "_index_template" / String).and(warp::get())
+}
+
+#[utoipa::path(get, tag = "Templates", path = "/_index_template")]
+pub(crate) fn elastic_list_index_templates_filter(
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::path!("_elastic" / "_index_template").and(warp::get())
+}
+
+#[utoipa::path(delete, tag = "Templates", path = "/_index_template/{name}")]
+pub(crate) fn elastic_delete_index_template_filter(
+) -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+    warp::path!("_elastic" / "_index_template" / String).and(warp::delete())
+}
+
+#[utoipa::path(post, tag = "Indexes", path = "/_reindex")]
+pub(crate) fn elastic_reindex_filter(
+) -> impl Filter<Extract = (ReindexQueryParams, ReindexRequestBody), Error = Rejection> + Clone {
+    warp::path!("_elastic" / "_reindex")
+        .and(warp::post())
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+        .and(warp::body::content_length_limit(BODY_LENGTH_LIMIT.as_u64()))
+        .and(warp::body::json())
+}
+
 fn merge_scroll_body_params(
     from_query_string: ScrollQueryParams,
     from_body: ScrollQueryParams,
@@ -21,6 +21,7 @@ mod bulk;
 mod bulk_v2;
 mod filter;
 mod model;
+mod reindex;
 mod rest_handler;
 
 use std::sync::Arc;
@@ -28,18 +29,24 @@ use std::sync::Arc;
 use bulk::{es_compat_bulk_handler, es_compat_index_bulk_handler};
 pub use filter::ElasticCompatibleApi;
 use hyper::StatusCode;
+pub use model::{ReindexQueryParams, ReindexResponse};
 use quickwit_config::NodeConfig;
 use quickwit_index_management::IndexService;
 use quickwit_ingest::IngestServiceClient;
 use quickwit_proto::ingest::router::IngestRouterServiceClient;
 use quickwit_proto::metastore::MetastoreServiceClient;
 use quickwit_search::SearchService;
+use reindex::es_compat_reindex_handler;
 pub use rest_handler::{
     es_compat_cat_indices_handler, es_compat_cluster_info_handler, es_compat_delete_index_handler,
+    es_compat_delete_index_template_handler, es_compat_get_index_template_handler,
     es_compat_index_cat_indices_handler, es_compat_index_count_handler,
-    es_compat_index_field_capabilities_handler, es_compat_index_multi_search_handler,
-    es_compat_index_search_handler, es_compat_index_stats_handler, es_compat_resolve_index_handler,
-    es_compat_scroll_handler, es_compat_search_handler, es_compat_stats_handler,
+    es_compat_index_field_capabilities_handler, es_compat_index_mapping_handler,
+    es_compat_index_multi_search_handler, es_compat_index_search_handler,
+    es_compat_index_settings_handler, es_compat_index_stats_handler,
+    es_compat_list_index_templates_handler, es_compat_put_index_template_handler,
+    es_compat_resolve_index_handler, es_compat_scroll_handler, es_compat_search_handler,
+    es_compat_stats_handler,
 };
 use serde::{Deserialize, Serialize};
 use warp::{Filter, Rejection};
@@ -66,9 +73,14 @@ pub fn elastic_api_handlers(
         .or(es_compat_bulk_handler(
             ingest_service.clone(),
             ingest_router.clone(),
+            metastore.clone(),
         ))
         .boxed()
-        .or(es_compat_index_bulk_handler(ingest_service, ingest_router))
+        .or(es_compat_index_bulk_handler(
+            ingest_service,
+            ingest_router.clone(),
+            metastore.clone(),
+        ))
         .or(es_compat_index_search_handler(search_service.clone()))
         .or(es_compat_index_count_handler(search_service.clone()))
         .or(es_compat_scroll_handler(search_service.clone()))
@@ -83,6 +95,22 @@ pub fn elastic_api_handlers(
         .or(es_compat_index_cat_indices_handler(metastore.clone()))
         .or(es_compat_cat_indices_handler(metastore.clone()))
         .or(es_compat_resolve_index_handler(metastore.clone()))
+        .boxed()
+        .or(es_compat_index_mapping_handler(
+            metastore.clone(),
+            search_service.clone(),
+        ))
+        .or(es_compat_index_settings_handler(metastore.clone()))
+        .or(es_compat_put_index_template_handler(metastore.clone()))
+        .or(es_compat_get_index_template_handler(metastore.clone()))
+        .or(es_compat_list_index_templates_handler(metastore.clone()))
+        .or(es_compat_delete_index_template_handler(metastore.clone()))
+        .boxed()
+        .or(es_compat_reindex_handler(
+            search_service,
+            ingest_router,
+            metastore,
+        ))
         .recover(recover_fn)
         .boxed()
     // Register newly created handlers here.
@@ -148,7 +176,10 @@ mod tests {
     use super::elastic_api_handlers;
     use super::model::ElasticsearchError;
     use crate::elasticsearch_api::model::MultiSearchResponse;
-    use crate::elasticsearch_api::rest_handler::es_compat_cluster_info_handler;
+    use crate::elasticsearch_api::rest_handler::{
+        es_compat_cluster_info_handler, es_compat_delete_index_template_handler,
+        es_compat_get_index_template_handler, es_compat_put_index_template_handler,
+    };
     use crate::rest::recover_fn;
     use crate::BuildInfo;
 
@@ -483,4 +514,81 @@ mod tests {
             .await;
         assert_eq!(resp.status(), 200);
     }
+
+    #[tokio::test]
+    async fn test_es_compat_index_template_handlers() {
+        let metastore = metastore_for_test();
+        let handler = es_compat_put_index_template_handler(metastore.clone())
+            .or(es_compat_get_index_template_handler(metastore.clone()))
+            .or(es_compat_delete_index_template_handler(metastore));
+
+        let index_template_json = r#"{
+            "index_patterns": ["logs-*"],
+            "priority": 10,
+            "template": {
+                "mappings": {
+                    "properties": {
+                        "@timestamp": {"type": "date"},
+                        "service": {"type": "keyword"},
+                        "message": {"type": "text"}
+                    }
+                }
+            }
+        }"#;
+        let resp = warp::test::request()
+            .path("/_elastic/_index_template/logs")
+            .method("PUT")
+            .body(index_template_json)
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+
+        let resp = warp::test::request()
+            .path("/_elastic/_index_template/logs?create=true")
+            .method("PUT")
+            .body(index_template_json)
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+
+        let resp = warp::test::request()
+            .path("/_elastic/_index_template/logs")
+            .method("GET")
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        let expected_response_json = serde_json::json!({
+            "index_templates": [{
+                "name": "logs",
+                "index_template": {
+                    "index_patterns": ["logs-*"],
+                    "priority": 10,
+                    "template": {
+                        "mappings": {
+                            "properties": {
+                                "service": {"type": "keyword"},
+                                "message": {"type": "text"}
+                            }
+                        }
+                    }
+                }
+            }]
+        });
+        assert_json_include!(actual: resp_json, expected: expected_response_json);
+
+        let resp = warp::test::request()
+            .path("/_elastic/_index_template/logs")
+            .method("DELETE")
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+
+        let resp = warp::test::request()
+            .path("/_elastic/_index_template/logs")
+            .method("GET")
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), 404);
+    }
 }
@@ -23,6 +23,7 @@ use quickwit_common::{rate_limited_debug, rate_limited_error};
 use quickwit_index_management::IndexServiceError;
 use quickwit_ingest::IngestServiceError;
 use quickwit_proto::ingest::IngestV2Error;
+use quickwit_proto::metastore::MetastoreError;
 use quickwit_proto::ServiceError;
 use quickwit_search::SearchError;
 use serde::{Deserialize, Serialize};
@@ -120,6 +121,19 @@ impl From<IngestV2Error> for ElasticsearchError {
     }
 }
 
+impl From<MetastoreError> for ElasticsearchError {
+    fn from(metastore_error: MetastoreError) -> Self {
+        let status = metastore_error.error_code().http_status_code();
+        let exception_opt = match &metastore_error {
+            MetastoreError::AlreadyExists(_) => Some(ElasticException::ResourceAlreadyExists),
+            MetastoreError::InvalidArgument { .. } => Some(ElasticException::IllegalArgument),
+            MetastoreError::NotFound(_) => Some(ElasticException::ResourceNotFound),
+            _ => None,
+        };
+        ElasticsearchError::new(status, metastore_error.to_string(), exception_opt)
+    }
+}
+
 impl From<IndexServiceError> for ElasticsearchError {
     fn from(ingest_error: IndexServiceError) -> Self {
         let status = ingest_error.error_code().http_status_code();
@@ -156,6 +170,10 @@ pub enum ElasticException {
     // This is an exception proper to Quickwit.
     #[serde(rename = "rate_limited_exception")]
     RateLimited,
+    #[serde(rename = "resource_already_exists_exception")]
+    ResourceAlreadyExists,
+    #[serde(rename = "resource_not_found_exception")]
+    ResourceNotFound,
     // This is an exception proper to Quickwit.
     #[serde(rename = "source_not_found_exception")]
     SourceNotFound,
@@ -172,6 +190,8 @@ impl ElasticException {
             Self::RateLimited => "rate_limited_exception",
             Self::IllegalArgument => "illegal_argument_exception",
             Self::IndexNotFound => "index_not_found_exception",
+            Self::ResourceAlreadyExists => "resource_already_exists_exception",
+            Self::ResourceNotFound => "resource_not_found_exception",
             Self::SourceNotFound => "source_not_found_exception",
             Self::Timeout => "timeout_exception",
         }
@@ -0,0 +1,663 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use anyhow::{bail, Context};
+use quickwit_config::{DocMapping, IndexTemplate, IndexTemplateId};
+use quickwit_doc_mapper::{FastFieldOptions, FieldMappingEntry, FieldMappingType, Mode};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+
+/// Name of the field that Elasticsearch clients (Beats, Logstash, Vector, ...) use to store the
+/// timestamp of the events. When present in the mappings, it is used as the timestamp field of the
+/// index.
+const ELASTIC_TIMESTAMP_FIELD: &str = "@timestamp";
+
+/// Body of the `PUT _index_template/{name}` requests.
+///
+/// Only the `index_patterns`, `priority`, `_meta.description`, and `template.mappings` entries are
+/// taken into account. Settings, aliases, and component templates are accepted but ignored.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ElasticIndexTemplate {
+    pub index_patterns: Vec<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<usize>,
+    #[serde(default)]
+    pub template: ElasticTemplateBody,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub composed_of: Vec<String>,
+    #[serde(rename = "_meta")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<JsonMap<String, JsonValue>>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ElasticTemplateBody {
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settings: Option<JsonValue>,
+    #[serde(default)]
+    pub mappings: ElasticMappings,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ElasticMappings {
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dynamic: Option<JsonValue>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dynamic_templates: Vec<JsonMap<String, JsonValue>>,
+    #[serde(default)]
+    pub properties: JsonMap<String, JsonValue>,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+pub struct PutIndexTemplateQueryParams {
+    /// If `true`, the request fails if the index template already exists.
+    #[serde(default)]
+    pub create: bool,
+}
+
+/// Response of the `GET _index_template` requests.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ElasticIndexTemplatesResponse {
+    pub index_templates: Vec<ElasticIndexTemplateEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ElasticIndexTemplateEntry {
+    pub name: String,
+    pub index_template: ElasticIndexTemplate,
+}
+
+/// Converts an Elasticsearch index template into a Quickwit index template.
+pub(crate) fn elastic_to_quickwit_index_template(
+    template_id: IndexTemplateId,
+    elastic_index_template: ElasticIndexTemplate,
+) -> anyhow::Result<IndexTemplate> {
+    let description = elastic_index_template
+        .meta
+        .as_ref()
+        .and_then(|meta| meta.get("description"))
+        .and_then(|description| description.as_str())
+        .map(|description| description.to_string());
+    let doc_mapping = elastic_mappings_to_doc_mapping(&elastic_index_template.template.mappings)?;
+    let index_template = IndexTemplate {
+        template_id,
+        index_id_patterns: elastic_index_template.index_patterns,
+        index_root_uri: None,
+        priority: elastic_index_template.priority.unwrap_or_default(),
+        description,
+        doc_mapping,
+        indexing_settings: Default::default(),
+        search_settings: Default::default(),
+        retention_policy_opt: None,
+    };
+    Ok(index_template)
+}
+
+/// Converts a Quickwit index template into an Elasticsearch index template.
+pub(crate) fn quickwit_to_elastic_index_template(
+    index_template: &IndexTemplate,
+) -> ElasticIndexTemplate {
+    let meta = index_template.description.as_ref().map(|description| {
+        let mut meta = JsonMap::new();
+        meta.insert("description".to_string(), json!(description));
+        meta
+    });
+    ElasticIndexTemplate {
+        index_patterns: index_template.index_id_patterns.clone(),
+        priority: Some(index_template.priority),
+        template: ElasticTemplateBody {
+            settings: None,
+            mappings: doc_mapping_to_elastic_mappings(&index_template.doc_mapping),
+        },
+        composed_of: Vec::new(),
+        meta,
+    }
+}
+
+/// Translates Elasticsearch mappings into a Quickwit doc mapping.
+///
+/// - `keyword`, `constant_keyword`, and `wildcard` fields become `text` fields with the `raw`
+///   tokenizer.
+/// - `text` and `match_only_text` fields become `text` fields with the `default` tokenizer.
+/// - `date` and `date_nanos` fields become `datetime` fields.
+/// - numeric fields become `i64`, `u64`, or `f64` fields.
+/// - `object` and `nested` fields become `object` fields, and `flattened` fields become `json`
+///   fields.
+///
+/// Fields of unsupported types are left out of the doc mapping and handled according to the
+/// `dynamic` setting. Dynamic templates matching all the string fields set the dynamic mapping of
+/// the doc mapping. Other dynamic templates are ignored.
+pub(crate) fn elastic_mappings_to_doc_mapping(
+    elastic_mappings: &ElasticMappings,
+) -> anyhow::Result<DocMapping> {
+    let field_mappings = elastic_properties_to_field_mappings(&elastic_mappings.properties)?;
+
+    let mode = match &elastic_mappings.dynamic {
+        None | Some(JsonValue::Null) => "dynamic",
+        Some(JsonValue::Bool(true)) => "dynamic",
+        Some(JsonValue::Bool(false)) => "lenient",
+        Some(JsonValue::String(dynamic)) => match dynamic.as_str() {
+            "true" | "runtime" => "dynamic",
+            "false" => "lenient",
+            "strict" => "strict",
+            _ => bail!("unsupported `dynamic` mapping parameter `{dynamic}`"),
+        },
+        Some(dynamic) => bail!("unsupported `dynamic` mapping parameter `{dynamic}`"),
+    };
+    let timestamp_field_opt = field_mappings
+        .iter()
+        .any(|field_mapping| {
+            field_mapping["name"] == ELASTIC_TIMESTAMP_FIELD && field_mapping["type"] == "datetime"
+        })
+        .then_some(ELASTIC_TIMESTAMP_FIELD);
+
+    let mut doc_mapping_json = json!({
+        "mode": mode,
+        "field_mappings": field_mappings,
+        "timestamp_field": timestamp_field_opt,
+    });
+    if mode == "dynamic" {
+        if let Some(dynamic_mapping) =
+            elastic_dynamic_templates_to_dynamic_mapping(&elastic_mappings.dynamic_templates)
+        {
+            doc_mapping_json["dynamic_mapping"] = dynamic_mapping;
+        }
+    }
+    let doc_mapping: DocMapping = serde_json::from_value(doc_mapping_json)
+        .context("failed to convert Elasticsearch mappings into doc mapping")?;
+    Ok(doc_mapping)
+}
+
+fn elastic_properties_to_field_mappings(
+    properties: &JsonMap<String, JsonValue>,
+) -> anyhow::Result<Vec<JsonValue>> {
+    let mut field_mappings = Vec::with_capacity(properties.len());
+
+    for (field_name, property) in properties {
+        if let Some(field_mapping) = elastic_property_to_field_mapping(field_name, property)? {
+            field_mappings.push(field_mapping);
+        }
+    }
+    Ok(field_mappings)
+}
+
+fn elastic_property_to_field_mapping(
+    field_name: &str,
+    property: &JsonValue,
+) -> anyhow::Result<Option<JsonValue>> {
+    let Some(property) = property.as_object() else {
+        bail!("mapping of field `{field_name}` must be an object");
+    };
+    let property_type = match property.get("type").and_then(JsonValue::as_str) {
+        Some(property_type) => property_type,
+        // Fields with sub-properties but no explicit type are objects.
+        None if property.contains_key("properties") => "object",
+        None => bail!("mapping of field `{field_name}` has no type"),
+    };
+    let indexed = property
+        .get("index")
+        .and_then(JsonValue::as_bool)
+        .unwrap_or(true);
+    let fast = |default: bool| {
+        property
+            .get("doc_values")
+            .and_then(JsonValue::as_bool)
+            .unwrap_or(default)
+    };
+    let mut field_mapping = match property_type {
+        "keyword" | "constant_keyword" | "wildcard" => json!({
+            "name": field_name,
+            "type": "text",
+            "tokenizer": "raw",
+            "fast": fast(true),
+        }),
+        "text" | "match_only_text" => json!({
+            "name": field_name,
+            "type": "text",
+            "tokenizer": "default",
+            "record": "position",
+        }),
+        "date" | "date_nanos" => {
+            let input_formats = property
+                .get("format")
+                .and_then(JsonValue::as_str)
+                .map(elastic_date_formats_to_input_formats)
+                .unwrap_or_default();
+            let mut field_mapping = json!({
+                "name": field_name,
+                "type": "datetime",
+                "indexed": indexed,
+                // The timestamp field must be a fast field.
+                "fast": fast(true) || field_name == ELASTIC_TIMESTAMP_FIELD,
+            });
+            if !input_formats.is_empty() {
+                field_mapping["input_formats"] = json!(input_formats);
+            }
+            field_mapping
+        }
+        "long" | "integer" | "short" | "byte" => json!({
+            "name": field_name,
+            "type": "i64",
+            "indexed": indexed,
+            "fast": fast(true),
+        }),
+        "unsigned_long" => json!({
+            "name": field_name,
+            "type": "u64",
+            "indexed": indexed,
+            "fast": fast(true),
+        }),
+        "double" | "float" | "half_float" | "scaled_float" => json!({
+            "name": field_name,
+            "type": "f64",
+            "indexed": indexed,
+            "fast": fast(true),
+        }),
+        "boolean" => json!({
+            "name": field_name,
+            "type": "bool",
+            "indexed": indexed,
+            "fast": fast(true),
+        }),
+        "ip" => json!({
+            "name": field_name,
+            "type": "ip",
+            "indexed": indexed,
+            "fast": fast(true),
+        }),
+        "binary" => json!({
+            "name": field_name,
+            "type": "bytes",
+            "indexed": false,
+            "fast": fast(false),
+        }),
+        "flattened" => json!({
+            "name": field_name,
+            "type": "json",
+            "tokenizer": "raw",
+            "fast": fast(true),
+        }),
+        "object" | "nested" => {
+            let enabled = property
+                .get("enabled")
+                .and_then(JsonValue::as_bool)
+                .unwrap_or(true);
+            if !enabled {
+                return Ok(None);
+            }
+            let sub_properties = property
+                .get("properties")
+                .and_then(JsonValue::as_object)
+                .cloned()
+                .unwrap_or_default();
+            // The elements of the arrays of nested fields are stored in the `_nested.<path>` bytes
+            // fast field of the document itself, so that `nested` queries match the subfields of a
+            // single element.
+            json!({
+                "name": field_name,
+                "type": property_type,
+                "field_mappings": elastic_properties_to_field_mappings(&sub_properties)?,
+            })
+        }
+        // Other types (`alias`, `geo_point`, `histogram`, ...) have no Quickwit equivalent.
+        _ => return Ok(None),
+    };
+    if !indexed && matches!(field_mapping["type"].as_str(), Some("text" | "json")) {
+        // The `tokenizer` and `record` parameters are only allowed for indexed fields.
+        let field_mapping_object = field_mapping
+            .as_object_mut()
+            .expect("field mapping should be an object");
+        field_mapping_object.remove("tokenizer");
+        field_mapping_object.remove("record");
+        field_mapping_object.insert("indexed".to_string(), JsonValue::Bool(false));
+    }
+    Ok(Some(field_mapping))
+}
+
+/// Translates the `format` parameter of Elasticsearch `date` fields into Quickwit input formats.
+/// Custom formats are not supported and ignored.
+fn elastic_date_formats_to_input_formats(elastic_date_formats: &str) -> Vec<&'static str> {
+    let mut input_formats = Vec::new();
+
+    for elastic_date_format in elastic_date_formats.split("||") {
+        let input_format = match elastic_date_format.trim() {
+            "epoch_millis" | "epoch_second" => "unix_timestamp",
+            "strict_date_optional_time"
+            | "date_optional_time"
+            | "strict_date_optional_time_nanos"
+            | "strict_date_time"
+            | "date_time"
+            | "strict_date_time_no_millis"
+            | "date_time_no_millis" => "iso8601",
+            _ => continue,
+        };
+        if !input_formats.contains(&input_format) {
+            input_formats.push(input_format);
+        }
+    }
+    input_formats
+}
+
+/// Returns the dynamic mapping corresponding to the first dynamic template that applies to all the
+/// string fields, if any.
+fn elastic_dynamic_templates_to_dynamic_mapping(
+    dynamic_templates: &[JsonMap<String, JsonValue>],
+) -> Option<JsonValue> {
+    for dynamic_template in dynamic_templates.iter().flat_map(|entry| entry.values()) {
+        if dynamic_template.get("match_mapping_type") != Some(&json!("string")) {
+            continue;
+        }
+        let matches_all_fields = ["match", "path_match"].iter().all(|key| {
+            dynamic_template
+                .get(*key)
+                .map_or(true, |pattern| *pattern == "*")
+        }) && dynamic_template.get("unmatch").is_none()
+            && dynamic_template.get("path_unmatch").is_none();
+
+        if !matches_all_fields {
+            continue;
+        }
+        let mapping_type = dynamic_template
+            .get("mapping")
+            .and_then(|mapping| mapping.get("type"))
+            .and_then(JsonValue::as_str);
+
+        match mapping_type {
+            Some("keyword") => {
+                return Some(json!({
+                    "tokenizer": "raw",
+                    "fast": true,
+                    "expand_dots": true,
+                }))
+            }
+            Some("text") => {
+                return Some(json!({
+                    "tokenizer": "default",
+                    "record": "position",
+                    "expand_dots": true,
+                }))
+            }
+            _ => continue,
+        }
+    }
+    None
+}
+
+/// Translates a Quickwit doc mapping into Elasticsearch mappings.
+pub(crate) fn doc_mapping_to_elastic_mappings(doc_mapping: &DocMapping) -> ElasticMappings {
+    let dynamic = match doc_mapping.mode {
+        Mode::Strict => json!("strict"),
+        Mode::Lenient => json!(false),
+        Mode::Dynamic(_) => json!(true),
+    };
+    let properties = field_mappings_to_elastic_properties(&doc_mapping.field_mappings);
+
+    ElasticMappings {
+        dynamic: Some(dynamic),
+        dynamic_templates: Vec::new(),
+        properties,
+    }
+}
+
+fn field_mappings_to_elastic_properties(
+    field_mappings: &[FieldMappingEntry],
+) -> JsonMap<String, JsonValue> {
+    let mut properties = JsonMap::with_capacity(field_mappings.len());
+
+    for field_mapping in field_mappings {
+        if let Some(property) = field_mapping_to_elastic_property(&field_mapping.mapping_type) {
+            properties.insert(field_mapping.name.clone(), property);
+        }
+    }
+    properties
+}
+
+/// Translates a Quickwit field mapping into an Elasticsearch property. ES fields are multi-valued
+/// by default, so the cardinality of the field is ignored. Concatenate fields have no
+/// Elasticsearch equivalent and are left out.
+fn field_mapping_to_elastic_property(mapping_type: &FieldMappingType) -> Option<JsonValue> {
+    // Returns the property of a primitive field, setting the `index` and `doc_values` parameters
+    // only when they differ from the Elasticsearch defaults.
+    let primitive_property = |elastic_type: &str, indexed: bool, fast: bool| {
+        let mut property = json!({"type": elastic_type});
+        if !indexed {
+            property["index"] = json!(false);
+        }
+        if !fast {
+            property["doc_values"] = json!(false);
+        }
+        property
+    };
+    let property = match mapping_type {
+        FieldMappingType::Text(text_options, _) => {
+            let Some(indexing_options) = &text_options.indexing_options else {
+                return Some(json!({"type": "text", "index": false}));
+            };
+            if indexing_options.tokenizer.name() == "raw" {
+                json!({"type": "keyword"})
+            } else {
+                json!({"type": "text"})
+            }
+        }
+        FieldMappingType::I64(numeric_options, _) => {
+            primitive_property("long", numeric_options.indexed, numeric_options.fast)
+        }
+        FieldMappingType::U64(numeric_options, _) => primitive_property(
+            "unsigned_long",
+            numeric_options.indexed,
+            numeric_options.fast,
+        ),
+        FieldMappingType::F64(numeric_options, _) => {
+            primitive_property("double", numeric_options.indexed, numeric_options.fast)
+        }
+        FieldMappingType::Bool(bool_options, _) => {
+            primitive_property("boolean", bool_options.indexed, bool_options.fast)
+        }
+        FieldMappingType::DateTime(date_time_options, _) => {
+            primitive_property("date", date_time_options.indexed, date_time_options.fast)
+        }
+        FieldMappingType::IpAddr(ip_addr_options, _) => {
+            primitive_property("ip", ip_addr_options.indexed, ip_addr_options.fast)
+        }
+        // Binary fields are neither indexed nor have doc values by default in Elasticsearch.
+        FieldMappingType::Bytes(bytes_options, _) if bytes_options.fast => {
+            json!({"type": "binary", "doc_values": true})
+        }
+        FieldMappingType::Bytes(..) => json!({"type": "binary"}),
+        FieldMappingType::Json(json_options, _) => {
+            let is_fast = json_options.fast != FastFieldOptions::Disabled;
+            primitive_property(
+                "flattened",
+                json_options.indexing_options.is_some(),
+                is_fast,
+            )
+        }
+        FieldMappingType::Object(object_options) => json!({
+            "type": "object",
+            "properties": field_mappings_to_elastic_properties(&object_options.field_mappings),
+        }),
+        FieldMappingType::Nested(object_options) => json!({
+            "type": "nested",
+            "properties": field_mappings_to_elastic_properties(&object_options.field_mappings),
+        }),
+        FieldMappingType::Concatenate(_) => return None,
+    };
+    Some(property)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_elastic_to_quickwit_index_template() {
+        let elastic_index_template: ElasticIndexTemplate = serde_json::from_value(json!({
+            "index_patterns": ["logs-*"],
+            "priority": 10,
+            "template": {
+                "settings": {"number_of_shards": 1},
+                "mappings": {
+                    "dynamic_templates": [
+                        {
+                            "strings_as_keyword": {
+                                "match_mapping_type": "string",
+                                "mapping": {"type": "keyword"}
+                            }
+                        }
+                    ],
+                    "properties": {
+                        "@timestamp": {"type": "date", "format": "strict_date_optional_time||epoch_millis"},
+                        "message": {"type": "text"},
+                        "level": {"type": "keyword"},
+                        "status": {"type": "long"},
+                        "client_ip": {"type": "ip"},
+                        "location": {"type": "geo_point"},
+                        "host": {
+                            "properties": {
+                                "name": {"type": "keyword", "doc_values": false}
+                            }
+                        }
+                    }
+                }
+            },
+            "_meta": {"description": "Logs template."}
+        }))
+        .unwrap();
+        let index_template =
+            elastic_to_quickwit_index_template("logs".to_string(), elastic_index_template).unwrap();
+        index_template.validate().unwrap();
+
+        assert_eq!(index_template.template_id, "logs");
+        assert_eq!(index_template.index_id_patterns, ["logs-*"]);
+        assert_eq!(index_template.priority, 10);
+        assert_eq!(
+            index_template.description.as_deref(),
+            Some("Logs template.")
+        );
+        let doc_mapping = &index_template.doc_mapping;
+        assert_eq!(doc_mapping.timestamp_field.as_deref(), Some("@timestamp"));
+        // The `geo_point` field is left out.
+        assert_eq!(doc_mapping.field_mappings.len(), 6);
+
+        let doc_mapping_json = serde_json::to_value(doc_mapping).unwrap();
+        assert_eq!(doc_mapping_json["mode"], "dynamic");
+        assert_eq!(doc_mapping_json["dynamic_mapping"]["tokenizer"], "raw");
+
+        let elastic_mappings = doc_mapping_to_elastic_mappings(doc_mapping);
+        assert_eq!(elastic_mappings.dynamic, Some(json!(true)));
+
+        let properties = JsonValue::Object(elastic_mappings.properties);
+        assert_eq!(properties["@timestamp"], json!({"type": "date"}));
+        assert_eq!(properties["message"], json!({"type": "text"}));
+        assert_eq!(properties["level"], json!({"type": "keyword"}));
+        assert_eq!(properties["status"], json!({"type": "long"}));
+        assert_eq!(properties["client_ip"], json!({"type": "ip"}));
+        assert_eq!(
+            properties["host"],
+            json!({
+                "type": "object",
+                "properties": {
+                    "name": {"type": "keyword"}
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_elastic_nested_property_to_field_mapping() {
+        let elastic_mappings: ElasticMappings = serde_json::from_value(json!({
+            "properties": {
+                "comments": {
+                    "type": "nested",
+                    "properties": {
+                        "author": {"type": "keyword"},
+                        "stars": {"type": "integer"}
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let doc_mapping = elastic_mappings_to_doc_mapping(&elastic_mappings).unwrap();
+        let doc_mapping_json = serde_json::to_value(&doc_mapping).unwrap();
+        let comments_field_mapping = &doc_mapping_json["field_mappings"][0];
+        assert_eq!(comments_field_mapping["name"], "comments");
+        assert_eq!(comments_field_mapping["type"], "nested");
+        assert_eq!(
+            comments_field_mapping["field_mappings"][0]["name"],
+            "author"
+        );
+        assert_eq!(comments_field_mapping["field_mappings"][1]["name"], "stars");
+
+        let elastic_mappings = doc_mapping_to_elastic_mappings(&doc_mapping);
+        assert_eq!(
+            JsonValue::Object(elastic_mappings.properties),
+            json!({
+                "comments": {
+                    "type": "nested",
+                    "properties": {
+                        "author": {"type": "keyword"},
+                        "stars": {"type": "long"}
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_elastic_mappings_dynamic_parameter() {
+        for (dynamic, expected_mode) in [
+            (json!(true), "dynamic"),
+            (json!("true"), "dynamic"),
+            (json!(false), "lenient"),
+            (json!("strict"), "strict"),
+        ] {
+            let elastic_mappings = ElasticMappings {
+                dynamic: Some(dynamic),
+                ..Default::default()
+            };
+            let doc_mapping = elastic_mappings_to_doc_mapping(&elastic_mappings).unwrap();
+            let doc_mapping_json = serde_json::to_value(&doc_mapping).unwrap();
+            assert_eq!(doc_mapping_json["mode"], expected_mode);
+        }
+        let elastic_mappings = ElasticMappings {
+            dynamic: Some(json!("foo")),
+            ..Default::default()
+        };
+        elastic_mappings_to_doc_mapping(&elastic_mappings).unwrap_err();
+    }
+
+    #[test]
+    fn test_elastic_date_formats_to_input_formats() {
+        assert!(elastic_date_formats_to_input_formats("yyyy-MM-dd").is_empty());
+        assert_eq!(
+            elastic_date_formats_to_input_formats("strict_date_optional_time||epoch_millis"),
+            ["iso8601", "unix_timestamp"]
+        );
+        assert_eq!(
+            elastic_date_formats_to_input_formats("epoch_millis||epoch_second"),
+            ["unix_timestamp"]
+        );
+    }
+}
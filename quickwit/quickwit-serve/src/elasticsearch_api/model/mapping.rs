@@ -0,0 +1,294 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+
+use quickwit_metastore::IndexMetadata;
+use quickwit_proto::search::{ListFieldType, ListFieldsEntryResponse};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+
+use super::index_template::{doc_mapping_to_elastic_mappings, ElasticMappings};
+
+/// Response of the `GET {index}/_mapping` requests, keyed by index ID.
+pub type ElasticsearchMappingsResponse = HashMap<String, ElasticsearchMappingsEntry>;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ElasticsearchMappingsEntry {
+    pub mappings: ElasticMappings,
+}
+
+/// Response of the `GET {index}/_settings` requests, keyed by index ID.
+pub type ElasticsearchSettingsResponse = HashMap<String, ElasticsearchSettingsEntry>;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ElasticsearchSettingsEntry {
+    pub settings: JsonValue,
+}
+
+/// Builds the mappings of each index from its doc mapping, completed with the fields discovered
+/// in its splits by the list fields API. The latter covers the fields captured by JSON fields and
+/// by the dynamic mode, which are absent from the doc mapping. The internal fields of the splits,
+/// whose names start with `_` (`_nested.<path>`, `_field_presence`, ...), are left out.
+pub fn convert_to_es_mappings_response(
+    indexes_metadata: &[IndexMetadata],
+    list_fields_entries: &[ListFieldsEntryResponse],
+) -> ElasticsearchMappingsResponse {
+    let mut mappings_response =
+        ElasticsearchMappingsResponse::with_capacity(indexes_metadata.len());
+
+    for index_metadata in indexes_metadata {
+        let index_id = index_metadata.index_id();
+        let mut mappings =
+            doc_mapping_to_elastic_mappings(&index_metadata.index_config.doc_mapping);
+
+        for list_fields_entry in list_fields_entries {
+            if !list_fields_entry
+                .index_ids
+                .iter()
+                .any(|entry_index_id| entry_index_id == index_id)
+            {
+                continue;
+            }
+            if list_fields_entry.field_name.starts_with('_') {
+                continue;
+            }
+            let Some(property) = list_fields_entry_to_elastic_property(list_fields_entry) else {
+                continue;
+            };
+            insert_dynamic_property(
+                &mut mappings.properties,
+                &list_fields_entry.field_name,
+                property,
+            );
+        }
+        mappings_response.insert(
+            index_id.to_string(),
+            ElasticsearchMappingsEntry { mappings },
+        );
+    }
+    mappings_response
+}
+
+/// Builds Elasticsearch-shaped settings from the index metadata.
+pub fn convert_to_es_settings_response(
+    indexes_metadata: &[IndexMetadata],
+) -> ElasticsearchSettingsResponse {
+    indexes_metadata
+        .iter()
+        .map(|index_metadata| {
+            let index_config = &index_metadata.index_config;
+            let mut index_settings = json!({
+                "provided_name": index_metadata.index_id(),
+                "uuid": index_metadata.index_uid.incarnation_id.to_string(),
+                // Elasticsearch serializes the settings values as strings.
+                "creation_date": (index_metadata.create_timestamp * 1_000).to_string(),
+                "number_of_shards": "1",
+                "number_of_replicas": "0",
+                "refresh_interval": format!("{}s", index_config.indexing_settings.commit_timeout_secs),
+            });
+            let default_search_fields = &index_config.search_settings.default_search_fields;
+
+            if !default_search_fields.is_empty() {
+                index_settings["query"] = json!({ "default_field": default_search_fields });
+            }
+            let settings_entry = ElasticsearchSettingsEntry {
+                settings: json!({ "index": index_settings }),
+            };
+            (index_metadata.index_id().to_string(), settings_entry)
+        })
+        .collect()
+}
+
+fn list_fields_entry_to_elastic_property(
+    list_fields_entry: &ListFieldsEntryResponse,
+) -> Option<JsonValue> {
+    let field_type = ListFieldType::from_i32(list_fields_entry.field_type)?;
+    let elastic_field_type = match field_type {
+        // Fast string fields are the closest match to keywords: they can be aggregated and sorted
+        // on.
+        ListFieldType::Str if list_fields_entry.aggregatable => "keyword",
+        ListFieldType::Str => "text",
+        ListFieldType::U64 => "unsigned_long",
+        ListFieldType::I64 => "long",
+        ListFieldType::F64 => "double",
+        ListFieldType::Bool => "boolean",
+        ListFieldType::Date => "date_nanos",
+        ListFieldType::Bytes => "binary",
+        ListFieldType::IpAddr => "ip",
+        ListFieldType::Facet | ListFieldType::Json => return None,
+    };
+    Some(json!({ "type": elastic_field_type }))
+}
+
+/// Inserts a field discovered in the splits into the properties, creating the intermediate object
+/// properties along its path. Fields already declared in the doc mapping take precedence.
+fn insert_dynamic_property(
+    properties: &mut JsonMap<String, JsonValue>,
+    field_name: &str,
+    property: JsonValue,
+) {
+    let mut path = field_name.split('.').peekable();
+    let mut current_properties = properties;
+
+    while let Some(segment) = path.next() {
+        if path.peek().is_none() {
+            current_properties
+                .entry(segment.to_string())
+                .or_insert(property);
+            return;
+        }
+        let parent_property = current_properties
+            .entry(segment.to_string())
+            .or_insert_with(|| json!({ "type": "object", "properties": {} }));
+
+        // JSON fields are exposed as `flattened` fields. Since we know their content, we expose
+        // them as objects instead.
+        if parent_property["type"] == "flattened" {
+            *parent_property = json!({ "type": "object", "properties": {} });
+        }
+        if parent_property["type"] != "object" {
+            return;
+        }
+        let Some(sub_properties) = parent_property
+            .as_object_mut()
+            .and_then(|parent_property| parent_property.get_mut("properties"))
+            .and_then(JsonValue::as_object_mut)
+        else {
+            return;
+        };
+        current_properties = sub_properties;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list_fields_entry(
+        field_name: &str,
+        field_type: ListFieldType,
+        aggregatable: bool,
+        index_ids: &[&str],
+    ) -> ListFieldsEntryResponse {
+        ListFieldsEntryResponse {
+            field_name: field_name.to_string(),
+            field_type: field_type as i32,
+            index_ids: index_ids
+                .iter()
+                .map(|index_id| index_id.to_string())
+                .collect(),
+            searchable: true,
+            aggregatable,
+            non_searchable_index_ids: Vec::new(),
+            non_aggregatable_index_ids: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_convert_to_es_mappings_response() {
+        let mut index_metadata = IndexMetadata::for_test("test-index", "ram:///indexes/test-index");
+        index_metadata.index_config.doc_mapping = serde_json::from_value(json!({
+            "mode": "strict",
+            "field_mappings": [
+                {"name": "timestamp", "type": "datetime", "fast": true},
+                {"name": "service", "type": "text", "tokenizer": "raw", "fast": true},
+                {"name": "attributes", "type": "json"},
+                {
+                    "name": "resource",
+                    "type": "object",
+                    "field_mappings": [{"name": "host", "type": "text"}]
+                }
+            ],
+            "timestamp_field": "timestamp"
+        }))
+        .unwrap();
+        let list_fields_entries = vec![
+            list_fields_entry("service", ListFieldType::Str, true, &["test-index"]),
+            list_fields_entry(
+                "attributes.http.status",
+                ListFieldType::U64,
+                true,
+                &["test-index"],
+            ),
+            list_fields_entry(
+                "attributes.user",
+                ListFieldType::Str,
+                false,
+                &["test-index"],
+            ),
+            list_fields_entry("other", ListFieldType::Bool, true, &["other-index"]),
+            list_fields_entry(
+                "_nested.resource",
+                ListFieldType::Bytes,
+                true,
+                &["test-index"],
+            ),
+            list_fields_entry(
+                "_field_presence",
+                ListFieldType::U64,
+                false,
+                &["test-index"],
+            ),
+        ];
+        let mappings_response =
+            convert_to_es_mappings_response(&[index_metadata], &list_fields_entries);
+        let mappings_json = serde_json::to_value(&mappings_response).unwrap();
+        let expected_mappings_json = json!({
+            "test-index": {
+                "mappings": {
+                    "dynamic": "strict",
+                    "properties": {
+                        "timestamp": {"type": "date"},
+                        "service": {"type": "keyword"},
+                        "attributes": {
+                            "type": "object",
+                            "properties": {
+                                "http": {
+                                    "type": "object",
+                                    "properties": {
+                                        "status": {"type": "unsigned_long"}
+                                    }
+                                },
+                                "user": {"type": "text"}
+                            }
+                        },
+                        "resource": {
+                            "type": "object",
+                            "properties": {
+                                "host": {"type": "text"}
+                            }
+                        }
+                    }
+                }
+            }
+        });
+        assert_eq!(mappings_json, expected_mappings_json);
+    }
+
+    #[test]
+    fn test_convert_to_es_settings_response() {
+        let index_metadata = IndexMetadata::for_test("test-index", "ram:///indexes/test-index");
+        let settings_response = convert_to_es_settings_response(&[index_metadata]);
+        let index_settings = &settings_response["test-index"].settings["index"];
+        assert_eq!(index_settings["provided_name"], "test-index");
+        assert_eq!(index_settings["number_of_shards"], "1");
+        assert_eq!(index_settings["refresh_interval"], "60s");
+    }
+}
@@ -22,10 +22,15 @@ mod bulk_query_params;
 mod cat_indices;
 mod error;
 mod field_capability;
+mod index_template;
+mod mapping;
 mod multi_search;
+mod nested_aggregation;
+mod reindex;
 mod scroll;
 mod search_body;
 mod search_query_params;
+mod search_response;
 mod stats;
 
 pub use bulk_body::BulkAction;
@@ -39,13 +44,28 @@ pub use field_capability::{
     build_list_field_request_for_es_api, convert_to_es_field_capabilities_response,
     FieldCapabilityQueryParams, FieldCapabilityRequestBody, FieldCapabilityResponse,
 };
+pub(crate) use index_template::{
+    doc_mapping_to_elastic_mappings, elastic_to_quickwit_index_template,
+    quickwit_to_elastic_index_template, ElasticIndexTemplate, ElasticIndexTemplateEntry,
+    ElasticIndexTemplatesResponse, PutIndexTemplateQueryParams,
+};
+pub use mapping::{
+    convert_to_es_mappings_response, convert_to_es_settings_response, ElasticsearchMappingsEntry,
+    ElasticsearchMappingsResponse, ElasticsearchSettingsEntry, ElasticsearchSettingsResponse,
+};
 pub use multi_search::{
     MultiSearchHeader, MultiSearchQueryParams, MultiSearchResponse, MultiSearchSingleResponse,
 };
+pub(crate) use nested_aggregation::build_aggregation_request;
 use quickwit_proto::search::{SortDatetimeFormat, SortOrder};
+pub use reindex::{
+    ReindexDest, ReindexFailure, ReindexQueryParams, ReindexRequestBody, ReindexResponse,
+    ReindexRetries, ReindexScript, ReindexSource,
+};
 pub use scroll::ScrollQueryParams;
 pub use search_body::SearchBody;
 pub use search_query_params::{DeleteQueryParams, SearchQueryParams, SearchQueryParamsCount};
+pub use search_response::{ElasticsearchProfile, ElasticsearchSearchResponse};
 use serde::{Deserialize, Serialize};
 pub use stats::{ElasticsearchStatsResponse, StatsResponseEntry};
 
@@ -0,0 +1,386 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use quickwit_search::{ElementAggregation, NestedAggregation};
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+
+/// Default number of buckets returned by a `terms` aggregation.
+const DEFAULT_TERMS_SIZE: usize = 10;
+
+/// Builds the aggregation request of an Elasticsearch search request.
+///
+/// Top-level `nested` aggregations are translated into nested aggregations, which are computed
+/// over the elements of the nested field so that their sub-aggregations count elements rather
+/// than documents. They support the `terms`, `value_count`, `min`, `max`, `sum`, `avg`, `stats`,
+/// and `reverse_nested` sub-aggregations. The other aggregations are passed through as is.
+pub(crate) fn build_aggregation_request(
+    aggregations: &JsonMap<String, JsonValue>,
+) -> Result<String, String> {
+    let mut nested_aggregations: BTreeMap<String, NestedAggregation> = BTreeMap::new();
+    let mut regular_aggregations = JsonMap::new();
+
+    for (aggregation_name, aggregation) in aggregations {
+        if let Some(JsonValue::Object(nested_aggregation)) = aggregation.get("nested") {
+            let nested_aggregation =
+                parse_nested_aggregation(aggregation_name, nested_aggregation, aggregation)?;
+            nested_aggregations.insert(aggregation_name.clone(), nested_aggregation);
+        } else {
+            reject_nested_sub_aggregations(aggregation_name, aggregation)?;
+            regular_aggregations.insert(aggregation_name.clone(), aggregation.clone());
+        }
+    }
+    let aggregation_request_json = if nested_aggregations.is_empty() {
+        JsonValue::Object(regular_aggregations)
+    } else {
+        json!({
+            "nested_aggregations": nested_aggregations,
+            "aggregations": regular_aggregations,
+        })
+    };
+    Ok(aggregation_request_json.to_string())
+}
+
+/// Returns the sub-aggregations of an aggregation.
+fn sub_aggregations(aggregation: &JsonValue) -> Option<&JsonMap<String, JsonValue>> {
+    ["aggs", "aggregations"]
+        .into_iter()
+        .find_map(|sub_aggregations_key| aggregation.get(sub_aggregations_key)?.as_object())
+}
+
+/// Rejects the `nested` and `reverse_nested` aggregations that are not at a supported location.
+fn reject_nested_sub_aggregations(
+    aggregation_name: &str,
+    aggregation: &JsonValue,
+) -> Result<(), String> {
+    if aggregation.get("nested").is_some() {
+        return Err(format!(
+            "`nested` aggregations are only supported at the top level of the request \
+             (aggregation `{aggregation_name}`)"
+        ));
+    }
+    if aggregation.get("reverse_nested").is_some() {
+        return Err(format!(
+            "`reverse_nested` aggregations are only supported within a `nested` aggregation \
+             (aggregation `{aggregation_name}`)"
+        ));
+    }
+    if let Some(sub_aggregations) = sub_aggregations(aggregation) {
+        for (sub_aggregation_name, sub_aggregation) in sub_aggregations {
+            reject_nested_sub_aggregations(sub_aggregation_name, sub_aggregation)?;
+        }
+    }
+    Ok(())
+}
+
+fn parse_nested_aggregation(
+    aggregation_name: &str,
+    nested_aggregation: &JsonMap<String, JsonValue>,
+    aggregation: &JsonValue,
+) -> Result<NestedAggregation, String> {
+    let Some(path) = nested_aggregation.get("path").and_then(JsonValue::as_str) else {
+        return Err(format!(
+            "`nested` aggregation `{aggregation_name}` requires a `path`"
+        ));
+    };
+    let aggs = parse_element_aggregations(path, sub_aggregations(aggregation))?;
+    Ok(NestedAggregation {
+        path: path.to_string(),
+        aggs,
+    })
+}
+
+fn parse_element_aggregations(
+    path: &str,
+    aggregations_opt: Option<&JsonMap<String, JsonValue>>,
+) -> Result<BTreeMap<String, ElementAggregation>, String> {
+    let mut element_aggregations = BTreeMap::new();
+
+    for (aggregation_name, aggregation) in aggregations_opt.into_iter().flatten() {
+        let element_aggregation = parse_element_aggregation(path, aggregation_name, aggregation)?;
+        element_aggregations.insert(aggregation_name.clone(), element_aggregation);
+    }
+    Ok(element_aggregations)
+}
+
+fn parse_element_aggregation(
+    path: &str,
+    aggregation_name: &str,
+    aggregation: &JsonValue,
+) -> Result<ElementAggregation, String> {
+    let Some(aggregation_obj) = aggregation.as_object() else {
+        return Err(format!(
+            "aggregation `{aggregation_name}` must be an object"
+        ));
+    };
+    let sub_aggregations_opt = sub_aggregations(aggregation);
+    let Some((aggregation_type, params)) = aggregation_obj
+        .iter()
+        .find(|(key, _)| !matches!(key.as_str(), "aggs" | "aggregations"))
+    else {
+        return Err(format!(
+            "aggregation `{aggregation_name}` does not have a type"
+        ));
+    };
+    if aggregation_type == "nested" {
+        return Err(format!(
+            "`nested` aggregation `{aggregation_name}` cannot be nested in another `nested` \
+             aggregation"
+        ));
+    }
+    if aggregation_type != "terms" && sub_aggregations_opt.is_some() {
+        return Err(format!(
+            "`{aggregation_type}` aggregation `{aggregation_name}` cannot have sub-aggregations \
+             within a `nested` aggregation"
+        ));
+    }
+    let params = params.as_object().cloned().unwrap_or_default();
+    let field = || -> Result<String, String> {
+        let field = params
+            .get("field")
+            .and_then(JsonValue::as_str)
+            .ok_or_else(|| format!("aggregation `{aggregation_name}` requires a `field`"))?;
+        if !field.starts_with(&format!("{path}.")) {
+            return Err(format!(
+                "field `{field}` of aggregation `{aggregation_name}` does not belong to nested \
+                 field `{path}`"
+            ));
+        }
+        Ok(field.to_string())
+    };
+    let check_params = |supported_params: &[&str]| -> Result<(), String> {
+        if let Some(param) = params
+            .keys()
+            .find(|param| !supported_params.contains(&param.as_str()))
+        {
+            return Err(format!(
+                "parameter `{param}` of `{aggregation_type}` aggregation `{aggregation_name}` is \
+                 not supported within a `nested` aggregation"
+            ));
+        }
+        Ok(())
+    };
+    let element_aggregation = match aggregation_type.as_str() {
+        "terms" => {
+            check_params(&["field", "size"])?;
+            let size = match params.get("size") {
+                Some(size) => size.as_u64().ok_or_else(|| {
+                    format!("`size` of aggregation `{aggregation_name}` must be a positive integer")
+                })? as usize,
+                None => DEFAULT_TERMS_SIZE,
+            };
+            ElementAggregation::Terms {
+                field: field()?,
+                size,
+                aggs: parse_element_aggregations(path, sub_aggregations_opt)?,
+            }
+        }
+        "value_count" | "min" | "max" | "sum" | "avg" | "stats" => {
+            check_params(&["field"])?;
+            let field = field()?;
+            match aggregation_type.as_str() {
+                "value_count" => ElementAggregation::ValueCount { field },
+                "min" => ElementAggregation::Min { field },
+                "max" => ElementAggregation::Max { field },
+                "sum" => ElementAggregation::Sum { field },
+                "avg" => ElementAggregation::Avg { field },
+                _ => ElementAggregation::Stats { field },
+            }
+        }
+        "reverse_nested" => {
+            check_params(&[])?;
+            ElementAggregation::ReverseNested
+        }
+        _ => {
+            return Err(format!(
+                "`{aggregation_type}` aggregation `{aggregation_name}` is not supported within a \
+                 `nested` aggregation"
+            ));
+        }
+    };
+    Ok(element_aggregation)
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_search::{NestedAggregations, QuickwitAggregations};
+
+    use super::*;
+
+    fn build_aggregation_request_from_json(aggregations: JsonValue) -> Result<String, String> {
+        let JsonValue::Object(aggregations) = aggregations else {
+            panic!();
+        };
+        build_aggregation_request(&aggregations)
+    }
+
+    #[test]
+    fn test_build_aggregation_request_without_nested_aggregations() {
+        let aggregations = json!({
+            "by_sku": { "terms": { "field": "items.sku" } },
+            "count": { "value_count": { "field": "timestamp" } }
+        });
+        let aggregation_request =
+            build_aggregation_request_from_json(aggregations.clone()).unwrap();
+        let aggregation_request_json: JsonValue =
+            serde_json::from_str(&aggregation_request).unwrap();
+        assert_eq!(aggregation_request_json, aggregations);
+    }
+
+    #[test]
+    fn test_build_aggregation_request_with_nested_aggregations() {
+        let aggregations = json!({
+            "items": {
+                "nested": { "path": "items" },
+                "aggs": {
+                    "by_sku": {
+                        "terms": { "field": "items.sku", "size": 5 },
+                        "aggs": {
+                            "quantity": { "sum": { "field": "items.quantity" } },
+                            "orders": { "reverse_nested": {} }
+                        }
+                    },
+                    "max_price": { "max": { "field": "items.price" } }
+                }
+            },
+            "count": { "value_count": { "field": "timestamp" } }
+        });
+        let aggregation_request = build_aggregation_request_from_json(aggregations).unwrap();
+        let QuickwitAggregations::NestedAggregations(NestedAggregations {
+            nested_aggregations,
+            aggregations,
+        }) = serde_json::from_str(&aggregation_request).unwrap()
+        else {
+            panic!("expected nested aggregations");
+        };
+        assert_eq!(aggregations.len(), 1);
+        assert!(aggregations.contains_key("count"));
+
+        let expected_nested_aggregation = NestedAggregation {
+            path: "items".to_string(),
+            aggs: BTreeMap::from([
+                (
+                    "by_sku".to_string(),
+                    ElementAggregation::Terms {
+                        field: "items.sku".to_string(),
+                        size: 5,
+                        aggs: BTreeMap::from([
+                            (
+                                "quantity".to_string(),
+                                ElementAggregation::Sum {
+                                    field: "items.quantity".to_string(),
+                                },
+                            ),
+                            ("orders".to_string(), ElementAggregation::ReverseNested),
+                        ]),
+                    },
+                ),
+                (
+                    "max_price".to_string(),
+                    ElementAggregation::Max {
+                        field: "items.price".to_string(),
+                    },
+                ),
+            ]),
+        };
+        assert_eq!(nested_aggregations.len(), 1);
+        assert_eq!(nested_aggregations["items"], expected_nested_aggregation);
+    }
+
+    #[test]
+    fn test_build_aggregation_request_rejects_unsupported_nested_aggregations() {
+        let error = build_aggregation_request_from_json(json!({
+            "by_day": {
+                "date_histogram": { "field": "timestamp", "fixed_interval": "1d" },
+                "aggs": {
+                    "items": {
+                        "nested": { "path": "items" },
+                        "aggs": { "by_sku": { "terms": { "field": "items.sku" } } }
+                    }
+                }
+            }
+        }))
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "`nested` aggregations are only supported at the top level of the request \
+             (aggregation `items`)"
+        );
+
+        let error = build_aggregation_request_from_json(json!({
+            "items": {
+                "nested": { "path": "items" },
+                "aggs": {
+                    "by_sku": {
+                        "terms": { "field": "items.sku" },
+                        "aggs": {
+                            "parts": {
+                                "nested": { "path": "items.parts" }
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "`nested` aggregation `parts` cannot be nested in another `nested` aggregation"
+        );
+
+        let error = build_aggregation_request_from_json(json!({
+            "items": {
+                "nested": { "path": "items" },
+                "aggs": {
+                    "by_day": {
+                        "date_histogram": { "field": "items.date", "fixed_interval": "1d" }
+                    }
+                }
+            }
+        }))
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "`date_histogram` aggregation `by_day` is not supported within a `nested` aggregation"
+        );
+
+        let error = build_aggregation_request_from_json(json!({
+            "items": {
+                "nested": { "path": "items" },
+                "aggs": { "max_price": { "max": { "field": "price" } } }
+            }
+        }))
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "field `price` of aggregation `max_price` does not belong to nested field `items`"
+        );
+
+        let error = build_aggregation_request_from_json(json!({
+            "orders": { "reverse_nested": {} }
+        }))
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "`reverse_nested` aggregations are only supported within a `nested` aggregation \
+             (aggregation `orders`)"
+        );
+    }
+}
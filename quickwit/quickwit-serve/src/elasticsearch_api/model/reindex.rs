@@ -0,0 +1,127 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use elasticsearch_dsl::search::ErrorCause;
+use hyper::StatusCode;
+use quickwit_query::ElasticQueryDsl;
+use serde::{Deserialize, Serialize};
+
+use super::ElasticsearchError;
+
+fn default_reindex_batch_size() -> u64 {
+    1_000
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ReindexQueryParams {
+    /// Maximum number of documents copied per second. Reindexing is not throttled when unset or
+    /// set to `-1`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requests_per_second: Option<f32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReindexRequestBody {
+    pub source: ReindexSource,
+    pub dest: ReindexDest,
+    /// Transform applied to the documents before they are ingested into the destination index.
+    #[serde(default)]
+    pub script: Option<ReindexScript>,
+    /// Maximum number of documents to copy.
+    #[serde(default)]
+    pub max_docs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReindexSource {
+    pub index: String,
+    #[serde(default)]
+    pub query: Option<ElasticQueryDsl>,
+    /// Number of documents fetched and ingested per batch.
+    #[serde(default = "default_reindex_batch_size")]
+    pub size: u64,
+    /// Resumes the reindex after the document designated by this cursor, as returned by a
+    /// previous reindex request. This is an extension proper to Quickwit.
+    #[serde(default)]
+    pub search_after: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReindexDest {
+    pub index: String,
+}
+
+/// Unlike Elasticsearch, which runs painless scripts, Quickwit transforms documents with VRL
+/// scripts.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReindexScript {
+    pub source: String,
+    #[serde(default)]
+    pub lang: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ReindexResponse {
+    #[serde(rename = "took")]
+    pub took_millis: u64,
+    pub timed_out: bool,
+    /// Number of documents read from the source index.
+    pub total: u64,
+    /// Number of documents successfully ingested into the destination index.
+    pub created: u64,
+    pub batches: u64,
+    pub retries: ReindexRetries,
+    pub throttled_millis: u64,
+    pub requests_per_second: f32,
+    pub failures: Vec<ReindexFailure>,
+    /// Cursor to pass as `source.search_after` to resume the reindex, set when documents matching
+    /// the query may remain to be copied. This is an extension proper to Quickwit.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search_after: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ReindexRetries {
+    pub bulk: u64,
+    pub search: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReindexFailure {
+    pub index: String,
+    #[serde(with = "http_serde::status_code")]
+    pub status: StatusCode,
+    pub cause: ErrorCause,
+}
+
+impl ReindexFailure {
+    pub fn new(index: String, error: ElasticsearchError) -> Self {
+        Self {
+            index,
+            status: error.status,
+            cause: error.error,
+        }
+    }
+}
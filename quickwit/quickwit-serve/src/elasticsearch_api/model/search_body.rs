@@ -86,6 +86,9 @@ pub struct SearchBody {
     pub stored_fields: Option<BTreeSet<String>>,
     #[serde(default)]
     pub search_after: Vec<serde_json::Value>,
+    /// If set, the response contains the profile of the search.
+    #[serde(default)]
+    pub profile: Option<bool>,
 
     // Ignored values, only here for compatibility with OpenSearch Dashboards.
     #[serde(default)]
@@ -0,0 +1,64 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use elasticsearch_dsl::search::SearchResponse as ElasticsearchResponse;
+use quickwit_proto::search::SplitSearchProfile;
+use serde::Serialize;
+
+/// Response of the `_search` endpoints: the Elasticsearch search response, extended with the
+/// profile of the search if it was requested.
+#[derive(Serialize, Debug)]
+pub struct ElasticsearchSearchResponse {
+    #[serde(flatten)]
+    pub response: ElasticsearchResponse,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<ElasticsearchProfile>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ElasticsearchProfile {
+    /// Quickwit has no concept of shards: like in `_shards`, each entry describes a split.
+    pub shards: Vec<ElasticsearchShardProfile>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ElasticsearchShardProfile {
+    /// Identifies the split in the `[<leaf address>][<split ID>]` format, the Quickwit counterpart
+    /// of the `[<node ID>][<index>][<shard>]` format used by Elasticsearch.
+    pub id: String,
+    #[serde(flatten)]
+    pub split_profile: SplitSearchProfile,
+}
+
+impl ElasticsearchProfile {
+    /// Returns `None` if the search was not profiled.
+    pub fn from_split_profiles(split_profiles: Vec<SplitSearchProfile>) -> Option<Self> {
+        if split_profiles.is_empty() {
+            return None;
+        }
+        let shards = split_profiles
+            .into_iter()
+            .map(|split_profile| ElasticsearchShardProfile {
+                id: format!("[{}][{}]", split_profile.leaf_addr, split_profile.split_id),
+                split_profile,
+            })
+            .collect();
+        Some(ElasticsearchProfile { shards })
+    }
+}
@@ -0,0 +1,737 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
+use hyper::StatusCode;
+use quickwit_config::{TransformConfig, INGEST_V2_SOURCE_ID};
+use quickwit_doc_mapper::{JsonObject, SOURCE_FIELD_NAME};
+use quickwit_indexing::JsonDocTransform;
+use quickwit_ingest::JsonDocBatchV2Builder;
+use quickwit_proto::ingest::router::{
+    IngestFailure, IngestFailureReason, IngestRequestV2, IngestRouterService,
+    IngestRouterServiceClient, IngestSubrequest,
+};
+use quickwit_proto::ingest::{CommitTypeV2, DocBatchV2};
+use quickwit_proto::metastore::MetastoreServiceClient;
+use quickwit_proto::search::{CountHits, PartialHit, SearchRequest, SortField, SortOrder};
+use quickwit_proto::types::{DocUidGenerator, SplitId};
+use quickwit_query::query_ast::QueryAst;
+use quickwit_search::{
+    list_all_splits, resolve_index_patterns, GlobalDocAddress, SearchError, SearchService,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use warp::{Filter, Rejection};
+
+use super::filter::elastic_reindex_filter;
+use super::make_elastic_api_response;
+use super::model::{
+    ElasticException, ElasticsearchError, ReindexFailure, ReindexQueryParams, ReindexRequestBody,
+    ReindexResponse,
+};
+use crate::format::BodyFormat;
+use crate::rest::recover_fn;
+use crate::with_arg;
+
+const MAX_INGEST_ATTEMPTS: u64 = 5;
+
+const INGEST_RETRY_DELAY: Duration = if cfg!(test) {
+    Duration::from_millis(10)
+} else {
+    Duration::from_millis(500)
+};
+
+/// POST _elastic/_reindex
+pub fn es_compat_reindex_handler(
+    search_service: Arc<dyn SearchService>,
+    ingest_router: IngestRouterServiceClient,
+    metastore: MetastoreServiceClient,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    elastic_reindex_filter()
+        .and(with_arg(search_service))
+        .and(with_arg(ingest_router))
+        .and(with_arg(metastore))
+        .then(es_compat_reindex)
+        .map(|result| make_elastic_api_response(result, BodyFormat::default()))
+        .recover(recover_fn)
+        .boxed()
+}
+
+/// Position of a reindex in the splits of the source index, returned to the client as an opaque
+/// `search_after` cursor.
+///
+/// The splits of the source index are pinned when the reindex starts, so that the merges happening
+/// in the meantime do not change the set of documents the reindex iterates over. Addresses of
+/// documents are stable within a split, so the documents of the pinned splits are read in the
+/// order of their addresses, split after split.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ReindexCursor {
+    /// IDs of the pinned splits, in ascending order, that may still contain documents to copy.
+    split_ids: Vec<SplitId>,
+    /// Address of the last copied document.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_doc_address: Option<String>,
+}
+
+impl ReindexCursor {
+    fn encode(&self) -> String {
+        let cursor_json = serde_json::to_vec(self).expect("cursor should be JSON serializable");
+        BASE64_STANDARD.encode(cursor_json)
+    }
+
+    fn decode(cursor_str: &str) -> Option<(ReindexCursor, Option<GlobalDocAddress>)> {
+        let cursor_json = BASE64_STANDARD.decode(cursor_str).ok()?;
+        let cursor: ReindexCursor = serde_json::from_slice(&cursor_json).ok()?;
+        let last_doc_address_opt = match cursor.last_doc_address.as_deref() {
+            Some(last_doc_address) => Some(last_doc_address.parse().ok()?),
+            None => None,
+        };
+        Some((cursor, last_doc_address_opt))
+    }
+}
+
+/// Lists the IDs of the published splits of the indexes matching the index ID pattern, in
+/// ascending order.
+async fn pin_splits(
+    index_id_pattern: &str,
+    metastore: &mut MetastoreServiceClient,
+) -> Result<Vec<SplitId>, SearchError> {
+    let index_uids = resolve_index_patterns(&[index_id_pattern.to_string()], metastore)
+        .await?
+        .into_iter()
+        .map(|index_metadata| index_metadata.index_uid)
+        .collect();
+    let mut split_ids: Vec<SplitId> = list_all_splits(index_uids, metastore)
+        .await?
+        .into_iter()
+        .map(|split_metadata| split_metadata.split_id)
+        .collect();
+    split_ids.sort_unstable();
+    Ok(split_ids)
+}
+
+/// Copies the documents matching a query from a source index into a destination index.
+///
+/// Documents are read in batches from the splits pinned at the start of the reindex, sorted by
+/// address, and resumed with `search_after`. When the reindex stops before all the documents are
+/// copied, because of a failure or because `max_docs` is reached, the response returns a cursor
+/// carrying the pinned splits and the address of the last copied document, which can be passed to
+/// a subsequent request to resume the reindex.
+async fn es_compat_reindex(
+    reindex_query_params: ReindexQueryParams,
+    reindex_body: ReindexRequestBody,
+    search_service: Arc<dyn SearchService>,
+    ingest_router: IngestRouterServiceClient,
+    mut metastore: MetastoreServiceClient,
+) -> Result<ReindexResponse, ElasticsearchError> {
+    let start_instant = Instant::now();
+    let ReindexRequestBody {
+        source,
+        dest,
+        script,
+        max_docs,
+    } = reindex_body;
+
+    if source.size == 0 {
+        return Err(ElasticsearchError::new(
+            StatusCode::BAD_REQUEST,
+            "[size] must be greater than 0".to_string(),
+            Some(ElasticException::ActionRequestValidation),
+        ));
+    }
+    let mut json_doc_transform_opt = script
+        .map(|script| {
+            if let Some(lang) = script.lang.as_deref().filter(|lang| *lang != "vrl") {
+                return Err(ElasticsearchError::new(
+                    StatusCode::BAD_REQUEST,
+                    format!("script language `{lang}` is not supported: only `vrl` is supported"),
+                    Some(ElasticException::IllegalArgument),
+                ));
+            }
+            let transform_config = TransformConfig::new(script.source, None);
+            JsonDocTransform::try_from_transform_config(transform_config).map_err(|error| {
+                ElasticsearchError::new(
+                    StatusCode::BAD_REQUEST,
+                    format!("failed to compile script: {error}"),
+                    Some(ElasticException::IllegalArgument),
+                )
+            })
+        })
+        .transpose()?;
+    let query_ast = if let Some(query_dsl) = source.query {
+        query_dsl
+            .try_into()
+            .map_err(|err: anyhow::Error| SearchError::InvalidQuery(err.to_string()))?
+    } else {
+        QueryAst::MatchAll
+    };
+    let query_ast_json = serde_json::to_string(&query_ast).expect("Failed to serialize QueryAst");
+    let (mut split_ids, mut search_after_opt) = if let Some(search_after) = source.search_after {
+        let (cursor, search_after_opt) = ReindexCursor::decode(&search_after).ok_or_else(|| {
+            ElasticsearchError::new(
+                StatusCode::BAD_REQUEST,
+                format!("invalid [search_after] cursor `{search_after}`"),
+                Some(ElasticException::IllegalArgument),
+            )
+        })?;
+        (cursor.split_ids, search_after_opt)
+    } else {
+        (pin_splits(&source.index, &mut metastore).await?, None)
+    };
+    let requests_per_second_opt = reindex_query_params
+        .requests_per_second
+        .filter(|requests_per_second| *requests_per_second > 0.0);
+
+    let mut reindex_response = ReindexResponse {
+        requests_per_second: requests_per_second_opt.unwrap_or(-1.0),
+        ..Default::default()
+    };
+    let mut doc_uid_generator = DocUidGenerator::default();
+
+    loop {
+        let num_docs_to_read = if let Some(max_docs) = max_docs {
+            source
+                .size
+                .min(max_docs.saturating_sub(reindex_response.total))
+        } else {
+            source.size
+        };
+        if num_docs_to_read == 0 || split_ids.is_empty() {
+            break;
+        }
+        let batch_start_instant = Instant::now();
+        let search_request = SearchRequest {
+            index_id_patterns: vec![source.index.clone()],
+            query_ast: query_ast_json.clone(),
+            max_hits: num_docs_to_read,
+            sort_fields: vec![SortField {
+                field_name: "_doc".to_string(),
+                sort_order: SortOrder::Asc as i32,
+                sort_datetime_format: None,
+            }],
+            search_after: search_after_opt.as_ref().map(|doc_address| PartialHit {
+                split_id: doc_address.split.clone(),
+                segment_ord: doc_address.doc_addr.segment_ord,
+                doc_id: doc_address.doc_addr.doc_id,
+                ..Default::default()
+            }),
+            count_hits: CountHits::Underestimate as i32,
+            split_ids: split_ids.clone(),
+            ..Default::default()
+        };
+        let search_response = match search_service.root_search(search_request).await {
+            Ok(search_response) => search_response,
+            Err(search_error) => {
+                let failure = ReindexFailure::new(source.index.clone(), search_error.into());
+                reindex_response.failures.push(failure);
+                break;
+            }
+        };
+        // Moving on past the splits that failed to be searched would skip their documents.
+        if let Some(search_error) = SearchError::from_split_errors(&search_response.failed_splits) {
+            let failure = ReindexFailure::new(source.index.clone(), search_error.into());
+            reindex_response.failures.push(failure);
+            break;
+        }
+        let num_hits = search_response.hits.len() as u64;
+
+        let Some(last_partial_hit) = search_response
+            .hits
+            .last()
+            .and_then(|hit| hit.partial_hit.as_ref())
+        else {
+            split_ids.clear();
+            break;
+        };
+        let last_doc_address = GlobalDocAddress::from_partial_hit(last_partial_hit);
+
+        let mut doc_batch_builder = JsonDocBatchV2Builder::default();
+        let mut num_docs_in_batch = 0;
+
+        for hit in &search_response.hits {
+            match reindex_doc(&hit.json, json_doc_transform_opt.as_mut()) {
+                Ok(json_doc) => {
+                    doc_batch_builder
+                        .add_doc(doc_uid_generator.next_doc_uid(), json_doc)
+                        .expect("JSON object should be serializable");
+                    num_docs_in_batch += 1;
+                }
+                Err(error) => {
+                    let elasticsearch_error = ElasticsearchError::new(
+                        StatusCode::BAD_REQUEST,
+                        format!("failed to reindex document: {error}"),
+                        Some(ElasticException::DocumentParsing),
+                    );
+                    let failure = ReindexFailure::new(dest.index.clone(), elasticsearch_error);
+                    reindex_response.failures.push(failure);
+                }
+            }
+        }
+        if num_docs_in_batch > 0 {
+            let doc_batch = doc_batch_builder.build();
+
+            if let Err(elasticsearch_error) = ingest_doc_batch(
+                &dest.index,
+                doc_batch,
+                &ingest_router,
+                &mut reindex_response,
+            )
+            .await
+            {
+                let failure = ReindexFailure::new(dest.index.clone(), elasticsearch_error);
+                reindex_response.failures.push(failure);
+                break;
+            }
+            reindex_response.created += num_docs_in_batch;
+        }
+        reindex_response.total += num_hits;
+        reindex_response.batches += 1;
+
+        // The documents of the splits preceding the split of the last copied document are all
+        // copied.
+        split_ids.retain(|split_id| *split_id >= last_doc_address.split);
+        search_after_opt = Some(last_doc_address);
+
+        // Like Elasticsearch, the reindex is aborted on the first failure.
+        if !reindex_response.failures.is_empty() {
+            break;
+        }
+        if num_hits < num_docs_to_read {
+            split_ids.clear();
+            break;
+        }
+        if let Some(requests_per_second) = requests_per_second_opt {
+            let batch_target_duration =
+                Duration::from_secs_f32(num_hits as f32 / requests_per_second);
+
+            if let Some(throttle_duration) =
+                batch_target_duration.checked_sub(batch_start_instant.elapsed())
+            {
+                tokio::time::sleep(throttle_duration).await;
+                reindex_response.throttled_millis += throttle_duration.as_millis() as u64;
+            }
+        }
+    }
+    if !split_ids.is_empty() {
+        let cursor = ReindexCursor {
+            split_ids,
+            last_doc_address: search_after_opt.map(|doc_address| doc_address.to_string()),
+        };
+        reindex_response.search_after = Some(cursor.encode());
+    }
+    reindex_response.took_millis = start_instant.elapsed().as_millis() as u64;
+    Ok(reindex_response)
+}
+
+/// Rebuilds the document to ingest into the destination index from a search hit, preferring the
+/// original document stored in `_source` when the source index stores it.
+fn reindex_doc(
+    hit_json: &str,
+    json_doc_transform_opt: Option<&mut JsonDocTransform>,
+) -> anyhow::Result<JsonObject> {
+    let mut json_doc: JsonObject = serde_json::from_str(hit_json)?;
+
+    if let Some(JsonValue::Object(source_json_doc)) = json_doc.remove(SOURCE_FIELD_NAME) {
+        json_doc = source_json_doc;
+    }
+    if let Some(json_doc_transform) = json_doc_transform_opt {
+        json_doc = json_doc_transform.transform(json_doc)?;
+    }
+    Ok(json_doc)
+}
+
+/// Ingests a batch of documents into the destination index, retrying on transient failures.
+async fn ingest_doc_batch(
+    index_id: &str,
+    doc_batch: DocBatchV2,
+    ingest_router: &IngestRouterServiceClient,
+    reindex_response: &mut ReindexResponse,
+) -> Result<(), ElasticsearchError> {
+    let mut num_attempts = 0;
+
+    loop {
+        num_attempts += 1;
+
+        let subrequest = IngestSubrequest {
+            subrequest_id: 0,
+            index_id: index_id.to_string(),
+            source_id: INGEST_V2_SOURCE_ID.to_string(),
+            doc_batch: Some(doc_batch.clone()),
+        };
+        let ingest_request = IngestRequestV2 {
+            commit_type: CommitTypeV2::Auto as i32,
+            subrequests: vec![subrequest],
+        };
+        let ingest_response = ingest_router.ingest(ingest_request).await?;
+        let Some(failure) = ingest_response.failures.into_iter().next() else {
+            return Ok(());
+        };
+        if !is_transient_failure(&failure) || num_attempts >= MAX_INGEST_ATTEMPTS {
+            return Err(ingest_failure_to_elasticsearch_error(&failure));
+        }
+        reindex_response.retries.bulk += 1;
+        tokio::time::sleep(INGEST_RETRY_DELAY * num_attempts as u32).await;
+    }
+}
+
+fn is_transient_failure(failure: &IngestFailure) -> bool {
+    matches!(
+        failure.reason(),
+        IngestFailureReason::NoShardsAvailable
+            | IngestFailureReason::ShardRateLimited
+            | IngestFailureReason::WalFull
+            | IngestFailureReason::Timeout
+            | IngestFailureReason::RouterLoadShedding
+            | IngestFailureReason::LoadShedding
+    )
+}
+
+fn ingest_failure_to_elasticsearch_error(failure: &IngestFailure) -> ElasticsearchError {
+    let (exception, reason, status) = match failure.reason() {
+        IngestFailureReason::IndexNotFound => (
+            ElasticException::IndexNotFound,
+            format!("no such index [{}]", failure.index_id),
+            StatusCode::NOT_FOUND,
+        ),
+        IngestFailureReason::SourceNotFound => (
+            ElasticException::SourceNotFound,
+            format!("no such source [{}]", failure.index_id),
+            StatusCode::NOT_FOUND,
+        ),
+        IngestFailureReason::Timeout => (
+            ElasticException::Timeout,
+            format!("timeout [{}]", failure.index_id),
+            StatusCode::REQUEST_TIMEOUT,
+        ),
+        IngestFailureReason::ShardRateLimited => (
+            ElasticException::RateLimited,
+            format!("shard rate limiting [{}]", failure.index_id),
+            StatusCode::TOO_MANY_REQUESTS,
+        ),
+        IngestFailureReason::QuotaExceeded => (
+            ElasticException::RateLimited,
+            format!("quota exceeded [{}]", failure.index_id),
+            StatusCode::TOO_MANY_REQUESTS,
+        ),
+        reason => {
+            let pretty_reason = reason
+                .as_str_name()
+                .strip_prefix("INGEST_FAILURE_REASON_")
+                .unwrap_or("")
+                .replace('_', " ")
+                .to_ascii_lowercase();
+            (
+                ElasticException::Internal,
+                format!("{} error [{}]", pretty_reason, failure.index_id),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            )
+        }
+    };
+    ElasticsearchError::new(status, reason, Some(exception))
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_common::ServiceStream;
+    use quickwit_metastore::{
+        IndexMetadata, ListIndexesMetadataResponseExt, ListSplitsResponseExt, Split, SplitMetadata,
+        SplitState,
+    };
+    use quickwit_proto::ingest::router::{
+        IngestResponseV2, IngestSuccess, MockIngestRouterService,
+    };
+    use quickwit_proto::metastore::{
+        ListIndexesMetadataResponse, ListSplitsResponse, MockMetastoreService,
+    };
+    use quickwit_proto::search::{Hit, SearchResponse};
+    use quickwit_search::MockSearchService;
+
+    use super::*;
+
+    fn mock_metastore(split_ids: &[&str]) -> MetastoreServiceClient {
+        let index_metadata = IndexMetadata::for_test("source-index", "ram:///indexes/source-index");
+        let splits: Vec<Split> = split_ids
+            .iter()
+            .map(|split_id| Split {
+                split_metadata: SplitMetadata {
+                    split_id: split_id.to_string(),
+                    index_uid: index_metadata.index_uid.clone(),
+                    ..Default::default()
+                },
+                split_state: SplitState::Published,
+                update_timestamp: 0,
+                publish_timestamp: None,
+            })
+            .collect();
+        let mut mock_metastore = MockMetastoreService::new();
+        mock_metastore
+            .expect_list_indexes_metadata()
+            .returning(move |_| {
+                Ok(ListIndexesMetadataResponse::for_test(vec![
+                    index_metadata.clone()
+                ]))
+            });
+        mock_metastore.expect_list_splits().returning(move |_| {
+            let splits_response = ListSplitsResponse::try_from_splits(splits.clone()).unwrap();
+            Ok(ServiceStream::from(vec![Ok(splits_response)]))
+        });
+        MetastoreServiceClient::from_mock(mock_metastore)
+    }
+
+    fn mock_hit(split_id: &str, doc_id: u32, json: &str) -> Hit {
+        Hit {
+            json: json.to_string(),
+            partial_hit: Some(PartialHit {
+                split_id: split_id.to_string(),
+                segment_ord: 0,
+                doc_id,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn ingest_success_response() -> IngestResponseV2 {
+        IngestResponseV2 {
+            successes: vec![IngestSuccess {
+                subrequest_id: 0,
+                ..Default::default()
+            }],
+            failures: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reindex_api() {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .times(2)
+            .returning(|search_request| {
+                assert_eq!(search_request.index_id_patterns, vec!["source-index"]);
+                assert_eq!(search_request.max_hits, 2);
+                assert_eq!(search_request.sort_fields[0].field_name, "_doc");
+                assert_eq!(search_request.split_ids, ["split-1", "split-2"]);
+
+                let hits = if let Some(search_after) = search_request.search_after {
+                    assert_eq!(search_after.split_id, "split-1");
+                    assert_eq!(search_after.doc_id, 1);
+                    vec![mock_hit("split-2", 0, r#"{"_source": {"body": "baz"}}"#)]
+                } else {
+                    vec![
+                        mock_hit("split-1", 0, r#"{"body": "foo"}"#),
+                        mock_hit("split-1", 1, r#"{"body": "bar"}"#),
+                    ]
+                };
+                Ok(SearchResponse {
+                    num_hits: hits.len() as u64,
+                    hits,
+                    ..Default::default()
+                })
+            });
+        let mut mock_ingest_router = MockIngestRouterService::new();
+        mock_ingest_router
+            .expect_ingest()
+            .times(2)
+            .returning(|ingest_request| {
+                let subrequest = &ingest_request.subrequests[0];
+                assert_eq!(subrequest.index_id, "dest-index");
+                assert_eq!(subrequest.source_id, INGEST_V2_SOURCE_ID);
+
+                let doc_batch = subrequest.doc_batch.as_ref().unwrap();
+                let docs: Vec<String> = doc_batch
+                    .docs()
+                    .map(|(_doc_uid, doc)| String::from_utf8(doc.to_vec()).unwrap())
+                    .collect();
+                assert!(
+                    docs == [r#"{"body":"foo"}"#, r#"{"body":"bar"}"#]
+                        || docs == [r#"{"body":"baz"}"#]
+                );
+                Ok(ingest_success_response())
+            });
+        let handler = es_compat_reindex_handler(
+            Arc::new(mock_search_service),
+            IngestRouterServiceClient::from_mock(mock_ingest_router),
+            mock_metastore(&["split-2", "split-1"]),
+        );
+        let resp = warp::test::request()
+            .path("/_elastic/_reindex")
+            .method("POST")
+            .json(&serde_json::json!({
+                "source": {"index": "source-index", "size": 2},
+                "dest": {"index": "dest-index"},
+            }))
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let reindex_response: ReindexResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(reindex_response.total, 3);
+        assert_eq!(reindex_response.created, 3);
+        assert_eq!(reindex_response.batches, 2);
+        assert_eq!(reindex_response.requests_per_second, -1.0);
+        assert!(reindex_response.failures.is_empty());
+        assert!(reindex_response.search_after.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reindex_api_max_docs_and_resume() {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .once()
+            .returning(|search_request| {
+                assert_eq!(search_request.max_hits, 1);
+
+                let search_after = search_request.search_after.unwrap();
+                assert_eq!(search_after.split_id, "split-1");
+                assert_eq!(search_after.segment_ord, 0);
+                assert_eq!(search_after.doc_id, 3);
+                assert_eq!(search_request.split_ids, ["split-1", "split-2"]);
+
+                Ok(SearchResponse {
+                    num_hits: 1,
+                    hits: vec![mock_hit("split-2", 4, r#"{"body": "foo"}"#)],
+                    ..Default::default()
+                })
+            });
+        let mut mock_ingest_router = MockIngestRouterService::new();
+        mock_ingest_router
+            .expect_ingest()
+            .once()
+            .returning(|_| Ok(ingest_success_response()));
+        // The splits are pinned by the cursor, so the metastore is not queried.
+        let handler = es_compat_reindex_handler(
+            Arc::new(mock_search_service),
+            IngestRouterServiceClient::from_mock(mock_ingest_router),
+            MetastoreServiceClient::from_mock(MockMetastoreService::new()),
+        );
+        let cursor = ReindexCursor {
+            split_ids: vec!["split-1".to_string(), "split-2".to_string()],
+            last_doc_address: Some("split-1:00000000:00000003".to_string()),
+        };
+        let resp = warp::test::request()
+            .path("/_elastic/_reindex")
+            .method("POST")
+            .json(&serde_json::json!({
+                "source": {
+                    "index": "source-index",
+                    "search_after": cursor.encode(),
+                },
+                "dest": {"index": "dest-index"},
+                "max_docs": 1,
+            }))
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let reindex_response: ReindexResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(reindex_response.created, 1);
+
+        let (cursor, last_doc_address_opt) =
+            ReindexCursor::decode(&reindex_response.search_after.unwrap()).unwrap();
+        // The documents of the first split are all copied.
+        assert_eq!(cursor.split_ids, ["split-2"]);
+        assert_eq!(
+            last_doc_address_opt.unwrap().to_string(),
+            "split-2:00000000:00000004"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reindex_api_ingest_failure() {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .once()
+            .returning(|_| {
+                Ok(SearchResponse {
+                    num_hits: 1,
+                    hits: vec![mock_hit("split-1", 0, r#"{"body": "foo"}"#)],
+                    ..Default::default()
+                })
+            });
+        let mut mock_ingest_router = MockIngestRouterService::new();
+        mock_ingest_router
+            .expect_ingest()
+            .times(MAX_INGEST_ATTEMPTS as usize)
+            .returning(|_| {
+                Ok(IngestResponseV2 {
+                    successes: Vec::new(),
+                    failures: vec![IngestFailure {
+                        subrequest_id: 0,
+                        index_id: "dest-index".to_string(),
+                        source_id: INGEST_V2_SOURCE_ID.to_string(),
+                        reason: IngestFailureReason::ShardRateLimited as i32,
+                    }],
+                })
+            });
+        let handler = es_compat_reindex_handler(
+            Arc::new(mock_search_service),
+            IngestRouterServiceClient::from_mock(mock_ingest_router),
+            mock_metastore(&["split-2", "split-1"]),
+        );
+        let resp = warp::test::request()
+            .path("/_elastic/_reindex")
+            .method("POST")
+            .json(&serde_json::json!({
+                "source": {"index": "source-index"},
+                "dest": {"index": "dest-index"},
+            }))
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let reindex_response: ReindexResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(reindex_response.created, 0);
+        assert_eq!(reindex_response.retries.bulk, MAX_INGEST_ATTEMPTS - 1);
+        assert_eq!(reindex_response.failures.len(), 1);
+        assert_eq!(
+            reindex_response.failures[0].status,
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        // The reindex can be resumed from the start with the pinned splits.
+        let (cursor, last_doc_address_opt) =
+            ReindexCursor::decode(&reindex_response.search_after.unwrap()).unwrap();
+        assert_eq!(cursor.split_ids, ["split-1", "split-2"]);
+        assert!(last_doc_address_opt.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reindex_api_rejects_non_vrl_script() {
+        let handler = es_compat_reindex_handler(
+            Arc::new(MockSearchService::new()),
+            IngestRouterServiceClient::from_mock(MockIngestRouterService::new()),
+            MetastoreServiceClient::from_mock(MockMetastoreService::new()),
+        );
+        let resp = warp::test::request()
+            .path("/_elastic/_reindex")
+            .method("POST")
+            .json(&serde_json::json!({
+                "source": {"index": "source-index"},
+                "dest": {"index": "dest-index"},
+                "script": {"source": "ctx._source.foo = 1", "lang": "painless"},
+            }))
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+    }
+}
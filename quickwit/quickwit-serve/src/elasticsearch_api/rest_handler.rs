@@ -29,10 +29,13 @@ use futures_util::StreamExt;
 use hyper::StatusCode;
 use itertools::Itertools;
 use quickwit_common::truncate_str;
-use quickwit_config::{validate_index_id_pattern, NodeConfig};
+use quickwit_config::{validate_index_id_pattern, IndexTemplate, NodeConfig};
 use quickwit_index_management::IndexService;
 use quickwit_metastore::*;
-use quickwit_proto::metastore::MetastoreServiceClient;
+use quickwit_proto::metastore::{
+    serde_utils, CreateIndexTemplateRequest, DeleteIndexTemplatesRequest, GetIndexTemplateRequest,
+    ListIndexTemplatesRequest, MetastoreService, MetastoreServiceClient,
+};
 use quickwit_proto::search::{
     CountHits, ListFieldsResponse, PartialHit, ScrollRequest, SearchResponse, SortByValue,
     SortDatetimeFormat,
@@ -47,20 +50,27 @@ use warp::{Filter, Rejection};
 
 use super::filter::{
     elastic_cat_indices_filter, elastic_cluster_info_filter, elastic_delete_index_filter,
-    elastic_field_capabilities_filter, elastic_index_cat_indices_filter,
+    elastic_delete_index_template_filter, elastic_field_capabilities_filter,
+    elastic_get_index_template_filter, elastic_index_cat_indices_filter,
     elastic_index_count_filter, elastic_index_field_capabilities_filter,
-    elastic_index_search_filter, elastic_index_stats_filter, elastic_multi_search_filter,
-    elastic_resolve_index_filter, elastic_scroll_filter, elastic_stats_filter,
-    elasticsearch_filter,
+    elastic_index_mapping_filter, elastic_index_search_filter, elastic_index_settings_filter,
+    elastic_index_stats_filter, elastic_list_index_templates_filter, elastic_multi_search_filter,
+    elastic_put_index_template_filter, elastic_resolve_index_filter, elastic_scroll_filter,
+    elastic_stats_filter, elasticsearch_filter,
 };
 use super::model::{
-    build_list_field_request_for_es_api, convert_to_es_field_capabilities_response,
-    CatIndexQueryParams, DeleteQueryParams, ElasticsearchCatIndexResponse, ElasticsearchError,
-    ElasticsearchResolveIndexEntryResponse, ElasticsearchResolveIndexResponse,
+    build_aggregation_request, build_list_field_request_for_es_api,
+    convert_to_es_field_capabilities_response, convert_to_es_mappings_response,
+    convert_to_es_settings_response, elastic_to_quickwit_index_template,
+    quickwit_to_elastic_index_template, CatIndexQueryParams, DeleteQueryParams, ElasticException,
+    ElasticIndexTemplate, ElasticIndexTemplateEntry, ElasticIndexTemplatesResponse,
+    ElasticsearchCatIndexResponse, ElasticsearchError, ElasticsearchMappingsResponse,
+    ElasticsearchProfile, ElasticsearchResolveIndexEntryResponse,
+    ElasticsearchResolveIndexResponse, ElasticsearchSearchResponse, ElasticsearchSettingsResponse,
     ElasticsearchStatsResponse, FieldCapabilityQueryParams, FieldCapabilityRequestBody,
     FieldCapabilityResponse, MultiSearchHeader, MultiSearchQueryParams, MultiSearchResponse,
-    MultiSearchSingleResponse, ScrollQueryParams, SearchBody, SearchQueryParams,
-    SearchQueryParamsCount, StatsResponseEntry,
+    MultiSearchSingleResponse, PutIndexTemplateQueryParams, ScrollQueryParams, SearchBody,
+    SearchQueryParams, SearchQueryParamsCount, StatsResponseEntry,
 };
 use super::{make_elastic_api_response, TrackTotalHits};
 use crate::format::BodyFormat;
@@ -163,6 +173,32 @@ pub fn es_compat_index_stats_handler(
         .boxed()
 }
 
+/// GET _elastic/{index}/_mapping
+pub fn es_compat_index_mapping_handler(
+    metastore_service: MetastoreServiceClient,
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    elastic_index_mapping_filter()
+        .and(with_arg(metastore_service))
+        .and(with_arg(search_service))
+        .then(es_compat_index_mapping)
+        .map(|result| make_elastic_api_response(result, BodyFormat::default()))
+        .recover(recover_fn)
+        .boxed()
+}
+
+/// GET _elastic/{index}/_settings
+pub fn es_compat_index_settings_handler(
+    metastore_service: MetastoreServiceClient,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    elastic_index_settings_filter()
+        .and(with_arg(metastore_service))
+        .then(es_compat_index_settings)
+        .map(|result| make_elastic_api_response(result, BodyFormat::default()))
+        .recover(recover_fn)
+        .boxed()
+}
+
 /// GET _elastic/_cat/indices
 pub fn es_compat_cat_indices_handler(
     metastore_service: MetastoreServiceClient,
@@ -198,11 +234,57 @@ pub fn es_compat_resolve_index_handler(
         .boxed()
 }
 
+/// PUT or POST _elastic/_index_template/{name}
+pub fn es_compat_put_index_template_handler(
+    metastore_service: MetastoreServiceClient,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    elastic_put_index_template_filter()
+        .and(with_arg(metastore_service))
+        .then(es_compat_put_index_template)
+        .map(|result| make_elastic_api_response(result, BodyFormat::default()))
+        .recover(recover_fn)
+        .boxed()
+}
+
+/// GET _elastic/_index_template/{name}
+pub fn es_compat_get_index_template_handler(
+    metastore_service: MetastoreServiceClient,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    elastic_get_index_template_filter()
+        .and(with_arg(metastore_service))
+        .then(es_compat_get_index_template)
+        .map(|result| make_elastic_api_response(result, BodyFormat::default()))
+        .boxed()
+}
+
+/// GET _elastic/_index_template
+pub fn es_compat_list_index_templates_handler(
+    metastore_service: MetastoreServiceClient,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    elastic_list_index_templates_filter()
+        .and(with_arg(metastore_service))
+        .then(es_compat_list_index_templates)
+        .map(|result| make_elastic_api_response(result, BodyFormat::default()))
+        .boxed()
+}
+
+/// DELETE _elastic/_index_template/{name}
+pub fn es_compat_delete_index_template_handler(
+    metastore_service: MetastoreServiceClient,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    elastic_delete_index_template_filter()
+        .and(with_arg(metastore_service))
+        .then(es_compat_delete_index_template)
+        .map(|result| make_elastic_api_response(result, BodyFormat::default()))
+        .boxed()
+}
+
 /// GET or POST _elastic/{index}/_search
 pub fn es_compat_index_search_handler(
     search_service: Arc<dyn SearchService>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
     elastic_index_search_filter()
+        .and(warp::header::optional::<String>("user-agent"))
         .and(with_arg(search_service))
         .then(es_compat_index_search)
         .map(|result| make_elastic_api_response(result, BodyFormat::default()))
@@ -227,6 +309,7 @@ pub fn es_compat_index_multi_search_handler(
     search_service: Arc<dyn SearchService>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
     elastic_multi_search_filter()
+        .and(warp::header::optional::<String>("user-agent"))
         .and(with_arg(search_service))
         .then(es_compat_index_multi_search)
         .map(|result: Result<MultiSearchResponse, ElasticsearchError>| {
@@ -302,7 +385,9 @@ fn build_request_for_es_api(
     let aggregation_request: Option<String> = if search_body.aggs.is_empty() {
         None
     } else {
-        serde_json::to_string(&search_body.aggs).ok()
+        let aggregation_request = build_aggregation_request(&search_body.aggs)
+            .map_err(|err| ElasticsearchError::from(SearchError::InvalidAggregationRequest(err)))?;
+        Some(aggregation_request)
     };
 
     let max_hits = search_params.size.or(search_body.size).unwrap_or(10);
@@ -344,6 +429,7 @@ fn build_request_for_es_api(
 
     let has_doc_id_field = sort_fields.iter().any(is_doc_field);
     let search_after = partial_hit_from_search_after_param(search_body.search_after, &sort_fields)?;
+    let profile = search_body.profile.unwrap_or(false);
 
     Ok((
         quickwit_proto::search::SearchRequest {
@@ -359,6 +445,11 @@ fn build_request_for_es_api(
             scroll_ttl_secs,
             search_after,
             count_hits,
+            real_time: false,
+            runtime_fields: None,
+            profile,
+            user_agent: None,
+            split_ids: Vec::new(),
         },
         has_doc_id_field,
     ))
@@ -451,8 +542,9 @@ async fn es_compat_index_search(
     index_id_patterns: Vec<String>,
     search_params: SearchQueryParams,
     search_body: SearchBody,
+    user_agent_opt: Option<String>,
     search_service: Arc<dyn SearchService>,
-) -> Result<ElasticsearchResponse, ElasticsearchError> {
+) -> Result<ElasticsearchSearchResponse, ElasticsearchError> {
     if search_params.scroll.is_some() && !search_params.allow_partial_search_results() {
         return Err(ElasticsearchError::from(SearchError::InvalidArgument(
             "Quickwit only supports scroll API with allow_partial_search_results set to true"
@@ -463,10 +555,14 @@ async fn es_compat_index_search(
     let _source_includes = search_params._source_includes.clone();
     let start_instant = Instant::now();
     let allow_partial_search_results = search_params.allow_partial_search_results();
-    let (search_request, append_shard_doc) =
+    let (mut search_request, append_shard_doc) =
         build_request_for_es_api(index_id_patterns, search_params, search_body)?;
-    let search_response: SearchResponse = search_service.root_search(search_request).await?;
+    search_request.user_agent = user_agent_opt;
+    let mut search_response: SearchResponse = search_service.root_search(search_request).await?;
     let elapsed = start_instant.elapsed();
+    let profile_opt = ElasticsearchProfile::from_split_profiles(std::mem::take(
+        &mut search_response.split_profiles,
+    ));
     let mut search_response_rest: ElasticsearchResponse = convert_to_es_search_response(
         search_response,
         append_shard_doc,
@@ -475,7 +571,10 @@ async fn es_compat_index_search(
         allow_partial_search_results,
     )?;
     search_response_rest.took = elapsed.as_millis() as u32;
-    Ok(search_response_rest)
+    Ok(ElasticsearchSearchResponse {
+        response: search_response_rest,
+        profile: profile_opt,
+    })
 }
 
 /// Returns JSON in the format:
@@ -534,6 +633,37 @@ async fn es_compat_index_stats(
     Ok(search_response_rest)
 }
 
+async fn es_compat_index_mapping(
+    index_id_patterns: Vec<String>,
+    mut metastore: MetastoreServiceClient,
+    search_service: Arc<dyn SearchService>,
+) -> Result<ElasticsearchMappingsResponse, ElasticsearchError> {
+    let indexes_metadata = resolve_index_patterns(&index_id_patterns, &mut metastore).await?;
+
+    // The doc mappings do not describe the fields captured by JSON fields and by the dynamic mode,
+    // so we complete them with the fields actually present in the splits.
+    let list_fields_request = quickwit_proto::search::ListFieldsRequest {
+        index_id_patterns,
+        fields: Vec::new(),
+        start_timestamp: None,
+        end_timestamp: None,
+    };
+    let list_fields_response: ListFieldsResponse =
+        search_service.root_list_fields(list_fields_request).await?;
+    let mappings_response =
+        convert_to_es_mappings_response(&indexes_metadata, &list_fields_response.fields);
+    Ok(mappings_response)
+}
+
+async fn es_compat_index_settings(
+    index_id_patterns: Vec<String>,
+    mut metastore: MetastoreServiceClient,
+) -> Result<ElasticsearchSettingsResponse, ElasticsearchError> {
+    let indexes_metadata = resolve_index_patterns(&index_id_patterns, &mut metastore).await?;
+    let settings_response = convert_to_es_settings_response(&indexes_metadata);
+    Ok(settings_response)
+}
+
 async fn es_compat_cat_indices(
     query_params: CatIndexQueryParams,
     metastore: MetastoreServiceClient,
@@ -606,6 +736,90 @@ async fn es_compat_resolve_index(
     })
 }
 
+async fn es_compat_put_index_template(
+    template_id: String,
+    query_params: PutIndexTemplateQueryParams,
+    elastic_index_template: ElasticIndexTemplate,
+    metastore: MetastoreServiceClient,
+) -> Result<ElasticsearchDeleteResponse, ElasticsearchError> {
+    let index_template = elastic_to_quickwit_index_template(template_id, elastic_index_template)
+        .map_err(|error| {
+            ElasticsearchError::new(
+                StatusCode::BAD_REQUEST,
+                error.to_string(),
+                Some(ElasticException::IllegalArgument),
+            )
+        })?;
+    index_template.validate().map_err(|error| {
+        ElasticsearchError::new(
+            StatusCode::BAD_REQUEST,
+            format!("invalid index template: {error}"),
+            Some(ElasticException::IllegalArgument),
+        )
+    })?;
+    let index_template_json = serde_utils::to_json_str(&index_template)?;
+    let create_index_template_request = CreateIndexTemplateRequest {
+        index_template_json,
+        overwrite: !query_params.create,
+    };
+    metastore
+        .create_index_template(create_index_template_request)
+        .await?;
+    Ok(ElasticsearchDeleteResponse { acknowledged: true })
+}
+
+async fn es_compat_get_index_template(
+    template_id: String,
+    metastore: MetastoreServiceClient,
+) -> Result<ElasticIndexTemplatesResponse, ElasticsearchError> {
+    let get_index_template_request = GetIndexTemplateRequest { template_id };
+    let get_index_template_response = metastore
+        .get_index_template(get_index_template_request)
+        .await?;
+    let index_template: IndexTemplate =
+        serde_utils::from_json_str(&get_index_template_response.index_template_json)?;
+    let index_template_entry = ElasticIndexTemplateEntry {
+        index_template: quickwit_to_elastic_index_template(&index_template),
+        name: index_template.template_id,
+    };
+    Ok(ElasticIndexTemplatesResponse {
+        index_templates: vec![index_template_entry],
+    })
+}
+
+async fn es_compat_list_index_templates(
+    metastore: MetastoreServiceClient,
+) -> Result<ElasticIndexTemplatesResponse, ElasticsearchError> {
+    let list_index_templates_response = metastore
+        .list_index_templates(ListIndexTemplatesRequest {})
+        .await?;
+    let mut index_templates =
+        Vec::with_capacity(list_index_templates_response.index_templates_json.len());
+    for index_template_json in list_index_templates_response.index_templates_json {
+        let index_template: IndexTemplate = serde_utils::from_json_str(&index_template_json)?;
+        let index_template_entry = ElasticIndexTemplateEntry {
+            index_template: quickwit_to_elastic_index_template(&index_template),
+            name: index_template.template_id,
+        };
+        index_templates.push(index_template_entry);
+    }
+    index_templates.sort_by(|left, right| left.name.cmp(&right.name));
+    Ok(ElasticIndexTemplatesResponse { index_templates })
+}
+
+async fn es_compat_delete_index_template(
+    template_id: String,
+    metastore: MetastoreServiceClient,
+) -> Result<ElasticsearchDeleteResponse, ElasticsearchError> {
+    let delete_index_templates_request = DeleteIndexTemplatesRequest {
+        template_ids: vec![template_id],
+    };
+    metastore
+        .delete_index_templates(delete_index_templates_request)
+        .await?;
+    Ok(ElasticsearchDeleteResponse { acknowledged: true })
+}
+
 async fn es_compat_index_field_capabilities(
     index_id_patterns: Vec<String>,
     search_params: FieldCapabilityQueryParams,
@@ -754,6 +968,7 @@ fn convert_hit(
 async fn es_compat_index_multi_search(
     payload: Bytes,
     multi_search_params: MultiSearchQueryParams,
+    user_agent_opt: Option<String>,
     search_service: Arc<dyn SearchService>,
 ) -> Result<MultiSearchResponse, ElasticsearchError> {
     let mut search_requests = Vec::new();
@@ -807,9 +1022,10 @@ async fn es_compat_index_multi_search(
         if let Some(extra_filters) = &multi_search_params.extra_filters {
             search_query_params.extra_filters = Some(extra_filters.to_vec());
         }
-        let es_request =
+        let (mut search_request, append_shard_doc) =
             build_request_for_es_api(index_ids_patterns, search_query_params, search_body)?;
-        search_requests.push(es_request);
+        search_request.user_agent = user_agent_opt.clone();
+        search_requests.push((search_request, append_shard_doc));
     }
 
     // TODO: forced to do weird referencing to work around https://github.com/rust-lang/rust/issues/100905
@@ -0,0 +1,965 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use arrow::datatypes::SchemaRef;
+use quickwit_common::uri::Uri;
+use quickwit_common::{new_coolid, spawn_named_task};
+use quickwit_doc_mapper::{JsonObject, SOURCE_FIELD_NAME};
+use quickwit_metastore::{
+    IndexMetadata, IndexMetadataResponseExt, ListSplitsQuery, ListSplitsRequestExt,
+    MetastoreServiceStreamSplitsExt, SplitState,
+};
+use quickwit_proto::metastore::{
+    IndexMetadataRequest, ListSplitsRequest, MetastoreError, MetastoreService,
+    MetastoreServiceClient,
+};
+use quickwit_proto::search::{CountHits, PartialHit, SearchRequest, SortField, SortOrder};
+use quickwit_proto::types::{IndexId, IndexUid, SplitId};
+use quickwit_proto::{ServiceError, ServiceErrorCode};
+use quickwit_query::query_ast::query_ast_from_user_text;
+use quickwit_search::{SearchError, SearchService};
+use quickwit_storage::{Storage, StorageErrorKind, StorageResolver};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use time::{Date, Month, OffsetDateTime};
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+use super::export_writer::{build_parquet_schema, write_csv, write_parquet};
+
+/// Name of the file recording the progress of an export in its output directory. Like other files
+/// starting with an underscore, it is ignored by Spark and DuckDB when reading the exported files.
+const EXPORT_MANIFEST_FILE_NAME: &str = "_export_manifest.json";
+
+/// Number of documents fetched per search request.
+const EXPORT_SEARCH_BATCH_SIZE: u64 = 1_000;
+
+const MAX_NUM_PARTITIONS: usize = 100_000;
+
+fn default_query() -> String {
+    "*".to_string()
+}
+
+fn default_max_docs_per_file() -> usize {
+    100_000
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    #[default]
+    Parquet,
+    Csv,
+}
+
+impl ExportFormat {
+    fn file_extension(&self) -> &'static str {
+        match self {
+            Self::Parquet => "parquet",
+            Self::Csv => "csv",
+        }
+    }
+}
+
+/// Granularity of the time partitions of an export. Each partition is written to a directory
+/// named after the granularity and the start of the partition, for instance `day=2024-01-31`,
+/// following the Hive partitioning layout.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportPartitionGranularity {
+    Hour,
+    #[default]
+    Day,
+    Month,
+}
+
+impl ExportPartitionGranularity {
+    /// Returns the start and end timestamps of the time bucket containing `timestamp`.
+    fn bucket(&self, timestamp: i64) -> anyhow::Result<(i64, i64)> {
+        let bucket = match self {
+            Self::Hour => {
+                let start_timestamp = timestamp - timestamp.rem_euclid(3_600);
+                (start_timestamp, start_timestamp + 3_600)
+            }
+            Self::Day => {
+                let start_timestamp = timestamp - timestamp.rem_euclid(86_400);
+                (start_timestamp, start_timestamp + 86_400)
+            }
+            Self::Month => {
+                let date = OffsetDateTime::from_unix_timestamp(timestamp)?.date();
+                let start_date = Date::from_calendar_date(date.year(), date.month(), 1)?;
+                let end_date = if date.month() == Month::December {
+                    Date::from_calendar_date(date.year() + 1, Month::January, 1)?
+                } else {
+                    Date::from_calendar_date(date.year(), date.month().next(), 1)?
+                };
+                (
+                    start_date.midnight().assume_utc().unix_timestamp(),
+                    end_date.midnight().assume_utc().unix_timestamp(),
+                )
+            }
+        };
+        Ok(bucket)
+    }
+
+    /// Returns the name of the directory of the partition starting at `start_timestamp`.
+    fn partition_key(&self, start_timestamp: i64) -> anyhow::Result<String> {
+        let datetime = OffsetDateTime::from_unix_timestamp(start_timestamp)?;
+        let (year, month, day) = (datetime.year(), datetime.month() as u8, datetime.day());
+        let partition_key = match self {
+            Self::Hour => format!("hour={year:04}-{month:02}-{day:02}T{:02}", datetime.hour()),
+            Self::Day => format!("day={year:04}-{month:02}-{day:02}"),
+            Self::Month => format!("month={year:04}-{month:02}"),
+        };
+        Ok(partition_key)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ExportRequest {
+    /// URI of the directory the exported files are written to, for instance
+    /// `s3://my-bucket/exports/my-export`.
+    #[schema(value_type = String)]
+    pub output_uri: Uri,
+    #[serde(default)]
+    pub format: ExportFormat,
+    /// Query expressed in the Quickwit query language selecting the documents to export.
+    #[serde(default = "default_query")]
+    pub query: String,
+    /// Fields to export. Nested fields are designated with their dotted path. All the fields of
+    /// the documents are exported when unset.
+    #[serde(default)]
+    pub fields: Option<Vec<String>>,
+    /// If set, restricts the export to documents with a `timestamp >= start_timestamp`, expressed
+    /// in seconds.
+    #[serde(default)]
+    pub start_timestamp: Option<i64>,
+    /// If set, restricts the export to documents with a `timestamp < end_timestamp`, expressed in
+    /// seconds.
+    #[serde(default)]
+    pub end_timestamp: Option<i64>,
+    #[serde(default)]
+    pub partition_by: ExportPartitionGranularity,
+    /// Maximum number of documents per exported file.
+    #[serde(default = "default_max_docs_per_file")]
+    pub max_docs_per_file: usize,
+}
+
+impl ExportRequest {
+    fn validate(&self) -> Result<(), ExportError> {
+        if self.max_docs_per_file == 0 {
+            return Err(ExportError::InvalidArgument(
+                "`max_docs_per_file` must be greater than 0".to_string(),
+            ));
+        }
+        if self.fields.as_ref().is_some_and(|fields| fields.is_empty()) {
+            return Err(ExportError::InvalidArgument(
+                "`fields` must not be empty".to_string(),
+            ));
+        }
+        if let (Some(start_timestamp), Some(end_timestamp)) =
+            (self.start_timestamp, self.end_timestamp)
+        {
+            if start_timestamp >= end_timestamp {
+                return Err(ExportError::InvalidArgument(
+                    "`start_timestamp` must be less than `end_timestamp`".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportState {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ExportStatus {
+    pub export_id: String,
+    pub index_id: IndexId,
+    pub output_uri: String,
+    pub state: ExportState,
+    /// Number of time partitions of the export.
+    pub num_partitions: usize,
+    /// Number of time partitions entirely written to the output directory.
+    pub num_exported_partitions: usize,
+    /// Number of documents written to the output directory.
+    pub num_exported_docs: u64,
+    /// Error that made the export fail.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    #[error("invalid export request: {0}")]
+    InvalidArgument(String),
+    #[error("export `{0}` not found")]
+    NotFound(String),
+    #[error("failed to access export output: {0}")]
+    Storage(String),
+    #[error(transparent)]
+    Metastore(#[from] MetastoreError),
+}
+
+impl ServiceError for ExportError {
+    fn error_code(&self) -> ServiceErrorCode {
+        match self {
+            Self::InvalidArgument(_) => ServiceErrorCode::BadRequest,
+            Self::NotFound(_) => ServiceErrorCode::NotFound,
+            Self::Storage(_) => ServiceErrorCode::Internal,
+            Self::Metastore(metastore_error) => metastore_error.error_code(),
+        }
+    }
+}
+
+/// The manifest of an export records its request and the partitions already written. Submitting
+/// the same export request again resumes the export, skipping the partitions already written.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ExportManifest {
+    index_id: IndexId,
+    export_request: ExportRequest,
+    exported_partitions: BTreeMap<String, ExportedPartition>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ExportedPartition {
+    files: Vec<String>,
+    num_docs: u64,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct ExportPartition {
+    partition_key: String,
+    start_timestamp: i64,
+    end_timestamp: i64,
+}
+
+/// Splits the `[start_timestamp, end_timestamp)` time range into partitions.
+fn build_partitions(
+    partition_by: ExportPartitionGranularity,
+    start_timestamp: i64,
+    end_timestamp: i64,
+) -> anyhow::Result<Vec<ExportPartition>> {
+    let mut partitions = Vec::new();
+    let mut partition_start_timestamp = start_timestamp;
+
+    while partition_start_timestamp < end_timestamp {
+        if partitions.len() >= MAX_NUM_PARTITIONS {
+            anyhow::bail!(
+                "the export spans more than {MAX_NUM_PARTITIONS} partitions: use a coarser \
+                 `partition_by` granularity or a narrower time range"
+            );
+        }
+        let (bucket_start_timestamp, bucket_end_timestamp) =
+            partition_by.bucket(partition_start_timestamp)?;
+        let partition = ExportPartition {
+            partition_key: partition_by.partition_key(bucket_start_timestamp)?,
+            start_timestamp: partition_start_timestamp,
+            end_timestamp: bucket_end_timestamp.min(end_timestamp),
+        };
+        partition_start_timestamp = partition.end_timestamp;
+        partitions.push(partition);
+    }
+    Ok(partitions)
+}
+
+struct ExportTask {
+    status: Arc<Mutex<ExportStatus>>,
+    join_handle: JoinHandle<()>,
+}
+
+/// Runs exports in the background on the node that received the export request and keeps track of
+/// their progress.
+///
+/// Exports are not persisted: an export interrupted by a restart of the node is resumed by
+/// submitting the same export request again.
+#[derive(Clone)]
+pub(crate) struct ExportTaskManager {
+    search_service: Arc<dyn SearchService>,
+    metastore: MetastoreServiceClient,
+    storage_resolver: StorageResolver,
+    export_tasks: Arc<Mutex<HashMap<String, ExportTask>>>,
+}
+
+impl ExportTaskManager {
+    pub fn new(
+        search_service: Arc<dyn SearchService>,
+        metastore: MetastoreServiceClient,
+        storage_resolver: StorageResolver,
+    ) -> Self {
+        Self {
+            search_service,
+            metastore,
+            storage_resolver,
+            export_tasks: Default::default(),
+        }
+    }
+
+    /// Starts exporting the documents of an index matching a query, or resumes the export
+    /// previously written to the same output URI.
+    pub async fn start_export(
+        &self,
+        index_id: IndexId,
+        export_request: ExportRequest,
+    ) -> Result<ExportStatus, ExportError> {
+        export_request.validate()?;
+
+        let index_metadata_request = IndexMetadataRequest::for_index_id(index_id.clone());
+        let index_metadata = self
+            .metastore
+            .index_metadata(index_metadata_request)
+            .await?
+            .deserialize_index_metadata()?;
+
+        if index_metadata
+            .index_config
+            .doc_mapping
+            .timestamp_field
+            .is_none()
+        {
+            return Err(ExportError::InvalidArgument(format!(
+                "index `{index_id}` has no timestamp field: exports are partitioned by time"
+            )));
+        }
+        let default_search_fields = &index_metadata
+            .index_config
+            .search_settings
+            .default_search_fields;
+        let query_ast = query_ast_from_user_text(&export_request.query, None)
+            .parse_user_query(default_search_fields)
+            .map_err(|error| ExportError::InvalidArgument(format!("invalid query: {error}")))?;
+        let query_ast_json =
+            serde_json::to_string(&query_ast).expect("query AST should be JSON serializable");
+
+        let output_uri = export_request.output_uri.clone();
+
+        if self.export_statuses().any(|export_status| {
+            export_status.state == ExportState::Running
+                && export_status.output_uri == output_uri.as_str()
+        }) {
+            return Err(ExportError::InvalidArgument(format!(
+                "an export is already running for output URI `{output_uri}`"
+            )));
+        }
+        let storage = self
+            .storage_resolver
+            .resolve(&output_uri)
+            .await
+            .map_err(|error| {
+                ExportError::InvalidArgument(format!(
+                    "failed to resolve output URI `{output_uri}`: {error}"
+                ))
+            })?;
+        let manifest = if let Some(manifest) = load_manifest(&*storage).await? {
+            if manifest.index_id != index_id || manifest.export_request != export_request {
+                return Err(ExportError::InvalidArgument(format!(
+                    "output URI `{output_uri}` already holds a different export"
+                )));
+            }
+            manifest
+        } else {
+            ExportManifest {
+                index_id: index_id.clone(),
+                export_request: export_request.clone(),
+                exported_partitions: BTreeMap::new(),
+            }
+        };
+        let partitions = self
+            .list_partitions(&index_metadata, &export_request)
+            .await?;
+        let (num_exported_partitions, num_exported_docs) = partitions
+            .iter()
+            .filter_map(|partition| manifest.exported_partitions.get(&partition.partition_key))
+            .fold((0, 0), |(num_partitions, num_docs), exported_partition| {
+                (num_partitions + 1, num_docs + exported_partition.num_docs)
+            });
+        let export_id = new_coolid("export");
+        let export_status = ExportStatus {
+            export_id: export_id.clone(),
+            index_id: index_id.clone(),
+            output_uri: output_uri.to_string(),
+            state: ExportState::Running,
+            num_partitions: partitions.len(),
+            num_exported_partitions,
+            num_exported_docs,
+            error: None,
+        };
+        let status = Arc::new(Mutex::new(export_status.clone()));
+        let parquet_schema = build_parquet_schema(
+            &index_metadata.index_config.doc_mapping,
+            export_request.fields.as_deref(),
+        );
+        let export_context = ExportContext {
+            search_service: self.search_service.clone(),
+            metastore: self.metastore.clone(),
+            storage,
+            index_uid: index_metadata.index_uid,
+            query_ast_json,
+            export_request,
+            parquet_schema,
+            manifest,
+            status: status.clone(),
+        };
+        info!(export_id=%export_id, output_uri=%output_uri, "starting export");
+        let join_handle = spawn_named_task(export_context.run(partitions), "export_task");
+        let export_task = ExportTask {
+            status,
+            join_handle,
+        };
+        self.export_tasks
+            .lock()
+            .unwrap()
+            .insert(export_id, export_task);
+        Ok(export_status)
+    }
+
+    fn export_statuses(&self) -> impl Iterator<Item = ExportStatus> {
+        let export_statuses: Vec<ExportStatus> = self
+            .export_tasks
+            .lock()
+            .unwrap()
+            .values()
+            .map(|export_task| export_task.status.lock().unwrap().clone())
+            .collect();
+        export_statuses.into_iter()
+    }
+
+    /// Lists the exports of an index started on this node.
+    pub fn list_exports(&self, index_id: &str) -> Vec<ExportStatus> {
+        let mut export_statuses: Vec<ExportStatus> = self
+            .export_statuses()
+            .filter(|export_status| export_status.index_id == index_id)
+            .collect();
+        export_statuses.sort_by(|left, right| left.export_id.cmp(&right.export_id));
+        export_statuses
+    }
+
+    pub fn get_export(&self, index_id: &str, export_id: &str) -> Result<ExportStatus, ExportError> {
+        let export_tasks = self.export_tasks.lock().unwrap();
+        let export_status = export_tasks
+            .get(export_id)
+            .map(|export_task| export_task.status.lock().unwrap().clone())
+            .filter(|export_status| export_status.index_id == index_id)
+            .ok_or_else(|| ExportError::NotFound(export_id.to_string()))?;
+        Ok(export_status)
+    }
+
+    /// Cancels an export. The files and the partitions already written are kept so that the
+    /// export can be resumed later.
+    pub fn cancel_export(
+        &self,
+        index_id: &str,
+        export_id: &str,
+    ) -> Result<ExportStatus, ExportError> {
+        let export_tasks = self.export_tasks.lock().unwrap();
+        let export_task = export_tasks
+            .get(export_id)
+            .filter(|export_task| export_task.status.lock().unwrap().index_id == index_id)
+            .ok_or_else(|| ExportError::NotFound(export_id.to_string()))?;
+        export_task.join_handle.abort();
+
+        let mut export_status = export_task.status.lock().unwrap();
+
+        if export_status.state == ExportState::Running {
+            export_status.state = ExportState::Cancelled;
+        }
+        Ok(export_status.clone())
+    }
+
+    async fn list_partitions(
+        &self,
+        index_metadata: &IndexMetadata,
+        export_request: &ExportRequest,
+    ) -> Result<Vec<ExportPartition>, ExportError> {
+        let query = ListSplitsQuery::for_index(index_metadata.index_uid.clone())
+            .with_split_state(SplitState::Published);
+        let list_splits_request = ListSplitsRequest::try_from_list_splits_query(&query)?;
+        let splits_metadata = self
+            .metastore
+            .list_splits(list_splits_request)
+            .await?
+            .collect_splits_metadata()
+            .await?;
+        let mut split_time_range_opt: Option<(i64, i64)> = None;
+
+        for time_range in splits_metadata
+            .iter()
+            .filter_map(|split_metadata| split_metadata.time_range.as_ref())
+        {
+            let (start_timestamp, end_timestamp) =
+                split_time_range_opt.unwrap_or((*time_range.start(), *time_range.end()));
+            split_time_range_opt = Some((
+                start_timestamp.min(*time_range.start()),
+                end_timestamp.max(*time_range.end()),
+            ));
+        }
+        let Some((split_start_timestamp, split_end_timestamp)) = split_time_range_opt else {
+            return Ok(Vec::new());
+        };
+        let start_timestamp = export_request
+            .start_timestamp
+            .unwrap_or(split_start_timestamp)
+            .max(split_start_timestamp);
+        // The time range of splits is inclusive.
+        let end_timestamp = export_request
+            .end_timestamp
+            .unwrap_or(i64::MAX)
+            .min(split_end_timestamp + 1);
+
+        build_partitions(export_request.partition_by, start_timestamp, end_timestamp)
+            .map_err(|error| ExportError::InvalidArgument(error.to_string()))
+    }
+}
+
+async fn load_manifest(storage: &dyn Storage) -> Result<Option<ExportManifest>, ExportError> {
+    let manifest_path = Path::new(EXPORT_MANIFEST_FILE_NAME);
+
+    match storage.get_all(manifest_path).await {
+        Ok(manifest_bytes) => {
+            let manifest = serde_json::from_slice(&manifest_bytes).map_err(|error| {
+                ExportError::Storage(format!("failed to parse export manifest: {error}"))
+            })?;
+            Ok(Some(manifest))
+        }
+        Err(storage_error) if storage_error.kind() == StorageErrorKind::NotFound => Ok(None),
+        Err(storage_error) => Err(ExportError::Storage(storage_error.to_string())),
+    }
+}
+
+struct ExportContext {
+    search_service: Arc<dyn SearchService>,
+    metastore: MetastoreServiceClient,
+    storage: Arc<dyn Storage>,
+    index_uid: IndexUid,
+    query_ast_json: String,
+    export_request: ExportRequest,
+    /// Schema shared by all the Parquet files of the export.
+    parquet_schema: SchemaRef,
+    manifest: ExportManifest,
+    status: Arc<Mutex<ExportStatus>>,
+}
+
+impl ExportContext {
+    async fn run(mut self, partitions: Vec<ExportPartition>) {
+        let export_result = self.export_partitions(partitions).await;
+        let mut export_status = self.status.lock().unwrap();
+
+        if let Err(error) = export_result {
+            error!(export_id=%export_status.export_id, error=?error, "export failed");
+            export_status.state = ExportState::Failed;
+            export_status.error = Some(format!("{error:#}"));
+        } else {
+            info!(export_id=%export_status.export_id, "export completed");
+            export_status.state = ExportState::Completed;
+        }
+    }
+
+    async fn export_partitions(&mut self, partitions: Vec<ExportPartition>) -> anyhow::Result<()> {
+        for partition in partitions {
+            if self
+                .manifest
+                .exported_partitions
+                .contains_key(&partition.partition_key)
+            {
+                continue;
+            }
+            let exported_partition = self.export_partition(&partition).await?;
+            self.manifest
+                .exported_partitions
+                .insert(partition.partition_key, exported_partition);
+            let manifest_bytes = serde_json::to_vec_pretty(&self.manifest)?;
+            self.storage
+                .put(
+                    Path::new(EXPORT_MANIFEST_FILE_NAME),
+                    Box::new(manifest_bytes),
+                )
+                .await?;
+            self.status.lock().unwrap().num_exported_partitions += 1;
+        }
+        Ok(())
+    }
+
+    /// Lists the published splits that may hold documents of a partition, sorted by ID.
+    async fn pin_splits(&self, partition: &ExportPartition) -> anyhow::Result<Vec<SplitId>> {
+        let query = ListSplitsQuery::for_index(self.index_uid.clone())
+            .with_split_state(SplitState::Published)
+            .with_time_range_start_gte(partition.start_timestamp)
+            .with_time_range_end_lt(partition.end_timestamp);
+        let list_splits_request = ListSplitsRequest::try_from_list_splits_query(&query)?;
+        let mut split_ids: Vec<SplitId> = self
+            .metastore
+            .list_splits(list_splits_request)
+            .await?
+            .collect_splits_metadata()
+            .await?
+            .into_iter()
+            .map(|split_metadata| split_metadata.split_id)
+            .collect();
+        split_ids.sort_unstable();
+        Ok(split_ids)
+    }
+
+    /// Exports the documents of a partition. Documents are read in batches sorted by address and
+    /// written to files of at most `max_docs_per_file` documents.
+    ///
+    /// The splits of the partition are pinned when its export starts: the splits merged meanwhile
+    /// remain searchable until they are garbage collected, so the addresses of the documents, and
+    /// therefore their order, do not change while the partition is read.
+    async fn export_partition(
+        &self,
+        partition: &ExportPartition,
+    ) -> anyhow::Result<ExportedPartition> {
+        let mut split_ids = self.pin_splits(partition).await?;
+        let mut exported_partition = ExportedPartition::default();
+        let mut docs = Vec::new();
+        let mut search_after_opt: Option<PartialHit> = None;
+
+        while !split_ids.is_empty() {
+            let search_request = SearchRequest {
+                index_id_patterns: vec![self.index_uid.index_id.clone()],
+                query_ast: self.query_ast_json.clone(),
+                start_timestamp: Some(partition.start_timestamp),
+                end_timestamp: Some(partition.end_timestamp),
+                max_hits: EXPORT_SEARCH_BATCH_SIZE,
+                sort_fields: vec![SortField {
+                    field_name: "_doc".to_string(),
+                    sort_order: SortOrder::Asc as i32,
+                    sort_datetime_format: None,
+                }],
+                search_after: search_after_opt.take(),
+                count_hits: CountHits::Underestimate as i32,
+                split_ids: split_ids.clone(),
+                ..Default::default()
+            };
+            let search_response = self.search_service.root_search(search_request).await?;
+
+            // Moving on past the splits that failed to be searched would skip their documents.
+            if let Some(search_error) =
+                SearchError::from_split_errors(&search_response.failed_splits)
+            {
+                return Err(search_error.into());
+            }
+            let num_hits = search_response.hits.len() as u64;
+
+            for hit in search_response.hits {
+                if let Some(partial_hit) = &hit.partial_hit {
+                    search_after_opt = Some(PartialHit {
+                        split_id: partial_hit.split_id.clone(),
+                        segment_ord: partial_hit.segment_ord,
+                        doc_id: partial_hit.doc_id,
+                        ..Default::default()
+                    });
+                }
+                let doc = export_doc(&hit.json, self.export_request.fields.as_deref())?;
+                docs.push(doc);
+
+                if docs.len() >= self.export_request.max_docs_per_file {
+                    self.write_file(partition, &mut exported_partition, &mut docs)
+                        .await?;
+                }
+            }
+            if num_hits < EXPORT_SEARCH_BATCH_SIZE {
+                break;
+            }
+            let Some(search_after) = &search_after_opt else {
+                break;
+            };
+            // The splits sorted before the split of the last document read are entirely exported.
+            split_ids.retain(|split_id| *split_id >= search_after.split_id);
+        }
+        if !docs.is_empty() {
+            self.write_file(partition, &mut exported_partition, &mut docs)
+                .await?;
+        }
+        Ok(exported_partition)
+    }
+
+    async fn write_file(
+        &self,
+        partition: &ExportPartition,
+        exported_partition: &mut ExportedPartition,
+        docs: &mut Vec<JsonObject>,
+    ) -> anyhow::Result<()> {
+        let format = self.export_request.format;
+        let file_path = format!(
+            "{}/part-{:05}.{}",
+            partition.partition_key,
+            exported_partition.files.len(),
+            format.file_extension()
+        );
+        let file_content = match format {
+            ExportFormat::Parquet => write_parquet(docs, &self.parquet_schema)?,
+            ExportFormat::Csv => write_csv(docs, self.export_request.fields.as_deref()),
+        };
+        self.storage
+            .put(Path::new(&file_path), Box::new(file_content))
+            .await?;
+
+        let num_docs = docs.len() as u64;
+        exported_partition.files.push(file_path);
+        exported_partition.num_docs += num_docs;
+        self.status.lock().unwrap().num_exported_docs += num_docs;
+        docs.clear();
+        Ok(())
+    }
+}
+
+/// Builds the exported document from a search hit, preferring the original document stored in
+/// `_source` when the index stores it.
+fn export_doc(hit_json: &str, fields_opt: Option<&[String]>) -> anyhow::Result<JsonObject> {
+    let mut json_doc: JsonObject = serde_json::from_str(hit_json)?;
+
+    if let Some(JsonValue::Object(source_json_doc)) = json_doc.remove(SOURCE_FIELD_NAME) {
+        json_doc = source_json_doc;
+    }
+    let Some(fields) = fields_opt else {
+        return Ok(json_doc);
+    };
+    let mut exported_doc = JsonObject::new();
+
+    for field in fields {
+        if let Some(field_value) = get_field_value(&json_doc, field) {
+            exported_doc.insert(field.clone(), field_value.clone());
+        }
+    }
+    Ok(exported_doc)
+}
+
+fn get_field_value<'a>(json_doc: &'a JsonObject, field_path: &str) -> Option<&'a JsonValue> {
+    if let Some(field_value) = json_doc.get(field_path) {
+        return Some(field_value);
+    }
+    let (parent_field_name, child_field_path) = field_path.split_once('.')?;
+
+    match json_doc.get(parent_field_name)? {
+        JsonValue::Object(child_json_doc) => get_field_value(child_json_doc, child_field_path),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_proto::search::{Hit, SearchResponse};
+    use quickwit_search::MockSearchService;
+    use quickwit_storage::RamStorage;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_build_partitions() {
+        // 2024-01-31T22:30:00Z
+        let start_timestamp = 1_706_740_200;
+
+        let partitions = build_partitions(
+            ExportPartitionGranularity::Hour,
+            start_timestamp,
+            start_timestamp + 3_600,
+        )
+        .unwrap();
+        assert_eq!(
+            partitions,
+            [
+                ExportPartition {
+                    partition_key: "hour=2024-01-31T22".to_string(),
+                    start_timestamp,
+                    end_timestamp: start_timestamp + 1_800,
+                },
+                ExportPartition {
+                    partition_key: "hour=2024-01-31T23".to_string(),
+                    start_timestamp: start_timestamp + 1_800,
+                    end_timestamp: start_timestamp + 3_600,
+                },
+            ]
+        );
+        let partitions = build_partitions(
+            ExportPartitionGranularity::Day,
+            start_timestamp,
+            start_timestamp + 7_200,
+        )
+        .unwrap();
+        let partition_keys: Vec<&str> = partitions
+            .iter()
+            .map(|partition| partition.partition_key.as_str())
+            .collect();
+        assert_eq!(partition_keys, ["day=2024-01-31", "day=2024-02-01"]);
+
+        // 2024-03-01T00:00:00Z
+        let end_timestamp = 1_709_251_200;
+        let partitions = build_partitions(
+            ExportPartitionGranularity::Month,
+            start_timestamp,
+            end_timestamp,
+        )
+        .unwrap();
+        assert_eq!(partitions.len(), 2);
+        assert_eq!(partitions[0].partition_key, "month=2024-01");
+        assert_eq!(partitions[1].partition_key, "month=2024-02");
+        // 2024-02-01T00:00:00Z
+        assert_eq!(partitions[1].start_timestamp, 1_706_745_600);
+        assert_eq!(partitions[1].end_timestamp, end_timestamp);
+
+        assert!(build_partitions(ExportPartitionGranularity::Day, 10, 10)
+            .unwrap()
+            .is_empty());
+        assert!(build_partitions(ExportPartitionGranularity::Hour, 0, i64::MAX).is_err());
+    }
+
+    #[test]
+    fn test_export_doc() {
+        let hit_json =
+            r#"{"_source": {"body": "foo", "attributes": {"host": "bar"}}, "body": "baz"}"#;
+        let exported_doc = export_doc(hit_json, None).unwrap();
+        assert_eq!(
+            JsonValue::Object(exported_doc),
+            json!({"body": "foo", "attributes": {"host": "bar"}})
+        );
+        let fields = vec!["attributes.host".to_string(), "missing".to_string()];
+        let exported_doc = export_doc(hit_json, Some(&fields)).unwrap();
+        assert_eq!(
+            JsonValue::Object(exported_doc),
+            json!({"attributes.host": "bar"})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_context_resumes_from_manifest() {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .times(2)
+            .returning(|search_request| {
+                assert_eq!(search_request.start_timestamp, Some(86_400));
+                assert_eq!(search_request.end_timestamp, Some(172_800));
+
+                let hits = if search_request.search_after.is_none() {
+                    (0..EXPORT_SEARCH_BATCH_SIZE as u32)
+                        .map(|doc_id| Hit {
+                            json: format!(r#"{{"body": "doc-{doc_id}"}}"#),
+                            partial_hit: Some(PartialHit {
+                                split_id: "split".to_string(),
+                                doc_id,
+                                ..Default::default()
+                            }),
+                            ..Default::default()
+                        })
+                        .collect()
+                } else {
+                    Vec::new()
+                };
+                Ok(SearchResponse {
+                    num_hits: hits.len() as u64,
+                    hits,
+                    ..Default::default()
+                })
+            });
+        let storage = Arc::new(RamStorage::default());
+        let export_request = ExportRequest {
+            output_uri: Uri::for_test("ram:///exports/my-export"),
+            format: ExportFormat::Csv,
+            query: "*".to_string(),
+            fields: Some(vec!["body".to_string()]),
+            start_timestamp: None,
+            end_timestamp: None,
+            partition_by: ExportPartitionGranularity::Day,
+            max_docs_per_file: 600,
+        };
+        let partitions = build_partitions(ExportPartitionGranularity::Day, 0, 172_800).unwrap();
+        // The first partition was written by a previous run of the export.
+        let mut manifest = ExportManifest {
+            index_id: "my-index".to_string(),
+            export_request: export_request.clone(),
+            exported_partitions: BTreeMap::new(),
+        };
+        manifest
+            .exported_partitions
+            .insert("day=1970-01-01".to_string(), ExportedPartition::default());
+
+        let export_status = ExportStatus {
+            export_id: "my-export".to_string(),
+            index_id: "my-index".to_string(),
+            output_uri: "ram:///exports/my-export".to_string(),
+            state: ExportState::Running,
+            num_partitions: 2,
+            num_exported_partitions: 1,
+            num_exported_docs: 0,
+            error: None,
+        };
+        let status = Arc::new(Mutex::new(export_status));
+        let export_context = ExportContext {
+            search_service: Arc::new(mock_search_service),
+            storage: storage.clone(),
+            index_id: "my-index".to_string(),
+            query_ast_json: "{}".to_string(),
+            export_request,
+            manifest,
+            status: status.clone(),
+        };
+        export_context.run(partitions).await;
+
+        let export_status = status.lock().unwrap().clone();
+        assert_eq!(export_status.state, ExportState::Completed);
+        assert_eq!(export_status.num_exported_partitions, 2);
+        assert_eq!(export_status.num_exported_docs, 1_000);
+
+        let first_file = storage
+            .get_all(Path::new("day=1970-01-02/part-00000.csv"))
+            .await
+            .unwrap();
+        let first_file_content = std::str::from_utf8(&first_file).unwrap();
+        assert!(first_file_content.starts_with("body\ndoc-0\ndoc-1\n"));
+        assert_eq!(first_file_content.lines().count(), 601);
+
+        let second_file = storage
+            .get_all(Path::new("day=1970-01-02/part-00001.csv"))
+            .await
+            .unwrap();
+        assert_eq!(
+            std::str::from_utf8(&second_file).unwrap().lines().count(),
+            401
+        );
+
+        let manifest_bytes = storage
+            .get_all(Path::new(EXPORT_MANIFEST_FILE_NAME))
+            .await
+            .unwrap();
+        let manifest: ExportManifest = serde_json::from_slice(&manifest_bytes).unwrap();
+        let exported_partition = &manifest.exported_partitions["day=1970-01-02"];
+        assert_eq!(exported_partition.num_docs, 1_000);
+        assert_eq!(
+            exported_partition.files,
+            [
+                "day=1970-01-02/part-00000.csv",
+                "day=1970-01-02/part-00001.csv"
+            ]
+        );
+    }
+}
@@ -0,0 +1,499 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use arrow::datatypes::{DataType, Field, Fields, Schema, SchemaRef};
+use arrow::json::ReaderBuilder;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use quickwit_doc_mapper::{
+    Cardinality, DocMapping, FieldMappingEntry, FieldMappingType, JsonObject, Mode,
+    DYNAMIC_FIELD_NAME,
+};
+use serde_json::Value as JsonValue;
+
+/// Number of rows converted at once into an Arrow record batch.
+const RECORD_BATCH_SIZE: usize = 1_024;
+
+/// Builds the schema of the Parquet files of an export from the field mappings of the index, so
+/// that all the files of the export share the same schema.
+///
+/// The columns are the exported fields when they are specified, or the top-level fields of the
+/// doc mapping otherwise. In the latter case, the unmapped fields of the indexes in dynamic mode
+/// are exported as a JSON string in the `_dynamic` column.
+pub(crate) fn build_parquet_schema(
+    doc_mapping: &DocMapping,
+    fields_opt: Option<&[String]>,
+) -> SchemaRef {
+    let fields: Vec<Field> = if let Some(fields) = fields_opt {
+        fields
+            .iter()
+            .map(|field_path| {
+                // The unmapped fields are exported as JSON strings.
+                let data_type = find_field_mapping_type(&doc_mapping.field_mappings, field_path)
+                    .and_then(field_data_type)
+                    .unwrap_or(DataType::Utf8);
+                Field::new(field_path, data_type, true)
+            })
+            .collect()
+    } else {
+        let mut fields = struct_fields(&doc_mapping.field_mappings);
+
+        if matches!(doc_mapping.mode, Mode::Dynamic(_)) {
+            fields.push(Field::new(DYNAMIC_FIELD_NAME, DataType::Utf8, true));
+        }
+        fields
+    };
+    Arc::new(Schema::new(fields))
+}
+
+fn find_field_mapping_type<'a>(
+    field_mappings: &'a [FieldMappingEntry],
+    field_path: &str,
+) -> Option<&'a FieldMappingType> {
+    let find_field_mapping_entry = |field_name: &str| {
+        field_mappings
+            .iter()
+            .find(|field_mapping| field_mapping.name == field_name)
+    };
+    if let Some(field_mapping) = find_field_mapping_entry(field_path) {
+        return Some(&field_mapping.mapping_type);
+    }
+    let (parent_field_name, child_field_path) = field_path.split_once('.')?;
+
+    match &find_field_mapping_entry(parent_field_name)?.mapping_type {
+        FieldMappingType::Object(object_options) => {
+            find_field_mapping_type(&object_options.field_mappings, child_field_path)
+        }
+        _ => None,
+    }
+}
+
+fn struct_fields(field_mappings: &[FieldMappingEntry]) -> Vec<Field> {
+    field_mappings
+        .iter()
+        .filter_map(|field_mapping| {
+            let data_type = field_data_type(&field_mapping.mapping_type)?;
+            Some(Field::new(&field_mapping.name, data_type, true))
+        })
+        .collect()
+}
+
+/// Returns the Arrow data type of the values of a field, or `None` for the fields that are not
+/// part of the documents.
+///
+/// Datetimes are exported as they were ingested, since their input formats vary. IP addresses,
+/// bytes, and JSON objects are exported as strings, the latter serialized as JSON.
+fn field_data_type(mapping_type: &FieldMappingType) -> Option<DataType> {
+    let (data_type, cardinality) = match mapping_type {
+        FieldMappingType::Text(_, cardinality)
+        | FieldMappingType::DateTime(_, cardinality)
+        | FieldMappingType::IpAddr(_, cardinality)
+        | FieldMappingType::Bytes(_, cardinality)
+        | FieldMappingType::Json(_, cardinality) => (DataType::Utf8, *cardinality),
+        FieldMappingType::I64(_, cardinality) => (DataType::Int64, *cardinality),
+        FieldMappingType::U64(_, cardinality) => (DataType::UInt64, *cardinality),
+        FieldMappingType::F64(_, cardinality) => (DataType::Float64, *cardinality),
+        FieldMappingType::Bool(_, cardinality) => (DataType::Boolean, *cardinality),
+        FieldMappingType::Object(object_options) => {
+            let fields = struct_fields(&object_options.field_mappings);
+            (
+                DataType::Struct(Fields::from(fields)),
+                Cardinality::SingleValued,
+            )
+        }
+        FieldMappingType::Nested(object_options) => {
+            let fields = struct_fields(&object_options.field_mappings);
+            (
+                DataType::Struct(Fields::from(fields)),
+                Cardinality::MultiValued,
+            )
+        }
+        FieldMappingType::Concatenate(_) => return None,
+    };
+    if cardinality == Cardinality::MultiValued {
+        let item_field = Field::new("item", data_type, true);
+        return Some(DataType::List(Arc::new(item_field)));
+    }
+    Some(data_type)
+}
+
+/// Converts a document into a row of the schema. The values that do not match the type of their
+/// column are coerced into it, or dropped when they cannot be.
+fn doc_to_row(doc: JsonObject, schema: &Schema) -> JsonObject {
+    let (mut row, unmapped_fields) = object_to_struct(doc, schema.fields());
+
+    if !unmapped_fields.is_empty() && schema.field_with_name(DYNAMIC_FIELD_NAME).is_ok() {
+        let dynamic_value = JsonValue::Object(unmapped_fields).to_string();
+        row.insert(
+            DYNAMIC_FIELD_NAME.to_string(),
+            JsonValue::String(dynamic_value),
+        );
+    }
+    row
+}
+
+/// Converts an object into a struct of the given fields. Returns the struct and the values of the
+/// object missing from the fields.
+fn object_to_struct(mut object: JsonObject, fields: &Fields) -> (JsonObject, JsonObject) {
+    let mut struct_object = JsonObject::new();
+
+    for field in fields.iter() {
+        let Some(value) = object.remove(field.name()) else {
+            continue;
+        };
+        let struct_value = match (field.data_type(), value) {
+            (DataType::Struct(child_fields), JsonValue::Object(child_object)) => {
+                let (child_struct_object, unmapped_child_object) =
+                    object_to_struct(child_object, child_fields);
+
+                if !unmapped_child_object.is_empty() {
+                    object.insert(
+                        field.name().clone(),
+                        JsonValue::Object(unmapped_child_object),
+                    );
+                }
+                JsonValue::Object(child_struct_object)
+            }
+            (data_type, value) => to_data_type(value, data_type),
+        };
+        struct_object.insert(field.name().clone(), struct_value);
+    }
+    (struct_object, object)
+}
+
+fn to_data_type(value: JsonValue, data_type: &DataType) -> JsonValue {
+    match (data_type, value) {
+        (_, JsonValue::Null) => JsonValue::Null,
+        (DataType::List(item_field), JsonValue::Array(values)) => JsonValue::Array(
+            values
+                .into_iter()
+                .map(|value| to_data_type(value, item_field.data_type()))
+                .collect(),
+        ),
+        (DataType::List(item_field), value) => {
+            JsonValue::Array(vec![to_data_type(value, item_field.data_type())])
+        }
+        (DataType::Struct(fields), JsonValue::Object(object)) => {
+            JsonValue::Object(object_to_struct(object, fields).0)
+        }
+        (DataType::Utf8, JsonValue::String(value)) => JsonValue::String(value),
+        (DataType::Utf8, value) => JsonValue::String(value.to_string()),
+        (DataType::Int64, value) => coerce_value(value, JsonValue::as_i64),
+        (DataType::UInt64, value) => coerce_value(value, JsonValue::as_u64),
+        (DataType::Float64, value) => coerce_value(value, JsonValue::as_f64),
+        (DataType::Boolean, value) => coerce_value(value, JsonValue::as_bool),
+        _ => JsonValue::Null,
+    }
+}
+
+/// Coerces a value into a primitive type, parsing it when it is a string like Quickwit does at
+/// indexing time.
+fn coerce_value<T>(value: JsonValue, as_primitive: impl Fn(&JsonValue) -> Option<T>) -> JsonValue
+where T: FromStr + Into<JsonValue> {
+    let primitive_opt = if let JsonValue::String(value) = &value {
+        value.parse().ok()
+    } else {
+        as_primitive(&value)
+    };
+    primitive_opt.map(Into::into).unwrap_or(JsonValue::Null)
+}
+
+/// Writes documents into a Parquet file with the given schema, built with
+/// [`build_parquet_schema`].
+pub(crate) fn write_parquet(docs: &[JsonObject], schema: &SchemaRef) -> anyhow::Result<Vec<u8>> {
+    let mut decoder = ReaderBuilder::new(schema.clone())
+        .with_batch_size(RECORD_BATCH_SIZE)
+        .build_decoder()?;
+    let writer_properties = WriterProperties::builder()
+        .set_compression(Compression::ZSTD(ZstdLevel::default()))
+        .build();
+    let mut writer = ArrowWriter::try_new(Vec::new(), schema.clone(), Some(writer_properties))?;
+
+    for docs_chunk in docs.chunks(RECORD_BATCH_SIZE) {
+        let rows: Vec<JsonObject> = docs_chunk
+            .iter()
+            .map(|doc| doc_to_row(doc.clone(), schema))
+            .collect();
+        decoder.serialize(&rows)?;
+
+        if let Some(record_batch) = decoder.flush()? {
+            writer.write(&record_batch)?;
+        }
+    }
+    let file_content = writer.into_inner()?;
+    Ok(file_content)
+}
+
+/// Writes documents into a CSV file with a header row.
+///
+/// The columns are the exported fields when they are specified, or the top-level fields of the
+/// documents in order of appearance otherwise. Strings are written as is, missing and null values
+/// as empty cells, and other values as JSON.
+pub(crate) fn write_csv(docs: &[JsonObject], fields_opt: Option<&[String]>) -> Vec<u8> {
+    let columns: Vec<&str> = if let Some(fields) = fields_opt {
+        fields.iter().map(String::as_str).collect()
+    } else {
+        let mut columns: Vec<&str> = Vec::new();
+
+        for doc in docs {
+            for field_name in doc.keys() {
+                if !columns.contains(&field_name.as_str()) {
+                    columns.push(field_name);
+                }
+            }
+        }
+        columns
+    };
+    let mut file_content = String::new();
+    write_csv_row(&mut file_content, columns.iter().copied());
+
+    for doc in docs {
+        let cells = columns.iter().map(|column| match doc.get(*column) {
+            None | Some(JsonValue::Null) => String::new(),
+            Some(JsonValue::String(value)) => value.clone(),
+            Some(value) => value.to_string(),
+        });
+        write_csv_row(&mut file_content, cells);
+    }
+    file_content.into_bytes()
+}
+
+fn write_csv_row<S: AsRef<str>>(file_content: &mut String, cells: impl Iterator<Item = S>) {
+    for (cell_idx, cell) in cells.enumerate() {
+        if cell_idx > 0 {
+            file_content.push(',');
+        }
+        let cell = cell.as_ref();
+
+        if cell.contains([',', '"', '\n', '\r']) {
+            file_content.push('"');
+            file_content.push_str(&cell.replace('"', "\"\""));
+            file_content.push('"');
+        } else {
+            file_content.push_str(cell);
+        }
+    }
+    file_content.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::{Array, Int64Array, ListArray, StringArray};
+    use bytes::Bytes;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use serde_json::json;
+
+    use super::*;
+
+    fn json_docs(docs: JsonValue) -> Vec<JsonObject> {
+        serde_json::from_value(docs).unwrap()
+    }
+
+    #[test]
+    fn test_write_csv() {
+        let docs = json_docs(json!([
+            {"count": 1, "name": "foo"},
+            {"name": "bar, \"baz\"", "tags": ["a", "b"]},
+            {"count": 3, "name": null},
+        ]));
+        let file_content = write_csv(&docs, None);
+        let expected_file_content = r#"count,name,tags
+1,foo,
+,"bar, ""baz""","[""a"",""b""]"
+3,,
+"#;
+        assert_eq!(
+            String::from_utf8(file_content).unwrap(),
+            expected_file_content
+        );
+        let fields = vec!["count".to_string(), "missing".to_string()];
+        let file_content = write_csv(&docs, Some(&fields));
+        assert_eq!(
+            String::from_utf8(file_content).unwrap(),
+            "count,missing\n1,\n,\n3,\n"
+        );
+    }
+
+    fn doc_mapping() -> DocMapping {
+        serde_json::from_value(json!({
+            "mode": "dynamic",
+            "field_mappings": [
+                {"name": "doc_idx", "type": "i64"},
+                {"name": "body", "type": "text"},
+                {"name": "tags", "type": "array<text>"},
+                {
+                    "name": "attributes",
+                    "type": "object",
+                    "field_mappings": [{"name": "host", "type": "text"}]
+                },
+                {"name": "all", "type": "concatenate", "concatenate_fields": ["body"]},
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_build_parquet_schema() {
+        let doc_mapping = doc_mapping();
+        let schema = build_parquet_schema(&doc_mapping, None);
+        let field_names: Vec<&str> = schema
+            .fields()
+            .iter()
+            .map(|field| field.name().as_str())
+            .collect();
+        assert_eq!(
+            field_names,
+            ["doc_idx", "body", "tags", "attributes", "_dynamic"]
+        );
+        assert_eq!(
+            schema.field_with_name("doc_idx").unwrap().data_type(),
+            &DataType::Int64
+        );
+        assert!(matches!(
+            schema.field_with_name("tags").unwrap().data_type(),
+            DataType::List(item_field) if item_field.data_type() == &DataType::Utf8
+        ));
+        assert!(matches!(
+            schema.field_with_name("attributes").unwrap().data_type(),
+            DataType::Struct(fields) if fields.len() == 1
+        ));
+        let fields = vec!["attributes.host".to_string(), "unmapped".to_string()];
+        let schema = build_parquet_schema(&doc_mapping, Some(&fields));
+        assert_eq!(schema.fields().len(), 2);
+        assert_eq!(
+            schema
+                .field_with_name("attributes.host")
+                .unwrap()
+                .data_type(),
+            &DataType::Utf8
+        );
+        assert_eq!(
+            schema.field_with_name("unmapped").unwrap().data_type(),
+            &DataType::Utf8
+        );
+    }
+
+    #[test]
+    fn test_write_parquet() {
+        let schema = build_parquet_schema(&doc_mapping(), None);
+        let docs: Vec<JsonObject> = (0..2_500)
+            .map(|doc_idx| {
+                serde_json::from_value(
+                    json!({"doc_idx": doc_idx, "body": format!("doc-{doc_idx}")}),
+                )
+                .unwrap()
+            })
+            .collect();
+        let file_content = write_parquet(&docs, &schema).unwrap();
+        let reader = ParquetRecordBatchReaderBuilder::try_new(Bytes::from(file_content))
+            .unwrap()
+            .build()
+            .unwrap();
+        let record_batches: Vec<_> = reader.map(|record_batch| record_batch.unwrap()).collect();
+        let num_rows: usize = record_batches
+            .iter()
+            .map(|record_batch| record_batch.num_rows())
+            .sum();
+        assert_eq!(num_rows, 2_500);
+
+        let record_batch = &record_batches[0];
+        assert_eq!(record_batch.schema().fields(), schema.fields());
+
+        let doc_idx_column = record_batch
+            .column_by_name("doc_idx")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(doc_idx_column.value(1), 1);
+        let body_column = record_batch
+            .column_by_name("body")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(body_column.value(1), "doc-1");
+        assert!(!body_column.is_null(1));
+    }
+
+    #[test]
+    fn test_write_parquet_coerces_values_into_schema() {
+        let schema = build_parquet_schema(&doc_mapping(), None);
+        let docs = json_docs(json!([
+            {
+                "doc_idx": "1",
+                "body": 2,
+                "tags": "foo",
+                "attributes": {"host": "bar", "region": "baz"},
+                "extra": true
+            },
+            {"doc_idx": "not-a-number"},
+        ]));
+        let file_content = write_parquet(&docs, &schema).unwrap();
+        let mut reader = ParquetRecordBatchReaderBuilder::try_new(Bytes::from(file_content))
+            .unwrap()
+            .build()
+            .unwrap();
+        let record_batch = reader.next().unwrap().unwrap();
+        assert_eq!(record_batch.schema().fields(), schema.fields());
+        assert_eq!(record_batch.num_rows(), 2);
+
+        let doc_idx_column = record_batch
+            .column_by_name("doc_idx")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(doc_idx_column.value(0), 1);
+        assert!(doc_idx_column.is_null(1));
+
+        let body_column = record_batch
+            .column_by_name("body")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(body_column.value(0), "2");
+
+        let tags_column = record_batch
+            .column_by_name("tags")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<ListArray>()
+            .unwrap();
+        assert_eq!(tags_column.value_length(0), 1);
+
+        let dynamic_column = record_batch
+            .column_by_name("_dynamic")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        let dynamic_value: JsonValue = serde_json::from_str(dynamic_column.value(0)).unwrap();
+        assert_eq!(
+            dynamic_value,
+            json!({"attributes": {"region": "baz"}, "extra": true})
+        );
+        assert!(dynamic_column.is_null(1));
+    }
+}
@@ -0,0 +1,200 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use quickwit_proto::types::IndexId;
+use tracing::info;
+use warp::{Filter, Rejection};
+
+use super::export_task::{
+    ExportError, ExportFormat, ExportPartitionGranularity, ExportRequest, ExportState,
+    ExportStatus, ExportTaskManager,
+};
+use crate::format::extract_format_from_qs;
+use crate::rest::recover_fn;
+use crate::rest_api_response::into_rest_api_response;
+use crate::with_arg;
+
+#[derive(utoipa::OpenApi)]
+#[openapi(
+    paths(create_export, list_exports, get_export, cancel_export),
+    components(schemas(
+        ExportRequest,
+        ExportFormat,
+        ExportPartitionGranularity,
+        ExportStatus,
+        ExportState
+    ))
+)]
+pub struct ExportApi;
+
+/// Export API handlers.
+pub(crate) fn export_api_handlers(
+    export_task_manager: ExportTaskManager,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    create_export_handler(export_task_manager.clone())
+        .or(list_exports_handler(export_task_manager.clone()))
+        .or(get_export_handler(export_task_manager.clone()))
+        .or(cancel_export_handler(export_task_manager))
+        .recover(recover_fn)
+        .boxed()
+}
+
+fn create_export_handler(
+    export_task_manager: ExportTaskManager,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("indexes" / String / "exports")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(1024 * 1024).and(warp::body::json()))
+        .and(with_arg(export_task_manager))
+        .then(create_export)
+        .and(extract_format_from_qs())
+        .map(into_rest_api_response)
+        .boxed()
+}
+
+#[utoipa::path(
+    post,
+    tag = "Indexes",
+    path = "/indexes/{index_id}/exports",
+    request_body = ExportRequest,
+    responses(
+        (status = 200, description = "Successfully started the export.", body = ExportStatus)
+    ),
+    params(
+        ("index_id" = String, Path, description = "The index ID to export documents from."),
+    )
+)]
+/// Starts an export.
+///
+/// The documents matching the query are written in the background to Parquet or CSV files,
+/// partitioned by time. Submitting the same export request again resumes the export where it
+/// stopped.
+async fn create_export(
+    index_id: IndexId,
+    export_request: ExportRequest,
+    export_task_manager: ExportTaskManager,
+) -> Result<ExportStatus, ExportError> {
+    info!(index_id = %index_id, "create-export");
+    export_task_manager
+        .start_export(index_id, export_request)
+        .await
+}
+
+fn list_exports_handler(
+    export_task_manager: ExportTaskManager,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("indexes" / String / "exports")
+        .and(warp::get())
+        .and(with_arg(export_task_manager))
+        .then(list_exports)
+        .and(extract_format_from_qs())
+        .map(into_rest_api_response)
+        .boxed()
+}
+
+#[utoipa::path(
+    get,
+    tag = "Indexes",
+    path = "/indexes/{index_id}/exports",
+    responses(
+        (status = 200, description = "Successfully fetched the exports.", body = [ExportStatus])
+    ),
+    params(
+        ("index_id" = String, Path, description = "The index ID to list the exports of."),
+    )
+)]
+/// Lists the exports of an index started on the node.
+async fn list_exports(
+    index_id: IndexId,
+    export_task_manager: ExportTaskManager,
+) -> Result<Vec<ExportStatus>, ExportError> {
+    info!(index_id = %index_id, "list-exports");
+    Ok(export_task_manager.list_exports(&index_id))
+}
+
+fn get_export_handler(
+    export_task_manager: ExportTaskManager,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("indexes" / String / "exports" / String)
+        .and(warp::get())
+        .and(with_arg(export_task_manager))
+        .then(get_export)
+        .and(extract_format_from_qs())
+        .map(into_rest_api_response)
+        .boxed()
+}
+
+#[utoipa::path(
+    get,
+    tag = "Indexes",
+    path = "/indexes/{index_id}/exports/{export_id}",
+    responses(
+        (status = 200, description = "Successfully fetched the export status.", body = ExportStatus)
+    ),
+    params(
+        ("index_id" = String, Path, description = "The index ID of the export."),
+        ("export_id" = String, Path, description = "The export ID."),
+    )
+)]
+/// Gets the status of an export.
+async fn get_export(
+    index_id: IndexId,
+    export_id: String,
+    export_task_manager: ExportTaskManager,
+) -> Result<ExportStatus, ExportError> {
+    info!(index_id = %index_id, export_id = %export_id, "get-export");
+    export_task_manager.get_export(&index_id, &export_id)
+}
+
+fn cancel_export_handler(
+    export_task_manager: ExportTaskManager,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("indexes" / String / "exports" / String)
+        .and(warp::delete())
+        .and(with_arg(export_task_manager))
+        .then(cancel_export)
+        .and(extract_format_from_qs())
+        .map(into_rest_api_response)
+        .boxed()
+}
+
+#[utoipa::path(
+    delete,
+    tag = "Indexes",
+    path = "/indexes/{index_id}/exports/{export_id}",
+    responses(
+        (status = 200, description = "Successfully cancelled the export.", body = ExportStatus)
+    ),
+    params(
+        ("index_id" = String, Path, description = "The index ID of the export."),
+        ("export_id" = String, Path, description = "The export ID."),
+    )
+)]
+/// Cancels an export.
+///
+/// The files already written are kept and the export can be resumed by submitting the same export
+/// request again.
+async fn cancel_export(
+    index_id: IndexId,
+    export_id: String,
+    export_task_manager: ExportTaskManager,
+) -> Result<ExportStatus, ExportError> {
+    info!(index_id = %index_id, export_id = %export_id, "cancel-export");
+    export_task_manager.cancel_export(&index_id, &export_id)
+}
@@ -23,17 +23,21 @@ use std::sync::Arc;
 
 use bytesize::ByteSize;
 use quickwit_cluster::cluster_grpc_server;
+use quickwit_common::tls::{tls_incoming, ClientAuth, ReloadingServerConfig};
 use quickwit_common::tower::BoxFutureInfaillible;
 use quickwit_config::service::QuickwitService;
+use quickwit_config::TlsConfig;
 use quickwit_proto::developer::DeveloperServiceClient;
 use quickwit_proto::indexing::IndexingServiceClient;
 use quickwit_proto::jaeger::storage::v1::span_reader_plugin_server::SpanReaderPluginServer;
 use quickwit_proto::opentelemetry::proto::collector::logs::v1::logs_service_server::LogsServiceServer;
 use quickwit_proto::opentelemetry::proto::collector::trace::v1::trace_service_server::TraceServiceServer;
 use quickwit_proto::search::search_service_server::SearchServiceServer;
-use quickwit_proto::tonic::codegen::CompressionEncoding;
+use quickwit_proto::tonic::codegen::{CompressionEncoding, InterceptedService};
+use quickwit_proto::tonic::service::Interceptor;
 use quickwit_proto::tonic::transport::server::TcpIncoming;
 use quickwit_proto::tonic::transport::Server;
+use quickwit_proto::tonic::{Request, Status};
 use tokio::net::TcpListener;
 use tracing::*;
 
@@ -45,6 +49,7 @@ use crate::{QuickwitServices, INDEXING_GRPC_SERVER_METRICS_LAYER};
 pub(crate) async fn start_grpc_server(
     tcp_listener: TcpListener,
     max_message_size: ByteSize,
+    tls_config_opt: Option<TlsConfig>,
     services: Arc<QuickwitServices>,
     readiness_trigger: BoxFutureInfaillible<()>,
     shutdown_signal: BoxFutureInfaillible<()>,
@@ -52,6 +57,13 @@ pub(crate) async fn start_grpc_server(
     let mut enabled_grpc_services = BTreeSet::new();
     let mut server = Server::builder();
 
+    let ingest_client_cert_interceptor = IngestClientCertInterceptor {
+        require_client_cert: tls_config_opt
+            .as_ref()
+            .map(|tls_config| tls_config.require_client_cert_for_ingest)
+            .unwrap_or(false),
+    };
+
     let cluster_grpc_service = cluster_grpc_server(services.cluster.clone());
 
     // Mount gRPC metastore service if `QuickwitService::Metastore` is enabled on node.
@@ -84,7 +96,10 @@ pub(crate) async fn start_grpc_server(
         .is_service_enabled(QuickwitService::Indexer)
     {
         enabled_grpc_services.insert("ingest-api");
-        Some(services.ingest_service.as_grpc_service(max_message_size))
+        Some(InterceptedService::new(
+            services.ingest_service.as_grpc_service(max_message_size),
+            ingest_client_cert_interceptor,
+        ))
     } else {
         None
     };
@@ -97,7 +112,10 @@ pub(crate) async fn start_grpc_server(
         let ingest_router_service = services
             .ingest_router_service
             .as_grpc_service(max_message_size);
-        Some(ingest_router_service)
+        Some(InterceptedService::new(
+            ingest_router_service,
+            ingest_client_cert_interceptor,
+        ))
     } else {
         None
     };
@@ -129,7 +147,10 @@ pub(crate) async fn start_grpc_server(
             enabled_grpc_services.insert("otlp-traces");
             let trace_service = TraceServiceServer::new(otlp_traces_service)
                 .accept_compressed(CompressionEncoding::Gzip);
-            Some(trace_service)
+            Some(InterceptedService::new(
+                trace_service,
+                ingest_client_cert_interceptor,
+            ))
         } else {
             None
         };
@@ -138,7 +159,10 @@ pub(crate) async fn start_grpc_server(
             enabled_grpc_services.insert("otlp-logs");
             let logs_service = LogsServiceServer::new(otlp_logs_service)
                 .accept_compressed(CompressionEncoding::Gzip);
-            Some(logs_service)
+            Some(InterceptedService::new(
+                logs_service,
+                ingest_client_cert_interceptor,
+            ))
         } else {
             None
         };
@@ -194,6 +218,23 @@ pub(crate) async fn start_grpc_server(
         grpc_listen_addr=?grpc_listen_addr,
         "Starting gRPC server listening on {grpc_listen_addr}."
     );
+    if let Some(tls_config) = tls_config_opt {
+        let client_auth = if tls_config.require_client_cert {
+            ClientAuth::Required
+        } else {
+            ClientAuth::Optional
+        };
+        let server_config = ReloadingServerConfig::try_new(
+            tls_config.tls_paths(),
+            client_auth,
+            vec![b"h2".to_vec()],
+        )?;
+        let incoming = tls_incoming(tcp_listener, server_config);
+        let serve_fut = server_router.serve_with_incoming_shutdown(incoming, shutdown_signal);
+        let (serve_res, _trigger_res) = tokio::join!(serve_fut, readiness_trigger);
+        serve_res?;
+        return Ok(());
+    }
     // nodelay=true and keepalive=None are the default values for Server::builder()
     let tcp_incoming = TcpIncoming::from_listener(tcp_listener, true, None)
         .map_err(|err: Box<dyn Error + Send + Sync>| anyhow::anyhow!(err))?;
@@ -202,3 +243,22 @@ pub(crate) async fn start_grpc_server(
     serve_res?;
     Ok(())
 }
+
+/// Rejects the ingest requests of the clients that did not present a certificate when
+/// `grpc.tls.require_client_cert_for_ingest` is set. The certificates presented by the clients are
+/// verified against the CA during the TLS handshake.
+#[derive(Clone, Copy)]
+struct IngestClientCertInterceptor {
+    require_client_cert: bool,
+}
+
+impl Interceptor for IngestClientCertInterceptor {
+    fn call(&mut self, request: Request<()>) -> Result<Request<()>, Status> {
+        if self.require_client_cert && request.peer_certs().is_none() {
+            return Err(Status::permission_denied(
+                "a client certificate is required to ingest documents",
+            ));
+        }
+        Ok(request)
+    }
+}
@@ -22,25 +22,27 @@ use std::sync::Arc;
 use bytes::Bytes;
 use quickwit_common::uri::Uri;
 use quickwit_config::{
-    load_index_config_update, load_source_config_from_user_config, validate_index_id_pattern,
-    ConfigFormat, FileSourceParams, NodeConfig, SourceConfig, SourceParams, CLI_SOURCE_ID,
-    INGEST_API_SOURCE_ID,
+    load_index_config_from_user_config, load_source_config_from_user_config,
+    validate_index_config_update, validate_index_id_pattern, ConfigFormat, FileSourceParams,
+    NodeConfig, SourceConfig, SourceParams, TransformConfig, CLI_SOURCE_ID, INGEST_API_SOURCE_ID,
+    REINDEX_TASK_SOURCE_ID, ROLLUP_SOURCE_ID,
 };
 use quickwit_doc_mapper::{analyze_text, TokenizerConfig};
-use quickwit_index_management::{IndexService, IndexServiceError};
+use quickwit_index_management::{inline_tokenizer_word_lists, IndexService, IndexServiceError};
+use quickwit_janitor::actors::failed_reindex_split_ids;
 use quickwit_metastore::{
-    IndexMetadata, IndexMetadataResponseExt, ListIndexesMetadataResponseExt, ListSplitsQuery,
-    ListSplitsRequestExt, MetastoreServiceStreamSplitsExt, Split, SplitInfo, SplitState,
-    UpdateIndexRequestExt,
+    AddSourceRequestExt, IndexMetadata, IndexMetadataResponseExt, ListIndexesMetadataResponseExt,
+    ListSplitsQuery, ListSplitsRequestExt, MetastoreServiceStreamSplitsExt, Split, SplitInfo,
+    SplitState, UpdateIndexRequestExt,
 };
 use quickwit_proto::ingest::Shard;
 use quickwit_proto::metastore::{
-    DeleteSourceRequest, EntityKind, IndexMetadataRequest, ListIndexesMetadataRequest,
-    ListShardsRequest, ListShardsSubrequest, ListSplitsRequest, MarkSplitsForDeletionRequest,
-    MetastoreError, MetastoreResult, MetastoreService, MetastoreServiceClient,
-    ResetSourceCheckpointRequest, ToggleSourceRequest, UpdateIndexRequest,
+    AddSourceRequest, DeleteSourceRequest, EntityKind, IndexMetadataRequest,
+    ListIndexesMetadataRequest, ListShardsRequest, ListShardsSubrequest, ListSplitsRequest,
+    MarkSplitsForDeletionRequest, MetastoreError, MetastoreResult, MetastoreService,
+    MetastoreServiceClient, ResetSourceCheckpointRequest, ToggleSourceRequest, UpdateIndexRequest,
 };
-use quickwit_proto::types::{IndexId, IndexUid, SourceId};
+use quickwit_proto::types::{DocMappingUid, IndexId, IndexUid, SourceId, SplitId};
 use quickwit_query::query_ast::{query_ast_from_user_text, QueryAst};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
@@ -68,8 +70,17 @@ use crate::with_arg;
         reset_source_checkpoint,
         toggle_source,
         delete_source,
+        create_reindex_task,
+        get_reindex_task,
+        delete_reindex_task,
     ),
-    components(schemas(ToggleSource, SplitsForDeletion, IndexStats))
+    components(schemas(
+        ToggleSource,
+        SplitsForDeletion,
+        IndexStats,
+        ReindexTaskRequest,
+        ReindexTaskStatus
+    ))
 )]
 pub struct IndexApi;
 
@@ -92,7 +103,7 @@ pub fn index_management_handlers(
     get_index_metadata_handler(index_service.metastore())
         .or(list_indexes_metadata_handler(index_service.metastore()))
         .or(create_index_handler(index_service.clone(), node_config))
-        .or(update_index_handler(index_service.metastore()))
+        .or(update_index_handler(index_service.clone()))
         .or(clear_index_handler(index_service.clone()))
         .or(delete_index_handler(index_service.clone()))
         .boxed()
@@ -101,6 +112,11 @@ pub fn index_management_handlers(
         .or(describe_index_handler(index_service.metastore()))
         .or(mark_splits_for_deletion_handler(index_service.metastore()))
         .boxed()
+        // Reindex task handlers.
+        .or(create_reindex_task_handler(index_service.metastore()))
+        .or(get_reindex_task_handler(index_service.metastore()))
+        .or(delete_reindex_task_handler(index_service.metastore()))
+        .boxed()
         // Sources handlers.
         .or(reset_source_checkpoint_handler(index_service.metastore()))
         .or(toggle_source_handler(index_service.metastore()))
@@ -542,14 +558,14 @@ async fn create_index(
 }
 
 fn update_index_handler(
-    metastore: MetastoreServiceClient,
+    index_service: IndexService,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
     warp::path!("indexes" / String)
         .and(warp::put())
         .and(extract_config_format())
         .and(warp::body::content_length_limit(1024 * 1024))
         .and(warp::filters::body::bytes())
-        .and(with_arg(metastore))
+        .and(with_arg(index_service))
         .then(update_index)
         .map(log_failure("failed to update index"))
         .and(extract_format_from_qs())
@@ -581,10 +597,11 @@ async fn update_index(
     target_index_id: IndexId,
     config_format: ConfigFormat,
     index_config_bytes: Bytes,
-    metastore: MetastoreServiceClient,
+    index_service: IndexService,
 ) -> Result<IndexMetadata, IndexServiceError> {
     info!(index_id = %target_index_id, "update-index");
 
+    let metastore = index_service.metastore();
     let index_metadata_request = IndexMetadataRequest::for_index_id(target_index_id.to_string());
     let current_index_metadata = metastore
         .index_metadata(index_metadata_request)
@@ -593,9 +610,23 @@ async fn update_index(
     let index_uid = current_index_metadata.index_uid.clone();
     let current_index_config = current_index_metadata.into_index_config();
 
-    let new_index_config =
-        load_index_config_update(config_format, &index_config_bytes, &current_index_config)
-            .map_err(IndexServiceError::InvalidConfig)?;
+    let current_index_parent_dir = current_index_config
+        .index_uri
+        .parent()
+        .expect("index URI should have a parent");
+    let mut new_index_config = load_index_config_from_user_config(
+        config_format,
+        &index_config_bytes,
+        &current_index_parent_dir,
+    )
+    .map_err(IndexServiceError::InvalidConfig)?;
+    // The word lists of the current tokenizers are inlined: the word lists of the new tokenizers
+    // must be inlined as well before comparing them.
+    inline_tokenizer_word_lists(&index_service.storage_resolver(), &mut new_index_config)
+        .await
+        .map_err(IndexServiceError::InvalidConfig)?;
+    let new_index_config = validate_index_config_update(new_index_config, &current_index_config)
+        .map_err(IndexServiceError::InvalidConfig)?;
 
     let update_request = UpdateIndexRequest::try_from_updates(
         index_uid,
@@ -873,7 +904,14 @@ async fn toggle_source(
         .await?
         .deserialize_index_metadata()?
         .index_uid;
-    if [CLI_SOURCE_ID, INGEST_API_SOURCE_ID].contains(&source_id.as_str()) {
+    if [
+        CLI_SOURCE_ID,
+        INGEST_API_SOURCE_ID,
+        ROLLUP_SOURCE_ID,
+        REINDEX_TASK_SOURCE_ID,
+    ]
+    .contains(&source_id.as_str())
+    {
         return Err(IndexServiceError::OperationNotAllowed(format!(
             "source `{source_id}` is managed by Quickwit, you cannot enable or disable a source \
              managed by Quickwit"
@@ -925,7 +963,14 @@ async fn delete_source(
         .await?
         .deserialize_index_metadata()?
         .index_uid;
-    if [INGEST_API_SOURCE_ID, CLI_SOURCE_ID].contains(&source_id.as_str()) {
+    if [
+        INGEST_API_SOURCE_ID,
+        CLI_SOURCE_ID,
+        ROLLUP_SOURCE_ID,
+        REINDEX_TASK_SOURCE_ID,
+    ]
+    .contains(&source_id.as_str())
+    {
         return Err(IndexServiceError::OperationNotAllowed(format!(
             "source `{source_id}` is managed by Quickwit, you cannot delete a source managed by \
              Quickwit"
@@ -939,6 +984,208 @@ async fn delete_source(
     Ok(())
 }
 
+#[derive(Debug, Default, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+struct ReindexTaskRequest {
+    /// Optional VRL transform applied to the documents before they are indexed with the
+    /// current doc mapping.
+    #[serde(default)]
+    transform: Option<TransformConfig>,
+}
+
+/// Progress of the reindex of the splits of an index into its current doc mapping.
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ReindexTaskStatus {
+    /// Whether a reindex task is ongoing on the index. The task is not in progress anymore once
+    /// all the remaining splits have failed to be reindexed.
+    pub in_progress: bool,
+    /// The doc mapping UID splits are reindexed into.
+    #[schema(value_type = String)]
+    pub doc_mapping_uid: DocMappingUid,
+    /// Number of published splits built with the current doc mapping.
+    pub num_reindexed_splits: usize,
+    /// Number of published splits built with an older doc mapping.
+    pub num_remaining_splits: usize,
+    /// Number of documents in splits built with the current doc mapping.
+    pub num_reindexed_docs: usize,
+    /// Number of documents in splits built with an older doc mapping.
+    pub num_remaining_docs: usize,
+    /// IDs of the splits the reindex task failed to reindex. These splits are skipped by the
+    /// task and keep their original doc mapping.
+    #[schema(value_type = Vec<String>)]
+    pub failed_split_ids: Vec<SplitId>,
+}
+
+fn create_reindex_task_handler(
+    metastore: MetastoreServiceClient,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("indexes" / String / "reindex-task")
+        .and(warp::post())
+        .and(json_body())
+        .and(with_arg(metastore))
+        .then(create_reindex_task)
+        .map(log_failure("failed to create reindex task"))
+        .and(extract_format_from_qs())
+        .map(into_rest_api_response)
+        .boxed()
+}
+
+#[utoipa::path(
+    post,
+    tag = "Indexes",
+    path = "/indexes/{index_id}/reindex-task",
+    request_body = ReindexTaskRequest,
+    responses(
+        (status = 200, description = "Successfully created reindex task.", body = ReindexTaskStatus)
+    ),
+    params(
+        ("index_id" = String, Path, description = "The index ID to reindex."),
+    )
+)]
+/// Creates a reindex task.
+///
+/// The janitor rebuilds in the background the splits of the index that were built with an older
+/// doc mapping, re-reading their documents and indexing them with the current doc mapping.
+async fn create_reindex_task(
+    index_id: IndexId,
+    reindex_task_request: ReindexTaskRequest,
+    metastore: MetastoreServiceClient,
+) -> Result<ReindexTaskStatus, IndexServiceError> {
+    info!(index_id = %index_id, "create-reindex-task");
+    let index_metadata_request = IndexMetadataRequest::for_index_id(index_id.to_string());
+    let index_uid: IndexUid = metastore
+        .index_metadata(index_metadata_request)
+        .await?
+        .deserialize_index_metadata()?
+        .index_uid;
+    let source_config = SourceConfig::reindex_task(reindex_task_request.transform);
+    let add_source_request = AddSourceRequest::try_from_source_config(index_uid, &source_config)?;
+    metastore.add_source(add_source_request).await?;
+    let reindex_task_status = get_reindex_task(index_id, metastore).await?;
+    Ok(reindex_task_status)
+}
+
+fn get_reindex_task_handler(
+    metastore: MetastoreServiceClient,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("indexes" / String / "reindex-task")
+        .and(warp::get())
+        .and(with_arg(metastore))
+        .then(get_reindex_task)
+        .and(extract_format_from_qs())
+        .map(into_rest_api_response)
+        .boxed()
+}
+
+#[utoipa::path(
+    get,
+    tag = "Indexes",
+    path = "/indexes/{index_id}/reindex-task",
+    responses(
+        (status = 200, description = "Successfully fetched reindex task status.", body = ReindexTaskStatus)
+    ),
+    params(
+        ("index_id" = String, Path, description = "The index ID to retrieve the reindex task status for."),
+    )
+)]
+/// Gets the reindex task status.
+async fn get_reindex_task(
+    index_id: IndexId,
+    metastore: MetastoreServiceClient,
+) -> MetastoreResult<ReindexTaskStatus> {
+    info!(index_id = %index_id, "get-reindex-task");
+    let index_metadata_request = IndexMetadataRequest::for_index_id(index_id.to_string());
+    let index_metadata = metastore
+        .index_metadata(index_metadata_request)
+        .await?
+        .deserialize_index_metadata()?;
+    let doc_mapping_uid = index_metadata.index_config.doc_mapping.doc_mapping_uid;
+    let failed_split_ids = failed_reindex_split_ids(&index_metadata);
+    let query = ListSplitsQuery::for_index(index_metadata.index_uid.clone())
+        .with_split_state(SplitState::Published);
+    let list_splits_request = ListSplitsRequest::try_from_list_splits_query(&query)?;
+    let splits = metastore
+        .list_splits(list_splits_request)
+        .await?
+        .collect_splits_metadata()
+        .await?;
+    let mut reindex_task_status = ReindexTaskStatus {
+        in_progress: false,
+        doc_mapping_uid,
+        num_reindexed_splits: 0,
+        num_remaining_splits: 0,
+        num_reindexed_docs: 0,
+        num_remaining_docs: 0,
+        failed_split_ids: Vec::new(),
+    };
+    for split_metadata in splits {
+        if split_metadata.doc_mapping_uid == doc_mapping_uid {
+            reindex_task_status.num_reindexed_splits += 1;
+            reindex_task_status.num_reindexed_docs += split_metadata.num_docs;
+        } else {
+            reindex_task_status.num_remaining_splits += 1;
+            reindex_task_status.num_remaining_docs += split_metadata.num_docs;
+
+            if failed_split_ids.contains(split_metadata.split_id()) {
+                reindex_task_status
+                    .failed_split_ids
+                    .push(split_metadata.split_id);
+            }
+        }
+    }
+    reindex_task_status.failed_split_ids.sort_unstable();
+    reindex_task_status.in_progress = index_metadata.sources.contains_key(REINDEX_TASK_SOURCE_ID)
+        && (reindex_task_status.num_remaining_splits == 0
+            || reindex_task_status.num_remaining_splits
+                > reindex_task_status.failed_split_ids.len());
+    Ok(reindex_task_status)
+}
+
+fn delete_reindex_task_handler(
+    metastore: MetastoreServiceClient,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("indexes" / String / "reindex-task")
+        .and(warp::delete())
+        .and(with_arg(metastore))
+        .then(delete_reindex_task)
+        .and(extract_format_from_qs())
+        .map(into_rest_api_response)
+        .boxed()
+}
+
+#[utoipa::path(
+    delete,
+    tag = "Indexes",
+    path = "/indexes/{index_id}/reindex-task",
+    responses(
+        (status = 200, description = "Successfully cancelled reindex task.")
+    ),
+    params(
+        ("index_id" = String, Path, description = "The index ID to cancel the reindex task for."),
+    )
+)]
+/// Cancels the reindex task.
+///
+/// Splits already reindexed are kept, an interrupted reindex operation leaves its split untouched.
+async fn delete_reindex_task(
+    index_id: IndexId,
+    metastore: MetastoreServiceClient,
+) -> MetastoreResult<()> {
+    info!(index_id = %index_id, "delete-reindex-task");
+    let index_metadata_request = IndexMetadataRequest::for_index_id(index_id.to_string());
+    let index_uid: IndexUid = metastore
+        .index_metadata(index_metadata_request)
+        .await?
+        .deserialize_index_metadata()?
+        .index_uid;
+    let delete_source_request = DeleteSourceRequest {
+        index_uid: Some(index_uid),
+        source_id: REINDEX_TASK_SOURCE_ID.to_string(),
+    };
+    metastore.delete_source(delete_source_request).await?;
+    Ok(())
+}
+
 fn get_source_shards_handler(
     metastore: MetastoreServiceClient,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
@@ -987,6 +1234,9 @@ struct AnalyzeRequest {
     pub tokenizer_config: TokenizerConfig,
     /// The text to analyze.
     pub text: String,
+    /// If true, the text is tokenized as a query rather than as a document.
+    #[serde(default)]
+    pub query_time: bool,
 }
 
 fn analyze_request_filter() -> impl Filter<Extract = (AnalyzeRequest,), Error = Rejection> + Clone {
@@ -1015,7 +1265,7 @@ fn analyze_request_handler() -> impl Filter<Extract = (impl warp::Reply,), Error
     ),
 )]
 async fn analyze_request(request: AnalyzeRequest) -> Result<serde_json::Value, IndexServiceError> {
-    let tokens = analyze_text(&request.text, &request.tokenizer_config)
+    let tokens = analyze_text(&request.text, &request.tokenizer_config, request.query_time)
         .map_err(|err| IndexServiceError::Internal(format!("{err:?}")))?;
     let json_value = serde_json::to_value(tokens)
         .map_err(|err| IndexServiceError::Internal(format!("cannot serialize tokens: {err}")))?;
@@ -1070,6 +1320,7 @@ async fn parse_query_request(request: ParseQueryRequest) -> Result<QueryAst, Ind
 #[cfg(test)]
 mod tests {
     use std::ops::{Bound, RangeInclusive};
+    use std::path::Path;
 
     use assert_json_diff::assert_json_include;
     use quickwit_common::uri::Uri;
@@ -1891,6 +2142,154 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_update_index_inlines_word_lists() {
+        let metastore = metastore_for_test();
+        let storage_resolver = StorageResolver::for_test();
+        let storage = storage_resolver
+            .resolve(&Uri::for_test("ram://indexes/hdfs-logs"))
+            .await
+            .unwrap();
+        let index_service = IndexService::new(metastore.clone(), storage_resolver);
+        let index_management_handler =
+            super::index_management_handlers(index_service, Arc::new(NodeConfig::for_test()));
+
+        let resp = warp::test::request()
+            .path("/indexes")
+            .method("POST")
+            .json(&true)
+            .body(r#"{"version": "0.8", "index_id": "hdfs-logs", "index_uri": "ram://indexes/hdfs-logs", "doc_mapping": {"field_mappings":[{"name": "body", "type": "text"}]}}"#)
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+
+        let update_index_config = r#"{"version": "0.8", "index_id": "hdfs-logs", "index_uri": "ram://indexes/hdfs-logs", "doc_mapping": {"tokenizers": [{"name": "french", "type": "simple", "filters": ["lower_caser", {"stop_words": {"words_path": "stop_words.txt"}}]}], "field_mappings":[{"name": "body", "type": "text", "tokenizer": "french"}]}}"#;
+        let resp = warp::test::request()
+            .path("/indexes/hdfs-logs")
+            .method("PUT")
+            .json(&true)
+            .body(update_index_config)
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+        let body = std::str::from_utf8(resp.body()).unwrap();
+        assert!(body.contains("failed to read word list file `stop_words.txt`"));
+
+        storage
+            .put(
+                Path::new("stop_words.txt"),
+                Box::new(b"le\nla\nles\n".to_vec()),
+            )
+            .await
+            .unwrap();
+        let resp = warp::test::request()
+            .path("/indexes/hdfs-logs")
+            .method("PUT")
+            .json(&true)
+            .body(update_index_config)
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+
+        let index_metadata = metastore
+            .index_metadata(IndexMetadataRequest::for_index_id("hdfs-logs".to_string()))
+            .await
+            .unwrap()
+            .deserialize_index_metadata()
+            .unwrap();
+        let tokenizer_entry = &index_metadata.index_config.doc_mapping.tokenizers[0];
+        assert!(tokenizer_entry.word_list_paths().is_empty());
+
+        // The tokenizer is unchanged once its word list is inlined.
+        let resp = warp::test::request()
+            .path("/indexes/hdfs-logs")
+            .method("PUT")
+            .json(&true)
+            .body(update_index_config)
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_reindex_task() {
+        let metastore = metastore_for_test();
+        let index_service = IndexService::new(metastore.clone(), StorageResolver::unconfigured());
+        let mut node_config = NodeConfig::for_test();
+        node_config.default_index_root_uri = Uri::for_test("file:///default-index-root-uri");
+        let index_management_handler =
+            super::index_management_handlers(index_service, Arc::new(node_config));
+        let resp = warp::test::request()
+            .path("/indexes")
+            .method("POST")
+            .json(&true)
+            .body(r#"{"version": "0.7", "index_id": "hdfs-logs", "doc_mapping": {"field_mappings":[{"name": "timestamp", "type": "i64", "fast": true, "indexed": true}]}}"#)
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+
+        let resp = warp::test::request()
+            .path("/indexes/hdfs-logs/reindex-task")
+            .method("POST")
+            .json(&true)
+            .body(r#"{"transform": {"script": ".severity = \"INFO\""}}"#)
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let reindex_task_status: ReindexTaskStatus = serde_json::from_slice(resp.body()).unwrap();
+        assert!(reindex_task_status.in_progress);
+        assert_eq!(reindex_task_status.num_reindexed_splits, 0);
+        assert_eq!(reindex_task_status.num_remaining_splits, 0);
+        assert!(reindex_task_status.failed_split_ids.is_empty());
+
+        let index_metadata = metastore
+            .index_metadata(IndexMetadataRequest::for_index_id("hdfs-logs".to_string()))
+            .await
+            .unwrap()
+            .deserialize_index_metadata()
+            .unwrap();
+        let reindex_task_source_config = &index_metadata.sources[REINDEX_TASK_SOURCE_ID];
+        assert!(!reindex_task_source_config.enabled);
+        assert!(reindex_task_source_config.transform_config.is_some());
+
+        // A single reindex task can run at a time.
+        let resp = warp::test::request()
+            .path("/indexes/hdfs-logs/reindex-task")
+            .method("POST")
+            .json(&true)
+            .body("{}")
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+
+        // The reindex task source is managed by Quickwit.
+        let resp = warp::test::request()
+            .path(format!("/indexes/hdfs-logs/sources/{REINDEX_TASK_SOURCE_ID}").as_str())
+            .method("DELETE")
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 403);
+
+        let resp = warp::test::request()
+            .path("/indexes/hdfs-logs/reindex-task")
+            .method("DELETE")
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+
+        let resp = warp::test::request()
+            .path("/indexes/hdfs-logs/reindex-task")
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let reindex_task_status: ReindexTaskStatus = serde_json::from_slice(resp.body()).unwrap();
+        assert!(!reindex_task_status.in_progress);
+        assert_eq!(
+            reindex_task_status.doc_mapping_uid,
+            index_metadata.index_config.doc_mapping.doc_mapping_uid
+        );
+    }
+
     #[tokio::test]
     async fn test_create_source_with_bad_config() {
         let metastore = metastore_for_test();
@@ -2184,6 +2583,47 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_analyze_request_query_time_synonyms() {
+        let index_service = IndexService::new(
+            MetastoreServiceClient::mocked(),
+            StorageResolver::unconfigured(),
+        );
+        let index_management_handler =
+            super::index_management_handlers(index_service, Arc::new(NodeConfig::for_test()))
+                .recover(recover_fn);
+        let body = r#"{"type": "simple", "text": "Coche", "query_time": true, "filters": [
+            "lower_caser",
+            {"synonyms": {"synonyms": ["coche, auto"], "query_time": true}},
+            {"stemmer": {"language": "spanish"}}
+        ]}"#;
+        let resp = warp::test::request()
+            .path("/analyze")
+            .method("POST")
+            .body(body)
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let actual_response_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        let tokens: Vec<&str> = actual_response_json
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|token| token["text"].as_str().unwrap())
+            .collect();
+        assert_eq!(tokens, ["coch", "aut"]);
+
+        let resp = warp::test::request()
+            .path("/analyze")
+            .method("POST")
+            .body(body.replace(r#""query_time": true, "filters""#, r#""filters""#))
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let actual_response_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(actual_response_json.as_array().unwrap().len(), 1);
+    }
+
     #[tokio::test]
     async fn test_parse_query_request() {
         let index_service = IndexService::new(
@@ -482,6 +482,7 @@ mod tests {
                     scroll_id: None,
                     failed_splits: Vec::new(),
                     num_successful_splits: 1,
+                    split_profiles: Vec::new(),
                 })
             });
         let mock_search_service = Arc::new(mock_search_service);
@@ -515,6 +516,7 @@ mod tests {
                     scroll_id: None,
                     failed_splits: Vec::new(),
                     num_successful_splits: 1,
+                    split_profiles: Vec::new(),
                 })
             });
         let mock_search_service = Arc::new(mock_search_service);
@@ -25,6 +25,7 @@ mod decompression;
 mod delete_task_api;
 mod developer_api;
 mod elasticsearch_api;
+mod export_api;
 mod format;
 mod grpc;
 mod health_check_api;
@@ -33,11 +34,13 @@ mod indexing_api;
 mod ingest_api;
 mod jaeger_api;
 mod load_shield;
+mod loki_api;
 mod metrics;
 mod metrics_api;
 mod node_info_handler;
 mod openapi;
 mod otlp_api;
+mod prometheus_api;
 mod rate_modulator;
 mod rest;
 mod rest_api_response;
@@ -45,6 +48,7 @@ mod search_api;
 pub(crate) mod simple_list;
 pub mod tcp_listener;
 mod template_api;
+mod tenant_quotas;
 mod ui_handler;
 
 use std::collections::{HashMap, HashSet};
@@ -71,6 +75,7 @@ use quickwit_common::pubsub::{EventBroker, EventSubscriptionHandle};
 use quickwit_common::rate_limiter::RateLimiterSettings;
 use quickwit_common::retry::RetryParams;
 use quickwit_common::runtimes::RuntimesConfig;
+use quickwit_common::tenant_quotas::TenantQuotas;
 use quickwit_common::tower::{
     BalanceChannel, BoxFutureInfaillible, BufferLayer, Change, CircuitBreakerEvaluator,
     ConstantRate, EstimateRateLayer, EventListenerLayer, GrpcMetricsLayer, LoadShedLayer,
@@ -113,7 +118,7 @@ use quickwit_proto::search::ReportSplitsRequest;
 use quickwit_proto::types::NodeId;
 use quickwit_search::{
     create_search_client_from_channel, start_searcher_service, SearchJobPlacer, SearchService,
-    SearchServiceClient, SearcherContext, SearcherPool,
+    SearchServiceClient, SearcherContext, SearcherPool, SearcherZonePool, SlowQueryLog,
 };
 use quickwit_storage::{SplitCache, StorageResolver};
 use tcp_listener::TcpListenerResolver;
@@ -124,12 +129,18 @@ use tracing::{debug, error, info, warn};
 use warp::{Filter, Rejection};
 
 pub use crate::build_info::{BuildInfo, RuntimeInfo};
+pub use crate::elasticsearch_api::{ReindexQueryParams, ReindexResponse};
 pub use crate::index_api::{ListSplitsQueryParams, ListSplitsResponse};
 pub use crate::metrics::SERVE_METRICS;
 use crate::rate_modulator::RateModulator;
 #[cfg(test)]
 use crate::rest::recover_fn;
-pub use crate::search_api::{search_request_from_api_request, SearchRequestQueryString, SortBy};
+pub use crate::search_api::{
+    search_request_from_api_request, SearchRequestQueryString, SortBy, TailRequestQueryString,
+};
+use crate::tenant_quotas::{
+    build_tenant_quotas, setup_storage_usage_listener, spawn_storage_usage_refresh_task,
+};
 
 const READINESS_REPORTING_INTERVAL: Duration = if cfg!(any(test, feature = "testsuite")) {
     Duration::from_millis(25)
@@ -209,6 +220,8 @@ struct QuickwitServices {
     /// notifications. Otherwise, the subscriptions are dropped.
     _local_shards_update_listener_handle_opt: Option<ListenerHandle>,
     _report_splits_subscription_handle_opt: Option<EventSubscriptionHandle>,
+    /// Keeps the storage usage of the tenant quotas up to date.
+    _storage_usage_listener_handle_opt: Option<ListenerHandle>,
 }
 
 impl QuickwitServices {
@@ -528,12 +541,25 @@ pub async fn serve_quickwit(
     );
 
     // Setup ingest service v2.
+    let tenant_quotas = build_tenant_quotas(&node_config);
+
+    if node_config.is_service_enabled(QuickwitService::ControlPlane) {
+        spawn_storage_usage_refresh_task(
+            tenant_quotas.clone(),
+            metastore_through_control_plane.clone(),
+            cluster.clone(),
+        );
+    }
+    let storage_usage_listener_handle_opt =
+        setup_storage_usage_listener(tenant_quotas.clone(), &cluster).await;
+
     let (ingest_router, ingest_router_service, ingester_opt) = setup_ingest_v2(
         &node_config,
         &cluster,
         &event_broker,
         control_plane_client.clone(),
-        ingester_pool,
+        ingester_pool.clone(),
+        tenant_quotas.clone(),
     )
     .await
     .context("failed to start ingest v2 service")?;
@@ -581,6 +607,33 @@ pub async fn serve_quickwit(
             }
         }
     }
+    let slow_query_log_opt = match &node_config.searcher_config.slow_query_log {
+        Some(slow_query_log_config)
+            if node_config.is_service_enabled(QuickwitService::Searcher) =>
+        {
+            let slow_query_log_index_config = SlowQueryLog::index_config(
+                slow_query_log_config,
+                &node_config.default_index_root_uri,
+            )
+            .context("failed to load slow query log index config")?;
+
+            match index_manager
+                .create_index(slow_query_log_index_config, false)
+                .await
+            {
+                Ok(_)
+                | Err(IndexServiceError::Metastore(MetastoreError::AlreadyExists(
+                    EntityKind::Index { .. },
+                ))) => {}
+                Err(error) => bail!("failed to create slow query log index: {error}"),
+            };
+            Some(SlowQueryLog::new(
+                slow_query_log_config.clone(),
+                ingest_router_service.clone(),
+            ))
+        }
+        _ => None,
+    };
     let split_cache_root_directory: PathBuf =
         node_config.data_dir_path.join("searcher-split-cache");
     let split_cache_opt: Option<Arc<SplitCache>> =
@@ -596,16 +649,21 @@ pub async fn serve_quickwit(
             None
         };
 
-    let searcher_context = Arc::new(SearcherContext::new(
-        node_config.searcher_config.clone(),
-        split_cache_opt,
-    ));
+    let mut searcher_context =
+        SearcherContext::new(node_config.searcher_config.clone(), split_cache_opt)
+            .with_tenant_quotas(tenant_quotas);
+
+    if let Some(slow_query_log) = slow_query_log_opt {
+        searcher_context = searcher_context.with_slow_query_log(slow_query_log);
+    }
+    let searcher_context = Arc::new(searcher_context);
 
     let (search_job_placer, search_service) = setup_searcher(
         &node_config,
         cluster.change_stream(),
         metastore_through_control_plane.clone(),
         storage_resolver.clone(),
+        ingester_pool.clone(),
         searcher_context,
     )
     .await
@@ -639,6 +697,7 @@ pub async fn serve_quickwit(
             metastore_through_control_plane.clone(),
             search_job_placer,
             storage_resolver.clone(),
+            Some(ingest_router_service.clone()),
             event_broker.clone(),
             !get_bool_from_env(DISABLE_DELETE_TASK_SERVICE_ENV_KEY, false),
         )
@@ -690,6 +749,7 @@ pub async fn serve_quickwit(
         control_plane_server_opt,
         control_plane_client,
         _local_shards_update_listener_handle_opt: local_shards_update_listener_handle_opt,
+        _storage_usage_listener_handle_opt: storage_usage_listener_handle_opt,
         _report_splits_subscription_handle_opt: report_splits_subscription_handle_opt,
         index_manager,
         indexing_service_opt,
@@ -720,6 +780,7 @@ pub async fn serve_quickwit(
     let grpc_server = grpc::start_grpc_server(
         tcp_listener_resolver.resolve(grpc_listen_addr).await?,
         grpc_config.max_message_size,
+        grpc_config.tls.clone(),
         quickwit_services.clone(),
         grpc_readiness_trigger,
         grpc_shutdown_signal,
@@ -842,6 +903,7 @@ fn ingester_service_layer_stack(
         .stack_truncate_shards_layer(quickwit_common::tower::OneTaskPerCallLayer)
         .stack_close_shards_layer(quickwit_common::tower::OneTaskPerCallLayer)
         .stack_decommission_layer(quickwit_common::tower::OneTaskPerCallLayer)
+        .stack_search_shards_layer(quickwit_common::tower::OneTaskPerCallLayer)
 }
 
 async fn setup_ingest_v2(
@@ -850,6 +912,7 @@ async fn setup_ingest_v2(
     event_broker: &EventBroker,
     control_plane: ControlPlaneServiceClient,
     ingester_pool: IngesterPool,
+    tenant_quotas: TenantQuotas,
 ) -> anyhow::Result<(IngestRouter, IngestRouterServiceClient, Option<Ingester>)> {
     // Instantiate ingest router.
     let self_node_id: NodeId = cluster.self_node_id().into();
@@ -868,7 +931,8 @@ async fn setup_ingest_v2(
         ingester_pool.clone(),
         replication_factor,
         event_broker.clone(),
-    );
+    )
+    .with_tenant_quotas(tenant_quotas);
     ingest_router.subscribe();
 
     let ingest_router_service = IngestRouterServiceClient::tower()
@@ -981,14 +1045,22 @@ async fn setup_searcher(
     cluster_change_stream: ClusterChangeStream,
     metastore: MetastoreServiceClient,
     storage_resolver: StorageResolver,
+    ingester_pool: IngesterPool,
     searcher_context: Arc<SearcherContext>,
 ) -> anyhow::Result<(SearchJobPlacer, Arc<dyn SearchService>)> {
     let searcher_pool = SearcherPool::default();
-    let search_job_placer = SearchJobPlacer::new(searcher_pool.clone());
+    let searcher_zone_pool = SearcherZonePool::default();
+    let mut search_job_placer = SearchJobPlacer::new(searcher_pool.clone());
+
+    if let Some(availability_zone) = &node_config.availability_zone_opt {
+        search_job_placer = search_job_placer
+            .with_availability_zone(availability_zone.clone(), searcher_zone_pool.clone());
+    }
     let search_service = start_searcher_service(
         metastore,
         storage_resolver,
         search_job_placer.clone(),
+        ingester_pool,
         searcher_context,
     )
     .await?;
@@ -997,6 +1069,7 @@ async fn setup_searcher(
     let request_timeout = node_config.searcher_config.request_timeout();
     let searcher_change_stream = cluster_change_stream.filter_map(move |cluster_change| {
         let search_service_clone = search_service_clone.clone();
+        let searcher_zone_pool = searcher_zone_pool.clone();
         Box::pin(async move {
             match cluster_change {
                 ClusterChange::Add(node) if node.is_searcher() => {
@@ -1009,6 +1082,10 @@ async fn setup_searcher(
                     );
                     let grpc_addr = node.grpc_advertise_addr();
 
+                    if let Some(availability_zone) = node.availability_zone() {
+                        searcher_zone_pool.insert(grpc_addr, availability_zone.to_string());
+                    }
+
                     if node.is_self_node() {
                         let search_client =
                             SearchServiceClient::from_service(search_service_clone, grpc_addr);
@@ -1031,6 +1108,7 @@ async fn setup_searcher(
                         "removing node `{}` from searcher pool",
                         chitchat_id.node_id,
                     );
+                    searcher_zone_pool.remove(&node.grpc_advertise_addr());
                     Some(Change::Remove(node.grpc_advertise_addr()))
                 }
                 _ => None,
@@ -1121,6 +1199,7 @@ fn setup_indexer_pool(
                     let node_id = node.node_id().to_owned();
                     let indexing_tasks = node.indexing_tasks().to_vec();
                     let indexing_capacity = node.indexing_capacity();
+                    let availability_zone_opt = node.availability_zone().map(str::to_string);
 
                     if node.is_self_node() {
                         // Here, since the service is available locally, we bypass the network stack
@@ -1144,6 +1223,7 @@ fn setup_indexer_pool(
                                 client,
                                 indexing_tasks,
                                 indexing_capacity,
+                                availability_zone_opt,
                             },
                         );
                         Some(change)
@@ -1164,6 +1244,7 @@ fn setup_indexer_pool(
                                 client,
                                 indexing_tasks,
                                 indexing_capacity,
+                                availability_zone_opt,
                             },
                         );
                         Some(change)
@@ -1485,6 +1566,7 @@ mod tests {
             change_stream,
             metastore,
             storage_resolver,
+            IngesterPool::default(),
             searcher_context,
         )
         .await
@@ -0,0 +1,451 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Parser for the subset of LogQL supported by the Loki API.
+//!
+//! Supported queries are log queries, made of a stream selector followed by line filters, and
+//! metric queries computed over log queries:
+//! - `{<matchers>} |= "text" != "text" |~ "regex" !~ "regex"`
+//! - `count_over_time(<log query>[<range>])`
+//! - `rate(<log query>[<range>])`
+//! - `sum [by (<labels>)] (<range aggregation>)`
+//!
+//! Stream labels are fast fields of the searched indexes. Log lines are searched in the default
+//! search fields of the indexes: `|= "text"` matches the lines containing the phrase `text`
+//! rather than the substring `text`.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use quickwit_query::query_ast::{BoolQuery, FullTextMode, FullTextParams, FullTextQuery, QueryAst};
+use quickwit_query::MatchAllOrNone;
+
+use crate::prometheus_api::{
+    label_matchers_bool_query, regex_literal_alternatives, LabelMatcher, MatchOp, Parser,
+    PromqlQuery, RangeFunction,
+};
+
+/// Flag making regexes case-insensitive, commonly added by Grafana. Full-text queries are
+/// already case-insensitive with the default tokenizers.
+const CASE_INSENSITIVE_FLAG: &str = "(?i)";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum LineFilterOp {
+    Contains,
+    NotContains,
+    RegexMatch,
+    RegexNotMatch,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct LineFilter {
+    pub op: LineFilterOp,
+    pub value: String,
+}
+
+impl LineFilter {
+    /// Builds the query AST matching the lines accepted by the filter, ignoring its polarity, or
+    /// returns `None` if the filter matches every line.
+    fn positive_query_ast(&self, line_fields: &[String]) -> Result<Option<QueryAst>, String> {
+        let texts: Vec<String> = match self.op {
+            LineFilterOp::Contains | LineFilterOp::NotContains => {
+                if self.value.is_empty() {
+                    return Ok(None);
+                }
+                vec![self.value.clone()]
+            }
+            LineFilterOp::RegexMatch | LineFilterOp::RegexNotMatch => {
+                let regex = self
+                    .value
+                    .strip_prefix(CASE_INSENSITIVE_FLAG)
+                    .unwrap_or(&self.value);
+                if regex.is_empty() || regex == ".*" {
+                    return Ok(None);
+                }
+                // Line filter regexes are not anchored.
+                let regex = regex.strip_prefix(".*").unwrap_or(regex);
+                let regex = regex.strip_suffix(".*").unwrap_or(regex);
+                let Some(alternatives) = regex_literal_alternatives(regex) else {
+                    return Err(format!(
+                        "unsupported line filter regex `{}`: only `.*` and alternations of \
+                         literals are supported",
+                        self.value
+                    ));
+                };
+                alternatives.into_iter().collect()
+            }
+        };
+        if line_fields.is_empty() {
+            return Err(
+                "line filters require the queried indexes to have default search fields"
+                    .to_string(),
+            );
+        }
+        let mut bool_query = BoolQuery {
+            minimum_should_match: Some(1),
+            ..Default::default()
+        };
+        for text in &texts {
+            for line_field in line_fields {
+                let full_text_query = FullTextQuery {
+                    field: line_field.clone(),
+                    text: text.clone(),
+                    params: FullTextParams {
+                        tokenizer: None,
+                        mode: FullTextMode::PhraseFallbackToIntersection,
+                        zero_terms_query: MatchAllOrNone::MatchAll,
+                    },
+                    lenient: true,
+                };
+                bool_query.should.push(full_text_query.into());
+            }
+        }
+        if bool_query.should.len() == 1 {
+            return Ok(bool_query.should.pop());
+        }
+        Ok(Some(bool_query.into()))
+    }
+}
+
+/// A stream selector followed by line filters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct LogQuery {
+    pub label_matchers: Vec<LabelMatcher>,
+    pub line_filters: Vec<LineFilter>,
+}
+
+impl LogQuery {
+    /// Builds the query AST selecting the documents matched by the log query. Line filters are
+    /// evaluated over `line_fields`.
+    pub fn query_ast(&self, line_fields: &[String]) -> Result<QueryAst, String> {
+        let Some(mut bool_query) = label_matchers_bool_query(&self.label_matchers)? else {
+            return Ok(QueryAst::MatchNone);
+        };
+        for line_filter in &self.line_filters {
+            let query_ast_opt = line_filter.positive_query_ast(line_fields)?;
+
+            match (line_filter.op, query_ast_opt) {
+                (LineFilterOp::Contains | LineFilterOp::RegexMatch, Some(query_ast)) => {
+                    bool_query.filter.push(query_ast)
+                }
+                (LineFilterOp::Contains | LineFilterOp::RegexMatch, None) => {}
+                (LineFilterOp::NotContains | LineFilterOp::RegexNotMatch, Some(query_ast)) => {
+                    bool_query.must_not.push(query_ast)
+                }
+                (LineFilterOp::NotContains | LineFilterOp::RegexNotMatch, None) => {
+                    return Ok(QueryAst::MatchNone)
+                }
+            }
+        }
+        if bool_query.filter.is_empty() && bool_query.must_not.is_empty() {
+            return Ok(QueryAst::MatchAll);
+        }
+        Ok(bool_query.into())
+    }
+
+    /// Returns the labels of the stream made up of the log lines matching the query. Documents
+    /// have no notion of stream, so all the log lines make up a single stream identified by the
+    /// equality matchers of the selector.
+    pub fn stream_labels(&self) -> BTreeMap<String, String> {
+        self.label_matchers
+            .iter()
+            .filter(|label_matcher| {
+                label_matcher.op == MatchOp::Equal && !label_matcher.value.is_empty()
+            })
+            .map(|label_matcher| (label_matcher.name.clone(), label_matcher.value.clone()))
+            .collect()
+    }
+}
+
+/// A range aggregation computed over a log query, optionally wrapped into a `sum` aggregation.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct MetricQuery {
+    pub function: RangeFunction,
+    pub log_query: LogQuery,
+    pub range: Duration,
+    pub sum_by: Option<Vec<String>>,
+}
+
+impl MetricQuery {
+    /// Returns the equivalent PromQL query, which selector ignores the line filters.
+    pub fn promql_query(&self) -> PromqlQuery {
+        PromqlQuery {
+            function: self.function,
+            metric_name: None,
+            label_matchers: self.log_query.label_matchers.clone(),
+            range: self.range,
+            sum_by: self.sum_by.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum LogqlQuery {
+    Log(LogQuery),
+    Metric(MetricQuery),
+}
+
+/// Parses a LogQL query.
+pub(super) fn parse_logql(query: &str) -> Result<LogqlQuery, String> {
+    let mut parser = Parser::new(query);
+
+    let logql_query = if parser.peek_char() == Some('{') {
+        LogqlQuery::Log(parse_log_query(&mut parser)?)
+    } else {
+        let function_name = parser
+            .parse_identifier()
+            .ok_or_else(|| parser.error("expected a stream selector or a function name"))?;
+        LogqlQuery::Metric(parse_metric_query(&mut parser, function_name)?)
+    };
+    parser.expect_end()?;
+    Ok(logql_query)
+}
+
+/// Parses a LogQL log query, which must not contain any line filter, e.g. the selectors of the
+/// `series` endpoint.
+pub(super) fn parse_stream_selector(selector: &str) -> Result<Vec<LabelMatcher>, String> {
+    let mut parser = Parser::new(selector);
+    let log_query = parse_log_query(&mut parser)?;
+    parser.expect_end()?;
+
+    if !log_query.line_filters.is_empty() {
+        return Err(format!(
+            "expected a stream selector, got a log query with line filters `{selector}`"
+        ));
+    }
+    Ok(log_query.label_matchers)
+}
+
+fn parse_log_query(parser: &mut Parser) -> Result<LogQuery, String> {
+    parser.expect_char('{')?;
+    let label_matchers = parser.parse_label_matchers()?;
+
+    if label_matchers.is_empty() {
+        return Err("stream selectors must contain at least one label matcher".to_string());
+    }
+    let mut line_filters = Vec::new();
+
+    loop {
+        let op = if parser.consume_str("|=") {
+            LineFilterOp::Contains
+        } else if parser.consume_str("!=") {
+            LineFilterOp::NotContains
+        } else if parser.consume_str("|~") {
+            LineFilterOp::RegexMatch
+        } else if parser.consume_str("!~") {
+            LineFilterOp::RegexNotMatch
+        } else if parser.peek_char() == Some('|') {
+            return Err(parser.error("only line filters are supported in log pipelines"));
+        } else {
+            break;
+        };
+        let value = parser.parse_string()?;
+        line_filters.push(LineFilter { op, value });
+    }
+    Ok(LogQuery {
+        label_matchers,
+        line_filters,
+    })
+}
+
+fn parse_metric_query(parser: &mut Parser, function_name: &str) -> Result<MetricQuery, String> {
+    if function_name != "sum" {
+        return parse_range_aggregation(parser, function_name);
+    }
+    let mut sum_by = parser.parse_grouping()?;
+    parser.expect_char('(')?;
+    let function_name = parser
+        .parse_identifier()
+        .ok_or_else(|| parser.error("expected a function name"))?;
+    let mut metric_query = parse_range_aggregation(parser, function_name)?;
+    parser.expect_char(')')?;
+
+    if sum_by.is_none() {
+        sum_by = parser.parse_grouping()?;
+    }
+    metric_query.sum_by = Some(sum_by.unwrap_or_default());
+    Ok(metric_query)
+}
+
+fn parse_range_aggregation(
+    parser: &mut Parser,
+    function_name: &str,
+) -> Result<MetricQuery, String> {
+    let function = match function_name {
+        "count_over_time" => RangeFunction::CountOverTime,
+        "rate" => RangeFunction::Rate,
+        _ => {
+            return Err(format!(
+                "unsupported function `{function_name}`: supported functions are \
+                 `count_over_time`, `rate` and `sum`"
+            ))
+        }
+    };
+    parser.expect_char('(')?;
+    let log_query = parse_log_query(parser)?;
+    parser.expect_char('[')?;
+    let range = parser.parse_duration()?;
+    parser.expect_char(']')?;
+    parser.expect_char(')')?;
+
+    if range.is_zero() {
+        return Err("range must be strictly positive".to_string());
+    }
+    Ok(MetricQuery {
+        function,
+        log_query,
+        range,
+        sum_by: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_query::query_ast::TermQuery;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_logql_log_query() {
+        let LogqlQuery::Log(log_query) =
+            parse_logql(r#"{app="api", env!~"dev|test"} |= "error" != `timeout` |~ "(?i)a|b""#)
+                .unwrap()
+        else {
+            panic!("expected a log query");
+        };
+        assert_eq!(
+            log_query.label_matchers,
+            vec![
+                LabelMatcher {
+                    name: "app".to_string(),
+                    op: MatchOp::Equal,
+                    value: "api".to_string(),
+                },
+                LabelMatcher {
+                    name: "env".to_string(),
+                    op: MatchOp::RegexNotMatch,
+                    value: "dev|test".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            log_query.line_filters,
+            vec![
+                LineFilter {
+                    op: LineFilterOp::Contains,
+                    value: "error".to_string(),
+                },
+                LineFilter {
+                    op: LineFilterOp::NotContains,
+                    value: "timeout".to_string(),
+                },
+                LineFilter {
+                    op: LineFilterOp::RegexMatch,
+                    value: "(?i)a|b".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_logql_metric_query() {
+        let LogqlQuery::Metric(metric_query) =
+            parse_logql(r#"sum by (level) (count_over_time({app="api"} |= "error" [5m]))"#)
+                .unwrap()
+        else {
+            panic!("expected a metric query");
+        };
+        assert_eq!(metric_query.function, RangeFunction::CountOverTime);
+        assert_eq!(metric_query.range, Duration::from_secs(300));
+        assert_eq!(metric_query.sum_by, Some(vec!["level".to_string()]));
+        assert_eq!(metric_query.log_query.line_filters.len(), 1);
+
+        let promql_query = metric_query.promql_query();
+        assert_eq!(promql_query.metric_name, None);
+        assert_eq!(promql_query.label_matchers.len(), 1);
+
+        let LogqlQuery::Metric(metric_query) = parse_logql(r#"rate({app="api"}[1m])"#).unwrap()
+        else {
+            panic!("expected a metric query");
+        };
+        assert_eq!(metric_query.function, RangeFunction::Rate);
+        assert_eq!(metric_query.sum_by, None);
+    }
+
+    #[test]
+    fn test_parse_logql_errors() {
+        parse_logql("{}").unwrap_err();
+        parse_logql(r#"{app="api"} | json"#).unwrap_err();
+        parse_logql(r#"bytes_over_time({app="api"}[1m])"#).unwrap_err();
+        parse_logql(r#"count_over_time({app="api"})"#).unwrap_err();
+        parse_logql(r#"{app="api"} |= error"#).unwrap_err();
+        parse_stream_selector(r#"{app="api"} |= "error""#).unwrap_err();
+        assert_eq!(parse_stream_selector(r#"{app="api"}"#).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_log_query_ast() {
+        let LogqlQuery::Log(log_query) =
+            parse_logql(r#"{app="api"} |= "connection refused" !~ "debug|trace""#).unwrap()
+        else {
+            panic!("expected a log query");
+        };
+        let line_fields = vec!["body".to_string()];
+        let QueryAst::Bool(bool_query) = log_query.query_ast(&line_fields).unwrap() else {
+            panic!("expected a boolean query");
+        };
+        assert_eq!(bool_query.filter.len(), 2);
+        assert_eq!(
+            bool_query.filter[0],
+            QueryAst::Term(TermQuery {
+                field: "app".to_string(),
+                value: "api".to_string(),
+            })
+        );
+        let QueryAst::FullText(full_text_query) = &bool_query.filter[1] else {
+            panic!("expected a full-text query");
+        };
+        assert_eq!(full_text_query.field, "body");
+        assert_eq!(full_text_query.text, "connection refused");
+
+        assert_eq!(bool_query.must_not.len(), 1);
+        let QueryAst::Bool(regex_bool_query) = &bool_query.must_not[0] else {
+            panic!("expected a boolean query");
+        };
+        assert_eq!(regex_bool_query.should.len(), 2);
+
+        // Line filters require line fields.
+        log_query.query_ast(&[]).unwrap_err();
+
+        let LogqlQuery::Log(log_query) = parse_logql(r#"{app=~".*"} |~ ".*""#).unwrap() else {
+            panic!("expected a log query");
+        };
+        assert_eq!(log_query.query_ast(&[]).unwrap(), QueryAst::MatchAll);
+
+        let LogqlQuery::Log(log_query) = parse_logql(r#"{app="api"} !~ ".*""#).unwrap() else {
+            panic!("expected a log query");
+        };
+        assert_eq!(log_query.query_ast(&[]).unwrap(), QueryAst::MatchNone);
+
+        let LogqlQuery::Log(log_query) = parse_logql(r#"{app="api"} |~ "err.+""#).unwrap() else {
+            panic!("expected a log query");
+        };
+        log_query.query_ast(&line_fields).unwrap_err();
+    }
+}
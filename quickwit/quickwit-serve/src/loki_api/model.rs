@@ -0,0 +1,168 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+
+use crate::prometheus_api::{MatrixSeries, PrometheusError};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    #[default]
+    Backward,
+    Forward,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct QueryRangeParams {
+    pub query: String,
+    #[serde(default)]
+    pub start: Option<String>,
+    #[serde(default)]
+    pub end: Option<String>,
+    #[serde(default)]
+    pub limit: Option<u64>,
+    #[serde(default)]
+    pub step: Option<String>,
+    #[serde(default)]
+    pub direction: Direction,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LabelsParams {
+    #[serde(default)]
+    pub start: Option<String>,
+    #[serde(default)]
+    pub end: Option<String>,
+    /// Stream selector restricting the documents the label values are collected from.
+    #[serde(default)]
+    pub query: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SeriesParams {
+    #[serde(rename = "match", default)]
+    pub matches: Vec<String>,
+    #[serde(default)]
+    pub start: Option<String>,
+    #[serde(default)]
+    pub end: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TailParams {
+    pub query: String,
+    #[serde(default)]
+    pub start: Option<String>,
+    #[serde(default)]
+    pub limit: Option<u64>,
+}
+
+/// Log lines of a stream, as `(timestamp in nanoseconds, line)` pairs. Loki serializes
+/// timestamps as strings.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LokiStream {
+    pub stream: BTreeMap<String, String>,
+    pub values: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "resultType", content = "result", rename_all = "snake_case")]
+pub enum QueryRangeResult {
+    Streams(Vec<LokiStream>),
+    Matrix(Vec<MatrixSeries>),
+}
+
+/// Message sent over the websocket of the `tail` endpoint.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TailResponse {
+    pub streams: Vec<LokiStream>,
+}
+
+/// Parses a Loki timestamp, either a Unix timestamp in nanoseconds, a Unix timestamp in
+/// seconds with a fractional part, or an RFC 3339 date, into a Unix timestamp in nanoseconds.
+pub(super) fn parse_timestamp_nanos(timestamp_str: &str) -> Result<i64, PrometheusError> {
+    if let Ok(timestamp_nanos) = timestamp_str.parse::<i64>() {
+        return Ok(timestamp_nanos);
+    }
+    if let Ok(timestamp_secs) = timestamp_str.parse::<f64>() {
+        if timestamp_secs.is_finite() {
+            return Ok((timestamp_secs * 1_000_000_000.0) as i64);
+        }
+    }
+    let system_time = humantime::parse_rfc3339_weak(timestamp_str).map_err(|_| {
+        PrometheusError::bad_data(format!(
+            "invalid timestamp `{timestamp_str}`: expected an RFC 3339 date or a Unix timestamp \
+             in nanoseconds"
+        ))
+    })?;
+    let timestamp_nanos = match system_time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_nanos() as i64,
+        Err(error) => -(error.duration().as_nanos() as i64),
+    };
+    Ok(timestamp_nanos)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_timestamp_nanos() {
+        assert_eq!(
+            parse_timestamp_nanos("1700000000123456789").unwrap(),
+            1_700_000_000_123_456_789
+        );
+        assert_eq!(
+            parse_timestamp_nanos("1700000000.5").unwrap(),
+            1_700_000_000_500_000_000
+        );
+        assert_eq!(
+            parse_timestamp_nanos("2023-11-14T22:13:20Z").unwrap(),
+            1_700_000_000_000_000_000
+        );
+        parse_timestamp_nanos("yesterday").unwrap_err();
+    }
+
+    #[test]
+    fn test_serialize_query_range_result() {
+        let query_range_result = QueryRangeResult::Streams(vec![LokiStream {
+            stream: BTreeMap::from([("app".to_string(), "api".to_string())]),
+            values: vec![(
+                "1700000000000000000".to_string(),
+                r#"{"body":"hello"}"#.to_string(),
+            )],
+        }]);
+        assert_eq!(
+            serde_json::to_value(query_range_result).unwrap(),
+            json!({
+                "resultType": "streams",
+                "result": [{
+                    "stream": { "app": "api" },
+                    "values": [["1700000000000000000", r#"{"body":"hello"}"#]],
+                }]
+            })
+        );
+    }
+}
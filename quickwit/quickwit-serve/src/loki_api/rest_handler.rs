@@ -0,0 +1,979 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use hyper::StatusCode;
+use quickwit_proto::metastore::MetastoreServiceClient;
+use quickwit_proto::search::{
+    CountHits, Hit, SearchRequest, SortDatetimeFormat, SortField, SortOrder,
+};
+use quickwit_proto::types::IndexUid;
+use quickwit_query::query_ast::QueryAst;
+use quickwit_search::{resolve_index_patterns, SearchService};
+use serde_json::{json, Value as JsonValue};
+use tracing::warn;
+use warp::ws::{Message, WebSocket, Ws};
+use warp::{Filter, Rejection, Reply};
+
+use super::logql::{parse_logql, parse_stream_selector, LogQuery, LogqlQuery, MetricQuery};
+use super::model::{
+    parse_timestamp_nanos, Direction, LabelsParams, LokiStream, QueryRangeParams, QueryRangeResult,
+    SeriesParams, TailParams, TailResponse,
+};
+use crate::prometheus_api::{
+    list_label_values, list_labels, parse_duration, shared_timestamp_field, EvaluationRange,
+    MatrixSeries, PrometheusError, PrometheusErrorType, PrometheusResponseBody, QueryEvaluation,
+    MAX_NUM_GROUPS_PER_LABEL,
+};
+use crate::rest::recover_fn;
+use crate::rest_api_response::RestApiResponse;
+use crate::search_api::{
+    extract_index_id_patterns, hit_timestamp_nanos, new_tail_poll_interval, now_nanos, SearchTail,
+    TailDocument,
+};
+use crate::{with_arg, BodyFormat};
+
+/// Time range of the queries that do not specify a start timestamp.
+const DEFAULT_LOOKBACK: Duration = Duration::from_secs(3_600);
+
+const DEFAULT_LIMIT: u64 = 100;
+
+const MAX_LIMIT: u64 = 5_000;
+
+/// Number of points of the series returned by metric queries that do not specify a step.
+const DEFAULT_NUM_POINTS: i64 = 250;
+
+const NANOS_PER_SEC: i64 = 1_000_000_000;
+
+#[derive(utoipa::OpenApi)]
+#[openapi(paths(
+    loki_query_range_handler,
+    loki_labels_handler,
+    loki_label_values_handler,
+    loki_series_handler,
+    loki_tail_handler
+))]
+pub(crate) struct LokiApi;
+
+/// Setup Loki API handlers
+///
+/// This is where all Loki handlers
+/// should be registered.
+/// Grafana can use `<quickwit-url>/api/v1/<index-id>/loki` as a Loki data source.
+pub(crate) fn loki_api_handlers(
+    search_service: Arc<dyn SearchService>,
+    metastore: MetastoreServiceClient,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    loki_query_range_handler(search_service.clone(), metastore.clone())
+        .or(loki_labels_handler(search_service.clone()))
+        .or(loki_label_values_handler(search_service.clone()))
+        .or(loki_series_handler(search_service.clone()))
+        .or(loki_tail_handler(search_service, metastore))
+        .recover(recover_fn)
+        .boxed()
+}
+
+fn loki_api_path_filter() -> impl Filter<Extract = (Vec<String>,), Error = Rejection> + Clone {
+    warp::path!(String / "loki" / "api" / "v1" / ..).and_then(extract_index_id_patterns)
+}
+
+/// Loki clients send repeated parameters with brackets, e.g. `match[]`, which are usually
+/// percent-encoded.
+fn loki_params_filter<T: serde::de::DeserializeOwned + Send + 'static>(
+) -> impl Filter<Extract = (T,), Error = Rejection> + Clone {
+    serde_qs::warp::query(serde_qs::Config::new(5, false))
+}
+
+#[utoipa::path(
+    get,
+    tag = "Loki",
+    path = "/{index-id}/loki/api/v1/query_range",
+    responses(
+        (status = 200, description = "Successfully evaluated the LogQL query.")
+    ),
+    params(
+        ("index-id" = String, Path, description = "The index ID patterns of the indexes to query."),
+        ("query" = String, Query, description = "LogQL query."),
+        ("start" = Option<String>, Query, description = "Start timestamp, RFC 3339 or Unix timestamp in nanoseconds. Defaults to one hour before `end`."),
+        ("end" = Option<String>, Query, description = "End timestamp, RFC 3339 or Unix timestamp in nanoseconds. Defaults to now."),
+        ("limit" = Option<u64>, Query, description = "Maximum number of log lines returned by log queries. Defaults to 100."),
+        ("step" = Option<String>, Query, description = "Resolution step of metric queries, duration or number of seconds."),
+        ("direction" = Option<String>, Query, description = "Sort order of log lines, `forward` or `backward`. Defaults to `backward`."),
+    )
+)]
+pub fn loki_query_range_handler(
+    search_service: Arc<dyn SearchService>,
+    metastore: MetastoreServiceClient,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    loki_api_path_filter()
+        .and(warp::path!("query_range"))
+        .and(warp::get())
+        .and(loki_params_filter::<QueryRangeParams>())
+        .and(with_arg(search_service))
+        .and(with_arg(metastore))
+        .then(loki_query_range)
+        .map(make_loki_api_response)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Loki",
+    path = "/{index-id}/loki/api/v1/labels",
+    responses(
+        (status = 200, description = "Successfully fetched label names.")
+    ),
+    params(
+        ("index-id" = String, Path, description = "The index ID patterns of the indexes to query."),
+        ("start" = Option<String>, Query, description = "Start timestamp, RFC 3339 or Unix timestamp in nanoseconds."),
+        ("end" = Option<String>, Query, description = "End timestamp, RFC 3339 or Unix timestamp in nanoseconds."),
+    )
+)]
+pub fn loki_labels_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    loki_api_path_filter()
+        .and(warp::path!("labels"))
+        .and(warp::get())
+        .and(loki_params_filter::<LabelsParams>())
+        .and(with_arg(search_service))
+        .then(loki_labels)
+        .map(make_loki_api_response)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Loki",
+    path = "/{index-id}/loki/api/v1/label/{label}/values",
+    responses(
+        (status = 200, description = "Successfully fetched label values.")
+    ),
+    params(
+        ("index-id" = String, Path, description = "The index ID patterns of the indexes to query."),
+        ("label" = String, Path, description = "The label name."),
+        ("start" = Option<String>, Query, description = "Start timestamp, RFC 3339 or Unix timestamp in nanoseconds."),
+        ("end" = Option<String>, Query, description = "End timestamp, RFC 3339 or Unix timestamp in nanoseconds."),
+        ("query" = Option<String>, Query, description = "Stream selector restricting the documents the values are collected from."),
+    )
+)]
+pub fn loki_label_values_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    loki_api_path_filter()
+        .and(warp::path!("label" / String / "values"))
+        .and(warp::get())
+        .and(loki_params_filter::<LabelsParams>())
+        .and(with_arg(search_service))
+        .then(loki_label_values)
+        .map(make_loki_api_response)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Loki",
+    path = "/{index-id}/loki/api/v1/series",
+    responses(
+        (status = 200, description = "Successfully fetched series.")
+    ),
+    params(
+        ("index-id" = String, Path, description = "The index ID patterns of the indexes to query."),
+        ("match[]" = Vec<String>, Query, description = "Stream selectors of the series."),
+        ("start" = Option<String>, Query, description = "Start timestamp, RFC 3339 or Unix timestamp in nanoseconds."),
+        ("end" = Option<String>, Query, description = "End timestamp, RFC 3339 or Unix timestamp in nanoseconds."),
+    )
+)]
+pub fn loki_series_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    loki_api_path_filter()
+        .and(warp::path!("series"))
+        .and(warp::get())
+        .and(loki_params_filter::<SeriesParams>())
+        .and(with_arg(search_service))
+        .then(loki_series)
+        .map(make_loki_api_response)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Loki",
+    path = "/{index-id}/loki/api/v1/tail",
+    responses(
+        (status = 101, description = "Switched to the websocket protocol. New log lines are streamed as they are indexed.")
+    ),
+    params(
+        ("index-id" = String, Path, description = "The index ID patterns of the indexes to query."),
+        ("query" = String, Query, description = "LogQL log query."),
+        ("start" = Option<String>, Query, description = "Start timestamp, RFC 3339 or Unix timestamp in nanoseconds. Defaults to one hour ago."),
+        ("limit" = Option<u64>, Query, description = "Maximum number of log lines sent per message. Defaults to 100."),
+    )
+)]
+pub fn loki_tail_handler(
+    search_service: Arc<dyn SearchService>,
+    metastore: MetastoreServiceClient,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    loki_api_path_filter()
+        .and(warp::path!("tail"))
+        .and(warp::get())
+        .and(loki_params_filter::<TailParams>())
+        .and(warp::ws())
+        .and(with_arg(search_service))
+        .and(with_arg(metastore))
+        .then(loki_tail)
+}
+
+/// Fields of the queried indexes used to search log lines.
+struct LogFields {
+    index_uids: Vec<IndexUid>,
+    timestamp_field: String,
+    /// Fields the line filters are evaluated over, i.e. the default search fields of the indexes.
+    line_fields: Vec<String>,
+}
+
+async fn resolve_log_fields(
+    index_id_patterns: &[String],
+    mut metastore: MetastoreServiceClient,
+) -> Result<LogFields, PrometheusError> {
+    let indexes_metadata = resolve_index_patterns(index_id_patterns, &mut metastore).await?;
+    let timestamp_field = shared_timestamp_field(&indexes_metadata)?;
+    let line_fields: BTreeSet<String> = indexes_metadata
+        .iter()
+        .flat_map(|index_metadata| {
+            index_metadata
+                .index_config
+                .search_settings
+                .default_search_fields
+                .iter()
+                .cloned()
+        })
+        .collect();
+    let index_uids = indexes_metadata
+        .into_iter()
+        .map(|index_metadata| index_metadata.index_uid)
+        .collect();
+    Ok(LogFields {
+        index_uids,
+        timestamp_field,
+        line_fields: line_fields.into_iter().collect(),
+    })
+}
+
+/// Parses the optional start and end timestamps of a request into timestamps in seconds, as
+/// expected by search requests.
+fn parse_time_bounds_secs(
+    start: Option<&str>,
+    end: Option<&str>,
+) -> Result<(Option<i64>, Option<i64>), PrometheusError> {
+    let start_timestamp = start
+        .map(parse_timestamp_nanos)
+        .transpose()?
+        .map(|start_nanos| start_nanos.div_euclid(NANOS_PER_SEC));
+    let end_timestamp = end
+        .map(parse_timestamp_nanos)
+        .transpose()?
+        .map(|end_nanos| ceil_div(end_nanos, NANOS_PER_SEC));
+    Ok((start_timestamp, end_timestamp))
+}
+
+fn ceil_div(value: i64, divisor: i64) -> i64 {
+    let quotient = value.div_euclid(divisor);
+    if value.rem_euclid(divisor) == 0 {
+        quotient
+    } else {
+        quotient + 1
+    }
+}
+
+fn timestamp_sort_field(timestamp_field: &str, sort_order: SortOrder) -> SortField {
+    SortField {
+        field_name: timestamp_field.to_string(),
+        sort_order: sort_order as i32,
+        sort_datetime_format: Some(SortDatetimeFormat::UnixTimestampNanos as i32),
+    }
+}
+
+async fn loki_query_range(
+    index_id_patterns: Vec<String>,
+    params: QueryRangeParams,
+    search_service: Arc<dyn SearchService>,
+    metastore: MetastoreServiceClient,
+) -> Result<PrometheusResponseBody<QueryRangeResult>, PrometheusError> {
+    let logql_query = parse_logql(&params.query).map_err(PrometheusError::bad_data)?;
+    let end_nanos = params
+        .end
+        .as_deref()
+        .map(parse_timestamp_nanos)
+        .transpose()?
+        .unwrap_or_else(now_nanos);
+    let start_nanos = params
+        .start
+        .as_deref()
+        .map(parse_timestamp_nanos)
+        .transpose()?
+        .unwrap_or(end_nanos - DEFAULT_LOOKBACK.as_nanos() as i64);
+
+    if end_nanos < start_nanos {
+        return Err(PrometheusError::bad_data(
+            "end timestamp must not be before start timestamp".to_string(),
+        ));
+    }
+    let log_fields = resolve_log_fields(&index_id_patterns, metastore).await?;
+
+    let query_range_result = match logql_query {
+        LogqlQuery::Log(log_query) => {
+            let limit = params.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+            let streams = search_log_lines(
+                index_id_patterns,
+                &log_query,
+                &log_fields,
+                (start_nanos, end_nanos),
+                limit,
+                params.direction,
+                search_service,
+            )
+            .await?;
+            QueryRangeResult::Streams(streams)
+        }
+        LogqlQuery::Metric(metric_query) => {
+            let step_secs = if let Some(step) = &params.step {
+                parse_duration(step)
+                    .map_err(PrometheusError::bad_data)?
+                    .as_secs() as i64
+            } else {
+                ceil_div(end_nanos - start_nanos, DEFAULT_NUM_POINTS * NANOS_PER_SEC).max(1)
+            };
+            let evaluation_range = EvaluationRange {
+                start_secs: start_nanos.div_euclid(NANOS_PER_SEC),
+                end_secs: end_nanos.div_euclid(NANOS_PER_SEC),
+                step_secs,
+            };
+            let matrix = evaluate_metric_query(
+                index_id_patterns,
+                &metric_query,
+                &log_fields,
+                evaluation_range,
+                search_service,
+            )
+            .await?;
+            QueryRangeResult::Matrix(matrix)
+        }
+    };
+    Ok(PrometheusResponseBody::success(query_range_result))
+}
+
+async fn search_log_lines(
+    index_id_patterns: Vec<String>,
+    log_query: &LogQuery,
+    log_fields: &LogFields,
+    (start_nanos, end_nanos): (i64, i64),
+    limit: u64,
+    direction: Direction,
+    search_service: Arc<dyn SearchService>,
+) -> Result<Vec<LokiStream>, PrometheusError> {
+    let query_ast = log_query
+        .query_ast(&log_fields.line_fields)
+        .map_err(PrometheusError::bad_data)?;
+    let sort_order = match direction {
+        Direction::Backward => SortOrder::Desc,
+        Direction::Forward => SortOrder::Asc,
+    };
+    let search_request = SearchRequest {
+        index_id_patterns,
+        query_ast: serde_json::to_string(&query_ast).expect("failed to serialize query AST"),
+        start_timestamp: Some(start_nanos.div_euclid(NANOS_PER_SEC)),
+        end_timestamp: Some(ceil_div(end_nanos, NANOS_PER_SEC)),
+        max_hits: limit,
+        sort_fields: vec![timestamp_sort_field(
+            &log_fields.timestamp_field,
+            sort_order,
+        )],
+        count_hits: CountHits::Underestimate.into(),
+        ..Default::default()
+    };
+    let search_response = search_service.root_search(search_request).await?;
+
+    let values: Vec<(String, String)> = search_response
+        .hits
+        .into_iter()
+        .filter_map(|hit| {
+            let timestamp_nanos = hit_timestamp_nanos(&hit)?;
+            Some((timestamp_nanos.to_string(), hit.json))
+        })
+        .collect();
+    if values.is_empty() {
+        return Ok(Vec::new());
+    }
+    let stream = log_query.stream_labels();
+    Ok(vec![LokiStream { stream, values }])
+}
+
+async fn evaluate_metric_query(
+    index_id_patterns: Vec<String>,
+    metric_query: &MetricQuery,
+    log_fields: &LogFields,
+    evaluation_range: EvaluationRange,
+    search_service: Arc<dyn SearchService>,
+) -> Result<Vec<MatrixSeries>, PrometheusError> {
+    let query_ast = metric_query
+        .log_query
+        .query_ast(&log_fields.line_fields)
+        .map_err(PrometheusError::bad_data)?;
+    let query_evaluation = QueryEvaluation::new(metric_query.promql_query(), evaluation_range)
+        .map_err(PrometheusError::bad_data)?;
+    let search_request = query_evaluation.search_request_for_query_ast(
+        index_id_patterns,
+        &log_fields.timestamp_field,
+        query_ast,
+    );
+    let search_response = search_service.root_search(search_request).await?;
+
+    let Some(aggregation_json) = search_response.aggregation else {
+        return Ok(Vec::new());
+    };
+    let aggregation_results: JsonValue =
+        serde_json::from_str(&aggregation_json).map_err(|error| {
+            PrometheusError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                PrometheusErrorType::Internal,
+                format!("failed to parse aggregation results: {error}"),
+            )
+        })?;
+    let matrix = query_evaluation
+        .series(&aggregation_results)
+        .into_iter()
+        .map(MatrixSeries::from)
+        .collect();
+    Ok(matrix)
+}
+
+async fn loki_labels(
+    index_id_patterns: Vec<String>,
+    params: LabelsParams,
+    search_service: Arc<dyn SearchService>,
+) -> Result<PrometheusResponseBody<Vec<String>>, PrometheusError> {
+    let (start_timestamp, end_timestamp) =
+        parse_time_bounds_secs(params.start.as_deref(), params.end.as_deref())?;
+    let labels = list_labels(
+        index_id_patterns,
+        start_timestamp,
+        end_timestamp,
+        search_service,
+    )
+    .await?;
+    Ok(PrometheusResponseBody::success(labels))
+}
+
+async fn loki_label_values(
+    index_id_patterns: Vec<String>,
+    label: String,
+    params: LabelsParams,
+    search_service: Arc<dyn SearchService>,
+) -> Result<PrometheusResponseBody<Vec<String>>, PrometheusError> {
+    let (start_timestamp, end_timestamp) =
+        parse_time_bounds_secs(params.start.as_deref(), params.end.as_deref())?;
+    let query_ast = if let Some(selector) = &params.query {
+        stream_selector_query_ast(selector)?
+    } else {
+        QueryAst::MatchAll
+    };
+    let label_values = list_label_values(
+        index_id_patterns,
+        label,
+        query_ast,
+        start_timestamp,
+        end_timestamp,
+        search_service,
+    )
+    .await?;
+    Ok(PrometheusResponseBody::success(label_values))
+}
+
+fn stream_selector_query_ast(selector: &str) -> Result<QueryAst, PrometheusError> {
+    let label_matchers = parse_stream_selector(selector).map_err(PrometheusError::bad_data)?;
+    let log_query = LogQuery {
+        label_matchers,
+        line_filters: Vec::new(),
+    };
+    log_query.query_ast(&[]).map_err(PrometheusError::bad_data)
+}
+
+/// Returns the label sets of the series matched by the stream selectors. A series is identified
+/// by the values of the labels referenced by its selector.
+async fn loki_series(
+    index_id_patterns: Vec<String>,
+    params: SeriesParams,
+    search_service: Arc<dyn SearchService>,
+) -> Result<PrometheusResponseBody<Vec<BTreeMap<String, String>>>, PrometheusError> {
+    if params.matches.is_empty() {
+        return Err(PrometheusError::bad_data(
+            "at least one `match[]` stream selector is required".to_string(),
+        ));
+    }
+    let (start_timestamp, end_timestamp) =
+        parse_time_bounds_secs(params.start.as_deref(), params.end.as_deref())?;
+    let mut label_sets = BTreeSet::new();
+
+    for selector in &params.matches {
+        let label_matchers = parse_stream_selector(selector).map_err(PrometheusError::bad_data)?;
+        let labels: Vec<String> = label_matchers
+            .iter()
+            .map(|label_matcher| label_matcher.name.clone())
+            .collect::<BTreeSet<String>>()
+            .into_iter()
+            .collect();
+        let query_ast = stream_selector_query_ast(selector)?;
+
+        let mut aggregation = JsonValue::Null;
+
+        for (label_idx, label) in labels.iter().enumerate().rev() {
+            let mut terms_agg = json!({
+                "terms": {
+                    "field": label,
+                    "size": MAX_NUM_GROUPS_PER_LABEL,
+                }
+            });
+            if !aggregation.is_null() {
+                terms_agg["aggs"] = aggregation;
+            }
+            aggregation = json!({ series_agg_name(label_idx): terms_agg });
+        }
+        let search_request = SearchRequest {
+            index_id_patterns: index_id_patterns.clone(),
+            query_ast: serde_json::to_string(&query_ast).expect("failed to serialize query AST"),
+            start_timestamp,
+            end_timestamp,
+            max_hits: 0,
+            aggregation_request: Some(aggregation.to_string()),
+            count_hits: CountHits::Underestimate.into(),
+            ..Default::default()
+        };
+        let search_response = search_service.root_search(search_request).await?;
+
+        let Some(aggregation_json) = search_response.aggregation else {
+            continue;
+        };
+        let aggregation_results: JsonValue =
+            serde_json::from_str(&aggregation_json).unwrap_or_default();
+        collect_label_sets(
+            &aggregation_results,
+            &labels,
+            0,
+            BTreeMap::new(),
+            &mut label_sets,
+        );
+    }
+    Ok(PrometheusResponseBody::success(
+        label_sets.into_iter().collect(),
+    ))
+}
+
+fn series_agg_name(label_idx: usize) -> String {
+    format!("label_{label_idx}")
+}
+
+fn collect_label_sets(
+    aggregation_results: &JsonValue,
+    labels: &[String],
+    label_idx: usize,
+    label_set: BTreeMap<String, String>,
+    label_sets: &mut BTreeSet<BTreeMap<String, String>>,
+) {
+    if label_idx == labels.len() {
+        label_sets.insert(label_set);
+        return;
+    }
+    let Some(buckets) = aggregation_results[series_agg_name(label_idx)]["buckets"].as_array()
+    else {
+        return;
+    };
+    for bucket in buckets {
+        let label_value = match &bucket["key"] {
+            JsonValue::String(key) => key.clone(),
+            JsonValue::Null => continue,
+            key => key.to_string(),
+        };
+        let mut bucket_label_set = label_set.clone();
+        bucket_label_set.insert(labels[label_idx].clone(), label_value);
+        collect_label_sets(bucket, labels, label_idx + 1, bucket_label_set, label_sets);
+    }
+}
+
+async fn loki_tail(
+    index_id_patterns: Vec<String>,
+    params: TailParams,
+    ws: Ws,
+    search_service: Arc<dyn SearchService>,
+    metastore: MetastoreServiceClient,
+) -> warp::reply::Response {
+    let log_tail = match LogTail::new(index_id_patterns, params, metastore).await {
+        Ok(log_tail) => log_tail,
+        Err(error) => return make_loki_api_response::<()>(Err(error)).into_response(),
+    };
+    ws.on_upgrade(move |websocket| tail_log_lines(websocket, log_tail, search_service))
+        .into_response()
+}
+
+/// State of a Loki tail, which streams the log lines matching a log query.
+struct LogTail {
+    stream: BTreeMap<String, String>,
+    search_tail: SearchTail,
+}
+
+impl LogTail {
+    async fn new(
+        index_id_patterns: Vec<String>,
+        params: TailParams,
+        metastore: MetastoreServiceClient,
+    ) -> Result<Self, PrometheusError> {
+        let LogqlQuery::Log(log_query) =
+            parse_logql(&params.query).map_err(PrometheusError::bad_data)?
+        else {
+            return Err(PrometheusError::bad_data(
+                "only log queries can be tailed".to_string(),
+            ));
+        };
+        let start_nanos = params
+            .start
+            .as_deref()
+            .map(parse_timestamp_nanos)
+            .transpose()?
+            .unwrap_or_else(|| now_nanos() - DEFAULT_LOOKBACK.as_nanos() as i64);
+        let log_fields = resolve_log_fields(&index_id_patterns, metastore.clone()).await?;
+        let query_ast = log_query
+            .query_ast(&log_fields.line_fields)
+            .map_err(PrometheusError::bad_data)?;
+        let search_tail = SearchTail::start(
+            index_id_patterns,
+            log_fields.index_uids,
+            query_ast,
+            log_fields.timestamp_field,
+            Some(start_nanos),
+            params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+            metastore,
+        )
+        .await?;
+        Ok(Self {
+            stream: log_query.stream_labels(),
+            search_tail,
+        })
+    }
+}
+
+/// Returns the documents streamed by a tail as Loki values.
+fn tail_values(documents: Vec<TailDocument>) -> Vec<(String, String)> {
+    documents
+        .into_iter()
+        .map(|document| {
+            let timestamp_nanos = document.timestamp_nanos_opt.unwrap_or_default();
+            (timestamp_nanos.to_string(), document.json)
+        })
+        .collect()
+}
+
+async fn tail_log_lines(
+    websocket: WebSocket,
+    mut log_tail: LogTail,
+    search_service: Arc<dyn SearchService>,
+) {
+    let (mut websocket_tx, mut websocket_rx) = websocket.split();
+    let mut poll_interval = new_tail_poll_interval();
+
+    loop {
+        let documents_result = tokio::select! {
+            documents_result = log_tail
+                .search_tail
+                .poll_documents(&*search_service, &mut poll_interval) => documents_result,
+            message_opt = websocket_rx.next() => {
+                match message_opt {
+                    Some(Ok(message)) if !message.is_close() => continue,
+                    // The client closed the connection.
+                    _ => return,
+                }
+            }
+        };
+        let documents = match documents_result {
+            Ok(documents) => documents,
+            Err(search_error) => {
+                warn!(error=%search_error, "failed to tail log lines");
+                let close_message = Message::close_with(1011u16, "failed to search log lines");
+                let _ = websocket_tx.send(close_message).await;
+                return;
+            }
+        };
+        let values = tail_values(documents);
+        let tail_response = TailResponse {
+            streams: vec![LokiStream {
+                stream: log_tail.stream.clone(),
+                values,
+            }],
+        };
+        let tail_response_json =
+            serde_json::to_string(&tail_response).expect("failed to serialize tail response");
+
+        if websocket_tx
+            .send(Message::text(tail_response_json))
+            .await
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+fn make_loki_api_response<T: serde::Serialize>(
+    loki_result: Result<T, PrometheusError>,
+) -> RestApiResponse {
+    let status_code = match &loki_result {
+        Ok(_) => StatusCode::OK,
+        Err(error) => error.status_code,
+    };
+    RestApiResponse::new(&loki_result, status_code, BodyFormat::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_metastore::{IndexMetadata, ListIndexesMetadataResponseExt};
+    use quickwit_proto::metastore::{ListIndexesMetadataResponse, MockMetastoreService};
+    use quickwit_proto::search::sort_by_value::SortValue;
+    use quickwit_proto::search::{PartialHit, SearchResponse, SortByValue};
+    use quickwit_search::MockSearchService;
+
+    use super::*;
+
+    fn mock_metastore() -> MetastoreServiceClient {
+        let mut mock_metastore = MockMetastoreService::new();
+        mock_metastore
+            .expect_list_indexes_metadata()
+            .return_once(|_| {
+                let index_metadata = IndexMetadata::for_test("logs", "ram:///indexes/logs");
+                Ok(ListIndexesMetadataResponse::for_test(vec![index_metadata]))
+            });
+        MetastoreServiceClient::from_mock(mock_metastore)
+    }
+
+    fn hit(timestamp_nanos: i64, json: &str) -> Hit {
+        Hit {
+            json: json.to_string(),
+            partial_hit: Some(PartialHit {
+                sort_value: Some(SortByValue {
+                    sort_value: Some(SortValue::I64(timestamp_nanos)),
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_loki_query_range_log_query() {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .withf(|search_request| {
+                search_request.start_timestamp == Some(1_000)
+                    && search_request.end_timestamp == Some(2_000)
+                    && search_request.max_hits == 10
+                    && search_request.sort_fields[0].sort_order == SortOrder::Desc as i32
+                    && search_request.query_ast.contains(r#""field":"body""#)
+            })
+            .return_once(|_| {
+                Ok(SearchResponse {
+                    hits: vec![
+                        hit(1_500_000_000_000, r#"{"body":"error 2"}"#),
+                        hit(1_200_000_000_000, r#"{"body":"error 1"}"#),
+                    ],
+                    ..Default::default()
+                })
+            });
+        let loki_api_handler = loki_api_handlers(Arc::new(mock_search_service), mock_metastore());
+        let resp = warp::test::request()
+            .path(
+                "/logs/loki/api/v1/query_range?query=%7Bapp%3D%22api%22%7D%20%7C%3D%20%22error%22&start=1000000000000&end=2000000000000&limit=10",
+            )
+            .reply(&loki_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(
+            resp_json,
+            json!({
+                "status": "success",
+                "data": {
+                    "resultType": "streams",
+                    "result": [{
+                        "stream": { "app": "api" },
+                        "values": [
+                            ["1500000000000", r#"{"body":"error 2"}"#],
+                            ["1200000000000", r#"{"body":"error 1"}"#],
+                        ],
+                    }]
+                }
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_loki_query_range_metric_query() {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .withf(|search_request| {
+                search_request.start_timestamp == Some(960)
+                    && search_request.end_timestamp == Some(1_080)
+                    && search_request
+                        .aggregation_request
+                        .as_ref()
+                        .unwrap()
+                        .contains(r#""field":"timestamp""#)
+            })
+            .return_once(|_| {
+                let aggregation = json!({
+                    "samples": {
+                        "buckets": [
+                            { "key": 960_000.0, "doc_count": 3 },
+                            { "key": 1_020_000.0, "doc_count": 1 },
+                        ]
+                    }
+                });
+                Ok(SearchResponse {
+                    aggregation: Some(aggregation.to_string()),
+                    ..Default::default()
+                })
+            });
+        let loki_api_handler = loki_api_handlers(Arc::new(mock_search_service), mock_metastore());
+        let resp = warp::test::request()
+            .path(
+                "/logs/loki/api/v1/query_range?query=count_over_time(%7Bapp%3D%22api%22%7D%5B1m%5D)&start=1020000000000&end=1080000000000&step=60",
+            )
+            .reply(&loki_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(
+            resp_json,
+            json!({
+                "status": "success",
+                "data": {
+                    "resultType": "matrix",
+                    "result": [{
+                        "metric": { "app": "api" },
+                        "values": [[1020, "3"], [1080, "1"]],
+                    }]
+                }
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_loki_query_range_invalid_query() {
+        let loki_api_handler = loki_api_handlers(
+            Arc::new(MockSearchService::new()),
+            MetastoreServiceClient::from_mock(MockMetastoreService::new()),
+        );
+        let resp = warp::test::request()
+            .path("/logs/loki/api/v1/query_range?query=%7Bapp%3D%22api%22%7D%20%7C%20json")
+            .reply(&loki_api_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(resp_json["errorType"], "bad_data");
+    }
+
+    #[tokio::test]
+    async fn test_loki_series() {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .withf(|search_request| {
+                search_request
+                    .aggregation_request
+                    .as_ref()
+                    .unwrap()
+                    .contains(r#""label_1""#)
+            })
+            .return_once(|_| {
+                let aggregation = json!({
+                    "label_0": {
+                        "buckets": [
+                            {
+                                "key": "api",
+                                "doc_count": 3,
+                                "label_1": {
+                                    "buckets": [
+                                        { "key": "error", "doc_count": 2 },
+                                        { "key": "info", "doc_count": 1 },
+                                    ]
+                                }
+                            },
+                        ]
+                    }
+                });
+                Ok(SearchResponse {
+                    aggregation: Some(aggregation.to_string()),
+                    ..Default::default()
+                })
+            });
+        let loki_api_handler = loki_api_handlers(
+            Arc::new(mock_search_service),
+            MetastoreServiceClient::from_mock(MockMetastoreService::new()),
+        );
+        let resp = warp::test::request()
+            .path(
+                "/logs/loki/api/v1/series?match%5B%5D=%7Bapp%3D%22api%22%2Clevel%3D~%22.%2B%22%7D",
+            )
+            .reply(&loki_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(
+            resp_json,
+            json!({
+                "status": "success",
+                "data": [
+                    { "app": "api", "level": "error" },
+                    { "app": "api", "level": "info" },
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_tail_values() {
+        let documents = vec![
+            TailDocument {
+                timestamp_nanos_opt: Some(1_000),
+                json: "a".to_string(),
+            },
+            TailDocument {
+                timestamp_nanos_opt: None,
+                json: "b".to_string(),
+            },
+        ];
+        assert_eq!(
+            tail_values(documents),
+            vec![
+                ("1000".to_string(), "a".to_string()),
+                ("0".to_string(), "b".to_string()),
+            ]
+        );
+    }
+}
@@ -31,14 +31,17 @@ use crate::cluster_api::ClusterApi;
 use crate::delete_task_api::DeleteTaskApi;
 use crate::developer_api::DeveloperApi;
 use crate::elasticsearch_api::ElasticCompatibleApi;
+use crate::export_api::ExportApi;
 use crate::health_check_api::HealthCheckApi;
 use crate::index_api::IndexApi;
 use crate::indexing_api::IndexingApi;
 use crate::ingest_api::{IngestApi, IngestApiSchemas};
 use crate::jaeger_api::JaegerApi;
+use crate::loki_api::LokiApi;
 use crate::metrics_api::MetricsApi;
 use crate::node_info_handler::NodeInfoApi;
 use crate::otlp_api::OtlpApi;
+use crate::prometheus_api::PrometheusApi;
 use crate::search_api::SearchApi;
 use crate::template_api::IndexTemplateApi;
 
@@ -80,6 +83,8 @@ pub fn build_docs() -> utoipa::openapi::OpenApi {
         Tag::new("Splits"),
         Tag::new("Jaeger"),
         Tag::new("Open Telemetry"),
+        Tag::new("Prometheus"),
+        Tag::new("Loki"),
         Tag::new("Debug"),
     ];
     docs_base.tags = Some(tags);
@@ -91,6 +96,7 @@ pub fn build_docs() -> utoipa::openapi::OpenApi {
         .merge_components_and_paths(DeveloperApi::openapi().with_path_prefix("/api/developer"));
     docs_base
         .merge_components_and_paths(ElasticCompatibleApi::openapi().with_path_prefix("/api/v1"));
+    docs_base.merge_components_and_paths(ExportApi::openapi().with_path_prefix("/api/v1"));
     docs_base.merge_components_and_paths(OtlpApi::openapi().with_path_prefix("/api/v1"));
     docs_base.merge_components_and_paths(HealthCheckApi::openapi().with_path_prefix("/health"));
     docs_base.merge_components_and_paths(IndexApi::openapi().with_path_prefix("/api/v1"));
@@ -98,6 +104,8 @@ pub fn build_docs() -> utoipa::openapi::OpenApi {
     docs_base.merge_components_and_paths(IndexTemplateApi::openapi().with_path_prefix("/api/v1"));
     docs_base.merge_components_and_paths(IngestApi::openapi().with_path_prefix("/api/v1"));
     docs_base.merge_components_and_paths(JaegerApi::openapi().with_path_prefix("/api/v1"));
+    docs_base.merge_components_and_paths(PrometheusApi::openapi().with_path_prefix("/api/v1"));
+    docs_base.merge_components_and_paths(LokiApi::openapi().with_path_prefix("/api/v1"));
     docs_base.merge_components_and_paths(MetricsApi::openapi().with_path_prefix("/metrics"));
     docs_base.merge_components_and_paths(NodeInfoApi::openapi().with_path_prefix("/api/v1"));
     docs_base.merge_components_and_paths(SearchApi::openapi().with_path_prefix("/api/v1"));
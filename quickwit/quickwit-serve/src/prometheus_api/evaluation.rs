@@ -0,0 +1,515 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Evaluation of PromQL queries with date histogram aggregations.
+//!
+//! A series is evaluated at the timestamps `start`, `start + step`, ..., up to `end`. The value
+//! of the series at timestamp `t` is computed over the documents of the window `[t - range, t)`.
+//! Windows are assembled from the buckets of a date histogram aggregation, which width divides
+//! both `range` and `step`, so that counts are exact. When that would require too many buckets,
+//! buckets are as wide as `step` and windows are rounded up to a whole number of buckets.
+//!
+//! Quantiles cannot be merged across buckets, so `quantile_over_time` is computed over buckets as
+//! wide as `range` and is only supported when `range` and `step` are equal.
+
+use std::collections::BTreeMap;
+
+use quickwit_proto::search::{CountHits, SearchRequest};
+use quickwit_query::query_ast::QueryAst;
+use serde_json::{json, Value as JsonValue};
+
+use super::promql::{PromqlQuery, RangeFunction};
+
+/// Name of the date histogram aggregation computing the samples of a series.
+const SAMPLES_AGG_NAME: &str = "samples";
+
+/// Name of the percentiles aggregation computing the value of a sample for
+/// `quantile_over_time`.
+const QUANTILE_AGG_NAME: &str = "quantile";
+
+/// Maximum number of groups returned for each label of a `sum by` aggregation.
+pub(crate) const MAX_NUM_GROUPS_PER_LABEL: usize = 1_000;
+
+/// Maximum number of buckets of the date histogram used to compute exact windows. Prometheus
+/// limits the number of points per series to 11,000.
+const MAX_NUM_BUCKETS: i64 = 11_000;
+
+/// Timestamps, in seconds, at which a query is evaluated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct EvaluationRange {
+    pub start_secs: i64,
+    pub end_secs: i64,
+    pub step_secs: i64,
+}
+
+impl EvaluationRange {
+    fn evaluation_timestamps(&self) -> impl Iterator<Item = i64> + '_ {
+        (self.start_secs..=self.end_secs).step_by(self.step_secs as usize)
+    }
+}
+
+/// A series of samples, `(timestamp in seconds, value)`, identified by its labels.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Series {
+    pub labels: BTreeMap<String, String>,
+    pub samples: Vec<(i64, f64)>,
+}
+
+/// Describes how a PromQL query is evaluated: the search request to execute and how to turn
+/// its aggregation results into series.
+pub(crate) struct QueryEvaluation {
+    promql_query: PromqlQuery,
+    evaluation_range: EvaluationRange,
+    range_secs: i64,
+    bucket_width_secs: i64,
+}
+
+impl QueryEvaluation {
+    pub fn new(
+        promql_query: PromqlQuery,
+        evaluation_range: EvaluationRange,
+    ) -> Result<Self, String> {
+        if evaluation_range.step_secs <= 0 {
+            return Err("step must be at least one second".to_string());
+        }
+        if evaluation_range.end_secs < evaluation_range.start_secs {
+            return Err("end timestamp must not be before start timestamp".to_string());
+        }
+        let num_evaluations = (evaluation_range.end_secs - evaluation_range.start_secs)
+            / evaluation_range.step_secs
+            + 1;
+        if num_evaluations > MAX_NUM_BUCKETS {
+            return Err(format!(
+                "exceeded maximum resolution of {MAX_NUM_BUCKETS} points per timeseries, try \
+                 decreasing the query resolution (?step=XX)"
+            ));
+        }
+        let range_secs = (promql_query.range.as_secs() as i64).max(1);
+        let bucket_width_secs =
+            match promql_query.function {
+                RangeFunction::QuantileOverTime(_) => {
+                    if range_secs != evaluation_range.step_secs {
+                        return Err(format!(
+                            "`quantile_over_time` requires the range ({range_secs}s) to be equal \
+                             to                          the step ({}s)",
+                            evaluation_range.step_secs
+                        ));
+                    }
+                    range_secs
+                }
+                RangeFunction::CountOverTime | RangeFunction::Rate => {
+                    let bucket_width_secs = gcd(range_secs, evaluation_range.step_secs);
+                    let num_buckets = (evaluation_range.end_secs - evaluation_range.start_secs
+                        + range_secs)
+                        / bucket_width_secs;
+                    if num_buckets <= MAX_NUM_BUCKETS {
+                        bucket_width_secs
+                    } else {
+                        evaluation_range.step_secs
+                    }
+                }
+            };
+        Ok(Self {
+            promql_query,
+            evaluation_range,
+            range_secs,
+            bucket_width_secs,
+        })
+    }
+
+    /// Builds the search request computing the samples of the series.
+    pub fn search_request(
+        &self,
+        index_id_patterns: Vec<String>,
+        timestamp_field: &str,
+    ) -> Result<SearchRequest, String> {
+        let query_ast = self.promql_query.query_ast()?;
+        Ok(self.search_request_for_query_ast(index_id_patterns, timestamp_field, query_ast))
+    }
+
+    /// Builds the search request computing the samples of the series over the documents matched
+    /// by `query_ast` rather than by the selector of the query.
+    pub fn search_request_for_query_ast(
+        &self,
+        index_id_patterns: Vec<String>,
+        timestamp_field: &str,
+        query_ast: QueryAst,
+    ) -> SearchRequest {
+        let bucket_offset_secs = self
+            .evaluation_range
+            .start_secs
+            .rem_euclid(self.bucket_width_secs);
+        let mut samples_agg = json!({
+            "date_histogram": {
+                "field": timestamp_field,
+                "fixed_interval": format!("{}s", self.bucket_width_secs),
+                "offset": format!("{bucket_offset_secs}s"),
+            }
+        });
+        if let (RangeFunction::QuantileOverTime(quantile), Some(metric_name)) =
+            (self.promql_query.function, &self.promql_query.metric_name)
+        {
+            samples_agg["aggs"] = json!({
+                QUANTILE_AGG_NAME: {
+                    "percentiles": {
+                        "field": metric_name,
+                        "percents": [quantile * 100.0],
+                    }
+                }
+            });
+        }
+        let mut aggregation = json!({ SAMPLES_AGG_NAME: samples_agg });
+
+        for (label_idx, label) in self.group_by_labels().iter().enumerate().rev() {
+            aggregation = json!({
+                group_by_agg_name(label_idx): {
+                    "terms": {
+                        "field": label,
+                        "size": MAX_NUM_GROUPS_PER_LABEL,
+                    },
+                    "aggs": aggregation,
+                }
+            });
+        }
+        SearchRequest {
+            index_id_patterns,
+            query_ast: serde_json::to_string(&query_ast).expect("failed to serialize query AST"),
+            start_timestamp: Some(self.evaluation_range.start_secs - self.range_secs),
+            end_timestamp: Some(self.evaluation_range.end_secs),
+            max_hits: 0,
+            aggregation_request: Some(aggregation.to_string()),
+            count_hits: CountHits::Underestimate.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Builds the series from the aggregation results of the search request.
+    pub fn series(&self, aggregation_results: &JsonValue) -> Vec<Series> {
+        let mut series = Vec::new();
+        let labels = if self.promql_query.sum_by.is_some() {
+            BTreeMap::new()
+        } else {
+            self.promql_query.selector_labels()
+        };
+        self.collect_series(aggregation_results, 0, labels, &mut series);
+        series
+    }
+
+    fn group_by_labels(&self) -> &[String] {
+        self.promql_query.sum_by.as_deref().unwrap_or_default()
+    }
+
+    fn collect_series(
+        &self,
+        aggregation_results: &JsonValue,
+        label_idx: usize,
+        labels: BTreeMap<String, String>,
+        series: &mut Vec<Series>,
+    ) {
+        let group_by_labels = self.group_by_labels();
+
+        if label_idx == group_by_labels.len() {
+            let samples = self.samples(&aggregation_results[SAMPLES_AGG_NAME]);
+
+            if !samples.is_empty() {
+                series.push(Series { labels, samples });
+            }
+            return;
+        }
+        let Some(buckets) = aggregation_results[group_by_agg_name(label_idx)]["buckets"].as_array()
+        else {
+            return;
+        };
+        for bucket in buckets {
+            let label_value = match &bucket["key"] {
+                JsonValue::String(key) => key.clone(),
+                JsonValue::Null => continue,
+                key => key.to_string(),
+            };
+            let mut bucket_labels = labels.clone();
+            bucket_labels.insert(group_by_labels[label_idx].clone(), label_value);
+            self.collect_series(bucket, label_idx + 1, bucket_labels, series);
+        }
+    }
+
+    /// Computes the samples of a series from its date histogram buckets.
+    fn samples(&self, samples_agg_result: &JsonValue) -> Vec<(i64, f64)> {
+        let Some(buckets) = samples_agg_result["buckets"].as_array() else {
+            return Vec::new();
+        };
+        // Bucket start timestamp in seconds -> bucket value.
+        let bucket_values: BTreeMap<i64, f64> = buckets
+            .iter()
+            .filter_map(|bucket| {
+                let bucket_start_secs = (bucket["key"].as_f64()? / 1_000.0).floor() as i64;
+                let bucket_value = match self.promql_query.function {
+                    RangeFunction::CountOverTime | RangeFunction::Rate => {
+                        bucket["doc_count"].as_f64()?
+                    }
+                    RangeFunction::QuantileOverTime(_) => {
+                        quantile_value(&bucket[QUANTILE_AGG_NAME]["values"])?
+                    }
+                };
+                Some((bucket_start_secs, bucket_value))
+            })
+            .collect();
+
+        let mut samples = Vec::new();
+
+        for timestamp_secs in self.evaluation_range.evaluation_timestamps() {
+            let sample_value_opt = match self.promql_query.function {
+                RangeFunction::CountOverTime | RangeFunction::Rate => {
+                    let window_len_secs = round_up(self.range_secs, self.bucket_width_secs);
+                    let count: f64 = bucket_values
+                        .range(timestamp_secs - window_len_secs..timestamp_secs)
+                        .map(|(_, count)| count)
+                        .sum();
+                    if count == 0.0 {
+                        None
+                    } else if self.promql_query.function == RangeFunction::Rate {
+                        Some(count / self.range_secs as f64)
+                    } else {
+                        Some(count)
+                    }
+                }
+                RangeFunction::QuantileOverTime(_) => bucket_values
+                    .get(&(timestamp_secs - self.bucket_width_secs))
+                    .copied(),
+            };
+            if let Some(sample_value) = sample_value_opt {
+                samples.push((timestamp_secs, sample_value));
+            }
+        }
+        samples
+    }
+}
+
+fn group_by_agg_name(label_idx: usize) -> String {
+    format!("group_by_{label_idx}")
+}
+
+/// Extracts the value of the single percentile computed by the percentiles aggregation, which
+/// can be keyed or not.
+fn quantile_value(percentile_values: &JsonValue) -> Option<f64> {
+    match percentile_values {
+        JsonValue::Object(keyed_values) => keyed_values.values().next()?.as_f64(),
+        JsonValue::Array(values) => values.first()?["value"].as_f64(),
+        _ => None,
+    }
+}
+
+fn gcd(mut lhs: i64, mut rhs: i64) -> i64 {
+    while rhs != 0 {
+        (lhs, rhs) = (rhs, lhs % rhs);
+    }
+    lhs
+}
+
+fn round_up(value: i64, multiple: i64) -> i64 {
+    (value + multiple - 1) / multiple * multiple
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prometheus_api::promql::parse_promql;
+
+    fn histogram_bucket(key_secs: i64, doc_count: u64) -> JsonValue {
+        json!({ "key": (key_secs * 1_000) as f64, "doc_count": doc_count })
+    }
+
+    #[test]
+    fn test_query_evaluation_search_request() {
+        let promql_query = parse_promql(r#"sum by (service) (rate({env="prod"}[60s]))"#).unwrap();
+        let evaluation_range = EvaluationRange {
+            start_secs: 1_000,
+            end_secs: 1_180,
+            step_secs: 90,
+        };
+        let query_evaluation = QueryEvaluation::new(promql_query, evaluation_range).unwrap();
+        assert_eq!(query_evaluation.bucket_width_secs, 30);
+
+        let search_request = query_evaluation
+            .search_request(vec!["logs".to_string()], "timestamp")
+            .unwrap();
+        assert_eq!(search_request.index_id_patterns, vec!["logs".to_string()]);
+        assert_eq!(search_request.start_timestamp, Some(940));
+        assert_eq!(search_request.end_timestamp, Some(1_180));
+        assert_eq!(search_request.max_hits, 0);
+
+        let aggregation: JsonValue =
+            serde_json::from_str(search_request.aggregation_request.as_ref().unwrap()).unwrap();
+        assert_eq!(
+            aggregation,
+            json!({
+                "group_by_0": {
+                    "terms": { "field": "service", "size": 1_000 },
+                    "aggs": {
+                        "samples": {
+                            "date_histogram": {
+                                "field": "timestamp",
+                                "fixed_interval": "30s",
+                                "offset": "10s",
+                            }
+                        }
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_query_evaluation_count_over_time_series() {
+        let promql_query =
+            parse_promql(r#"count_over_time({env="prod", level!="debug"}[60s])"#).unwrap();
+        let evaluation_range = EvaluationRange {
+            start_secs: 1_020,
+            end_secs: 1_200,
+            step_secs: 90,
+        };
+        let query_evaluation = QueryEvaluation::new(promql_query, evaluation_range).unwrap();
+        assert_eq!(query_evaluation.bucket_width_secs, 30);
+
+        let aggregation_results = json!({
+            "samples": {
+                "buckets": [
+                    histogram_bucket(960, 1),
+                    histogram_bucket(990, 2),
+                    histogram_bucket(1_050, 4),
+                    histogram_bucket(1_080, 8),
+                    histogram_bucket(1_170, 16),
+                ]
+            }
+        });
+        let series = query_evaluation.series(&aggregation_results);
+        assert_eq!(
+            series,
+            vec![Series {
+                labels: BTreeMap::from([("env".to_string(), "prod".to_string())]),
+                samples: vec![(1_020, 3.0), (1_110, 12.0), (1_200, 16.0)],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_query_evaluation_sum_by_rate_series() {
+        let promql_query =
+            parse_promql(r#"sum by (service, status) (rate({env="prod"}[60s]))"#).unwrap();
+        let evaluation_range = EvaluationRange {
+            start_secs: 1_020,
+            end_secs: 1_080,
+            step_secs: 60,
+        };
+        let query_evaluation = QueryEvaluation::new(promql_query, evaluation_range).unwrap();
+        let aggregation_results = json!({
+            "group_by_0": {
+                "buckets": [{
+                    "key": "api",
+                    "doc_count": 12,
+                    "group_by_1": {
+                        "buckets": [{
+                            "key": 500.0,
+                            "doc_count": 12,
+                            "samples": {
+                                "buckets": [histogram_bucket(960, 6), histogram_bucket(1_020, 6)]
+                            }
+                        }]
+                    }
+                }]
+            }
+        });
+        let series = query_evaluation.series(&aggregation_results);
+        assert_eq!(
+            series,
+            vec![Series {
+                labels: BTreeMap::from([
+                    ("service".to_string(), "api".to_string()),
+                    ("status".to_string(), "500.0".to_string()),
+                ]),
+                samples: vec![(1_020, 0.1), (1_080, 0.1)],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_query_evaluation_quantile_over_time_series() {
+        let promql_query =
+            parse_promql(r#"quantile_over_time(0.5, latency_ms{env="prod"}[60s])"#).unwrap();
+        let evaluation_range = EvaluationRange {
+            start_secs: 1_020,
+            end_secs: 1_080,
+            step_secs: 60,
+        };
+        let query_evaluation = QueryEvaluation::new(promql_query, evaluation_range).unwrap();
+        let search_request = query_evaluation
+            .search_request(vec!["logs".to_string()], "timestamp")
+            .unwrap();
+        let aggregation: JsonValue =
+            serde_json::from_str(search_request.aggregation_request.as_ref().unwrap()).unwrap();
+        assert_eq!(
+            aggregation["samples"]["aggs"],
+            json!({
+                "quantile": {
+                    "percentiles": { "field": "latency_ms", "percents": [50.0] }
+                }
+            })
+        );
+        let aggregation_results = json!({
+            "samples": {
+                "buckets": [
+                    { "key": 960_000.0, "doc_count": 3, "quantile": { "values": { "50.0": 12.5 } } },
+                    { "key": 1_020_000.0, "doc_count": 0, "quantile": { "values": { "50.0": null } } },
+                ]
+            }
+        });
+        let series = query_evaluation.series(&aggregation_results);
+        assert_eq!(series[0].samples, vec![(1_020, 12.5)]);
+    }
+
+    #[test]
+    fn test_query_evaluation_invalid_range() {
+        let promql_query = parse_promql(r#"rate({env="prod"}[60s])"#).unwrap();
+        let evaluation_range = EvaluationRange {
+            start_secs: 0,
+            end_secs: 1_000_000,
+            step_secs: 1,
+        };
+        QueryEvaluation::new(promql_query.clone(), evaluation_range).unwrap_err();
+
+        let evaluation_range = EvaluationRange {
+            start_secs: 10,
+            end_secs: 0,
+            step_secs: 1,
+        };
+        QueryEvaluation::new(promql_query, evaluation_range).unwrap_err();
+
+        let promql_query =
+            parse_promql(r#"quantile_over_time(0.5, latency_ms{env="prod"}[5m])"#).unwrap();
+        let evaluation_range = EvaluationRange {
+            start_secs: 0,
+            end_secs: 3_600,
+            step_secs: 60,
+        };
+        let error = QueryEvaluation::new(promql_query, evaluation_range).unwrap_err();
+        assert_eq!(
+            error,
+            "`quantile_over_time` requires the range (300s) to be equal to the step (60s)"
+        );
+    }
+}
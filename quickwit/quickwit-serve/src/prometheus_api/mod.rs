@@ -0,0 +1,35 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+mod evaluation;
+mod model;
+mod promql;
+mod rest_handler;
+
+pub(crate) use evaluation::{EvaluationRange, QueryEvaluation, MAX_NUM_GROUPS_PER_LABEL};
+pub(crate) use model::{
+    MatrixSeries, PrometheusError, PrometheusErrorType, PrometheusResponseBody,
+};
+pub(crate) use promql::{
+    label_matchers_bool_query, parse_duration, regex_literal_alternatives, LabelMatcher, MatchOp,
+    Parser, PromqlQuery, RangeFunction,
+};
+pub(crate) use rest_handler::{
+    list_label_values, list_labels, prometheus_api_handlers, shared_timestamp_field, PrometheusApi,
+};
@@ -0,0 +1,268 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+use std::time::UNIX_EPOCH;
+
+use hyper::StatusCode;
+use quickwit_proto::ServiceError;
+use quickwit_search::SearchError;
+use serde::{Deserialize, Serialize};
+
+use super::evaluation::Series;
+
+/// Query parameters of the instant query endpoint.
+#[derive(Debug, Clone, Deserialize)]
+pub struct InstantQueryParams {
+    /// PromQL query.
+    pub query: String,
+    /// Evaluation timestamp, as an RFC 3339 date or a Unix timestamp in seconds. Defaults to now.
+    #[serde(default)]
+    pub time: Option<String>,
+}
+
+/// Query parameters of the range query endpoint.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RangeQueryParams {
+    /// PromQL query.
+    pub query: String,
+    /// Start timestamp, as an RFC 3339 date or a Unix timestamp in seconds.
+    pub start: String,
+    /// End timestamp, as an RFC 3339 date or a Unix timestamp in seconds.
+    pub end: String,
+    /// Resolution step, as a duration (e.g. `1m`) or a number of seconds.
+    pub step: String,
+}
+
+/// Query parameters of the label endpoints.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LabelsQueryParams {
+    /// Start timestamp, as an RFC 3339 date or a Unix timestamp in seconds.
+    #[serde(default)]
+    pub start: Option<String>,
+    /// End timestamp, as an RFC 3339 date or a Unix timestamp in seconds.
+    #[serde(default)]
+    pub end: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PrometheusResponseBody<T> {
+    status: &'static str,
+    pub data: T,
+}
+
+impl<T> PrometheusResponseBody<T> {
+    pub fn success(data: T) -> Self {
+        Self {
+            status: "success",
+            data,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "resultType", content = "result", rename_all = "snake_case")]
+pub enum QueryResult {
+    Matrix(Vec<MatrixSeries>),
+    Vector(Vec<VectorSample>),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MatrixSeries {
+    metric: BTreeMap<String, String>,
+    values: Vec<(i64, String)>,
+}
+
+impl From<Series> for MatrixSeries {
+    fn from(series: Series) -> Self {
+        Self {
+            metric: series.labels,
+            values: series
+                .samples
+                .into_iter()
+                .map(|(timestamp_secs, value)| (timestamp_secs, format_sample_value(value)))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VectorSample {
+    metric: BTreeMap<String, String>,
+    value: (i64, String),
+}
+
+impl VectorSample {
+    /// Returns the last sample of the series, if any.
+    pub fn from_series(series: Series) -> Option<Self> {
+        let (timestamp_secs, value) = series.samples.last().copied()?;
+        Some(Self {
+            metric: series.labels,
+            value: (timestamp_secs, format_sample_value(value)),
+        })
+    }
+}
+
+/// Formats sample values the way Prometheus does.
+fn format_sample_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value == f64::INFINITY {
+        "+Inf".to_string()
+    } else if value == f64::NEG_INFINITY {
+        "-Inf".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrometheusErrorType {
+    BadData,
+    Execution,
+    Internal,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrometheusError {
+    #[serde(skip_serializing)]
+    pub status_code: StatusCode,
+    status: &'static str,
+    pub error_type: PrometheusErrorType,
+    pub error: String,
+}
+
+impl PrometheusError {
+    pub fn new(status_code: StatusCode, error_type: PrometheusErrorType, error: String) -> Self {
+        Self {
+            status_code,
+            status: "error",
+            error_type,
+            error,
+        }
+    }
+
+    pub fn bad_data(error: String) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, PrometheusErrorType::BadData, error)
+    }
+}
+
+impl From<SearchError> for PrometheusError {
+    fn from(search_error: SearchError) -> Self {
+        let status_code = search_error.error_code().http_status_code();
+        let error_type = if status_code.is_client_error() {
+            PrometheusErrorType::BadData
+        } else {
+            PrometheusErrorType::Execution
+        };
+        Self::new(status_code, error_type, search_error.to_string())
+    }
+}
+
+/// Parses a Prometheus timestamp, either an RFC 3339 date or a Unix timestamp in seconds, into
+/// a Unix timestamp in seconds.
+pub(super) fn parse_timestamp_secs(timestamp_str: &str) -> Result<i64, PrometheusError> {
+    if let Ok(timestamp_secs) = timestamp_str.parse::<f64>() {
+        if timestamp_secs.is_finite() {
+            return Ok(timestamp_secs.floor() as i64);
+        }
+    }
+    let system_time = humantime::parse_rfc3339_weak(timestamp_str).map_err(|_| {
+        PrometheusError::bad_data(format!(
+            "invalid timestamp `{timestamp_str}`: expected an RFC 3339 date or a Unix timestamp"
+        ))
+    })?;
+    let timestamp_secs = match system_time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(error) => -(error.duration().as_secs() as i64),
+    };
+    Ok(timestamp_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_timestamp_secs() {
+        assert_eq!(parse_timestamp_secs("1700000000").unwrap(), 1_700_000_000);
+        assert_eq!(
+            parse_timestamp_secs("1700000000.781").unwrap(),
+            1_700_000_000
+        );
+        assert_eq!(
+            parse_timestamp_secs("2023-11-14T22:13:20Z").unwrap(),
+            1_700_000_000
+        );
+        let error = parse_timestamp_secs("yesterday").unwrap_err();
+        assert_eq!(error.status_code, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_serialize_prometheus_responses() {
+        let series = Series {
+            labels: BTreeMap::from([("service".to_string(), "api".to_string())]),
+            samples: vec![(1_020, 3.0), (1_080, 0.5)],
+        };
+        let matrix =
+            PrometheusResponseBody::success(QueryResult::Matrix(vec![series.clone().into()]));
+        assert_eq!(
+            serde_json::to_value(matrix).unwrap(),
+            json!({
+                "status": "success",
+                "data": {
+                    "resultType": "matrix",
+                    "result": [{
+                        "metric": { "service": "api" },
+                        "values": [[1020, "3"], [1080, "0.5"]],
+                    }]
+                }
+            })
+        );
+        let vector = PrometheusResponseBody::success(QueryResult::Vector(
+            VectorSample::from_series(series).into_iter().collect(),
+        ));
+        assert_eq!(
+            serde_json::to_value(vector).unwrap(),
+            json!({
+                "status": "success",
+                "data": {
+                    "resultType": "vector",
+                    "result": [{
+                        "metric": { "service": "api" },
+                        "value": [1080, "0.5"],
+                    }]
+                }
+            })
+        );
+        let error = PrometheusError::bad_data("invalid query".to_string());
+        assert_eq!(
+            serde_json::to_value(error).unwrap(),
+            json!({
+                "status": "error",
+                "errorType": "bad_data",
+                "error": "invalid query",
+            })
+        );
+    }
+}
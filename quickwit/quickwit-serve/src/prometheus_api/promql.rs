@@ -0,0 +1,731 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Parser for the subset of PromQL supported by the Prometheus API.
+//!
+//! Supported queries are range functions applied to a selector over log documents, optionally
+//! wrapped into a `sum` aggregation:
+//! - `count_over_time(<selector>[<range>])`
+//! - `rate(<selector>[<range>])`
+//! - `quantile_over_time(<quantile>, <field>{<matchers>}[<range>])`
+//! - `sum [by (<labels>)] (<range function>)`
+//!
+//! Labels are fast fields of the searched indexes: they can be dotted paths, e.g.
+//! `{resource.service_name="api"}`. The metric name, if any, is the field holding the values of
+//! the series, and only documents having a value for it are taken into account.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::Duration;
+
+use quickwit_query::query_ast::{
+    BoolQuery, FieldPresenceQuery, QueryAst, TermQuery, TermSetQuery, WildcardQuery,
+};
+
+/// Label carrying the metric name in Prometheus.
+const METRIC_NAME_LABEL: &str = "__name__";
+
+const REGEX_METACHARACTERS: &[char] = &[
+    '\\', '.', '+', '*', '?', '(', ')', '|', '[', ']', '{', '}', '^', '$',
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MatchOp {
+    Equal,
+    NotEqual,
+    RegexMatch,
+    RegexNotMatch,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LabelMatcher {
+    pub name: String,
+    pub op: MatchOp,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum RangeFunction {
+    CountOverTime,
+    Rate,
+    QuantileOverTime(f64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PromqlQuery {
+    pub function: RangeFunction,
+    pub metric_name: Option<String>,
+    pub label_matchers: Vec<LabelMatcher>,
+    pub range: Duration,
+    /// Labels of the `sum` aggregation wrapping the range function, if any. `sum(...)` without
+    /// `by` clause is represented by an empty list of labels.
+    pub sum_by: Option<Vec<String>>,
+}
+
+impl PromqlQuery {
+    /// Builds the query AST selecting the documents matched by the selector.
+    pub fn query_ast(&self) -> Result<QueryAst, String> {
+        let Some(mut bool_query) = label_matchers_bool_query(&self.label_matchers)? else {
+            return Ok(QueryAst::MatchNone);
+        };
+        if let Some(metric_name) = &self.metric_name {
+            bool_query.filter.insert(0, exists_query(metric_name));
+        }
+        if bool_query.filter.is_empty() && bool_query.must_not.is_empty() {
+            return Ok(QueryAst::MatchAll);
+        }
+        Ok(bool_query.into())
+    }
+
+    /// Returns the labels of the series when the range function is not wrapped into a `sum`
+    /// aggregation. Documents have no notion of series, so all the documents matching the
+    /// selector make up a single series identified by the equality matchers of the selector.
+    pub fn selector_labels(&self) -> BTreeMap<String, String> {
+        self.label_matchers
+            .iter()
+            .filter(|label_matcher| {
+                label_matcher.op == MatchOp::Equal && !label_matcher.value.is_empty()
+            })
+            .map(|label_matcher| (label_matcher.name.clone(), label_matcher.value.clone()))
+            .collect()
+    }
+}
+
+/// Builds the boolean query selecting the documents matched by label matchers, or returns `None`
+/// if no document can match.
+pub(crate) fn label_matchers_bool_query(
+    label_matchers: &[LabelMatcher],
+) -> Result<Option<BoolQuery>, String> {
+    let mut bool_query = BoolQuery::default();
+
+    for label_matcher in label_matchers {
+        let field = label_matcher.name.as_str();
+        let value = label_matcher.value.as_str();
+
+        match label_matcher.op {
+            // In Prometheus, matching the empty string matches series without the label.
+            MatchOp::Equal if value.is_empty() => bool_query.must_not.push(exists_query(field)),
+            MatchOp::Equal => bool_query.filter.push(term_query(field, value)),
+            MatchOp::NotEqual if value.is_empty() => bool_query.filter.push(exists_query(field)),
+            MatchOp::NotEqual => bool_query.must_not.push(term_query(field, value)),
+            MatchOp::RegexMatch => match regex_query_ast(field, value)? {
+                RegexQueryAst::MatchAll => {}
+                RegexQueryAst::Query(query_ast) => bool_query.filter.push(query_ast),
+            },
+            MatchOp::RegexNotMatch => match regex_query_ast(field, value)? {
+                RegexQueryAst::MatchAll => return Ok(None),
+                RegexQueryAst::Query(query_ast) => bool_query.must_not.push(query_ast),
+            },
+        }
+    }
+    Ok(Some(bool_query))
+}
+
+fn exists_query(field: &str) -> QueryAst {
+    FieldPresenceQuery {
+        field: field.to_string(),
+    }
+    .into()
+}
+
+fn term_query(field: &str, value: &str) -> QueryAst {
+    TermQuery {
+        field: field.to_string(),
+        value: value.to_string(),
+    }
+    .into()
+}
+
+enum RegexQueryAst {
+    MatchAll,
+    Query(QueryAst),
+}
+
+/// Converts a regex label matcher into a query AST.
+///
+/// Only the regexes commonly generated by Grafana are supported: `.*`, `.+`, alternations of
+/// literals (`a|b|c`), and literal prefixes (`abc.*`).
+fn regex_query_ast(field: &str, regex: &str) -> Result<RegexQueryAst, String> {
+    if regex == ".*" {
+        return Ok(RegexQueryAst::MatchAll);
+    }
+    if regex == ".+" {
+        return Ok(RegexQueryAst::Query(exists_query(field)));
+    }
+    if let Some(prefix) = regex.strip_suffix(".*").and_then(unescape_regex_literal) {
+        if !prefix.is_empty() && !prefix.contains(['*', '?']) {
+            let wildcard_query = WildcardQuery {
+                field: field.to_string(),
+                value: format!("{prefix}*"),
+            };
+            return Ok(RegexQueryAst::Query(wildcard_query.into()));
+        }
+    }
+    let Some(mut alternatives) = regex_literal_alternatives(regex) else {
+        return Err(format!(
+            "unsupported regex `{regex}` for label `{field}`: only `.*`, `.+`, alternations of \
+             literals and literal prefixes are supported"
+        ));
+    };
+    if alternatives.len() == 1 {
+        let value = alternatives
+            .pop_first()
+            .expect("alternatives should not be empty");
+        return Ok(RegexQueryAst::Query(term_query(field, &value)));
+    }
+    let terms_per_field = HashMap::from([(field.to_string(), alternatives)]);
+    Ok(RegexQueryAst::Query(
+        TermSetQuery { terms_per_field }.into(),
+    ))
+}
+
+/// Returns the literals matched by `regex` if it is an alternation of literals, e.g. `a|b|c`.
+pub(crate) fn regex_literal_alternatives(regex: &str) -> Option<BTreeSet<String>> {
+    split_regex_alternatives(regex)
+        .into_iter()
+        .map(unescape_regex_literal)
+        .collect()
+}
+
+fn split_regex_alternatives(regex: &str) -> Vec<&str> {
+    let mut alternatives = Vec::new();
+    let mut alternative_start = 0;
+    let mut escaped = false;
+
+    for (idx, chr) in regex.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if chr == '\\' {
+            escaped = true;
+        } else if chr == '|' {
+            alternatives.push(&regex[alternative_start..idx]);
+            alternative_start = idx + 1;
+        }
+    }
+    alternatives.push(&regex[alternative_start..]);
+    alternatives
+}
+
+/// Returns the literal matched by `regex` if it does not contain any unescaped metacharacter.
+fn unescape_regex_literal(regex: &str) -> Option<String> {
+    let mut literal = String::with_capacity(regex.len());
+    let mut chars = regex.chars();
+
+    while let Some(chr) = chars.next() {
+        if chr == '\\' {
+            let escaped_chr = chars.next()?;
+            if !REGEX_METACHARACTERS.contains(&escaped_chr) {
+                return None;
+            }
+            literal.push(escaped_chr);
+        } else if REGEX_METACHARACTERS.contains(&chr) {
+            return None;
+        } else {
+            literal.push(chr);
+        }
+    }
+    Some(literal)
+}
+
+/// Parses a Prometheus duration, e.g. `5m`, `1h30m`, or a number of seconds, e.g. `15` or `0.5`.
+pub(crate) fn parse_duration(duration_str: &str) -> Result<Duration, String> {
+    let duration_str = duration_str.trim();
+
+    if let Ok(secs) = duration_str.parse::<f64>() {
+        return Duration::try_from_secs_f64(secs)
+            .map_err(|error| format!("invalid duration `{duration_str}`: {error}"));
+    }
+    let mut parser = Parser::new(duration_str);
+    let duration = parser.parse_duration()?;
+    parser.expect_end()?;
+    Ok(duration)
+}
+
+/// Parses a PromQL query.
+pub(super) fn parse_promql(query: &str) -> Result<PromqlQuery, String> {
+    let mut parser = Parser::new(query);
+    let identifier = parser
+        .parse_identifier()
+        .ok_or_else(|| parser.error("expected a function name"))?;
+
+    let promql_query = if identifier == "sum" {
+        let mut sum_by = parser.parse_grouping()?;
+        parser.expect_char('(')?;
+        let function_name = parser
+            .parse_identifier()
+            .ok_or_else(|| parser.error("expected a function name"))?;
+        let mut promql_query = parser.parse_range_function(function_name)?;
+        parser.expect_char(')')?;
+
+        if sum_by.is_none() {
+            sum_by = parser.parse_grouping()?;
+        }
+        promql_query.sum_by = Some(sum_by.unwrap_or_default());
+        promql_query
+    } else {
+        parser.parse_range_function(identifier)?
+    };
+    parser.expect_end()?;
+    Ok(promql_query)
+}
+
+/// Parser shared by the PromQL and LogQL parsers, which have the same lexical structure.
+pub(crate) struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    pub(crate) fn new(input: &'a str) -> Self {
+        Self { input, position: 0 }
+    }
+
+    pub(crate) fn error(&self, message: &str) -> String {
+        format!(
+            "failed to parse query `{}` at position {}: {message}",
+            self.input, self.position
+        )
+    }
+
+    fn remaining(&self) -> &'a str {
+        &self.input[self.position..]
+    }
+
+    fn skip_whitespaces(&mut self) {
+        let remaining = self.remaining();
+        self.position += remaining.len() - remaining.trim_start().len();
+    }
+
+    pub(crate) fn peek_char(&mut self) -> Option<char> {
+        self.skip_whitespaces();
+        self.remaining().chars().next()
+    }
+
+    pub(crate) fn consume_char(&mut self, expected_chr: char) -> bool {
+        if self.peek_char() == Some(expected_chr) {
+            self.position += expected_chr.len_utf8();
+            return true;
+        }
+        false
+    }
+
+    /// Consumes `expected_str` if the remaining input starts with it.
+    pub(crate) fn consume_str(&mut self, expected_str: &str) -> bool {
+        self.skip_whitespaces();
+
+        if self.remaining().starts_with(expected_str) {
+            self.position += expected_str.len();
+            return true;
+        }
+        false
+    }
+
+    pub(crate) fn expect_char(&mut self, expected_chr: char) -> Result<(), String> {
+        if self.consume_char(expected_chr) {
+            return Ok(());
+        }
+        Err(self.error(&format!("expected `{expected_chr}`")))
+    }
+
+    pub(crate) fn expect_end(&mut self) -> Result<(), String> {
+        if self.peek_char().is_some() {
+            return Err(self.error("unexpected trailing characters"));
+        }
+        Ok(())
+    }
+
+    /// Parses a metric, label or function name. Contrary to Prometheus, label names can contain
+    /// dots so that they can refer to the fields of objects.
+    pub(crate) fn parse_identifier(&mut self) -> Option<&'a str> {
+        self.skip_whitespaces();
+        let remaining = self.remaining();
+        let mut chars = remaining.char_indices();
+
+        match chars.next() {
+            Some((_, chr)) if chr.is_ascii_alphabetic() || chr == '_' => {}
+            _ => return None,
+        }
+        let identifier_len = chars
+            .find(|(_, chr)| !(chr.is_ascii_alphanumeric() || matches!(chr, '_' | '.' | ':')))
+            .map(|(idx, _)| idx)
+            .unwrap_or(remaining.len());
+        self.position += identifier_len;
+        Some(&remaining[..identifier_len])
+    }
+
+    /// Parses an optional `by (<labels>)` clause.
+    pub(crate) fn parse_grouping(&mut self) -> Result<Option<Vec<String>>, String> {
+        let position = self.position;
+
+        match self.parse_identifier() {
+            Some("by") => {}
+            Some("without") => return Err(self.error("`without` clauses are not supported")),
+            _ => {
+                self.position = position;
+                return Ok(None);
+            }
+        }
+        self.expect_char('(')?;
+        let mut labels = Vec::new();
+
+        while !self.consume_char(')') {
+            let label = self
+                .parse_identifier()
+                .ok_or_else(|| self.error("expected a label name"))?;
+            labels.push(label.to_string());
+
+            if !self.consume_char(',') {
+                self.expect_char(')')?;
+                break;
+            }
+        }
+        Ok(Some(labels))
+    }
+
+    fn parse_range_function(&mut self, function_name: &str) -> Result<PromqlQuery, String> {
+        let function = match function_name {
+            "count_over_time" => RangeFunction::CountOverTime,
+            "rate" => RangeFunction::Rate,
+            "quantile_over_time" => RangeFunction::QuantileOverTime(0.0),
+            _ => {
+                return Err(format!(
+                    "unsupported function `{function_name}`: supported functions are \
+                     `count_over_time`, `rate`, `quantile_over_time` and `sum`"
+                ))
+            }
+        };
+        self.expect_char('(')?;
+
+        let function = if let RangeFunction::QuantileOverTime(_) = function {
+            let quantile = self.parse_number()?;
+
+            if !(0.0..=1.0).contains(&quantile) {
+                return Err(format!(
+                    "quantile must be between 0 and 1, got `{quantile}`"
+                ));
+            }
+            self.expect_char(',')?;
+            RangeFunction::QuantileOverTime(quantile)
+        } else {
+            function
+        };
+        let mut metric_name = self.parse_identifier().map(str::to_string);
+        let label_matchers = if self.consume_char('{') {
+            self.parse_label_matchers()?
+        } else {
+            Vec::new()
+        };
+        if metric_name.is_none() && label_matchers.is_empty() {
+            return Err(self.error("expected a selector"));
+        }
+        let mut selector_matchers = Vec::with_capacity(label_matchers.len());
+
+        for label_matcher in label_matchers {
+            if label_matcher.name != METRIC_NAME_LABEL {
+                selector_matchers.push(label_matcher);
+                continue;
+            }
+            if label_matcher.op != MatchOp::Equal || metric_name.is_some() {
+                return Err(format!(
+                    "unsupported matcher on label `{METRIC_NAME_LABEL}`: the metric name can only \
+                     be set once, with the `=` operator"
+                ));
+            }
+            metric_name = Some(label_matcher.value);
+        }
+        if metric_name.is_none() && matches!(function, RangeFunction::QuantileOverTime(_)) {
+            return Err(
+                "`quantile_over_time` requires a metric name, i.e. the field to compute the \
+                 quantile of"
+                    .to_string(),
+            );
+        }
+        self.expect_char('[')?;
+        let range = self.parse_duration()?;
+        self.expect_char(']')?;
+        self.expect_char(')')?;
+
+        if range.is_zero() {
+            return Err("range must be strictly positive".to_string());
+        }
+        Ok(PromqlQuery {
+            function,
+            metric_name,
+            label_matchers: selector_matchers,
+            range,
+            sum_by: None,
+        })
+    }
+
+    /// Parses the label matchers of a selector, after the opening brace.
+    pub(crate) fn parse_label_matchers(&mut self) -> Result<Vec<LabelMatcher>, String> {
+        let mut label_matchers = Vec::new();
+
+        while !self.consume_char('}') {
+            let name = self
+                .parse_identifier()
+                .ok_or_else(|| self.error("expected a label name"))?
+                .to_string();
+            self.skip_whitespaces();
+            let remaining = self.remaining();
+            let (op, op_len) = if remaining.starts_with("=~") {
+                (MatchOp::RegexMatch, 2)
+            } else if remaining.starts_with("!~") {
+                (MatchOp::RegexNotMatch, 2)
+            } else if remaining.starts_with("!=") {
+                (MatchOp::NotEqual, 2)
+            } else if remaining.starts_with('=') {
+                (MatchOp::Equal, 1)
+            } else {
+                return Err(self.error("expected a label matching operator"));
+            };
+            self.position += op_len;
+            let value = self.parse_string()?;
+            label_matchers.push(LabelMatcher { name, op, value });
+
+            if !self.consume_char(',') {
+                self.expect_char('}')?;
+                break;
+            }
+        }
+        Ok(label_matchers)
+    }
+
+    /// Parses a string literal delimited by double quotes, single quotes or backticks.
+    pub(crate) fn parse_string(&mut self) -> Result<String, String> {
+        let quote = match self.peek_char() {
+            Some(quote @ ('"' | '\'' | '`')) => quote,
+            _ => return Err(self.error("expected a string")),
+        };
+        let mut value = String::new();
+        let mut chars = self.remaining().char_indices().skip(1);
+
+        while let Some((idx, chr)) = chars.next() {
+            if chr == quote {
+                self.position += idx + 1;
+                return Ok(value);
+            }
+            if chr == '\\' && quote != '`' {
+                let Some((_, escaped_chr)) = chars.next() else {
+                    break;
+                };
+                match escaped_chr {
+                    'n' => value.push('\n'),
+                    't' => value.push('\t'),
+                    _ => value.push(escaped_chr),
+                }
+            } else {
+                value.push(chr);
+            }
+        }
+        Err(self.error("unterminated string"))
+    }
+
+    fn parse_number(&mut self) -> Result<f64, String> {
+        self.skip_whitespaces();
+        let remaining = self.remaining();
+        let number_len = remaining
+            .find(|chr: char| !(chr.is_ascii_digit() || chr == '.'))
+            .unwrap_or(remaining.len());
+        let number = remaining[..number_len]
+            .parse::<f64>()
+            .map_err(|_| self.error("expected a number"))?;
+        self.position += number_len;
+        Ok(number)
+    }
+
+    /// Parses a duration such as `5m` or `1h30m`.
+    pub(crate) fn parse_duration(&mut self) -> Result<Duration, String> {
+        self.skip_whitespaces();
+        let mut duration = Duration::ZERO;
+        let mut num_units = 0;
+
+        loop {
+            let remaining = self.remaining();
+            let digits_len = remaining
+                .find(|chr: char| !chr.is_ascii_digit())
+                .unwrap_or(remaining.len());
+            if digits_len == 0 {
+                break;
+            }
+            let value: u32 = remaining[..digits_len]
+                .parse()
+                .map_err(|_| self.error("invalid duration"))?;
+            let unit_str = &remaining[digits_len..];
+            let (unit, unit_len) = if unit_str.starts_with("ms") {
+                (Duration::from_millis(1), 2)
+            } else {
+                let unit = match unit_str.chars().next() {
+                    Some('s') => Duration::from_secs(1),
+                    Some('m') => Duration::from_secs(60),
+                    Some('h') => Duration::from_secs(3_600),
+                    Some('d') => Duration::from_secs(86_400),
+                    Some('w') => Duration::from_secs(7 * 86_400),
+                    Some('y') => Duration::from_secs(365 * 86_400),
+                    _ => return Err(self.error("expected a duration unit")),
+                };
+                (unit, 1)
+            };
+            duration = unit
+                .checked_mul(value)
+                .and_then(|unit_duration| duration.checked_add(unit_duration))
+                .ok_or_else(|| self.error("duration overflow"))?;
+            self.position += digits_len + unit_len;
+            num_units += 1;
+        }
+        if num_units == 0 {
+            return Err(self.error("expected a duration"));
+        }
+        Ok(duration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_promql_range_functions() {
+        let promql_query =
+            parse_promql(r#"count_over_time({service="api", level!="debug"}[5m])"#).unwrap();
+        assert_eq!(promql_query.function, RangeFunction::CountOverTime);
+        assert_eq!(promql_query.metric_name, None);
+        assert_eq!(promql_query.range, Duration::from_secs(300));
+        assert_eq!(promql_query.sum_by, None);
+        assert_eq!(
+            promql_query.label_matchers,
+            vec![
+                LabelMatcher {
+                    name: "service".to_string(),
+                    op: MatchOp::Equal,
+                    value: "api".to_string(),
+                },
+                LabelMatcher {
+                    name: "level".to_string(),
+                    op: MatchOp::NotEqual,
+                    value: "debug".to_string(),
+                },
+            ]
+        );
+        let promql_query =
+            parse_promql(r#"quantile_over_time(0.99, latency_ms{resource.service="api"}[1h30m])"#)
+                .unwrap();
+        assert_eq!(promql_query.function, RangeFunction::QuantileOverTime(0.99));
+        assert_eq!(promql_query.metric_name.as_deref(), Some("latency_ms"));
+        assert_eq!(promql_query.range, Duration::from_secs(5_400));
+        assert_eq!(promql_query.label_matchers[0].name, "resource.service");
+
+        let promql_query = parse_promql(r#"rate({__name__="latency_ms"}[30s])"#).unwrap();
+        assert_eq!(promql_query.function, RangeFunction::Rate);
+        assert_eq!(promql_query.metric_name.as_deref(), Some("latency_ms"));
+        assert!(promql_query.label_matchers.is_empty());
+    }
+
+    #[test]
+    fn test_parse_promql_sum() {
+        let promql_query =
+            parse_promql(r#"sum by (service, level) (rate({env="prod"}[1m]))"#).unwrap();
+        assert_eq!(
+            promql_query.sum_by,
+            Some(vec!["service".to_string(), "level".to_string()])
+        );
+        let promql_query = parse_promql(r#"sum(rate({env="prod"}[1m])) by (service)"#).unwrap();
+        assert_eq!(promql_query.sum_by, Some(vec!["service".to_string()]));
+
+        let promql_query = parse_promql(r#"sum(count_over_time({env="prod"}[1m]))"#).unwrap();
+        assert_eq!(promql_query.sum_by, Some(Vec::new()));
+    }
+
+    #[test]
+    fn test_parse_promql_errors() {
+        for invalid_query in [
+            "",
+            r#"{env="prod"}"#,
+            r#"avg_over_time({env="prod"}[1m])"#,
+            r#"rate({env="prod"})"#,
+            r#"rate({env="prod}[1m])"#,
+            r#"rate({env="prod"}[1m]) + 1"#,
+            r#"quantile_over_time(0.9, {env="prod"}[1m])"#,
+            r#"quantile_over_time(1.5, latency{env="prod"}[1m])"#,
+            r#"sum without (env) (rate({env="prod"}[1m]))"#,
+        ] {
+            parse_promql(invalid_query).unwrap_err();
+        }
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("15").unwrap(), Duration::from_secs(15));
+        assert_eq!(parse_duration("0.5").unwrap(), Duration::from_millis(500));
+        assert_eq!(parse_duration("1m30s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("250ms").unwrap(), Duration::from_millis(250));
+        assert_eq!(parse_duration("1d").unwrap(), Duration::from_secs(86_400));
+        parse_duration("1x").unwrap_err();
+        parse_duration("-1").unwrap_err();
+        parse_duration("NaN").unwrap_err();
+        parse_duration("inf").unwrap_err();
+        parse_duration("1e30").unwrap_err();
+        parse_duration(&"4294967295y".repeat(200)).unwrap_err();
+    }
+
+    #[test]
+    fn test_promql_query_ast() {
+        let promql_query = parse_promql(
+            r#"count_over_time(latency_ms{service="api", level!="debug", host=~"a|b", zone!~"eu-.*", team=""}[5m])"#,
+        )
+        .unwrap();
+        let QueryAst::Bool(bool_query) = promql_query.query_ast().unwrap() else {
+            panic!("expected a bool query");
+        };
+        assert_eq!(
+            bool_query.filter,
+            vec![
+                exists_query("latency_ms"),
+                term_query("service", "api"),
+                TermSetQuery {
+                    terms_per_field: HashMap::from([(
+                        "host".to_string(),
+                        BTreeSet::from(["a".to_string(), "b".to_string()])
+                    )])
+                }
+                .into(),
+            ]
+        );
+        assert_eq!(
+            bool_query.must_not,
+            vec![
+                term_query("level", "debug"),
+                WildcardQuery {
+                    field: "zone".to_string(),
+                    value: "eu-*".to_string(),
+                }
+                .into(),
+                exists_query("team"),
+            ]
+        );
+        assert_eq!(
+            promql_query.selector_labels(),
+            BTreeMap::from([("service".to_string(), "api".to_string())])
+        );
+        let promql_query = parse_promql(r#"rate({host=~".*"}[5m])"#).unwrap();
+        assert_eq!(promql_query.query_ast().unwrap(), QueryAst::MatchAll);
+
+        let promql_query = parse_promql(r#"rate({host=~"web-[0-9]+"}[5m])"#).unwrap();
+        promql_query.query_ast().unwrap_err();
+    }
+}
@@ -0,0 +1,597 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hyper::StatusCode;
+use quickwit_metastore::IndexMetadata;
+use quickwit_proto::metastore::MetastoreServiceClient;
+use quickwit_proto::search::{CountHits, ListFieldType, ListFieldsRequest, SearchRequest};
+use quickwit_query::query_ast::QueryAst;
+use quickwit_search::{resolve_index_patterns, SearchService};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value as JsonValue};
+use warp::{Filter, Rejection};
+
+use super::evaluation::{EvaluationRange, QueryEvaluation, Series, MAX_NUM_GROUPS_PER_LABEL};
+use super::model::{
+    parse_timestamp_secs, InstantQueryParams, LabelsQueryParams, MatrixSeries, PrometheusError,
+    PrometheusErrorType, PrometheusResponseBody, QueryResult, RangeQueryParams, VectorSample,
+};
+use super::promql::{parse_duration, parse_promql, PromqlQuery};
+use crate::rest::recover_fn;
+use crate::rest_api_response::RestApiResponse;
+use crate::search_api::{self, extract_index_id_patterns};
+use crate::{with_arg, BodyFormat};
+
+#[derive(utoipa::OpenApi)]
+#[openapi(paths(
+    prometheus_instant_query_handler,
+    prometheus_range_query_handler,
+    prometheus_labels_handler,
+    prometheus_label_values_handler
+))]
+pub(crate) struct PrometheusApi;
+
+/// Setup Prometheus API handlers
+///
+/// This is where all Prometheus handlers
+/// should be registered.
+/// Grafana can use `<quickwit-url>/api/v1/<index-id>/prometheus` as a Prometheus data source.
+pub(crate) fn prometheus_api_handlers(
+    search_service: Arc<dyn SearchService>,
+    metastore: MetastoreServiceClient,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    prometheus_instant_query_handler(search_service.clone(), metastore.clone())
+        .or(prometheus_range_query_handler(
+            search_service.clone(),
+            metastore,
+        ))
+        .or(prometheus_labels_handler(search_service.clone()))
+        .or(prometheus_label_values_handler(search_service))
+        .recover(recover_fn)
+        .boxed()
+}
+
+fn prometheus_api_path_filter() -> impl Filter<Extract = (Vec<String>,), Error = Rejection> + Clone
+{
+    warp::path!(String / "prometheus" / "api" / "v1" / ..).and_then(extract_index_id_patterns)
+}
+
+/// Prometheus accepts parameters either in the query string of GET requests or in the
+/// URL-encoded body of POST requests.
+fn prometheus_params_filter<T: DeserializeOwned + Send + 'static>(
+) -> impl Filter<Extract = (T,), Error = Rejection> + Clone {
+    warp::get()
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+        .or(warp::post().and(warp::body::form()))
+        .unify()
+}
+
+#[utoipa::path(
+    get,
+    tag = "Prometheus",
+    path = "/{index-id}/prometheus/api/v1/query",
+    responses(
+        (status = 200, description = "Successfully evaluated the PromQL query.")
+    ),
+    params(
+        ("index-id" = String, Path, description = "The index ID patterns of the indexes to query."),
+        ("query" = String, Query, description = "PromQL query."),
+        ("time" = Option<String>, Query, description = "Evaluation timestamp, RFC 3339 or Unix timestamp in seconds."),
+    )
+)]
+pub fn prometheus_instant_query_handler(
+    search_service: Arc<dyn SearchService>,
+    metastore: MetastoreServiceClient,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    prometheus_api_path_filter()
+        .and(warp::path!("query"))
+        .and(prometheus_params_filter::<InstantQueryParams>())
+        .and(with_arg(search_service))
+        .and(with_arg(metastore))
+        .then(prometheus_instant_query)
+        .map(make_prometheus_api_response)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Prometheus",
+    path = "/{index-id}/prometheus/api/v1/query_range",
+    responses(
+        (status = 200, description = "Successfully evaluated the PromQL query.")
+    ),
+    params(
+        ("index-id" = String, Path, description = "The index ID patterns of the indexes to query."),
+        ("query" = String, Query, description = "PromQL query."),
+        ("start" = String, Query, description = "Start timestamp, RFC 3339 or Unix timestamp in seconds."),
+        ("end" = String, Query, description = "End timestamp, RFC 3339 or Unix timestamp in seconds."),
+        ("step" = String, Query, description = "Resolution step, duration or number of seconds."),
+    )
+)]
+pub fn prometheus_range_query_handler(
+    search_service: Arc<dyn SearchService>,
+    metastore: MetastoreServiceClient,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    prometheus_api_path_filter()
+        .and(warp::path!("query_range"))
+        .and(prometheus_params_filter::<RangeQueryParams>())
+        .and(with_arg(search_service))
+        .and(with_arg(metastore))
+        .then(prometheus_range_query)
+        .map(make_prometheus_api_response)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Prometheus",
+    path = "/{index-id}/prometheus/api/v1/labels",
+    responses(
+        (status = 200, description = "Successfully fetched label names.")
+    ),
+    params(
+        ("index-id" = String, Path, description = "The index ID patterns of the indexes to query."),
+        ("start" = Option<String>, Query, description = "Start timestamp, RFC 3339 or Unix timestamp in seconds."),
+        ("end" = Option<String>, Query, description = "End timestamp, RFC 3339 or Unix timestamp in seconds."),
+    )
+)]
+pub fn prometheus_labels_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    prometheus_api_path_filter()
+        .and(warp::path!("labels"))
+        .and(prometheus_params_filter::<LabelsQueryParams>())
+        .and(with_arg(search_service))
+        .then(prometheus_labels)
+        .map(make_prometheus_api_response)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Prometheus",
+    path = "/{index-id}/prometheus/api/v1/label/{label}/values",
+    responses(
+        (status = 200, description = "Successfully fetched label values.")
+    ),
+    params(
+        ("index-id" = String, Path, description = "The index ID patterns of the indexes to query."),
+        ("label" = String, Path, description = "The label name."),
+        ("start" = Option<String>, Query, description = "Start timestamp, RFC 3339 or Unix timestamp in seconds."),
+        ("end" = Option<String>, Query, description = "End timestamp, RFC 3339 or Unix timestamp in seconds."),
+    )
+)]
+pub fn prometheus_label_values_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    prometheus_api_path_filter()
+        .and(warp::path!("label" / String / "values"))
+        .and(prometheus_params_filter::<LabelsQueryParams>())
+        .and(with_arg(search_service))
+        .then(prometheus_label_values)
+        .map(make_prometheus_api_response)
+}
+
+async fn prometheus_instant_query(
+    index_id_patterns: Vec<String>,
+    params: InstantQueryParams,
+    search_service: Arc<dyn SearchService>,
+    metastore: MetastoreServiceClient,
+) -> Result<PrometheusResponseBody<QueryResult>, PrometheusError> {
+    let promql_query = parse_promql(&params.query).map_err(PrometheusError::bad_data)?;
+    let time_secs = if let Some(time) = &params.time {
+        parse_timestamp_secs(time)?
+    } else {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time should be after the Unix epoch")
+            .as_secs() as i64
+    };
+    // An instant query is a range query with a single evaluation, which window is covered by a
+    // single bucket.
+    let evaluation_range = EvaluationRange {
+        start_secs: time_secs,
+        end_secs: time_secs,
+        step_secs: (promql_query.range.as_secs() as i64).max(1),
+    };
+    let series = evaluate_promql_query(
+        index_id_patterns,
+        promql_query,
+        evaluation_range,
+        search_service,
+        metastore,
+    )
+    .await?;
+    let samples = series
+        .into_iter()
+        .filter_map(VectorSample::from_series)
+        .collect();
+    Ok(PrometheusResponseBody::success(QueryResult::Vector(
+        samples,
+    )))
+}
+
+async fn prometheus_range_query(
+    index_id_patterns: Vec<String>,
+    params: RangeQueryParams,
+    search_service: Arc<dyn SearchService>,
+    metastore: MetastoreServiceClient,
+) -> Result<PrometheusResponseBody<QueryResult>, PrometheusError> {
+    let promql_query = parse_promql(&params.query).map_err(PrometheusError::bad_data)?;
+    let step = parse_duration(&params.step).map_err(PrometheusError::bad_data)?;
+    let evaluation_range = EvaluationRange {
+        start_secs: parse_timestamp_secs(&params.start)?,
+        end_secs: parse_timestamp_secs(&params.end)?,
+        step_secs: step.as_secs() as i64,
+    };
+    let series = evaluate_promql_query(
+        index_id_patterns,
+        promql_query,
+        evaluation_range,
+        search_service,
+        metastore,
+    )
+    .await?;
+    let matrix = series.into_iter().map(MatrixSeries::from).collect();
+    Ok(PrometheusResponseBody::success(QueryResult::Matrix(matrix)))
+}
+
+async fn evaluate_promql_query(
+    index_id_patterns: Vec<String>,
+    promql_query: PromqlQuery,
+    evaluation_range: EvaluationRange,
+    search_service: Arc<dyn SearchService>,
+    metastore: MetastoreServiceClient,
+) -> Result<Vec<Series>, PrometheusError> {
+    let query_evaluation =
+        QueryEvaluation::new(promql_query, evaluation_range).map_err(PrometheusError::bad_data)?;
+    let timestamp_field = resolve_timestamp_field(&index_id_patterns, metastore).await?;
+    let search_request = query_evaluation
+        .search_request(index_id_patterns, &timestamp_field)
+        .map_err(PrometheusError::bad_data)?;
+    let search_response = search_service.root_search(search_request).await?;
+
+    let Some(aggregation_json) = search_response.aggregation else {
+        return Ok(Vec::new());
+    };
+    let aggregation_results: JsonValue =
+        serde_json::from_str(&aggregation_json).map_err(|error| {
+            PrometheusError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                PrometheusErrorType::Internal,
+                format!("failed to parse aggregation results: {error}"),
+            )
+        })?;
+    Ok(query_evaluation.series(&aggregation_results))
+}
+
+/// Returns the timestamp field shared by the indexes matching the index ID patterns.
+async fn resolve_timestamp_field(
+    index_id_patterns: &[String],
+    mut metastore: MetastoreServiceClient,
+) -> Result<String, PrometheusError> {
+    let indexes_metadata = resolve_index_patterns(index_id_patterns, &mut metastore).await?;
+    shared_timestamp_field(&indexes_metadata)
+}
+
+/// Returns the timestamp field shared by the indexes, which must exist.
+pub(crate) fn shared_timestamp_field(
+    indexes_metadata: &[IndexMetadata],
+) -> Result<String, PrometheusError> {
+    search_api::shared_timestamp_field(indexes_metadata).map_err(PrometheusError::from)
+}
+
+fn parse_time_bounds(
+    params: &LabelsQueryParams,
+) -> Result<(Option<i64>, Option<i64>), PrometheusError> {
+    let start_timestamp = params
+        .start
+        .as_deref()
+        .map(parse_timestamp_secs)
+        .transpose()?;
+    let end_timestamp = params
+        .end
+        .as_deref()
+        .map(parse_timestamp_secs)
+        .transpose()?;
+    Ok((start_timestamp, end_timestamp))
+}
+
+async fn prometheus_labels(
+    index_id_patterns: Vec<String>,
+    params: LabelsQueryParams,
+    search_service: Arc<dyn SearchService>,
+) -> Result<PrometheusResponseBody<Vec<String>>, PrometheusError> {
+    let (start_timestamp, end_timestamp) = parse_time_bounds(&params)?;
+    let labels = list_labels(
+        index_id_patterns,
+        start_timestamp,
+        end_timestamp,
+        search_service,
+    )
+    .await?;
+    Ok(PrometheusResponseBody::success(labels))
+}
+
+async fn prometheus_label_values(
+    index_id_patterns: Vec<String>,
+    label: String,
+    params: LabelsQueryParams,
+    search_service: Arc<dyn SearchService>,
+) -> Result<PrometheusResponseBody<Vec<String>>, PrometheusError> {
+    let (start_timestamp, end_timestamp) = parse_time_bounds(&params)?;
+    let label_values = list_label_values(
+        index_id_patterns,
+        label,
+        QueryAst::MatchAll,
+        start_timestamp,
+        end_timestamp,
+        search_service,
+    )
+    .await?;
+    Ok(PrometheusResponseBody::success(label_values))
+}
+
+/// Lists the labels, i.e. the fields series can be grouped by, of the indexes. Timestamps are
+/// expressed in seconds.
+pub(crate) async fn list_labels(
+    index_id_patterns: Vec<String>,
+    start_timestamp: Option<i64>,
+    end_timestamp: Option<i64>,
+    search_service: Arc<dyn SearchService>,
+) -> Result<Vec<String>, PrometheusError> {
+    let list_fields_request = ListFieldsRequest {
+        index_id_patterns,
+        fields: Vec::new(),
+        start_timestamp,
+        end_timestamp,
+    };
+    let list_fields_response = search_service.root_list_fields(list_fields_request).await?;
+    let labels: BTreeSet<String> = list_fields_response
+        .fields
+        .into_iter()
+        .filter(|field| {
+            field.aggregatable
+                && !field.field_name.starts_with('_')
+                && !matches!(
+                    field.field_type(),
+                    ListFieldType::Date | ListFieldType::Bytes | ListFieldType::Facet
+                )
+        })
+        .map(|field| field.field_name)
+        .collect();
+    Ok(labels.into_iter().collect())
+}
+
+/// Lists the values of a label over the documents matched by `query_ast`, in lexicographic
+/// order. Timestamps are expressed in seconds.
+pub(crate) async fn list_label_values(
+    index_id_patterns: Vec<String>,
+    label: String,
+    query_ast: QueryAst,
+    start_timestamp: Option<i64>,
+    end_timestamp: Option<i64>,
+    search_service: Arc<dyn SearchService>,
+) -> Result<Vec<String>, PrometheusError> {
+    let aggregation = json!({
+        "values": {
+            "terms": {
+                "field": label,
+                "size": MAX_NUM_GROUPS_PER_LABEL,
+            }
+        }
+    });
+    let search_request = SearchRequest {
+        index_id_patterns,
+        query_ast: serde_json::to_string(&query_ast).expect("failed to serialize query AST"),
+        start_timestamp,
+        end_timestamp,
+        max_hits: 0,
+        aggregation_request: Some(aggregation.to_string()),
+        count_hits: CountHits::Underestimate.into(),
+        ..Default::default()
+    };
+    let search_response = search_service.root_search(search_request).await?;
+
+    let Some(aggregation_json) = search_response.aggregation else {
+        return Ok(Vec::new());
+    };
+    let aggregation_results: JsonValue =
+        serde_json::from_str(&aggregation_json).unwrap_or_default();
+    let label_values: BTreeSet<String> = aggregation_results["values"]["buckets"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|bucket| match &bucket["key"] {
+            JsonValue::String(key) => Some(key.clone()),
+            JsonValue::Null => None,
+            key => Some(key.to_string()),
+        })
+        .collect();
+    Ok(label_values.into_iter().collect())
+}
+
+fn make_prometheus_api_response<T: serde::Serialize>(
+    prometheus_result: Result<T, PrometheusError>,
+) -> RestApiResponse {
+    let status_code = match &prometheus_result {
+        Ok(_) => StatusCode::OK,
+        Err(error) => error.status_code,
+    };
+    RestApiResponse::new(&prometheus_result, status_code, BodyFormat::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_metastore::{IndexMetadata, ListIndexesMetadataResponseExt};
+    use quickwit_proto::metastore::{ListIndexesMetadataResponse, MockMetastoreService};
+    use quickwit_proto::search::{ListFieldsEntryResponse, ListFieldsResponse, SearchResponse};
+    use quickwit_search::MockSearchService;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_prometheus_range_query() {
+        let mut mock_metastore = MockMetastoreService::new();
+        mock_metastore
+            .expect_list_indexes_metadata()
+            .return_once(|_| {
+                let index_metadata = IndexMetadata::for_test("logs", "ram:///indexes/logs");
+                Ok(ListIndexesMetadataResponse::for_test(vec![index_metadata]))
+            });
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .withf(|search_request| {
+                search_request.index_id_patterns == vec!["logs".to_string()]
+                    && search_request.start_timestamp == Some(960)
+                    && search_request.end_timestamp == Some(1_080)
+                    && search_request
+                        .aggregation_request
+                        .as_ref()
+                        .unwrap()
+                        .contains(r#""field":"timestamp""#)
+            })
+            .return_once(|_| {
+                let aggregation = json!({
+                    "samples": {
+                        "buckets": [
+                            { "key": 960_000.0, "doc_count": 2 },
+                            { "key": 1_020_000.0, "doc_count": 4 },
+                        ]
+                    }
+                });
+                Ok(SearchResponse {
+                    aggregation: Some(aggregation.to_string()),
+                    ..Default::default()
+                })
+            });
+        let prometheus_api_handler = prometheus_api_handlers(
+            Arc::new(mock_search_service),
+            MetastoreServiceClient::from_mock(mock_metastore),
+        );
+        let resp = warp::test::request()
+            .path(
+                "/logs/prometheus/api/v1/query_range?query=count_over_time(%7Blevel%3D%22error%22%7D%5B1m%5D)&start=1020&end=1080&step=60",
+            )
+            .reply(&prometheus_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(
+            resp_json,
+            json!({
+                "status": "success",
+                "data": {
+                    "resultType": "matrix",
+                    "result": [{
+                        "metric": { "level": "error" },
+                        "values": [[1020, "2"], [1080, "4"]],
+                    }]
+                }
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_prometheus_instant_query_invalid_query() {
+        let prometheus_api_handler = prometheus_api_handlers(
+            Arc::new(MockSearchService::new()),
+            MetastoreServiceClient::from_mock(MockMetastoreService::new()),
+        );
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/logs/prometheus/api/v1/query")
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body("query=avg_over_time(%7Blevel%3D%22error%22%7D%5B1m%5D)")
+            .reply(&prometheus_api_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(resp_json["status"], "error");
+        assert_eq!(resp_json["errorType"], "bad_data");
+    }
+
+    #[tokio::test]
+    async fn test_prometheus_labels_and_label_values() {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_list_fields()
+            .return_once(|_| {
+                let field = |field_name: &str, field_type: ListFieldType, aggregatable: bool| {
+                    ListFieldsEntryResponse {
+                        field_name: field_name.to_string(),
+                        field_type: field_type as i32,
+                        aggregatable,
+                        ..Default::default()
+                    }
+                };
+                Ok(ListFieldsResponse {
+                    fields: vec![
+                        field("service", ListFieldType::Str, true),
+                        field("message", ListFieldType::Str, false),
+                        field("timestamp", ListFieldType::Date, true),
+                        field("status", ListFieldType::U64, true),
+                    ],
+                })
+            });
+        mock_search_service
+            .expect_root_search()
+            .withf(|search_request| search_request.start_timestamp == Some(1_000))
+            .return_once(|_| {
+                let aggregation = json!({
+                    "values": {
+                        "buckets": [
+                            { "key": "api", "doc_count": 2 },
+                            { "key": "web", "doc_count": 1 },
+                        ]
+                    }
+                });
+                Ok(SearchResponse {
+                    aggregation: Some(aggregation.to_string()),
+                    ..Default::default()
+                })
+            });
+        let prometheus_api_handler = prometheus_api_handlers(
+            Arc::new(mock_search_service),
+            MetastoreServiceClient::from_mock(MockMetastoreService::new()),
+        );
+        let resp = warp::test::request()
+            .path("/logs/prometheus/api/v1/labels")
+            .reply(&prometheus_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(
+            resp_json,
+            json!({ "status": "success", "data": ["service", "status"] })
+        );
+        let resp = warp::test::request()
+            .path("/logs/prometheus/api/v1/label/service/values?start=1000")
+            .reply(&prometheus_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(
+            resp_json,
+            json!({ "status": "success", "data": ["api", "web"] })
+        );
+    }
+}
@@ -17,21 +17,29 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::convert::Infallible;
 use std::fmt::Formatter;
 use std::sync::Arc;
 
 use hyper::http::HeaderValue;
+use hyper::server::accept;
+use hyper::service::make_service_fn;
 use hyper::{http, Method, StatusCode};
+use quickwit_common::tls::{
+    has_client_cert, tls_incoming, ClientAuth, ReloadingServerConfig, ServerTlsStream,
+};
 use quickwit_common::tower::BoxFutureInfaillible;
+use quickwit_proto::metastore::MetastoreServiceClient;
 use quickwit_search::SearchService;
 use tokio::net::TcpListener;
 use tower::make::Shared;
-use tower::ServiceBuilder;
+use tower::{ServiceBuilder, ServiceExt};
 use tower_http::compression::predicate::{NotForContentType, Predicate, SizeAbove};
 use tower_http::compression::CompressionLayer;
 use tower_http::cors::CorsLayer;
 use tracing::{error, info};
 use warp::filters::log::Info;
+use warp::path::FullPath;
 use warp::{redirect, Filter, Rejection, Reply};
 
 use crate::cluster_api::cluster_handler;
@@ -39,18 +47,21 @@ use crate::decompression::{CorruptedData, UnsupportedEncoding};
 use crate::delete_task_api::delete_task_api_handlers;
 use crate::developer_api::developer_api_routes;
 use crate::elasticsearch_api::elastic_api_handlers;
+use crate::export_api::{export_api_handlers, ExportTaskManager};
 use crate::health_check_api::health_check_handlers;
 use crate::index_api::index_management_handlers;
 use crate::indexing_api::indexing_get_handler;
 use crate::ingest_api::ingest_api_handlers;
 use crate::jaeger_api::jaeger_api_handlers;
+use crate::loki_api::loki_api_handlers;
 use crate::metrics_api::metrics_handler;
 use crate::node_info_handler::node_info_handler;
 use crate::otlp_api::otlp_ingest_api_handlers;
+use crate::prometheus_api::prometheus_api_handlers;
 use crate::rest_api_response::{RestApiError, RestApiResponse};
 use crate::search_api::{
     search_get_handler, search_plan_get_handler, search_plan_post_handler, search_post_handler,
-    search_stream_handler,
+    search_stream_handler, search_tail_handler,
 };
 use crate::template_api::index_template_api_handlers;
 use crate::ui_handler::ui_handler;
@@ -66,6 +77,11 @@ pub(crate) struct InvalidArgument(pub String);
 
 impl warp::reject::Reject for InvalidArgument {}
 
+#[derive(Debug)]
+pub(crate) struct ClientCertRequired;
+
+impl warp::reject::Reject for ClientCertRequired {}
+
 #[derive(Debug)]
 pub struct TooManyRequests;
 
@@ -186,14 +202,23 @@ pub(crate) async fn start_rest_server(
             .clone(),
     );
 
+    let rest_tls_config_opt = quickwit_services.node_config.rest_config.tls.clone();
+    let require_client_cert_for_ingest = rest_tls_config_opt
+        .as_ref()
+        .map(|tls_config| tls_config.require_client_cert_for_ingest)
+        .unwrap_or(false);
+
     // Combine all the routes together.
-    let rest_routes = api_v1_root_route
-        .or(api_doc)
-        .or(redirect_root_to_ui_route)
-        .or(ui_handler())
-        .or(health_check_routes)
-        .or(metrics_routes)
-        .or(developer_routes)
+    let rest_routes = ingest_client_cert_guard(require_client_cert_for_ingest)
+        .and(
+            api_v1_root_route
+                .or(api_doc)
+                .or(redirect_root_to_ui_route)
+                .or(ui_handler())
+                .or(health_check_routes)
+                .or(metrics_routes)
+                .or(developer_routes),
+        )
         .with(request_counter)
         .recover(recover_fn_final)
         .with(extra_headers)
@@ -220,7 +245,6 @@ pub(crate) async fn start_rest_server(
         "Starting REST server listening on {rest_listen_addr}."
     );
 
-    let rest_listener_std = tcp_listener.into_std()?;
     // `graceful_shutdown()` seems to be blocking in presence of existing connections.
     // The following approach of dropping the serve supposedly is not bullet proof, but it seems to
     // work in our unit test.
@@ -228,6 +252,45 @@ pub(crate) async fn start_rest_server(
     // See more of the discussion here:
     // https://github.com/hyperium/hyper/issues/2386
 
+    if let Some(tls_config) = rest_tls_config_opt {
+        let client_auth = if tls_config.require_client_cert {
+            ClientAuth::Required
+        } else if tls_config.ca_path.is_some() {
+            ClientAuth::Optional
+        } else {
+            ClientAuth::Disabled
+        };
+        let server_config = ReloadingServerConfig::try_new(
+            tls_config.tls_paths(),
+            client_auth,
+            vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+        )?;
+        let incoming = accept::from_stream(tls_incoming(tcp_listener, server_config));
+        let make_service = make_service_fn(move |tls_stream: &ServerTlsStream| {
+            let has_client_cert = has_client_cert(tls_stream);
+            let service =
+                service
+                    .clone()
+                    .map_request(move |mut request: hyper::Request<hyper::Body>| {
+                        if has_client_cert {
+                            request.extensions_mut().insert(VerifiedClientCert);
+                        }
+                        request
+                    });
+            async move { Ok::<_, Infallible>(service) }
+        });
+        let serve_fut = async move {
+            tokio::select! {
+                 res = hyper::Server::builder(incoming).serve(make_service) => { res }
+                 _ = shutdown_signal => { Ok(()) }
+            }
+        };
+        let (serve_res, _trigger_res) = tokio::join!(serve_fut, readiness_trigger);
+        serve_res?;
+        return Ok(());
+    }
+    let rest_listener_std = tcp_listener.into_std()?;
+
     let serve_fut = async move {
         tokio::select! {
              res = hyper::Server::from_tcp(rest_listener_std)?.serve(Shared::new(service)) => { res }
@@ -239,14 +302,58 @@ pub(crate) async fn start_rest_server(
     Ok(())
 }
 
+/// Request extension marking the requests received over a TLS connection whose client presented a
+/// certificate verified against the CA.
+#[derive(Clone, Copy, Debug)]
+struct VerifiedClientCert;
+
+/// Rejects the requests to the ingest endpoints that were not sent over a connection
+/// authenticated with a client certificate when `require_client_cert` is set.
+fn ingest_client_cert_guard(
+    require_client_cert: bool,
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::path::full()
+        .and(warp::ext::optional::<VerifiedClientCert>())
+        .and_then(
+            move |full_path: FullPath, client_cert_opt: Option<VerifiedClientCert>| async move {
+                if require_client_cert
+                    && client_cert_opt.is_none()
+                    && is_ingest_path(full_path.as_str())
+                {
+                    return Err(warp::reject::custom(ClientCertRequired));
+                }
+                Ok(())
+            },
+        )
+        .untuple_one()
+}
+
+/// Returns whether the path points to one of the ingest endpoints: the native ingest APIs, the
+/// Elasticsearch bulk and reindex APIs, and the OTLP APIs.
+fn is_ingest_path(path: &str) -> bool {
+    let Some(api_path) = path.strip_prefix("/api/v1/") else {
+        return false;
+    };
+    let api_path = api_path.trim_end_matches('/');
+    api_path.ends_with("/ingest")
+        || api_path.ends_with("/ingest-v2")
+        || api_path == "_elastic/_bulk"
+        || api_path == "_elastic/_reindex"
+        || (api_path.starts_with("_elastic/") && api_path.ends_with("/_bulk"))
+        || api_path.starts_with("otlp/v1/")
+        || api_path.contains("/otlp/v1/")
+}
+
 fn search_routes(
     search_service: Arc<dyn SearchService>,
+    metastore: MetastoreServiceClient,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
     search_get_handler(search_service.clone())
         .or(search_post_handler(search_service.clone()))
         .or(search_plan_get_handler(search_service.clone()))
         .or(search_plan_post_handler(search_service.clone()))
-        .or(search_stream_handler(search_service))
+        .or(search_stream_handler(search_service.clone()))
+        .or(search_tail_handler(search_service, metastore))
         .recover(recover_fn)
         .boxed()
 }
@@ -276,7 +383,10 @@ fn api_v1_routes(
             quickwit_services.indexing_service_opt.clone(),
         ))
         .boxed()
-        .or(search_routes(quickwit_services.search_service.clone()))
+        .or(search_routes(
+            quickwit_services.search_service.clone(),
+            quickwit_services.metastore_client.clone(),
+        ))
         .boxed()
         .or(ingest_api_handlers(
             quickwit_services.ingest_router_service.clone(),
@@ -298,6 +408,12 @@ fn api_v1_routes(
             quickwit_services.metastore_client.clone(),
         ))
         .boxed()
+        .or(export_api_handlers(ExportTaskManager::new(
+            quickwit_services.search_service.clone(),
+            quickwit_services.metastore_client.clone(),
+            quickwit_services.index_manager.storage_resolver(),
+        )))
+        .boxed()
         .or(jaeger_api_handlers(
             quickwit_services.jaeger_service_opt.clone(),
         ))
@@ -305,6 +421,16 @@ fn api_v1_routes(
         .or(index_template_api_handlers(
             quickwit_services.metastore_client.clone(),
         ))
+        .boxed()
+        .or(prometheus_api_handlers(
+            quickwit_services.search_service.clone(),
+            quickwit_services.metastore_client.clone(),
+        ))
+        .boxed()
+        .or(loki_api_handlers(
+            quickwit_services.search_service.clone(),
+            quickwit_services.metastore_client.clone(),
+        ))
         .boxed(),
     )
 }
@@ -355,7 +481,12 @@ pub async fn recover_fn_final(rejection: Rejection) -> Result<impl Reply, Reject
 }
 
 fn get_status_with_error(rejection: Rejection) -> Result<RestApiError, Rejection> {
-    if let Some(error) = rejection.find::<crate::format::UnsupportedMediaType>() {
+    if rejection.find::<ClientCertRequired>().is_some() {
+        Ok(RestApiError {
+            status_code: StatusCode::FORBIDDEN,
+            message: "a client certificate is required to ingest documents".to_string(),
+        })
+    } else if let Some(error) = rejection.find::<crate::format::UnsupportedMediaType>() {
         Ok(RestApiError {
             status_code: StatusCode::UNSUPPORTED_MEDIA_TYPE,
             message: error.to_string(),
@@ -727,6 +858,7 @@ mod tests {
         let quickwit_services = QuickwitServices {
             _report_splits_subscription_handle_opt: None,
             _local_shards_update_listener_handle_opt: None,
+            _storage_usage_listener_handle_opt: None,
             cluster,
             control_plane_server_opt: None,
             control_plane_client,
@@ -783,4 +915,52 @@ mod tests {
             "custom-value-2"
         );
     }
+
+    #[test]
+    fn test_is_ingest_path() {
+        assert!(is_ingest_path("/api/v1/my-index/ingest"));
+        assert!(is_ingest_path("/api/v1/my-index/ingest-v2"));
+        assert!(is_ingest_path("/api/v1/my-index/ingest/"));
+        assert!(is_ingest_path("/api/v1/_elastic/_bulk"));
+        assert!(is_ingest_path("/api/v1/_elastic/my-index/_bulk"));
+        assert!(is_ingest_path("/api/v1/_elastic/_reindex"));
+        assert!(is_ingest_path("/api/v1/otlp/v1/logs"));
+        assert!(is_ingest_path("/api/v1/my-index/otlp/v1/traces"));
+
+        assert!(!is_ingest_path("/api/v1/my-index/search"));
+        assert!(!is_ingest_path("/api/v1/_elastic/my-index/_search"));
+        assert!(!is_ingest_path("/api/v1/indexes/ingest"));
+        assert!(!is_ingest_path("/ingest"));
+    }
+
+    #[tokio::test]
+    async fn test_ingest_client_cert_guard() {
+        let guard = ingest_client_cert_guard(true).map(warp::reply);
+
+        let response = warp::test::request()
+            .path("/api/v1/my-index/search")
+            .reply(&guard.clone().recover(recover_fn_final))
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = warp::test::request()
+            .path("/api/v1/my-index/ingest")
+            .reply(&guard.clone().recover(recover_fn_final))
+            .await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = warp::test::request()
+            .path("/api/v1/my-index/ingest")
+            .extension(VerifiedClientCert)
+            .reply(&guard.recover(recover_fn_final))
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let guard = ingest_client_cert_guard(false).map(warp::reply);
+        let response = warp::test::request()
+            .path("/api/v1/_elastic/_bulk")
+            .reply(&guard.recover(recover_fn_final))
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
 }
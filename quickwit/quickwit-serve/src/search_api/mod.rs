@@ -19,6 +19,7 @@
 
 mod grpc_adapter;
 mod rest_handler;
+mod tail;
 
 pub use self::grpc_adapter::GrpcSearchAdapter;
 pub(crate) use self::rest_handler::{extract_index_id_patterns, extract_index_id_patterns_default};
@@ -27,6 +28,11 @@ pub use self::rest_handler::{
     search_request_from_api_request, search_stream_handler, SearchApi, SearchRequestQueryString,
     SortBy,
 };
+pub(crate) use self::tail::{
+    hit_timestamp_nanos, new_tail_poll_interval, now_nanos, shared_timestamp_field, SearchTail,
+    TailDocument,
+};
+pub use self::tail::{search_tail_handler, TailRequestQueryString};
 
 #[cfg(test)]
 mod tests {
@@ -81,6 +87,7 @@ mod tests {
             fast_field: "timestamp".to_string(),
             output_format: OutputFormat::Csv as i32,
             partition_by_field: None,
+            fast_fields: Vec::new(),
         };
         let mut mock_metastore = MockMetastoreService::new();
         let index_metadata = IndexMetadata::for_test("test-index", "ram:///indexes/test-index");
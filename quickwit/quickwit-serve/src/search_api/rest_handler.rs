@@ -24,7 +24,7 @@ use futures::stream::StreamExt;
 use hyper::header::HeaderValue;
 use hyper::HeaderMap;
 use percent_encoding::percent_decode_str;
-use quickwit_config::validate_index_id_pattern;
+use quickwit_config::{validate_index_id_pattern, RuntimeField};
 use quickwit_proto::search::{CountHits, OutputFormat, SortField, SortOrder};
 use quickwit_proto::types::IndexId;
 use quickwit_proto::ServiceError;
@@ -37,6 +37,7 @@ use warp::hyper::header::CONTENT_TYPE;
 use warp::hyper::StatusCode;
 use warp::{reply, Filter, Rejection, Reply};
 
+use super::tail::TailRequestQueryString;
 use crate::rest_api_response::into_rest_api_response;
 use crate::simple_list::{from_simple_list, to_simple_list};
 use crate::{with_arg, BodyFormat};
@@ -49,6 +50,7 @@ use crate::{with_arg, BodyFormat};
         search_stream_handler,
         search_plan_get_handler,
         search_plan_post_handler,
+        super::tail::search_tail_handler,
     ),
     components(schemas(
         BodyFormat,
@@ -58,6 +60,7 @@ use crate::{with_arg, BodyFormat};
         SearchPlanResponseRest,
         SortBy,
         SortField,
+        TailRequestQueryString,
         SortOrder,
     ),)
 )]
@@ -243,6 +246,25 @@ pub struct SearchRequestQueryString {
     #[schema(value_type = bool)]
     #[serde(default)]
     pub allow_failed_splits: bool,
+    /// If set, the documents held by the ingesters and not yet indexed are searched as well.
+    #[param(value_type = bool)]
+    #[schema(value_type = bool)]
+    #[serde(default)]
+    pub real_time: bool,
+    /// Fields computed at query time by evaluating a VRL script over the stored documents.
+    /// They cannot use the inverted index: each document matching the rest of the query is
+    /// fetched and evaluated, so they should be combined with selective filters.
+    #[param(value_type = Object)]
+    #[schema(value_type = Object)]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub runtime_fields: Vec<RuntimeField>,
+    /// If set, the response contains, for each split searched, the time spent in each phase of
+    /// the search and the number of bytes read from storage and from the caches.
+    #[param(value_type = bool)]
+    #[schema(value_type = bool)]
+    #[serde(default)]
+    pub profile: bool,
 }
 
 mod count_hits_from_bool {
@@ -282,6 +304,11 @@ pub fn search_request_from_api_request(
     // the user of the docmapper default fields (which we do not have at this point).
     let query_ast = query_ast_from_user_text(&search_request.query, search_request.search_fields);
     let query_ast_json = serde_json::to_string(&query_ast)?;
+    let runtime_fields = if search_request.runtime_fields.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(&search_request.runtime_fields)?)
+    };
     let search_request = quickwit_proto::search::SearchRequest {
         index_id_patterns,
         query_ast: query_ast_json,
@@ -297,6 +324,11 @@ pub fn search_request_from_api_request(
         scroll_ttl_secs: None,
         search_after: None,
         count_hits: search_request.count_all.into(),
+        real_time: search_request.real_time,
+        runtime_fields,
+        profile: search_request.profile,
+        user_agent: None,
+        split_ids: Vec::new(),
     };
     Ok(search_request)
 }
@@ -304,10 +336,12 @@ pub fn search_request_from_api_request(
 async fn search_endpoint(
     index_id_patterns: Vec<String>,
     search_request: SearchRequestQueryString,
+    user_agent_opt: Option<String>,
     search_service: &dyn SearchService,
 ) -> Result<SearchResponseRest, SearchError> {
     let allow_failed_splits = search_request.allow_failed_splits;
-    let search_request = search_request_from_api_request(index_id_patterns, search_request)?;
+    let mut search_request = search_request_from_api_request(index_id_patterns, search_request)?;
+    search_request.user_agent = user_agent_opt;
     let search_response =
         search_service
             .root_search(search_request)
@@ -363,11 +397,18 @@ fn search_plan_post_filter(
 async fn search(
     index_id_patterns: Vec<String>,
     search_request: SearchRequestQueryString,
+    user_agent_opt: Option<String>,
     search_service: Arc<dyn SearchService>,
 ) -> impl warp::Reply {
     info!(request =? search_request, "search");
     let body_format = search_request.format;
-    let result = search_endpoint(index_id_patterns, search_request, &*search_service).await;
+    let result = search_endpoint(
+        index_id_patterns,
+        search_request,
+        user_agent_opt,
+        &*search_service,
+    )
+    .await;
     into_rest_api_response(result, body_format)
 }
 
@@ -406,6 +447,7 @@ pub fn search_get_handler(
     search_service: Arc<dyn SearchService>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
     search_get_filter()
+        .and(warp::header::optional::<String>("user-agent"))
         .and(with_arg(search_service))
         .then(search)
 }
@@ -431,6 +473,7 @@ pub fn search_post_handler(
     search_service: Arc<dyn SearchService>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
     search_post_filter()
+        .and(warp::header::optional::<String>("user-agent"))
         .and(with_arg(search_service))
         .then(search)
 }
@@ -525,7 +568,7 @@ struct SearchStreamRequestQueryString {
     pub start_timestamp: Option<i64>,
     /// If set, restricts search to documents with a `timestamp < end_timestamp``.
     pub end_timestamp: Option<i64>,
-    /// The fast field to extract.
+    /// The fast fields to extract, separated by commas, e.g. "field1,field2".
     #[serde(deserialize_with = "deserialize_non_empty_string")]
     pub fast_field: String,
     /// The requested output format.
@@ -542,15 +585,35 @@ async fn search_stream_endpoint(
 ) -> Result<hyper::Body, SearchError> {
     let query_ast = query_ast_from_user_text(&search_request.query, search_request.search_fields);
     let query_ast_json = serde_json::to_string(&query_ast)?;
+    let mut fast_fields: Vec<String> = search_request
+        .fast_field
+        .split(',')
+        .map(|fast_field| fast_field.trim().to_string())
+        .collect();
+    if fast_fields.iter().any(|fast_field| fast_field.is_empty()) {
+        return Err(SearchError::InvalidArgument(format!(
+            "invalid fast field list `{}`",
+            search_request.fast_field
+        )));
+    }
+    // A single fast field is passed through `fast_field` for compatibility with older nodes.
+    let fast_field = if fast_fields.len() == 1 {
+        fast_fields
+            .pop()
+            .expect("the list of fast fields should not be empty")
+    } else {
+        String::new()
+    };
     let request = quickwit_proto::search::SearchStreamRequest {
         index_id,
         query_ast: query_ast_json,
         snippet_fields: search_request.snippet_fields.unwrap_or_default(),
         start_timestamp: search_request.start_timestamp,
         end_timestamp: search_request.end_timestamp,
-        fast_field: search_request.fast_field,
+        fast_field,
         output_format: search_request.output_format as i32,
         partition_by_field: search_request.partition_by_field,
+        fast_fields,
     };
     let mut data = search_service.root_search_stream(request).await?;
     let (mut sender, body) = hyper::Body::channel();
@@ -611,6 +674,8 @@ async fn search_stream(
     let content_type = match request.output_format {
         OutputFormat::ClickHouseRowBinary => "application/octet-stream",
         OutputFormat::Csv => "text/csv",
+        OutputFormat::ArrowIpc => "application/vnd.apache.arrow.stream",
+        OutputFormat::Ndjson => "application/x-ndjson",
     };
     let reply =
         make_streaming_reply(search_stream_endpoint(index_id, request, &*search_service).await);
@@ -679,6 +744,7 @@ mod tests {
             elapsed_time_micros: 0u64,
             errors: Vec::new(),
             aggregations: None,
+            profile: Vec::new(),
         };
         let search_response_json: JsonValue = serde_json::to_value(search_response)?;
         let expected_search_response_json: JsonValue = json!({
@@ -721,6 +787,32 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_rest_search_api_route_post_runtime_fields() {
+        let rest_search_api_filter = search_post_filter();
+        let (indexes, req) = warp::test::request()
+            .method("POST")
+            .path("/quickwit-demo-index/search")
+            .json(&json!({
+                "query": "status:404",
+                "runtime_fields": [{"name": "status", "type": "u64", "script": ".status_code"}]
+            }))
+            .filter(&rest_search_api_filter)
+            .await
+            .unwrap();
+        let expected_runtime_fields = vec![RuntimeField {
+            name: "status".to_string(),
+            field_type: quickwit_config::RuntimeFieldType::U64,
+            script: ".status_code".to_string(),
+        }];
+        assert_eq!(req.runtime_fields, expected_runtime_fields);
+
+        let search_request = search_request_from_api_request(indexes, req).unwrap();
+        let runtime_fields: Vec<RuntimeField> =
+            serde_json::from_str(&search_request.runtime_fields.unwrap()).unwrap();
+        assert_eq!(runtime_fields, expected_runtime_fields);
+    }
+
     #[tokio::test]
     async fn test_rest_search_api_route_post_multi_indexes() {
         let rest_search_api_filter = search_post_filter();
@@ -1192,6 +1284,37 @@ mod tests {
         assert_eq!(body, "first row\nsecond row");
     }
 
+    #[tokio::test]
+    async fn test_rest_search_stream_api_multiple_fast_fields() {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search_stream()
+            .withf(|request| {
+                request.fast_field.is_empty()
+                    && request.fast_fields == ["ts", "client_ip"]
+                    && request.output_format == OutputFormat::ArrowIpc as i32
+            })
+            .return_once(|_| {
+                Ok(Box::pin(futures::stream::iter(vec![Ok(Bytes::from(
+                    "arrow",
+                ))])))
+            });
+        let rest_search_stream_api_handler = search_handler(mock_search_service);
+        let response = warp::test::request()
+            .path(
+                "/my-index/search/stream?query=obama&fast_field=ts,client_ip&\
+                 output_format=arrow_ipc",
+            )
+            .reply(&rest_search_stream_api_handler)
+            .await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(
+            response.headers().get(CONTENT_TYPE).unwrap(),
+            "application/vnd.apache.arrow.stream"
+        );
+        assert_eq!(response.body(), "arrow");
+    }
+
     #[tokio::test]
     async fn test_rest_search_stream_api_csv() {
         let (index, req) = warp::test::request()
@@ -1254,7 +1377,8 @@ mod tests {
         let parse_error = rejection.find::<serde_qs::Error>().unwrap();
         assert_eq!(
             parse_error.to_string(),
-            "unknown variant `ClickHouseRowBinary`, expected `csv` or `click_house_row_binary`"
+            "unknown variant `ClickHouseRowBinary`, expected one of `csv`, \
+             `click_house_row_binary`, `arrow_ipc`, `ndjson`"
         );
     }
 
@@ -0,0 +1,735 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Live tail of the documents matching a query.
+//!
+//! A tail tracks the splits published since its start, searches the documents of each new split
+//! once, sorted by ascending timestamp, and streams them to the client as server-sent events.
+
+use std::collections::{BTreeSet, HashMap};
+use std::convert::Infallible;
+use std::ops::Bound;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures::stream::{self, Stream, StreamExt};
+use quickwit_metastore::{
+    IndexMetadata, ListSplitsQuery, ListSplitsRequestExt, MetastoreServiceStreamSplitsExt, Split,
+    SplitState,
+};
+use quickwit_proto::metastore::{ListSplitsRequest, MetastoreService, MetastoreServiceClient};
+use quickwit_proto::search::sort_by_value::SortValue;
+use quickwit_proto::search::{
+    CountHits, Hit, PartialHit, SearchRequest, SortDatetimeFormat, SortField, SortOrder,
+};
+use quickwit_proto::types::{IndexUid, SplitId};
+use quickwit_proto::ServiceError;
+use quickwit_query::query_ast::{query_ast_from_user_text, BoolQuery, QueryAst, RangeQuery};
+use quickwit_query::JsonLiteral;
+use quickwit_search::{resolve_index_patterns, SearchError, SearchService};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use warp::sse::Event;
+use warp::{Filter, Rejection, Reply};
+
+use super::rest_handler::extract_index_id_patterns;
+use crate::rest_api_response::RestApiResponse;
+use crate::simple_list::{from_simple_list, to_simple_list};
+use crate::{with_arg, BodyFormat};
+
+const TAIL_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Maximum number of documents fetched per search.
+const MAX_TAIL_MAX_HITS: u64 = 5_000;
+
+/// The splits are listed from the latest publish timestamp observed minus this margin, so that
+/// the splits whose publication committed after more recent ones are not missed.
+const PUBLISH_TIMESTAMP_MARGIN_SECS: i64 = 60;
+
+const NANOS_PER_SEC: i64 = 1_000_000_000;
+
+fn default_tail_max_hits() -> u64 {
+    100
+}
+
+/// This struct represents the tail query string passed to
+/// the REST API.
+#[derive(
+    Debug, Clone, Eq, PartialEq, Serialize, Deserialize, utoipa::IntoParams, utoipa::ToSchema,
+)]
+#[into_params(parameter_in = Query)]
+#[serde(deny_unknown_fields)]
+pub struct TailRequestQueryString {
+    /// Query text. The query language is that of tantivy.
+    pub query: String,
+    // Fields to search on.
+    #[param(rename = "search_field")]
+    #[schema(rename = "search_field")]
+    #[serde(default)]
+    #[serde(rename = "search_field")]
+    #[serde(deserialize_with = "from_simple_list")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(serialize_with = "to_simple_list")]
+    pub search_fields: Option<Vec<String>>,
+    /// If set, first streams the documents already searchable with a `timestamp >=
+    /// start_timestamp`, then only streams the new documents matching this condition. Otherwise,
+    /// streams the documents that become searchable after the start of the tail, whatever their
+    /// timestamp. This timestamp is expressed in seconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_timestamp: Option<i64>,
+    /// Maximum number of documents fetched per search (by default 100).
+    #[serde(default = "default_tail_max_hits")]
+    pub max_hits: u64,
+}
+
+/// Returns the timestamp field shared by the indexes, which must exist.
+pub(crate) fn shared_timestamp_field(
+    indexes_metadata: &[IndexMetadata],
+) -> Result<String, SearchError> {
+    let mut timestamp_fields = BTreeSet::new();
+
+    for index_metadata in indexes_metadata {
+        let Some(timestamp_field) = &index_metadata.index_config.doc_mapping.timestamp_field else {
+            return Err(SearchError::InvalidArgument(format!(
+                "index `{}` has no timestamp field",
+                index_metadata.index_id()
+            )));
+        };
+        timestamp_fields.insert(timestamp_field.clone());
+    }
+    if timestamp_fields.len() > 1 {
+        return Err(SearchError::InvalidArgument(format!(
+            "queried indexes must share the same timestamp field, found {}",
+            timestamp_fields.into_iter().collect::<Vec<_>>().join(", ")
+        )));
+    }
+    timestamp_fields
+        .pop_first()
+        .ok_or_else(|| SearchError::InvalidArgument("no index matches the query".to_string()))
+}
+
+pub(crate) fn now_nanos() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time should be after the Unix epoch")
+        .as_nanos() as i64
+}
+
+/// A document streamed by a tail.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TailDocument {
+    pub timestamp_nanos_opt: Option<i64>,
+    pub json: String,
+}
+
+/// Splits searched by a tail, page by page.
+#[derive(Debug, Clone, PartialEq)]
+struct SplitBatch {
+    split_ids: Vec<SplitId>,
+    /// Last hit of the previous page.
+    search_after_opt: Option<PartialHit>,
+}
+
+/// State of a tail.
+///
+/// A tail tracks its progress by published split rather than by document timestamp, so that the
+/// documents ingested late are not missed: the documents of each new split are searched once,
+/// whatever their timestamp. Only the splits produced by indexing are searched, since merged
+/// splits hold documents of splits searched already. A split merged before being listed by the
+/// tail remains searchable until it is garbage collected, so it is still listed and searched.
+pub(crate) struct SearchTail {
+    metastore: MetastoreServiceClient,
+    index_id_patterns: Vec<String>,
+    index_uids: Vec<IndexUid>,
+    query_ast: QueryAst,
+    timestamp_field: String,
+    start_nanos_opt: Option<i64>,
+    max_hits: u64,
+    /// Latest publish timestamp of the splits listed so far, in seconds.
+    publish_cursor_secs: i64,
+    /// Publish timestamps of the splits listed so far, forgotten once they fall out of the
+    /// listing window.
+    listed_splits: HashMap<SplitId, i64>,
+    /// Splits being searched.
+    split_batch_opt: Option<SplitBatch>,
+}
+
+impl SearchTail {
+    /// Starts a tail of the documents matching `query_ast`.
+    ///
+    /// If `start_nanos_opt` is set, the documents already searchable with a timestamp greater
+    /// than or equal to it are streamed first, and only the new documents matching this condition
+    /// are streamed afterwards. Otherwise, only the documents of the splits published after the
+    /// start of the tail are streamed.
+    pub async fn start(
+        index_id_patterns: Vec<String>,
+        index_uids: Vec<IndexUid>,
+        query_ast: QueryAst,
+        timestamp_field: String,
+        start_nanos_opt: Option<i64>,
+        max_hits: u64,
+        metastore: MetastoreServiceClient,
+    ) -> Result<Self, SearchError> {
+        let mut search_tail = Self {
+            metastore,
+            index_id_patterns,
+            index_uids,
+            query_ast,
+            timestamp_field,
+            start_nanos_opt,
+            max_hits: max_hits.clamp(1, MAX_TAIL_MAX_HITS),
+            publish_cursor_secs: now_nanos().div_euclid(NANOS_PER_SEC),
+            listed_splits: HashMap::new(),
+            split_batch_opt: None,
+        };
+        let splits = search_tail.list_splits(None).await?;
+        search_tail.start_from_splits(splits);
+        Ok(search_tail)
+    }
+
+    /// Records the splits existing at the start of the tail as listed, and schedules the search
+    /// of the published ones if the tail starts from a timestamp.
+    fn start_from_splits(&mut self, splits: Vec<Split>) {
+        if let Some(max_publish_timestamp) = splits
+            .iter()
+            .filter_map(|split| split.publish_timestamp)
+            .max()
+        {
+            self.publish_cursor_secs = max_publish_timestamp;
+        }
+        let mut published_split_ids = Vec::new();
+
+        for split in splits {
+            if split.split_state == SplitState::Published {
+                published_split_ids.push(split.split_id().to_string());
+            }
+            let publish_timestamp = split.publish_timestamp.unwrap_or_default();
+            self.listed_splits
+                .insert(split.split_metadata.split_id, publish_timestamp);
+        }
+        if self.start_nanos_opt.is_some() && !published_split_ids.is_empty() {
+            self.split_batch_opt = Some(SplitBatch {
+                split_ids: published_split_ids,
+                search_after_opt: None,
+            });
+        }
+    }
+
+    /// Lists the published splits, and the splits marked for deletion, optionally updated after
+    /// `update_timestamp_gte_opt`.
+    async fn list_splits(
+        &mut self,
+        update_timestamp_gte_opt: Option<i64>,
+    ) -> Result<Vec<Split>, SearchError> {
+        let Some(mut query) = ListSplitsQuery::try_from_index_uids(self.index_uids.clone()) else {
+            return Ok(Vec::new());
+        };
+        query = query.with_split_states([SplitState::Published, SplitState::MarkedForDeletion]);
+
+        if let Some(update_timestamp_gte) = update_timestamp_gte_opt {
+            query = query.with_update_timestamp_gte(update_timestamp_gte);
+        }
+        let list_splits_request = ListSplitsRequest::try_from_list_splits_query(&query)?;
+        let splits = self
+            .metastore
+            .list_splits(list_splits_request)
+            .await?
+            .collect_splits()
+            .await?;
+        Ok(splits)
+    }
+
+    /// Returns the IDs of the splits produced by indexing among `splits` that were not listed yet,
+    /// and advances the publish cursor.
+    fn new_split_ids(&mut self, splits: Vec<Split>) -> Vec<SplitId> {
+        let listing_start_secs = self.publish_cursor_secs - PUBLISH_TIMESTAMP_MARGIN_SECS;
+        let mut new_split_ids = Vec::new();
+
+        for split in splits {
+            let Some(publish_timestamp) = split.publish_timestamp else {
+                continue;
+            };
+            // A split that falls out of the listing window is forgotten: it may be listed again
+            // once marked for deletion, and must not be searched again.
+            if publish_timestamp < listing_start_secs || split.split_metadata.num_merge_ops > 0 {
+                continue;
+            }
+            self.publish_cursor_secs = self.publish_cursor_secs.max(publish_timestamp);
+
+            if self
+                .listed_splits
+                .insert(split.split_id().to_string(), publish_timestamp)
+                .is_none()
+            {
+                new_split_ids.push(split.split_metadata.split_id);
+            }
+        }
+        let listing_start_secs = self.publish_cursor_secs - PUBLISH_TIMESTAMP_MARGIN_SECS;
+        self.listed_splits
+            .retain(|_, publish_timestamp| *publish_timestamp >= listing_start_secs);
+        new_split_ids
+    }
+
+    /// Builds the request searching the next page of the documents of `split_batch`.
+    fn search_request(&self, split_batch: &SplitBatch) -> SearchRequest {
+        let query_ast: QueryAst = if let Some(start_nanos) = self.start_nanos_opt {
+            let timestamp_range_query = RangeQuery {
+                field: self.timestamp_field.clone(),
+                lower_bound: Bound::Included(JsonLiteral::Number(start_nanos.into())),
+                upper_bound: Bound::Unbounded,
+            };
+            BoolQuery {
+                filter: vec![self.query_ast.clone(), timestamp_range_query.into()],
+                ..Default::default()
+            }
+            .into()
+        } else {
+            self.query_ast.clone()
+        };
+        let sort_field = SortField {
+            field_name: self.timestamp_field.clone(),
+            sort_order: SortOrder::Asc as i32,
+            sort_datetime_format: Some(SortDatetimeFormat::UnixTimestampNanos as i32),
+        };
+        SearchRequest {
+            index_id_patterns: self.index_id_patterns.clone(),
+            query_ast: serde_json::to_string(&query_ast).expect("failed to serialize query AST"),
+            start_timestamp: self
+                .start_nanos_opt
+                .map(|start_nanos| start_nanos.div_euclid(NANOS_PER_SEC)),
+            max_hits: self.max_hits,
+            sort_fields: vec![sort_field],
+            search_after: split_batch.search_after_opt.clone(),
+            split_ids: split_batch.split_ids.clone(),
+            count_hits: CountHits::Underestimate.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the documents of a page of hits, and moves on to the next page, or to the next
+    /// splits if this page is the last one.
+    fn next_documents(&mut self, hits: Vec<Hit>) -> Vec<TailDocument> {
+        if (hits.len() as u64) < self.max_hits {
+            self.split_batch_opt = None;
+        } else if let Some(split_batch) = &mut self.split_batch_opt {
+            split_batch.search_after_opt = hits.last().and_then(|hit| hit.partial_hit.clone());
+        }
+        hits.into_iter()
+            .map(|hit| TailDocument {
+                timestamp_nanos_opt: hit_timestamp_nanos(&hit),
+                json: hit.json,
+            })
+            .collect()
+    }
+
+    /// Searches the next page of the splits being searched, or the first page of the splits
+    /// published since the last listing. Returns no documents if there are no new splits.
+    pub async fn search_next_documents(
+        &mut self,
+        search_service: &dyn SearchService,
+    ) -> Result<Vec<TailDocument>, SearchError> {
+        if self.split_batch_opt.is_none() {
+            let listing_start_secs = self.publish_cursor_secs - PUBLISH_TIMESTAMP_MARGIN_SECS;
+            let splits = self.list_splits(Some(listing_start_secs)).await?;
+            let new_split_ids = self.new_split_ids(splits);
+
+            if new_split_ids.is_empty() {
+                return Ok(Vec::new());
+            }
+            self.split_batch_opt = Some(SplitBatch {
+                split_ids: new_split_ids,
+                search_after_opt: None,
+            });
+        }
+        let Some(split_batch) = &self.split_batch_opt else {
+            return Ok(Vec::new());
+        };
+        let search_request = self.search_request(split_batch);
+        let search_response = search_service.root_search(search_request).await?;
+        Ok(self.next_documents(search_response.hits))
+    }
+
+    /// Searches the documents not sent yet, waiting for `poll_interval` between consecutive
+    /// listings of the new splits until some are found.
+    ///
+    /// This method is cancel-safe: the state of the tail is only updated once a listing or a
+    /// search completes.
+    pub async fn poll_documents(
+        &mut self,
+        search_service: &dyn SearchService,
+        poll_interval: &mut tokio::time::Interval,
+    ) -> Result<Vec<TailDocument>, SearchError> {
+        loop {
+            if self.split_batch_opt.is_none() {
+                poll_interval.tick().await;
+            }
+            let documents = self.search_next_documents(search_service).await?;
+
+            if !documents.is_empty() {
+                return Ok(documents);
+            }
+        }
+    }
+}
+
+/// Returns the timestamp of a hit sorted by timestamp in nanoseconds.
+pub(crate) fn hit_timestamp_nanos(hit: &Hit) -> Option<i64> {
+    let sort_value = hit.partial_hit.as_ref()?.sort_value.as_ref()?;
+
+    match sort_value.sort_value.as_ref()? {
+        SortValue::I64(timestamp_nanos) => Some(*timestamp_nanos),
+        SortValue::U64(timestamp_nanos) => i64::try_from(*timestamp_nanos).ok(),
+        _ => None,
+    }
+}
+
+pub(crate) fn new_tail_poll_interval() -> tokio::time::Interval {
+    tokio::time::interval(TAIL_POLL_INTERVAL)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Search",
+    path = "/{index_id}/search/tail",
+    responses(
+        (status = 200, description = "Stream of the documents matching the query, as server-sent events, as they become searchable.")
+    ),
+    params(
+        TailRequestQueryString,
+        ("index_id" = String, Path, description = "The index ID to tail."),
+    )
+)]
+/// Tail Index
+///
+/// Streams the documents matching the query as they become searchable, as server-sent events:
+/// each `document` event holds a document and the timestamp of the document in nanoseconds as
+/// its ID. The documents of each new split are sent once, sorted by ascending timestamp, so a
+/// document ingested late is sent after documents with a later timestamp. If a search fails, an
+/// `error` event is sent and the stream ends.
+pub fn search_tail_handler(
+    search_service: Arc<dyn SearchService>,
+    metastore: MetastoreServiceClient,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!(String / "search" / "tail")
+        .and_then(extract_index_id_patterns)
+        .and(warp::get())
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+        .and(with_arg(search_service))
+        .and(with_arg(metastore))
+        .then(search_tail)
+}
+
+async fn search_tail(
+    index_id_patterns: Vec<String>,
+    tail_request: TailRequestQueryString,
+    search_service: Arc<dyn SearchService>,
+    metastore: MetastoreServiceClient,
+) -> warp::reply::Response {
+    info!(request =? tail_request, "tail");
+    let search_tail = match start_search_tail(index_id_patterns, tail_request, metastore).await {
+        Ok(search_tail) => search_tail,
+        Err(error) => {
+            let status_code = error.error_code().http_status_code();
+            return RestApiResponse::new::<(), _>(&Err(error), status_code, BodyFormat::default())
+                .into_response();
+        }
+    };
+    let event_stream = tail_event_stream(search_tail, search_service);
+    warp::sse::reply(warp::sse::keep_alive().stream(event_stream)).into_response()
+}
+
+async fn start_search_tail(
+    index_id_patterns: Vec<String>,
+    tail_request: TailRequestQueryString,
+    mut metastore: MetastoreServiceClient,
+) -> Result<SearchTail, SearchError> {
+    let indexes_metadata = resolve_index_patterns(&index_id_patterns, &mut metastore).await?;
+    let timestamp_field = shared_timestamp_field(&indexes_metadata)?;
+    let index_uids = indexes_metadata
+        .into_iter()
+        .map(|index_metadata| index_metadata.index_uid)
+        .collect();
+    let query_ast = query_ast_from_user_text(&tail_request.query, tail_request.search_fields);
+    let start_nanos_opt = tail_request
+        .start_timestamp
+        .map(|start_timestamp| start_timestamp.saturating_mul(NANOS_PER_SEC));
+    SearchTail::start(
+        index_id_patterns,
+        index_uids,
+        query_ast,
+        timestamp_field,
+        start_nanos_opt,
+        tail_request.max_hits,
+        metastore,
+    )
+    .await
+}
+
+fn tail_event_stream(
+    search_tail: SearchTail,
+    search_service: Arc<dyn SearchService>,
+) -> impl Stream<Item = Result<Event, Infallible>> {
+    let poll_interval = new_tail_poll_interval();
+    let state_opt = Some((search_tail, search_service, poll_interval));
+
+    stream::unfold(state_opt, |state_opt| async move {
+        let (mut search_tail, search_service, mut poll_interval) = state_opt?;
+
+        match search_tail
+            .poll_documents(&*search_service, &mut poll_interval)
+            .await
+        {
+            Ok(documents) => {
+                let events: Vec<Result<Event, Infallible>> = documents
+                    .into_iter()
+                    .map(|document| {
+                        let mut event = Event::default().event("document");
+
+                        if let Some(timestamp_nanos) = document.timestamp_nanos_opt {
+                            event = event.id(timestamp_nanos.to_string());
+                        }
+                        Ok(event.data(document.json))
+                    })
+                    .collect();
+                let next_state_opt = Some((search_tail, search_service, poll_interval));
+                Some((stream::iter(events), next_state_opt))
+            }
+            Err(search_error) => {
+                warn!(error=%search_error, "failed to tail documents");
+                let event = Event::default()
+                    .event("error")
+                    .data(search_error.to_string());
+                Some((stream::iter(vec![Ok(event)]), None))
+            }
+        }
+    })
+    .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_common::ServiceStream;
+    use quickwit_metastore::{
+        ListIndexesMetadataResponseExt, ListSplitsResponseExt, SplitMetadata,
+    };
+    use quickwit_proto::metastore::{
+        ListIndexesMetadataResponse, ListSplitsResponse, MockMetastoreService,
+    };
+    use quickwit_proto::search::{SearchResponse, SortByValue};
+    use quickwit_search::MockSearchService;
+
+    use super::*;
+
+    fn hit(timestamp_nanos: i64, doc_id: u32, json: &str) -> Hit {
+        Hit {
+            json: json.to_string(),
+            partial_hit: Some(PartialHit {
+                split_id: "split".to_string(),
+                doc_id,
+                sort_value: Some(SortByValue {
+                    sort_value: Some(SortValue::I64(timestamp_nanos)),
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn document(timestamp_nanos: i64, json: &str) -> TailDocument {
+        TailDocument {
+            timestamp_nanos_opt: Some(timestamp_nanos),
+            json: json.to_string(),
+        }
+    }
+
+    fn split(
+        split_id: &str,
+        split_state: SplitState,
+        publish_timestamp: i64,
+        num_merge_ops: usize,
+    ) -> Split {
+        Split {
+            split_state,
+            update_timestamp: publish_timestamp,
+            publish_timestamp: Some(publish_timestamp),
+            split_metadata: SplitMetadata {
+                split_id: split_id.to_string(),
+                num_merge_ops,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn search_tail(start_nanos_opt: Option<i64>, max_hits: u64) -> SearchTail {
+        SearchTail {
+            metastore: MetastoreServiceClient::from_mock(MockMetastoreService::new()),
+            index_id_patterns: vec!["logs".to_string()],
+            index_uids: Vec::new(),
+            query_ast: QueryAst::MatchAll,
+            timestamp_field: "timestamp".to_string(),
+            start_nanos_opt,
+            max_hits,
+            publish_cursor_secs: 0,
+            listed_splits: HashMap::new(),
+            split_batch_opt: None,
+        }
+    }
+
+    #[test]
+    fn test_search_tail_new_split_ids() {
+        let mut search_tail = search_tail(None, 2);
+        search_tail.start_from_splits(vec![
+            split("a", SplitState::Published, 1_000, 0),
+            split("b", SplitState::MarkedForDeletion, 990, 0),
+        ]);
+        assert_eq!(search_tail.publish_cursor_secs, 1_000);
+        // Without a start timestamp, the splits existing at the start of the tail are not
+        // searched.
+        assert!(search_tail.split_batch_opt.is_none());
+
+        let new_split_ids = search_tail.new_split_ids(vec![
+            split("a", SplitState::Published, 1_000, 0),
+            split("c", SplitState::Published, 1_010, 0),
+            split("d", SplitState::Published, 1_020, 1),
+            // Published late, but within the listing window.
+            split("e", SplitState::Published, 950, 0),
+            split("f", SplitState::Published, 900, 0),
+        ]);
+        assert_eq!(new_split_ids, vec!["c".to_string(), "e".to_string()]);
+        assert_eq!(search_tail.publish_cursor_secs, 1_010);
+
+        // A split marked for deletion after being merged is not searched again.
+        let new_split_ids = search_tail.new_split_ids(vec![
+            split("a", SplitState::MarkedForDeletion, 1_000, 0),
+            split("c", SplitState::MarkedForDeletion, 1_010, 0),
+        ]);
+        assert!(new_split_ids.is_empty());
+        assert!(!search_tail.listed_splits.contains_key("d"));
+    }
+
+    #[test]
+    fn test_search_tail_next_documents() {
+        let mut search_tail = search_tail(Some(1_000), 2);
+        search_tail.start_from_splits(vec![
+            split("a", SplitState::Published, 1_000, 0),
+            split("b", SplitState::MarkedForDeletion, 990, 0),
+        ]);
+        let split_batch = search_tail.split_batch_opt.clone().unwrap();
+        let search_request = search_tail.search_request(&split_batch);
+        assert_eq!(search_request.split_ids, vec!["a".to_string()]);
+        assert_eq!(search_request.max_hits, 2);
+        assert_eq!(search_request.start_timestamp, Some(0));
+        assert_eq!(
+            search_request.sort_fields[0].sort_order,
+            SortOrder::Asc as i32
+        );
+        assert!(search_request.search_after.is_none());
+
+        let hits = vec![hit(1_000, 0, "a"), hit(2_000, 1, "b")];
+        let last_partial_hit = hits[1].partial_hit.clone();
+        let documents = search_tail.next_documents(hits);
+        assert_eq!(documents, vec![document(1_000, "a"), document(2_000, "b")]);
+
+        let split_batch = search_tail.split_batch_opt.clone().unwrap();
+        let search_request = search_tail.search_request(&split_batch);
+        assert_eq!(search_request.search_after, last_partial_hit);
+
+        // The last page ends the search of the splits.
+        let documents = search_tail.next_documents(vec![hit(1_500, 2, "c")]);
+        assert_eq!(documents, vec![document(1_500, "c")]);
+        assert!(search_tail.split_batch_opt.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_search_tail_handler() {
+        let mut mock_metastore = MockMetastoreService::new();
+        mock_metastore
+            .expect_list_indexes_metadata()
+            .return_once(|_| {
+                let index_metadata = IndexMetadata::for_test("logs", "ram:///indexes/logs");
+                Ok(ListIndexesMetadataResponse::for_test(vec![index_metadata]))
+            });
+        let mut num_listings = 0;
+        mock_metastore
+            .expect_list_splits()
+            .times(2)
+            .returning(move |_| {
+                num_listings += 1;
+                let mut splits = vec![split("split-1", SplitState::Published, 1_000, 0)];
+                if num_listings > 1 {
+                    splits.push(split("split-2", SplitState::Published, 1_001, 0));
+                }
+                let list_splits_response = ListSplitsResponse::try_from_splits(splits).unwrap();
+                Ok(ServiceStream::from(vec![Ok(list_splits_response)]))
+            });
+        let mut mock_search_service = MockSearchService::new();
+        let mut num_searches = 0;
+        mock_search_service
+            .expect_root_search()
+            .withf(|search_request| search_request.start_timestamp == Some(1_000))
+            .times(2)
+            .returning(move |search_request| {
+                num_searches += 1;
+                if num_searches > 1 {
+                    assert_eq!(search_request.split_ids, vec!["split-2".to_string()]);
+                    return Err(SearchError::Internal("search failed".to_string()));
+                }
+                assert_eq!(search_request.split_ids, vec!["split-1".to_string()]);
+                Ok(SearchResponse {
+                    hits: vec![hit(1_000_000_000_000, 0, r#"{"body":"hello"}"#)],
+                    ..Default::default()
+                })
+            });
+        let tail_handler = search_tail_handler(
+            Arc::new(mock_search_service),
+            MetastoreServiceClient::from_mock(mock_metastore),
+        );
+        let resp = warp::test::request()
+            .path("/logs/search/tail?query=body:hello&start_timestamp=1000")
+            .reply(&tail_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let body = std::str::from_utf8(resp.body()).unwrap();
+        assert!(body.contains("event:document\n"));
+        assert!(body.contains("data:{\"body\":\"hello\"}\n"));
+        assert!(body.contains("id:1000000000000\n"));
+        assert!(body.contains("event:error\n"));
+    }
+
+    #[tokio::test]
+    async fn test_search_tail_handler_index_without_timestamp_field() {
+        let mut mock_metastore = MockMetastoreService::new();
+        mock_metastore
+            .expect_list_indexes_metadata()
+            .return_once(|_| {
+                let mut index_metadata = IndexMetadata::for_test("logs", "ram:///indexes/logs");
+                index_metadata.index_config.doc_mapping.timestamp_field = None;
+                Ok(ListIndexesMetadataResponse::for_test(vec![index_metadata]))
+            });
+        let tail_handler = search_tail_handler(
+            Arc::new(MockSearchService::new()),
+            MetastoreServiceClient::from_mock(mock_metastore),
+        );
+        let resp = warp::test::request()
+            .path("/logs/search/tail?query=*")
+            .reply(&tail_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+    }
+}
@@ -0,0 +1,288 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use quickwit_cluster::{Cluster, ListenerHandle};
+use quickwit_common::rate_limiter::RateLimiterSettings;
+use quickwit_common::spawn_named_task;
+use quickwit_common::tenant_quotas::{QuotaSettings, TenantQuotas};
+use quickwit_common::tower::ConstantRate;
+use quickwit_config::NodeConfig;
+use quickwit_metastore::{
+    ListIndexesMetadataResponseExt, ListSplitsQuery, ListSplitsRequestExt,
+    MetastoreServiceStreamSplitsExt, SplitState,
+};
+use quickwit_proto::metastore::{
+    ListIndexesMetadataRequest, ListSplitsRequest, MetastoreResult, MetastoreService,
+    MetastoreServiceClient,
+};
+use quickwit_proto::types::{IndexId, IndexUid};
+use tracing::warn;
+
+/// How often the storage usage of the indexes subject to a storage quota is refreshed.
+const STORAGE_USAGE_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Prefix of the cluster keys holding the storage usage of the quotas, as computed by the control
+/// plane node: `tenant_quota.storage_bytes:{index_id_pattern}` -> `{storage_bytes}`.
+const STORAGE_USAGE_KEY_PREFIX: &str = "tenant_quota.storage_bytes:";
+
+/// Builds the quotas defined in the node config.
+pub(crate) fn build_tenant_quotas(node_config: &NodeConfig) -> TenantQuotas {
+    let content_length_limit = node_config.ingest_api_config.content_length_limit;
+
+    let quota_settings = node_config.quota_configs.iter().map(|quota_config| {
+        let ingest_rate_limiter_settings_opt =
+            quota_config
+                .max_ingest_throughput
+                .map(|max_ingest_throughput| RateLimiterSettings {
+                    // A request as large as the content length limit must be able to go through.
+                    burst_limit: max_ingest_throughput
+                        .as_u64()
+                        .max(content_length_limit.as_u64()),
+                    rate_limit: ConstantRate::bytes_per_sec(max_ingest_throughput),
+                    // Refill every 100ms.
+                    refill_period: Duration::from_millis(100),
+                });
+        QuotaSettings {
+            index_id_pattern: quota_config.index_id.clone(),
+            ingest_rate_limiter_settings_opt,
+            max_storage_bytes_opt: quota_config
+                .max_storage
+                .map(|max_storage| max_storage.as_u64()),
+            max_concurrent_searches_opt: quota_config
+                .max_concurrent_searches
+                .map(|max_concurrent_searches| max_concurrent_searches.get()),
+            max_scanned_bytes_per_query_opt: quota_config
+                .max_scanned_bytes_per_query
+                .map(|max_scanned_bytes| max_scanned_bytes.as_u64()),
+        }
+    });
+    TenantQuotas::new(quota_settings)
+}
+
+/// Periodically refreshes the storage usage of the indexes subject to a storage quota and
+/// broadcasts it to the other nodes of the cluster. It must only run on the control plane node, so
+/// that the metastore is not polled by every node. Does nothing if no quota limits storage.
+pub(crate) fn spawn_storage_usage_refresh_task(
+    tenant_quotas: TenantQuotas,
+    metastore: MetastoreServiceClient,
+    cluster: Cluster,
+) {
+    let index_id_patterns = tenant_quotas.storage_quota_index_id_patterns();
+
+    if index_id_patterns.is_empty() {
+        return;
+    }
+    let refresh_storage_usage_loop = async move {
+        let mut interval = tokio::time::interval(STORAGE_USAGE_REFRESH_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            if let Err(error) =
+                refresh_storage_usage(&tenant_quotas, &metastore, &index_id_patterns).await
+            {
+                warn!(%error, "failed to refresh storage usage of quotas");
+                continue;
+            }
+            for (index_id_pattern, storage_bytes) in tenant_quotas.storage_usage() {
+                let key = format!("{STORAGE_USAGE_KEY_PREFIX}{index_id_pattern}");
+                cluster.set_self_key_value(key, storage_bytes).await;
+            }
+        }
+    };
+    spawn_named_task(refresh_storage_usage_loop, "storage_usage_refresh");
+}
+
+/// Keeps the storage usage of the quotas up to date with the values broadcast by the control plane
+/// node. Returns `None` if no quota limits storage.
+pub(crate) async fn setup_storage_usage_listener(
+    tenant_quotas: TenantQuotas,
+    cluster: &Cluster,
+) -> Option<ListenerHandle> {
+    if tenant_quotas.storage_quota_index_id_patterns().is_empty() {
+        return None;
+    }
+    // The control plane node may have broadcast the storage usage before we subscribed.
+    let chitchat = cluster.chitchat().await;
+
+    for node_state in chitchat.lock().await.node_states().values() {
+        for (key, versioned_value) in node_state.iter_prefix(STORAGE_USAGE_KEY_PREFIX) {
+            let index_id_pattern = &key[STORAGE_USAGE_KEY_PREFIX.len()..];
+            set_storage_usage(&tenant_quotas, index_id_pattern, &versioned_value.value);
+        }
+    }
+    let listener_handle = cluster
+        .subscribe(STORAGE_USAGE_KEY_PREFIX, move |event| {
+            set_storage_usage(&tenant_quotas, event.key, event.value);
+        })
+        .await;
+    Some(listener_handle)
+}
+
+fn set_storage_usage(tenant_quotas: &TenantQuotas, index_id_pattern: &str, value: &str) {
+    let Ok(storage_bytes) = value.parse::<u64>() else {
+        warn!("failed to parse storage usage `{value}` of quota `{index_id_pattern}`");
+        return;
+    };
+    tenant_quotas.set_storage_usage(index_id_pattern, storage_bytes);
+}
+
+async fn refresh_storage_usage(
+    tenant_quotas: &TenantQuotas,
+    metastore: &MetastoreServiceClient,
+    index_id_patterns: &[String],
+) -> MetastoreResult<()> {
+    let list_indexes_metadata_request = ListIndexesMetadataRequest {
+        index_id_patterns: index_id_patterns.to_vec(),
+    };
+    let index_uids: Vec<IndexUid> = metastore
+        .list_indexes_metadata(list_indexes_metadata_request)
+        .await?
+        .deserialize_indexes_metadata()
+        .await?
+        .into_iter()
+        .map(|index_metadata| index_metadata.index_uid)
+        .collect();
+
+    let mut storage_bytes_per_index: HashMap<IndexId, u64> = HashMap::new();
+
+    if let Some(list_splits_query) = ListSplitsQuery::try_from_index_uids(index_uids) {
+        let list_splits_query = list_splits_query.with_split_state(SplitState::Published);
+        let list_splits_request =
+            ListSplitsRequest::try_from_list_splits_query(&list_splits_query)?;
+        let splits = metastore
+            .list_splits(list_splits_request)
+            .await?
+            .collect_splits()
+            .await?;
+
+        for split in splits {
+            *storage_bytes_per_index
+                .entry(split.split_metadata.index_uid.index_id)
+                .or_default() += split.split_metadata.footer_offsets.end;
+        }
+    }
+    tenant_quotas.update_storage_usage(
+        storage_bytes_per_index
+            .iter()
+            .map(|(index_id, num_bytes)| (index_id.as_str(), *num_bytes)),
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use bytesize::ByteSize;
+    use quickwit_cluster::{create_cluster_for_test, ChannelTransport};
+    use quickwit_common::ServiceStream;
+    use quickwit_config::QuotaConfig;
+    use quickwit_metastore::{IndexMetadata, ListSplitsResponseExt, Split, SplitMetadata};
+    use quickwit_proto::metastore::{
+        ListIndexesMetadataResponse, ListSplitsResponse, MockMetastoreService,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_refresh_storage_usage() {
+        let mut node_config = NodeConfig::for_test();
+        node_config.quota_configs = vec![QuotaConfig {
+            index_id: "acme-*".to_string(),
+            max_ingest_throughput: None,
+            max_storage: Some(ByteSize::kb(1)),
+            max_concurrent_searches: None,
+            max_scanned_bytes_per_query: None,
+        }];
+        let tenant_quotas = build_tenant_quotas(&node_config);
+        let index_id_patterns = tenant_quotas.storage_quota_index_id_patterns();
+        assert_eq!(index_id_patterns, ["acme-*"]);
+
+        let mut mock_metastore = MockMetastoreService::new();
+        mock_metastore
+            .expect_list_indexes_metadata()
+            .returning(|request| {
+                assert_eq!(request.index_id_patterns, ["acme-*"]);
+
+                let indexes_metadata = vec![
+                    IndexMetadata::for_test("acme-logs", "ram:///indexes/acme-logs"),
+                    IndexMetadata::for_test("acme-traces", "ram:///indexes/acme-traces"),
+                ];
+                Ok(ListIndexesMetadataResponse::for_test(indexes_metadata))
+            });
+        mock_metastore.expect_list_splits().returning(|_request| {
+            let splits: Vec<Split> = [("acme-logs", 600), ("acme-traces", 600)]
+                .into_iter()
+                .map(|(index_id, num_bytes)| Split {
+                    split_metadata: SplitMetadata {
+                        index_uid: IndexUid::for_test(index_id, 0),
+                        footer_offsets: 0..num_bytes,
+                        ..Default::default()
+                    },
+                    split_state: SplitState::Published,
+                    update_timestamp: 0,
+                    publish_timestamp: None,
+                })
+                .collect();
+            let response = ListSplitsResponse::try_from_splits(splits).unwrap();
+            Ok(ServiceStream::from(vec![Ok(response)]))
+        });
+        let metastore = MetastoreServiceClient::from_mock(mock_metastore);
+
+        tenant_quotas.acquire_ingest("acme-logs", 1).unwrap();
+
+        refresh_storage_usage(&tenant_quotas, &metastore, &index_id_patterns)
+            .await
+            .unwrap();
+        tenant_quotas.acquire_ingest("acme-logs", 1).unwrap_err();
+        tenant_quotas.acquire_ingest("foo", 1).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_setup_storage_usage_listener() {
+        let mut node_config = NodeConfig::for_test();
+        node_config.quota_configs = vec![QuotaConfig {
+            index_id: "acme-*".to_string(),
+            max_ingest_throughput: None,
+            max_storage: Some(ByteSize::kb(1)),
+            max_concurrent_searches: None,
+            max_scanned_bytes_per_query: None,
+        }];
+        let tenant_quotas = build_tenant_quotas(&node_config);
+
+        let transport = ChannelTransport::default();
+        let cluster = create_cluster_for_test(Vec::new(), &["control_plane"], &transport, true)
+            .await
+            .unwrap();
+        cluster
+            .set_self_key_value(format!("{STORAGE_USAGE_KEY_PREFIX}acme-*"), 2_000)
+            .await;
+
+        let listener_handle_opt =
+            setup_storage_usage_listener(tenant_quotas.clone(), &cluster).await;
+        assert!(listener_handle_opt.is_some());
+        tenant_quotas.acquire_ingest("acme-logs", 1).unwrap_err();
+
+        let listener_handle_opt =
+            setup_storage_usage_listener(TenantQuotas::default(), &cluster).await;
+        assert!(listener_handle_opt.is_none());
+    }
+}
@@ -0,0 +1,170 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::ops::Range;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use quickwit_common::uri::Uri;
+use tantivy::directory::OwnedBytes;
+use tokio::io::AsyncRead;
+
+use crate::storage::SendableAsync;
+use crate::{BulkDeleteError, PutPayload, Storage, StorageResult};
+
+/// Storage proxy that counts the number of bytes read from the underlying storage.
+///
+/// This is used to profile search requests. Bytes read through `copy_to` and `copy_to_file` are
+/// not counted.
+#[derive(Clone)]
+pub struct ByteCountingStorage {
+    underlying: Arc<dyn Storage>,
+    num_bytes_read: Arc<AtomicU64>,
+}
+
+impl std::fmt::Debug for ByteCountingStorage {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ByteCountingStorage")
+            .field("uri", self.underlying.uri())
+            .finish()
+    }
+}
+
+impl ByteCountingStorage {
+    /// Creates a new `ByteCountingStorage` adding the number of bytes read to `num_bytes_read`.
+    pub fn new(storage: Arc<dyn Storage>, num_bytes_read: Arc<AtomicU64>) -> Self {
+        ByteCountingStorage {
+            underlying: storage,
+            num_bytes_read,
+        }
+    }
+
+    fn record_bytes_read(&self, num_bytes: usize) {
+        self.num_bytes_read
+            .fetch_add(num_bytes as u64, Ordering::Relaxed);
+    }
+}
+
+#[async_trait]
+impl Storage for ByteCountingStorage {
+    async fn check_connectivity(&self) -> anyhow::Result<()> {
+        self.underlying.check_connectivity().await
+    }
+
+    async fn put(&self, path: &Path, payload: Box<dyn PutPayload>) -> StorageResult<()> {
+        self.underlying.put(path, payload).await
+    }
+
+    fn copy_to<'life0, 'life1, 'life2, 'async_trait>(
+        &'life0 self,
+        path: &'life1 Path,
+        output: &'life2 mut dyn SendableAsync,
+    ) -> ::core::pin::Pin<
+        Box<
+            dyn ::core::future::Future<Output = StorageResult<()>>
+                + ::core::marker::Send
+                + 'async_trait,
+        >,
+    >
+    where
+        'life0: 'async_trait,
+        'life1: 'async_trait,
+        'life2: 'async_trait,
+        Self: 'async_trait,
+    {
+        self.underlying.copy_to(path, output)
+    }
+
+    async fn copy_to_file(&self, path: &Path, output_path: &Path) -> StorageResult<u64> {
+        self.underlying.copy_to_file(path, output_path).await
+    }
+
+    async fn get_slice(&self, path: &Path, range: Range<usize>) -> StorageResult<OwnedBytes> {
+        let bytes = self.underlying.get_slice(path, range).await?;
+        self.record_bytes_read(bytes.len());
+        Ok(bytes)
+    }
+
+    async fn get_slice_stream(
+        &self,
+        path: &Path,
+        range: Range<usize>,
+    ) -> StorageResult<Box<dyn AsyncRead + Send + Unpin>> {
+        let num_bytes = range.len();
+        let stream = self.underlying.get_slice_stream(path, range).await?;
+        self.record_bytes_read(num_bytes);
+        Ok(stream)
+    }
+
+    async fn get_all(&self, path: &Path) -> StorageResult<OwnedBytes> {
+        let bytes = self.underlying.get_all(path).await?;
+        self.record_bytes_read(bytes.len());
+        Ok(bytes)
+    }
+
+    async fn delete(&self, path: &Path) -> StorageResult<()> {
+        self.underlying.delete(path).await
+    }
+
+    async fn bulk_delete<'a>(&self, paths: &[&'a Path]) -> Result<(), BulkDeleteError> {
+        self.underlying.bulk_delete(paths).await
+    }
+
+    async fn exists(&self, path: &Path) -> StorageResult<bool> {
+        self.underlying.exists(path).await
+    }
+
+    async fn file_num_bytes(&self, path: &Path) -> StorageResult<u64> {
+        self.underlying.file_num_bytes(path).await
+    }
+
+    fn uri(&self) -> &Uri {
+        self.underlying.uri()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RamStorage;
+
+    #[tokio::test]
+    async fn test_byte_counting_storage() {
+        let ram_storage = Arc::new(RamStorage::default());
+        let path = Path::new("foo");
+        ram_storage
+            .put(path, Box::new(b"hello world".to_vec()))
+            .await
+            .unwrap();
+
+        let num_bytes_read = Arc::new(AtomicU64::new(0));
+        let storage = ByteCountingStorage::new(ram_storage, num_bytes_read.clone());
+
+        storage.get_slice(path, 0..5).await.unwrap();
+        assert_eq!(num_bytes_read.load(Ordering::Relaxed), 5);
+
+        storage.get_all(path).await.unwrap();
+        assert_eq!(num_bytes_read.load(Ordering::Relaxed), 16);
+
+        storage.get_slice(Path::new("bar"), 0..5).await.unwrap_err();
+        assert_eq!(num_bytes_read.load(Ordering::Relaxed), 16);
+    }
+}
@@ -30,6 +30,7 @@
 //! - etc.
 //!
 //! The `BundleStorage` bundles together multiple files into a single file.
+mod byte_counting_storage;
 mod cache;
 mod debouncer;
 mod file_descriptor_cache;
@@ -65,6 +66,7 @@ pub use tantivy::directory::OwnedBytes;
 pub use versioned_component::VersionedComponent;
 
 pub use self::bundle_storage::{BundleStorage, BundleStorageFileOffsets};
+pub use self::byte_counting_storage::ByteCountingStorage;
 #[cfg(any(test, feature = "testsuite"))]
 pub use self::cache::MockStorageCache;
 pub use self::cache::{